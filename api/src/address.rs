@@ -0,0 +1,168 @@
+//! EVM address extractor
+//!
+//! Every address coming in through a path or body is validated here and
+//! turned into an [`Address20`] before it reaches SQL.
+//!
+//! Serde flattens a field's error into text, so an address that fails inside
+//! a body or query string is also noted on the side as an
+//! [`InvalidAddressError`]. [`AddressChecked`] clears the note before it
+//! deserializes, and the rejection is classified by what it holds afterwards.
+
+use std::{cell::RefCell, ops::Deref};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+};
+use indexer_db::Address20;
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::error::ApiError;
+
+/// Why an address string was refused
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid address `{input}`: {reason}")]
+pub struct InvalidAddressError {
+    pub input: String,
+    pub reason: String,
+}
+
+impl From<InvalidAddressError> for ApiError {
+    fn from(e: InvalidAddressError) -> Self {
+        ApiError::InvalidAddress(e.to_string())
+    }
+}
+
+thread_local! {
+    /// The address that failed during the current [`AddressChecked`] run
+    static LAST_FAILURE: RefCell<Option<InvalidAddressError>> = const { RefCell::new(None) };
+}
+
+/// Take the address failure noted since the last [`AddressChecked`] started.
+/// Call it right after the deserialization fails, before yielding.
+pub fn take_failure() -> Option<InvalidAddressError> {
+    LAST_FAILURE.with(|f| f.borrow_mut().take())
+}
+
+/// Deserializes `T` after clearing the noted address failure, so a failure
+/// noted afterwards belongs to this value
+pub struct AddressChecked<T>(pub T);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for AddressChecked<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        take_failure();
+        T::deserialize(deserializer).map(AddressChecked)
+    }
+}
+
+/// A validated 20-byte address taken from user input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct EvmAddress(Address20);
+
+impl EvmAddress {
+    /// Validate an address string, accepting any hex casing
+    pub fn parse(input: &str) -> Result<Self, ApiError> {
+        Ok(Self::validate(input)?)
+    }
+
+    fn validate(input: &str) -> Result<Self, InvalidAddressError> {
+        input
+            .trim()
+            .parse::<Address20>()
+            .map(Self)
+            .map_err(|e| InvalidAddressError {
+                input: input.to_string(),
+                reason: e.to_string(),
+            })
+    }
+}
+
+/// Used when deserializing; a failure is also noted for [`take_failure`]
+impl TryFrom<String> for EvmAddress {
+    type Error = InvalidAddressError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::validate(&value).inspect_err(|e| {
+            LAST_FAILURE.with(|f| *f.borrow_mut() = Some(e.clone()));
+        })
+    }
+}
+
+impl Deref for EvmAddress {
+    type Target = Address20;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Extracts and validates a single `:address` path segment
+#[async_trait]
+impl<S> FromRequestParts<S> for EvmAddress
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(raw) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::InvalidAddress(e.body_text()))?;
+
+        Self::parse(&raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_to_lowercase() {
+        let addr = EvmAddress::parse("0XbB4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c").unwrap();
+        assert_eq!(
+            addr.to_string(),
+            "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(EvmAddress::parse("bb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c").is_err());
+        assert!(EvmAddress::parse("0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095").is_err());
+        assert!(EvmAddress::parse("0xzz4cdb9cbd36b01bd1cbaebf2de08d9173bc095c").is_err());
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Body {
+        address: EvmAddress,
+        count: u32,
+    }
+
+    #[test]
+    fn notes_only_address_failures() {
+        let good = r#"{ "address": "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c", "count": 1 }"#;
+        let AddressChecked(body) = serde_json::from_str::<AddressChecked<Body>>(good).unwrap();
+        assert_eq!(
+            body.address.to_string(),
+            "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"
+        );
+        assert_eq!(body.count, 1);
+
+        let bad_address = r#"{ "address": "0xnothex", "count": 1 }"#;
+        assert!(serde_json::from_str::<AddressChecked<Body>>(bad_address).is_err());
+        let failure = take_failure().expect("address failure noted");
+        assert_eq!(failure.input, "0xnothex");
+        assert_eq!(take_failure(), None);
+
+        // Left over from an earlier value, and cleared before this one
+        assert!(serde_json::from_str::<EvmAddress>(r#""0xnothex""#).is_err());
+        let bad_count = r#"{ "address": "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c",
+                            "count": "Invalid address `0x`" }"#;
+        assert!(serde_json::from_str::<AddressChecked<Body>>(bad_count).is_err());
+        assert_eq!(take_failure(), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! New alert notifications
+//!
+//! Every insert into `alert_events` is announced with `pg_notify` (see the
+//! `notify_alert_events` migration). One listener connection per API process
+//! turns those into a watch channel, which long-polling feed requests
+//! (`/api/alerts/feed?wait=`) wait on instead of holding a connection each.
+
+use std::time::Duration;
+
+use indexer_db::entity::alert::AlertEvent;
+use sqlx::{postgres::PgListener, Pool, Postgres};
+use tokio::{sync::watch, time::sleep};
+
+/// Wait before listening again after the connection failed
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Id of the newest alert announced, for feed requests to wait on
+#[derive(Debug, Clone)]
+pub struct AlertNotifier {
+    latest: watch::Sender<i32>,
+}
+
+impl Default for AlertNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlertNotifier {
+    pub fn new() -> Self {
+        Self {
+            latest: watch::channel(0).0,
+        }
+    }
+
+    /// A receiver woken by the next alert
+    pub fn subscribe(&self) -> watch::Receiver<i32> {
+        self.latest.subscribe()
+    }
+
+    fn announce(&self, id: i32) {
+        self.latest.send_replace(id);
+    }
+}
+
+/// Listen for new alerts and wake the waiting requests, reconnecting when
+/// the connection drops
+pub async fn run(notifier: AlertNotifier, db_pool: Pool<Postgres>) {
+    loop {
+        if let Err(e) = listen(&notifier, &db_pool).await {
+            tracing::error!("Alert notifications interrupted: {}", e);
+        }
+        sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn listen(notifier: &AlertNotifier, db_pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(db_pool).await?;
+    listener.listen(AlertEvent::NOTIFY_CHANNEL).await?;
+    tracing::debug!("Listening for new alerts");
+
+    loop {
+        let notification = listener.recv().await?;
+        match notification.payload().parse::<i32>() {
+            Ok(id) => notifier.announce(id),
+            Err(_) => tracing::warn!(
+                "Ignoring alert notification with payload `{}`",
+                notification.payload()
+            ),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Constant-product (x*y=k) trade simulation
+//!
+//! Mirrors the UniswapV2/PancakeSwap V2 `getAmountOut` formula on
+//! human-unit reserves. Good enough for quotes and tax estimation; not for
+//! building transactions.
+
+/// PancakeSwap V2 swap fee, in basis points
+pub const PANCAKE_V2_FEE_BPS: u32 = 25;
+
+/// Result of simulating one swap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapQuote {
+    pub amount_in: f64,
+    pub amount_out: f64,
+    /// How much worse the effective rate is than spot, fee included (0-100)
+    pub price_impact_percent: f64,
+}
+
+/// Simulate swapping `amount_in` into a pool holding `reserve_in`/`reserve_out`
+///
+/// Returns `None` when the pool is empty or the input is not a positive amount.
+pub fn quote(reserve_in: f64, reserve_out: f64, amount_in: f64, fee_bps: u32) -> Option<SwapQuote> {
+    if !(reserve_in > 0.0 && reserve_out > 0.0 && amount_in > 0.0 && amount_in.is_finite()) {
+        return None;
+    }
+
+    let amount_in_with_fee = amount_in * (10_000 - fee_bps.min(10_000)) as f64 / 10_000.0;
+    let amount_out = amount_in_with_fee * reserve_out / (reserve_in + amount_in_with_fee);
+
+    let spot_rate = reserve_out / reserve_in;
+    let effective_rate = amount_out / amount_in;
+
+    Some(SwapQuote {
+        amount_in,
+        amount_out,
+        price_impact_percent: (1.0 - effective_rate / spot_rate) * 100.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_get_amount_out() {
+        // 1 in against 100/100 with 0.25% fee
+        let q = quote(100.0, 100.0, 1.0, PANCAKE_V2_FEE_BPS).unwrap();
+        let expected = 0.9975 * 100.0 / 100.9975;
+        assert!((q.amount_out - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn impact_grows_with_size_and_includes_fee() {
+        let small = quote(1_000.0, 1_000_000.0, 0.001, PANCAKE_V2_FEE_BPS).unwrap();
+        let large = quote(1_000.0, 1_000_000.0, 100.0, PANCAKE_V2_FEE_BPS).unwrap();
+        assert!((small.price_impact_percent - 0.25).abs() < 0.01);
+        assert!(large.price_impact_percent > 9.0);
+        assert!(large.amount_out < 1_000_000.0);
+    }
+
+    #[test]
+    fn rejects_empty_pools_and_bad_amounts() {
+        assert!(quote(0.0, 100.0, 1.0, PANCAKE_V2_FEE_BPS).is_none());
+        assert!(quote(100.0, 100.0, 0.0, PANCAKE_V2_FEE_BPS).is_none());
+        assert!(quote(100.0, 100.0, f64::NAN, PANCAKE_V2_FEE_BPS).is_none());
+        assert!(quote(100.0, 100.0, f64::INFINITY, PANCAKE_V2_FEE_BPS).is_none());
+    }
+}
@@ -0,0 +1,101 @@
+//! API key extractor
+//!
+//! Write endpoints fed by external collectors (e.g. social ingestion), alert
+//! webhook management and operator controls are guarded by a shared key sent in the
+//! `X-API-Key` header. When no key is configured the endpoints are disabled
+//! rather than left open.
+//!
+//! Clients also send a key of their own in `X-API-Key` to have their alert
+//! preferences applied; see [`ClientKey`].
+
+use std::sync::Arc;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+use crate::{error::ApiError, AppState};
+
+/// Header carrying the ingestion key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Proof that the request carried the configured ingestion key
+#[derive(Debug, Clone, Copy)]
+pub struct IngestKey;
+
+/// Shortest key accepted as a [`ClientKey`]
+const MIN_CLIENT_KEY_LEN: usize = 12;
+
+/// The `X-API-Key` a client identifies itself with. Any sufficiently long
+/// key works: it only selects whose alert preferences to read and write.
+#[derive(Debug, Clone)]
+pub struct ClientKey(pub String);
+
+/// Compare without short-circuiting so timing doesn't leak the key
+fn keys_match(expected: &[u8], given: &[u8]) -> bool {
+    expected.len() == given.len()
+        && expected
+            .iter()
+            .zip(given)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for IngestKey {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let expected = state
+            .ingest_api_key
+            .as_deref()
+            .ok_or_else(|| ApiError::Unauthorized("Ingestion is disabled on this server".into()))?;
+
+        let given = parts
+            .headers
+            .get(API_KEY_HEADER)
+            .map(|v| v.as_bytes())
+            .ok_or_else(|| ApiError::Unauthorized("Missing X-API-Key header".into()))?;
+
+        if keys_match(expected.as_bytes(), given) {
+            Ok(IngestKey)
+        } else {
+            Err(ApiError::Unauthorized("Invalid API key".into()))
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientKey {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let key = parts
+            .headers
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| ApiError::Unauthorized("Missing X-API-Key header".into()))?;
+
+        if key.len() < MIN_CLIENT_KEY_LEN {
+            return Err(ApiError::Unauthorized(format!(
+                "X-API-Key must be at least {} characters",
+                MIN_CLIENT_KEY_LEN
+            )));
+        }
+        Ok(ClientKey(key.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_must_match_exactly() {
+        assert!(keys_match(b"secret", b"secret"));
+        assert!(!keys_match(b"secret", b"secreT"));
+        assert!(!keys_match(b"secret", b"secret2"));
+        assert!(!keys_match(b"secret", b""));
+    }
+}
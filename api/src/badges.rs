@@ -0,0 +1,214 @@
+//! Token risk badges
+//!
+//! Short labels drawn from a token's stored flags and metrics by a fixed set
+//! of rules, so every frontend shows the same ones. Risks come first.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use indexer_db::entity::{external_report::ReportSeverity, token::Token};
+
+/// Top-10 holder share (percent) from which a token is whale heavy
+const WHALE_HEAVY_PERCENT: f64 = 50.0;
+
+/// External report severity from which a token is a honeypot risk
+const HONEYPOT_SEVERITY: ReportSeverity = ReportSeverity::High;
+
+/// What a badge says, stable across label changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadgeKind {
+    HoneypotRisk,
+    DevSold,
+    WhaleHeavy,
+    LpLocked,
+    Renounced,
+}
+
+/// Whether a badge reassures or warns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeTone {
+    Positive,
+    Risk,
+}
+
+/// One badge, e.g. `LP Locked 6mo`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    pub kind: BadgeKind,
+    pub label: String,
+    pub tone: BadgeTone,
+}
+
+impl Badge {
+    fn new(kind: BadgeKind, label: impl Into<String>, tone: BadgeTone) -> Self {
+        Self {
+            kind,
+            label: label.into(),
+            tone,
+        }
+    }
+}
+
+/// What the rules read off a token
+#[derive(Debug, Default)]
+struct Signals {
+    lp_locked: bool,
+    lp_unlock: Option<DateTime<Utc>>,
+    renounced: bool,
+    dev_sold: bool,
+    external_risk: Option<ReportSeverity>,
+    top_10_percent: Option<f64>,
+}
+
+impl Signals {
+    fn of(t: &Token) -> Self {
+        Self {
+            lp_locked: t.lp_locked.unwrap_or(false),
+            lp_unlock: t.lp_unlock_date,
+            renounced: t.ownership_renounced.unwrap_or(false),
+            dev_sold: t.dev_sold_at.is_some(),
+            external_risk: t.external_risk.as_deref().and_then(ReportSeverity::parse),
+            top_10_percent: t
+                .top_10_holder_percent
+                .as_ref()
+                .and_then(|p| p.to_string().parse().ok()),
+        }
+    }
+}
+
+/// `6mo`, `2y`, `12d`: how long until `unlock`, rounded down
+fn lock_left(unlock: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let days = (unlock - now).num_days();
+    if days >= 365 {
+        format!("{}y", days / 365)
+    } else if days >= 30 {
+        format!("{}mo", days / 30)
+    } else {
+        format!("{}d", days.max(1))
+    }
+}
+
+fn rules(s: &Signals, now: DateTime<Utc>) -> Vec<Badge> {
+    let mut badges = Vec::new();
+
+    if s.external_risk >= Some(HONEYPOT_SEVERITY) {
+        badges.push(Badge::new(
+            BadgeKind::HoneypotRisk,
+            "Honeypot risk",
+            BadgeTone::Risk,
+        ));
+    }
+    if s.dev_sold {
+        badges.push(Badge::new(BadgeKind::DevSold, "Dev sold", BadgeTone::Risk));
+    }
+    if s.top_10_percent.is_some_and(|p| p >= WHALE_HEAVY_PERCENT) {
+        badges.push(Badge::new(
+            BadgeKind::WhaleHeavy,
+            "Whale heavy",
+            BadgeTone::Risk,
+        ));
+    }
+
+    // A lock past its unlock date no longer holds anything
+    match (s.lp_locked, s.lp_unlock) {
+        (true, Some(unlock)) if unlock > now => badges.push(Badge::new(
+            BadgeKind::LpLocked,
+            format!("LP Locked {}", lock_left(unlock, now)),
+            BadgeTone::Positive,
+        )),
+        (true, None) => badges.push(Badge::new(
+            BadgeKind::LpLocked,
+            "LP Locked",
+            BadgeTone::Positive,
+        )),
+        _ => {}
+    }
+    if s.renounced {
+        badges.push(Badge::new(
+            BadgeKind::Renounced,
+            "Renounced",
+            BadgeTone::Positive,
+        ));
+    }
+
+    badges
+}
+
+/// Badges for `t` as of `now`
+pub fn of(t: &Token, now: DateTime<Utc>) -> Vec<Badge> {
+    rules(&Signals::of(t), now)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn labels(badges: &[Badge]) -> Vec<&str> {
+        badges.iter().map(|b| b.label.as_str()).collect()
+    }
+
+    #[test]
+    fn lp_locks_show_the_time_left() {
+        let now = Utc::now();
+        let locked = |days| Signals {
+            lp_locked: true,
+            lp_unlock: Some(now + Duration::days(days) + Duration::hours(1)),
+            ..Signals::default()
+        };
+
+        assert_eq!(labels(&rules(&locked(185), now)), vec!["LP Locked 6mo"]);
+        assert_eq!(labels(&rules(&locked(800), now)), vec!["LP Locked 2y"]);
+        assert_eq!(labels(&rules(&locked(12), now)), vec!["LP Locked 12d"]);
+        assert_eq!(labels(&rules(&locked(0), now)), vec!["LP Locked 1d"]);
+        assert!(rules(&locked(-3), now).is_empty());
+
+        let no_date = Signals {
+            lp_locked: true,
+            ..Signals::default()
+        };
+        assert_eq!(labels(&rules(&no_date, now)), vec!["LP Locked"]);
+    }
+
+    #[test]
+    fn risks_come_before_reassurances() {
+        let now = Utc::now();
+        let signals = Signals {
+            lp_locked: true,
+            renounced: true,
+            dev_sold: true,
+            external_risk: Some(ReportSeverity::Critical),
+            top_10_percent: Some(62.5),
+            ..Signals::default()
+        };
+
+        let badges = rules(&signals, now);
+        assert_eq!(
+            labels(&badges),
+            vec![
+                "Honeypot risk",
+                "Dev sold",
+                "Whale heavy",
+                "LP Locked",
+                "Renounced"
+            ]
+        );
+        assert_eq!(badges[0].tone, BadgeTone::Risk);
+        assert_eq!(badges[4].tone, BadgeTone::Positive);
+    }
+
+    #[test]
+    fn mild_signals_earn_no_badge() {
+        let signals = Signals {
+            external_risk: Some(ReportSeverity::Medium),
+            top_10_percent: Some(49.9),
+            ..Signals::default()
+        };
+
+        assert!(rules(&signals, Utc::now()).is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+//! In-memory response cache
+//!
+//! Holds values that are costly to compute for `ttl` after they were
+//! computed, per API process. Entries past their time are swept once the
+//! cache grows to `SWEEP_AT` keys.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Keys held before expired entries are swept
+const SWEEP_AT: usize = 10_000;
+
+/// Values with the time they were computed, kept for a fixed time
+#[derive(Debug, Clone)]
+pub struct TtlCache<K, V> {
+    /// How long a value is served; zero disables caching
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<K, (Instant, V)>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::default(),
+        }
+    }
+
+    /// The value cached for `key`, unless it is older than the TTL
+    pub fn get(&self, key: &K, now: Instant) -> Option<V> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(key)
+            .filter(|(at, _)| now.duration_since(*at) < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Cache `value` for `key`, computed at `now`
+    pub fn insert(&self, key: K, value: V, now: Instant) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= SWEEP_AT {
+            entries.retain(|_, (at, _)| now.duration_since(*at) < self.ttl);
+        }
+        entries.insert(key, (now, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_expire_after_the_ttl() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        cache.insert("a", 1, now);
+        assert_eq!(cache.get(&"a", now + Duration::from_secs(59)), Some(1));
+        assert_eq!(cache.get(&"a", now + Duration::from_secs(60)), None);
+        assert_eq!(cache.get(&"b", now), None);
+
+        let disabled = TtlCache::new(Duration::ZERO);
+        disabled.insert("a", 1, now);
+        assert_eq!(disabled.get(&"a", now), None);
+    }
+}
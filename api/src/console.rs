@@ -0,0 +1,163 @@
+//! Landing page query console
+//!
+//! `/` lists the endpoints registered in [`crate::routes::api_routes`], so a
+//! route can't be added or removed without the page following. The page polls
+//! `/api/v1/status` for the sync position and can send a request to any
+//! endpoint and show the response.
+
+use crate::routes::Endpoint;
+
+/// Render the console for the registered endpoints
+pub fn page(endpoints: &[Endpoint]) -> String {
+    let mut list = String::new();
+    let mut group = "";
+    for endpoint in endpoints {
+        let section = endpoint
+            .path
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        if section != group {
+            group = section;
+            list.push_str(&format!("\n    <h3>{}</h3>", heading(section)));
+        }
+
+        let path = format!("/api/v1{}", endpoint.path);
+        let shown = if endpoint.method == "GET" && !path.contains(':') {
+            format!("<a href=\"{0}\">{0}</a>", path)
+        } else {
+            format!("<code>{}</code>", path)
+        };
+        list.push_str(&format!(
+            r#"
+    <div class="endpoint" data-method="{method}" data-path="{path}">
+        <span class="method">{method}</span> {shown} - {summary}
+        <button class="try">Try</button>
+    </div>"#,
+            method = endpoint.method,
+            path = path,
+            shown = shown,
+            summary = escape(endpoint.summary),
+        ));
+    }
+
+    TEMPLATE.replace("{endpoints}", &list)
+}
+
+/// `tokens` → `Tokens`
+fn heading(section: &str) -> String {
+    let mut chars = section.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>BeanBee API</title>
+    <style>
+        body { font-family: system-ui, sans-serif; max-width: 800px; margin: 50px auto; padding: 20px; background: #1a1a2e; color: #eee; }
+        h1 { color: #f5a623; }
+        a { color: #4fc3f7; }
+        code { background: #333; padding: 2px 6px; border-radius: 4px; }
+        .endpoint { margin: 10px 0; padding: 10px; background: #252540; border-radius: 8px; }
+        .method { color: #4caf50; font-weight: bold; }
+        .try { float: right; }
+        #console { padding: 10px; background: #252540; border-radius: 8px; }
+        #console input, #console select, #console textarea { background: #1a1a2e; color: #eee; border: 1px solid #444; border-radius: 4px; padding: 4px; }
+        #console-path { width: 60%; }
+        #console-body { width: 100%; height: 60px; margin-top: 6px; }
+        #console-output { max-height: 400px; overflow: auto; background: #111; padding: 10px; border-radius: 4px; white-space: pre-wrap; }
+    </style>
+</head>
+<body>
+    <h1>BeanBee API</h1>
+    <p>BSC Memecoin Alpha Discovery Engine</p>
+    <p id="sync-status">Sync status: loading...</p>
+
+    <h2>Console</h2>
+    <div id="console">
+        <select id="console-method">
+            <option>GET</option><option>POST</option><option>PUT</option><option>PATCH</option><option>DELETE</option>
+        </select>
+        <input id="console-path" value="/api/v1/status">
+        <select id="console-version" title="API version">
+            <option value="v1">v1</option><option value="v2">v2</option>
+        </select>
+        <input id="console-key" placeholder="X-API-Key" size="12">
+        <button id="console-send">Send</button>
+        <textarea id="console-body" placeholder="JSON body"></textarea>
+        <pre id="console-output">Pick an endpoint below or type a path, then Send.</pre>
+    </div>
+
+    <h2>Endpoints</h2>
+    <p>Add <code>?precision=string</code> to any endpoint to get prices and amounts as exact decimal strings.</p>
+    <p>Every endpoint is also served under <code>/api/v2</code>, which returns lists as <code>{ data, meta: { total, cursor, generatedAt, syncLagBlocks } }</code> instead of bare arrays and decimals as exact strings.</p>
+    <p>The unversioned <code>/api/...</code> paths are deprecated: they answer as version 1 (or version 2 with <code>Accept: application/vnd.beanbee.v2+json</code> or <code>API-Version: 2</code>) and send <code>Deprecation</code> and <code>Sunset</code> headers.</p>
+
+    <div class="endpoint">
+        <span class="method">GET</span> <a href="/health">/health</a> - Health check
+    </div>
+{endpoints}
+
+    <script>
+        const $ = (id) => document.getElementById(id);
+
+        async function refreshStatus() {
+            try {
+                const s = await (await fetch('/api/v1/status')).json();
+                $('sync-status').textContent = s.syncedBlock === null
+                    ? 'Sync status: no listener has polled yet'
+                    : `Sync status: indexed to block ${s.syncedBlock} of ${s.headBlock ?? '?'}` +
+                      ` (${s.lagBlocks ?? '?'} blocks behind, updated ${s.updatedAt})`;
+            } catch (e) {
+                $('sync-status').textContent = 'Sync status: unavailable';
+            }
+        }
+
+        async function send() {
+            const version = $('console-version').value;
+            const path = $('console-path').value.replace(/^\/api\/v[0-9]+/, `/api/${version}`);
+            const headers = {};
+            const body = $('console-body').value.trim();
+            if (body) headers['Content-Type'] = 'application/json';
+            if ($('console-key').value) headers['X-API-Key'] = $('console-key').value;
+
+            $('console-output').textContent = '...';
+            try {
+                const res = await fetch(path, { method: $('console-method').value, headers, body: body || undefined });
+                const text = await res.text();
+                let shown = text;
+                try { shown = JSON.stringify(JSON.parse(text), null, 2); } catch (e) {}
+                $('console-output').textContent = `${res.status} ${res.statusText}\n\n${shown}`;
+            } catch (e) {
+                $('console-output').textContent = `Request failed: ${e}`;
+            }
+        }
+
+        document.querySelectorAll('.endpoint .try').forEach((button) => {
+            button.addEventListener('click', () => {
+                const endpoint = button.parentElement;
+                $('console-method').value = endpoint.dataset.method;
+                $('console-path').value = endpoint.dataset.path;
+                $('console-path').focus();
+            });
+        });
+        $('console-send').addEventListener('click', send);
+
+        refreshStatus();
+        setInterval(refreshStatus, 15000);
+    </script>
+</body>
+</html>
+"#;
@@ -0,0 +1,167 @@
+//! Exact decimal serialization
+//!
+//! Prices and amounts are stored as NUMERIC and rendered as JSON numbers by
+//! default, which goes through f64 and loses digits on micro-cap prices. With
+//! `?precision=string` (or `DECIMAL_PRECISION=string` server-wide, or API
+//! version 2) they are rendered as exact decimal strings instead.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, Serializer};
+use sqlx::types::BigDecimal;
+
+use crate::{error::ApiError, version::ApiVersion, AppState};
+
+tokio::task_local! {
+    /// Precision of the request being served
+    static PRECISION: Precision;
+}
+
+/// How decimal prices and amounts are written in responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// JSON numbers (f64)
+    #[default]
+    Number,
+    /// Exact decimal strings
+    String,
+}
+
+impl Precision {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "number" => Some(Precision::Number),
+            "string" => Some(Precision::String),
+            _ => None,
+        }
+    }
+
+    /// Precision of the current request, or the default outside of one
+    pub fn current() -> Self {
+        PRECISION.try_with(|p| *p).unwrap_or_default()
+    }
+
+    /// Run `f` with decimals written at this precision, e.g. to serialize
+    /// outside of the request that asked for it
+    pub fn apply<R>(self, f: impl FnOnce() -> R) -> R {
+        PRECISION.sync_scope(self, f)
+    }
+}
+
+/// A NUMERIC value serialized according to the request's precision
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Decimal(pub BigDecimal);
+
+impl Decimal {
+    /// Wrap an optional column, treating NULL as zero
+    pub fn of(value: &Option<BigDecimal>) -> Self {
+        Self(value.clone().unwrap_or_default())
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match Precision::current() {
+            Precision::Number => {
+                serializer.serialize_f64(self.0.to_string().parse().unwrap_or(0.0))
+            }
+            Precision::String => serializer.serialize_str(&plain(&self.0)),
+        }
+    }
+}
+
+/// Exact decimal string without exponent notation or trailing zeros
+fn plain(value: &BigDecimal) -> String {
+    let (digits, scale) = value.normalized().as_bigint_and_exponent();
+    let digits = digits.to_string();
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(abs) => ("-", abs),
+        None => ("", digits.as_str()),
+    };
+
+    if scale <= 0 {
+        return format!(
+            "{}{}{}",
+            sign,
+            digits,
+            "0".repeat(scale.unsigned_abs() as usize)
+        );
+    }
+
+    let scale = scale as usize;
+    if digits.len() > scale {
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        format!("{}{}.{}", sign, integer, fraction)
+    } else {
+        format!("{}0.{}{}", sign, "0".repeat(scale - digits.len()), digits)
+    }
+}
+
+/// Value of the `precision` query parameter, if given
+fn requested(query: Option<&str>) -> Option<&str> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "precision")
+        .map(|(_, value)| value)
+}
+
+/// Serve the request with the precision it asked for, or the default for its
+/// API version
+pub async fn middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let precision = match requested(request.uri().query()) {
+        Some(value) => match Precision::parse(value) {
+            Some(precision) => precision,
+            None => {
+                return ApiError::InvalidQuery(format!(
+                    "`precision` must be `number` or `string`, got `{}`",
+                    value
+                ))
+                .into_response()
+            }
+        },
+        None => match request.extensions().get::<ApiVersion>() {
+            Some(ApiVersion::V2) => Precision::String,
+            _ => state.precision,
+        },
+    };
+
+    PRECISION.scope(precision, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn decimals_follow_the_request_precision() {
+        let price = Decimal("0.000000000001234567890123".parse().unwrap());
+
+        let number = serde_json::to_value(&price).unwrap();
+        assert!(number.is_f64());
+
+        let exact = PRECISION
+            .scope(Precision::String, async {
+                serde_json::to_value(&price).unwrap()
+            })
+            .await;
+        assert_eq!(exact, "0.000000000001234567890123");
+
+        for (value, expected) in [("52300", "52300"), ("-1.50", "-1.5"), ("12.345", "12.345")] {
+            assert_eq!(plain(&value.parse().unwrap()), expected);
+        }
+
+        assert_eq!(requested(Some("limit=5&precision=string")), Some("string"));
+        assert_eq!(requested(Some("limit=5")), None);
+        assert_eq!(requested(None), None);
+    }
+}
@@ -0,0 +1,116 @@
+//! Public demo mode
+//!
+//! With `DEMO_MODE=true` the API can be exposed to anyone: every mutating
+//! request and the operator endpoints are refused, rate limits drop to
+//! `DEMO_RATE_LIMIT_PER_MINUTE`, and wallet addresses in responses are masked
+//! (`0x1234...abcd`) so the tracked wallets aren't published in full.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use indexer_db::Address20;
+use serde::{Serialize, Serializer};
+
+use crate::{error::ApiError, version, AppState};
+
+tokio::task_local! {
+    /// Whether the request being served is a demo request
+    static MASK_WALLETS: bool;
+}
+
+/// Paths refused in demo mode even for reads
+const OPERATOR_PATHS: [&str; 2] = ["/api/admin", "/api/alerts/webhooks"];
+
+/// A wallet address, masked in demo responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalletAddress(pub Address20);
+
+impl From<Address20> for WalletAddress {
+    fn from(address: Address20) -> Self {
+        Self(address)
+    }
+}
+
+impl Serialize for WalletAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if MASK_WALLETS.try_with(|mask| *mask).unwrap_or(false) {
+            serializer.serialize_str(&mask(&self.0))
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+/// `0x` plus the first and last four hex digits
+fn mask(address: &Address20) -> String {
+    let hex = address.to_hex();
+    format!("{}...{}", &hex[..6], &hex[hex.len() - 4..])
+}
+
+/// Whether a demo deployment should refuse this request
+fn refused(method: &Method, path: &str) -> bool {
+    let read = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+    let path = version::unversioned(path);
+    !read || OPERATOR_PATHS.iter().any(|p| path.starts_with(p))
+}
+
+/// Run `f` with wallets masked or not, e.g. to serialize outside of the
+/// request it was for
+pub fn masking<R>(mask: bool, f: impl FnOnce() -> R) -> R {
+    MASK_WALLETS.sync_scope(mask, f)
+}
+
+/// Refuse writes and operator endpoints and mask wallets when in demo mode
+pub async fn middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.demo && refused(request.method(), request.uri().path()) {
+        return ApiError::DemoReadOnly(format!(
+            "{} {} is disabled in the public demo",
+            request.method(),
+            request.uri().path()
+        ))
+        .into_response();
+    }
+
+    MASK_WALLETS.scope(state.demo, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wallets_are_masked_only_in_demo_requests() {
+        let wallet = WalletAddress(Address20::new([0xab; 20]));
+        let full = "0xabababababababababababababababababababab";
+
+        assert_eq!(serde_json::to_value(wallet).unwrap(), full);
+        let masked = MASK_WALLETS
+            .scope(true, async { serde_json::to_value(wallet).unwrap() })
+            .await;
+        assert_eq!(masked, "0xabab...abab");
+        let plain = MASK_WALLETS
+            .scope(false, async { serde_json::to_value(wallet).unwrap() })
+            .await;
+        assert_eq!(plain, full);
+    }
+
+    #[test]
+    fn demo_refuses_writes_and_operator_reads() {
+        assert!(!refused(&Method::GET, "/api/wallets"));
+        assert!(refused(&Method::POST, "/api/wallets"));
+        assert!(refused(&Method::DELETE, "/api/wallets/0x01"));
+        assert!(refused(&Method::GET, "/api/admin/listeners"));
+        assert!(refused(&Method::GET, "/api/v2/admin/listeners"));
+        assert!(refused(&Method::GET, "/api/alerts/webhooks"));
+        assert!(!refused(&Method::GET, "/api/alerts/feed"));
+    }
+}
@@ -0,0 +1,184 @@
+//! List response envelopes
+//!
+//! List endpoints return a bare JSON array under API version 1. Version 2
+//! (`/api/v2`, or `Accept: application/vnd.beanbee.v2+json` / `API-Version: 2`
+//! on the unversioned paths) wraps the list as `{ "data": [...], "meta": {...} }`
+//! instead, which leaves room for paging and freshness information without
+//! breaking clients that expect an array.
+
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use indexer_db::entity::evm_sync_logs::EvmSyncLogs;
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+
+use crate::{error::ApiResult, version::ApiVersion};
+
+/// Media type asking for enveloped lists
+pub const ENVELOPE_MEDIA_TYPE: &str = "application/vnd.beanbee.v2+json";
+
+/// Shape the client asked list responses to take
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseShape {
+    /// A bare JSON array
+    #[default]
+    Array,
+    /// `{ data, meta }`
+    Envelope,
+}
+
+impl ResponseShape {
+    /// Shape for the version negotiated by [`crate::version::middleware`],
+    /// falling back to the request headers outside of it
+    fn from_parts(parts: &Parts) -> Self {
+        let version = match parts.extensions.get::<ApiVersion>() {
+            Some(version) => *version,
+            None => ApiVersion::requested(&parts.headers)
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+        };
+
+        match version {
+            ApiVersion::V1 => ResponseShape::Array,
+            ApiVersion::V2 => ResponseShape::Envelope,
+        }
+    }
+
+    /// Respond with `data` in this shape, looking up the sync lag only when
+    /// it is going to be sent
+    pub async fn list<T: Serialize>(
+        self,
+        data: Vec<T>,
+        db_pool: &Pool<Postgres>,
+    ) -> ApiResult<Listing<T>> {
+        let meta = match self {
+            ResponseShape::Array => None,
+            ResponseShape::Envelope => Some(ListMeta {
+                count: data.len(),
+                cursor: None,
+                generated_at: Utc::now().to_rfc3339(),
+                sync_lag_blocks: EvmSyncLogs::max_lag_blocks(db_pool).await?,
+            }),
+        };
+        Ok(Listing { data, meta })
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ResponseShape {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_parts(parts))
+    }
+}
+
+/// Freshness and paging information sent alongside an enveloped list
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListMeta {
+    /// Items in `data`; no endpoint counts the matches past this page
+    pub count: usize,
+    /// Where the next page starts; only sent by endpoints that page by cursor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub generated_at: String,
+    /// Blocks the indexed data trails the chain head, as of the listener's
+    /// last poll
+    pub sync_lag_blocks: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct Envelope<T> {
+    data: Vec<T>,
+    meta: ListMeta,
+}
+
+/// A list response in the shape the client asked for
+#[derive(Debug)]
+pub struct Listing<T> {
+    data: Vec<T>,
+    meta: Option<ListMeta>,
+}
+
+impl<T> Listing<T> {
+    /// Tell enveloped clients where the next page starts
+    pub fn with_cursor(mut self, cursor: Option<String>) -> Self {
+        if let Some(meta) = self.meta.as_mut() {
+            meta.cursor = cursor;
+        }
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for Listing<T> {
+    fn into_response(self) -> Response {
+        let mut response = match self.meta {
+            None => Json(self.data).into_response(),
+            Some(meta) => Json(Envelope {
+                data: self.data,
+                meta,
+            })
+            .into_response(),
+        };
+        // Caches must key list responses on the headers that pick the shape
+        response.headers_mut().insert(
+            header::VARY,
+            HeaderValue::from_static("accept, api-version"),
+        );
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    fn shape(headers: &[(&str, &str)]) -> ResponseShape {
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (parts, _) = request.body(()).unwrap().into_parts();
+        ResponseShape::from_parts(&parts)
+    }
+
+    #[test]
+    fn envelopes_are_opt_in() {
+        assert_eq!(shape(&[]), ResponseShape::Array);
+        assert_eq!(
+            shape(&[("accept", "application/json")]),
+            ResponseShape::Array
+        );
+        assert_eq!(shape(&[("api-version", "1")]), ResponseShape::Array);
+
+        assert_eq!(
+            shape(&[(
+                "accept",
+                "text/html, application/vnd.beanbee.v2+json; q=0.9"
+            )]),
+            ResponseShape::Envelope
+        );
+        assert_eq!(shape(&[("api-version", "2")]), ResponseShape::Envelope);
+
+        // The negotiated version wins over the headers
+        let mut request = Request::builder()
+            .header("api-version", "1")
+            .body(())
+            .unwrap();
+        request.extensions_mut().insert(ApiVersion::V2);
+        let (parts, _) = request.into_parts();
+        assert_eq!(ResponseShape::from_parts(&parts), ResponseShape::Envelope);
+    }
+}
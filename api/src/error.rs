@@ -0,0 +1,302 @@
+//! API error type
+//!
+//! Every handler returns [`ApiError`] on failure. Errors are rendered as
+//! RFC 7807 `application/problem+json` documents carrying a stable,
+//! machine-readable `code` so clients don't have to match on messages.
+
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        FromRequest, FromRequestParts, Query, Request,
+    },
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::address::{self, AddressChecked, InvalidAddressError};
+
+/// Result type for API handlers
+pub type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("Token `{0}` not found")]
+    TokenNotFound(String),
+
+    #[error("Wallet `{0}` not found")]
+    WalletNotFound(String),
+
+    #[error("Webhook `{0}` not found")]
+    WebhookNotFound(String),
+
+    #[error("Listener `{0}` not found")]
+    ListenerNotFound(String),
+
+    #[error("Tag `{0}` not found")]
+    TagNotFound(String),
+
+    #[error("Rescan `{0}` not found")]
+    RescanNotFound(String),
+
+    #[error("Holder verification `{0}` not found")]
+    VerificationNotFound(String),
+
+    #[error("Job `{0}` not found")]
+    JobNotFound(String),
+
+    #[error("No token list has been published yet")]
+    TokenListNotPublished,
+
+    #[error("{0}")]
+    InvalidAddress(String),
+
+    #[error("Invalid request body: {0}")]
+    InvalidBody(String),
+
+    #[error("Invalid query parameters: {0}")]
+    InvalidQuery(String),
+
+    #[error("{0}")]
+    UnsupportedVersion(String),
+
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("Token `{0}` has no pair with known reserves")]
+    NoLiquidity(String),
+
+    #[error("Token `{0}` has no BeeScore computation yet")]
+    NotScored(String),
+
+    #[error("Token `{0}` is already tracked")]
+    TokenAlreadyTracked(String),
+
+    #[error("{0}")]
+    DemoReadOnly(String),
+
+    #[error("Too many requests, slow down")]
+    RateLimited,
+
+    #[error("Too many token imports are queued, try again later")]
+    ImportQueueFull,
+
+    #[error("{0}")]
+    InvalidIdempotencyKey(String),
+
+    #[error("A request with Idempotency-Key `{0}` is still being processed")]
+    IdempotencyKeyInUse(String),
+
+    #[error("Idempotency-Key `{0}` was already used for a different request")]
+    IdempotencyKeyReused(String),
+
+    #[error("Database error")]
+    Database(#[from] sqlx::Error),
+}
+
+/// RFC 7807 problem details body
+#[derive(Debug, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+}
+
+impl ApiError {
+    /// Machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::TokenNotFound(_) => "TOKEN_NOT_FOUND",
+            ApiError::WalletNotFound(_) => "WALLET_NOT_FOUND",
+            ApiError::WebhookNotFound(_) => "WEBHOOK_NOT_FOUND",
+            ApiError::ListenerNotFound(_) => "LISTENER_NOT_FOUND",
+            ApiError::TagNotFound(_) => "TAG_NOT_FOUND",
+            ApiError::RescanNotFound(_) => "RESCAN_NOT_FOUND",
+            ApiError::VerificationNotFound(_) => "VERIFICATION_NOT_FOUND",
+            ApiError::JobNotFound(_) => "JOB_NOT_FOUND",
+            ApiError::TokenListNotPublished => "TOKEN_LIST_NOT_PUBLISHED",
+            ApiError::InvalidAddress(_) => "INVALID_ADDRESS",
+            ApiError::InvalidBody(_) => "INVALID_BODY",
+            ApiError::InvalidQuery(_) => "INVALID_QUERY",
+            ApiError::UnsupportedVersion(_) => "UNSUPPORTED_API_VERSION",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::NoLiquidity(_) => "NO_LIQUIDITY",
+            ApiError::NotScored(_) => "NOT_SCORED",
+            ApiError::TokenAlreadyTracked(_) => "TOKEN_ALREADY_TRACKED",
+            ApiError::DemoReadOnly(_) => "DEMO_READ_ONLY",
+            ApiError::RateLimited => "RATE_LIMITED",
+            ApiError::ImportQueueFull => "IMPORT_QUEUE_FULL",
+            ApiError::InvalidIdempotencyKey(_) => "INVALID_IDEMPOTENCY_KEY",
+            ApiError::IdempotencyKeyInUse(_) => "IDEMPOTENCY_KEY_IN_USE",
+            ApiError::IdempotencyKeyReused(_) => "IDEMPOTENCY_KEY_REUSED",
+            ApiError::Database(_) => "DATABASE_ERROR",
+        }
+    }
+
+    /// HTTP status for the error
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::TokenNotFound(_)
+            | ApiError::WalletNotFound(_)
+            | ApiError::WebhookNotFound(_)
+            | ApiError::ListenerNotFound(_)
+            | ApiError::TagNotFound(_)
+            | ApiError::RescanNotFound(_)
+            | ApiError::VerificationNotFound(_)
+            | ApiError::JobNotFound(_)
+            | ApiError::TokenListNotPublished => StatusCode::NOT_FOUND,
+            ApiError::InvalidAddress(_)
+            | ApiError::InvalidBody(_)
+            | ApiError::InvalidQuery(_)
+            | ApiError::UnsupportedVersion(_)
+            | ApiError::InvalidIdempotencyKey(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::DemoReadOnly(_) => StatusCode::FORBIDDEN,
+            ApiError::NoLiquidity(_)
+            | ApiError::NotScored(_)
+            | ApiError::IdempotencyKeyReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::IdempotencyKeyInUse(_) | ApiError::TokenAlreadyTracked(_) => {
+                StatusCode::CONFLICT
+            }
+            ApiError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::ImportQueueFull => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Short human-readable summary, constant per code
+    fn title(&self) -> &'static str {
+        match self {
+            ApiError::TokenNotFound(_) => "Token not found",
+            ApiError::WalletNotFound(_) => "Wallet not found",
+            ApiError::WebhookNotFound(_) => "Webhook not found",
+            ApiError::ListenerNotFound(_) => "Listener not found",
+            ApiError::TagNotFound(_) => "Tag not found",
+            ApiError::RescanNotFound(_) => "Rescan not found",
+            ApiError::VerificationNotFound(_) => "Verification not found",
+            ApiError::JobNotFound(_) => "Job not found",
+            ApiError::TokenListNotPublished => "Token list not published",
+            ApiError::InvalidAddress(_) => "Invalid address",
+            ApiError::InvalidBody(_) => "Invalid request body",
+            ApiError::InvalidQuery(_) => "Invalid query parameters",
+            ApiError::UnsupportedVersion(_) => "Unsupported API version",
+            ApiError::Unauthorized(_) => "Unauthorized",
+            ApiError::NoLiquidity(_) => "No liquidity",
+            ApiError::NotScored(_) => "Not scored",
+            ApiError::TokenAlreadyTracked(_) => "Token already tracked",
+            ApiError::DemoReadOnly(_) => "Read-only demo",
+            ApiError::RateLimited => "Rate limited",
+            ApiError::ImportQueueFull => "Import queue full",
+            ApiError::InvalidIdempotencyKey(_) => "Invalid idempotency key",
+            ApiError::IdempotencyKeyInUse(_) => "Idempotency key in use",
+            ApiError::IdempotencyKeyReused(_) => "Idempotency key reused",
+            ApiError::Database(_) => "Internal server error",
+        }
+    }
+
+    /// Build the problem details body for this error
+    pub fn to_problem(&self) -> ProblemDetails {
+        ProblemDetails {
+            problem_type: format!("/problems/{}", self.code().to_lowercase().replace('_', "-")),
+            title: self.title(),
+            status: self.status().as_u16(),
+            detail: self.to_string(),
+            code: self.code(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if let ApiError::Database(e) = &self {
+            tracing::error!("Database error: {}", e);
+        }
+
+        let status = self.status();
+        let body = Json(self.to_problem());
+
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            body,
+        )
+            .into_response()
+    }
+}
+
+impl ApiError {
+    /// A rejected body, reported as an invalid address when `address` failed
+    /// while deserializing it
+    fn from_json_rejection(rejection: JsonRejection, address: Option<InvalidAddressError>) -> Self {
+        let text = rejection.body_text();
+        match (rejection, address) {
+            (JsonRejection::JsonDataError(_), Some(_)) => ApiError::InvalidAddress(text),
+            _ => ApiError::InvalidBody(text),
+        }
+    }
+
+    /// A rejected query string, reported as an invalid address when `address`
+    /// failed while deserializing it
+    fn from_query_rejection(
+        rejection: QueryRejection,
+        address: Option<InvalidAddressError>,
+    ) -> Self {
+        let text = rejection.body_text();
+        match (rejection, address) {
+            (QueryRejection::FailedToDeserializeQueryString(_), Some(_)) => {
+                ApiError::InvalidAddress(text)
+            }
+            _ => ApiError::InvalidQuery(text),
+        }
+    }
+}
+
+/// JSON body extractor that rejects with [`ApiError`]
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<AddressChecked<T>>::from_request(req, state).await {
+            Ok(Json(AddressChecked(value))) => Ok(ApiJson(value)),
+            Err(rejection) => Err(ApiError::from_json_rejection(
+                rejection,
+                address::take_failure(),
+            )),
+        }
+    }
+}
+
+/// Query string extractor that rejects with [`ApiError`]
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<AddressChecked<T>>::from_request_parts(parts, state).await {
+            Ok(Query(AddressChecked(value))) => Ok(ApiQuery(value)),
+            Err(rejection) => Err(ApiError::from_query_rejection(
+                rejection,
+                address::take_failure(),
+            )),
+        }
+    }
+}
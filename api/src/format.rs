@@ -0,0 +1,129 @@
+//! Display formatting for API responses
+//!
+//! Raw numbers stay in every response; these strings sit next to them so
+//! frontends don't each reimplement memecoin price formatting. Micro-prices
+//! use subscript-zero notation: 0.00000421 is shown as `0.0₅421`.
+
+/// Significant digits shown for prices below 1
+const PRICE_SIGNIFICANT_DIGITS: usize = 4;
+
+/// Leading zeros after the decimal point from which they're collapsed
+const SUBSCRIPT_MIN_ZEROS: usize = 4;
+
+/// Suffixes for compact amounts, smallest first
+const COMPACT_UNITS: [(f64, &str); 4] = [(1e3, "K"), (1e6, "M"), (1e9, "B"), (1e12, "T")];
+
+/// Format a price: grouped with 2 decimals from 1 up, 4 significant digits
+/// below, collapsing long runs of leading zeros into a subscript count
+pub fn price(value: f64) -> String {
+    if !value.is_finite() || value <= 0.0 {
+        return "0".to_string();
+    }
+    if value >= 1.0 {
+        return grouped(value, 2);
+    }
+
+    // Zeros between the decimal point and the first significant digit
+    let zeros = (-value.log10()).ceil() as usize - 1;
+    let rounded = format!("{:.*}", zeros + PRICE_SIGNIFICANT_DIGITS, value);
+    let Some(fraction) = rounded.strip_prefix("0.") else {
+        // Rounded up to 1
+        return grouped(1.0, 2);
+    };
+
+    let digits = fraction.trim_start_matches('0');
+    let zeros = fraction.len() - digits.len();
+    let digits = digits.trim_end_matches('0');
+
+    if zeros >= SUBSCRIPT_MIN_ZEROS {
+        format!("0.0{}{}", subscript(zeros), digits)
+    } else {
+        format!("0.{}{}", "0".repeat(zeros), digits)
+    }
+}
+
+/// Format a USD amount compactly, e.g. `$52.3K`, `$1.2M`, `$840.50`
+pub fn compact_usd(value: f64) -> String {
+    if !value.is_finite() {
+        return "$0".to_string();
+    }
+
+    let sign = if value < 0.0 { "-" } else { "" };
+    let abs = value.abs();
+    if abs < 1.0 {
+        return format!("{}${}", sign, price(abs));
+    }
+
+    // Largest unit the amount reaches once rounded to one decimal
+    let unit = COMPACT_UNITS
+        .iter()
+        .rev()
+        .find(|(size, _)| (abs / size * 10.0).round() / 10.0 >= 1.0);
+
+    match unit {
+        Some((size, suffix)) => {
+            let scaled = format!("{:.1}", abs / size);
+            let scaled = scaled.strip_suffix(".0").unwrap_or(&scaled);
+            format!("{}${}{}", sign, scaled, suffix)
+        }
+        None => format!("{}${}", sign, grouped(abs, 2)),
+    }
+}
+
+/// Fixed decimals with comma-grouped thousands
+fn grouped(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value);
+    let (integer, fraction) = formatted
+        .split_once('.')
+        .map_or((formatted.as_str(), None), |(i, f)| (i, Some(f)));
+
+    let mut out = String::with_capacity(formatted.len() + integer.len() / 3);
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    if let Some(fraction) = fraction {
+        out.push('.');
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// Render a count with Unicode subscript digits
+fn subscript(n: usize) -> String {
+    n.to_string()
+        .chars()
+        .filter_map(|d| d.to_digit(10))
+        .filter_map(|d| char::from_u32(0x2080 + d))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_collapse_leading_zeros() {
+        assert_eq!(price(0.00000421), "0.0₅421");
+        assert_eq!(price(0.000000000012345), "0.0₁₀1235");
+        assert_eq!(price(0.0001234), "0.0001234");
+        assert_eq!(price(0.05), "0.05");
+        assert_eq!(price(0.99999), "1.00");
+        assert_eq!(price(1234.5678), "1,234.57");
+        assert_eq!(price(0.0), "0");
+        assert_eq!(price(f64::NAN), "0");
+    }
+
+    #[test]
+    fn usd_amounts_are_compact() {
+        assert_eq!(compact_usd(52_300.0), "$52.3K");
+        assert_eq!(compact_usd(1_000_000.0), "$1M");
+        assert_eq!(compact_usd(999_960.0), "$1M");
+        assert_eq!(compact_usd(2_500_000_000.0), "$2.5B");
+        assert_eq!(compact_usd(840.5), "$840.50");
+        assert_eq!(compact_usd(-1_500.0), "-$1.5K");
+        assert_eq!(compact_usd(0.0042), "$0.0042");
+    }
+}
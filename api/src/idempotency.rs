@@ -0,0 +1,249 @@
+//! Idempotent retries of mutating requests
+//!
+//! A `POST`, `PUT`, `PATCH` or `DELETE` sent with an `Idempotency-Key` header
+//! has its response stored under that key for [`KEY_TTL_HOURS`]. Keys are
+//! scoped to the request's `X-API-Key`, so clients never see each other's
+//! responses. Retrying with the same key replays the stored status and body
+//! (marked with `Idempotent-Replayed: true`) instead of running the request
+//! again. Reusing a key for a different request is refused with `422`, and a
+//! retry that arrives while the first attempt is still running gets `409`;
+//! an attempt that never finished frees its key after [`PENDING_TTL_SECS`].
+//! Server errors and auth or rate limit refusals aren't stored, so a request
+//! that failed that way can be retried as is.
+
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use indexer_db::entity::IdempotencyKey;
+
+use crate::{auth::API_KEY_HEADER, error::ApiError, AppState};
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Set on responses replayed from an earlier attempt
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Hours a key's response is kept for replay
+pub const KEY_TTL_HOURS: i64 = 24;
+
+/// Seconds a key stays taken by an attempt that didn't store a response,
+/// e.g. one cut off by a restart
+pub const PENDING_TTL_SECS: i64 = 60;
+
+/// Longest key accepted
+const MAX_KEY_LEN: usize = 255;
+
+/// Largest request or response body buffered for a keyed request
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// The request's idempotency key, if it is a mutation that carries one
+fn key_of(request: &Request) -> Option<Result<String, ApiError>> {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return None;
+    }
+
+    let value = request.headers().get(IDEMPOTENCY_KEY)?;
+    let key = value.to_str().ok().map(str::trim).unwrap_or_default();
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Some(Err(ApiError::InvalidIdempotencyKey(format!(
+            "Idempotency-Key must be 1 to {} visible ASCII characters",
+            MAX_KEY_LEN
+        ))));
+    }
+    Some(Ok(key.to_string()))
+}
+
+/// Responses a retry should run again rather than replay: server errors,
+/// and refusals that say nothing about the request itself
+fn is_stored(status: StatusCode) -> bool {
+    !status.is_server_error()
+        && !matches!(
+            status,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+        )
+}
+
+/// What a key is checked against: method, path with query, and body
+fn fingerprint(method: &Method, uri: &str, body: &[u8]) -> Vec<u8> {
+    let mut request = format!("{} {}\n", method, uri).into_bytes();
+    request.extend_from_slice(body);
+    request
+}
+
+/// Replay the stored response to a keyed request or store a new one
+pub async fn middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let key = match key_of(&request) {
+        None => return next.run(request).await,
+        Some(Ok(key)) => key,
+        Some(Err(e)) => return e.into_response(),
+    };
+
+    let (parts, body) = request.into_parts();
+    let client = parts
+        .headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return ApiError::InvalidBody("Request body too large".to_string()).into_response();
+    };
+    let uri = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_else(|| parts.uri.path());
+    let request_print = fingerprint(&parts.method, uri, &body);
+
+    let now = Utc::now();
+    match IdempotencyKey::claim(
+        &client,
+        &key,
+        &request_print,
+        now - Duration::hours(KEY_TTL_HOURS),
+        now - Duration::seconds(PENDING_TTL_SECS),
+        &state.db_pool,
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => return replay(&state, &client, &key, &request_print).await,
+        Err(e) => return ApiError::Database(e).into_response(),
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    if !is_stored(response.status()) {
+        if let Err(e) = IdempotencyKey::release(&client, &key, &state.db_pool).await {
+            tracing::warn!("Failed to release idempotency key `{}`: {}", key, e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!(
+                "Failed to buffer response for idempotency key `{}`: {}",
+                key,
+                e
+            );
+            if let Err(e) = IdempotencyKey::release(&client, &key, &state.db_pool).await {
+                tracing::warn!("Failed to release idempotency key `{}`: {}", key, e);
+            }
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if let Err(e) = IdempotencyKey::complete(
+        &client,
+        &key,
+        parts.status.as_u16() as i16,
+        content_type,
+        &body,
+        &state.db_pool,
+    )
+    .await
+    {
+        tracing::warn!(
+            "Failed to store response for idempotency key `{}`: {}",
+            key,
+            e
+        );
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Answer a retry from the stored response
+async fn replay(state: &AppState, client: &str, key: &str, request_print: &[u8]) -> Response {
+    let stored = match IdempotencyKey::find_by_key(client, key, request_print, &state.db_pool)
+        .await
+    {
+        Ok(stored) => stored,
+        Err(e) => return ApiError::Database(e).into_response(),
+    };
+
+    // Released between the claim and the lookup: the first attempt just failed
+    let Some(stored) = stored else {
+        return ApiError::IdempotencyKeyInUse(key.to_string()).into_response();
+    };
+    if !stored.same_request {
+        return ApiError::IdempotencyKeyReused(key.to_string()).into_response();
+    }
+    let Some(status) = stored
+        .response_status
+        .and_then(|s| StatusCode::from_u16(s as u16).ok())
+    else {
+        return ApiError::IdempotencyKeyInUse(key.to_string()).into_response();
+    };
+
+    let mut response = (status, stored.response_body.unwrap_or_default()).into_response();
+    let headers = response.headers_mut();
+    headers.remove(header::CONTENT_TYPE);
+    if let Some(content_type) = stored
+        .response_content_type
+        .and_then(|c| HeaderValue::from_str(&c).ok())
+    {
+        headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, key: Option<&str>) -> Request {
+        let mut request = Request::builder().method(method).uri("/api/wallets");
+        if let Some(key) = key {
+            request = request.header(IDEMPOTENCY_KEY, key);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn only_keyed_mutations_are_tracked() {
+        assert!(key_of(&request(Method::GET, Some("abc"))).is_none());
+        assert!(key_of(&request(Method::POST, None)).is_none());
+        assert_eq!(
+            key_of(&request(Method::POST, Some(" abc ")))
+                .unwrap()
+                .unwrap(),
+            "abc"
+        );
+        assert!(key_of(&request(Method::DELETE, Some(""))).unwrap().is_err());
+        let long = "k".repeat(MAX_KEY_LEN + 1);
+        assert!(key_of(&request(Method::PUT, Some(&long))).unwrap().is_err());
+    }
+
+    #[test]
+    fn refusals_and_server_errors_are_not_stored() {
+        assert!(is_stored(StatusCode::CREATED));
+        assert!(is_stored(StatusCode::BAD_REQUEST));
+        assert!(is_stored(StatusCode::CONFLICT));
+        assert!(!is_stored(StatusCode::UNAUTHORIZED));
+        assert!(!is_stored(StatusCode::FORBIDDEN));
+        assert!(!is_stored(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_stored(StatusCode::SERVICE_UNAVAILABLE));
+    }
+}
@@ -2,35 +2,88 @@
 //!
 //! REST API endpoints for the BeanBee frontend.
 
-use std::{env, net::SocketAddr, sync::Arc};
+use std::{env, net::SocketAddr, sync::Arc, time::Duration};
 
-use axum::{routing::get, Router};
+use axum::{http::HeaderName, response::Html, routing::get, Router};
+use indexer_db::Address20;
 use sqlx::{Pool, Postgres};
 use tower_http::cors::{Any, CorsLayer};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
+mod address;
+mod alert_notify;
+mod amm;
+mod auth;
+mod badges;
+mod cache;
+mod console;
+mod decimal;
+mod demo;
+mod envelope;
+mod error;
+mod format;
+mod idempotency;
+mod query_stats;
+mod rate_limit;
 mod routes;
+mod score_advice;
+mod stream;
+mod version;
+
+#[cfg(test)]
+mod tests;
 
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: Pool<Postgres>,
+    /// Key required by ingestion endpoints; ingestion is disabled when unset
+    pub ingest_api_key: Option<String>,
+    /// How prices and amounts are serialized unless a request asks otherwise
+    pub precision: decimal::Precision,
+    /// Public read-only demo: no writes, lower rate limits, masked wallets
+    pub demo: bool,
+    pub rate_limiter: rate_limit::RateLimiter,
+    /// `Sunset` HTTP-date sent on the deprecated unversioned `/api` paths
+    pub legacy_sunset: String,
+    /// Seconds alert feed pollers are told to wait between polls
+    pub alert_poll_interval_secs: u64,
+    /// Recent swaps and alerts for the WebSocket streams
+    pub streams: Arc<stream::Streams>,
+    /// Wakes long-polling alert feed requests when an alert is raised
+    pub alert_notifier: alert_notify::AlertNotifier,
+    /// Wallet trading profiles, kept `WALLET_PROFILE_CACHE_SECS`
+    pub wallet_profiles: cache::TtlCache<Address20, routes::wallets::WalletTradingProfile>,
+    /// Statements and DB time per endpoint, with the per-request budget
+    pub query_metrics: query_stats::QueryMetrics,
 }
 
 mod defaults {
     pub const API_PORT: &str = "8080";
     pub const API_HOST: &str = "0.0.0.0";
+    pub const QUERY_PLAN_CHECK: &str = "false";
+    pub const DECIMAL_PRECISION: &str = "number";
+    pub const DEMO_MODE: &str = "false";
+    pub const RATE_LIMIT_PER_MINUTE: &str = "0";
+    pub const DEMO_RATE_LIMIT_PER_MINUTE: &str = "60";
+    pub const LEGACY_API_SUNSET: &str = "Thu, 01 Jul 2027 00:00:00 GMT";
+    pub const ALERT_POLL_INTERVAL_SECS: &str = "5";
+    pub const STREAM_POLL_MS: &str = "1000";
+    pub const STREAM_REPLAY_SIZE: &str = "1000";
+    pub const WALLET_PROFILE_CACHE_SECS: &str = "300";
+    pub const REQUEST_QUERY_BUDGET: &str = "25";
+    pub const REQUEST_DB_TIME_BUDGET_MS: &str = "500";
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
+    // Initialize tracing; statements are counted per request whatever is logged
     tracing_subscriber::registry()
-        .with(
+        .with(tracing_subscriber::fmt::layer().with_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "api=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        ))
+        .with(query_stats::layer())
         .init();
 
     tracing::info!("Starting BeanBee API Server...");
@@ -39,27 +92,117 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_pool = indexer_db::initialize_database().await?;
     tracing::info!("Connected to database");
 
-    // Create app state
-    let state = Arc::new(AppState { db_pool });
+    // Optionally EXPLAIN the hot queries and warn if any need a sequential scan
+    let query_plan_check = env::var("QUERY_PLAN_CHECK")
+        .unwrap_or_else(|_| defaults::QUERY_PLAN_CHECK.to_string())
+        .parse::<bool>()
+        .unwrap_or(false);
 
-    // CORS configuration
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    if query_plan_check {
+        match indexer_db::query_plan::check_query_plans(&db_pool).await {
+            Ok(warnings) => {
+                for warning in &warnings {
+                    tracing::warn!(
+                        "Query `{}` falls back to a sequential scan on `{}`",
+                        warning.query,
+                        warning.relation
+                    );
+                }
+                tracing::info!("Query plan check done ({} warnings)", warnings.len());
+            }
+            Err(e) => tracing::warn!("Query plan check failed: {}", e),
+        }
+    }
+
+    let ingest_api_key = env::var("INGEST_API_KEY").ok().filter(|k| !k.is_empty());
+    if ingest_api_key.is_none() {
+        tracing::info!("INGEST_API_KEY not set, ingestion endpoints are disabled");
+    }
+
+    let precision =
+        env::var("DECIMAL_PRECISION").unwrap_or_else(|_| defaults::DECIMAL_PRECISION.to_string());
+    let precision = decimal::Precision::parse(&precision).unwrap_or_default();
+
+    let demo = env::var("DEMO_MODE")
+        .unwrap_or_else(|_| defaults::DEMO_MODE.to_string())
+        .parse::<bool>()
+        .unwrap_or(false);
+
+    let rate_limit = if demo {
+        tracing::info!("DEMO_MODE is on: read-only, wallet addresses masked");
+        env::var("DEMO_RATE_LIMIT_PER_MINUTE")
+            .unwrap_or_else(|_| defaults::DEMO_RATE_LIMIT_PER_MINUTE.to_string())
+            .parse::<u32>()
+            .unwrap_or(60)
+    } else {
+        env::var("RATE_LIMIT_PER_MINUTE")
+            .unwrap_or_else(|_| defaults::RATE_LIMIT_PER_MINUTE.to_string())
+            .parse::<u32>()
+            .unwrap_or(0)
+    };
+
+    let legacy_sunset =
+        env::var("LEGACY_API_SUNSET").unwrap_or_else(|_| defaults::LEGACY_API_SUNSET.to_string());
+
+    let alert_poll_interval_secs = env::var("ALERT_POLL_INTERVAL_SECS")
+        .unwrap_or_else(|_| defaults::ALERT_POLL_INTERVAL_SECS.to_string())
+        .parse::<u64>()
+        .unwrap_or(5);
+
+    let stream_poll_ms = env::var("STREAM_POLL_MS")
+        .unwrap_or_else(|_| defaults::STREAM_POLL_MS.to_string())
+        .parse::<u64>()
+        .unwrap_or(1000)
+        .max(100);
+    let stream_replay_size = env::var("STREAM_REPLAY_SIZE")
+        .unwrap_or_else(|_| defaults::STREAM_REPLAY_SIZE.to_string())
+        .parse::<usize>()
+        .unwrap_or(1000);
+    let streams = Arc::new(stream::Streams::new(stream_replay_size));
+    tokio::spawn(stream::run(
+        streams.clone(),
+        db_pool.clone(),
+        Duration::from_millis(stream_poll_ms),
+    ));
+
+    let alert_notifier = alert_notify::AlertNotifier::new();
+    tokio::spawn(alert_notify::run(alert_notifier.clone(), db_pool.clone()));
+
+    let wallet_profile_cache_secs = env::var("WALLET_PROFILE_CACHE_SECS")
+        .unwrap_or_else(|_| defaults::WALLET_PROFILE_CACHE_SECS.to_string())
+        .parse::<u64>()
+        .unwrap_or(300);
+
+    let query_budget = query_stats::QueryBudget {
+        max_queries: env::var("REQUEST_QUERY_BUDGET")
+            .unwrap_or_else(|_| defaults::REQUEST_QUERY_BUDGET.to_string())
+            .parse::<usize>()
+            .unwrap_or(25),
+        max_db_time: Duration::from_millis(
+            env::var("REQUEST_DB_TIME_BUDGET_MS")
+                .unwrap_or_else(|_| defaults::REQUEST_DB_TIME_BUDGET_MS.to_string())
+                .parse::<u64>()
+                .unwrap_or(500),
+        ),
+    };
+
+    // Create app state
+    let state = Arc::new(AppState {
+        db_pool,
+        ingest_api_key,
+        precision,
+        demo,
+        rate_limiter: rate_limit::RateLimiter::new(rate_limit),
+        legacy_sunset,
+        alert_poll_interval_secs,
+        streams,
+        alert_notifier,
+        wallet_profiles: cache::TtlCache::new(Duration::from_secs(wallet_profile_cache_secs)),
+        query_metrics: query_stats::QueryMetrics::new(query_budget),
+    });
 
     // Build router
-    let app = Router::new()
-        // Root endpoint with API info
-        .route("/", get(root))
-        // Health check
-        .route("/health", get(health_check))
-        // API routes
-        .nest("/api", routes::api_routes())
-        // State and middleware
-        .with_state(state)
-        .layer(cors)
-        .layer(tower_http::trace::TraceLayer::new_for_http());
+    let app = app(state);
 
     // Get port from environment
     let port = env::var("API_PORT")
@@ -74,81 +217,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Start server
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
 
-/// Root endpoint - API information
-async fn root() -> axum::response::Html<&'static str> {
-    axum::response::Html(r#"
-<!DOCTYPE html>
-<html>
-<head>
-    <title>BeanBee API</title>
-    <style>
-        body { font-family: system-ui, sans-serif; max-width: 800px; margin: 50px auto; padding: 20px; background: #1a1a2e; color: #eee; }
-        h1 { color: #f5a623; }
-        a { color: #4fc3f7; }
-        code { background: #333; padding: 2px 6px; border-radius: 4px; }
-        .endpoint { margin: 10px 0; padding: 10px; background: #252540; border-radius: 8px; }
-        .method { color: #4caf50; font-weight: bold; }
-    </style>
-</head>
-<body>
-    <h1>BeanBee API</h1>
-    <p>BSC Memecoin Alpha Discovery Engine</p>
-
-    <h2>Endpoints</h2>
-
-    <div class="endpoint">
-        <span class="method">GET</span> <a href="/health">/health</a> - Health check
-    </div>
-
-    <h3>Tokens</h3>
-    <div class="endpoint">
-        <span class="method">GET</span> <a href="/api/tokens/new">/api/tokens/new</a> - Newest tokens
-    </div>
-    <div class="endpoint">
-        <span class="method">GET</span> <a href="/api/tokens/hot">/api/tokens/hot</a> - Hot tokens by volume
-    </div>
-    <div class="endpoint">
-        <span class="method">GET</span> <code>/api/tokens/:address</code> - Token details
-    </div>
-    <div class="endpoint">
-        <span class="method">GET</span> <code>/api/tokens/:address/swaps</code> - Token swaps
-    </div>
-    <div class="endpoint">
-        <span class="method">GET</span> <code>/api/tokens/:address/holders</code> - Token holders
-    </div>
-    <div class="endpoint">
-        <span class="method">GET</span> <code>/api/tokens/:address/chart</code> - Price chart data
-    </div>
-
-    <h3>Wallets</h3>
-    <div class="endpoint">
-        <span class="method">GET</span> <a href="/api/wallets">/api/wallets</a> - List tracked wallets
-    </div>
-    <div class="endpoint">
-        <span class="method">POST</span> <code>/api/wallets</code> - Add wallet to track
-    </div>
-    <div class="endpoint">
-        <span class="method">GET</span> <code>/api/wallets/:address</code> - Get wallet details
-    </div>
-    <div class="endpoint">
-        <span class="method">DELETE</span> <code>/api/wallets/:address</code> - Remove wallet
-    </div>
-    <div class="endpoint">
-        <span class="method">GET</span> <code>/api/wallets/:address/activity</code> - Wallet activity
-    </div>
-
-    <h3>Alerts</h3>
-    <div class="endpoint">
-        <span class="method">GET</span> <a href="/api/alerts/feed">/api/alerts/feed</a> - Alert feed
-    </div>
-</body>
-</html>
-    "#)
+/// Build the application router
+fn app(state: Arc<AppState>) -> Router {
+    // CORS configuration
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any)
+        // Browser pollers read the alert feed's poll hint, retries the replay marker
+        .expose_headers([
+            HeaderName::from_static("x-poll-interval"),
+            idempotency::IDEMPOTENT_REPLAYED,
+        ]);
+
+    let api = routes::api_routes();
+    let console = Html(console::page(&api.endpoints));
+
+    Router::new()
+        // Query console listing the API routes
+        .route("/", get(move || async move { console }))
+        // Health check
+        .route("/health", get(health_check))
+        // API routes, versioned and the deprecated unversioned paths
+        .nest("/api/v1", api.router.clone())
+        .nest("/api/v2", api.router.clone())
+        .nest("/api", api.router)
+        // State and middleware
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            idempotency::middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            decimal::middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            version::middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            demo::middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            query_stats::middleware,
+        ))
+        .with_state(state)
+        .layer(cors)
+        .layer(tower_http::trace::TraceLayer::new_for_http())
 }
 
 /// Health check endpoint
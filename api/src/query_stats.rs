@@ -0,0 +1,302 @@
+//! Per-request database query accounting
+//!
+//! sqlx reports every statement it runs as a `sqlx::query` tracing event;
+//! [`layer`] adds them up for the request they ran in. A request running more
+//! than `REQUEST_QUERY_BUDGET` statements, or spending more than
+//! `REQUEST_DB_TIME_BUDGET_MS` in them, is logged with its SQL. Totals per
+//! endpoint are served at `/api/admin/query-metrics`.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use tracing::{field::Field, Event, Level, Subscriber};
+use tracing_subscriber::{
+    filter::Targets,
+    layer::{Context, Layer},
+};
+
+use crate::AppState;
+
+/// Target sqlx logs statements under
+const SQLX_TARGET: &str = "sqlx::query";
+
+tokio::task_local! {
+    /// Statements run so far by the request on this task
+    static STATEMENTS: Arc<Mutex<Vec<Statement>>>;
+}
+
+/// One statement a request ran
+#[derive(Debug, Clone, PartialEq)]
+struct Statement {
+    sql: String,
+    elapsed: Duration,
+}
+
+/// Reads a statement off a `sqlx::query` event. `db.statement` is left empty
+/// when the summary is the whole statement.
+#[derive(Default)]
+struct StatementVisitor {
+    summary: String,
+    statement: String,
+    elapsed_secs: f64,
+}
+
+impl tracing::field::Visit for StatementVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed_secs = value;
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "summary" => self.summary = value.to_string(),
+            "db.statement" => self.statement = value.trim().to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+impl StatementVisitor {
+    fn into_statement(self) -> Statement {
+        Statement {
+            sql: if self.statement.is_empty() {
+                self.summary
+            } else {
+                self.statement
+            },
+            elapsed: Duration::from_secs_f64(self.elapsed_secs.max(0.0)),
+        }
+    }
+}
+
+/// Records sqlx statements into the request running them
+struct StatementLayer;
+
+impl<S: Subscriber> Layer<S> for StatementLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // Statements outside a request (background tasks) aren't counted
+        let _ = STATEMENTS.try_with(|statements| {
+            let mut visitor = StatementVisitor::default();
+            event.record(&mut visitor);
+            statements
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(visitor.into_statement());
+        });
+    }
+}
+
+/// Tracing layer counting statements per request. It asks for sqlx's
+/// statement events whatever `RUST_LOG` says; they are only printed when
+/// `RUST_LOG` enables `sqlx::query`.
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    StatementLayer.with_filter(Targets::new().with_target(SQLX_TARGET, Level::TRACE))
+}
+
+/// Most statements and DB time a request may take before it is logged; zero
+/// turns a limit off
+#[derive(Debug, Clone, Copy)]
+pub struct QueryBudget {
+    pub max_queries: usize,
+    pub max_db_time: Duration,
+}
+
+impl QueryBudget {
+    fn exceeded_by(&self, queries: usize, db_time: Duration) -> bool {
+        (self.max_queries > 0 && queries > self.max_queries)
+            || (!self.max_db_time.is_zero() && db_time > self.max_db_time)
+    }
+}
+
+/// Statement totals of one endpoint since the API started
+#[derive(Debug, Clone, Default, PartialEq)]
+struct EndpointTotals {
+    requests: u64,
+    queries: u64,
+    db_time: Duration,
+    max_queries: u64,
+    max_db_time: Duration,
+    over_budget: u64,
+}
+
+/// Statement totals per endpoint, with the budget requests are held to
+#[derive(Debug, Clone)]
+pub struct QueryMetrics {
+    budget: QueryBudget,
+    endpoints: Arc<Mutex<HashMap<String, EndpointTotals>>>,
+}
+
+/// One endpoint's statement totals
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointQueryMetrics {
+    /// Method and route, e.g. `GET /api/tokens/:address`
+    pub endpoint: String,
+    pub requests: u64,
+    pub queries: u64,
+    pub avg_queries: f64,
+    pub db_time_ms: f64,
+    pub avg_db_time_ms: f64,
+    pub max_queries: u64,
+    pub max_db_time_ms: f64,
+    /// Requests over the query or DB time budget
+    pub over_budget: u64,
+}
+
+fn millis(d: Duration) -> f64 {
+    (d.as_secs_f64() * 100_000.0).round() / 100.0
+}
+
+impl QueryMetrics {
+    pub fn new(budget: QueryBudget) -> Self {
+        Self {
+            budget,
+            endpoints: Arc::default(),
+        }
+    }
+
+    /// Add a request's statements to its endpoint, `true` if it went over
+    /// the budget
+    fn record(&self, endpoint: &str, statements: &[Statement]) -> bool {
+        let queries = statements.len();
+        let db_time: Duration = statements.iter().map(|s| s.elapsed).sum();
+        let over = self.budget.exceeded_by(queries, db_time);
+
+        let mut endpoints = self.endpoints.lock().unwrap_or_else(|e| e.into_inner());
+        let totals = endpoints.entry(endpoint.to_string()).or_default();
+        totals.requests += 1;
+        totals.queries += queries as u64;
+        totals.db_time += db_time;
+        totals.max_queries = totals.max_queries.max(queries as u64);
+        totals.max_db_time = totals.max_db_time.max(db_time);
+        totals.over_budget += over as u64;
+        over
+    }
+
+    /// Every endpoint's totals, most DB time first
+    pub fn snapshot(&self) -> Vec<EndpointQueryMetrics> {
+        let endpoints = self.endpoints.lock().unwrap_or_else(|e| e.into_inner());
+        let mut metrics: Vec<EndpointQueryMetrics> = endpoints
+            .iter()
+            .map(|(endpoint, t)| EndpointQueryMetrics {
+                endpoint: endpoint.clone(),
+                requests: t.requests,
+                queries: t.queries,
+                avg_queries: (t.queries as f64 / t.requests as f64 * 100.0).round() / 100.0,
+                db_time_ms: millis(t.db_time),
+                avg_db_time_ms: millis(t.db_time / t.requests as u32),
+                max_queries: t.max_queries,
+                max_db_time_ms: millis(t.max_db_time),
+                over_budget: t.over_budget,
+            })
+            .collect();
+        metrics.sort_by(|a, b| {
+            b.db_time_ms
+                .total_cmp(&a.db_time_ms)
+                .then_with(|| a.endpoint.cmp(&b.endpoint))
+        });
+        metrics
+    }
+}
+
+/// Count the statements each routed request runs, logging those over budget
+pub async fn middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = request.extensions().get::<MatchedPath>() else {
+        return next.run(request).await;
+    };
+    let endpoint = format!("{} {}", request.method(), route.as_str());
+    let uri = request.uri().clone();
+
+    let statements = Arc::new(Mutex::new(Vec::new()));
+    let response = STATEMENTS
+        .scope(statements.clone(), next.run(request))
+        .await;
+
+    let statements = std::mem::take(&mut *statements.lock().unwrap_or_else(|e| e.into_inner()));
+    if state.query_metrics.record(&endpoint, &statements) {
+        let db_time: Duration = statements.iter().map(|s| s.elapsed).sum();
+        let sql: Vec<String> = statements
+            .iter()
+            .map(|s| format!("  {:>9.2} ms  {}", millis(s.elapsed), s.sql))
+            .collect();
+        tracing::warn!(
+            "{} ({}) ran {} queries in {:.2} ms, over the budget of {} queries / {} ms:\n{}",
+            uri,
+            endpoint,
+            statements.len(),
+            millis(db_time),
+            state.query_metrics.budget.max_queries,
+            state.query_metrics.budget.max_db_time.as_millis(),
+            sql.join("\n")
+        );
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(ms: u64) -> Statement {
+        Statement {
+            sql: "SELECT 1".to_string(),
+            elapsed: Duration::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn totals_are_kept_per_endpoint_against_the_budget() {
+        let metrics = QueryMetrics::new(QueryBudget {
+            max_queries: 2,
+            max_db_time: Duration::from_millis(100),
+        });
+
+        assert!(!metrics.record("GET /tokens", &[statement(10), statement(20)]));
+        assert!(metrics.record("GET /tokens", &vec![statement(1); 3]));
+        assert!(metrics.record("GET /pairs", &[statement(150)]));
+        assert!(!metrics.record("GET /health", &[]));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(
+            snapshot
+                .iter()
+                .map(|m| m.endpoint.as_str())
+                .collect::<Vec<_>>(),
+            vec!["GET /pairs", "GET /tokens", "GET /health"]
+        );
+        let tokens = &snapshot[1];
+        assert_eq!((tokens.requests, tokens.queries), (2, 5));
+        assert_eq!(tokens.avg_queries, 2.5);
+        assert_eq!(tokens.db_time_ms, 33.0);
+        assert_eq!(tokens.avg_db_time_ms, 16.5);
+        assert_eq!((tokens.max_queries, tokens.max_db_time_ms), (3, 30.0));
+        assert_eq!(tokens.over_budget, 1);
+
+        let unlimited = QueryBudget {
+            max_queries: 0,
+            max_db_time: Duration::ZERO,
+        };
+        assert!(!unlimited.exceeded_by(1_000, Duration::from_secs(60)));
+    }
+}
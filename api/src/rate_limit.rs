@@ -0,0 +1,109 @@
+//! Per-client rate limiting
+//!
+//! Each client IP gets `RATE_LIMIT_PER_MINUTE` requests per fixed one-minute
+//! window (`DEMO_RATE_LIMIT_PER_MINUTE` in demo mode); past that requests get
+//! `429 RATE_LIMITED` until the window rolls over. Outside demo mode there is
+//! no limit unless `RATE_LIMIT_PER_MINUTE` is set. Behind a reverse proxy all
+//! clients share the proxy's address, so set the limit with that in mind.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{error::ApiError, AppState};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Clients tracked before expired windows are swept
+const SWEEP_AT: usize = 10_000;
+
+/// Window start and request count per client (`None` when the peer address
+/// is unknown)
+type Windows = HashMap<Option<IpAddr>, (Instant, u32)>;
+
+/// Fixed-window request counter per client
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Requests per window; 0 disables limiting
+    per_minute: u32,
+    windows: Arc<Mutex<Windows>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            windows: Arc::default(),
+        }
+    }
+
+    /// Count a request from `client`, `false` if it is over the limit
+    fn allow(&self, client: Option<IpAddr>, now: Instant) -> bool {
+        if self.per_minute == 0 {
+            return true;
+        }
+
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= SWEEP_AT {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+        }
+
+        let (start, count) = windows.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.per_minute
+    }
+}
+
+/// Reject requests from clients over their limit
+pub async fn middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if !state.rate_limiter.allow(client, Instant::now()) {
+        return ApiError::RateLimited.into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_each_client_per_window() {
+        let limiter = RateLimiter::new(2);
+        let now = Instant::now();
+        let a = Some(IpAddr::from([10, 0, 0, 1]));
+        let b = Some(IpAddr::from([10, 0, 0, 2]));
+
+        assert!(limiter.allow(a, now));
+        assert!(limiter.allow(a, now));
+        assert!(!limiter.allow(a, now));
+        assert!(limiter.allow(b, now));
+        // A new window resets the count
+        assert!(limiter.allow(a, now + WINDOW));
+
+        let unlimited = RateLimiter::new(0);
+        assert!((0..100).all(|_| unlimited.allow(a, now)));
+    }
+}
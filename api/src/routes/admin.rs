@@ -0,0 +1,495 @@
+//! Operator API routes
+//!
+//! Runtime controls for the indexer services. All of them require an
+//! [`IngestKey`].
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use indexer_db::{
+    entity::{
+        holder_verification::HolderVerification,
+        listener_filter::{ListenerFilter, ListenerFilterUpdate},
+        processing_error::ProcessingError,
+        processing_lag::ProcessingLag,
+        processor_event_metric::{ProcessorEventMetric, LATENCY_BUCKETS_MS},
+        token::Token,
+        token_rescan::{NewTokenRescan, TokenRescan},
+    },
+    Address20, Hash32,
+};
+
+use crate::{
+    address::EvmAddress,
+    auth::IngestKey,
+    envelope::{Listing, ResponseShape},
+    error::{ApiError, ApiJson, ApiQuery, ApiResult},
+    query_stats::EndpointQueryMetrics,
+    AppState,
+};
+
+/// Listener filter definition and state
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenerItem {
+    pub name: String,
+    pub address: Option<Address20>,
+    pub topic: Option<Hash32>,
+    pub enabled: bool,
+    pub priority: i32,
+    /// `None` means the chain's block time
+    pub poll_interval_secs: Option<i32>,
+    pub updated_at: String,
+}
+
+impl From<ListenerFilter> for ListenerItem {
+    fn from(f: ListenerFilter) -> Self {
+        Self {
+            name: f.name,
+            address: f.address,
+            topic: f.topic,
+            enabled: f.enabled,
+            priority: f.priority,
+            poll_interval_secs: f.poll_interval_secs,
+            updated_at: f.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Latest processing lag window of an event type against its SLO
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingLagItem {
+    pub event_type: String,
+    pub target_secs: i32,
+    pub samples: i32,
+    pub p50_lag_ms: i64,
+    pub p95_lag_ms: i64,
+    pub max_lag_ms: i64,
+    /// The p95 is over target
+    pub breached: bool,
+    pub breached_since: Option<String>,
+    pub updated_at: String,
+}
+
+impl From<ProcessingLag> for ProcessingLagItem {
+    fn from(l: ProcessingLag) -> Self {
+        Self {
+            event_type: l.event_type,
+            target_secs: l.target_secs,
+            samples: l.samples,
+            p50_lag_ms: l.p50_lag_ms,
+            p95_lag_ms: l.p95_lag_ms,
+            max_lag_ms: l.max_lag_ms,
+            breached: l.breached_since.is_some(),
+            breached_since: l.breached_since.map(|dt| dt.to_rfc3339()),
+            updated_at: l.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// One bucket of a latency histogram
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyBucket {
+    /// Upper bound in ms; `None` for the open-ended last bucket
+    pub le_ms: Option<i64>,
+    /// Handler runs at most this slow, so counts grow bucket to bucket
+    pub count: i64,
+}
+
+/// An event type's processing counters since the processor first recorded it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventMetricItem {
+    pub event_type: String,
+    pub events: i64,
+    pub decode_failures: i64,
+    pub handler_errors: i64,
+    /// Events of topics without a decoder; not failures
+    pub skipped: i64,
+    /// Percent of events that failed to decode
+    pub decode_failure_rate: f64,
+    /// Percent of events whose handler errored
+    pub handler_error_rate: f64,
+    pub latency_avg_ms: f64,
+    pub latency_buckets: Vec<LatencyBucket>,
+    /// The last window's failures were over the alert limit
+    pub failing: bool,
+    pub failing_since: Option<String>,
+    pub updated_at: String,
+}
+
+impl From<ProcessorEventMetric> for EventMetricItem {
+    fn from(m: ProcessorEventMetric) -> Self {
+        let percent = |count: i64| count as f64 * 100.0 / m.events.max(1) as f64;
+        let handled: i64 = m.latency_buckets.iter().sum();
+        let bounds = LATENCY_BUCKETS_MS.iter().map(|&b| Some(b)).chain([None]);
+        let latency_buckets = bounds
+            .zip(&m.latency_buckets)
+            .scan(0, |total, (le_ms, count)| {
+                *total += count;
+                Some(LatencyBucket {
+                    le_ms,
+                    count: *total,
+                })
+            })
+            .collect();
+
+        Self {
+            decode_failure_rate: percent(m.decode_failures),
+            handler_error_rate: percent(m.handler_errors),
+            latency_avg_ms: m.latency_sum_ms as f64 / handled.max(1) as f64,
+            latency_buckets,
+            failing: m.failing_since.is_some(),
+            failing_since: m.failing_since.map(|dt| dt.to_rfc3339()),
+            updated_at: m.updated_at.to_rfc3339(),
+            event_type: m.event_type,
+            events: m.events,
+            decode_failures: m.decode_failures,
+            handler_errors: m.handler_errors,
+            skipped: m.skipped,
+        }
+    }
+}
+
+/// A log the processor failed to decode or handle
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingErrorItem {
+    pub id: i64,
+    pub log_id: String,
+    pub topic: String,
+    pub tx_hash: Hash32,
+    pub log_index: i64,
+    /// `decode` or `handler`
+    pub stage: String,
+    pub error: String,
+    pub created_at: String,
+}
+
+impl From<ProcessingError> for ProcessingErrorItem {
+    fn from(e: ProcessingError) -> Self {
+        Self {
+            id: e.id,
+            log_id: e.log_id,
+            topic: e.topic,
+            tx_hash: e.tx_hash,
+            log_index: e.log_index,
+            stage: e.stage,
+            error: e.error,
+            created_at: e.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Query params for the processing error log
+#[derive(Debug, Deserialize)]
+pub struct ErrorParams {
+    pub limit: Option<i32>,
+    /// RFC 3339 timestamp; only errors recorded after it
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A queued or finished single-token rescan
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RescanItem {
+    pub id: i32,
+    pub token_address: Address20,
+    /// `None` means the token's creation block
+    pub from_block: Option<i64>,
+    /// `None` means the processor's last processed block when the rescan
+    /// starts, which also caps a later block
+    pub to_block: Option<i64>,
+    /// `pending`, `running`, `done` or `failed`
+    pub status: String,
+    pub logs_replayed: i32,
+    pub error: Option<String>,
+    pub requested_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+impl From<TokenRescan> for RescanItem {
+    fn from(r: TokenRescan) -> Self {
+        Self {
+            id: r.id,
+            token_address: r.token_address,
+            from_block: r.from_block,
+            to_block: r.to_block,
+            status: r.status,
+            logs_replayed: r.logs_replayed,
+            error: r.error,
+            requested_at: r.requested_at.to_rfc3339(),
+            started_at: r.started_at.map(|dt| dt.to_rfc3339()),
+            finished_at: r.finished_at.map(|dt| dt.to_rfc3339()),
+        }
+    }
+}
+
+/// A queued or finished holder verification
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationItem {
+    pub id: i32,
+    pub token_address: Address20,
+    /// `pending`, `running`, `done` or `failed`
+    pub status: String,
+    pub holders_checked: i32,
+    pub balances_corrected: i32,
+    pub holders_pruned: i32,
+    pub block_number: Option<i64>,
+    /// Token metrics recomputed once the verification is done
+    pub holder_count: Option<i32>,
+    pub top10_holder_percent: Option<f64>,
+    pub error: Option<String>,
+    pub requested_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+impl From<HolderVerification> for VerificationItem {
+    fn from(v: HolderVerification) -> Self {
+        Self {
+            id: v.id,
+            token_address: v.token_address,
+            status: v.status,
+            holders_checked: v.holders_checked,
+            balances_corrected: v.balances_corrected,
+            holders_pruned: v.holders_pruned,
+            block_number: v.block_number,
+            holder_count: v.holder_count,
+            top10_holder_percent: v
+                .top_10_holder_percent
+                .and_then(|p| p.to_string().parse().ok()),
+            error: v.error,
+            requested_at: v.requested_at.to_rfc3339(),
+            started_at: v.started_at.map(|dt| dt.to_rfc3339()),
+            finished_at: v.finished_at.map(|dt| dt.to_rfc3339()),
+        }
+    }
+}
+
+/// Request body for queueing a token rescan
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RescanRequest {
+    /// Defaults to the token's creation block
+    pub from_block: Option<u64>,
+    /// Defaults to the chain head
+    pub to_block: Option<u64>,
+}
+
+/// Request body for changing a listener's controls
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateListenerRequest {
+    pub enabled: Option<bool>,
+    /// Seconds between polls; 0 resets to the chain's block time
+    pub poll_interval_secs: Option<u32>,
+}
+
+/// GET /api/admin/listeners
+/// List listener filters, highest priority first
+pub async fn get_listeners(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+) -> ApiResult<Listing<ListenerItem>> {
+    let filters = ListenerFilter::find_all(&state.db_pool).await?;
+    shape
+        .list(
+            filters.into_iter().map(Into::into).collect(),
+            &state.db_pool,
+        )
+        .await
+}
+
+/// PATCH /api/admin/listeners/:name
+/// Enable/disable a listener filter or change its poll interval
+pub async fn update_listener(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    ApiJson(body): ApiJson<UpdateListenerRequest>,
+) -> ApiResult<Json<ListenerItem>> {
+    if body.enabled.is_none() && body.poll_interval_secs.is_none() {
+        return Err(ApiError::InvalidBody(
+            "At least one of `enabled` or `pollIntervalSecs` is required".to_string(),
+        ));
+    }
+    let poll_interval_secs = body
+        .poll_interval_secs
+        .map(|secs| {
+            i32::try_from(secs)
+                .map_err(|_| ApiError::InvalidBody("`pollIntervalSecs` is too large".to_string()))
+        })
+        .transpose()?;
+
+    let update = ListenerFilterUpdate {
+        enabled: body.enabled,
+        poll_interval_secs,
+    };
+
+    match ListenerFilter::update(&name, &update, &state.db_pool).await? {
+        Some(filter) => Ok(Json(filter.into())),
+        None => Err(ApiError::ListenerNotFound(name)),
+    }
+}
+
+/// GET /api/admin/lag
+/// Block-to-processed lag per event type, as last measured by the processor
+pub async fn get_processing_lag(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+) -> ApiResult<Listing<ProcessingLagItem>> {
+    let lags = ProcessingLag::find_all(&state.db_pool).await?;
+    shape
+        .list(lags.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await
+}
+
+/// GET /api/admin/event-metrics
+/// Processed, undecodable and failed logs and handler latency per event type
+pub async fn get_event_metrics(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+) -> ApiResult<Listing<EventMetricItem>> {
+    let metrics = ProcessorEventMetric::find_all(&state.db_pool).await?;
+    shape
+        .list(metrics.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await
+}
+
+/// GET /api/admin/query-metrics
+/// Statements and DB time per API endpoint since the API started, most DB
+/// time first
+pub async fn get_query_metrics(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+) -> ApiResult<Listing<EndpointQueryMetrics>> {
+    shape
+        .list(state.query_metrics.snapshot(), &state.db_pool)
+        .await
+}
+
+/// GET /api/admin/errors
+/// Logs the processor failed to decode or handle: the latest, newest first,
+/// or with `since` the ones after it, oldest first
+pub async fn get_processing_errors(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<ErrorParams>,
+) -> ApiResult<Listing<ProcessingErrorItem>> {
+    let limit = params.limit.unwrap_or(100).clamp(1, 500);
+
+    let errors = ProcessingError::find_since(params.since, limit, &state.db_pool).await?;
+    shape
+        .list(errors.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await
+}
+
+/// POST /api/admin/tokens/:address/rescan
+/// Queue a rescan that replays one token's logs and rebuilds its data
+pub async fn rescan_token(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+    ApiJson(body): ApiJson<RescanRequest>,
+) -> ApiResult<(StatusCode, Json<RescanItem>)> {
+    let block = |name: &str, value: Option<u64>| {
+        value
+            .map(|b| {
+                i64::try_from(b)
+                    .map_err(|_| ApiError::InvalidBody(format!("`{}` is too large", name)))
+            })
+            .transpose()
+    };
+    let from_block = block("fromBlock", body.from_block)?;
+    let to_block = block("toBlock", body.to_block)?;
+    if let (Some(from), Some(to)) = (from_block, to_block) {
+        if from > to {
+            return Err(ApiError::InvalidBody(
+                "`fromBlock` must not be after `toBlock`".to_string(),
+            ));
+        }
+    }
+
+    if Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::TokenNotFound(address.to_string()));
+    }
+
+    let rescan = NewTokenRescan {
+        token_address: *address,
+        from_block,
+        to_block,
+    };
+    let queued = TokenRescan::create(&rescan, &state.db_pool).await?;
+    Ok((StatusCode::ACCEPTED, Json(queued.into())))
+}
+
+/// GET /api/admin/rescans/:id
+/// Progress of a queued rescan
+pub async fn get_rescan(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<RescanItem>> {
+    let rescan_id = id
+        .parse::<i32>()
+        .map_err(|_| ApiError::RescanNotFound(id.clone()))?;
+
+    match TokenRescan::find_by_id(rescan_id, &state.db_pool).await? {
+        Some(rescan) => Ok(Json(rescan.into())),
+        None => Err(ApiError::RescanNotFound(id)),
+    }
+}
+
+/// POST /api/admin/tokens/:address/verify-holders
+/// Queue a balanceOf check of every stored holder of one token
+pub async fn verify_holders(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+) -> ApiResult<(StatusCode, Json<VerificationItem>)> {
+    if Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::TokenNotFound(address.to_string()));
+    }
+
+    let queued = HolderVerification::create(&address, &state.db_pool).await?;
+    Ok((StatusCode::ACCEPTED, Json(queued.into())))
+}
+
+/// GET /api/admin/holder-verifications/:id
+/// Progress of a queued holder verification
+pub async fn get_holder_verification(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<VerificationItem>> {
+    let verification_id = id
+        .parse::<i32>()
+        .map_err(|_| ApiError::VerificationNotFound(id.clone()))?;
+
+    match HolderVerification::find_by_id(verification_id, &state.db_pool).await? {
+        Some(verification) => Ok(Json(verification.into())),
+        None => Err(ApiError::VerificationNotFound(id)),
+    }
+}
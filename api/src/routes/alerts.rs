@@ -1,18 +1,50 @@
 //! Alert API routes
 
-use std::sync::Arc;
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    time::Duration,
+};
 
 use axum::{
-    extract::{Query, State},
+    extract::{Path, State},
     http::StatusCode,
-    response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
+use tokio::time::{sleep_until, timeout_at, Instant};
+use url::{Host, Url};
+
+use indexer_db::{
+    entity::{
+        alert::{AlertEvent, AlertType},
+        alert_metadata::AlertMetadata,
+        alert_preference::{AlertLevel, AlertPreference},
+        alert_webhook::{AlertWebhook, NewAlertWebhook},
+        COMMIT_LAG,
+    },
+    net::{is_public_domain, is_public_ip},
+    Address20,
+};
+
+use crate::{
+    address::EvmAddress,
+    auth::{ClientKey, IngestKey},
+    decimal::Decimal,
+    demo::WalletAddress,
+    envelope::{Listing, ResponseShape},
+    error::{ApiError, ApiJson, ApiQuery, ApiResult},
+    AppState,
+};
 
-use indexer_db::entity::alert::AlertEvent;
+/// Shortest accepted webhook secret
+const MIN_WEBHOOK_SECRET_LEN: usize = 16;
 
-use crate::AppState;
+/// Longest accepted webhook URL
+const MAX_WEBHOOK_URL_LEN: usize = 2048;
+
+/// Longest a feed request may wait for a new alert, in seconds
+const MAX_FEED_WAIT_SECS: u64 = 60;
 
 /// Helper to convert BigDecimal to f64
 fn bd_to_f64(bd: &sqlx::types::BigDecimal) -> f64 {
@@ -27,7 +59,11 @@ fn map_alert_type(alert_type: &str) -> &str {
         "price_pump" | "price_dump" => "token_signal",
         "lp_locked" | "lp_unlocking" => "token_signal",
         "high_bee_score" => "token_signal",
+        "trending_enter" | "trending_exit" => "token_signal",
         "dev_sell" => "wallet_activity",
+        "cex_inflow" | "cex_outflow" => "wallet_activity",
+        "holder_blacklisted" => "token_signal",
+        "risky_approval" => "wallet_activity",
         "filter_match" => "filter_match",
         _ => "token_signal",
     }
@@ -42,30 +78,47 @@ pub struct AlertItem {
     pub alert_type: String,
     pub title: String,
     pub message: String,
-    pub token_address: Option<String>,
-    pub wallet_address: Option<String>,
+    pub token_address: Option<Address20>,
+    pub wallet_address: Option<WalletAddress>,
     pub timestamp: String,
     pub is_read: bool,
     // Additional fields for enrichment
     pub bee_score: Option<i16>,
-    pub amount_usd: Option<f64>,
+    pub amount_usd: Option<Decimal>,
     pub change_percent: Option<f64>,
+    /// Number of alerts a rollup stands for; `None` for a single alert
+    pub alert_count: Option<i64>,
+    /// Payload of the alert's type (see "Alert metadata" in the README), or
+    /// `{"rollup": ...}` on a rollup; left out when it doesn't match its type
+    pub metadata: Option<AlertMetadata>,
 }
 
 impl From<AlertEvent> for AlertItem {
     fn from(a: AlertEvent) -> Self {
+        let metadata = match a.typed_metadata() {
+            Some(Ok(metadata)) => Some(metadata),
+            Some(Err(e)) => {
+                tracing::warn!("Alert #{} has invalid metadata: {}", a.id, e);
+                None
+            }
+            None => None,
+        };
+        let alert_count = metadata.as_ref().and_then(|m| m.rollup()).map(|r| r.count);
+
         Self {
             id: a.id.to_string(),
             alert_type: map_alert_type(&a.alert_type).to_string(),
             title: a.title,
             message: a.message.unwrap_or_default(),
             token_address: a.token_address,
-            wallet_address: a.wallet_address,
+            wallet_address: a.wallet_address.map(WalletAddress),
             timestamp: a.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_default(),
             is_read: false, // Default to unread - frontend manages read state locally
             bee_score: a.bee_score,
-            amount_usd: a.amount_usd.as_ref().map(bd_to_f64),
+            amount_usd: a.amount_usd.map(Decimal),
             change_percent: a.change_percent.as_ref().map(bd_to_f64),
+            alert_count,
+            metadata,
         }
     }
 }
@@ -75,30 +128,295 @@ impl From<AlertEvent> for AlertItem {
 pub struct FeedParams {
     pub limit: Option<i32>,
     pub alert_type: Option<String>,
+    /// Only alerts newer than this id: the largest id of the previous poll
+    pub after_id: Option<i32>,
+    /// Seconds to hold the request while there is nothing to return
+    pub wait: Option<u64>,
 }
 
+/// Header telling pollers how many seconds to wait before the next poll
+const POLL_INTERVAL_HEADER: &str = "x-poll-interval";
+
 /// GET /api/alerts/feed
-/// Returns recent alerts for the live feed
+/// Returns recent alerts for the live feed, without the ones the caller's
+/// alert preferences silence
+///
+/// Pollers pass the largest id they have seen as `after_id` to get only what
+/// was raised since, oldest page first, and wait `X-Poll-Interval` seconds
+/// before asking again (0 while more alerts are waiting). Alerts show on
+/// those pages a couple of seconds after they are raised, once no slower
+/// commit can still land below them.
+///
+/// With `wait` (seconds, at most 60) an empty answer is held back until an
+/// alert the caller would see is raised or the wait runs out; long pollers
+/// are told to ask again right away.
 pub async fn get_alert_feed(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<FeedParams>,
-) -> impl IntoResponse {
-    let limit = params.limit.unwrap_or(50).min(200);
+    key: Option<ClientKey>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<FeedParams>,
+) -> ApiResult<([(&'static str, String); 1], Listing<AlertItem>)> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let wait = Duration::from_secs(params.wait.unwrap_or(0).min(MAX_FEED_WAIT_SECS));
+    let deadline = Instant::now() + wait;
 
-    let result = if let Some(alert_type) = params.alert_type {
-        AlertEvent::find_by_type(&alert_type, limit, &state.db_pool).await
+    let find = || {
+        AlertEvent::find_feed(
+            params.alert_type.as_deref(),
+            key.as_ref().map(|k| k.0.as_str()),
+            params.after_id,
+            limit,
+            &state.db_pool,
+        )
+    };
+    // Subscribed before the first read, so an alert raised in between still
+    // wakes the request
+    let mut raised = state.alert_notifier.subscribe();
+    let mut alerts = find().await?;
+    // Forward pages hold alerts back until they are COMMIT_LAG old, so those
+    // raised just before the first read are picked up once that has passed
+    let settled = Instant::now() + COMMIT_LAG;
+    // Alerts of other types or silenced tokens wake the request too; it
+    // keeps waiting until one it returns comes in
+    while alerts.is_empty() && !wait.is_zero() {
+        let until = if Instant::now() < settled {
+            settled.min(deadline)
+        } else {
+            deadline
+        };
+        match timeout_at(until, raised.changed()).await {
+            // The new alert shows once it is old enough to have no slower
+            // commits left below it
+            Ok(Ok(())) => sleep_until((Instant::now() + COMMIT_LAG).min(deadline)).await,
+            Err(_) if until < deadline => {}
+            Ok(Err(_)) | Err(_) => break,
+        }
+        alerts = find().await?;
+    }
+
+    let backlog = params.after_id.is_some() && alerts.len() == limit as usize;
+    let poll_interval = if backlog || !wait.is_zero() {
+        0
     } else {
-        AlertEvent::find_recent(limit, &state.db_pool).await
+        state.alert_poll_interval_secs
     };
+    let cursor = alerts.iter().map(|a| a.id).max().or(params.after_id);
+
+    let listing = shape
+        .list(alerts.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await?
+        .with_cursor(cursor.map(|id| id.to_string()));
+    Ok(([(POLL_INTERVAL_HEADER, poll_interval.to_string())], listing))
+}
+
+/// A token's alert level for the calling key
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferenceItem {
+    pub token_address: Address20,
+    /// `mute`, `critical` or `all`
+    pub level: String,
+    pub updated_at: Option<String>,
+}
 
-    match result {
-        Ok(alerts) => {
-            let items: Vec<AlertItem> = alerts.into_iter().map(Into::into).collect();
-            Json(items).into_response()
+impl From<AlertPreference> for PreferenceItem {
+    fn from(p: AlertPreference) -> Self {
+        Self {
+            token_address: p.token_address,
+            level: p.level,
+            updated_at: Some(p.updated_at.to_rfc3339()),
         }
-        Err(e) => {
-            tracing::error!("Failed to get alert feed: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+    }
+}
+
+/// Request body for setting a token's alert level
+#[derive(Debug, Deserialize)]
+pub struct SetPreferenceRequest {
+    pub level: String,
+}
+
+/// GET /api/alerts/preferences
+/// Tokens the calling key has muted or limited to critical alerts
+pub async fn get_preferences(
+    key: ClientKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+) -> ApiResult<Listing<PreferenceItem>> {
+    let preferences = AlertPreference::find_by_key(&key.0, &state.db_pool).await?;
+    shape
+        .list(
+            preferences.into_iter().map(Into::into).collect(),
+            &state.db_pool,
+        )
+        .await
+}
+
+/// PUT /api/alerts/preferences/:address
+/// Mute a token, limit it to critical alerts, or (`all`) reset it
+pub async fn set_preference(
+    key: ClientKey,
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+    ApiJson(body): ApiJson<SetPreferenceRequest>,
+) -> ApiResult<Json<PreferenceItem>> {
+    let token_address = *address;
+    let level = AlertLevel::parse(&body.level).ok_or_else(|| {
+        ApiError::InvalidBody(format!(
+            "`level` must be `mute`, `critical` or `all`, got `{}`",
+            body.level
+        ))
+    })?;
+
+    AlertPreference::set(&key.0, &token_address, level, &state.db_pool).await?;
+    let stored = AlertPreference::find_by_key(&key.0, &state.db_pool)
+        .await?
+        .into_iter()
+        .find(|p| p.token_address == token_address);
+
+    Ok(Json(match stored {
+        Some(preference) => preference.into(),
+        None => PreferenceItem {
+            token_address,
+            level: AlertLevel::All.as_str().to_string(),
+            updated_at: None,
+        },
+    }))
+}
+
+/// Request body for registering a webhook
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Key for the HMAC-SHA256 signature on each delivery
+    pub secret: String,
+    /// Backend alert types to deliver (e.g. `whale_buy`); empty or omitted means all
+    #[serde(default)]
+    pub alert_types: Vec<String>,
+}
+
+/// Registered webhook; the secret is never returned
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookItem {
+    pub id: i32,
+    pub url: String,
+    pub alert_types: Vec<String>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+impl From<AlertWebhook> for WebhookItem {
+    fn from(w: AlertWebhook) -> Self {
+        Self {
+            id: w.id,
+            url: w.url,
+            alert_types: w.alert_types,
+            active: w.active,
+            created_at: w.created_at.to_rfc3339(),
         }
     }
 }
+
+/// Whether `url` names a host the processor may deliver to. Loopback,
+/// private, link-local and other non-routable addresses are refused, as are
+/// single-label and internal-only host names.
+fn is_public_url(url: &str) -> bool {
+    let Ok(parsed) = Url::parse(url) else {
+        return false;
+    };
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return false;
+    }
+    match parsed.host() {
+        Some(Host::Domain(domain)) => is_public_domain(domain),
+        Some(Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+/// GET /api/alerts/webhooks
+/// List registered webhooks
+pub async fn get_webhooks(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+) -> ApiResult<Listing<WebhookItem>> {
+    let webhooks = AlertWebhook::find_all(&state.db_pool).await?;
+    shape
+        .list(
+            webhooks.into_iter().map(Into::into).collect(),
+            &state.db_pool,
+        )
+        .await
+}
+
+/// POST /api/alerts/webhooks
+/// Register a webhook for alerts created from now on, filtered by the
+/// registering key's alert preferences
+pub async fn create_webhook(
+    _key: IngestKey,
+    client: ClientKey,
+    State(state): State<Arc<AppState>>,
+    ApiJson(body): ApiJson<CreateWebhookRequest>,
+) -> ApiResult<(StatusCode, Json<WebhookItem>)> {
+    let url = body.url.trim();
+    if !(url.starts_with("https://") || url.starts_with("http://"))
+        || url.len() > MAX_WEBHOOK_URL_LEN
+    {
+        return Err(ApiError::InvalidBody(
+            "`url` must be an http(s) URL of at most 2048 characters".to_string(),
+        ));
+    }
+    if !is_public_url(url) {
+        return Err(ApiError::InvalidBody(
+            "`url` must point at a public host".to_string(),
+        ));
+    }
+    if body.secret.len() < MIN_WEBHOOK_SECRET_LEN {
+        return Err(ApiError::InvalidBody(format!(
+            "`secret` must be at least {} characters",
+            MIN_WEBHOOK_SECRET_LEN
+        )));
+    }
+    if let Some(unknown) = body
+        .alert_types
+        .iter()
+        .find(|t| !AlertType::ALL.iter().any(|known| known.as_str() == t.as_str()))
+    {
+        return Err(ApiError::InvalidBody(format!("Unknown alert type `{}`", unknown)));
+    }
+
+    let mut alert_types = body.alert_types;
+    alert_types.sort();
+    alert_types.dedup();
+
+    let webhook = NewAlertWebhook {
+        url: url.to_string(),
+        secret: body.secret,
+        alert_types,
+        api_key: Some(client.0),
+    };
+
+    let created = AlertWebhook::create(&webhook, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(created.into())))
+}
+
+/// DELETE /api/alerts/webhooks/:id
+/// Remove a webhook and its pending deliveries
+pub async fn delete_webhook(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    let webhook_id = id
+        .parse::<i32>()
+        .map_err(|_| ApiError::WebhookNotFound(id.clone()))?;
+
+    if AlertWebhook::delete(webhook_id, &state.db_pool).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::WebhookNotFound(id))
+    }
+}
@@ -0,0 +1,107 @@
+//! Factory API routes
+
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use indexer_db::{
+    entity::{
+        chain_constant::{ChainConstant, ChainConstantKind},
+        pair::{FactoryDay, Pair, RUG_REMAINING_PERCENT},
+    },
+    Address20,
+};
+
+use crate::{
+    address::EvmAddress,
+    error::{ApiError, ApiQuery, ApiResult},
+    AppState,
+};
+
+/// Longest window the factory stats cover
+const MAX_FACTORY_STATS_DAYS: i64 = 90;
+
+/// Pairs a factory created on one day (UTC)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FactoryDayItem {
+    pub day: String,
+    pub pairs: i64,
+}
+
+impl From<FactoryDay> for FactoryDayItem {
+    fn from(d: FactoryDay) -> Self {
+        Self {
+            day: d.day.format("%Y-%m-%d").to_string(),
+            pairs: d.pairs,
+        }
+    }
+}
+
+/// Launch outcomes of the pairs a factory created, for comparing DEXes
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FactoryStatsResponse {
+    pub address: Address20,
+    /// Known DEX name, e.g. `PancakeSwap V2`
+    pub name: Option<String>,
+    pub since: String,
+    pub pairs: i64,
+    /// Every day of the window, empty ones included, oldest first
+    pub pairs_per_day: Vec<FactoryDayItem>,
+    /// Share (0-100) of pairs with an LP lock
+    pub lp_locked_percent: f64,
+    pub median_initial_liquidity: Option<f64>,
+    /// Share (0-100) of pairs with liquidity history whose liquidity fell
+    /// under `rugThresholdPercent` of its peak; `None` without any history
+    pub rug_rate_percent: Option<f64>,
+    pub rugged_pairs: i64,
+    pub rug_threshold_percent: f64,
+}
+
+/// Query params for factory stats
+#[derive(Debug, Deserialize)]
+pub struct FactoryStatsParams {
+    /// Days of pairs to cover, default 30
+    pub days: Option<i64>,
+}
+
+/// GET /api/factories/:address/stats
+/// Returns pairs created per day, LP lock share, median initial liquidity
+/// and rug rate of the pairs a factory created
+pub async fn get_factory_stats(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<FactoryStatsParams>,
+) -> ApiResult<Json<FactoryStatsResponse>> {
+    let days = params.days.unwrap_or(30);
+    if !(1..=MAX_FACTORY_STATS_DAYS).contains(&days) {
+        return Err(ApiError::InvalidQuery(format!(
+            "`days` must be between 1 and {}",
+            MAX_FACTORY_STATS_DAYS
+        )));
+    }
+    let since = Utc::now() - Duration::days(days);
+
+    let known =
+        ChainConstant::find_by_address(ChainConstantKind::Factory, &address, &state.db_pool)
+            .await?;
+    let stats = Pair::factory_stats(&address, since, &state.db_pool).await?;
+    let per_day = Pair::factory_pairs_per_day(&address, since, &state.db_pool).await?;
+
+    let percent = |count: i64, of: i64| (of > 0).then(|| count as f64 * 100.0 / of as f64);
+    Ok(Json(FactoryStatsResponse {
+        address: *address,
+        name: known.map(|c| c.name),
+        since: since.to_rfc3339(),
+        pairs: stats.pairs,
+        pairs_per_day: per_day.into_iter().map(Into::into).collect(),
+        lp_locked_percent: percent(stats.locked_pairs, stats.pairs).unwrap_or(0.0),
+        median_initial_liquidity: stats.median_initial_liquidity_usd,
+        rug_rate_percent: percent(stats.rugged_pairs, stats.pairs_with_liquidity),
+        rugged_pairs: stats.rugged_pairs,
+        rug_threshold_percent: RUG_REMAINING_PERCENT,
+    }))
+}
@@ -0,0 +1,239 @@
+//! Ingestion API routes
+//!
+//! Endpoints external collectors push data into. All of them require an
+//! [`IngestKey`].
+
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use indexer_db::{
+    entity::{
+        external_report::{ExternalReport, NewExternalReport, ReportSeverity},
+        job::{Job, JobKind, NewJob},
+        social_metric::{NewSocialMetric, SocialMetric},
+        token::Token,
+    },
+    Address20,
+};
+
+use crate::{
+    address::EvmAddress,
+    auth::IngestKey,
+    error::{ApiError, ApiJson, ApiResult},
+    AppState,
+};
+
+/// Request body for a social metrics reading
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SocialMetricsRequest {
+    pub token_address: EvmAddress,
+    pub source: String,
+    pub telegram_members: Option<u32>,
+    pub x_mentions24h: Option<u32>,
+    /// Defaults to now
+    pub recorded_at: Option<DateTime<Utc>>,
+}
+
+/// Stored social metrics reading
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SocialMetricsItem {
+    pub id: i32,
+    pub token_address: Address20,
+    pub source: String,
+    pub telegram_members: Option<i32>,
+    pub x_mentions24h: Option<i32>,
+    pub recorded_at: String,
+}
+
+impl From<SocialMetric> for SocialMetricsItem {
+    fn from(m: SocialMetric) -> Self {
+        Self {
+            id: m.id,
+            token_address: m.token_address,
+            source: m.source,
+            telegram_members: m.telegram_members,
+            x_mentions24h: m.x_mentions_24h,
+            recorded_at: m.recorded_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Longest accepted report URL
+const MAX_REPORT_URL_LEN: usize = 2048;
+
+/// Request body for an external risk report
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalReportRequest {
+    pub token_address: EvmAddress,
+    pub source: String,
+    /// `info`, `low`, `medium`, `high` or `critical`
+    pub severity: String,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    /// Verified by the source; only confirmed reports lower the safety score
+    #[serde(default)]
+    pub confirmed: bool,
+    /// Defaults to now
+    pub reported_at: Option<DateTime<Utc>>,
+}
+
+/// Stored external risk report
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalReportItem {
+    pub id: i32,
+    pub token_address: Address20,
+    pub source: String,
+    pub severity: String,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub confirmed: bool,
+    pub reported_at: String,
+    pub updated_at: String,
+}
+
+impl From<ExternalReport> for ExternalReportItem {
+    fn from(r: ExternalReport) -> Self {
+        Self {
+            id: r.id,
+            token_address: r.token_address,
+            source: r.source,
+            severity: r.severity,
+            category: r.category,
+            description: r.description,
+            url: r.url,
+            confirmed: r.confirmed,
+            reported_at: r.reported_at.to_rfc3339(),
+            updated_at: r.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+fn count_to_i32(field: &str, value: Option<u32>) -> ApiResult<Option<i32>> {
+    value
+        .map(|v| {
+            i32::try_from(v).map_err(|_| ApiError::InvalidBody(format!("`{}` is too large", field)))
+        })
+        .transpose()
+}
+
+/// POST /api/ingest/social
+/// Record a social metrics reading for a tracked token
+pub async fn ingest_social(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    ApiJson(body): ApiJson<SocialMetricsRequest>,
+) -> ApiResult<(StatusCode, Json<SocialMetricsItem>)> {
+    let source = body.source.trim();
+    if source.is_empty() || source.len() > 50 {
+        return Err(ApiError::InvalidBody(
+            "`source` must be 1-50 characters".to_string(),
+        ));
+    }
+    if body.telegram_members.is_none() && body.x_mentions24h.is_none() {
+        return Err(ApiError::InvalidBody(
+            "At least one of `telegramMembers` or `xMentions24h` is required".to_string(),
+        ));
+    }
+
+    if Token::find_by_address(&body.token_address, &state.db_pool)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::TokenNotFound(body.token_address.to_string()));
+    }
+
+    let metric = NewSocialMetric {
+        token_address: *body.token_address,
+        source: source.to_string(),
+        recorded_at: body.recorded_at.unwrap_or_else(Utc::now),
+        telegram_members: count_to_i32("telegramMembers", body.telegram_members)?,
+        x_mentions_24h: count_to_i32("xMentions24h", body.x_mentions24h)?,
+    };
+
+    let stored = SocialMetric::create(&metric, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(stored.into())))
+}
+
+/// POST /api/ingest/reports
+/// Record a source's risk report for a tracked token, replacing its earlier
+/// one, mark the token with the worst severity reported and queue a rescore
+/// of it
+pub async fn ingest_report(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    ApiJson(body): ApiJson<ExternalReportRequest>,
+) -> ApiResult<(StatusCode, Json<ExternalReportItem>)> {
+    let source = body.source.trim();
+    if source.is_empty() || source.len() > 50 {
+        return Err(ApiError::InvalidBody(
+            "`source` must be 1-50 characters".to_string(),
+        ));
+    }
+    let severity = ReportSeverity::parse(body.severity.trim()).ok_or_else(|| {
+        ApiError::InvalidBody(
+            "`severity` must be one of info, low, medium, high or critical".to_string(),
+        )
+    })?;
+    let category = body
+        .category
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    if category.is_some_and(|c| c.len() > 50) {
+        return Err(ApiError::InvalidBody(
+            "`category` must be at most 50 characters".to_string(),
+        ));
+    }
+    let url = body.url.as_deref().map(str::trim).filter(|u| !u.is_empty());
+    if let Some(url) = url {
+        if !(url.starts_with("https://") || url.starts_with("http://"))
+            || url.len() > MAX_REPORT_URL_LEN
+        {
+            return Err(ApiError::InvalidBody(
+                "`url` must be an http(s) URL of at most 2048 characters".to_string(),
+            ));
+        }
+    }
+
+    if Token::find_by_address(&body.token_address, &state.db_pool)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::TokenNotFound(body.token_address.to_string()));
+    }
+
+    let report = NewExternalReport {
+        token_address: *body.token_address,
+        source: source.to_string(),
+        severity,
+        category: category.map(str::to_string),
+        description: body.description,
+        url: url.map(str::to_string),
+        confirmed: body.confirmed,
+        reported_at: body.reported_at.unwrap_or_else(Utc::now),
+    };
+
+    // The report only reaches the BeeScore on a rescore; queued with it so a
+    // restart in between cannot lose it. One already pending will read it too.
+    let rescore = NewJob {
+        kind: JobKind::Rescore,
+        params: json!({ "tokens": [body.token_address.to_string()] }),
+        dedupe_key: Some(format!("rescore:{}", *body.token_address)),
+    };
+    let mut tx = state.db_pool.begin().await?;
+    let stored = ExternalReport::upsert(&report, &mut *tx).await?;
+    ExternalReport::update_token_risk(&body.token_address, &mut *tx).await?;
+    Job::create(&rescore, &mut *tx).await?;
+    tx.commit().await?;
+    Ok((StatusCode::CREATED, Json(stored.into())))
+}
@@ -0,0 +1,326 @@
+//! Long-running jobs
+//!
+//! The processor's job worker runs token imports, re-scores and holder
+//! reconciles queued here. Tracking a token is open to frontend users with a
+//! [`ClientKey`], up to [`MAX_OPEN_IMPORTS`] imports at a time; the other
+//! routes require an [`IngestKey`].
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+use indexer_db::{
+    entity::{
+        job::{Job, JobFilter, JobKind, NewJob},
+        token::Token,
+    },
+    Address20,
+};
+
+use crate::{
+    address::EvmAddress,
+    auth::{ClientKey, IngestKey},
+    envelope::{Listing, ResponseShape},
+    error::{ApiError, ApiJson, ApiQuery, ApiResult},
+    AppState,
+};
+
+const JOB_STATUSES: [&str; 5] = ["pending", "running", "done", "failed", "cancelled"];
+
+/// Token imports pending or running at once before tracking is refused;
+/// each one replays a token's history from the chain
+pub const MAX_OPEN_IMPORTS: i64 = 100;
+
+/// A queued, running or finished job
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobItem {
+    pub id: i32,
+    /// `token_import`, `rescore` or `reconcile`
+    pub kind: String,
+    /// `pending`, `running`, `done`, `failed` or `cancelled`
+    pub status: String,
+    pub params: JsonValue,
+    pub progress_done: i32,
+    /// `None` until the job knows how much work it has
+    pub progress_total: Option<i32>,
+    /// What the job did; kept for cancelled jobs too
+    pub result: Option<JsonValue>,
+    pub error: Option<String>,
+    pub cancel_requested: bool,
+    pub requested_at: String,
+    pub started_at: Option<String>,
+    pub updated_at: String,
+    pub finished_at: Option<String>,
+}
+
+impl From<Job> for JobItem {
+    fn from(j: Job) -> Self {
+        Self {
+            id: j.id,
+            kind: j.kind,
+            status: j.status,
+            params: j.params.0,
+            progress_done: j.progress_done,
+            progress_total: j.progress_total,
+            result: j.result.map(|r| r.0),
+            error: j.error,
+            cancel_requested: j.cancel_requested,
+            requested_at: j.requested_at.to_rfc3339(),
+            started_at: j.started_at.map(|dt| dt.to_rfc3339()),
+            updated_at: j.updated_at.to_rfc3339(),
+            finished_at: j.finished_at.map(|dt| dt.to_rfc3339()),
+        }
+    }
+}
+
+/// Request body for tracking a token
+#[derive(Debug, Deserialize)]
+pub struct TrackTokenRequest {
+    pub address: EvmAddress,
+}
+
+/// Request body for queueing a job
+#[derive(Debug, Deserialize)]
+pub struct CreateJobRequest {
+    pub kind: String,
+    #[serde(default)]
+    pub params: Option<JsonValue>,
+}
+
+/// Query params for listing jobs
+#[derive(Debug, Deserialize)]
+pub struct JobParams {
+    pub status: Option<String>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Params of a `token_import` job
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ImportParams {
+    address: Address20,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+}
+
+/// Params of a `rescore` job
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RescoreParams {
+    tokens: Option<Vec<Address20>>,
+}
+
+/// Params of a `reconcile` job
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReconcileParams {
+    address: Address20,
+}
+
+/// POST /api/tokens/track
+/// Queue an import of a token launched before indexing; asking again while
+/// it runs returns the same job
+pub async fn track_token(
+    _key: ClientKey,
+    State(state): State<Arc<AppState>>,
+    ApiJson(body): ApiJson<TrackTokenRequest>,
+) -> ApiResult<(StatusCode, Json<JobItem>)> {
+    let address = *body.address;
+    if Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .is_some_and(|token| token.pair_address.is_some())
+    {
+        return Err(ApiError::TokenAlreadyTracked(address.to_string()));
+    }
+
+    let dedupe_key = import_dedupe_key(&address);
+    if let Some(open) = Job::find_open(&dedupe_key, &state.db_pool).await? {
+        return Ok((StatusCode::ACCEPTED, Json(open.into())));
+    }
+    if Job::count_open(JobKind::TokenImport, &state.db_pool).await? >= MAX_OPEN_IMPORTS {
+        return Err(ApiError::ImportQueueFull);
+    }
+
+    let job = NewJob {
+        kind: JobKind::TokenImport,
+        params: json!({ "address": address }),
+        dedupe_key: Some(dedupe_key),
+    };
+    let queued = queue(&job, &state).await?;
+    Ok((StatusCode::ACCEPTED, Json(queued.into())))
+}
+
+/// GET /api/jobs
+/// Recent jobs, newest first
+pub async fn get_jobs(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<JobParams>,
+) -> ApiResult<Listing<JobItem>> {
+    if let Some(status) = params
+        .status
+        .as_deref()
+        .filter(|s| !JOB_STATUSES.contains(s))
+    {
+        return Err(ApiError::InvalidQuery(format!(
+            "`status` must be one of {}; got `{}`",
+            JOB_STATUSES.join(", "),
+            status
+        )));
+    }
+    if let Some(kind) = params
+        .kind
+        .as_deref()
+        .filter(|k| JobKind::parse(k).is_none())
+    {
+        return Err(ApiError::InvalidQuery(format!(
+            "`kind` must be one of {}; got `{}`",
+            kind_names(),
+            kind
+        )));
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+
+    let filter = JobFilter {
+        status: params.status,
+        kind: params.kind,
+    };
+    let jobs = Job::find_recent(&filter, limit, &state.db_pool).await?;
+    shape
+        .list(jobs.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await
+}
+
+/// POST /api/jobs
+/// Queue a job; a token import already open for the same token is returned
+/// instead of a new one
+pub async fn create_job(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    ApiJson(body): ApiJson<CreateJobRequest>,
+) -> ApiResult<(StatusCode, Json<JobItem>)> {
+    let kind = JobKind::parse(&body.kind).ok_or_else(|| {
+        ApiError::InvalidBody(format!(
+            "`kind` must be one of {}; got `{}`",
+            kind_names(),
+            body.kind
+        ))
+    })?;
+    let params = body.params.unwrap_or_else(|| json!({}));
+    let invalid = |e: serde_json::Error| {
+        ApiError::InvalidBody(format!("Invalid `params` for {}: {}", kind.name(), e))
+    };
+
+    let dedupe_key = match kind {
+        JobKind::TokenImport => {
+            let import: ImportParams = serde_json::from_value(params.clone()).map_err(invalid)?;
+            if let (Some(from), Some(to)) = (import.from_block, import.to_block) {
+                if from > to {
+                    return Err(ApiError::InvalidBody(
+                        "`fromBlock` must not be after `toBlock`".to_string(),
+                    ));
+                }
+            }
+            Some(import_dedupe_key(&import.address))
+        }
+        JobKind::Rescore => {
+            let rescore: RescoreParams = serde_json::from_value(params.clone()).map_err(invalid)?;
+            if rescore.tokens.as_ref().is_some_and(Vec::is_empty) {
+                return Err(ApiError::InvalidBody(
+                    "`tokens` must not be empty; leave it out to re-score every token"
+                        .to_string(),
+                ));
+            }
+            None
+        }
+        JobKind::Reconcile => {
+            let reconcile: ReconcileParams =
+                serde_json::from_value(params.clone()).map_err(invalid)?;
+            if Token::find_by_address(&reconcile.address, &state.db_pool)
+                .await?
+                .is_none()
+            {
+                return Err(ApiError::TokenNotFound(reconcile.address.to_string()));
+            }
+            None
+        }
+    };
+
+    let job = NewJob {
+        kind,
+        params,
+        dedupe_key,
+    };
+    let queued = queue(&job, &state).await?;
+    Ok((StatusCode::ACCEPTED, Json(queued.into())))
+}
+
+/// GET /api/jobs/:id
+/// Status and progress of a job
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<JobItem>> {
+    let job_id = id
+        .parse::<i32>()
+        .map_err(|_| ApiError::JobNotFound(id.clone()))?;
+
+    match Job::find_by_id(job_id, &state.db_pool).await? {
+        Some(job) => Ok(Json(job.into())),
+        None => Err(ApiError::JobNotFound(id)),
+    }
+}
+
+/// POST /api/jobs/:id/cancel
+/// Cancel a pending job, or ask a running one to stop at its next progress
+/// report; a finished job is returned as it is
+pub async fn cancel_job(
+    _key: IngestKey,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<JobItem>> {
+    let job_id = id
+        .parse::<i32>()
+        .map_err(|_| ApiError::JobNotFound(id.clone()))?;
+
+    match Job::cancel(job_id, &state.db_pool).await? {
+        Some(job) => Ok(Json(job.into())),
+        None => Err(ApiError::JobNotFound(id)),
+    }
+}
+
+/// Queue a job, or return the open one holding its dedupe key
+async fn queue(job: &NewJob, state: &AppState) -> ApiResult<Job> {
+    if let Some(queued) = Job::create(job, &state.db_pool).await? {
+        return Ok(queued);
+    }
+    let dedupe_key = job.dedupe_key.as_deref().unwrap_or_default();
+    // Queued already, unless it finished in between
+    match Job::find_open(dedupe_key, &state.db_pool).await? {
+        Some(open) => Ok(open),
+        None => Ok(Job::create(job, &state.db_pool)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?),
+    }
+}
+
+fn import_dedupe_key(address: &Address20) -> String {
+    format!("token_import:{}", address)
+}
+
+fn kind_names() -> String {
+    JobKind::ALL
+        .iter()
+        .map(|kind| kind.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
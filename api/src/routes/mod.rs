@@ -1,32 +1,447 @@
 //! API route definitions
 
+pub mod admin;
 pub mod alerts;
+pub mod factories;
+pub mod ingest;
+pub mod jobs;
+pub mod pairs;
+pub mod status;
+pub mod streams;
+pub mod tags;
+pub mod tokenlist;
 pub mod tokens;
 pub mod wallets;
 
 use std::sync::Arc;
 
-use axum::{routing::get, Router};
+use axum::{
+    routing::{delete, get, patch, post, put, MethodRouter},
+    Router,
+};
 
 use crate::AppState;
 
+/// An endpoint as listed on the landing page console
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub method: &'static str,
+    /// Path below the version prefix, e.g. `/tokens/:address`
+    pub path: &'static str,
+    pub summary: &'static str,
+}
+
+/// The API router and the endpoints registered on it
+pub struct ApiRoutes {
+    pub router: Router<Arc<AppState>>,
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl ApiRoutes {
+    fn new() -> Self {
+        Self {
+            router: Router::new(),
+            endpoints: Vec::new(),
+        }
+    }
+
+    /// Register `handler` at `path`, listing a `(method, summary)` endpoint
+    /// for each method it serves
+    fn route(
+        mut self,
+        path: &'static str,
+        handler: MethodRouter<Arc<AppState>>,
+        endpoints: &[(&'static str, &'static str)],
+    ) -> Self {
+        self.router = self.router.route(path, handler);
+        self.endpoints
+            .extend(endpoints.iter().map(|&(method, summary)| Endpoint {
+                method,
+                path,
+                summary,
+            }));
+        self
+    }
+}
+
 /// Create all API routes
-pub fn api_routes() -> Router<Arc<AppState>> {
-    Router::new()
+pub fn api_routes() -> ApiRoutes {
+    ApiRoutes::new()
+        // Sync status
+        .route(
+            "/status",
+            get(status::get_status),
+            &[("GET", "Indexed block, chain head and sync lag")],
+        )
         // Token routes
-        .route("/tokens/new", get(tokens::get_new_tokens))
-        .route("/tokens/hot", get(tokens::get_hot_tokens))
-        .route("/tokens/:address", get(tokens::get_token))
-        .route("/tokens/:address/swaps", get(tokens::get_token_swaps))
-        .route("/tokens/:address/holders", get(tokens::get_token_holders))
-        .route("/tokens/:address/chart", get(tokens::get_token_chart))
+        .route(
+            "/tokens/new",
+            get(tokens::get_new_tokens),
+            &[("GET", "Newest tokens")],
+        )
+        .route(
+            "/tokens/hot",
+            get(tokens::get_hot_tokens),
+            &[("GET", "Hot tokens by volume")],
+        )
+        .route(
+            "/tokens/trending",
+            get(tokens::get_trending_tokens),
+            &[("GET", "Trending tokens by 1h momentum")],
+        )
+        .route(
+            "/tokens/track",
+            post(jobs::track_token),
+            &[(
+                "POST",
+                "Start tracking a token launched before indexing; returns a job to poll",
+            )],
+        )
+        .route(
+            "/jobs",
+            get(jobs::get_jobs).post(jobs::create_job),
+            &[
+                ("GET", "Recent jobs, newest first (?status=&kind=&limit=) (X-API-Key required)"),
+                (
+                    "POST",
+                    "Queue a token_import, rescore or reconcile job (X-API-Key required)",
+                ),
+            ],
+        )
+        .route(
+            "/jobs/:id",
+            get(jobs::get_job),
+            &[("GET", "Status and progress of a job")],
+        )
+        .route(
+            "/jobs/:id/cancel",
+            post(jobs::cancel_job),
+            &[("POST", "Cancel a job or ask a running one to stop (X-API-Key required)")],
+        )
+        .route(
+            "/tokens/ranked",
+            get(tokens::get_ranked_tokens),
+            &[(
+                "GET",
+                "Tokens ranked by custom weights (?w_volume=2&w_liquidity=0.5&w_holders=1&w_score=1&w_momentum=0)",
+            )],
+        )
+        .route(
+            "/tokens/:address",
+            get(tokens::get_token),
+            &[("GET", "Token details")],
+        )
+        .route(
+            "/tokens/:address/swaps",
+            get(tokens::get_token_swaps),
+            &[(
+                "GET",
+                "Token swaps (?type=buy|sell&min_usd=1000&whales_only=true&since=RFC 3339 time)",
+            )],
+        )
+        .route(
+            "/tokens/:address/holders",
+            get(tokens::get_token_holders),
+            &[("GET", "Token holders")],
+        )
+        .route(
+            "/tokens/:address/snipers",
+            get(tokens::get_token_snipers),
+            &[("GET", "Sniper wallets and ratio")],
+        )
+        .route(
+            "/tokens/:address/activity-heatmap",
+            get(tokens::get_token_activity_heatmap),
+            &[(
+                "GET",
+                "Trades and volume by day of week and hour of day (UTC) over the last 30 days",
+            )],
+        )
+        .route(
+            "/tokens/:address/scores",
+            get(tokens::get_token_scores),
+            &[(
+                "GET",
+                "BeeScore history with the top-10 holders each score saw",
+            )],
+        )
+        .route(
+            "/tokens/:address/score",
+            get(tokens::get_token_score),
+            &[(
+                "GET",
+                "Latest BeeScore breakdown with suggestions (LP unlocks, concentration, score moves)",
+            )],
+        )
+        .route(
+            "/tokens/:address/chart",
+            get(tokens::get_token_chart),
+            &[("GET", "Price chart data")],
+        )
+        .route(
+            "/tokens/:address/card",
+            get(tokens::get_token_card),
+            &[(
+                "GET",
+                "Share card data: price, 24h change, BeeScore and a 24h sparkline",
+            )],
+        )
+        .route(
+            "/tokens/:address/similar",
+            get(tokens::get_similar_tokens),
+            &[(
+                "GET",
+                "Tokens with a similar launch profile (copycat check)",
+            )],
+        )
+        .route(
+            "/tokens/:address/quote",
+            get(tokens::get_token_quote),
+            &[(
+                "GET",
+                "Simulate a trade against current reserves, in the pair's base token (?amount_bnb=0.5&side=buy)",
+            )],
+        )
+        .route(
+            "/tokens/:address/tags",
+            post(tags::tag_token),
+            &[("POST", "Tag a token")],
+        )
+        .route(
+            "/tokens/:address/tags/:tag",
+            delete(tags::untag_token),
+            &[("DELETE", "Remove a tag from a token")],
+        )
+        // Factory routes
+        .route(
+            "/factories/:address/stats",
+            get(factories::get_factory_stats),
+            &[(
+                "GET",
+                "Pairs per day, LP lock share, median initial liquidity and rug rate of a factory's pairs (?days=30)",
+            )],
+        )
+        // Pair routes
+        .route(
+            "/pairs/top",
+            get(pairs::get_top_pairs),
+            &[(
+                "GET",
+                "Pools by 24h volume, LP fees and fee APR (?sort=volume|fees|apr|liquidity)",
+            )],
+        )
         // Wallet routes
-        .route("/wallets", get(wallets::get_wallets).post(wallets::create_wallet))
+        .route(
+            "/wallets",
+            get(wallets::get_wallets).post(wallets::create_wallet),
+            &[
+                ("GET", "List tracked wallets"),
+                ("POST", "Add wallet to track"),
+            ],
+        )
+        .route(
+            "/wallets/bulk",
+            post(wallets::bulk_create_wallets),
+            &[(
+                "POST",
+                "Import wallets from a JSON array or CSV upload (address,label)",
+            )],
+        )
+        .route(
+            "/wallets/suggestions",
+            get(wallets::get_wallet_suggestions),
+            &[(
+                "GET",
+                "Untracked wallets worth tracking (top 7d PnL, early buyers of high scorers)",
+            )],
+        )
         .route(
             "/wallets/:address",
             get(wallets::get_wallet).delete(wallets::delete_wallet),
+            &[("GET", "Get wallet details"), ("DELETE", "Remove wallet")],
+        )
+        .route(
+            "/wallets/:address/activity",
+            get(wallets::get_wallet_activity),
+            &[("GET", "Wallet activity")],
+        )
+        .route(
+            "/wallets/:address/profile",
+            get(wallets::get_wallet_profile),
+            &[(
+                "GET",
+                "Lifetime volume, hold time, win rate and biggest win/loss of any wallet",
+            )],
+        )
+        .route(
+            "/wallets/:address/tags",
+            post(tags::tag_wallet),
+            &[("POST", "Tag a wallet")],
+        )
+        .route(
+            "/wallets/:address/tags/:tag",
+            delete(tags::untag_wallet),
+            &[("DELETE", "Remove a tag from a wallet")],
+        )
+        // Published token list
+        .route(
+            "/tokenlist.json",
+            get(tokenlist::get_token_list),
+            &[("GET", "High-BeeScore tokens as a versioned TokenList")],
+        )
+        // Tag routes
+        .route(
+            "/tags",
+            get(tags::get_tags),
+            &[(
+                "GET",
+                "Tags with wallet and token counts (filter lists with ?tag=)",
+            )],
         )
-        .route("/wallets/:address/activity", get(wallets::get_wallet_activity))
         // Alert routes
-        .route("/alerts/feed", get(alerts::get_alert_feed))
+        .route(
+            "/alerts/feed",
+            get(alerts::get_alert_feed),
+            &[("GET", "Alert feed")],
+        )
+        // Live streams, replaying what a reconnecting client missed
+        .route(
+            "/ws/swaps",
+            get(streams::swaps_ws),
+            &[("GET", "WebSocket stream of swaps (?since= to replay)")],
+        )
+        .route(
+            "/ws/alerts",
+            get(streams::alerts_ws),
+            &[("GET", "WebSocket stream of alerts (?since= to replay)")],
+        )
+        // Per-token alert levels for the X-API-Key sent
+        .route(
+            "/alerts/preferences",
+            get(alerts::get_preferences),
+            &[(
+                "GET",
+                "Tokens muted or limited to critical alerts for your X-API-Key",
+            )],
+        )
+        .route(
+            "/alerts/preferences/:address",
+            put(alerts::set_preference),
+            &[(
+                "PUT",
+                "Set a token's alert level for your X-API-Key: mute, critical or all",
+            )],
+        )
+        // Webhook management (API key required)
+        .route(
+            "/alerts/webhooks",
+            get(alerts::get_webhooks).post(alerts::create_webhook),
+            &[
+                ("GET", "List alert webhooks (X-API-Key required)"),
+                (
+                    "POST",
+                    "Register an HMAC-signed alert webhook (X-API-Key required)",
+                ),
+            ],
+        )
+        .route(
+            "/alerts/webhooks/:id",
+            delete(alerts::delete_webhook),
+            &[("DELETE", "Remove an alert webhook (X-API-Key required)")],
+        )
+        // Ingestion routes (API key required)
+        .route(
+            "/ingest/social",
+            post(ingest::ingest_social),
+            &[("POST", "Push social metrics (X-API-Key required)")],
+        )
+        .route(
+            "/ingest/reports",
+            post(ingest::ingest_report),
+            &[(
+                "POST",
+                "Push an external risk report for a token (X-API-Key required)",
+            )],
+        )
+        // Operator routes (API key required)
+        .route(
+            "/admin/listeners",
+            get(admin::get_listeners),
+            &[(
+                "GET",
+                "Listener filters and their runtime controls (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/listeners/:name",
+            patch(admin::update_listener),
+            &[(
+                "PATCH",
+                "Enable/disable a listener filter or change its poll interval (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/lag",
+            get(admin::get_processing_lag),
+            &[(
+                "GET",
+                "Block-to-processed lag per event type against its SLO (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/event-metrics",
+            get(admin::get_event_metrics),
+            &[(
+                "GET",
+                "Logs, decode failures, handler errors and latency per event type (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/query-metrics",
+            get(admin::get_query_metrics),
+            &[(
+                "GET",
+                "Database queries and time per API endpoint, over-budget requests (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/errors",
+            get(admin::get_processing_errors),
+            &[(
+                "GET",
+                "Logs the processor failed to decode or handle, newest first; oldest first after ?since= (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/tokens/:address/rescan",
+            post(admin::rescan_token),
+            &[(
+                "POST",
+                "Queue a replay of one token's logs to rebuild its data (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/rescans/:id",
+            get(admin::get_rescan),
+            &[(
+                "GET",
+                "Status of a queued token rescan (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/tokens/:address/verify-holders",
+            post(admin::verify_holders),
+            &[(
+                "POST",
+                "Queue a balanceOf check of every stored holder of one token (X-API-Key required)",
+            )],
+        )
+        .route(
+            "/admin/holder-verifications/:id",
+            get(admin::get_holder_verification),
+            &[(
+                "GET",
+                "Status of a queued holder verification (X-API-Key required)",
+            )],
+        )
 }
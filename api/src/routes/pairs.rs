@@ -0,0 +1,94 @@
+//! Pair API routes
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+
+use indexer_db::{
+    entity::pair::{Pair, PairSort, PairStats},
+    Address20,
+};
+
+use crate::{
+    decimal::Decimal,
+    envelope::{Listing, ResponseShape},
+    error::{ApiError, ApiQuery, ApiResult},
+    AppState,
+};
+
+/// Pool ranking response item
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairItem {
+    pub address: Address20,
+    pub token_address: Address20,
+    pub token_symbol: Option<String>,
+    pub base_token_address: Address20,
+    pub liquidity: Option<Decimal>,
+    pub volume24h: Decimal,
+    pub trades24h: i32,
+    /// Estimated fees earned by LPs over 24h
+    pub fees24h: Decimal,
+    /// 24h fees annualized over liquidity, in percent
+    pub fee_apr: Option<f64>,
+    /// Share (0-100) of the 24h volume across all pairs
+    pub volume_share: f64,
+    pub stats_updated_at: Option<String>,
+}
+
+impl From<PairStats> for PairItem {
+    fn from(s: PairStats) -> Self {
+        Self {
+            address: s.pair.address,
+            token_address: *s.pair.get_token_address(),
+            token_symbol: s.token_symbol,
+            base_token_address: *s.pair.get_base_address(),
+            liquidity: s.liquidity_usd.map(Decimal),
+            volume24h: Decimal::of(&s.pair.volume_24h_usd),
+            trades24h: s.pair.trades_24h.unwrap_or(0),
+            fees24h: Decimal::of(&s.pair.fees_24h_usd),
+            fee_apr: s.fee_apr,
+            volume_share: s.volume_share,
+            stats_updated_at: s.pair.stats_updated_at.map(|dt| dt.to_rfc3339()),
+        }
+    }
+}
+
+/// Query params for the pool ranking
+#[derive(Debug, Deserialize)]
+pub struct TopPairsParams {
+    /// `volume` (default), `fees`, `apr` or `liquidity`
+    pub sort: Option<String>,
+    pub limit: Option<i32>,
+}
+
+/// Parse the `sort` query parameter
+fn pair_sort(sort: Option<&str>) -> ApiResult<PairSort> {
+    match sort.unwrap_or("volume") {
+        "volume" => Ok(PairSort::Volume),
+        "fees" => Ok(PairSort::Fees),
+        "apr" => Ok(PairSort::Apr),
+        "liquidity" => Ok(PairSort::Liquidity),
+        other => Err(ApiError::InvalidQuery(format!(
+            "unknown sort `{}`, expected volume, fees, apr or liquidity",
+            other
+        ))),
+    }
+}
+
+/// GET /api/pairs/top
+/// Returns pairs traded over the last 24h with volume, LP fees and fee APR
+pub async fn get_top_pairs(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<TopPairsParams>,
+) -> ApiResult<Listing<PairItem>> {
+    let sort = pair_sort(params.sort.as_deref())?;
+    let limit = params.limit.unwrap_or(50).min(100);
+
+    let pairs = Pair::find_top(sort, limit, &state.db_pool).await?;
+    shape
+        .list(pairs.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await
+}
@@ -0,0 +1,39 @@
+//! Sync status route
+
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+
+use indexer_db::entity::evm_sync_logs::EvmSyncLogs;
+
+use crate::{error::ApiResult, AppState};
+
+/// How far the indexer has got, across all listener filters
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    /// Lowest block every filter has synced through
+    pub synced_block: Option<i64>,
+    /// Chain head at the listeners' last poll
+    pub head_block: Option<i64>,
+    pub lag_blocks: Option<i64>,
+    pub updated_at: Option<String>,
+}
+
+/// GET /api/status - Indexer sync position against the chain head
+pub async fn get_status(State(state): State<Arc<AppState>>) -> ApiResult<Json<SyncStatus>> {
+    let syncs = EvmSyncLogs::find_all(&state.db_pool).await?;
+    let lag_blocks = EvmSyncLogs::max_lag_blocks(&state.db_pool).await?;
+
+    Ok(Json(SyncStatus {
+        synced_block: syncs.iter().map(|s| s.last_synced_block_number).min(),
+        head_block: syncs.iter().filter_map(|s| s.latest_block_number).max(),
+        lag_blocks,
+        updated_at: syncs
+            .iter()
+            .map(|s| s.updated_at)
+            .max()
+            .map(|t| t.and_utc().to_rfc3339()),
+    }))
+}
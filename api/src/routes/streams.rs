@@ -0,0 +1,118 @@
+//! Live WebSocket streams
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    response::Response,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    decimal::Precision,
+    demo,
+    error::ApiQuery,
+    stream::{Channel, StreamEvent},
+    AppState,
+};
+
+/// Query params for the streams
+#[derive(Debug, Deserialize)]
+pub struct StreamParams {
+    /// Id of the last event seen before reconnecting: the ones after it
+    /// still buffered are sent first
+    pub since: Option<i32>,
+}
+
+/// GET /api/ws/swaps
+/// Swaps on every token as they are indexed
+pub async fn swaps_ws(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<StreamParams>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    upgrade(state, Channel::Swaps, params.since, ws)
+}
+
+/// GET /api/ws/alerts
+/// Alerts as they are raised
+pub async fn alerts_ws(
+    State(state): State<Arc<AppState>>,
+    ApiQuery(params): ApiQuery<StreamParams>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    upgrade(state, Channel::Alerts, params.since, ws)
+}
+
+fn upgrade(
+    state: Arc<AppState>,
+    channel: Channel,
+    since: Option<i32>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    // The socket outlives the request, so take its rendering along
+    let precision = Precision::current();
+    ws.on_upgrade(move |socket| stream(socket, state, channel, since, precision))
+}
+
+/// Send the missed events after `since`, then the live ones, until the
+/// client goes away
+async fn stream(
+    mut socket: WebSocket,
+    state: Arc<AppState>,
+    channel: Channel,
+    since: Option<i32>,
+    precision: Precision,
+) {
+    let buffer = state.streams.get(channel);
+    let render = |event: &StreamEvent| {
+        demo::masking(state.demo, || {
+            precision.apply(|| serde_json::to_string(event).unwrap_or_default())
+        })
+    };
+    let mut last_sent = since;
+
+    'subscribe: loop {
+        let mut subscription = buffer.subscribe(last_sent);
+        if subscription.gap {
+            let gap = json!({ "channel": channel.as_str(), "gap": true, "since": last_sent });
+            if socket.send(Message::Text(gap.to_string())).await.is_err() {
+                return;
+            }
+        }
+        for event in subscription.missed.drain(..) {
+            if socket.send(Message::Text(render(&event))).await.is_err() {
+                return;
+            }
+            last_sent = Some(event.id);
+        }
+
+        loop {
+            tokio::select! {
+                received = subscription.live.recv() => match received {
+                    Ok(event) => {
+                        if last_sent.is_some_and(|id| event.id <= id) {
+                            continue;
+                        }
+                        if socket.send(Message::Text(render(&event))).await.is_err() {
+                            return;
+                        }
+                        last_sent = Some(event.id);
+                    }
+                    // Too slow for the live flow: pick up again from the buffer
+                    Err(RecvError::Lagged(_)) => continue 'subscribe,
+                    Err(RecvError::Closed) => return,
+                },
+                incoming = socket.recv() => match incoming {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    }
+}
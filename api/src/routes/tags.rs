@@ -0,0 +1,172 @@
+//! Tag API routes
+//!
+//! Tags are free-form labels ("insider", "cex", "gem") attached to wallets
+//! and tokens. Names are trimmed and lowercased when stored.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use indexer_db::{
+    entity::tag::{Tag, TagSubject, TagWithCounts},
+    Address20,
+};
+
+use crate::{
+    address::EvmAddress,
+    envelope::{Listing, ResponseShape},
+    error::{ApiError, ApiJson, ApiResult},
+    AppState,
+};
+
+/// Longest tag name the tags table stores
+const MAX_TAG_LEN: usize = 50;
+
+/// Tag with its usage counts
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagItem {
+    pub name: String,
+    pub wallets: i64,
+    pub tokens: i64,
+}
+
+impl From<TagWithCounts> for TagItem {
+    fn from(t: TagWithCounts) -> Self {
+        Self {
+            name: t.name,
+            wallets: t.wallets,
+            tokens: t.tokens,
+        }
+    }
+}
+
+/// Request body for tagging a wallet or token
+#[derive(Debug, Deserialize)]
+pub struct AddTagRequest {
+    pub tag: String,
+}
+
+/// Check a tag name before it reaches SQL
+fn validate(tag: &str) -> ApiResult<&str> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(ApiError::InvalidBody("Tag must not be empty".to_string()));
+    }
+    if tag.chars().count() > MAX_TAG_LEN {
+        return Err(ApiError::InvalidBody(format!(
+            "Tag longer than {} characters",
+            MAX_TAG_LEN
+        )));
+    }
+    Ok(tag)
+}
+
+/// Tag names of each address, for filling list responses
+pub async fn tags_by_address(
+    subject: TagSubject,
+    addresses: &[Address20],
+    db_pool: &Pool<Postgres>,
+) -> Result<HashMap<Address20, Vec<String>>, sqlx::Error> {
+    let mut tags: HashMap<Address20, Vec<String>> = HashMap::new();
+    for (address, name) in Tag::find_names(subject, addresses, db_pool).await? {
+        tags.entry(address).or_default().push(name);
+    }
+    Ok(tags)
+}
+
+/// Tag names of one address
+pub async fn tags_of(
+    subject: TagSubject,
+    address: &Address20,
+    db_pool: &Pool<Postgres>,
+) -> Result<Vec<String>, sqlx::Error> {
+    Ok(tags_by_address(subject, &[*address], db_pool)
+        .await?
+        .remove(address)
+        .unwrap_or_default())
+}
+
+async fn attach(
+    state: &AppState,
+    subject: TagSubject,
+    address: &Address20,
+    body: AddTagRequest,
+) -> ApiResult<(StatusCode, Json<Vec<String>>)> {
+    let tag = validate(&body.tag)?;
+    Tag::attach(subject, address, tag, &state.db_pool).await?;
+
+    let tags = tags_of(subject, address, &state.db_pool).await?;
+    Ok((StatusCode::CREATED, Json(tags)))
+}
+
+async fn detach(
+    state: &AppState,
+    subject: TagSubject,
+    raw_address: &str,
+    tag: &str,
+) -> ApiResult<StatusCode> {
+    let address = EvmAddress::parse(raw_address)?;
+
+    if Tag::detach(subject, &address, tag, &state.db_pool).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::TagNotFound(tag.to_string()))
+    }
+}
+
+/// GET /api/tags
+/// Returns every tag with how many wallets and tokens carry it
+pub async fn get_tags(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+) -> ApiResult<Listing<TagItem>> {
+    let tags = Tag::find_all_with_counts(&state.db_pool).await?;
+    shape
+        .list(tags.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await
+}
+
+/// POST /api/wallets/:address/tags
+/// Tag a wallet; returns all of its tags
+pub async fn tag_wallet(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+    ApiJson(body): ApiJson<AddTagRequest>,
+) -> ApiResult<(StatusCode, Json<Vec<String>>)> {
+    attach(&state, TagSubject::Wallet, &address, body).await
+}
+
+/// DELETE /api/wallets/:address/tags/:tag
+/// Remove a tag from a wallet
+pub async fn untag_wallet(
+    State(state): State<Arc<AppState>>,
+    Path((address, tag)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+    detach(&state, TagSubject::Wallet, &address, &tag).await
+}
+
+/// POST /api/tokens/:address/tags
+/// Tag a token; returns all of its tags
+pub async fn tag_token(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+    ApiJson(body): ApiJson<AddTagRequest>,
+) -> ApiResult<(StatusCode, Json<Vec<String>>)> {
+    attach(&state, TagSubject::Token, &address, body).await
+}
+
+/// DELETE /api/tokens/:address/tags/:tag
+/// Remove a tag from a token
+pub async fn untag_token(
+    State(state): State<Arc<AppState>>,
+    Path((address, tag)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+    detach(&state, TagSubject::Token, &address, &tag).await
+}
@@ -0,0 +1,128 @@
+//! Published token list route
+//!
+//! The processor publishes the tokens with a high BeeScore as versioned
+//! releases; this serves the latest one in the Uniswap TokenList format for
+//! wallets and aggregators to import.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use indexer_db::{
+    entity::token_list_release::{ListVersion, TokenListRelease},
+    Address20,
+};
+
+use crate::{
+    error::{ApiError, ApiResult},
+    AppState,
+};
+
+/// Seconds clients and caches may reuse the list without asking again
+const MAX_AGE_SECS: u64 = 300;
+
+const LIST_NAME: &str = "BeanBee BeeScore";
+const LIST_KEYWORDS: [&str; 3] = ["beanbee", "beescore", "bsc"];
+
+/// A token in the TokenList format
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub chain_id: i64,
+    pub address: Address20,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: i16,
+}
+
+/// A TokenList document
+#[derive(Debug, Serialize)]
+pub struct TokenListResponse {
+    pub name: &'static str,
+    /// When the release was published, RFC 3339
+    pub timestamp: String,
+    pub version: ListVersion,
+    pub keywords: [&'static str; 3],
+    pub tokens: Vec<TokenInfo>,
+}
+
+/// Whether an `If-None-Match` header lists `etag`
+fn matches_etag(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+/// GET /api/tokenlist.json
+/// Returns the latest published token list. Every change to the list is a
+/// new version, so the version doubles as the `ETag`; a client sending it
+/// back in `If-None-Match` gets a 304.
+pub async fn get_token_list(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let release = TokenListRelease::find_latest(&state.db_pool)
+        .await?
+        .ok_or(ApiError::TokenListNotPublished)?;
+
+    let version = release.version();
+    let etag = format!(
+        "\"{}-{}.{}.{}\"",
+        release.chain_id, version.major, version.minor, version.patch
+    );
+    let caching = [
+        (
+            header::CACHE_CONTROL,
+            HeaderValue::from_str(&format!("public, max-age={}", MAX_AGE_SECS))
+                .expect("valid header value"),
+        ),
+        (
+            header::ETAG,
+            HeaderValue::from_str(&etag).expect("valid header value"),
+        ),
+        (
+            header::LAST_MODIFIED,
+            HeaderValue::from_str(
+                &release
+                    .published_at
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string(),
+            )
+            .expect("valid header value"),
+        ),
+    ];
+
+    if matches_etag(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, caching).into_response());
+    }
+
+    let list = TokenListResponse {
+        name: LIST_NAME,
+        timestamp: release.published_at.to_rfc3339(),
+        version,
+        keywords: LIST_KEYWORDS,
+        tokens: release
+            .tokens
+            .0
+            .into_iter()
+            .map(|t| TokenInfo {
+                chain_id: release.chain_id,
+                address: t.address,
+                name: t.name,
+                symbol: t.symbol,
+                decimals: t.decimals,
+            })
+            .collect(),
+    };
+    Ok((caching, Json(list)).into_response())
+}
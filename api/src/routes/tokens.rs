@@ -1,24 +1,62 @@
 //! Token API routes
 
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::State,
     Json,
 };
-use chrono::{Duration, Utc};
+use chrono::{Duration, DurationRound, Utc};
 use serde::{Deserialize, Serialize};
 
-use indexer_db::entity::{
-    price_snapshot::PriceSnapshot,
-    swap::Swap,
-    token::Token,
-    token_holder::TokenHolder,
+use indexer_db::{
+    entity::{
+        lp_lock::LpLock,
+        pair::Pair,
+        price_snapshot::{HourlyClose, PriceBucket, PriceSnapshot},
+        score_history::{HolderShare, ScoreComponent, ScoreHistory},
+        swap::{ActivityCell, Swap, SwapFilter},
+        tag::TagSubject,
+        token::{RankWeights, RankedToken, SimilarToken, Token},
+        token_holder::TokenHolder,
+        token_impersonation::TokenImpersonation,
+        token_list::TokenList,
+        wallet_activity::WalletActivity,
+    },
+    Address20, Hash32,
+};
+
+use crate::{
+    address::EvmAddress,
+    amm::{self, PANCAKE_V2_FEE_BPS},
+    badges::{self, Badge},
+    decimal::Decimal,
+    demo::WalletAddress,
+    envelope::{Listing, ResponseShape},
+    error::{ApiError, ApiQuery, ApiResult},
+    format,
+    routes::tags::{tags_by_address, tags_of},
+    score_advice::{self, Suggestion},
+    AppState,
 };
 
-use crate::AppState;
+/// Below this many snapshots in range, charts are filled in from swaps
+const MIN_SNAPSHOT_POINTS: usize = 10;
+
+/// Points in a token list sparkline, one per hour
+const SPARKLINE_HOURS: usize = 24;
+
+/// Largest weight a custom ranking accepts for one metric
+const MAX_RANK_WEIGHT: f64 = 100.0;
+
+/// Days of trades an activity heatmap covers
+const HEATMAP_DAYS: i64 = 30;
+
+/// How far back a score explanation looks for the computation it compares to
+const SCORE_BASELINE_HOURS: i64 = 24;
 
 /// Helper to convert BigDecimal to f64
 fn bd_to_f64(bd: &sqlx::types::BigDecimal) -> f64 {
@@ -29,16 +67,16 @@ fn bd_to_f64(bd: &sqlx::types::BigDecimal) -> f64 {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TokenListItem {
-    pub address: String,
+    pub address: Address20,
     pub name: String,
     pub symbol: String,
-    pub price: f64,
+    pub price: Decimal,
     pub price_change1h: f64,
     pub price_change24h: f64,
-    pub liquidity: f64,
-    pub market_cap: f64,
-    pub volume1h: f64,
-    pub volume24h: f64,
+    pub liquidity: Decimal,
+    pub market_cap: Decimal,
+    pub volume1h: Decimal,
+    pub volume24h: Decimal,
     pub holders: i32,
     pub bee_score: i16,
     pub safety_score: i16,
@@ -46,23 +84,63 @@ pub struct TokenListItem {
     pub lp_locked: bool,
     pub dev_holdings: f64,
     pub sniper_ratio: f64,
+    /// Share (%) of supply held by detected sniper bots
+    pub bot_holder_percent: f64,
     pub created_at: String,
     pub chain: String,
+    pub tags: Vec<String>,
+    /// Risk badges, risks first
+    pub badges: Vec<Badge>,
+    /// Hourly closing prices over the last 24h, oldest first, when asked for
+    /// with `?sparkline=true`; `null` before the token's first price
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparkline: Option<Vec<Option<f64>>>,
+    #[serde(flatten)]
+    pub display: DisplayHints,
+}
+
+/// Display-friendly strings for a token's headline numbers
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayHints {
+    /// e.g. `0.0₅421`
+    pub price_display: String,
+    /// e.g. `$52.3K`
+    pub liquidity_display: String,
+    pub market_cap_display: String,
+    pub volume24h_display: String,
+}
+
+impl DisplayHints {
+    fn of(t: &Token) -> Self {
+        let usd = |v: &Option<sqlx::types::BigDecimal>| {
+            format::compact_usd(v.as_ref().map(bd_to_f64).unwrap_or(0.0))
+        };
+
+        Self {
+            price_display: format::price(t.price_usd.as_ref().map(bd_to_f64).unwrap_or(0.0)),
+            liquidity_display: usd(&t.liquidity_usd),
+            market_cap_display: usd(&t.market_cap_usd),
+            volume24h_display: usd(&t.volume_24h_usd),
+        }
+    }
 }
 
 impl From<Token> for TokenListItem {
     fn from(t: Token) -> Self {
+        let display = DisplayHints::of(&t);
+        let badges = badges::of(&t, Utc::now());
         Self {
             address: t.address,
             name: t.name.unwrap_or_else(|| "Unknown".to_string()),
             symbol: t.symbol.unwrap_or_else(|| "???".to_string()),
-            price: t.price_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            price: Decimal::of(&t.price_usd),
             price_change1h: t.price_change_1h.as_ref().map(bd_to_f64).unwrap_or(0.0),
             price_change24h: t.price_change_24h.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            liquidity: t.liquidity_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            market_cap: t.market_cap_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            volume1h: t.volume_1h_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            volume24h: t.volume_24h_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            liquidity: Decimal::of(&t.liquidity_usd),
+            market_cap: Decimal::of(&t.market_cap_usd),
+            volume1h: Decimal::of(&t.volume_1h_usd),
+            volume24h: Decimal::of(&t.volume_24h_usd),
             holders: t.holder_count.unwrap_or(0),
             bee_score: t.bee_score.unwrap_or(0),
             safety_score: t.safety_score.unwrap_or(0),
@@ -70,8 +148,13 @@ impl From<Token> for TokenListItem {
             lp_locked: t.lp_locked.unwrap_or(false),
             dev_holdings: t.dev_holdings_percent.as_ref().map(bd_to_f64).unwrap_or(0.0),
             sniper_ratio: t.sniper_ratio.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            bot_holder_percent: t.bot_holder_percent.as_ref().map(bd_to_f64).unwrap_or(0.0),
             created_at: t.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_else(|| Utc::now().to_rfc3339()),
             chain: "BSC".to_string(),
+            tags: Vec::new(),
+            badges,
+            sparkline: None,
+            display,
         }
     }
 }
@@ -80,25 +163,25 @@ impl From<Token> for TokenListItem {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TokenDetail {
-    pub address: String,
+    pub address: Address20,
     pub name: String,
     pub symbol: String,
     pub decimals: i16,
-    pub pair_address: Option<String>,
-    pub creator_address: Option<String>,
+    pub pair_address: Option<Address20>,
+    pub creator_address: Option<WalletAddress>,
     pub created_at: String,
     pub block_number: Option<i64>,
 
     // Price metrics
-    pub price: f64,
-    pub price_bnb: f64,
+    pub price: Decimal,
+    pub price_bnb: Decimal,
     pub price_change1h: f64,
     pub price_change24h: f64,
-    pub market_cap: f64,
-    pub liquidity: f64,
-    pub liquidity_bnb: f64,
-    pub volume1h: f64,
-    pub volume24h: f64,
+    pub market_cap: Decimal,
+    pub liquidity: Decimal,
+    pub liquidity_bnb: Decimal,
+    pub volume1h: Decimal,
+    pub volume24h: Decimal,
 
     // Trading metrics
     pub trades1h: i32,
@@ -111,43 +194,72 @@ pub struct TokenDetail {
     pub top10_holder_percent: f64,
     pub dev_holdings: f64,
     pub sniper_ratio: f64,
+    /// Holders other than detected sniper bots
+    pub organic_holders: i32,
+    /// Share (%) of supply held by detected sniper bots
+    pub bot_holder_percent: f64,
+    pub holders_entered24h: i32,
+    pub holders_exited24h: i32,
+    /// Exits over the last 24h per 100 holders at the start of the window
+    pub holder_churn_rate24h: f64,
+
+    // Exchange flows, in tokens moved to/from known CEX wallets over 24h
+    pub cex_inflow24h: f64,
+    pub cex_outflow24h: f64,
 
     // Safety
     pub lp_locked: bool,
     pub lp_lock_percent: f64,
     pub lp_unlock_date: Option<String>,
     pub ownership_renounced: bool,
+    /// The on-chain name or symbol hid invisible characters (spoofing)
+    pub name_spoofed: bool,
+    /// Worst severity external integrations reported for the token
+    pub external_risk: Option<String>,
 
     // BeeScore
     pub bee_score: i16,
     pub safety_score: i16,
     pub traction_score: i16,
+    /// Share of recent volume that looks gamed (0-100)
+    pub wash_trading_score: f64,
+    /// Set when the name or symbol copies an established token's
+    pub impersonation: Option<ImpersonationItem>,
 
     pub chain: String,
     pub last_updated: Option<String>,
+    /// When the token was archived for having no liquidity or trades
+    pub archived_at: Option<String>,
+    pub tags: Vec<String>,
+    /// Risk badges, risks first
+    pub badges: Vec<Badge>,
+    #[serde(flatten)]
+    pub display: DisplayHints,
 }
 
 impl From<Token> for TokenDetail {
     fn from(t: Token) -> Self {
+        let display = DisplayHints::of(&t);
+        let badges = badges::of(&t, Utc::now());
         Self {
             address: t.address,
             name: t.name.unwrap_or_else(|| "Unknown".to_string()),
             symbol: t.symbol.unwrap_or_else(|| "???".to_string()),
             decimals: t.decimals.unwrap_or(18),
             pair_address: t.pair_address,
-            creator_address: t.creator_address,
+            creator_address: t.creator_address.map(WalletAddress),
             created_at: t.created_at.map(|dt| dt.to_rfc3339()).unwrap_or_else(|| Utc::now().to_rfc3339()),
             block_number: t.block_number,
 
-            price: t.price_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            price_bnb: t.price_bnb.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            price: Decimal::of(&t.price_usd),
+            price_bnb: Decimal::of(&t.price_bnb),
             price_change1h: t.price_change_1h.as_ref().map(bd_to_f64).unwrap_or(0.0),
             price_change24h: t.price_change_24h.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            market_cap: t.market_cap_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            liquidity: t.liquidity_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            liquidity_bnb: t.liquidity_bnb.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            volume1h: t.volume_1h_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            volume24h: t.volume_24h_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            market_cap: Decimal::of(&t.market_cap_usd),
+            liquidity: Decimal::of(&t.liquidity_usd),
+            liquidity_bnb: Decimal::of(&t.liquidity_bnb),
+            volume1h: Decimal::of(&t.volume_1h_usd),
+            volume24h: Decimal::of(&t.volume_24h_usd),
 
             trades1h: t.trades_1h.unwrap_or(0),
             trades24h: t.trades_24h.unwrap_or(0),
@@ -158,18 +270,113 @@ impl From<Token> for TokenDetail {
             top10_holder_percent: t.top_10_holder_percent.as_ref().map(bd_to_f64).unwrap_or(0.0),
             dev_holdings: t.dev_holdings_percent.as_ref().map(bd_to_f64).unwrap_or(0.0),
             sniper_ratio: t.sniper_ratio.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            organic_holders: t
+                .organic_holder_count
+                .or(t.holder_count)
+                .unwrap_or(0),
+            bot_holder_percent: t.bot_holder_percent.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            holders_entered24h: t.holders_entered_24h.unwrap_or(0),
+            holders_exited24h: t.holders_exited_24h.unwrap_or(0),
+            holder_churn_rate24h: t.holder_churn_rate_24h.as_ref().map(bd_to_f64).unwrap_or(0.0),
+
+            cex_inflow24h: 0.0,
+            cex_outflow24h: 0.0,
 
             lp_locked: t.lp_locked.unwrap_or(false),
             lp_lock_percent: t.lp_lock_percent.as_ref().map(bd_to_f64).unwrap_or(0.0),
             lp_unlock_date: t.lp_unlock_date.map(|dt| dt.to_rfc3339()),
             ownership_renounced: t.ownership_renounced.unwrap_or(false),
+            name_spoofed: t.name_spoofed.unwrap_or(false),
+            external_risk: t.external_risk,
 
             bee_score: t.bee_score.unwrap_or(0),
             safety_score: t.safety_score.unwrap_or(0),
             traction_score: t.traction_score.unwrap_or(0),
+            wash_trading_score: t.wash_trading_score.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            impersonation: None,
 
             chain: "BSC".to_string(),
             last_updated: t.last_updated.map(|dt| dt.to_rfc3339()),
+            archived_at: t.archived_at.map(|dt| dt.to_rfc3339()),
+            tags: Vec::new(),
+            badges,
+            display,
+        }
+    }
+}
+
+/// The established token a token appears to impersonate
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationItem {
+    pub target_address: Address20,
+    /// `top` (by market cap) or `trending`
+    pub target_source: String,
+    /// `name` or `symbol`
+    pub matched_field: String,
+    /// 0-1, 1 being identical
+    pub similarity: f64,
+    pub detected_at: String,
+}
+
+impl From<TokenImpersonation> for ImpersonationItem {
+    fn from(i: TokenImpersonation) -> Self {
+        Self {
+            target_address: i.target_address,
+            target_source: i.target_source,
+            matched_field: i.matched_field,
+            similarity: bd_to_f64(&i.similarity),
+            detected_at: i.detected_at.to_rfc3339(),
+        }
+    }
+}
+
+/// A token resembling the requested one, with what matched
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarTokenItem {
+    #[serde(flatten)]
+    pub token: TokenListItem,
+    /// Trigram similarity (0-1) of the closer of name and symbol
+    pub name_similarity: f32,
+    pub same_deployer: bool,
+    pub same_liquidity_band: bool,
+    pub same_holder_band: bool,
+    /// Launched within a day of the requested token
+    pub similar_age: bool,
+    /// Overall match (0-1)
+    pub similarity_score: f64,
+}
+
+impl From<SimilarToken> for SimilarTokenItem {
+    fn from(s: SimilarToken) -> Self {
+        Self {
+            token: s.token.into(),
+            name_similarity: s.name_similarity,
+            same_deployer: s.same_deployer,
+            same_liquidity_band: s.same_liquidity_band,
+            same_holder_band: s.same_holder_band,
+            similar_age: s.similar_age,
+            similarity_score: s.score,
+        }
+    }
+}
+
+/// A token ranked by client-supplied weights
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedTokenItem {
+    #[serde(flatten)]
+    pub token: TokenListItem,
+    /// Weighted score (0-100) under the requested weights
+    pub custom_score: f64,
+}
+
+impl From<RankedToken> for RankedTokenItem {
+    fn from(r: RankedToken) -> Self {
+        Self {
+            token: r.token.into(),
+            custom_score: r.custom_score,
         }
     }
 }
@@ -178,27 +385,47 @@ impl From<Token> for TokenDetail {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SwapItem {
-    pub tx_hash: String,
-    pub wallet_address: String,
+    pub tx_hash: Hash32,
+    pub wallet_address: WalletAddress,
     pub trade_type: String,
-    pub amount_tokens: f64,
-    pub amount_usd: f64,
-    pub price_usd: f64,
+    pub amount_tokens: Decimal,
+    pub amount_usd: Decimal,
+    pub price_usd: Decimal,
     pub is_whale: bool,
     pub timestamp: String,
+    pub tx_index: Option<i32>,
+    /// Share (0-100) of the block's transactions paying less gas; whale swaps only
+    pub gas_price_percentile: Option<i16>,
+    /// Bot indicators: `top_of_block`, `high_gas`, `probable_mev`
+    pub mev_flags: Vec<String>,
+    /// Both legs, amounts normalized by decimals; absent on older swaps
+    pub token_in: Option<Address20>,
+    pub amount_in: Option<Decimal>,
+    pub token_out: Option<Address20>,
+    pub amount_out: Option<Decimal>,
+    /// Estimated LP fee
+    pub fee_usd: Option<Decimal>,
 }
 
 impl From<Swap> for SwapItem {
     fn from(s: Swap) -> Self {
         Self {
             tx_hash: s.tx_hash,
-            wallet_address: s.wallet_address,
+            wallet_address: s.wallet_address.into(),
             trade_type: s.trade_type,
-            amount_tokens: s.amount_tokens.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            amount_usd: s.amount_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            price_usd: s.price_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            amount_tokens: Decimal::of(&s.amount_tokens),
+            amount_usd: Decimal::of(&s.amount_usd),
+            price_usd: Decimal::of(&s.price_usd),
             is_whale: s.is_whale.unwrap_or(false),
             timestamp: s.timestamp.to_rfc3339(),
+            tx_index: s.tx_index,
+            gas_price_percentile: s.gas_price_percentile,
+            mev_flags: s.mev_flags,
+            token_in: s.token_in,
+            amount_in: s.amount_in.map(Decimal),
+            token_out: s.token_out,
+            amount_out: s.amount_out.map(Decimal),
+            fee_usd: s.fee_usd.map(Decimal),
         }
     }
 }
@@ -207,8 +434,8 @@ impl From<Swap> for SwapItem {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HolderItem {
-    pub wallet_address: String,
-    pub balance: f64,
+    pub wallet_address: WalletAddress,
+    pub balance: Decimal,
     pub percent_of_supply: f64,
     pub is_dev: bool,
     pub is_sniper: bool,
@@ -217,8 +444,8 @@ pub struct HolderItem {
 impl From<TokenHolder> for HolderItem {
     fn from(h: TokenHolder) -> Self {
         Self {
-            wallet_address: h.wallet_address,
-            balance: h.balance.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            wallet_address: h.wallet_address.into(),
+            balance: Decimal::of(&h.balance),
             percent_of_supply: h.percent_of_supply.as_ref().map(bd_to_f64).unwrap_or(0.0),
             is_dev: h.is_dev.unwrap_or(false),
             is_sniper: h.is_sniper.unwrap_or(false),
@@ -226,145 +453,812 @@ impl From<TokenHolder> for HolderItem {
     }
 }
 
+/// A top-10 holder as it stood when a score was computed
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoredHolderItem {
+    pub wallet_address: WalletAddress,
+    pub balance: Decimal,
+    pub percent_of_supply: Option<f64>,
+}
+
+impl From<HolderShare> for ScoredHolderItem {
+    fn from(h: HolderShare) -> Self {
+        Self {
+            wallet_address: h.address.into(),
+            balance: Decimal(h.balance.parse().unwrap_or_default()),
+            percent_of_supply: h.percent,
+        }
+    }
+}
+
+/// Score history response item
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreHistoryItem {
+    pub bee_score: i16,
+    pub safety_score: i16,
+    pub traction_score: i16,
+    pub top10_holder_percent: Option<f64>,
+    /// The top-10 holder set the score saw, largest first
+    pub top_holders: Vec<ScoredHolderItem>,
+    pub computed_at: String,
+}
+
+impl From<ScoreHistory> for ScoreHistoryItem {
+    fn from(s: ScoreHistory) -> Self {
+        Self {
+            bee_score: s.bee_score,
+            safety_score: s.safety_score,
+            traction_score: s.traction_score,
+            top10_holder_percent: s.top_10_holder_percent.as_ref().map(bd_to_f64),
+            top_holders: s.top_holders.0.into_iter().map(Into::into).collect(),
+            computed_at: s.computed_at.to_rfc3339(),
+        }
+    }
+}
+
+/// BeeScore explanation: the latest breakdown and what to watch
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreExplanation {
+    pub bee_score: i16,
+    pub safety_score: i16,
+    pub traction_score: i16,
+    pub computed_at: String,
+    /// Safety components first, then traction
+    pub breakdown: Vec<ScoreComponent>,
+    /// When the computation the suggestions compare to was made
+    pub compared_to: Option<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// Sniper response item
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SniperItem {
+    pub wallet_address: WalletAddress,
+    pub balance: Decimal,
+    pub percent_of_supply: f64,
+    pub first_buy_block: Option<i64>,
+    pub blocks_after_launch: Option<i64>,
+}
+
+impl SniperItem {
+    fn new(h: TokenHolder, launch_block: Option<i64>) -> Self {
+        Self {
+            wallet_address: h.wallet_address.into(),
+            balance: Decimal::of(&h.balance),
+            percent_of_supply: h.percent_of_supply.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            first_buy_block: h.first_buy_block,
+            blocks_after_launch: h.first_buy_block.zip(launch_block).map(|(b, l)| b - l),
+        }
+    }
+}
+
+/// Snipers response: the persisted ratio plus the wallets behind it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnipersResponse {
+    pub sniper_ratio: f64,
+    pub snipers: Vec<SniperItem>,
+}
+
+/// Trades in one day-of-week and hour-of-day slot (UTC)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapCell {
+    /// 1 (Monday) to 7 (Sunday)
+    pub day_of_week: i32,
+    pub hour: i32,
+    pub trades: i64,
+    pub volume_usd: Decimal,
+}
+
+impl From<ActivityCell> for HeatmapCell {
+    fn from(c: ActivityCell) -> Self {
+        Self {
+            day_of_week: c.day_of_week,
+            hour: c.hour,
+            trades: c.trades,
+            volume_usd: Decimal(c.volume_usd),
+        }
+    }
+}
+
+/// A token's trading activity by hour of the week; volume stuck to the
+/// same few slots every day points at a scheduled bot
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityHeatmap {
+    pub since: String,
+    pub trades: i64,
+    pub volume_usd: Decimal,
+    /// All 168 slots, Monday 00:00 first, empty ones included
+    pub cells: Vec<HeatmapCell>,
+}
+
+/// Share card / link preview data for a token
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCard {
+    pub address: Address20,
+    pub name: String,
+    pub symbol: String,
+    pub price: Decimal,
+    pub price_change24h: f64,
+    pub market_cap: Decimal,
+    pub bee_score: i16,
+    /// Hourly USD prices over the last 24h, oldest first
+    pub sparkline: Vec<f64>,
+    pub chain: String,
+}
+
+impl TokenCard {
+    fn new(t: Token, sparkline: Vec<f64>) -> Self {
+        Self {
+            address: t.address,
+            name: t.name.unwrap_or_else(|| "Unknown".to_string()),
+            symbol: t.symbol.unwrap_or_else(|| "???".to_string()),
+            price: Decimal::of(&t.price_usd),
+            price_change24h: t.price_change_24h.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            market_cap: Decimal::of(&t.market_cap_usd),
+            bee_score: t.bee_score.unwrap_or(0),
+            sparkline,
+            chain: "BSC".to_string(),
+        }
+    }
+}
+
 /// Chart data point
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChartDataPoint {
     pub timestamp: String,
-    pub price_usd: f64,
-    pub liquidity_usd: f64,
-    pub volume_usd: f64,
+    /// Last price at this point (or in the interval)
+    pub price_usd: Decimal,
+    /// Average price over the interval; bucketed points only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_price_usd: Option<Decimal>,
+    /// Highest liquidity in the interval
+    pub liquidity_usd: Decimal,
+    /// Summed over the interval
+    pub volume_usd: Decimal,
+    /// `snapshot`, `swaps` for points synthesized from trades (no liquidity),
+    /// or `filled` for empty intervals carrying the previous price forward
+    pub source: &'static str,
 }
 
 impl From<PriceSnapshot> for ChartDataPoint {
     fn from(s: PriceSnapshot) -> Self {
         Self {
             timestamp: s.timestamp.to_rfc3339(),
-            price_usd: s.price_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            liquidity_usd: s.liquidity_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            volume_usd: s.volume_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            price_usd: Decimal::of(&s.price_usd),
+            avg_price_usd: None,
+            liquidity_usd: Decimal::of(&s.liquidity_usd),
+            volume_usd: Decimal::of(&s.volume_usd),
+            source: "snapshot",
+        }
+    }
+}
+
+impl ChartDataPoint {
+    fn from_bucket(b: PriceBucket, source: &'static str) -> Self {
+        Self {
+            timestamp: b.timestamp.to_rfc3339(),
+            price_usd: Decimal::of(&b.price_usd),
+            avg_price_usd: b.avg_price_usd.map(Decimal),
+            liquidity_usd: Decimal::of(&b.liquidity_usd),
+            volume_usd: Decimal(b.volume_usd),
+            source,
+        }
+    }
+
+    /// Empty interval at `timestamp` holding `previous`'s price and liquidity
+    fn filled(timestamp: chrono::DateTime<Utc>, previous: &ChartDataPoint) -> Self {
+        Self {
+            timestamp: timestamp.to_rfc3339(),
+            price_usd: previous.price_usd.clone(),
+            avg_price_usd: None,
+            liquidity_usd: previous.liquidity_usd.clone(),
+            volume_usd: Decimal::default(),
+            source: "filled",
         }
     }
 }
 
+type TimedPoint = (chrono::DateTime<Utc>, ChartDataPoint);
+
+/// Snapshot points plus swap-derived points for the intervals no snapshot covers
+fn merge_chart(
+    snapshots: Vec<TimedPoint>,
+    swaps: Vec<PriceBucket>,
+    bucket_secs: i64,
+) -> Vec<TimedPoint> {
+    let bucket = |t: &chrono::DateTime<Utc>| t.timestamp().div_euclid(bucket_secs);
+    let covered: HashSet<i64> = snapshots.iter().map(|(t, _)| bucket(t)).collect();
+
+    let mut points: Vec<_> = snapshots
+        .into_iter()
+        .chain(
+            swaps
+                .into_iter()
+                .filter(|b| !covered.contains(&bucket(&b.timestamp)))
+                .map(|b| (b.timestamp, ChartDataPoint::from_bucket(b, "swaps"))),
+        )
+        .collect();
+    points.sort_by_key(|(timestamp, _)| *timestamp);
+    points
+}
+
+/// Evenly spaced points from the first interval with data through `end`
+fn fill_gaps(
+    points: Vec<TimedPoint>,
+    bucket_secs: i64,
+    end: chrono::DateTime<Utc>,
+) -> Vec<ChartDataPoint> {
+    let mut points = points.into_iter().peekable();
+    let Some(mut at) = points.peek().map(|(timestamp, _)| *timestamp) else {
+        return Vec::new();
+    };
+
+    let mut filled: Vec<ChartDataPoint> = Vec::new();
+    while at <= end {
+        match points.next_if(|(timestamp, _)| *timestamp <= at) {
+            Some((_, point)) => filled.push(point),
+            None => {
+                if let Some(previous) = filled.last() {
+                    let carried = ChartDataPoint::filled(at, previous);
+                    filled.push(carried);
+                }
+            }
+        }
+        at += Duration::seconds(bucket_secs);
+    }
+    filled
+}
+
+/// Seconds per chart interval, if one was requested
+fn interval_secs(interval: Option<&str>) -> ApiResult<Option<i64>> {
+    match interval {
+        None => Ok(None),
+        Some("5m") => Ok(Some(300)),
+        Some("15m") => Ok(Some(900)),
+        Some("1h") => Ok(Some(3_600)),
+        Some(other) => Err(ApiError::InvalidQuery(format!(
+            "`interval` must be one of 5m, 15m, 1h; got `{}`",
+            other
+        ))),
+    }
+}
+
 /// Query params for list endpoints
 #[derive(Debug, Deserialize)]
 pub struct ListParams {
     pub limit: Option<i32>,
 }
 
+/// Query params for a token's swap feed
+#[derive(Debug, Deserialize)]
+pub struct SwapParams {
+    pub limit: Option<i32>,
+    #[serde(rename = "type")]
+    pub trade_type: Option<TradeSide>,
+    /// Smallest trade, in USD
+    pub min_usd: Option<String>,
+    #[serde(default)]
+    pub whales_only: bool,
+    /// RFC 3339 timestamp of the oldest trade
+    pub since: Option<chrono::DateTime<Utc>>,
+}
+
+/// Query params for the precomputed token lists
+#[derive(Debug, Deserialize)]
+pub struct TokenListParams {
+    pub limit: Option<i32>,
+    /// Only tokens carrying this tag
+    pub tag: Option<String>,
+    /// Attach 24h hourly price sparklines
+    #[serde(default)]
+    pub sparkline: bool,
+    /// Also list tokens archived as dead, after the live ones
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+/// Query params for a custom token ranking; unset weights keep their defaults
+#[derive(Debug, Deserialize)]
+pub struct RankParams {
+    pub limit: Option<i32>,
+    pub w_volume: Option<f64>,
+    pub w_liquidity: Option<f64>,
+    pub w_holders: Option<f64>,
+    /// BeeScore
+    pub w_score: Option<f64>,
+    /// 24h price change
+    pub w_momentum: Option<f64>,
+}
+
+impl RankParams {
+    /// Weights with the overrides applied: each between 0 and
+    /// [`MAX_RANK_WEIGHT`], and not all zero
+    fn weights(&self) -> ApiResult<RankWeights> {
+        let weight = |name: &str, value: Option<f64>, default: f64| {
+            let value = value.unwrap_or(default);
+            if (0.0..=MAX_RANK_WEIGHT).contains(&value) {
+                Ok(value)
+            } else {
+                Err(ApiError::InvalidQuery(format!(
+                    "`{}` must be between 0 and {}, got {}",
+                    name, MAX_RANK_WEIGHT, value
+                )))
+            }
+        };
+        let weights = RankWeights {
+            volume: weight("w_volume", self.w_volume, 1.0)?,
+            liquidity: weight("w_liquidity", self.w_liquidity, 1.0)?,
+            holders: weight("w_holders", self.w_holders, 1.0)?,
+            bee_score: weight("w_score", self.w_score, 1.0)?,
+            momentum: weight("w_momentum", self.w_momentum, 0.0)?,
+        };
+        if weights.volume
+            + weights.liquidity
+            + weights.holders
+            + weights.bee_score
+            + weights.momentum
+            == 0.0
+        {
+            return Err(ApiError::InvalidQuery(
+                "at least one weight must be above 0".to_string(),
+            ));
+        }
+        Ok(weights)
+    }
+}
+
 /// Query params for chart endpoint
 #[derive(Debug, Deserialize)]
 pub struct ChartParams {
-    pub interval: Option<String>, // "5m", "1h"
+    pub interval: Option<String>, // "5m", "15m", "1h"
     pub range: Option<String>,    // "1h", "6h", "24h"
 }
 
-/// GET /api/tokens/new
-/// Returns newest tokens sorted by created_at
-pub async fn get_new_tokens(
-    State(state): State<Arc<AppState>>,
-    Query(params): Query<ListParams>,
-) -> impl IntoResponse {
+/// Trade direction for quotes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    #[default]
+    Buy,
+    Sell,
+}
+
+/// Query params for quote endpoint
+#[derive(Debug, Deserialize)]
+pub struct QuoteParams {
+    #[serde(default)]
+    pub side: TradeSide,
+    /// Base token (WBNB or a stablecoin) spent (buy)
+    pub amount_bnb: Option<f64>,
+    /// Tokens sold (sell)
+    pub amount_tokens: Option<f64>,
+}
+
+/// Simulated trade against current reserves. The `*Bnb` amounts and prices
+/// are in the pair's base token, WBNB or a stablecoin.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteResponse {
+    pub pair_address: Address20,
+    pub base_token_address: Address20,
+    pub side: TradeSide,
+    pub amount_bnb: f64,
+    pub amount_tokens: f64,
+    /// Base token per token before the trade
+    pub spot_price_bnb: f64,
+    /// Base token per token actually paid/received
+    pub effective_price_bnb: f64,
+    pub effective_price_usd: Option<f64>,
+    pub price_impact_percent: f64,
+    pub fee_percent: f64,
+}
+
+/// Scale a raw on-chain amount down by `decimals`
+fn from_raw(raw: &sqlx::types::BigDecimal, decimals: i16) -> f64 {
+    bd_to_f64(raw) / 10f64.powi(decimals.into())
+}
+
+/// Read a precomputed list, optionally filtered by tag, with each token's tags
+async fn list_tokens(
+    state: &AppState,
+    list: TokenList,
+    params: TokenListParams,
+    shape: ResponseShape,
+) -> ApiResult<Listing<TokenListItem>> {
     let limit = params.limit.unwrap_or(50).min(100);
 
-    match Token::find_newest(limit, &state.db_pool).await {
-        Ok(tokens) => {
-            let items: Vec<TokenListItem> = tokens.into_iter().map(Into::into).collect();
-            Json(items).into_response()
+    let tokens = match params.tag.as_deref() {
+        Some(tag) => {
+            list.find_tagged(tag, limit, params.include_archived, &state.db_pool)
+                .await?
         }
-        Err(e) => {
-            tracing::error!("Failed to get new tokens: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        None => {
+            list.find(limit, params.include_archived, &state.db_pool)
+                .await?
+        }
+    };
+    let addresses: Vec<Address20> = tokens.iter().map(|t| t.address).collect();
+    let mut tags = tags_by_address(TagSubject::Token, &addresses, &state.db_pool).await?;
+    let mut sparklines = if params.sparkline {
+        let now = Utc::now();
+        let first_hour = now.duration_trunc(Duration::hours(1)).unwrap_or(now)
+            - Duration::hours(SPARKLINE_HOURS as i64 - 1);
+        let closes =
+            PriceSnapshot::find_hourly_closes(&addresses, first_hour, now, &state.db_pool).await?;
+        Some(sparklines(closes, first_hour))
+    } else {
+        None
+    };
+
+    shape
+        .list(
+            tokens
+                .into_iter()
+                .map(|t| {
+                    let address = t.address;
+                    TokenListItem {
+                        tags: tags.remove(&address).unwrap_or_default(),
+                        sparkline: sparklines.as_mut().map(|s| {
+                            s.remove(&address)
+                                .unwrap_or_else(|| vec![None; SPARKLINE_HOURS])
+                        }),
+                        ..t.into()
+                    }
+                })
+                .collect(),
+            &state.db_pool,
+        )
+        .await
+}
+
+/// One price per hour, carrying the last close through hours without
+/// snapshots. Hours before a token's first close stay `None`.
+fn sparklines(
+    closes: Vec<HourlyClose>,
+    first_hour: chrono::DateTime<Utc>,
+) -> HashMap<Address20, Vec<Option<f64>>> {
+    let mut hourly: HashMap<Address20, Vec<Option<f64>>> = HashMap::new();
+    for close in closes {
+        let slot = (close.hour - first_hour).num_hours();
+        if !(0..SPARKLINE_HOURS as i64).contains(&slot) {
+            continue;
         }
+        hourly
+            .entry(close.token_address)
+            .or_insert_with(|| vec![None; SPARKLINE_HOURS])[slot as usize] =
+            Some(bd_to_f64(&close.price_usd));
     }
+
+    for prices in hourly.values_mut() {
+        let mut last = None;
+        for price in prices.iter_mut() {
+            match price {
+                Some(p) => last = Some(*p),
+                None => *price = last,
+            }
+        }
+    }
+    hourly
+}
+
+/// GET /api/tokens/new
+/// Returns newest tokens sorted by created_at (precomputed list)
+pub async fn get_new_tokens(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<TokenListParams>,
+) -> ApiResult<Listing<TokenListItem>> {
+    list_tokens(&state, TokenList::New, params, shape).await
 }
 
 /// GET /api/tokens/hot
-/// Returns hot tokens sorted by volume + BeeScore
+/// Returns hot tokens sorted by volume + BeeScore (precomputed list)
 pub async fn get_hot_tokens(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<ListParams>,
-) -> impl IntoResponse {
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<TokenListParams>,
+) -> ApiResult<Listing<TokenListItem>> {
+    list_tokens(&state, TokenList::Hot, params, shape).await
+}
+
+/// GET /api/tokens/trending
+/// Returns tokens with the strongest 1h price momentum (precomputed list)
+pub async fn get_trending_tokens(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<TokenListParams>,
+) -> ApiResult<Listing<TokenListItem>> {
+    list_tokens(&state, TokenList::Trending, params, shape).await
+}
+
+/// GET /api/tokens/ranked
+/// Returns tokens that traded in the last 24h ranked by the client's own
+/// weighting of volume, liquidity, holders, BeeScore and momentum
+pub async fn get_ranked_tokens(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<RankParams>,
+) -> ApiResult<Listing<RankedTokenItem>> {
     let limit = params.limit.unwrap_or(50).min(100);
+    let weights = params.weights()?;
 
-    match Token::find_hot(limit, &state.db_pool).await {
-        Ok(tokens) => {
-            let items: Vec<TokenListItem> = tokens.into_iter().map(Into::into).collect();
-            Json(items).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Failed to get hot tokens: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
-    }
+    let ranked = Token::find_ranked(&weights, limit, &state.db_pool).await?;
+    let addresses: Vec<Address20> = ranked.iter().map(|r| r.token.address).collect();
+    let mut tags = tags_by_address(TagSubject::Token, &addresses, &state.db_pool).await?;
+
+    shape
+        .list(
+            ranked
+                .into_iter()
+                .map(|r| {
+                    let mut item = RankedTokenItem::from(r);
+                    item.token.tags = tags.remove(&item.token.address).unwrap_or_default();
+                    item
+                })
+                .collect(),
+            &state.db_pool,
+        )
+        .await
 }
 
 /// GET /api/tokens/:address
 /// Returns full token details
 pub async fn get_token(
     State(state): State<Arc<AppState>>,
-    Path(address): Path<String>,
-) -> impl IntoResponse {
-    match Token::find_by_address(&address, &state.db_pool).await {
-        Ok(Some(token)) => Json(TokenDetail::from(token)).into_response(),
-        Ok(None) => (StatusCode::NOT_FOUND, "Token not found").into_response(),
-        Err(e) => {
-            tracing::error!("Failed to get token: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+    address: EvmAddress,
+) -> ApiResult<Json<TokenDetail>> {
+    match Token::find_by_address(&address, &state.db_pool).await? {
+        Some(token) => {
+            let tags = tags_of(TagSubject::Token, &address, &state.db_pool).await?;
+            let decimals = token.decimals.unwrap_or(18);
+            let (inflow, outflow) = WalletActivity::cex_flows(
+                &address,
+                Utc::now() - Duration::hours(24),
+                &state.db_pool,
+            )
+            .await?;
+            let impersonation = TokenImpersonation::find_by_token(&address, &state.db_pool).await?;
+            Ok(Json(TokenDetail {
+                tags,
+                cex_inflow24h: from_raw(&inflow, decimals),
+                cex_outflow24h: from_raw(&outflow, decimals),
+                impersonation: impersonation.map(Into::into),
+                ..token.into()
+            }))
         }
+        None => Err(ApiError::TokenNotFound(address.to_string())),
     }
 }
 
 /// GET /api/tokens/:address/swaps
-/// Returns recent swaps for a token
+/// Returns recent swaps for a token, optionally only one side, trades of at
+/// least `min_usd`, whale trades, or trades since a time
 pub async fn get_token_swaps(
     State(state): State<Arc<AppState>>,
-    Path(address): Path<String>,
-    Query(params): Query<ListParams>,
-) -> impl IntoResponse {
+    shape: ResponseShape,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<SwapParams>,
+) -> ApiResult<Listing<SwapItem>> {
     let limit = params.limit.unwrap_or(100).min(500);
+    let min_usd = params
+        .min_usd
+        .map(|v| {
+            v.parse::<sqlx::types::BigDecimal>().map_err(|_| {
+                ApiError::InvalidQuery(format!("`min_usd` must be a number, got `{}`", v))
+            })
+        })
+        .transpose()?;
+    let filter = SwapFilter {
+        trade_type: params.trade_type.map(|side| match side {
+            TradeSide::Buy => "buy".to_string(),
+            TradeSide::Sell => "sell".to_string(),
+        }),
+        min_usd,
+        whales_only: params.whales_only,
+        since: params.since,
+    };
 
-    match Swap::find_by_token(&address, limit, &state.db_pool).await {
-        Ok(swaps) => {
-            let items: Vec<SwapItem> = swaps.into_iter().map(Into::into).collect();
-            Json(items).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Failed to get token swaps: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
-    }
+    let swaps = Swap::find_by_token(&address, &filter, limit, &state.db_pool).await?;
+    shape
+        .list(swaps.into_iter().map(Into::into).collect(), &state.db_pool)
+        .await
 }
 
 /// GET /api/tokens/:address/holders
 /// Returns top holders for a token
 pub async fn get_token_holders(
     State(state): State<Arc<AppState>>,
-    Path(address): Path<String>,
-    Query(params): Query<ListParams>,
-) -> impl IntoResponse {
+    shape: ResponseShape,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<ListParams>,
+) -> ApiResult<Listing<HolderItem>> {
     let limit = params.limit.unwrap_or(20).min(100);
 
-    match TokenHolder::find_top_holders(&address, limit, &state.db_pool).await {
-        Ok(holders) => {
-            let items: Vec<HolderItem> = holders.into_iter().map(Into::into).collect();
-            Json(items).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Failed to get token holders: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
-    }
+    let holders = TokenHolder::find_top_holders(&address, limit, &state.db_pool).await?;
+    shape
+        .list(
+            holders.into_iter().map(Into::into).collect(),
+            &state.db_pool,
+        )
+        .await
+}
+
+/// GET /api/tokens/:address/scores
+/// Returns past BeeScore computations with the top holders each one saw,
+/// newest first
+pub async fn get_token_scores(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<ListParams>,
+) -> ApiResult<Listing<ScoreHistoryItem>> {
+    let limit = params.limit.unwrap_or(50).min(500);
+
+    Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .ok_or_else(|| ApiError::TokenNotFound(address.to_string()))?;
+
+    let history = ScoreHistory::find_by_token(&address, limit, &state.db_pool).await?;
+    shape
+        .list(
+            history.into_iter().map(Into::into).collect(),
+            &state.db_pool,
+        )
+        .await
+}
+
+/// GET /api/tokens/:address/score
+/// Returns the latest BeeScore breakdown with suggestions drawn from it, the
+/// token's LP unlock and how the score moved over the last day
+pub async fn get_token_score(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+) -> ApiResult<Json<ScoreExplanation>> {
+    Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .ok_or_else(|| ApiError::TokenNotFound(address.to_string()))?;
+
+    let latest = ScoreHistory::find_by_token(&address, 1, &state.db_pool)
+        .await?
+        .pop()
+        .ok_or_else(|| ApiError::NotScored(address.to_string()))?;
+    let baseline = ScoreHistory::find_at_or_before(
+        &address,
+        latest.computed_at - Duration::hours(SCORE_BASELINE_HOURS),
+        &state.db_pool,
+    )
+    .await?;
+    let lp_unlock = LpLock::earliest_unlock(&address, &state.db_pool).await?;
+
+    let suggestions = score_advice::suggestions(&latest, baseline.as_ref(), lp_unlock, Utc::now());
+    Ok(Json(ScoreExplanation {
+        bee_score: latest.bee_score,
+        safety_score: latest.safety_score,
+        traction_score: latest.traction_score,
+        computed_at: latest.computed_at.to_rfc3339(),
+        breakdown: latest.breakdown.0,
+        compared_to: baseline.map(|b| b.computed_at.to_rfc3339()),
+        suggestions,
+    }))
+}
+
+/// GET /api/tokens/:address/snipers
+/// Returns wallets that bought within the sniper window, largest first
+pub async fn get_token_snipers(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<ListParams>,
+) -> ApiResult<Json<SnipersResponse>> {
+    let limit = params.limit.unwrap_or(20).min(100);
+
+    let token = Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .ok_or_else(|| ApiError::TokenNotFound(address.to_string()))?;
+
+    let snipers = TokenHolder::find_sniper_holders(&address, limit, &state.db_pool).await?;
+    Ok(Json(SnipersResponse {
+        sniper_ratio: token.sniper_ratio.as_ref().map(bd_to_f64).unwrap_or(0.0),
+        snipers: snipers
+            .into_iter()
+            .map(|h| SniperItem::new(h, token.block_number))
+            .collect(),
+    }))
+}
+
+/// GET /api/tokens/:address/activity-heatmap
+/// Returns the last 30 days of trades bucketed by day of week and hour of day
+pub async fn get_token_activity_heatmap(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+) -> ApiResult<Json<ActivityHeatmap>> {
+    Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .ok_or_else(|| ApiError::TokenNotFound(address.to_string()))?;
+
+    let since = Utc::now() - Duration::days(HEATMAP_DAYS);
+    let mut active: HashMap<(i32, i32), ActivityCell> =
+        Swap::activity_by_hour(&address, since, &state.db_pool)
+            .await?
+            .into_iter()
+            .map(|c| ((c.day_of_week, c.hour), c))
+            .collect();
+
+    let trades = active.values().map(|c| c.trades).sum();
+    let volume_usd = active.values().map(|c| c.volume_usd.clone()).sum();
+    let cells = (1..=7)
+        .flat_map(|day| (0..24).map(move |hour| (day, hour)))
+        .map(|(day, hour)| {
+            active
+                .remove(&(day, hour))
+                .map(HeatmapCell::from)
+                .unwrap_or(HeatmapCell {
+                    day_of_week: day,
+                    hour,
+                    trades: 0,
+                    volume_usd: Decimal(0.into()),
+                })
+        })
+        .collect();
+
+    Ok(Json(ActivityHeatmap {
+        since: since.to_rfc3339(),
+        trades,
+        volume_usd: Decimal(volume_usd),
+        cells,
+    }))
+}
+
+/// GET /api/tokens/:address/similar
+/// Returns tokens with a similar launch profile (name, deployer, liquidity,
+/// holders, launch time), best match first, to flag copycat launches
+pub async fn get_similar_tokens(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<ListParams>,
+) -> ApiResult<Listing<SimilarTokenItem>> {
+    let limit = params.limit.unwrap_or(10).min(50);
+
+    Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .ok_or_else(|| ApiError::TokenNotFound(address.to_string()))?;
+
+    let similar = Token::find_similar(&address, limit, &state.db_pool).await?;
+    let addresses: Vec<Address20> = similar.iter().map(|s| s.token.address).collect();
+    let mut tags = tags_by_address(TagSubject::Token, &addresses, &state.db_pool).await?;
+
+    shape
+        .list(
+            similar
+                .into_iter()
+                .map(|s| {
+                    let mut item = SimilarTokenItem::from(s);
+                    item.token.tags = tags.remove(&item.token.address).unwrap_or_default();
+                    item
+                })
+                .collect(),
+            &state.db_pool,
+        )
+        .await
 }
 
 /// GET /api/tokens/:address/chart
 /// Returns price snapshots for charting
 pub async fn get_token_chart(
     State(state): State<Arc<AppState>>,
-    Path(address): Path<String>,
-    Query(params): Query<ChartParams>,
-) -> impl IntoResponse {
+    shape: ResponseShape,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<ChartParams>,
+) -> ApiResult<Listing<ChartDataPoint>> {
     let range = params.range.unwrap_or_else(|| "24h".to_string());
 
     let hours = match range.as_str() {
@@ -378,14 +1272,146 @@ pub async fn get_token_chart(
     let start = Utc::now() - Duration::hours(hours);
     let end = Utc::now();
 
-    match PriceSnapshot::find_in_range(&address, start, end, &state.db_pool).await {
-        Ok(snapshots) => {
-            let items: Vec<ChartDataPoint> = snapshots.into_iter().map(Into::into).collect();
-            Json(items).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Failed to get chart data: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
+    let bucket_secs = interval_secs(params.interval.as_deref())?;
+
+    let points = chart_points(&state, &address, start, end, bucket_secs).await?;
+    shape.list(points, &state.db_pool).await
+}
+
+/// Chart points from `start` to `end`: raw snapshots, or evenly spaced
+/// intervals of `bucket_secs`
+async fn chart_points(
+    state: &AppState,
+    address: &Address20,
+    start: chrono::DateTime<Utc>,
+    end: chrono::DateTime<Utc>,
+    bucket_secs: Option<i64>,
+) -> ApiResult<Vec<ChartDataPoint>> {
+    let snapshots: Vec<TimedPoint> = match bucket_secs {
+        Some(secs) => PriceSnapshot::find_bucketed(address, start, end, secs, &state.db_pool)
+            .await?
+            .into_iter()
+            .map(|b| (b.timestamp, ChartDataPoint::from_bucket(b, "snapshot")))
+            .collect(),
+        None => PriceSnapshot::find_in_range(address, start, end, &state.db_pool)
+            .await?
+            .into_iter()
+            .map(|s| (s.timestamp, s.into()))
+            .collect(),
+    };
+
+    // Brand-new tokens have few snapshots yet; fill in from their trades
+    let swap_bucket_secs = bucket_secs.unwrap_or(60);
+    let swaps = if snapshots.len() < MIN_SNAPSHOT_POINTS {
+        Swap::price_points(address, start, end, swap_bucket_secs, &state.db_pool).await?
+    } else {
+        Vec::new()
+    };
+    let points = merge_chart(snapshots, swaps, swap_bucket_secs);
+
+    Ok(match bucket_secs {
+        Some(secs) => fill_gaps(points, secs, end),
+        None => points.into_iter().map(|(_, point)| point).collect(),
+    })
+}
+
+/// GET /api/tokens/:address/card
+/// Returns the few fields a social share card or bot link preview shows,
+/// with an hourly 24h price sparkline
+pub async fn get_token_card(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+) -> ApiResult<Json<TokenCard>> {
+    let token = Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .ok_or_else(|| ApiError::TokenNotFound(address.to_string()))?;
+
+    let end = Utc::now();
+    let sparkline = chart_points(&state, &address, end - Duration::hours(24), end, Some(3_600))
+        .await?
+        .iter()
+        .map(|point| bd_to_f64(&point.price_usd.0))
+        .collect();
+
+    Ok(Json(TokenCard::new(token, sparkline)))
+}
+
+/// GET /api/tokens/:address/quote
+/// Simulates a trade against the pair's current reserves (x*y=k with the pair fee)
+pub async fn get_token_quote(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<QuoteParams>,
+) -> ApiResult<Json<QuoteResponse>> {
+    let amount_in = match params.side {
+        TradeSide::Buy => params.amount_bnb.ok_or_else(|| {
+            ApiError::InvalidQuery("`amount_bnb` is required for buys".to_string())
+        })?,
+        TradeSide::Sell => params.amount_tokens.ok_or_else(|| {
+            ApiError::InvalidQuery("`amount_tokens` is required for sells".to_string())
+        })?,
+    };
+    if !(amount_in > 0.0 && amount_in.is_finite()) {
+        return Err(ApiError::InvalidQuery(
+            "Trade amount must be a positive number".to_string(),
+        ));
     }
+
+    let token = Token::find_by_address(&address, &state.db_pool)
+        .await?
+        .ok_or_else(|| ApiError::TokenNotFound(address.to_string()))?;
+
+    let pair = match token.pair_address {
+        Some(pair_address) => Pair::find_by_address(&pair_address, &state.db_pool).await?,
+        None => None,
+    };
+    let (pair, base_reserve, token_reserve) = pair
+        .as_ref()
+        .and_then(|p| p.get_reserves().map(|(b, t)| (p, b, t)))
+        .ok_or_else(|| ApiError::NoLiquidity(address.to_string()))?;
+
+    // The chain's base tokens have 18 decimals unless the indexed token says
+    // otherwise
+    let base_address = *pair.get_base_address();
+    let base_decimals = Token::find_by_address(&base_address, &state.db_pool)
+        .await?
+        .and_then(|base| base.decimals)
+        .unwrap_or(18);
+    let bnb_reserve = from_raw(base_reserve, base_decimals);
+    let token_reserve = from_raw(token_reserve, token.decimals.unwrap_or(18));
+
+    let (reserve_in, reserve_out) = match params.side {
+        TradeSide::Buy => (bnb_reserve, token_reserve),
+        TradeSide::Sell => (token_reserve, bnb_reserve),
+    };
+    let q = amm::quote(reserve_in, reserve_out, amount_in, PANCAKE_V2_FEE_BPS)
+        .ok_or_else(|| ApiError::NoLiquidity(address.to_string()))?;
+    let (amount_bnb, amount_tokens) = match params.side {
+        TradeSide::Buy => (q.amount_in, q.amount_out),
+        TradeSide::Sell => (q.amount_out, q.amount_in),
+    };
+
+    let effective_price_bnb = amount_bnb / amount_tokens;
+
+    // BNB/USD implied by the token's last recorded prices
+    let bnb_price_usd = token
+        .price_usd
+        .as_ref()
+        .zip(token.price_bnb.as_ref())
+        .map(|(usd, bnb)| (bd_to_f64(usd), bd_to_f64(bnb)))
+        .filter(|(_, bnb)| *bnb > 0.0)
+        .map(|(usd, bnb)| usd / bnb);
+
+    Ok(Json(QuoteResponse {
+        pair_address: pair.address,
+        base_token_address: base_address,
+        side: params.side,
+        amount_bnb,
+        amount_tokens,
+        spot_price_bnb: bnb_reserve / token_reserve,
+        effective_price_bnb,
+        effective_price_usd: bnb_price_usd.map(|p| effective_price_bnb * p),
+        price_impact_percent: q.price_impact_percent,
+        fee_percent: PANCAKE_V2_FEE_BPS as f64 / 100.0,
+    }))
 }
@@ -1,46 +1,84 @@
 //! Wallet API routes
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
     Json,
 };
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use sqlx::types::BigDecimal;
 
-use indexer_db::entity::{
-    wallet::{NewWallet, Wallet, WalletWithStats},
-    wallet_activity::WalletActivity,
+use indexer_db::{
+    entity::{
+        swap::{Swap, WalletTradeSummary},
+        tag::TagSubject,
+        wallet::{NewWallet, Wallet, WalletWithStats},
+        wallet_activity::WalletActivity,
+        wallet_profile::WalletProfile,
+        wallet_suggestion::WalletSuggestion,
+    },
+    Address20,
 };
 
-use crate::AppState;
-
-/// Helper to convert BigDecimal to f64
-fn bd_to_f64(bd: &sqlx::types::BigDecimal) -> f64 {
-    bd.to_string().parse().unwrap_or(0.0)
-}
+use crate::{
+    address::EvmAddress,
+    decimal::Decimal,
+    demo::WalletAddress,
+    envelope::{Listing, ResponseShape},
+    error::{ApiError, ApiJson, ApiQuery, ApiResult},
+    routes::tags::{tags_by_address, tags_of},
+    AppState,
+};
 
 /// Wallet list response item - matches frontend Wallet interface
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WalletItem {
-    pub address: String,
+    pub address: WalletAddress,
     pub label: Option<String>,
     pub token_count: i64,
-    pub estimated_value: f64,
+    pub estimated_value: Decimal,
     pub last_activity: Option<String>,
+    pub tags: Vec<String>,
+    /// First swap or transfer of an indexed token the wallet appeared in
+    pub first_seen_block: Option<i64>,
+    pub first_seen_at: Option<String>,
+    /// Whole days since `firstSeenAt`: how long the index has known the
+    /// wallet, not its on-chain age
+    pub indexed_age_days: Option<i64>,
 }
 
 impl From<WalletWithStats> for WalletItem {
     fn from(w: WalletWithStats) -> Self {
         Self {
-            address: w.address,
+            address: w.address.into(),
             label: w.label,
             token_count: w.token_count,
-            estimated_value: w.estimated_value_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            estimated_value: Decimal::of(&w.estimated_value_usd),
             last_activity: w.last_activity.map(|dt| dt.to_rfc3339()),
+            tags: Vec::new(),
+            first_seen_block: None,
+            first_seen_at: None,
+            indexed_age_days: None,
+        }
+    }
+}
+
+impl WalletItem {
+    /// Attach when the wallet was first seen, if it ever was
+    fn with_profile(self, profile: Option<WalletProfile>) -> Self {
+        match profile {
+            Some(p) => Self {
+                first_seen_block: Some(p.first_seen_block),
+                first_seen_at: Some(p.first_seen_at.to_rfc3339()),
+                indexed_age_days: Some((Utc::now() - p.first_seen_at).num_days().max(0)),
+                ..self
+            },
+            None => self,
         }
     }
 }
@@ -48,11 +86,15 @@ impl From<WalletWithStats> for WalletItem {
 impl From<Wallet> for WalletItem {
     fn from(w: Wallet) -> Self {
         Self {
-            address: w.address,
+            address: w.address.into(),
             label: w.label,
             token_count: w.token_count.unwrap_or(0) as i64,
-            estimated_value: w.estimated_value_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            estimated_value: Decimal::of(&w.estimated_value_usd),
             last_activity: w.last_activity.map(|dt| dt.to_rfc3339()),
+            tags: Vec::new(),
+            first_seen_block: None,
+            first_seen_at: None,
+            indexed_age_days: None,
         }
     }
 }
@@ -62,12 +104,12 @@ impl From<Wallet> for WalletItem {
 #[serde(rename_all = "camelCase")]
 pub struct WalletActivityItem {
     pub id: String,
-    pub wallet_address: String,
+    pub wallet_address: WalletAddress,
     pub action: String,
-    pub token_address: String,
+    pub token_address: Address20,
     pub token_symbol: String,
-    pub amount: f64,
-    pub value: f64,
+    pub amount: Decimal,
+    pub value: Decimal,
     pub timestamp: String,
 }
 
@@ -75,89 +117,407 @@ impl From<WalletActivity> for WalletActivityItem {
     fn from(a: WalletActivity) -> Self {
         Self {
             id: a.id.to_string(),
-            wallet_address: a.wallet_address,
+            wallet_address: a.wallet_address.into(),
             action: a.action,
             token_address: a.token_address,
             token_symbol: a.token_symbol.unwrap_or_else(|| "???".to_string()),
-            amount: a.amount_tokens.as_ref().map(bd_to_f64).unwrap_or(0.0),
-            value: a.amount_usd.as_ref().map(bd_to_f64).unwrap_or(0.0),
+            amount: Decimal::of(&a.amount_tokens),
+            value: Decimal::of(&a.amount_usd),
             timestamp: a.timestamp.to_rfc3339(),
         }
     }
 }
 
+/// A wallet the nightly analysis suggests tracking; POST `address` and
+/// `suggestedLabel` to `/api/wallets` to add it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletSuggestionItem {
+    pub address: WalletAddress,
+    /// `top_pnl` and/or `early_buyer`
+    pub reasons: Vec<String>,
+    pub suggested_label: String,
+    /// USD sold minus USD bought over the last days
+    pub pnl_usd: Decimal,
+    /// Tokens bought right after launch that later scored high
+    pub early_win_tokens: Vec<Address20>,
+    pub analyzed_at: String,
+}
+
+impl From<WalletSuggestion> for WalletSuggestionItem {
+    fn from(s: WalletSuggestion) -> Self {
+        let suggested_label = s
+            .reasons
+            .iter()
+            .map(|reason| match reason.as_str() {
+                "top_pnl" => "Top trader",
+                "early_buyer" => "Early buyer",
+                _ => "Suggested",
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Self {
+            address: s.wallet_address.into(),
+            reasons: s.reasons,
+            suggested_label,
+            pnl_usd: Decimal(s.pnl_usd),
+            early_win_tokens: s.early_win_tokens,
+            analyzed_at: s.analyzed_at.to_rfc3339(),
+        }
+    }
+}
+
+/// A token a wallet made or lost the most on
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPnl {
+    pub token_address: Address20,
+    /// Realized against the average buy price
+    pub pnl_usd: Decimal,
+}
+
+/// A wallet's trading across every token, from its swaps kept by retention
+/// (`SWAP_RETENTION_DAYS`, 90 days by default)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletTradingProfile {
+    pub address: WalletAddress,
+    pub trades: i64,
+    pub lifetime_volume_usd: Decimal,
+    pub tokens_traded: i64,
+    /// Mean seconds from a token's first buy to the first sell after it
+    pub avg_hold_secs: Option<i64>,
+    /// Share (0-100) of the tokens it sold some of that it sold at a profit;
+    /// `None` until it has sold any
+    pub win_rate: Option<f64>,
+    pub biggest_win: Option<TokenPnl>,
+    pub biggest_loss: Option<TokenPnl>,
+    pub whale_trades: i64,
+    /// When this was computed; repeated requests get it until it is
+    /// `WALLET_PROFILE_CACHE_SECS` old
+    pub computed_at: String,
+}
+
+impl WalletTradingProfile {
+    fn of(address: Address20, s: WalletTradeSummary) -> Self {
+        let pnl = |token: Option<Address20>, usd: Option<BigDecimal>| {
+            token.zip(usd).map(|(token_address, usd)| TokenPnl {
+                token_address,
+                pnl_usd: Decimal(usd),
+            })
+        };
+
+        Self {
+            address: address.into(),
+            trades: s.trades,
+            lifetime_volume_usd: Decimal(s.volume_usd),
+            tokens_traded: s.tokens_traded,
+            avg_hold_secs: s.avg_hold_secs.map(|secs| secs.round() as i64),
+            win_rate: (s.tokens_sold > 0)
+                .then(|| (s.tokens_won as f64 * 10_000.0 / s.tokens_sold as f64).round() / 100.0),
+            biggest_win: pnl(s.biggest_win_token, s.biggest_win_usd),
+            biggest_loss: pnl(s.biggest_loss_token, s.biggest_loss_usd),
+            whale_trades: s.whale_trades,
+            computed_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
 /// Query params for list endpoints
 #[derive(Debug, Deserialize)]
 pub struct ListParams {
     pub limit: Option<i32>,
 }
 
+/// Query params for the wallet list
+#[derive(Debug, Deserialize)]
+pub struct WalletListParams {
+    pub limit: Option<i32>,
+    /// Only wallets carrying this tag
+    pub tag: Option<String>,
+}
+
 /// Request body for creating a wallet
 #[derive(Debug, Deserialize)]
 pub struct CreateWalletRequest {
+    pub address: EvmAddress,
+    pub label: Option<String>,
+}
+
+/// Most rows accepted by one bulk import
+const MAX_BULK_WALLETS: usize = 1000;
+
+/// Longest label the wallets table stores
+const MAX_LABEL_LEN: usize = 255;
+
+/// One row of a bulk import; validated per row so one bad address doesn't
+/// reject the whole upload
+#[derive(Debug, Deserialize)]
+pub struct BulkWalletRow {
     pub address: String,
     pub label: Option<String>,
 }
 
+/// Outcome of one bulk import row
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkWalletResult {
+    /// 1-based position in the upload (CSV header excluded)
+    pub row: usize,
+    pub address: String,
+    /// created, updated, duplicate or invalid
+    pub status: &'static str,
+    pub error: Option<String>,
+}
+
+/// Bulk import summary
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkWalletResponse {
+    pub created: usize,
+    pub updated: usize,
+    pub failed: usize,
+    pub results: Vec<BulkWalletResult>,
+}
+
+/// Split one CSV line into fields, honouring double-quoted fields
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// Parse `address,label` lines; a leading `address` header is skipped
+fn parse_csv(text: &str) -> Vec<BulkWalletRow> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(csv_fields)
+        .enumerate()
+        .filter(|(i, fields)| !(*i == 0 && fields[0].eq_ignore_ascii_case("address")))
+        .map(|(_, mut fields)| BulkWalletRow {
+            label: fields.get(1).filter(|l| !l.is_empty()).cloned(),
+            address: std::mem::take(&mut fields[0]),
+        })
+        .collect()
+}
+
+/// When a wallet was first seen, if it ever was
+async fn wallet_profile(address: &Address20, state: &AppState) -> ApiResult<Option<WalletProfile>> {
+    Ok(
+        WalletProfile::find_by_addresses(&[*address], &state.db_pool)
+            .await?
+            .pop(),
+    )
+}
+
 /// GET /api/wallets
 /// Returns list of all tracked wallets with computed stats
 pub async fn get_wallets(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<ListParams>,
-) -> impl IntoResponse {
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<WalletListParams>,
+) -> ApiResult<Listing<WalletItem>> {
     let limit = params.limit.unwrap_or(50).min(100);
 
-    match Wallet::find_all_with_stats(limit, &state.db_pool).await {
-        Ok(wallets) => {
-            let items: Vec<WalletItem> = wallets.into_iter().map(Into::into).collect();
-            Json(items).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Failed to get wallets: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
-    }
+    let wallets = Wallet::find_all_with_stats(limit, params.tag.as_deref(), &state.db_pool).await?;
+    let addresses: Vec<Address20> = wallets.iter().map(|w| w.address).collect();
+    let mut tags = tags_by_address(TagSubject::Wallet, &addresses, &state.db_pool).await?;
+    let mut profiles: HashMap<Address20, WalletProfile> =
+        WalletProfile::find_by_addresses(&addresses, &state.db_pool)
+            .await?
+            .into_iter()
+            .map(|p| (p.wallet_address, p))
+            .collect();
+
+    shape
+        .list(
+            wallets
+                .into_iter()
+                .map(|w| {
+                    let address = w.address;
+                    WalletItem {
+                        tags: tags.remove(&address).unwrap_or_default(),
+                        ..w.into()
+                    }
+                    .with_profile(profiles.remove(&address))
+                })
+                .collect(),
+            &state.db_pool,
+        )
+        .await
 }
 
 /// POST /api/wallets
 /// Add a new wallet to track
 pub async fn create_wallet(
     State(state): State<Arc<AppState>>,
-    Json(body): Json<CreateWalletRequest>,
-) -> impl IntoResponse {
+    ApiJson(body): ApiJson<CreateWalletRequest>,
+) -> ApiResult<(StatusCode, Json<WalletItem>)> {
     let new_wallet = NewWallet {
-        address: body.address.to_lowercase(),
+        address: *body.address,
         label: body.label,
     };
 
-    match Wallet::create(&new_wallet, &state.db_pool).await {
-        Ok(wallet) => {
-            let item: WalletItem = wallet.into();
-            (StatusCode::CREATED, Json(item)).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Failed to create wallet: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+    let wallet = Wallet::create(&new_wallet, &state.db_pool).await?;
+    let tags = tags_of(TagSubject::Wallet, &wallet.address, &state.db_pool).await?;
+    let profile = wallet_profile(&wallet.address, &state).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(
+            WalletItem {
+                tags,
+                ..wallet.into()
+            }
+            .with_profile(profile),
+        ),
+    ))
+}
+
+/// POST /api/wallets/bulk
+/// Import many wallets at once, from a JSON array or a `text/csv` upload
+///
+/// Valid rows are upserted in one statement; every row gets a result.
+pub async fn bulk_create_wallets(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<Json<BulkWalletResponse>> {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/csv"));
+
+    let rows = if is_csv {
+        let text = std::str::from_utf8(&body)
+            .map_err(|_| ApiError::InvalidBody("CSV upload is not valid UTF-8".to_string()))?;
+        parse_csv(text)
+    } else {
+        serde_json::from_slice::<Vec<BulkWalletRow>>(&body)
+            .map_err(|e| ApiError::InvalidBody(e.to_string()))?
+    };
+
+    if rows.len() > MAX_BULK_WALLETS {
+        return Err(ApiError::InvalidBody(format!(
+            "At most {} wallets per import, got {}",
+            MAX_BULK_WALLETS,
+            rows.len()
+        )));
+    }
+
+    let mut results = Vec::with_capacity(rows.len());
+    let mut wallets: Vec<NewWallet> = Vec::new();
+
+    for (i, row) in rows.into_iter().enumerate() {
+        let mut result = BulkWalletResult {
+            row: i + 1,
+            address: row.address.clone(),
+            status: "invalid",
+            error: None,
+        };
+
+        let label_too_long = row
+            .label
+            .as_ref()
+            .is_some_and(|l| l.chars().count() > MAX_LABEL_LEN);
+
+        match EvmAddress::parse(&row.address) {
+            Err(e) => result.error = Some(e.to_string()),
+            Ok(_) if label_too_long => {
+                result.error = Some(format!("Label longer than {} characters", MAX_LABEL_LEN));
+            }
+            Ok(address) if wallets.iter().any(|w| w.address == *address) => {
+                result.status = "duplicate";
+                result.error = Some("Address appears earlier in the upload".to_string());
+            }
+            Ok(address) => {
+                result.address = address.to_string();
+                wallets.push(NewWallet {
+                    address: *address,
+                    label: row.label,
+                });
+            }
         }
+
+        results.push(result);
+    }
+
+    // Accepted rows line up with `wallets`
+    let inserted: HashMap<Address20, bool> = Wallet::create_many(&wallets, &state.db_pool)
+        .await?
+        .into_iter()
+        .collect();
+    let accepted = results.iter_mut().filter(|r| r.error.is_none());
+    for (result, wallet) in accepted.zip(&wallets) {
+        result.status = match inserted.get(&wallet.address) {
+            Some(true) => "created",
+            _ => "updated",
+        };
     }
+
+    let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+    Ok(Json(BulkWalletResponse {
+        created: count("created"),
+        updated: count("updated"),
+        failed: results.iter().filter(|r| r.error.is_some()).count(),
+        results,
+    }))
+}
+
+/// GET /api/wallets/suggestions
+/// Untracked wallets worth tracking: top traders by recent PnL and repeat
+/// early buyers of tokens that went on to score high
+pub async fn get_wallet_suggestions(
+    State(state): State<Arc<AppState>>,
+    shape: ResponseShape,
+    ApiQuery(params): ApiQuery<ListParams>,
+) -> ApiResult<Listing<WalletSuggestionItem>> {
+    let limit = params.limit.unwrap_or(50).min(100);
+
+    let suggestions = WalletSuggestion::find(limit as i64, &state.db_pool).await?;
+    shape
+        .list(
+            suggestions.into_iter().map(Into::into).collect(),
+            &state.db_pool,
+        )
+        .await
 }
 
 /// GET /api/wallets/:address
 /// Get a specific wallet
 pub async fn get_wallet(
     State(state): State<Arc<AppState>>,
-    Path(address): Path<String>,
-) -> impl IntoResponse {
-    match Wallet::find_by_address(&address, &state.db_pool).await {
-        Ok(Some(wallet)) => {
-            let item: WalletItem = wallet.into();
-            Json(item).into_response()
-        }
-        Ok(None) => (StatusCode::NOT_FOUND, "Wallet not found").into_response(),
-        Err(e) => {
-            tracing::error!("Failed to get wallet: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+    address: EvmAddress,
+) -> ApiResult<Json<WalletItem>> {
+    match Wallet::find_by_address(&address, &state.db_pool).await? {
+        Some(wallet) => {
+            let tags = tags_of(TagSubject::Wallet, &address, &state.db_pool).await?;
+            let profile = wallet_profile(&address, &state).await?;
+            Ok(Json(
+                WalletItem {
+                    tags,
+                    ..wallet.into()
+                }
+                .with_profile(profile),
+            ))
         }
+        None => Err(ApiError::WalletNotFound(address.to_string())),
     }
 }
 
@@ -165,15 +525,12 @@ pub async fn get_wallet(
 /// Remove a wallet from tracking
 pub async fn delete_wallet(
     State(state): State<Arc<AppState>>,
-    Path(address): Path<String>,
-) -> impl IntoResponse {
-    match Wallet::delete_by_address(&address, &state.db_pool).await {
-        Ok(true) => StatusCode::NO_CONTENT.into_response(),
-        Ok(false) => (StatusCode::NOT_FOUND, "Wallet not found").into_response(),
-        Err(e) => {
-            tracing::error!("Failed to delete wallet: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
+    address: EvmAddress,
+) -> ApiResult<StatusCode> {
+    if Wallet::delete_by_address(&address, &state.db_pool).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::WalletNotFound(address.to_string()))
     }
 }
 
@@ -181,19 +538,34 @@ pub async fn delete_wallet(
 /// Returns recent activity for a wallet
 pub async fn get_wallet_activity(
     State(state): State<Arc<AppState>>,
-    Path(address): Path<String>,
-    Query(params): Query<ListParams>,
-) -> impl IntoResponse {
+    shape: ResponseShape,
+    address: EvmAddress,
+    ApiQuery(params): ApiQuery<ListParams>,
+) -> ApiResult<Listing<WalletActivityItem>> {
     let limit = params.limit.unwrap_or(50).min(500);
 
-    match WalletActivity::find_by_wallet(&address, limit, &state.db_pool).await {
-        Ok(activities) => {
-            let items: Vec<WalletActivityItem> = activities.into_iter().map(Into::into).collect();
-            Json(items).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Failed to get wallet activity: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
+    let activities = WalletActivity::find_by_wallet(&address, limit, &state.db_pool).await?;
+    shape
+        .list(
+            activities.into_iter().map(Into::into).collect(),
+            &state.db_pool,
+        )
+        .await
+}
+
+/// GET /api/wallets/:address/profile
+/// Lifetime trading summary of any wallet, tracked or not
+pub async fn get_wallet_profile(
+    State(state): State<Arc<AppState>>,
+    address: EvmAddress,
+) -> ApiResult<Json<WalletTradingProfile>> {
+    let now = Instant::now();
+    if let Some(cached) = state.wallet_profiles.get(&address, now) {
+        return Ok(Json(cached));
     }
+
+    let summary = Swap::wallet_summary(&address, &state.db_pool).await?;
+    let profile = WalletTradingProfile::of(*address, summary);
+    state.wallet_profiles.insert(*address, profile.clone(), now);
+    Ok(Json(profile))
 }
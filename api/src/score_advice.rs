@@ -0,0 +1,316 @@
+//! BeeScore explanations
+//!
+//! Rule-based suggestions drawn from the breakdown stored with a score and
+//! from how the token moved since an earlier computation. Nothing here
+//! recomputes the score: point values come from the processor's breakdown.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use indexer_db::entity::score_history::{ScoreComponent, ScoreHistory};
+
+/// How far ahead an LP unlock is worth warning about
+const UNLOCK_WARNING_DAYS: i64 = 7;
+
+/// Smallest move in top-10 concentration (percentage points) worth mentioning
+const CONCENTRATION_MOVE_POINTS: f64 = 2.0;
+
+/// Components with the most points left to gain that get a suggestion
+const MAX_OPPORTUNITIES: usize = 3;
+
+/// What a suggestion tells the reader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    /// Points the token is likely to lose
+    Risk,
+    /// Something that got worse since the baseline
+    Worsening,
+    /// Something that got better since the baseline
+    Improving,
+    /// Points still available in a component
+    Opportunity,
+}
+
+/// One rule-based suggestion
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    pub message: String,
+    /// Points at stake: negative for a likely loss, positive for a gain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub points: Option<i16>,
+}
+
+fn component<'a>(breakdown: &'a [ScoreComponent], name: &str) -> Option<&'a ScoreComponent> {
+    breakdown.iter().find(|c| c.name == name)
+}
+
+/// `1 day`, `3 days`
+fn count(n: i64, unit: &str) -> String {
+    if n == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
+}
+
+fn signed(points: i16) -> String {
+    if points > 0 {
+        format!("+{}", points)
+    } else {
+        points.to_string()
+    }
+}
+
+/// Suggestions for `latest`, risks first
+///
+/// `baseline` is an earlier computation to measure moves against and
+/// `lp_unlock` the token's earliest active LP unlock.
+pub fn suggestions(
+    latest: &ScoreHistory,
+    baseline: Option<&ScoreHistory>,
+    lp_unlock: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Vec<Suggestion> {
+    let breakdown = &latest.breakdown.0;
+    let mut suggestions = Vec::new();
+
+    // Lock points go once the LP can be withdrawn
+    let lock_points = component(breakdown, "LP Lock").map_or(0, |c| c.score);
+    if let Some(unlock) = lp_unlock.filter(|_| lock_points > 0) {
+        let left = unlock - now;
+        let when = if left <= Duration::zero() {
+            Some("LP lock has expired".to_string())
+        } else if left < Duration::days(1) {
+            Some(format!(
+                "LP unlock in {}",
+                count(left.num_hours().max(1), "hour")
+            ))
+        } else if left <= Duration::days(UNLOCK_WARNING_DAYS) {
+            Some(format!("LP unlock in {}", count(left.num_days(), "day")))
+        } else {
+            None
+        };
+        if let Some(when) = when {
+            suggestions.push(Suggestion {
+                kind: SuggestionKind::Risk,
+                message: format!("{}: -{} pts risk", when, lock_points),
+                points: Some(-lock_points),
+            });
+        }
+    }
+
+    // Penalties carry no points of their own, only a reason
+    suggestions.extend(
+        breakdown
+            .iter()
+            .filter(|c| c.max_score == 0)
+            .map(|c| Suggestion {
+                kind: SuggestionKind::Risk,
+                message: format!("{}: {}", c.name, c.reason),
+                points: None,
+            }),
+    );
+
+    if let Some(baseline) = baseline {
+        let concentration = latest
+            .top_10_holder_percent
+            .as_ref()
+            .zip(baseline.top_10_holder_percent.as_ref())
+            .and_then(|(now, then)| {
+                Some((
+                    now.to_string().parse::<f64>().ok()?,
+                    then.to_string().parse::<f64>().ok()?,
+                ))
+            });
+        if let Some((now, then)) = concentration {
+            if (now - then).abs() >= CONCENTRATION_MOVE_POINTS {
+                let (kind, trend, direction) = if now < then {
+                    (SuggestionKind::Improving, "improving", "down")
+                } else {
+                    (SuggestionKind::Worsening, "worsening", "up")
+                };
+                suggestions.push(Suggestion {
+                    kind,
+                    message: format!(
+                        "Holder concentration {}: top 10 hold {:.1}%, {} from {:.1}%",
+                        trend, now, direction, then
+                    ),
+                    points: None,
+                });
+            }
+        }
+
+        for current in breakdown.iter().filter(|c| c.max_score > 0) {
+            let Some(before) = component(&baseline.breakdown.0, &current.name) else {
+                continue;
+            };
+            let change = current.score - before.score;
+            if change == 0 {
+                continue;
+            }
+            suggestions.push(Suggestion {
+                kind: if change > 0 {
+                    SuggestionKind::Improving
+                } else {
+                    SuggestionKind::Worsening
+                },
+                message: format!(
+                    "{} {} pts since {}: {}",
+                    current.name,
+                    signed(change),
+                    baseline.computed_at.format("%Y-%m-%d %H:%M UTC"),
+                    current.reason
+                ),
+                points: Some(change),
+            });
+        }
+    }
+
+    let mut gaps: Vec<&ScoreComponent> =
+        breakdown.iter().filter(|c| c.score < c.max_score).collect();
+    gaps.sort_by_key(|c| std::cmp::Reverse(c.max_score - c.score));
+    suggestions.extend(
+        gaps.into_iter()
+            .take(MAX_OPPORTUNITIES)
+            .map(|c| Suggestion {
+                kind: SuggestionKind::Opportunity,
+                message: format!(
+                    "{}: {} ({}/{}), up to {} pts available",
+                    c.name,
+                    c.reason,
+                    c.score,
+                    c.max_score,
+                    c.max_score - c.score
+                ),
+                points: Some(c.max_score - c.score),
+            }),
+    );
+
+    // Stable, so rules keep their order within a kind
+    suggestions.sort_by_key(|s| s.kind as u8);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use indexer_db::Address20;
+    use sqlx::types::{BigDecimal, Json};
+
+    use super::*;
+
+    fn part(
+        category: &str,
+        name: &str,
+        score: i16,
+        max_score: i16,
+        reason: &str,
+    ) -> ScoreComponent {
+        ScoreComponent {
+            category: category.to_string(),
+            name: name.to_string(),
+            score,
+            max_score,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn history(
+        computed_at: DateTime<Utc>,
+        top_10: &str,
+        breakdown: Vec<ScoreComponent>,
+    ) -> ScoreHistory {
+        ScoreHistory {
+            id: 1,
+            token_address: Address20::new([1; 20]),
+            bee_score: breakdown.iter().map(|c| c.score).sum(),
+            safety_score: 0,
+            traction_score: 0,
+            top_10_holder_percent: top_10.parse::<BigDecimal>().ok(),
+            top_holders: Json(Vec::new()),
+            computed_at,
+            breakdown: Json(breakdown),
+        }
+    }
+
+    #[test]
+    fn suggestions_cover_unlocks_moves_and_gaps() {
+        let now = Utc::now();
+        let baseline = history(
+            now - Duration::hours(24),
+            "62.0",
+            vec![
+                part("safety", "LP Lock", 10, 15, "LP 50-90% locked - good"),
+                part("traction", "Trades", 6, 8, "Active (50-100 trades/hr)"),
+            ],
+        );
+        let latest = history(
+            now,
+            "55.30",
+            vec![
+                part("safety", "LP Lock", 10, 15, "LP 50-90% locked - good"),
+                part("safety", "Distribution", 10, 15, "Moderately distributed"),
+                part("traction", "Trades", 2, 8, "Low activity (5-20 trades/hr)"),
+                part(
+                    "safety",
+                    "Impersonation",
+                    0,
+                    0,
+                    "Name or symbol copies CAKE (-15)",
+                ),
+            ],
+        );
+
+        let unlock = now + Duration::days(3) + Duration::hours(2);
+        let advice = suggestions(&latest, Some(&baseline), Some(unlock), now);
+        let kinds: Vec<_> = advice.iter().map(|s| s.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SuggestionKind::Risk,
+                SuggestionKind::Risk,
+                SuggestionKind::Worsening,
+                SuggestionKind::Improving,
+                SuggestionKind::Opportunity,
+                SuggestionKind::Opportunity,
+                SuggestionKind::Opportunity,
+            ]
+        );
+        assert_eq!(advice[0].message, "LP unlock in 3 days: -10 pts risk");
+        assert_eq!(advice[0].points, Some(-10));
+        assert!(advice[1].message.starts_with("Impersonation: "));
+        assert!(advice[2].message.starts_with("Trades -4 pts since "));
+        assert_eq!(
+            advice[3].message,
+            "Holder concentration improving: top 10 hold 55.3%, down from 62.0%"
+        );
+        // Biggest gap first
+        assert!(advice[4].message.starts_with("Trades: "));
+        assert_eq!(advice[4].points, Some(6));
+    }
+
+    #[test]
+    fn distant_unlocks_and_small_moves_are_quiet() {
+        let now = Utc::now();
+        let full = vec![part(
+            "safety",
+            "LP Lock",
+            15,
+            15,
+            "LP >90% locked - excellent",
+        )];
+        let baseline = history(now - Duration::hours(24), "40.0", full.clone());
+        let latest = history(now, "41.0", full);
+
+        let far = now + Duration::days(30);
+        assert!(suggestions(&latest, Some(&baseline), Some(far), now).is_empty());
+        assert!(suggestions(&latest, None, None, now).is_empty());
+
+        let expired = suggestions(&latest, None, Some(now - Duration::days(1)), now);
+        assert_eq!(expired[0].message, "LP lock has expired: -15 pts risk");
+        let soon = suggestions(&latest, None, Some(now + Duration::minutes(90)), now);
+        assert_eq!(soon[0].message, "LP unlock in 1 hour: -15 pts risk");
+    }
+}
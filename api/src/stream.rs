@@ -0,0 +1,337 @@
+//! Live swap and alert streams with replay
+//!
+//! A poller reads new swaps and alerts every `STREAM_POLL_MS` and pushes them
+//! onto a per-channel buffer holding the last `STREAM_REPLAY_SIZE` events,
+//! which fans them out to the WebSocket clients. A client that reconnects
+//! with `?since=<id of the last event it saw>` is first sent what it missed
+//! from the buffer, then the live flow. When `since` is older than the
+//! buffer reaches back, it gets a `gap` message and should catch up over
+//! REST.
+//!
+//! Past the first read, events reach the buffer once they are
+//! [`COMMIT_LAG`](indexer_db::entity::COMMIT_LAG) old, so a row whose
+//! transaction commits late is not skipped over.
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use indexer_db::{
+    entity::{alert::AlertEvent, swap::Swap},
+    Address20,
+};
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+use tokio::sync::broadcast;
+
+use crate::routes::{alerts::AlertItem, tokens::SwapItem};
+
+/// Most rows read per channel in one query
+const POLL_BATCH: i32 = 500;
+
+/// A stream clients can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Swaps,
+    Alerts,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Swaps => "swaps",
+            Channel::Alerts => "alerts",
+        }
+    }
+}
+
+/// A swap on any token
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSwap {
+    pub token_address: Address20,
+    pub pair_address: Address20,
+    #[serde(flatten)]
+    pub swap: SwapItem,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Payload {
+    Swap(Box<StreamSwap>),
+    Alert(Box<AlertItem>),
+}
+
+/// One message on a channel; `id` is the swap or alert id
+#[derive(Debug, Serialize)]
+pub struct StreamEvent {
+    pub channel: &'static str,
+    pub id: i32,
+    pub data: Payload,
+}
+
+impl From<Swap> for StreamEvent {
+    fn from(s: Swap) -> Self {
+        Self {
+            channel: Channel::Swaps.as_str(),
+            id: s.id,
+            data: Payload::Swap(Box::new(StreamSwap {
+                token_address: s.token_address,
+                pair_address: s.pair_address,
+                swap: s.into(),
+            })),
+        }
+    }
+}
+
+impl From<AlertEvent> for StreamEvent {
+    fn from(a: AlertEvent) -> Self {
+        Self {
+            channel: Channel::Alerts.as_str(),
+            id: a.id,
+            data: Payload::Alert(Box::new(a.into())),
+        }
+    }
+}
+
+struct Buffered {
+    events: VecDeque<Arc<StreamEvent>>,
+    /// Id of the newest event dropped off the front; `None` while the
+    /// buffer still holds everything it was sent
+    evicted_through: Option<i32>,
+}
+
+/// A channel's recent events and its live subscribers
+pub struct ReplayBuffer {
+    capacity: usize,
+    buffered: Mutex<Buffered>,
+    live: broadcast::Sender<Arc<StreamEvent>>,
+}
+
+/// What a subscriber is sent: the events it missed, then the live ones
+pub struct Subscription {
+    /// `since` is older than the buffer; events in between are lost
+    pub gap: bool,
+    pub missed: Vec<Arc<StreamEvent>>,
+    pub live: broadcast::Receiver<Arc<StreamEvent>>,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            buffered: Mutex::new(Buffered {
+                events: VecDeque::with_capacity(capacity),
+                evicted_through: None,
+            }),
+            live: broadcast::channel(capacity).0,
+        }
+    }
+
+    /// Keep `event` for replay and send it to the live subscribers
+    pub fn push(&self, event: StreamEvent) {
+        let event = Arc::new(event);
+        let mut buffered = self.buffered.lock().unwrap();
+        buffered.events.push_back(event.clone());
+        while buffered.events.len() > self.capacity {
+            buffered.evicted_through = buffered.events.pop_front().map(|e| e.id);
+        }
+        // No receivers is fine: nobody is listening yet
+        let _ = self.live.send(event);
+    }
+
+    /// Start an empty buffer after event `id`: clients that saw it can
+    /// replay from here, earlier ones have a gap
+    pub fn start_after(&self, id: i32) {
+        self.buffered.lock().unwrap().evicted_through = Some(id);
+    }
+
+    /// Subscribe to the live events, with the buffered ones after `since`
+    /// to send first
+    pub fn subscribe(&self, since: Option<i32>) -> Subscription {
+        // Under the lock, so no event lands between the replay and the live flow
+        let buffered = self.buffered.lock().unwrap();
+        let live = self.live.subscribe();
+        let Some(since) = since else {
+            return Subscription {
+                gap: false,
+                missed: Vec::new(),
+                live,
+            };
+        };
+
+        Subscription {
+            gap: buffered.evicted_through.is_some_and(|id| since < id),
+            missed: buffered
+                .events
+                .iter()
+                .filter(|e| e.id > since)
+                .cloned()
+                .collect(),
+            live,
+        }
+    }
+}
+
+/// The replay buffers of every channel
+pub struct Streams {
+    pub swaps: ReplayBuffer,
+    pub alerts: ReplayBuffer,
+}
+
+impl Streams {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            swaps: ReplayBuffer::new(capacity),
+            alerts: ReplayBuffer::new(capacity),
+        }
+    }
+
+    pub fn get(&self, channel: Channel) -> &ReplayBuffer {
+        match channel {
+            Channel::Swaps => &self.swaps,
+            Channel::Alerts => &self.alerts,
+        }
+    }
+}
+
+/// Feed the buffers from the database every `interval`, starting with the
+/// latest events so clients reconnecting right after a restart can replay
+pub async fn run(streams: Arc<Streams>, db_pool: Pool<Postgres>, interval: Duration) {
+    let mut swaps_after = None;
+    let mut alerts_after = None;
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        let swaps = catch_up(&streams.swaps, &mut swaps_after, |after| {
+            Swap::find_after(after, POLL_BATCH, &db_pool)
+        });
+        if let Err(e) = swaps.await {
+            tracing::warn!("Swap stream: failed to read new swaps: {}", e);
+        }
+
+        // As the alert feed shows them, without the alerts rolled up
+        let alerts = catch_up(&streams.alerts, &mut alerts_after, |after| {
+            AlertEvent::find_feed(None, None, after, POLL_BATCH, &db_pool)
+        });
+        if let Err(e) = alerts.await {
+            tracing::warn!("Alert stream: failed to read new alerts: {}", e);
+        }
+    }
+}
+
+/// Push what `read` finds after `after` (newest first, [`POLL_BATCH`] at a
+/// time) onto `buffer`, paging through any backlog. Rows recorded within the
+/// last [`COMMIT_LAG`](indexer_db::entity::COMMIT_LAG) are left for a later
+/// poll, which reads again from `after`.
+async fn catch_up<T, F, Fut>(
+    buffer: &ReplayBuffer,
+    after: &mut Option<i32>,
+    read: F,
+) -> Result<(), sqlx::Error>
+where
+    T: Into<StreamEvent>,
+    F: Fn(Option<i32>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, sqlx::Error>>,
+{
+    loop {
+        let rows = read(*after).await?;
+        let full = rows.len() == POLL_BATCH as usize;
+        let latest_page = after.is_none();
+        let mut events = rows.into_iter().rev().map(Into::<StreamEvent>::into);
+
+        // Events older than the latest page are out of reach
+        if latest_page && full {
+            if let Some(oldest) = events.next() {
+                buffer.start_after(oldest.id);
+            }
+        }
+        for event in events {
+            *after = Some(after.map_or(event.id, |id| id.max(event.id)));
+            buffer.push(event);
+        }
+
+        if latest_page || !full {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn event(id: i32) -> StreamEvent {
+        StreamEvent {
+            channel: Channel::Alerts.as_str(),
+            id,
+            data: Payload::Alert(Box::new(AlertItem {
+                id: id.to_string(),
+                alert_type: "token_signal".to_string(),
+                title: "New token".to_string(),
+                message: String::new(),
+                token_address: None,
+                wallet_address: None,
+                timestamp: Utc::now().to_rfc3339(),
+                is_read: false,
+                bee_score: None,
+                amount_usd: None,
+                change_percent: None,
+                alert_count: None,
+                metadata: None,
+            })),
+        }
+    }
+
+    fn ids(events: &[Arc<StreamEvent>]) -> Vec<i32> {
+        events.iter().map(|e| e.id).collect()
+    }
+
+    #[tokio::test]
+    async fn reconnecting_clients_replay_what_they_missed() {
+        let buffer = ReplayBuffer::new(3);
+        for id in 1..=3 {
+            buffer.push(event(id));
+        }
+
+        // Nothing to replay without `since`, and nothing missed when caught up
+        let fresh = buffer.subscribe(None);
+        assert!(!fresh.gap && fresh.missed.is_empty());
+        let caught_up = buffer.subscribe(Some(3));
+        assert!(!caught_up.gap && caught_up.missed.is_empty());
+
+        let mut resumed = buffer.subscribe(Some(1));
+        assert!(!resumed.gap);
+        assert_eq!(ids(&resumed.missed), vec![2, 3]);
+        buffer.push(event(4));
+        assert_eq!(resumed.live.recv().await.unwrap().id, 4);
+
+        // 1 has dropped off: a client that saw it misses nothing, one that
+        // didn't has a gap
+        assert_eq!(ids(&buffer.subscribe(Some(1)).missed), vec![2, 3, 4]);
+        assert!(!buffer.subscribe(Some(1)).gap);
+        let behind = buffer.subscribe(Some(0));
+        assert!(behind.gap);
+        assert_eq!(ids(&behind.missed), vec![2, 3, 4]);
+
+        // A buffer started mid-history has a gap before its first event
+        let restarted = ReplayBuffer::new(3);
+        restarted.start_after(10);
+        restarted.push(event(11));
+        assert!(!restarted.subscribe(Some(10)).gap);
+        assert!(restarted.subscribe(Some(9)).gap);
+
+        let message = serde_json::to_value(&*resumed.missed[0]).unwrap();
+        assert_eq!(message["channel"], "alerts");
+        assert_eq!(message["id"], 2);
+        assert_eq!(message["data"]["title"], "New token");
+    }
+}
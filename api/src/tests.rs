@@ -0,0 +1,2850 @@
+//! End-to-end route tests
+//!
+//! Each test gets a fresh database with all migrations applied, seeds the rows
+//! it needs through the entities, and drives the full router with `oneshot`.
+
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, HeaderMap, Method, Request, StatusCode},
+};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use sqlx::{types::BigDecimal, PgPool};
+use tower::ServiceExt;
+
+use indexer_db::{
+    entity::{
+        alert::{AlertEvent, NewAlert},
+        alert_metadata::{AlertMetadata, WhaleMetadata},
+        bot_wallet::{BotDetection, BotWallet},
+        evm_sync_logs::EvmSyncLogs,
+        holder_verification::{HolderVerification, VerificationCounts},
+        job::{Job, JobKind, NewJob},
+        pair::{NewPair, Pair},
+        price_snapshot::{NewPriceSnapshot, PriceSnapshot},
+        processing_error::{ErrorStage, NewProcessingError, ProcessingError},
+        processing_lag::{NewProcessingLag, ProcessingLag},
+        processor_event_metric::{EventCounts, ProcessorEventMetric},
+        score_history::{NewScoreHistory, ScoreComponent, ScoreHistory},
+        swap::{NewSwap, Swap, SwapLegs},
+        token::{NewToken, Token},
+        token_holder::{NewTokenHolder, TokenHolder},
+        token_impersonation::{NewTokenImpersonation, TokenImpersonation},
+        token_list::TokenList,
+        token_list_release::{ListVersion, ListedToken, TokenListRelease},
+        token_metrics_minute::TokenMetricsMinute,
+        token_rescan::TokenRescan,
+        wallet::{NewWallet, Wallet},
+        wallet_activity::{NewWalletActivity, WalletActivity},
+        wallet_profile::WalletProfile,
+        wallet_suggestion::{SuggestionCriteria, WalletSuggestion},
+    },
+    Address20, Hash32,
+};
+
+use crate::{
+    alert_notify::{self, AlertNotifier},
+    app,
+    cache::TtlCache,
+    decimal::Precision,
+    query_stats::{self, QueryBudget, QueryMetrics},
+    rate_limit::RateLimiter,
+    routes::jobs::MAX_OPEN_IMPORTS,
+    stream::Streams,
+    AppState,
+};
+
+struct TestResponse {
+    status: StatusCode,
+    content_type: String,
+    headers: HeaderMap,
+    body: Value,
+}
+
+const INGEST_KEY: &str = "test-ingest-key";
+const LEGACY_SUNSET: &str = "Thu, 01 Jul 2027 00:00:00 GMT";
+
+fn state(pool: &PgPool) -> Arc<AppState> {
+    Arc::new(AppState {
+        db_pool: pool.clone(),
+        ingest_api_key: Some(INGEST_KEY.to_string()),
+        precision: Precision::default(),
+        demo: false,
+        rate_limiter: RateLimiter::new(0),
+        legacy_sunset: LEGACY_SUNSET.to_string(),
+        alert_poll_interval_secs: 5,
+        streams: Arc::new(Streams::new(16)),
+        alert_notifier: AlertNotifier::new(),
+        wallet_profiles: TtlCache::new(std::time::Duration::from_secs(60)),
+        query_metrics: QueryMetrics::new(QueryBudget {
+            max_queries: 25,
+            max_db_time: std::time::Duration::from_millis(500),
+        }),
+    })
+}
+
+/// Send one request through the router and decode the JSON body (if any)
+async fn send(pool: &PgPool, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+    send_with_headers(pool, method, uri, body, &[]).await
+}
+
+async fn send_with_headers(
+    pool: &PgPool,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+    headers: &[(&str, &str)],
+) -> TestResponse {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let body = match body {
+        Some(json) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+
+    dispatch(pool, request.body(body).unwrap()).await
+}
+
+/// Send a non-JSON body, e.g. a CSV upload
+async fn send_raw(
+    pool: &PgPool,
+    method: Method,
+    uri: &str,
+    content_type: &str,
+    body: &str,
+) -> TestResponse {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    dispatch(pool, request).await
+}
+
+async fn dispatch(pool: &PgPool, request: Request<Body>) -> TestResponse {
+    let response = app(state(pool)).oneshot(request).await.unwrap();
+
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+
+    TestResponse {
+        status,
+        content_type,
+        headers,
+        body,
+    }
+}
+
+async fn get(pool: &PgPool, uri: &str) -> TestResponse {
+    send(pool, Method::GET, uri, None).await
+}
+
+/// Drop the demo rows from the seed migration so each test starts empty
+async fn clear_seed_data(pool: &PgPool) {
+    sqlx::query(
+        r#"
+        TRUNCATE tokens, swaps, lp_locks, price_snapshots, wallet_activity,
+                 token_holders, alert_events, alert_webhook_deliveries, pairs, wallets,
+                 token_metrics_minute
+        RESTART IDENTITY
+        "#,
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+fn address(n: u8) -> Address20 {
+    Address20::new([n; 20])
+}
+
+fn hash(n: u8) -> Hash32 {
+    Hash32::new([n; 32])
+}
+
+async fn create_token(pool: &PgPool, n: u8, symbol: &str) -> Address20 {
+    let token = NewToken {
+        address: address(n),
+        name: Some(format!("{} Token", symbol)),
+        symbol: Some(symbol.to_string()),
+        name_raw: None,
+        symbol_raw: None,
+        name_spoofed: false,
+        decimals: Some(18),
+        total_supply: Some(BigDecimal::from(1_000_000)),
+        pair_address: Some(address(n + 100)),
+        creator_address: Some(address(200)),
+        block_number: Some(1_000 + n as i64),
+    };
+
+    Token::create(&token, pool).await.unwrap().address
+}
+
+async fn refresh_lists(pool: &PgPool) {
+    for list in TokenList::ALL {
+        list.refresh(pool).await.unwrap();
+    }
+}
+
+fn assert_problem(response: &TestResponse, status: StatusCode, code: &str) {
+    assert_eq!(response.status, status);
+    assert_eq!(response.content_type, "application/problem+json");
+    assert_eq!(response.body["code"], code);
+    assert_eq!(response.body["status"], status.as_u16());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn root_and_health(pool: PgPool) {
+    let app = app(state(&pool));
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let html = String::from_utf8_lossy(&html);
+    assert!(html.contains("/api/v1/tokens/hot"));
+    // Every registered route is listed
+    for endpoint in crate::routes::api_routes().endpoints {
+        let listed = format!(
+            "data-method=\"{}\" data-path=\"/api/v1{}\"",
+            endpoint.method, endpoint.path
+        );
+        assert!(html.contains(&listed), "{} is missing", listed);
+    }
+
+    let response = app
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"OK");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_lists_are_ranked_and_paginated(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let low = create_token(&pool, 1, "LOW").await;
+    let high = create_token(&pool, 2, "HIGH").await;
+    let mid = create_token(&pool, 3, "MID").await;
+    Token::update_bee_score(&low, 10, 5, 5, &pool)
+        .await
+        .unwrap();
+    Token::update_bee_score(&high, 50, 30, 20, &pool)
+        .await
+        .unwrap();
+    Token::update_bee_score(&mid, 30, 20, 10, &pool)
+        .await
+        .unwrap();
+
+    // Only MID traded in the last hour; its volume lifts it above HIGH on the hot list
+    TokenMetricsMinute::record_trade(&mid, Utc::now(), true, &BigDecimal::from(2_500), &pool)
+        .await
+        .unwrap();
+    Token::refresh_trade_rollup(&mid, &pool).await.unwrap();
+
+    refresh_lists(&pool).await;
+
+    let hot = get(&pool, "/api/tokens/hot").await;
+    assert_eq!(hot.status, StatusCode::OK);
+    let symbols: Vec<_> = hot
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["symbol"].clone())
+        .collect();
+    assert_eq!(symbols, vec![json!("MID"), json!("HIGH"), json!("LOW")]);
+
+    let page = get(&pool, "/api/tokens/hot?limit=2").await;
+    assert_eq!(page.body.as_array().unwrap().len(), 2);
+
+    let item = &hot.body[0];
+    assert_eq!(item["address"], mid.to_string());
+    assert_eq!(item["volume1h"], 2_500.0);
+    assert_eq!(item["beeScore"], 30);
+    assert_eq!(item["chain"], "BSC");
+    assert_eq!(item["volume24hDisplay"], "$2.5K");
+    assert_eq!(item["priceDisplay"], "0");
+    for key in [
+        "name",
+        "price",
+        "priceChange1h",
+        "liquidity",
+        "marketCap",
+        "holders",
+        "lpLocked",
+        "createdAt",
+        "liquidityDisplay",
+        "marketCapDisplay",
+    ] {
+        assert!(item.get(key).is_some(), "missing `{}`", key);
+    }
+
+    let new = get(&pool, "/api/tokens/new?limit=50").await;
+    assert_eq!(new.status, StatusCode::OK);
+    assert_eq!(new.body.as_array().unwrap().len(), 3);
+
+    // Archived tokens are left off the lists unless asked for
+    sqlx::query("UPDATE tokens SET archived_at = NOW() WHERE address = $1")
+        .bind(low)
+        .execute(&pool)
+        .await
+        .unwrap();
+    TokenList::New.refresh(&pool).await.unwrap();
+    let live = get(&pool, "/api/tokens/new?limit=50").await;
+    assert_eq!(live.body.as_array().unwrap().len(), 2);
+    let everything = get(&pool, "/api/tokens/new?limit=50&include_archived=true").await;
+    assert_eq!(everything.body.as_array().unwrap().len(), 3);
+    let archived = get(&pool, &format!("/api/tokens/{}", low)).await;
+    assert!(archived.body["archivedAt"].is_string());
+
+    let trending = get(&pool, "/api/tokens/trending").await;
+    assert_eq!(trending.status, StatusCode::OK);
+    assert_eq!(trending.body.as_array().unwrap().len(), 1);
+    assert_eq!(trending.body[0]["symbol"], "MID");
+
+    // Sparklines only on request, 24 hourly closes carried forward
+    assert!(hot.body[0].get("sparkline").is_none());
+    for (hours_ago, price) in [(5, 4), (2, 6)] {
+        let snapshot = NewPriceSnapshot {
+            token_address: mid,
+            timestamp: Utc::now() - Duration::hours(hours_ago),
+            price_usd: Some(BigDecimal::from(price)),
+            price_bnb: None,
+            liquidity_usd: None,
+            volume_usd: None,
+            market_cap_usd: None,
+            holder_count: None,
+        };
+        PriceSnapshot::create(&snapshot, &pool).await.unwrap();
+    }
+    let with_sparklines = get(&pool, "/api/tokens/hot?sparkline=true").await;
+    let sparkline = with_sparklines.body[0]["sparkline"].as_array().unwrap();
+    assert_eq!(sparkline.len(), 24);
+    assert_eq!(sparkline[0], Value::Null);
+    assert_eq!(sparkline[18], 4.0);
+    assert_eq!(sparkline[20], 4.0);
+    assert_eq!(sparkline[23], 6.0);
+    let unpriced = with_sparklines.body[1]["sparkline"].as_array().unwrap();
+    assert!(unpriced.iter().all(Value::is_null));
+
+    let invalid = get(&pool, "/api/tokens/hot?limit=lots").await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn decimals_can_be_served_as_exact_strings(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let token = create_token(&pool, 0x2c, "MICRO").await;
+    let price: BigDecimal = "0.000000001234567891".parse().unwrap();
+    Token::update_price_metrics(
+        &token,
+        &price,
+        &BigDecimal::from(0),
+        &BigDecimal::from(52_300),
+        &BigDecimal::from(87),
+        &pool,
+    )
+    .await
+    .unwrap();
+
+    let number = get(&pool, &format!("/api/tokens/{}", token)).await;
+    assert!(number.body["price"].is_f64());
+    assert_eq!(number.body["liquidity"], 52_300.0);
+
+    let exact = get(&pool, &format!("/api/tokens/{}?precision=string", token)).await;
+    assert_eq!(exact.status, StatusCode::OK);
+    assert_eq!(exact.body["price"], "0.000000001234567891");
+    assert_eq!(exact.body["liquidity"], "52300");
+    // Percentages and display hints aren't affected
+    assert_eq!(exact.body["devHoldings"], 0.0);
+    assert_eq!(exact.body["priceDisplay"], "0.0₈1235");
+
+    let invalid = get(&pool, &format!("/api/tokens/{}?precision=exact", token)).await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_detail_and_errors(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let token = create_token(&pool, 0x2b, "ABC").await;
+
+    let response = get(&pool, &format!("/api/tokens/{}", token)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["address"], token.to_string());
+    assert_eq!(response.body["symbol"], "ABC");
+    assert_eq!(response.body["decimals"], 18);
+    assert_eq!(
+        response.body["pairAddress"],
+        address(0x2b + 100).to_string()
+    );
+    assert_eq!(response.body["blockNumber"], 1_000 + 0x2b);
+    assert_eq!(response.body["trades24h"], 0);
+    assert_eq!(response.body["holdersExited24h"], 0);
+    assert_eq!(response.body["holderChurnRate24h"], 0.0);
+    assert_eq!(response.body["cexInflow24h"], 0.0);
+    assert!(response.body["impersonation"].is_null());
+    assert_eq!(response.body["nameSpoofed"], false);
+
+    let flag = NewTokenImpersonation {
+        token_address: token,
+        target_address: address(7),
+        target_source: "top".to_string(),
+        matched_field: "symbol".to_string(),
+        similarity: BigDecimal::from(1),
+    };
+    TokenImpersonation::create(&flag, &pool).await.unwrap();
+    let response = get(&pool, &format!("/api/tokens/{}", token)).await;
+    let impersonation = &response.body["impersonation"];
+    assert_eq!(impersonation["targetAddress"], address(7).to_string());
+    assert_eq!(impersonation["matchedField"], "symbol");
+    assert_eq!(impersonation["similarity"], 1.0);
+
+    // Addresses are matched regardless of case or prefix spelling
+    let shouty = format!("0X{}", token.to_string()[2..].to_uppercase());
+    let response = get(&pool, &format!("/api/tokens/{}", shouty)).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let missing = get(&pool, &format!("/api/tokens/{}", address(9))).await;
+    assert_problem(&missing, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+
+    let invalid = get(&pool, "/api/tokens/0x1234").await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_ADDRESS");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_swaps_holders_and_chart(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let token = create_token(&pool, 1, "SWP").await;
+    let now = Utc::now();
+
+    for i in 0..3u8 {
+        let swap = NewSwap {
+            tx_hash: hash(i + 1),
+            block_number: 2_000 + i as i64,
+            log_index: 0,
+            timestamp: now - Duration::minutes(10 - i as i64),
+            pair_address: address(101),
+            token_address: token,
+            wallet_address: address(50 + i),
+            trade_type: if i % 2 == 0 { "buy" } else { "sell" }.to_string(),
+            amount_tokens: Some(BigDecimal::from(1_000)),
+            amount_bnb: Some(BigDecimal::from(1)),
+            amount_usd: Some(BigDecimal::from(600)),
+            price_usd: Some(BigDecimal::from(1)),
+            is_whale: i == 2,
+            tx_index: Some(i as i32),
+            gas_price_percentile: (i == 2).then_some(95),
+            mev_flags: if i == 2 {
+                vec![
+                    "top_of_block".to_string(),
+                    "high_gas".to_string(),
+                    "probable_mev".to_string(),
+                ]
+            } else {
+                Vec::new()
+            },
+            legs: (i == 2).then(|| SwapLegs {
+                token_in: address(200),
+                amount_in: BigDecimal::from(1),
+                token_out: token,
+                amount_out: BigDecimal::from(1_000),
+                fee_amount: Some("0.0025".parse().unwrap()),
+                fee_usd: Some("1.5".parse().unwrap()),
+            }),
+            source_log_id: None,
+        };
+        Swap::create(&swap, &pool).await.unwrap();
+    }
+
+    let swaps = get(&pool, &format!("/api/tokens/{}/swaps?limit=2", token)).await;
+    assert_eq!(swaps.status, StatusCode::OK);
+    let swaps = swaps.body.as_array().unwrap();
+    assert_eq!(swaps.len(), 2);
+    // Newest first
+    assert_eq!(swaps[0]["txHash"], hash(3).to_string());
+    assert_eq!(swaps[0]["walletAddress"], address(52).to_string());
+    assert_eq!(swaps[0]["tradeType"], "buy");
+    assert_eq!(swaps[0]["amountUsd"], 600.0);
+    assert_eq!(swaps[0]["gasPricePercentile"], 95);
+    assert_eq!(
+        swaps[0]["mevFlags"],
+        json!(["top_of_block", "high_gas", "probable_mev"])
+    );
+    assert_eq!(swaps[0]["tokenOut"], token.to_string());
+    assert_eq!(swaps[0]["amountOut"], 1_000.0);
+    assert_eq!(swaps[0]["feeUsd"], 1.5);
+    assert!(swaps[1]["gasPricePercentile"].is_null());
+    assert_eq!(swaps[1]["mevFlags"], json!([]));
+    assert!(swaps[1]["tokenIn"].is_null());
+
+    let filtered = |query: String| {
+        let uri = format!("/api/tokens/{}/swaps?{}", token, query);
+        let pool = pool.clone();
+        async move { get(&pool, &uri).await }
+    };
+    let sells = filtered("type=sell".to_string()).await;
+    assert_eq!(sells.body.as_array().unwrap().len(), 1);
+    assert_eq!(sells.body[0]["walletAddress"], address(51).to_string());
+    let whales = filtered("whales_only=true".to_string()).await;
+    assert_eq!(whales.body.as_array().unwrap().len(), 1);
+    assert_eq!(whales.body[0]["txHash"], hash(3).to_string());
+    let large = filtered("min_usd=600.01".to_string()).await;
+    assert_eq!(large.body, json!([]));
+    let since = (now - Duration::seconds(510)).format("%Y-%m-%dT%H:%M:%SZ");
+    let recent = filtered(format!("type=buy&min_usd=600&since={}", since)).await;
+    assert_eq!(recent.body.as_array().unwrap().len(), 1);
+    assert_eq!(recent.body[0]["txHash"], hash(3).to_string());
+    for bad in ["type=hold", "min_usd=lots", "since=yesterday"] {
+        let rejected = filtered(bad.to_string()).await;
+        assert_problem(&rejected, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+    }
+
+    for (n, balance) in [(60u8, 100), (61, 900), (62, 500)] {
+        let holder = NewTokenHolder {
+            token_address: token,
+            wallet_address: address(n),
+            balance: BigDecimal::from(balance),
+            is_dev: n == 61,
+            is_sniper: false,
+            is_contract: false,
+            first_buy_block: None,
+            source: None,
+        };
+        TokenHolder::upsert(&holder, &pool).await.unwrap();
+    }
+
+    let holders = get(&pool, &format!("/api/tokens/{}/holders", token)).await;
+    assert_eq!(holders.status, StatusCode::OK);
+    let balances: Vec<_> = holders
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| h["balance"].clone())
+        .collect();
+    assert_eq!(balances, vec![json!(900.0), json!(500.0), json!(100.0)]);
+    assert_eq!(holders.body[0]["isDev"], true);
+
+    let unscored = get(&pool, &format!("/api/tokens/{}/score", token)).await;
+    assert_problem(&unscored, StatusCode::UNPROCESSABLE_ENTITY, "NOT_SCORED");
+
+    let score = NewScoreHistory {
+        token_address: token,
+        bee_score: 72,
+        safety_score: 40,
+        traction_score: 32,
+        breakdown: vec![ScoreComponent {
+            category: "safety".to_string(),
+            name: "LP Lock".to_string(),
+            score: 0,
+            max_score: 15,
+            reason: "LP not locked - high rug risk".to_string(),
+        }],
+    };
+    ScoreHistory::create(&score, &pool).await.unwrap();
+    let explained = get(&pool, &format!("/api/tokens/{}/score", token)).await;
+    assert_eq!(explained.status, StatusCode::OK);
+    assert_eq!(explained.body["beeScore"], 72);
+    assert_eq!(explained.body["breakdown"][0]["maxScore"], 15);
+    assert_eq!(explained.body["comparedTo"], Value::Null);
+    assert_eq!(explained.body["suggestions"][0]["kind"], "opportunity");
+    assert_eq!(explained.body["suggestions"][0]["points"], 15);
+    let scores = get(&pool, &format!("/api/tokens/{}/scores", token)).await;
+    assert_eq!(scores.status, StatusCode::OK);
+    assert_eq!(scores.body[0]["beeScore"], 72);
+    let scored: Vec<_> = scores.body[0]["topHolders"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| (h["walletAddress"].clone(), h["balance"].clone()))
+        .collect();
+    assert_eq!(scored[0], (json!(address(61).to_string()), json!(900.0)));
+    assert_eq!(scored.len(), 3);
+    let unknown = get(&pool, &format!("/api/tokens/{}/scores", address(199))).await;
+    assert_problem(&unknown, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+
+    for hours_ago in [2, 30] {
+        let snapshot = NewPriceSnapshot {
+            token_address: token,
+            timestamp: now - Duration::hours(hours_ago),
+            price_usd: Some(BigDecimal::from(hours_ago)),
+            price_bnb: None,
+            liquidity_usd: None,
+            volume_usd: None,
+            market_cap_usd: None,
+            holder_count: None,
+        };
+        PriceSnapshot::create(&snapshot, &pool).await.unwrap();
+    }
+
+    // Snapshots are sparse, so the three swaps fill in the last few minutes
+    let day = get(&pool, &format!("/api/tokens/{}/chart", token)).await;
+    assert_eq!(day.status, StatusCode::OK);
+    let sources: Vec<_> = day
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["source"].clone())
+        .collect();
+    assert_eq!(
+        sources,
+        vec![
+            json!("snapshot"),
+            json!("swaps"),
+            json!("swaps"),
+            json!("swaps")
+        ]
+    );
+    assert_eq!(day.body[0]["priceUsd"], 2.0);
+    assert_eq!(day.body[3]["volumeUsd"], 600.0);
+
+    let week = get(&pool, &format!("/api/tokens/{}/chart?range=7d", token)).await;
+    assert_eq!(week.body.as_array().unwrap().len(), 5);
+
+    // Hourly buckets run from the snapshot's hour through the current one
+    let hourly = get(&pool, &format!("/api/tokens/{}/chart?interval=1h", token)).await;
+    assert_eq!(hourly.status, StatusCode::OK);
+    let hourly = hourly.body.as_array().unwrap();
+    assert_eq!(hourly.len(), 3);
+    assert_eq!(hourly[0]["source"], "snapshot");
+    assert_eq!(hourly[0]["priceUsd"], 2.0);
+    assert_eq!(hourly[0]["avgPriceUsd"], 2.0);
+    let times: Vec<_> = hourly
+        .iter()
+        .map(|p| {
+            chrono::DateTime::parse_from_rfc3339(p["timestamp"].as_str().unwrap())
+                .unwrap()
+                .timestamp()
+        })
+        .collect();
+    assert!(times.windows(2).all(|w| w[1] - w[0] == 3_600));
+
+    // Share cards carry the same hourly prices as a sparkline
+    let card = get(&pool, &format!("/api/tokens/{}/card", token)).await;
+    assert_eq!(card.status, StatusCode::OK);
+    assert_eq!(card.body["symbol"], "SWP");
+    let sparkline = card.body["sparkline"].as_array().unwrap();
+    assert_eq!(sparkline.len(), hourly.len());
+    assert_eq!(sparkline[0], 2.0);
+    let unknown = get(&pool, &format!("/api/tokens/{}/card", address(199))).await;
+    assert_problem(&unknown, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+
+    let bad_interval = get(&pool, &format!("/api/tokens/{}/chart?interval=2m", token)).await;
+    assert_problem(&bad_interval, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+
+    let invalid = get(&pool, "/api/tokens/nope/swaps").await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_ADDRESS");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_snipers_report_ratio_and_launch_offset(pool: PgPool) {
+    // Launch block is 1_001
+    let token = create_token(&pool, 1, "SNP").await;
+
+    for (n, balance, is_sniper, block) in [
+        (70u8, 20_000, true, 1_002),
+        (71, 50_000, true, 1_001),
+        (72, 90_000, false, 1_200),
+    ] {
+        let holder = NewTokenHolder {
+            token_address: token,
+            wallet_address: address(n),
+            balance: BigDecimal::from(balance),
+            is_dev: false,
+            is_sniper,
+            is_contract: false,
+            first_buy_block: Some(block),
+            source: None,
+        };
+        TokenHolder::upsert(&holder, &pool).await.unwrap();
+    }
+    Token::refresh_sniper_ratio(&token, &pool).await.unwrap();
+
+    let snipers = get(&pool, &format!("/api/tokens/{}/snipers", token)).await;
+    assert_eq!(snipers.status, StatusCode::OK);
+    assert_eq!(snipers.body["sniperRatio"], 7.0);
+    let list = snipers.body["snipers"].as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["walletAddress"], address(71).to_string());
+    assert_eq!(list[0]["blocksAfterLaunch"], 0);
+    assert_eq!(list[1]["blocksAfterLaunch"], 1);
+
+    let missing = get(&pool, &format!("/api/tokens/{}/snipers", address(9))).await;
+    assert_problem(&missing, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_detail_separates_bot_holders(pool: PgPool) {
+    let token = create_token(&pool, 1, "BOT").await;
+    for (n, balance) in [(70u8, 30_000), (71, 10_000)] {
+        let holder = NewTokenHolder {
+            token_address: token,
+            wallet_address: address(n),
+            balance: BigDecimal::from(balance),
+            is_dev: false,
+            is_sniper: false,
+            is_contract: false,
+            first_buy_block: Some(1_001),
+            source: None,
+        };
+        TokenHolder::upsert(&holder, &pool).await.unwrap();
+    }
+    let detection = BotDetection {
+        wallet_address: address(70),
+        signals: vec!["creation_block".to_string(), "exact_gas_limit".to_string()],
+        token_address: token,
+        tx_hash: hash(1),
+        block_number: 1_001,
+    };
+    BotWallet::record(&detection, &pool).await.unwrap();
+    Token::refresh_holder_metrics(&token, &pool).await.unwrap();
+
+    let detail = get(&pool, &format!("/api/tokens/{}", token)).await;
+    assert_eq!(detail.status, StatusCode::OK);
+    assert_eq!(detail.body["holders"], 2);
+    assert_eq!(detail.body["organicHolders"], 1);
+    assert_eq!(detail.body["botHolderPercent"], 3.0);
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn similar_tokens_rank_copycats_first(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let original = create_token(&pool, 1, "PEPE").await;
+    let copycat = create_token(&pool, 2, "PEPE2").await;
+    let other = create_token(&pool, 3, "DOGE").await;
+
+    let similar = get(&pool, &format!("/api/tokens/{}/similar", original)).await;
+    assert_eq!(similar.status, StatusCode::OK);
+    let list = similar.body.as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["address"], copycat.to_string());
+    assert_eq!(list[0]["symbol"], "PEPE2");
+    assert_eq!(list[0]["sameDeployer"], true);
+    assert_eq!(list[0]["similarAge"], true);
+    assert_eq!(list[1]["address"], other.to_string());
+    assert!(
+        list[0]["nameSimilarity"].as_f64().unwrap() > list[1]["nameSimilarity"].as_f64().unwrap()
+    );
+    assert!(
+        list[0]["similarityScore"].as_f64().unwrap() > list[1]["similarityScore"].as_f64().unwrap()
+    );
+
+    let limited = get(&pool, &format!("/api/tokens/{}/similar?limit=1", original)).await;
+    assert_eq!(limited.body.as_array().unwrap().len(), 1);
+
+    let missing = get(&pool, &format!("/api/tokens/{}/similar", address(9))).await;
+    assert_problem(&missing, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn tokens_are_ranked_by_client_weights(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let busy = create_token(&pool, 1, "BUSY").await;
+    let deep = create_token(&pool, 2, "DEEP").await;
+    for (token, volume, liquidity) in [(busy, 90_000, 1_000), (deep, 1_000, 50_000)] {
+        sqlx::query(
+            "UPDATE tokens SET trades_24h = 5, volume_24h_usd = $2, liquidity_usd = $3 WHERE address = $1",
+        )
+        .bind(token)
+        .bind(volume)
+        .bind(liquidity)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let by_volume = get(
+        &pool,
+        "/api/tokens/ranked?w_volume=2&w_liquidity=0&w_holders=0&w_score=0",
+    )
+    .await;
+    assert_eq!(by_volume.status, StatusCode::OK);
+    let list = by_volume.body.as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["address"], busy.to_string());
+    assert_eq!(list[0]["symbol"], "BUSY");
+    assert_eq!(list[0]["customScore"], 100.0);
+
+    let by_liquidity = get(
+        &pool,
+        "/api/tokens/ranked?w_volume=0.5&w_liquidity=2&limit=1",
+    )
+    .await;
+    let list = by_liquidity.body.as_array().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["address"], deep.to_string());
+
+    let negative = get(&pool, "/api/tokens/ranked?w_volume=-1").await;
+    assert_problem(&negative, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+    let all_zero = get(
+        &pool,
+        "/api/tokens/ranked?w_volume=0&w_liquidity=0&w_holders=0&w_score=0&w_momentum=0",
+    )
+    .await;
+    assert_problem(&all_zero, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn activity_heatmap_buckets_trades_by_weekday_and_hour(pool: PgPool) {
+    use chrono::{Datelike, DurationRound, Timelike};
+
+    clear_seed_data(&pool).await;
+    let token = create_token(&pool, 1, "CRON").await;
+    // A bot trading in the same hour a week ago, and once outside the window
+    let slot = Utc::now().duration_trunc(Duration::hours(1)).unwrap() - Duration::days(7);
+    for (n, timestamp) in [
+        (1, slot + Duration::minutes(1)),
+        (2, slot + Duration::minutes(2)),
+        (3, slot - Duration::days(35)),
+    ] {
+        let swap = NewSwap {
+            tx_hash: hash(n),
+            block_number: 2_000 + n as i64,
+            log_index: 0,
+            timestamp,
+            pair_address: address(101),
+            token_address: token,
+            wallet_address: address(0x51),
+            trade_type: "buy".to_string(),
+            amount_tokens: Some(BigDecimal::from(1_000)),
+            amount_bnb: Some(BigDecimal::from(1)),
+            amount_usd: Some(BigDecimal::from(250)),
+            price_usd: Some(BigDecimal::from(1)),
+            is_whale: false,
+            tx_index: None,
+            gas_price_percentile: None,
+            mev_flags: Vec::new(),
+            legs: None,
+            source_log_id: None,
+        };
+        Swap::create(&swap, &pool).await.unwrap();
+    }
+
+    let heatmap = get(&pool, &format!("/api/tokens/{}/activity-heatmap", token)).await;
+    assert_eq!(heatmap.status, StatusCode::OK);
+    assert_eq!(heatmap.body["trades"], 2);
+    assert_eq!(heatmap.body["volumeUsd"], 500.0);
+    let cells = heatmap.body["cells"].as_array().unwrap();
+    assert_eq!(cells.len(), 168);
+    assert_eq!(cells[0]["dayOfWeek"], 1);
+    assert_eq!(cells[0]["hour"], 0);
+    let busy: Vec<_> = cells.iter().filter(|c| c["trades"] != 0).collect();
+    assert_eq!(busy.len(), 1);
+    assert_eq!(busy[0]["dayOfWeek"], slot.weekday().number_from_monday());
+    assert_eq!(busy[0]["hour"], slot.hour());
+    assert_eq!(busy[0]["trades"], 2);
+
+    let missing = get(
+        &pool,
+        &format!("/api/tokens/{}/activity-heatmap", address(9)),
+    )
+    .await;
+    assert_problem(&missing, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn wallet_lifecycle(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let wallet = address(0x42);
+
+    let created = send(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(json!({ "address": wallet.to_string().to_uppercase().replace("0X", "0x"), "label": "Whale" })),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["address"], wallet.to_string());
+    assert_eq!(created.body["label"], "Whale");
+
+    let rejected = send(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(json!({ "address": "0xnothex" })),
+    )
+    .await;
+    assert_problem(&rejected, StatusCode::BAD_REQUEST, "INVALID_ADDRESS");
+
+    Wallet::create(
+        &NewWallet {
+            address: address(0x43),
+            label: None,
+        },
+        &pool,
+    )
+    .await
+    .unwrap();
+
+    for i in 0..3u8 {
+        let activity = NewWalletActivity {
+            wallet_address: wallet,
+            tx_hash: hash(i + 1),
+            block_number: 3_000 + i as i64,
+            timestamp: Utc::now() - Duration::minutes(i as i64),
+            action: "buy".to_string(),
+            token_address: address(i + 1),
+            token_symbol: Some(format!("T{}", i)),
+            amount_tokens: Some(BigDecimal::from(10)),
+            amount_usd: Some(BigDecimal::from(100)),
+            source_log_id: None,
+        };
+        WalletActivity::create(&activity, &pool).await.unwrap();
+    }
+    WalletProfile::record_seen(&wallet, 2_500, Utc::now() - Duration::days(3), &pool)
+        .await
+        .unwrap();
+
+    let list = get(&pool, "/api/wallets").await;
+    assert_eq!(list.status, StatusCode::OK);
+    let list = list.body.as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["address"], wallet.to_string());
+    assert_eq!(list[0]["tokenCount"], 3);
+    assert_eq!(list[0]["estimatedValue"], 300.0);
+    assert_eq!(list[0]["firstSeenBlock"], 2_500);
+    assert_eq!(list[0]["indexedAgeDays"], 3);
+    assert!(list[1]["indexedAgeDays"].is_null());
+
+    let page = get(&pool, "/api/wallets?limit=1").await;
+    assert_eq!(page.body.as_array().unwrap().len(), 1);
+
+    let activity = get(&pool, &format!("/api/wallets/{}/activity?limit=2", wallet)).await;
+    assert_eq!(activity.status, StatusCode::OK);
+    let activity = activity.body.as_array().unwrap();
+    assert_eq!(activity.len(), 2);
+    assert_eq!(activity[0]["tokenSymbol"], "T0");
+    assert_eq!(activity[0]["action"], "buy");
+
+    let fetched = get(&pool, &format!("/api/wallets/{}", wallet)).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.body["label"], "Whale");
+    assert_eq!(fetched.body["indexedAgeDays"], 3);
+
+    let deleted = send(
+        &pool,
+        Method::DELETE,
+        &format!("/api/wallets/{}", wallet),
+        None,
+    )
+    .await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+
+    let gone = get(&pool, &format!("/api/wallets/{}", wallet)).await;
+    assert_problem(&gone, StatusCode::NOT_FOUND, "WALLET_NOT_FOUND");
+
+    let deleted_again = send(
+        &pool,
+        Method::DELETE,
+        &format!("/api/wallets/{}", wallet),
+        None,
+    )
+    .await;
+    assert_problem(&deleted_again, StatusCode::NOT_FOUND, "WALLET_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn demo_mode_is_read_only_masked_and_rate_limited(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let wallet = Address20::new([0xab; 20]);
+    let new_wallet = NewWallet {
+        address: wallet,
+        label: Some("Whale".to_string()),
+    };
+    Wallet::create(&new_wallet, &pool).await.unwrap();
+
+    let demo = app(Arc::new(AppState {
+        db_pool: pool.clone(),
+        ingest_api_key: Some(INGEST_KEY.to_string()),
+        precision: Precision::default(),
+        demo: true,
+        rate_limiter: RateLimiter::new(3),
+        legacy_sunset: LEGACY_SUNSET.to_string(),
+        alert_poll_interval_secs: 5,
+        streams: Arc::new(Streams::new(16)),
+        alert_notifier: AlertNotifier::new(),
+        wallet_profiles: TtlCache::new(std::time::Duration::from_secs(60)),
+        query_metrics: QueryMetrics::new(QueryBudget {
+            max_queries: 25,
+            max_db_time: std::time::Duration::from_millis(500),
+        }),
+    }));
+    let call = |method: Method, uri: &str| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-api-key", INGEST_KEY)
+            .body(Body::empty())
+            .unwrap();
+        let demo = demo.clone();
+        async move {
+            let response = demo.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+        }
+    };
+
+    let (status, list) = call(Method::GET, "/api/wallets").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list[0]["address"], "0xabab...abab");
+
+    // Refused even with the operator key
+    let (status, deleted) = call(Method::DELETE, &format!("/api/wallets/{}", wallet)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(deleted["code"], "DEMO_READ_ONLY");
+    let (_, admin) = call(Method::GET, "/api/admin/listeners").await;
+    assert_eq!(admin["code"], "DEMO_READ_ONLY");
+
+    let (status, limited) = call(Method::GET, "/api/wallets").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(limited["code"], "RATE_LIMITED");
+
+    assert!(Wallet::find_by_address(&wallet, &pool)
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn wallets_are_bulk_imported_from_json_and_csv(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    Wallet::create(
+        &NewWallet {
+            address: address(0x41),
+            label: Some("Old".to_string()),
+        },
+        &pool,
+    )
+    .await
+    .unwrap();
+
+    let imported = send(
+        &pool,
+        Method::POST,
+        "/api/wallets/bulk",
+        Some(json!([
+            { "address": address(0x41).to_string(), "label": null },
+            { "address": address(0x42).to_string().to_uppercase().replace("0X", "0x"), "label": "Whale" },
+            { "address": "0xnothex" },
+            { "address": address(0x42).to_string() },
+        ])),
+    )
+    .await;
+    assert_eq!(imported.status, StatusCode::OK);
+    assert_eq!(imported.body["created"], 1);
+    assert_eq!(imported.body["updated"], 1);
+    assert_eq!(imported.body["failed"], 2);
+    let statuses: Vec<&str> = imported.body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, vec!["updated", "created", "invalid", "duplicate"]);
+    assert_eq!(
+        imported.body["results"][1]["address"],
+        address(0x42).to_string()
+    );
+
+    let csv = format!(
+        "address,label\n{},\"Dev, team\"\n\n{}\nnot-an-address,Bad\n",
+        address(0x43),
+        address(0x41)
+    );
+    let imported = send_raw(&pool, Method::POST, "/api/wallets/bulk", "text/csv", &csv).await;
+    assert_eq!(imported.status, StatusCode::OK);
+    assert_eq!(imported.body["created"], 1);
+    assert_eq!(imported.body["updated"], 1);
+    assert_eq!(imported.body["results"][2]["row"], 3);
+    assert_eq!(imported.body["results"][2]["status"], "invalid");
+
+    let dev = get(&pool, &format!("/api/wallets/{}", address(0x43))).await;
+    assert_eq!(dev.body["label"], "Dev, team");
+    let kept = get(&pool, &format!("/api/wallets/{}", address(0x41))).await;
+    assert_eq!(kept.body["label"], "Old");
+
+    let malformed = send_raw(
+        &pool,
+        Method::POST,
+        "/api/wallets/bulk",
+        "application/json",
+        "{}",
+    )
+    .await;
+    assert_problem(&malformed, StatusCode::BAD_REQUEST, "INVALID_BODY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn retried_mutations_replay_the_original_response(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let body = json!({ "address": address(0x42).to_string(), "label": "Whale" });
+
+    let created = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(body.clone()),
+        &[("Idempotency-Key", "create-whale")],
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert!(created.headers.get("idempotent-replayed").is_none());
+
+    // The retry isn't run again: it gets the first response back
+    Wallet::delete_by_address(&address(0x42), &pool)
+        .await
+        .unwrap();
+    let retried = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(body.clone()),
+        &[("Idempotency-Key", "create-whale")],
+    )
+    .await;
+    assert_eq!(retried.status, StatusCode::CREATED);
+    assert_eq!(retried.content_type, created.content_type);
+    assert_eq!(retried.body, created.body);
+    assert_eq!(retried.headers["idempotent-replayed"], "true");
+    assert!(get(&pool, "/api/wallets").await.body[0].is_null());
+
+    // Keys are per client: another one's runs as a request of its own
+    let other = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(body.clone()),
+        &[
+            ("Idempotency-Key", "create-whale"),
+            ("X-API-Key", "client-key-0123456789"),
+        ],
+    )
+    .await;
+    assert_eq!(other.status, StatusCode::CREATED);
+    assert!(other.headers.get("idempotent-replayed").is_none());
+
+    let reused = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(json!({ "address": address(0x43).to_string() })),
+        &[("Idempotency-Key", "create-whale")],
+    )
+    .await;
+    assert_problem(
+        &reused,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "IDEMPOTENCY_KEY_REUSED",
+    );
+
+    // Errors are replayed too, and keys only apply to mutations
+    let invalid = json!({ "address": "0xnothex" });
+    for _ in 0..2 {
+        let rejected = send_with_headers(
+            &pool,
+            Method::POST,
+            "/api/wallets",
+            Some(invalid.clone()),
+            &[("Idempotency-Key", "bad-wallet")],
+        )
+        .await;
+        assert_problem(&rejected, StatusCode::BAD_REQUEST, "INVALID_ADDRESS");
+    }
+    let read = send_with_headers(
+        &pool,
+        Method::GET,
+        "/api/wallets",
+        None,
+        &[("Idempotency-Key", "create-whale")],
+    )
+    .await;
+    assert_eq!(read.status, StatusCode::OK);
+
+    let blank = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(invalid),
+        &[("Idempotency-Key", " ")],
+    )
+    .await;
+    assert_problem(&blank, StatusCode::BAD_REQUEST, "INVALID_IDEMPOTENCY_KEY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn wallet_suggestions_are_listed_until_tracked(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let token = create_token(&pool, 1, "PNL").await;
+    for (n, trade_type, usd) in [(1, "buy", 1_000), (2, "sell", 4_500)] {
+        let swap = NewSwap {
+            tx_hash: hash(n),
+            block_number: 2_000 + n as i64,
+            log_index: 0,
+            timestamp: Utc::now() - Duration::hours(n as i64),
+            pair_address: address(101),
+            token_address: token,
+            wallet_address: address(0x51),
+            trade_type: trade_type.to_string(),
+            amount_tokens: Some(BigDecimal::from(1_000)),
+            amount_bnb: Some(BigDecimal::from(1)),
+            amount_usd: Some(BigDecimal::from(usd)),
+            price_usd: Some(BigDecimal::from(1)),
+            is_whale: false,
+            tx_index: None,
+            gas_price_percentile: None,
+            mev_flags: Vec::new(),
+            legs: None,
+            source_log_id: None,
+        };
+        Swap::create(&swap, &pool).await.unwrap();
+    }
+    let criteria = SuggestionCriteria {
+        pnl_days: 7,
+        top_pnl: 10,
+        early_blocks: 100,
+        min_early_wins: 3,
+        min_score: 80,
+    };
+    WalletSuggestion::analyze(&criteria, &pool).await.unwrap();
+
+    let listed = get(&pool, "/api/wallets/suggestions").await;
+    assert_eq!(listed.status, StatusCode::OK);
+    let suggestions = listed.body.as_array().unwrap();
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0]["address"], address(0x51).to_string());
+    assert_eq!(suggestions[0]["reasons"], json!(["top_pnl"]));
+    assert_eq!(suggestions[0]["suggestedLabel"], "Top trader");
+    assert_eq!(suggestions[0]["pnlUsd"], 3_500.0);
+
+    // One click: the suggestion goes straight to the create endpoint
+    let created = send(
+        &pool,
+        Method::POST,
+        "/api/wallets",
+        Some(json!({
+            "address": suggestions[0]["address"],
+            "label": suggestions[0]["suggestedLabel"],
+        })),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["label"], "Top trader");
+
+    let listed = get(&pool, "/api/wallets/suggestions").await;
+    assert!(listed.body.as_array().unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn wallet_profiles_summarize_every_token_and_are_cached(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let trader = address(0x61);
+    let swap = |n: u8, token: u8, hours_ago: i64, trade_type: &str, usd: i64| NewSwap {
+        tx_hash: hash(n),
+        block_number: 3_000 + n as i64,
+        log_index: 0,
+        timestamp: Utc::now() - Duration::hours(hours_ago),
+        pair_address: address(100 + token),
+        token_address: address(token),
+        wallet_address: trader,
+        trade_type: trade_type.to_string(),
+        amount_tokens: Some(BigDecimal::from(1_000)),
+        amount_bnb: Some(BigDecimal::from(1)),
+        amount_usd: Some(BigDecimal::from(usd)),
+        price_usd: Some(BigDecimal::from(1)),
+        is_whale: usd >= 5_000,
+        tx_index: None,
+        gas_price_percentile: None,
+        mev_flags: Vec::new(),
+        legs: None,
+        source_log_id: None,
+    };
+    for (n, token, hours_ago, trade_type, usd) in [
+        (1, 1, 5, "buy", 1_000),
+        (2, 1, 3, "sell", 1_600),
+        (3, 2, 4, "buy", 6_000),
+        (4, 2, 2, "sell", 3_000),
+        (5, 3, 1, "buy", 400),
+    ] {
+        Swap::create(&swap(n, token, hours_ago, trade_type, usd), &pool)
+            .await
+            .unwrap();
+    }
+
+    let shared = state(&pool);
+    let profile = || {
+        let app = app(shared.clone());
+        async move {
+            let request = Request::get(format!("/api/wallets/{}/profile", trader))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<Value>(&bytes).unwrap()
+        }
+    };
+
+    let first = profile().await;
+    assert_eq!(first["address"], trader.to_string());
+    assert_eq!(first["trades"], 5);
+    assert_eq!(first["lifetimeVolumeUsd"], 12_000.0);
+    assert_eq!(first["tokensTraded"], 3);
+    assert_eq!(first["whaleTrades"], 1);
+    assert_eq!(first["avgHoldSecs"], 7_200);
+    assert_eq!(first["winRate"], 50.0);
+    assert_eq!(first["biggestWin"]["tokenAddress"], address(1).to_string());
+    assert_eq!(first["biggestWin"]["pnlUsd"], 600.0);
+    assert_eq!(first["biggestLoss"]["tokenAddress"], address(2).to_string());
+    assert_eq!(first["biggestLoss"]["pnlUsd"], -3_000.0);
+
+    // Served from the cache until it expires; a fresh process recomputes it
+    Swap::create(&swap(6, 3, 0, "sell", 900), &pool).await.unwrap();
+    assert_eq!(profile().await, first);
+    let fresh = get(&pool, &format!("/api/wallets/{}/profile", trader)).await;
+    assert_eq!(fresh.body["trades"], 6);
+    assert_eq!(fresh.body["winRate"], 66.67);
+
+    let unknown = get(&pool, &format!("/api/wallets/{}/profile", address(0x62))).await;
+    assert_eq!(unknown.status, StatusCode::OK);
+    assert_eq!(unknown.body["trades"], 0);
+    assert_eq!(unknown.body["winRate"], Value::Null);
+    assert_eq!(unknown.body["biggestWin"], Value::Null);
+
+    let invalid = get(&pool, "/api/wallets/0x123/profile").await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn wallets_and_tokens_are_tagged_and_filtered(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let gem = create_token(&pool, 1, "GEM").await;
+    create_token(&pool, 2, "MEH").await;
+    for n in [0x41, 0x42] {
+        Wallet::create(
+            &NewWallet {
+                address: address(n),
+                label: None,
+            },
+            &pool,
+        )
+        .await
+        .unwrap();
+    }
+
+    let tagged = send(
+        &pool,
+        Method::POST,
+        &format!("/api/tokens/{}/tags", gem),
+        Some(json!({ "tag": " Gem " })),
+    )
+    .await;
+    assert_eq!(tagged.status, StatusCode::CREATED);
+    assert_eq!(tagged.body, json!(["gem"]));
+
+    for tag in ["team wallet", "insider"] {
+        let tagged = send(
+            &pool,
+            Method::POST,
+            &format!("/api/wallets/{}/tags", address(0x42)),
+            Some(json!({ "tag": tag })),
+        )
+        .await;
+        assert_eq!(tagged.status, StatusCode::CREATED);
+    }
+
+    let blank = send(
+        &pool,
+        Method::POST,
+        &format!("/api/wallets/{}/tags", address(0x42)),
+        Some(json!({ "tag": "  " })),
+    )
+    .await;
+    assert_problem(&blank, StatusCode::BAD_REQUEST, "INVALID_BODY");
+
+    refresh_lists(&pool).await;
+    let all = get(&pool, "/api/tokens/new").await;
+    assert_eq!(all.body.as_array().unwrap().len(), 2);
+    let gems = get(&pool, "/api/tokens/new?tag=gem").await;
+    let gems = gems.body.as_array().unwrap();
+    assert_eq!(gems.len(), 1);
+    assert_eq!(gems[0]["symbol"], "GEM");
+    assert_eq!(gems[0]["tags"], json!(["gem"]));
+
+    let detail = get(&pool, &format!("/api/tokens/{}", gem)).await;
+    assert_eq!(detail.body["tags"], json!(["gem"]));
+
+    let insiders = get(&pool, "/api/wallets?tag=insider").await;
+    let insiders = insiders.body.as_array().unwrap();
+    assert_eq!(insiders.len(), 1);
+    assert_eq!(insiders[0]["address"], address(0x42).to_string());
+    assert_eq!(insiders[0]["tags"], json!(["insider", "team wallet"]));
+
+    let tags = get(&pool, "/api/tags").await;
+    assert_eq!(tags.status, StatusCode::OK);
+    assert_eq!(tags.body.as_array().unwrap().len(), 3);
+
+    let removed = send(
+        &pool,
+        Method::DELETE,
+        &format!("/api/wallets/{}/tags/insider", address(0x42)),
+        None,
+    )
+    .await;
+    assert_eq!(removed.status, StatusCode::NO_CONTENT);
+    let removed_again = send(
+        &pool,
+        Method::DELETE,
+        &format!("/api/wallets/{}/tags/insider", address(0x42)),
+        None,
+    )
+    .await;
+    assert_problem(&removed_again, StatusCode::NOT_FOUND, "TAG_NOT_FOUND");
+
+    let wallet = get(&pool, &format!("/api/wallets/{}", address(0x42))).await;
+    assert_eq!(wallet.body["tags"], json!(["team wallet"]));
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn alert_feed_filters_by_type(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    for (alert_type, title) in [
+        ("new_token", "Fresh pair"),
+        ("whale_buy", "Big buy"),
+        ("whale_buy", "Bigger buy"),
+    ] {
+        let alert = NewAlert {
+            alert_type: alert_type.to_string(),
+            token_address: Some(address(1)),
+            token_symbol: Some("ALRT".to_string()),
+            wallet_address: None,
+            title: title.to_string(),
+            message: None,
+            bee_score: Some(70),
+            amount_usd: Some(BigDecimal::from(5_000)),
+            change_percent: None,
+            metadata: None,
+            source: None,
+        };
+        AlertEvent::create(&alert, &pool).await.unwrap();
+    }
+
+    let feed = get(&pool, "/api/alerts/feed").await;
+    assert_eq!(feed.status, StatusCode::OK);
+    assert_eq!(feed.body.as_array().unwrap().len(), 3);
+
+    let item = &feed.body[0];
+    assert_eq!(item["tokenAddress"], address(1).to_string());
+    assert_eq!(item["walletAddress"], Value::Null);
+    assert_eq!(item["isRead"], false);
+    assert_eq!(item["amountUsd"], 5_000.0);
+
+    let whales = get(&pool, "/api/alerts/feed?alert_type=whale_buy&limit=1").await;
+    let whales = whales.body.as_array().unwrap();
+    assert_eq!(whales.len(), 1);
+    assert_eq!(whales[0]["type"], "wallet_activity");
+
+    let invalid = get(&pool, "/api/alerts/feed?limit=-").await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+
+    // A rollup takes the place of the alerts it covers
+    let burst = AlertEvent::find_bursts(&["whale_buy"], 600, 2, &pool)
+        .await
+        .unwrap()
+        .remove(0);
+    let rollup = AlertEvent::create(&burst.rollup_alert(600), &pool)
+        .await
+        .unwrap();
+    AlertEvent::roll_up(rollup.id, &burst.member_ids, &pool)
+        .await
+        .unwrap();
+    let feed = get(&pool, "/api/alerts/feed").await;
+    let feed = feed.body.as_array().unwrap();
+    assert_eq!(feed.len(), 2);
+    let rolled = feed.iter().find(|a| a["alertCount"] == 2).unwrap();
+    assert_eq!(rolled["title"], "2 whale buy alerts on $ALRT in 10 min");
+    assert_eq!(rolled["amountUsd"], 10_000.0);
+    assert!(feed.iter().any(|a| a["alertCount"] == Value::Null));
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn alert_feed_carries_metadata_matching_its_type(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    let whale = NewAlert {
+        alert_type: "whale_sell".to_string(),
+        token_address: Some(address(1)),
+        token_symbol: Some("META".to_string()),
+        wallet_address: Some(address(2)),
+        title: "Whale Sell".to_string(),
+        message: None,
+        bee_score: None,
+        amount_usd: Some(BigDecimal::from(20_000)),
+        change_percent: None,
+        metadata: Some(AlertMetadata::Whale(WhaleMetadata {
+            tx_hash: Some(hash(7)),
+            price_impact_percent: Some(-12.5),
+            gas_price_percentile: Some(99),
+            mev_flags: vec!["high_gas".to_string()],
+            known_counterparty: None,
+        })),
+        source: None,
+    };
+    let stray = NewAlert {
+        alert_type: "dev_sell".to_string(),
+        title: "Dev Sell".to_string(),
+        metadata: None,
+        ..whale.clone()
+    };
+    AlertEvent::create(&whale, &pool).await.unwrap();
+    let stray = AlertEvent::create(&stray, &pool).await.unwrap();
+    // Metadata that isn't a dev sell payload, as written before they were typed
+    sqlx::query("UPDATE alert_events SET metadata = '{\"filter\": \"low-cap\"}' WHERE id = $1")
+        .bind(stray.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let feed = get(&pool, "/api/alerts/feed").await;
+    let feed = feed.body.as_array().unwrap();
+    let whale = feed.iter().find(|a| a["title"] == "Whale Sell").unwrap();
+    assert_eq!(whale["metadata"]["txHash"], hash(7).to_string());
+    assert_eq!(whale["metadata"]["priceImpactPercent"], -12.5);
+    assert_eq!(whale["metadata"]["mevFlags"], json!(["high_gas"]));
+    let stray = feed.iter().find(|a| a["title"] == "Dev Sell").unwrap();
+    assert_eq!(stray["metadata"], Value::Null);
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn alert_feed_is_polled_after_the_last_seen_id(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let mut ids = Vec::new();
+    for n in 1..=3 {
+        let token = create_token(&pool, n, "POLL").await;
+        let alert = AlertEvent::create_new_token_alert(&token, "POLL", &pool)
+            .await
+            .unwrap();
+        ids.push(alert.id);
+    }
+    // Settled: forward pages hold back alerts raised in the last moments
+    sqlx::query("UPDATE alert_events SET created_at = created_at - INTERVAL '1 minute'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let first = get(&pool, "/api/alerts/feed?limit=1").await;
+    assert_eq!(first.headers["x-poll-interval"], "5");
+    assert_eq!(first.body[0]["id"], ids[2].to_string());
+
+    // Two new since ids[0]: a full page means poll again right away
+    let page = get(
+        &pool,
+        &format!("/api/alerts/feed?after_id={}&limit=1", ids[0]),
+    )
+    .await;
+    assert_eq!(page.status, StatusCode::OK);
+    assert_eq!(page.body.as_array().unwrap().len(), 1);
+    assert_eq!(page.body[0]["id"], ids[1].to_string());
+    assert_eq!(page.headers["x-poll-interval"], "0");
+
+    let rest = get(
+        &pool,
+        &format!("/api/alerts/feed?after_id={}&limit=5", ids[1]),
+    )
+    .await;
+    assert_eq!(rest.body.as_array().unwrap().len(), 1);
+    assert_eq!(rest.body[0]["id"], ids[2].to_string());
+    assert_eq!(rest.headers["x-poll-interval"], "5");
+
+    let accept = [("accept", "application/vnd.beanbee.v2+json")];
+    let idle = send_with_headers(
+        &pool,
+        Method::GET,
+        &format!("/api/alerts/feed?after_id={}", ids[2]),
+        None,
+        &accept,
+    )
+    .await;
+    assert!(idle.body["data"].as_array().unwrap().is_empty());
+    assert_eq!(idle.body["meta"]["cursor"], ids[2].to_string());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn alert_feed_wait_returns_when_an_alert_is_raised(pool: PgPool) {
+    use std::time::{Duration, Instant};
+
+    clear_seed_data(&pool).await;
+    let seen = AlertEvent::create_new_token_alert(&address(1), "WAIT", &pool)
+        .await
+        .unwrap();
+    let shared = state(&pool);
+    tokio::spawn(alert_notify::run(
+        shared.alert_notifier.clone(),
+        pool.clone(),
+    ));
+    let poll = |wait: u64| {
+        let app = app(shared.clone());
+        let uri = format!("/api/alerts/feed?after_id={}&wait={}", seen.id, wait);
+        async move {
+            let started = Instant::now();
+            let response = app
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let interval = response.headers()["x-poll-interval"].clone();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: Value = serde_json::from_slice(&bytes).unwrap();
+            (started.elapsed(), interval, body)
+        }
+    };
+
+    // Nothing is raised: the request is held for the whole wait
+    let (elapsed, interval, body) = poll(1).await;
+    assert!(elapsed >= Duration::from_secs(1));
+    assert!(body.as_array().unwrap().is_empty());
+    assert_eq!(interval, "0");
+
+    let waiting = tokio::spawn(poll(30));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let raised = AlertEvent::create_new_token_alert(&address(2), "WAIT", &pool)
+        .await
+        .unwrap();
+    let (elapsed, _, body) = waiting.await.unwrap();
+    assert!(elapsed < Duration::from_secs(10));
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["id"], raised.id.to_string());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn alert_preferences_mute_tokens_per_key(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let key = [("x-api-key", "client-key-0123456789")];
+    for n in [1, 2] {
+        AlertEvent::create_new_token_alert(&address(n), "PREF", &pool)
+            .await
+            .unwrap();
+    }
+
+    let set = |token: u8, level: &str| {
+        let uri = format!("/api/alerts/preferences/{}", address(token));
+        let body = json!({ "level": level });
+        let pool = pool.clone();
+        async move { send_with_headers(&pool, Method::PUT, &uri, Some(body), &key).await }
+    };
+
+    let muted = set(1, "mute").await;
+    assert_eq!(muted.status, StatusCode::OK);
+    assert_eq!(muted.body["level"], "mute");
+    assert_eq!(muted.body["tokenAddress"], address(1).to_string());
+    let critical = set(2, "critical").await;
+    assert_eq!(critical.body["level"], "critical");
+    let invalid = set(2, "loud").await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_BODY");
+
+    let short = send_with_headers(
+        &pool,
+        Method::PUT,
+        &format!("/api/alerts/preferences/{}", address(1)),
+        Some(json!({ "level": "mute" })),
+        &[("x-api-key", "short")],
+    )
+    .await;
+    assert_problem(&short, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    // Neither new-token alert is critical, so the key sees none of them
+    let feed = send_with_headers(&pool, Method::GET, "/api/alerts/feed", None, &key).await;
+    assert_eq!(feed.body, json!([]));
+    let anonymous = get(&pool, "/api/alerts/feed").await;
+    assert_eq!(anonymous.body.as_array().unwrap().len(), 2);
+
+    let reset = set(2, "all").await;
+    assert_eq!(reset.body["level"], "all");
+    assert_eq!(reset.body["updatedAt"], Value::Null);
+    let feed = send_with_headers(&pool, Method::GET, "/api/alerts/feed", None, &key).await;
+    let feed = feed.body.as_array().unwrap();
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0]["tokenAddress"], address(2).to_string());
+
+    let listed = send_with_headers(&pool, Method::GET, "/api/alerts/preferences", None, &key).await;
+    assert_eq!(
+        listed.body,
+        json!([{
+            "tokenAddress": address(1).to_string(),
+            "level": "mute",
+            "updatedAt": listed.body[0]["updatedAt"],
+        }])
+    );
+    let anonymous = get(&pool, "/api/alerts/preferences").await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn top_pairs_rank_pools_by_volume_and_fees(pool: PgPool) {
+    clear_seed_data(&pool).await;
+
+    for (n, symbol, trades) in [(1u8, "BIG", 3u8), (2, "SML", 1)] {
+        let token = create_token(&pool, n, symbol).await;
+        let pair = NewPair {
+            address: address(n + 100),
+            token0_address: address(150),
+            token1_address: token,
+            factory_address: address(200),
+            base_token_index: 0,
+            block_number: 1_000,
+            discovered: false,
+        };
+        Pair::create(&pair, &pool).await.unwrap();
+
+        for i in 0..trades {
+            let swap = NewSwap {
+                tx_hash: hash(n * 10 + i),
+                block_number: 2_000,
+                log_index: i as i32,
+                timestamp: Utc::now() - Duration::minutes(5),
+                pair_address: address(n + 100),
+                token_address: token,
+                wallet_address: address(50),
+                trade_type: "buy".to_string(),
+                amount_tokens: None,
+                amount_bnb: None,
+                amount_usd: Some(BigDecimal::from(1_000)),
+                price_usd: None,
+                is_whale: false,
+                tx_index: None,
+                gas_price_percentile: None,
+                mev_flags: Vec::new(),
+                legs: None,
+                source_log_id: None,
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+    }
+    Pair::refresh_volume_stats(25, &pool).await.unwrap();
+
+    let top = get(&pool, "/api/pairs/top?sort=volume").await;
+    assert_eq!(top.status, StatusCode::OK);
+    let list = top.body.as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["address"], address(101).to_string());
+    assert_eq!(list[0]["tokenSymbol"], "BIG");
+    assert_eq!(list[0]["volume24h"], 3_000.0);
+    assert_eq!(list[0]["fees24h"], 7.5);
+    assert_eq!(list[0]["volumeShare"], 75.0);
+    assert_eq!(list[1]["trades24h"], 1);
+
+    let by_fees = get(&pool, "/api/pairs/top?sort=fees&limit=1").await;
+    assert_eq!(by_fees.body.as_array().unwrap().len(), 1);
+
+    let invalid = get(&pool, "/api/pairs/top?sort=hype").await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn factory_stats_summarize_a_factorys_pairs(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let pancake = "0xca143ce32fe78f1f7019d7d551a6402fc5350c73";
+
+    for n in [1u8, 2] {
+        let token = create_token(&pool, n, "FAC").await;
+        let pair = NewPair {
+            address: address(n + 100),
+            token0_address: address(150),
+            token1_address: token,
+            factory_address: pancake.parse().unwrap(),
+            base_token_index: 0,
+            block_number: 1_000,
+            discovered: false,
+        };
+        Pair::create(&pair, &pool).await.unwrap();
+    }
+
+    let response = get(&pool, &format!("/api/factories/{}/stats?days=7", pancake)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["name"], "PancakeSwap V2");
+    assert_eq!(response.body["pairs"], 2);
+    let days = response.body["pairsPerDay"].as_array().unwrap();
+    assert_eq!(days.len(), 8);
+    assert_eq!(days[7]["pairs"], 2);
+    assert_eq!(response.body["lpLockedPercent"], 0.0);
+    // No liquidity history yet
+    assert_eq!(response.body["rugRatePercent"], Value::Null);
+
+    let other = get(&pool, &format!("/api/factories/{}/stats", address(200))).await;
+    assert_eq!(other.body["name"], Value::Null);
+    assert_eq!(other.body["pairs"], 0);
+
+    let invalid = get(&pool, &format!("/api/factories/{}/stats?days=0", pancake)).await;
+    assert_problem(&invalid, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_quote_simulates_constant_product(pool: PgPool) {
+    let token = create_token(&pool, 1, "QTE").await;
+    // A stablecoin base with 6 decimals
+    let base = NewToken {
+        address: address(150),
+        name: Some("USD Coin".to_string()),
+        symbol: Some("USDC".to_string()),
+        name_raw: None,
+        symbol_raw: None,
+        name_spoofed: false,
+        decimals: Some(6),
+        total_supply: None,
+        pair_address: None,
+        creator_address: None,
+        block_number: None,
+    };
+    Token::create(&base, &pool).await.unwrap();
+    let pair = NewPair {
+        address: address(101),
+        token0_address: address(150),
+        token1_address: token,
+        factory_address: address(200),
+        base_token_index: 0,
+        block_number: 1_001,
+        discovered: false,
+    };
+    Pair::create(&pair, &pool).await.unwrap();
+
+    let no_reserves = get(&pool, &format!("/api/tokens/{}/quote?amount_bnb=1", token)).await;
+    assert_problem(
+        &no_reserves,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "NO_LIQUIDITY",
+    );
+
+    // 10 USDC against 1M tokens
+    let ether = BigDecimal::from(10u64.pow(18));
+    Pair::update_reserves(
+        &address(101),
+        &BigDecimal::from(10_000_000),
+        &(BigDecimal::from(1_000_000) * &ether),
+        &pool,
+    )
+    .await
+    .unwrap();
+
+    let buy = get(
+        &pool,
+        &format!("/api/tokens/{}/quote?amount_bnb=1&side=buy", token),
+    )
+    .await;
+    assert_eq!(buy.status, StatusCode::OK);
+    assert_eq!(buy.body["pairAddress"], address(101).to_string());
+    assert_eq!(buy.body["baseTokenAddress"], address(150).to_string());
+    assert_eq!(buy.body["side"], "buy");
+    let tokens_out = buy.body["amountTokens"].as_f64().unwrap();
+    assert!((tokens_out - 0.9975 * 1_000_000.0 / 10.9975).abs() < 1e-6);
+    assert!((buy.body["spotPriceBnb"].as_f64().unwrap() - 1e-5).abs() < 1e-12);
+    let impact = buy.body["priceImpactPercent"].as_f64().unwrap();
+    assert!(impact > 9.0 && impact < 10.0);
+
+    let sell = get(
+        &pool,
+        &format!("/api/tokens/{}/quote?amount_tokens=1000&side=sell", token),
+    )
+    .await;
+    assert_eq!(sell.status, StatusCode::OK);
+    let bnb_out = sell.body["amountBnb"].as_f64().unwrap();
+    assert!(bnb_out > 0.0 && bnb_out < 0.01);
+
+    let missing_amount = get(
+        &pool,
+        &format!("/api/tokens/{}/quote?side=sell&amount_bnb=1", token),
+    )
+    .await;
+    assert_problem(&missing_amount, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+    let bad_side = get(
+        &pool,
+        &format!("/api/tokens/{}/quote?amount_bnb=1&side=hold", token),
+    )
+    .await;
+    assert_problem(&bad_side, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+    let negative = get(&pool, &format!("/api/tokens/{}/quote?amount_bnb=-1", token)).await;
+    assert_problem(&negative, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn social_ingest_requires_api_key(pool: PgPool) {
+    let token = create_token(&pool, 1, "SOC").await;
+    let reading = json!({
+        "tokenAddress": token.to_string(),
+        "source": "telegram-bot",
+        "telegramMembers": 1_500,
+        "xMentions24h": 42,
+    });
+    let ingest = |key: Option<&'static str>, body: Value| {
+        let pool = pool.clone();
+        async move {
+            let headers: Vec<_> = key.map(|k| ("x-api-key", k)).into_iter().collect();
+            send_with_headers(
+                &pool,
+                Method::POST,
+                "/api/ingest/social",
+                Some(body),
+                &headers,
+            )
+            .await
+        }
+    };
+
+    let missing = ingest(None, reading.clone()).await;
+    assert_problem(&missing, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+    let wrong = ingest(Some("nope"), reading.clone()).await;
+    assert_problem(&wrong, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let created = ingest(Some(INGEST_KEY), reading.clone()).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["tokenAddress"], token.to_string());
+    assert_eq!(created.body["telegramMembers"], 1_500);
+    assert_eq!(created.body["xMentions24h"], 42);
+
+    let unknown = ingest(
+        Some(INGEST_KEY),
+        json!({ "tokenAddress": address(9).to_string(), "source": "x", "xMentions24h": 1 }),
+    )
+    .await;
+    assert_problem(&unknown, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+
+    let empty = ingest(
+        Some(INGEST_KEY),
+        json!({ "tokenAddress": token.to_string(), "source": "x" }),
+    )
+    .await;
+    assert_problem(&empty, StatusCode::BAD_REQUEST, "INVALID_BODY");
+
+    // Without a configured key ingestion is closed, not open
+    let disabled = app(Arc::new(AppState {
+        db_pool: pool.clone(),
+        ingest_api_key: None,
+        precision: Precision::default(),
+        demo: false,
+        rate_limiter: RateLimiter::new(0),
+        legacy_sunset: LEGACY_SUNSET.to_string(),
+        alert_poll_interval_secs: 5,
+        streams: Arc::new(Streams::new(16)),
+        alert_notifier: AlertNotifier::new(),
+        wallet_profiles: TtlCache::new(std::time::Duration::from_secs(60)),
+        query_metrics: QueryMetrics::new(QueryBudget {
+            max_queries: 25,
+            max_db_time: std::time::Duration::from_millis(500),
+        }),
+    }))
+    .oneshot(
+        Request::post("/api/ingest/social")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-api-key", INGEST_KEY)
+            .body(Body::from(reading.to_string()))
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(disabled.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn tokens_carry_badges_from_their_flags(pool: PgPool) {
+    let token = create_token(&pool, 1, "BADGE").await;
+    let unlock = Utc::now() + Duration::days(200);
+    Token::update_lp_lock(&token, true, None, Some(unlock), &pool)
+        .await
+        .unwrap();
+    Token::record_dev_sell(&token, Utc::now(), &pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE tokens SET ownership_renounced = TRUE, bee_score = 50 WHERE address = $1")
+        .bind(token)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let detail = get(&pool, &format!("/api/tokens/{}", token)).await;
+    let badges = detail.body["badges"].as_array().unwrap();
+    let labels: Vec<_> = badges.iter().map(|b| b["label"].as_str().unwrap()).collect();
+    assert_eq!(labels, vec!["Dev sold", "LP Locked 6mo", "Renounced"]);
+    assert_eq!(badges[0]["kind"], "dev_sold");
+    assert_eq!(badges[0]["tone"], "risk");
+    assert_eq!(badges[1]["tone"], "positive");
+
+    refresh_lists(&pool).await;
+    let hot = get(&pool, "/api/tokens/hot").await;
+    let item = hot
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["address"] == token.to_string())
+        .unwrap();
+    assert_eq!(item["badges"], detail.body["badges"]);
+
+    let plain = create_token(&pool, 2, "PLAIN").await;
+    let detail = get(&pool, &format!("/api/tokens/{}", plain)).await;
+    assert_eq!(detail.body["badges"], json!([]));
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn external_reports_mark_the_token_with_the_worst_severity(pool: PgPool) {
+    let token = create_token(&pool, 1, "RUG").await;
+    let key = [("x-api-key", INGEST_KEY)];
+    let report = |source: &str, severity: &str| {
+        json!({
+            "tokenAddress": token.to_string(),
+            "source": source,
+            "severity": severity,
+            "category": "honeypot",
+            "url": "https://gopluslabs.io/token-security/56/x",
+            "confirmed": true,
+        })
+    };
+    let ingest = |body: Value| {
+        let pool = pool.clone();
+        async move {
+            send_with_headers(&pool, Method::POST, "/api/ingest/reports", Some(body), &key).await
+        }
+    };
+
+    let anonymous = send(
+        &pool,
+        Method::POST,
+        "/api/ingest/reports",
+        Some(report("goplus", "high")),
+    )
+    .await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let created = ingest(report("goplus", "high")).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["severity"], "high");
+    assert_eq!(created.body["confirmed"], true);
+    assert_eq!(
+        ingest(report("community-bot", "low")).await.status,
+        StatusCode::CREATED
+    );
+
+    let detail = get(&pool, &format!("/api/tokens/{}", token)).await;
+    assert_eq!(detail.body["externalRisk"], "high");
+    assert_eq!(detail.body["badges"][0]["kind"], "honeypot_risk");
+
+    // Both reports share one pending rescore of the token
+    let rescore = Job::find_open(&format!("rescore:{}", token), &pool)
+        .await
+        .unwrap()
+        .expect("rescore queued");
+    assert_eq!(rescore.kind, "rescore");
+    assert_eq!(rescore.params.0, json!({ "tokens": [token.to_string()] }));
+    assert_eq!(Job::count_open(JobKind::Rescore, &pool).await.unwrap(), 1);
+
+    let unknown_severity = ingest(report("goplus", "apocalyptic")).await;
+    assert_problem(&unknown_severity, StatusCode::BAD_REQUEST, "INVALID_BODY");
+    let mut bad_url = report("goplus", "high");
+    bad_url["url"] = json!("ftp://example.com");
+    assert_problem(&ingest(bad_url).await, StatusCode::BAD_REQUEST, "INVALID_BODY");
+    let mut unknown_token = report("goplus", "high");
+    unknown_token["tokenAddress"] = json!(address(9).to_string());
+    assert_problem(
+        &ingest(unknown_token).await,
+        StatusCode::NOT_FOUND,
+        "TOKEN_NOT_FOUND",
+    );
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn alert_webhooks_are_managed_with_the_api_key(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+    let body = json!({
+        "url": "https://example.com/hooks/beanbee",
+        "secret": "0123456789abcdef",
+        "alertTypes": ["whale_sell", "whale_buy", "whale_buy"],
+    });
+
+    let anonymous = send(
+        &pool,
+        Method::POST,
+        "/api/alerts/webhooks",
+        Some(body.clone()),
+    )
+    .await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    // A client key alone is not enough to register a webhook
+    let client = [("x-api-key", "client-key-0001")];
+    let rejected = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/alerts/webhooks",
+        Some(body.clone()),
+        &client,
+    )
+    .await;
+    assert_problem(&rejected, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let created = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/alerts/webhooks",
+        Some(body),
+        &key,
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(
+        created.body["alertTypes"],
+        json!(["whale_buy", "whale_sell"])
+    );
+    assert!(created.body.get("secret").is_none());
+    let id = created.body["id"].as_i64().unwrap();
+
+    for bad in [
+        json!({ "url": "ftp://example.com", "secret": "0123456789abcdef" }),
+        json!({ "url": "https://example.com", "secret": "short" }),
+        json!({ "url": "https://example.com", "secret": "0123456789abcdef", "alertTypes": ["nope"] }),
+        json!({ "url": "http://localhost:8080/hook", "secret": "0123456789abcdef" }),
+        json!({ "url": "http://127.0.0.1/hook", "secret": "0123456789abcdef" }),
+        json!({ "url": "http://10.0.0.5/hook", "secret": "0123456789abcdef" }),
+        json!({ "url": "http://169.254.169.254/latest/meta-data", "secret": "0123456789abcdef" }),
+        json!({ "url": "http://[::1]/hook", "secret": "0123456789abcdef" }),
+        json!({ "url": "http://[::ffff:192.168.1.1]/hook", "secret": "0123456789abcdef" }),
+        json!({ "url": "https://db.internal/hook", "secret": "0123456789abcdef" }),
+        json!({ "url": "https://intranet/hook", "secret": "0123456789abcdef" }),
+    ] {
+        let rejected =
+            send_with_headers(&pool, Method::POST, "/api/alerts/webhooks", Some(bad), &key).await;
+        assert_problem(&rejected, StatusCode::BAD_REQUEST, "INVALID_BODY");
+    }
+
+    let listed = send_with_headers(&pool, Method::GET, "/api/alerts/webhooks", None, &key).await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(listed.body.as_array().unwrap().len(), 1);
+
+    let uri = format!("/api/alerts/webhooks/{}", id);
+    let deleted = send_with_headers(&pool, Method::DELETE, &uri, None, &key).await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    let missing = send_with_headers(&pool, Method::DELETE, &uri, None, &key).await;
+    assert_problem(&missing, StatusCode::NOT_FOUND, "WEBHOOK_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn listeners_can_be_enabled_and_disabled(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+
+    let anonymous = get(&pool, "/api/admin/listeners").await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let listed = send_with_headers(&pool, Method::GET, "/api/admin/listeners", None, &key).await;
+    assert_eq!(listed.status, StatusCode::OK);
+    let swap = listed
+        .body
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|l| l["name"] == "Swap")
+        .unwrap()
+        .clone();
+    assert_eq!(swap["enabled"], false);
+    assert_eq!(swap["address"], Value::Null);
+    assert_eq!(
+        swap["topic"],
+        "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"
+    );
+
+    let resumed = send_with_headers(
+        &pool,
+        Method::PATCH,
+        "/api/admin/listeners/Swap",
+        Some(json!({ "enabled": true, "pollIntervalSecs": 15 })),
+        &key,
+    )
+    .await;
+    assert_eq!(resumed.status, StatusCode::OK);
+    assert_eq!(resumed.body["enabled"], true);
+    assert_eq!(resumed.body["pollIntervalSecs"], 15);
+
+    let empty = send_with_headers(
+        &pool,
+        Method::PATCH,
+        "/api/admin/listeners/Swap",
+        Some(json!({})),
+        &key,
+    )
+    .await;
+    assert_problem(&empty, StatusCode::BAD_REQUEST, "INVALID_BODY");
+
+    let unknown = send_with_headers(
+        &pool,
+        Method::PATCH,
+        "/api/admin/listeners/Nope",
+        Some(json!({ "enabled": false })),
+        &key,
+    )
+    .await;
+    assert_problem(&unknown, StatusCode::NOT_FOUND, "LISTENER_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn processing_lag_is_exported_to_operators(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+    let breached = NewProcessingLag {
+        event_type: "swap".to_string(),
+        target_secs: 30,
+        samples: 12,
+        p50_lag_ms: 20_000,
+        p95_lag_ms: 95_000,
+        max_lag_ms: 120_000,
+        breached_since: Some(Utc::now()),
+    };
+    ProcessingLag::upsert(&breached, &pool).await.unwrap();
+    let healthy = NewProcessingLag {
+        event_type: "new_pair".to_string(),
+        p95_lag_ms: 8_000,
+        breached_since: None,
+        ..breached.clone()
+    };
+    ProcessingLag::upsert(&healthy, &pool).await.unwrap();
+
+    let anonymous = get(&pool, "/api/admin/lag").await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let response = send_with_headers(&pool, Method::GET, "/api/admin/lag", None, &key).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let lags = response.body.as_array().unwrap();
+    assert_eq!(lags.len(), 2);
+    assert_eq!(lags[0]["eventType"], "new_pair");
+    assert_eq!(lags[0]["breached"], false);
+    assert_eq!(lags[1]["eventType"], "swap");
+    assert_eq!(lags[1]["targetSecs"], 30);
+    assert_eq!(lags[1]["p95LagMs"], 95_000);
+    assert_eq!(lags[1]["breached"], true);
+    assert!(lags[1]["breachedSince"].is_string());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn event_metrics_are_exported_to_operators(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+    let mut latency_buckets = vec![0; 11];
+    latency_buckets[..3].copy_from_slice(&[2, 4, 2]);
+    let swaps = EventCounts {
+        event_type: "swap".to_string(),
+        events: 10,
+        decode_failures: 1,
+        handler_errors: 1,
+        skipped: 0,
+        latency_buckets,
+        latency_sum_ms: 40,
+        failing_since: Some(Utc::now()),
+    };
+    ProcessorEventMetric::add(&swaps, &pool).await.unwrap();
+
+    let anonymous = get(&pool, "/api/admin/event-metrics").await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let response =
+        send_with_headers(&pool, Method::GET, "/api/admin/event-metrics", None, &key).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let swap = &response.body[0];
+    assert_eq!(swap["eventType"], "swap");
+    assert_eq!(swap["decodeFailureRate"], 10.0);
+    assert_eq!(swap["handlerErrorRate"], 10.0);
+    assert_eq!(swap["latencyAvgMs"], 5.0);
+    assert_eq!(swap["latencyBuckets"][1], json!({ "leMs": 5, "count": 6 }));
+    assert_eq!(swap["latencyBuckets"][10], json!({ "leMs": null, "count": 8 }));
+    assert_eq!(swap["failing"], true);
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn processing_errors_are_listed_for_operators(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+    for (log_id, stage) in [("7", ErrorStage::Decode), ("8", ErrorStage::Handler)] {
+        let failed = NewProcessingError {
+            log_id: log_id.to_string(),
+            topic: format!("0x{}", "d7".repeat(32)),
+            tx_hash: Hash32::new([2; 32]),
+            log_index: 1,
+            stage,
+            error: "Swap handler failed".to_string(),
+        };
+        ProcessingError::create(&failed, &pool).await.unwrap();
+    }
+
+    let anonymous = get(&pool, "/api/admin/errors").await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let uri = "/api/admin/errors?since=2020-01-01T00:00:00Z";
+    let response = send_with_headers(&pool, Method::GET, uri, None, &key).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let errors = response.body.as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    // Oldest first when paging forward from `since`
+    assert_eq!(errors[0]["logId"], "7");
+    assert_eq!(errors[0]["stage"], "decode");
+    assert_eq!(errors[0]["txHash"], format!("0x{}", "02".repeat(32)));
+    assert_eq!(errors[1]["stage"], "handler");
+
+    let latest = send_with_headers(&pool, Method::GET, "/api/admin/errors", None, &key).await;
+    assert_eq!(latest.body[0]["logId"], "8");
+
+    let uri = "/api/admin/errors?since=2999-01-01T00:00:00Z";
+    let response = send_with_headers(&pool, Method::GET, uri, None, &key).await;
+    assert_eq!(response.body, json!([]));
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn query_metrics_count_statements_per_endpoint(pool: PgPool) {
+    use tracing::instrument::WithSubscriber;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let token = create_token(&pool, 1, "QUERY").await;
+    let shared = Arc::new(AppState {
+        query_metrics: QueryMetrics::new(QueryBudget {
+            max_queries: 1,
+            max_db_time: std::time::Duration::ZERO,
+        }),
+        ..(*state(&pool)).clone()
+    });
+    let dispatch =
+        tracing::Dispatch::new(tracing_subscriber::registry().with(query_stats::layer()));
+    let call = |uri: String, key: bool| {
+        let app = app(shared.clone());
+        let dispatch = dispatch.clone();
+        async move {
+            let mut request = Request::get(uri);
+            if key {
+                request = request.header("x-api-key", INGEST_KEY);
+            }
+            let response = app
+                .oneshot(request.body(Body::empty()).unwrap())
+                .with_subscriber(dispatch)
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&bytes).unwrap_or(Value::Null))
+        }
+    };
+
+    for _ in 0..2 {
+        let (status, _) = call(format!("/api/tokens/{}", token), false).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = call("/api/admin/query-metrics".to_string(), false).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, metrics) = call("/api/admin/query-metrics".to_string(), true).await;
+    assert_eq!(status, StatusCode::OK);
+    let detail = metrics
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["endpoint"] == "GET /api/tokens/:address")
+        .unwrap();
+    assert_eq!(detail["requests"], 2);
+    assert!(detail["maxQueries"].as_u64().unwrap() > 1);
+    assert!(detail["queries"].as_u64().unwrap() >= 2 * 2);
+    assert!(detail["dbTimeMs"].as_f64().unwrap() > 0.0);
+    assert_eq!(detail["overBudget"], 2);
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_rescans_are_queued_and_tracked(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+    let token = create_token(&pool, 1, "RESCAN").await;
+    let uri = format!("/api/admin/tokens/{}/rescan", token);
+
+    let anonymous = send(&pool, Method::POST, &uri, Some(json!({}))).await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let queued = send_with_headers(
+        &pool,
+        Method::POST,
+        &uri,
+        Some(json!({ "fromBlock": 1_500, "toBlock": 2_000 })),
+        &key,
+    )
+    .await;
+    assert_eq!(queued.status, StatusCode::ACCEPTED);
+    assert_eq!(queued.body["tokenAddress"], token.to_string());
+    assert_eq!(queued.body["fromBlock"], 1_500);
+    assert_eq!(queued.body["status"], "pending");
+
+    let whole = send_with_headers(&pool, Method::POST, &uri, Some(json!({})), &key).await;
+    assert_eq!(whole.status, StatusCode::ACCEPTED);
+    assert_eq!(whole.body["fromBlock"], Value::Null);
+    assert_eq!(whole.body["toBlock"], Value::Null);
+
+    let id = queued.body["id"].as_i64().unwrap();
+    TokenRescan::claim_next(&pool).await.unwrap();
+    TokenRescan::finish(id as i32, 120, &pool).await.unwrap();
+    let status = send_with_headers(
+        &pool,
+        Method::GET,
+        &format!("/api/admin/rescans/{}", id),
+        None,
+        &key,
+    )
+    .await;
+    assert_eq!(status.status, StatusCode::OK);
+    assert_eq!(status.body["status"], "done");
+    assert_eq!(status.body["logsReplayed"], 120);
+    assert!(status.body["finishedAt"].is_string());
+
+    let backwards = send_with_headers(
+        &pool,
+        Method::POST,
+        &uri,
+        Some(json!({ "fromBlock": 2_000, "toBlock": 1_500 })),
+        &key,
+    )
+    .await;
+    assert_problem(&backwards, StatusCode::BAD_REQUEST, "INVALID_BODY");
+
+    let unknown_token = send_with_headers(
+        &pool,
+        Method::POST,
+        &format!("/api/admin/tokens/{}/rescan", address(9)),
+        Some(json!({})),
+        &key,
+    )
+    .await;
+    assert_problem(&unknown_token, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+
+    let unknown = send_with_headers(&pool, Method::GET, "/api/admin/rescans/999", None, &key).await;
+    assert_problem(&unknown, StatusCode::NOT_FOUND, "RESCAN_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn tracking_a_token_queues_one_job_and_reports_its_progress(pool: PgPool) {
+    let key = [("x-api-key", "client-key-0123456789")];
+    let body = json!({ "address": address(7).to_string() });
+    let anonymous = send(&pool, Method::POST, "/api/tokens/track", Some(body.clone())).await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let queued =
+        send_with_headers(&pool, Method::POST, "/api/tokens/track", Some(body.clone()), &key).await;
+    assert_eq!(queued.status, StatusCode::ACCEPTED);
+    assert_eq!(queued.body["kind"], "token_import");
+    assert_eq!(queued.body["status"], "pending");
+    assert_eq!(queued.body["params"]["address"], address(7).to_string());
+    let id = queued.body["id"].as_i64().unwrap();
+
+    // Asking again returns the same job
+    let again =
+        send_with_headers(&pool, Method::POST, "/api/tokens/track", Some(body), &key).await;
+    assert_eq!(again.body["id"], id);
+
+    // The processor indexes the pairs, then replays the history
+    Job::claim_next(600, &pool).await.unwrap();
+    let cancelled = Job::report_progress(id as i32, 1, Some(2), &pool)
+        .await
+        .unwrap();
+    assert!(!cancelled);
+    let uri = format!("/api/jobs/{}", id);
+    let replaying = get(&pool, &uri).await;
+    assert_eq!(replaying.body["status"], "running");
+    assert_eq!(replaying.body["progressDone"], 1);
+    assert_eq!(replaying.body["progressTotal"], 2);
+    assert_eq!(replaying.body["finishedAt"], Value::Null);
+
+    let result = json!({ "pairsIndexed": 2, "rescanId": 1, "logsReplayed": 300 });
+    Job::finish(id as i32, &result, &pool).await.unwrap();
+    let done = get(&pool, &uri).await;
+    assert_eq!(done.body["status"], "done");
+    assert_eq!(done.body["result"]["logsReplayed"], 300);
+    assert!(done.body["finishedAt"].is_string());
+
+    let tracked = create_token(&pool, 1, "TRACKED").await;
+    let conflict = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/tokens/track",
+        Some(json!({ "address": tracked.to_string() })),
+        &key,
+    )
+    .await;
+    assert_problem(&conflict, StatusCode::CONFLICT, "TOKEN_ALREADY_TRACKED");
+
+    let invalid = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/tokens/track",
+        Some(json!({ "address": "0x123" })),
+        &key,
+    )
+    .await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+
+    let unknown = get(&pool, "/api/jobs/999").await;
+    assert_problem(&unknown, StatusCode::NOT_FOUND, "JOB_NOT_FOUND");
+
+    // Past the cap only imports already queued are returned
+    for n in 0..MAX_OPEN_IMPORTS {
+        let job = NewJob {
+            kind: JobKind::TokenImport,
+            params: json!({}),
+            dedupe_key: Some(format!("token_import:filler-{}", n)),
+        };
+        Job::create(&job, &pool).await.unwrap();
+    }
+    let full = send_with_headers(
+        &pool,
+        Method::POST,
+        "/api/tokens/track",
+        Some(json!({ "address": address(8).to_string() })),
+        &key,
+    )
+    .await;
+    assert_problem(&full, StatusCode::SERVICE_UNAVAILABLE, "IMPORT_QUEUE_FULL");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn jobs_are_queued_listed_and_cancelled_with_the_key(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+    let rescore = json!({ "kind": "rescore", "params": { "tokens": [address(1).to_string()] } });
+
+    let anonymous = send(&pool, Method::POST, "/api/jobs", Some(rescore.clone())).await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let first = send_with_headers(&pool, Method::POST, "/api/jobs", Some(rescore), &key).await;
+    assert_eq!(first.status, StatusCode::ACCEPTED);
+    assert_eq!(first.body["kind"], "rescore");
+    assert_eq!(first.body["status"], "pending");
+    let everything = json!({ "kind": "rescore" });
+    let second = send_with_headers(&pool, Method::POST, "/api/jobs", Some(everything), &key).await;
+    assert_eq!(second.status, StatusCode::ACCEPTED);
+    assert_eq!(second.body["params"], json!({}));
+
+    let unknown_kind = json!({ "kind": "defrag" });
+    let unknown_kind =
+        send_with_headers(&pool, Method::POST, "/api/jobs", Some(unknown_kind), &key).await;
+    assert_problem(&unknown_kind, StatusCode::BAD_REQUEST, "INVALID_BODY");
+    let no_address = json!({ "kind": "reconcile", "params": {} });
+    let no_address =
+        send_with_headers(&pool, Method::POST, "/api/jobs", Some(no_address), &key).await;
+    assert_problem(&no_address, StatusCode::BAD_REQUEST, "INVALID_BODY");
+    let untracked = json!({ "kind": "reconcile", "params": { "address": address(9).to_string() } });
+    let untracked =
+        send_with_headers(&pool, Method::POST, "/api/jobs", Some(untracked), &key).await;
+    assert_problem(&untracked, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+
+    // A pending job is cancelled at once; a running one is asked to stop
+    let pending = second.body["id"].as_i64().unwrap();
+    let running = Job::claim_next(600, &pool).await.unwrap().unwrap();
+    assert_eq!(running.id as i64, first.body["id"].as_i64().unwrap());
+
+    let uri = format!("/api/jobs/{}/cancel", pending);
+    let cancelled = send_with_headers(&pool, Method::POST, &uri, None, &key).await;
+    assert_eq!(cancelled.body["status"], "cancelled");
+    assert!(cancelled.body["finishedAt"].is_string());
+    let uri = format!("/api/jobs/{}/cancel", running.id);
+    let stopping = send_with_headers(&pool, Method::POST, &uri, None, &key).await;
+    assert_eq!(stopping.body["status"], "running");
+    assert_eq!(stopping.body["cancelRequested"], true);
+    let stop = Job::report_progress(running.id, 1, Some(1), &pool)
+        .await
+        .unwrap();
+    assert!(stop);
+
+    let listed = send_with_headers(&pool, Method::GET, "/api/jobs", None, &key).await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(listed.body.as_array().unwrap().len(), 2);
+    let filtered =
+        send_with_headers(&pool, Method::GET, "/api/jobs?status=cancelled", None, &key).await;
+    assert_eq!(filtered.body.as_array().unwrap().len(), 1);
+    assert_eq!(filtered.body[0]["id"], pending);
+    let bad_status =
+        send_with_headers(&pool, Method::GET, "/api/jobs?status=stuck", None, &key).await;
+    assert_problem(&bad_status, StatusCode::BAD_REQUEST, "INVALID_QUERY");
+
+    let anonymous = get(&pool, "/api/jobs").await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+    let missing = send_with_headers(&pool, Method::POST, "/api/jobs/999/cancel", None, &key).await;
+    assert_problem(&missing, StatusCode::NOT_FOUND, "JOB_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn holder_verifications_are_queued_and_tracked(pool: PgPool) {
+    let key = [("x-api-key", INGEST_KEY)];
+    let token = create_token(&pool, 1, "VERIFY").await;
+    let uri = format!("/api/admin/tokens/{}/verify-holders", token);
+
+    let anonymous = send(&pool, Method::POST, &uri, None).await;
+    assert_problem(&anonymous, StatusCode::UNAUTHORIZED, "UNAUTHORIZED");
+
+    let queued = send_with_headers(&pool, Method::POST, &uri, None, &key).await;
+    assert_eq!(queued.status, StatusCode::ACCEPTED);
+    assert_eq!(queued.body["tokenAddress"], token.to_string());
+    assert_eq!(queued.body["status"], "pending");
+    assert_eq!(queued.body["holderCount"], Value::Null);
+
+    let id = queued.body["id"].as_i64().unwrap();
+    let counts = VerificationCounts {
+        holders_checked: 400,
+        balances_corrected: 2,
+        holders_pruned: 31,
+    };
+    HolderVerification::claim_next(600, &pool).await.unwrap();
+    HolderVerification::finish(id as i32, &counts, 5_000, &pool)
+        .await
+        .unwrap();
+    let status = send_with_headers(
+        &pool,
+        Method::GET,
+        &format!("/api/admin/holder-verifications/{}", id),
+        None,
+        &key,
+    )
+    .await;
+    assert_eq!(status.status, StatusCode::OK);
+    assert_eq!(status.body["status"], "done");
+    assert_eq!(status.body["holdersPruned"], 31);
+    assert_eq!(status.body["blockNumber"], 5_000);
+    assert!(status.body["holderCount"].is_number());
+    assert!(status.body["finishedAt"].is_string());
+
+    let unknown_token = send_with_headers(
+        &pool,
+        Method::POST,
+        &format!("/api/admin/tokens/{}/verify-holders", address(9)),
+        None,
+        &key,
+    )
+    .await;
+    assert_problem(&unknown_token, StatusCode::NOT_FOUND, "TOKEN_NOT_FOUND");
+
+    let unknown = send_with_headers(
+        &pool,
+        Method::GET,
+        "/api/admin/holder-verifications/999",
+        None,
+        &key,
+    )
+    .await;
+    assert_problem(&unknown, StatusCode::NOT_FOUND, "VERIFICATION_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn list_envelopes_are_negotiated_by_header(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let token = create_token(&pool, 1, "ENV").await;
+    AlertEvent::create_new_token_alert(&token, "ENV", &pool)
+        .await
+        .unwrap();
+
+    // Without the header lists stay bare arrays
+    let legacy = get(&pool, "/api/alerts/feed").await;
+    assert_eq!(legacy.status, StatusCode::OK);
+    assert_eq!(legacy.body.as_array().unwrap().len(), 1);
+
+    let accept = [("accept", "application/vnd.beanbee.v2+json")];
+    let enveloped = send_with_headers(&pool, Method::GET, "/api/alerts/feed", None, &accept).await;
+    assert_eq!(enveloped.status, StatusCode::OK);
+    assert_eq!(enveloped.body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(enveloped.body["data"][0]["type"], legacy.body[0]["type"]);
+    let meta = &enveloped.body["meta"];
+    assert_eq!(meta["count"], 1);
+    assert!(meta.get("total").is_none());
+    // The feed's cursor is the newest id, to poll from with ?after_id=
+    assert_eq!(meta["cursor"], enveloped.body["data"][0]["id"]);
+    assert!(meta["generatedAt"].is_string());
+    // No listener has polled yet
+    assert!(meta["syncLagBlocks"].is_null());
+
+    sqlx::query("INSERT INTO evm_chains (id, name, block_time) VALUES (56, 'bsc', 3)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let sync = EvmSyncLogs::create(&"ca".repeat(20), 56, Some(1_000), &pool)
+        .await
+        .unwrap();
+    sync.update_latest_block_number(1_004, &pool).await.unwrap();
+
+    let version = [("api-version", "2")];
+    let enveloped = send_with_headers(&pool, Method::GET, "/api/tags", None, &version).await;
+    assert!(enveloped.body["data"].is_array());
+    assert_eq!(enveloped.body["meta"]["syncLagBlocks"], 4);
+    // Tags aren't paged
+    assert!(enveloped.body["meta"].get("cursor").is_none());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn versioned_paths_pick_the_format_and_legacy_paths_are_deprecated(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let token = create_token(&pool, 1, "VER").await;
+    AlertEvent::create_new_token_alert(&token, "VER", &pool)
+        .await
+        .unwrap();
+    let header = |response: &TestResponse, name: &str| {
+        response
+            .headers
+            .get(name)
+            .map(|v| v.to_str().unwrap().to_string())
+    };
+
+    let v1 = get(&pool, "/api/v1/alerts/feed").await;
+    assert_eq!(v1.status, StatusCode::OK);
+    assert_eq!(v1.body.as_array().unwrap().len(), 1);
+    assert_eq!(header(&v1, "api-version").as_deref(), Some("1"));
+    assert_eq!(header(&v1, "deprecation"), None);
+    assert_eq!(header(&v1, "sunset"), None);
+
+    let v2 = get(&pool, "/api/v2/alerts/feed").await;
+    assert_eq!(v2.status, StatusCode::OK);
+    assert_eq!(v2.body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(v2.body["meta"]["count"], 1);
+    assert_eq!(header(&v2, "api-version").as_deref(), Some("2"));
+    // Version 2 sends exact decimal strings unless told otherwise
+    let detail = get(&pool, &format!("/api/v2/tokens/{}", token)).await;
+    assert!(detail.body["liquidity"].is_string());
+    let detail = get(&pool, &format!("/api/v1/tokens/{}", token)).await;
+    assert!(detail.body["liquidity"].is_number());
+
+    let legacy = get(&pool, "/api/alerts/feed").await;
+    assert_eq!(legacy.status, StatusCode::OK);
+    assert_eq!(legacy.body, v1.body);
+    assert_eq!(header(&legacy, "api-version").as_deref(), Some("1"));
+    assert_eq!(header(&legacy, "deprecation").as_deref(), Some("true"));
+    assert_eq!(header(&legacy, "sunset").as_deref(), Some(LEGACY_SUNSET));
+    assert_eq!(
+        header(&legacy, "link").as_deref(),
+        Some("</api/v1/alerts/feed>; rel=\"successor-version\"")
+    );
+
+    let unknown = [("api-version", "3")];
+    let rejected = send_with_headers(&pool, Method::GET, "/api/alerts/feed", None, &unknown).await;
+    assert_problem(
+        &rejected,
+        StatusCode::BAD_REQUEST,
+        "UNSUPPORTED_API_VERSION",
+    );
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn status_reports_the_sync_position(pool: PgPool) {
+    let idle = get(&pool, "/api/v1/status").await;
+    assert_eq!(idle.status, StatusCode::OK);
+    assert!(idle.body["syncedBlock"].is_null());
+    assert!(idle.body["lagBlocks"].is_null());
+
+    sqlx::query("INSERT INTO evm_chains (id, name, block_time) VALUES (56, 'bsc', 3)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let ahead = EvmSyncLogs::create(&"ca".repeat(20), 56, Some(1_010), &pool)
+        .await
+        .unwrap();
+    ahead
+        .update_latest_block_number(1_012, &pool)
+        .await
+        .unwrap();
+    let behind = EvmSyncLogs::create(&"cb".repeat(20), 56, Some(1_000), &pool)
+        .await
+        .unwrap();
+    behind
+        .update_latest_block_number(1_012, &pool)
+        .await
+        .unwrap();
+
+    let status = get(&pool, "/api/v1/status").await;
+    assert_eq!(status.body["syncedBlock"], 1_000);
+    assert_eq!(status.body["headBlock"], 1_012);
+    assert_eq!(status.body["lagBlocks"], 12);
+    assert!(status.body["updatedAt"].is_string());
+}
+
+#[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+async fn token_list_serves_the_latest_release_with_caching_headers(pool: PgPool) {
+    clear_seed_data(&pool).await;
+    let missing = get(&pool, "/api/tokenlist.json").await;
+    assert_problem(&missing, StatusCode::NOT_FOUND, "TOKEN_LIST_NOT_PUBLISHED");
+
+    let listed = ListedToken {
+        address: address(1),
+        name: "Bee Token".to_string(),
+        symbol: "BEE".to_string(),
+        decimals: 18,
+    };
+    let version = ListVersion {
+        major: 1,
+        minor: 0,
+        patch: 0,
+    };
+    TokenListRelease::create(56, version, &[], &pool)
+        .await
+        .unwrap();
+    TokenListRelease::create(56, ListVersion { minor: 1, ..version }, &[listed], &pool)
+        .await
+        .unwrap();
+
+    let list = get(&pool, "/api/tokenlist.json").await;
+    assert_eq!(list.status, StatusCode::OK);
+    assert_eq!(list.body["name"], "BeanBee BeeScore");
+    assert_eq!(list.body["version"], json!({"major": 1, "minor": 1, "patch": 0}));
+    assert_eq!(
+        list.body["tokens"],
+        json!([{
+            "chainId": 56,
+            "address": address(1).to_string(),
+            "name": "Bee Token",
+            "symbol": "BEE",
+            "decimals": 18
+        }])
+    );
+    assert_eq!(list.headers[header::ETAG], "\"56-1.1.0\"");
+    assert_eq!(list.headers[header::CACHE_CONTROL], "public, max-age=300");
+    assert!(list.headers[header::LAST_MODIFIED]
+        .to_str()
+        .unwrap()
+        .ends_with(" GMT"));
+
+    let unchanged = send_with_headers(
+        &pool,
+        Method::GET,
+        "/api/tokenlist.json",
+        None,
+        &[("if-none-match", "\"56-1.0.0\", \"56-1.1.0\"")],
+    )
+    .await;
+    assert_eq!(unchanged.status, StatusCode::NOT_MODIFIED);
+    assert_eq!(unchanged.headers[header::ETAG], "\"56-1.1.0\"");
+
+    let stale = send_with_headers(
+        &pool,
+        Method::GET,
+        "/api/v1/tokenlist.json",
+        None,
+        &[("if-none-match", "\"56-1.0.0\"")],
+    )
+    .await;
+    assert_eq!(stale.status, StatusCode::OK);
+}
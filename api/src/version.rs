@@ -0,0 +1,187 @@
+//! API versioning
+//!
+//! Routes are served under `/api/v1` and `/api/v2`. Version 2 sends lists as
+//! `{ data, meta }` envelopes and decimals as exact strings; version 1 keeps
+//! the original bare arrays and JSON numbers.
+//!
+//! The unversioned `/api/...` paths still work and negotiate a version from
+//! `API-Version` or the v2 `Accept` media type (version 1 otherwise), but they
+//! are deprecated: their responses carry `Deprecation`, `Sunset` (from
+//! `LEGACY_API_SUNSET`) and a `Link` to the `/api/v1` successor.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{envelope::ENVELOPE_MEDIA_TYPE, error::ApiError, AppState};
+
+/// Header asking for a response version on the unversioned paths
+pub const VERSION_HEADER: &str = "api-version";
+
+/// Prefix shared by every API route
+const API_PREFIX: &str = "/api";
+
+/// Response format version a request is served with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// Bare arrays, decimals as JSON numbers
+    #[default]
+    V1,
+    /// `{ data, meta }` envelopes, decimals as exact strings
+    V2,
+}
+
+impl ApiVersion {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "1" => Some(ApiVersion::V1),
+            "2" => Some(ApiVersion::V2),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "1",
+            ApiVersion::V2 => "2",
+        }
+    }
+
+    /// Version a request's headers ask for, if any
+    pub fn requested(headers: &HeaderMap) -> Result<Option<Self>, ApiError> {
+        let values = |name| {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>()
+        };
+
+        if let Some(value) = values(VERSION_HEADER).first() {
+            return match Self::parse(value) {
+                Some(version) => Ok(Some(version)),
+                None => Err(ApiError::UnsupportedVersion(format!(
+                    "`API-Version` must be `1` or `2`, got `{}`",
+                    value
+                ))),
+            };
+        }
+
+        let accepts_envelope = values(header::ACCEPT.as_str()).iter().any(|media| {
+            media
+                .split(';')
+                .next()
+                .is_some_and(|m| m.trim().eq_ignore_ascii_case(ENVELOPE_MEDIA_TYPE))
+        });
+        Ok(accepts_envelope.then_some(ApiVersion::V2))
+    }
+}
+
+/// Version named by a `/api/v<n>` path prefix, and the path without it
+fn split_path(path: &str) -> Option<(ApiVersion, &str)> {
+    let rest = path.strip_prefix(API_PREFIX)?.strip_prefix("/v")?;
+    let (number, rest) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    Some((ApiVersion::parse(number)?, rest))
+}
+
+/// `path` with any version prefix removed, e.g. `/api/v1/admin` → `/api/admin`
+pub fn unversioned(path: &str) -> String {
+    match split_path(path) {
+        Some((_, rest)) => format!("{}{}", API_PREFIX, rest),
+        None => path.to_string(),
+    }
+}
+
+/// Negotiate the version of API requests and mark legacy paths deprecated
+pub async fn middleware(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    if !path.starts_with(API_PREFIX) {
+        return next.run(request).await;
+    }
+
+    let (version, legacy) = match split_path(&path) {
+        Some((version, _)) => (version, false),
+        None => match ApiVersion::requested(request.headers()) {
+            Ok(version) => (version.unwrap_or_default(), true),
+            Err(e) => return e.into_response(),
+        },
+    };
+
+    request.extensions_mut().insert(version);
+    let mut response = next.run(request).await;
+
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static(VERSION_HEADER),
+        HeaderValue::from_static(version.as_str()),
+    );
+    if legacy {
+        headers.insert("deprecation", HeaderValue::from_static("true"));
+        if let Ok(sunset) = HeaderValue::from_str(&state.legacy_sunset) {
+            headers.insert("sunset", sunset);
+        }
+        let successor = format!(
+            "<{}/v1{}>; rel=\"successor-version\"",
+            API_PREFIX,
+            &path[API_PREFIX.len()..]
+        );
+        if let Ok(link) = HeaderValue::from_str(&successor) {
+            headers.insert(header::LINK, link);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requested(headers: &[(&'static str, &'static str)]) -> Option<ApiVersion> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        ApiVersion::requested(&map).unwrap()
+    }
+
+    #[test]
+    fn versions_come_from_the_path_or_the_headers() {
+        assert_eq!(
+            split_path("/api/v2/tokens/new"),
+            Some((ApiVersion::V2, "/tokens/new"))
+        );
+        assert_eq!(split_path("/api/v1"), Some((ApiVersion::V1, "")));
+        assert_eq!(split_path("/api/tokens/new"), None);
+        assert_eq!(split_path("/api/v9/tokens"), None);
+        assert_eq!(unversioned("/api/v1/admin/lag"), "/api/admin/lag");
+        assert_eq!(unversioned("/api/admin/lag"), "/api/admin/lag");
+
+        assert_eq!(requested(&[]), None);
+        assert_eq!(requested(&[("accept", "application/json")]), None);
+        assert_eq!(requested(&[("api-version", "1")]), Some(ApiVersion::V1));
+        assert_eq!(requested(&[("api-version", "2")]), Some(ApiVersion::V2));
+        assert_eq!(
+            requested(&[(
+                "accept",
+                "text/html, application/vnd.beanbee.v2+json; q=0.9"
+            )]),
+            Some(ApiVersion::V2)
+        );
+
+        let mut unknown = HeaderMap::new();
+        unknown.insert("api-version", HeaderValue::from_static("3"));
+        assert!(ApiVersion::requested(&unknown).is_err());
+    }
+}
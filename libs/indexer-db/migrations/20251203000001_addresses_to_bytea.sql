@@ -0,0 +1,89 @@
+-- Store addresses (20 bytes) and transaction hashes (32 bytes) as BYTEA
+-- instead of VARCHAR so lookups are exact regardless of input casing.
+--
+-- Well-formed 0x-hex values are decoded directly. Anything else (e.g. the
+-- placeholder rows from the seed migration) is mapped deterministically from
+-- its md5 so rows that referenced the same value keep joining after the move.
+
+CREATE OR REPLACE FUNCTION hex_to_bytea(value TEXT, len INT)
+RETURNS BYTEA AS $$
+BEGIN
+    IF value IS NULL THEN
+        RETURN NULL;
+    END IF;
+
+    IF value ~* ('^0x[0-9a-f]{' || len * 2 || '}$') THEN
+        RETURN decode(substring(value FROM 3), 'hex');
+    END IF;
+
+    RETURN substring(decode(md5(lower(value)) || md5(md5(lower(value))), 'hex') FROM 1 FOR len);
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+ALTER TABLE tokens
+    ALTER COLUMN address TYPE BYTEA USING hex_to_bytea(address, 20),
+    ALTER COLUMN pair_address TYPE BYTEA USING hex_to_bytea(pair_address, 20),
+    ALTER COLUMN creator_address TYPE BYTEA USING hex_to_bytea(creator_address, 20),
+    ADD CONSTRAINT tokens_address_len CHECK (octet_length(address) = 20),
+    ADD CONSTRAINT tokens_pair_address_len CHECK (octet_length(pair_address) = 20),
+    ADD CONSTRAINT tokens_creator_address_len CHECK (octet_length(creator_address) = 20);
+
+ALTER TABLE swaps
+    ALTER COLUMN tx_hash TYPE BYTEA USING hex_to_bytea(tx_hash, 32),
+    ALTER COLUMN pair_address TYPE BYTEA USING hex_to_bytea(pair_address, 20),
+    ALTER COLUMN token_address TYPE BYTEA USING hex_to_bytea(token_address, 20),
+    ALTER COLUMN wallet_address TYPE BYTEA USING hex_to_bytea(wallet_address, 20),
+    ADD CONSTRAINT swaps_tx_hash_len CHECK (octet_length(tx_hash) = 32),
+    ADD CONSTRAINT swaps_pair_address_len CHECK (octet_length(pair_address) = 20),
+    ADD CONSTRAINT swaps_token_address_len CHECK (octet_length(token_address) = 20),
+    ADD CONSTRAINT swaps_wallet_address_len CHECK (octet_length(wallet_address) = 20);
+
+ALTER TABLE lp_locks
+    ALTER COLUMN token_address TYPE BYTEA USING hex_to_bytea(token_address, 20),
+    ALTER COLUMN pair_address TYPE BYTEA USING hex_to_bytea(pair_address, 20),
+    ALTER COLUMN lock_contract TYPE BYTEA USING hex_to_bytea(lock_contract, 20),
+    ALTER COLUMN tx_hash TYPE BYTEA USING hex_to_bytea(tx_hash, 32),
+    ADD CONSTRAINT lp_locks_token_address_len CHECK (octet_length(token_address) = 20),
+    ADD CONSTRAINT lp_locks_pair_address_len CHECK (octet_length(pair_address) = 20),
+    ADD CONSTRAINT lp_locks_lock_contract_len CHECK (octet_length(lock_contract) = 20),
+    ADD CONSTRAINT lp_locks_tx_hash_len CHECK (octet_length(tx_hash) = 32);
+
+ALTER TABLE price_snapshots
+    ALTER COLUMN token_address TYPE BYTEA USING hex_to_bytea(token_address, 20),
+    ADD CONSTRAINT price_snapshots_token_address_len CHECK (octet_length(token_address) = 20);
+
+ALTER TABLE wallet_activity
+    ALTER COLUMN wallet_address TYPE BYTEA USING hex_to_bytea(wallet_address, 20),
+    ALTER COLUMN tx_hash TYPE BYTEA USING hex_to_bytea(tx_hash, 32),
+    ALTER COLUMN token_address TYPE BYTEA USING hex_to_bytea(token_address, 20),
+    ADD CONSTRAINT wallet_activity_wallet_address_len CHECK (octet_length(wallet_address) = 20),
+    ADD CONSTRAINT wallet_activity_tx_hash_len CHECK (octet_length(tx_hash) = 32),
+    ADD CONSTRAINT wallet_activity_token_address_len CHECK (octet_length(token_address) = 20);
+
+ALTER TABLE token_holders
+    ALTER COLUMN token_address TYPE BYTEA USING hex_to_bytea(token_address, 20),
+    ALTER COLUMN wallet_address TYPE BYTEA USING hex_to_bytea(wallet_address, 20),
+    ADD CONSTRAINT token_holders_token_address_len CHECK (octet_length(token_address) = 20),
+    ADD CONSTRAINT token_holders_wallet_address_len CHECK (octet_length(wallet_address) = 20);
+
+ALTER TABLE alert_events
+    ALTER COLUMN token_address TYPE BYTEA USING hex_to_bytea(token_address, 20),
+    ALTER COLUMN wallet_address TYPE BYTEA USING hex_to_bytea(wallet_address, 20),
+    ADD CONSTRAINT alert_events_token_address_len CHECK (octet_length(token_address) = 20),
+    ADD CONSTRAINT alert_events_wallet_address_len CHECK (octet_length(wallet_address) = 20);
+
+ALTER TABLE pairs
+    ALTER COLUMN address TYPE BYTEA USING hex_to_bytea(address, 20),
+    ALTER COLUMN token0_address TYPE BYTEA USING hex_to_bytea(token0_address, 20),
+    ALTER COLUMN token1_address TYPE BYTEA USING hex_to_bytea(token1_address, 20),
+    ALTER COLUMN factory_address TYPE BYTEA USING hex_to_bytea(factory_address, 20),
+    ADD CONSTRAINT pairs_address_len CHECK (octet_length(address) = 20),
+    ADD CONSTRAINT pairs_token0_address_len CHECK (octet_length(token0_address) = 20),
+    ADD CONSTRAINT pairs_token1_address_len CHECK (octet_length(token1_address) = 20),
+    ADD CONSTRAINT pairs_factory_address_len CHECK (octet_length(factory_address) = 20);
+
+ALTER TABLE wallets
+    ALTER COLUMN address TYPE BYTEA USING hex_to_bytea(address, 20),
+    ADD CONSTRAINT wallets_address_len CHECK (octet_length(address) = 20);
+
+DROP FUNCTION hex_to_bytea(TEXT, INT);
@@ -0,0 +1,27 @@
+-- Index audit for the hot API and processor queries
+
+-- Redundant: the UNIQUE constraints already index these columns
+DROP INDEX IF EXISTS idx_tokens_address;
+DROP INDEX IF EXISTS idx_pairs_address;
+DROP INDEX IF EXISTS idx_wallets_address;
+
+-- Swaps by token + time. INCLUDE lets the 1h trade count and volume
+-- aggregates run as index-only scans.
+DROP INDEX IF EXISTS idx_swaps_token_time;
+CREATE INDEX IF NOT EXISTS idx_swaps_token_time
+    ON swaps(token_address, timestamp DESC) INCLUDE (trade_type, amount_usd);
+
+-- Top holders are read with ORDER BY balance DESC NULLS LAST, which the old
+-- (token_address, balance DESC) index could not serve in order.
+DROP INDEX IF EXISTS idx_holders_token;
+CREATE INDEX IF NOT EXISTS idx_holders_token_balance
+    ON token_holders(token_address, balance DESC NULLS LAST) INCLUDE (percent_of_supply);
+
+-- Wallet activity by wallet + time, covering the per-wallet stats rollup
+DROP INDEX IF EXISTS idx_wallet_activity_wallet;
+CREATE INDEX IF NOT EXISTS idx_wallet_activity_wallet
+    ON wallet_activity(wallet_address, timestamp DESC) INCLUDE (token_address, action, amount_usd);
+
+-- Hot token ranking expression used by Token::find_hot
+CREATE INDEX IF NOT EXISTS idx_tokens_hot_rank
+    ON tokens((COALESCE(volume_1h_usd, 0) + COALESCE(bee_score, 0) * 100) DESC);
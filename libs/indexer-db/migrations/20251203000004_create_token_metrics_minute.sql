@@ -0,0 +1,28 @@
+-- Per-minute trade buckets. The 1h/24h counters on `tokens` are a cached
+-- rollup of these rows, recomputed by summation so the windows actually slide.
+CREATE TABLE IF NOT EXISTS token_metrics_minute (
+    token_address BYTEA NOT NULL,
+    minute TIMESTAMPTZ NOT NULL,
+    volume_usd DECIMAL(30, 2) NOT NULL DEFAULT 0,
+    buys INT NOT NULL DEFAULT 0,
+    sells INT NOT NULL DEFAULT 0,
+    trades INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (token_address, minute),
+    CONSTRAINT token_metrics_minute_token_address_len CHECK (octet_length(token_address) = 20)
+);
+
+-- Window sweeps across all tokens
+CREATE INDEX IF NOT EXISTS idx_token_metrics_minute_minute ON token_metrics_minute(minute);
+
+-- Backfill from swaps already recorded
+INSERT INTO token_metrics_minute (token_address, minute, volume_usd, buys, sells, trades)
+SELECT
+    token_address,
+    date_trunc('minute', timestamp),
+    COALESCE(SUM(amount_usd), 0),
+    COUNT(*) FILTER (WHERE trade_type = 'buy'),
+    COUNT(*) FILTER (WHERE trade_type = 'sell'),
+    COUNT(*)
+FROM swaps
+GROUP BY token_address, date_trunc('minute', timestamp)
+ON CONFLICT (token_address, minute) DO NOTHING;
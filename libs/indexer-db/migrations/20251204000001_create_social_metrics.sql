@@ -0,0 +1,17 @@
+-- Social metrics pushed in by external collectors (Telegram bots, X scrapers)
+CREATE TABLE IF NOT EXISTS social_metrics (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    source VARCHAR(50) NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    telegram_members INT,
+    x_mentions_24h INT,
+
+    CONSTRAINT social_metrics_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT social_metrics_counts_non_negative CHECK (
+        COALESCE(telegram_members, 0) >= 0 AND COALESCE(x_mentions_24h, 0) >= 0
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_social_metrics_token_time ON social_metrics(token_address, recorded_at DESC);
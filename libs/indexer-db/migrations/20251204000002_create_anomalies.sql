@@ -0,0 +1,25 @@
+-- Reserve updates that no swap or balanced mint/burn could have produced
+-- (direct pool transfers, K-invariant violations). Price snapshots from a
+-- flagged pair/block are suppressed.
+CREATE TABLE IF NOT EXISTS anomalies (
+    id SERIAL PRIMARY KEY,
+    kind VARCHAR(50) NOT NULL,
+    pair_address BYTEA NOT NULL,
+    token_address BYTEA,
+    block_number BIGINT NOT NULL,
+
+    prev_reserve0 DECIMAL(78, 0) NOT NULL,
+    prev_reserve1 DECIMAL(78, 0) NOT NULL,
+    reserve0 DECIMAL(78, 0) NOT NULL,
+    reserve1 DECIMAL(78, 0) NOT NULL,
+    -- How far the update is from what the invariant allows
+    deviation_percent DECIMAL(20, 4),
+
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT anomalies_pair_address_len CHECK (octet_length(pair_address) = 20),
+    CONSTRAINT anomalies_token_address_len CHECK (token_address IS NULL OR octet_length(token_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anomalies_pair_block ON anomalies(pair_address, block_number);
+CREATE INDEX IF NOT EXISTS idx_anomalies_token_detected ON anomalies(token_address, detected_at DESC);
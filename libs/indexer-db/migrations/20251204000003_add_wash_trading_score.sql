@@ -0,0 +1,54 @@
+-- Share of a token's recent volume that looks gamed (round trips, circular
+-- transfers, volume concentrated in one or two wallets), 0-100.
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS wash_trading_score DECIMAL(5, 2) NOT NULL DEFAULT 0;
+
+ALTER TABLE tokens ADD CONSTRAINT tokens_wash_trading_score_range
+    CHECK (wash_trading_score >= 0 AND wash_trading_score <= 100);
+
+-- The token list views select tokens.*, which was expanded when they were
+-- created; rebuild them so they carry the new column.
+DROP MATERIALIZED VIEW IF EXISTS token_list_hot;
+DROP MATERIALIZED VIEW IF EXISTS token_list_new;
+DROP MATERIALIZED VIEW IF EXISTS token_list_trending;
+
+-- Hot: volume + BeeScore ranking
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_hot AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY (COALESCE(t.volume_1h_usd, 0) + COALESCE(t.bee_score, 0) * 100) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE t.volume_1h_usd > 0 OR t.bee_score > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_hot_address ON token_list_hot(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_hot_rank ON token_list_hot(rank);
+
+-- New: most recently created
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_new AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (ORDER BY t.created_at DESC NULLS LAST, t.id DESC) AS rank
+FROM tokens t
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_new_address ON token_list_new(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_new_rank ON token_list_new(rank);
+
+-- Trending: 1h price momentum among tokens that actually traded
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_trending AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY COALESCE(t.price_change_1h, 0) DESC, COALESCE(t.volume_1h_usd, 0) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE COALESCE(t.trades_1h, 0) > 0 OR COALESCE(t.volume_1h_usd, 0) > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_trending_address ON token_list_trending(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_trending_rank ON token_list_trending(rank);
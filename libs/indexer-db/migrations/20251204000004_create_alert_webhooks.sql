@@ -0,0 +1,33 @@
+-- Generic HTTP webhook sinks for alert events
+CREATE TABLE IF NOT EXISTS alert_webhooks (
+    id SERIAL PRIMARY KEY,
+    url TEXT NOT NULL,
+    -- HMAC-SHA256 key used to sign each delivery
+    secret TEXT NOT NULL,
+    -- Alert types to deliver; empty means all
+    alert_types TEXT[] NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Highest alert_events.id already queued for this webhook
+    last_alert_id INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One row per (webhook, alert) with retry state
+CREATE TABLE IF NOT EXISTS alert_webhook_deliveries (
+    id SERIAL PRIMARY KEY,
+    webhook_id INT NOT NULL REFERENCES alert_webhooks(id) ON DELETE CASCADE,
+    alert_id INT NOT NULL REFERENCES alert_events(id) ON DELETE CASCADE,
+    -- pending, delivered, failed
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_status_code INT,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ,
+
+    CONSTRAINT alert_webhook_deliveries_unique UNIQUE (webhook_id, alert_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due
+    ON alert_webhook_deliveries(next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,19 @@
+-- Runtime controls for the listener's log filters, keyed by filter name.
+-- Read by each listener task on every poll, so changes apply without a restart.
+CREATE TABLE IF NOT EXISTS listener_controls (
+    name VARCHAR(50) PRIMARY KEY,
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Seconds between polls; NULL uses the chain's block time
+    poll_interval_secs INT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT listener_controls_poll_interval_positive CHECK (poll_interval_secs IS NULL OR poll_interval_secs > 0)
+);
+
+-- Global Swap/Transfer/Sync filters need a paid RPC, so they start paused
+INSERT INTO listener_controls (name, paused) VALUES
+    ('PairCreated', FALSE),
+    ('Swap', TRUE),
+    ('Transfer', TRUE),
+    ('Sync', TRUE)
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,38 @@
+-- Listener filter definitions live in the database instead of listener code.
+-- The listener reloads this table periodically, starting tasks for new or
+-- enabled filters and stopping tasks for removed, disabled or edited ones.
+-- Replaces listener_controls; `paused` becomes `enabled`.
+ALTER TABLE listener_controls RENAME TO listener_filters;
+ALTER TABLE listener_filters RENAME CONSTRAINT listener_controls_poll_interval_positive TO listener_filters_poll_interval_positive;
+ALTER INDEX listener_controls_pkey RENAME TO listener_filters_pkey;
+
+ALTER TABLE listener_filters
+    ADD COLUMN address BYTEA,
+    ADD COLUMN topic BYTEA,
+    ADD COLUMN enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Higher priority filters are started first
+    ADD COLUMN priority INT NOT NULL DEFAULT 0;
+
+UPDATE listener_filters SET enabled = NOT paused;
+ALTER TABLE listener_filters DROP COLUMN paused;
+
+-- PancakeSwap V2 factory and the standard event topics
+UPDATE listener_filters SET
+    address = '\xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73'::BYTEA,
+    topic = '\x0d3648bd0f6ba80134a33ba9275ac585d9d315f0ad8355cddefde31afa28d0e9'::BYTEA,
+    priority = 100
+WHERE name = 'PairCreated';
+UPDATE listener_filters SET topic = '\xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822'::BYTEA, priority = 50
+WHERE name = 'Swap';
+UPDATE listener_filters SET topic = '\x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1'::BYTEA, priority = 40
+WHERE name = 'Sync';
+UPDATE listener_filters SET topic = '\xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef'::BYTEA, priority = 30
+WHERE name = 'Transfer';
+
+-- Drop filters that still have nothing to match on
+DELETE FROM listener_filters WHERE address IS NULL AND topic IS NULL;
+
+ALTER TABLE listener_filters
+    ADD CONSTRAINT listener_filters_address_len CHECK (address IS NULL OR octet_length(address) = 20),
+    ADD CONSTRAINT listener_filters_topic_len CHECK (topic IS NULL OR octet_length(topic) = 32),
+    ADD CONSTRAINT listener_filters_has_criteria CHECK (address IS NOT NULL OR topic IS NOT NULL);
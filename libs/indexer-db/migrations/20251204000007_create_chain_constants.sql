@@ -0,0 +1,32 @@
+-- Per-chain contract registry: wrapped native token, stablecoins, DEX
+-- factories/routers and LP lockers. Supporting a new chain means inserting
+-- its rows here rather than changing processor defaults.
+CREATE TABLE IF NOT EXISTS chain_constants (
+    id SERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    -- wrapped_native, stablecoin, factory, router, locker
+    kind VARCHAR(20) NOT NULL,
+    name VARCHAR(50) NOT NULL,
+    address BYTEA NOT NULL,
+
+    CONSTRAINT chain_constants_address_len CHECK (octet_length(address) = 20),
+    CONSTRAINT chain_constants_kind_valid CHECK (
+        kind IN ('wrapped_native', 'stablecoin', 'factory', 'router', 'locker')
+    ),
+    CONSTRAINT chain_constants_unique UNIQUE (chain_id, kind, address)
+);
+
+-- A chain has exactly one wrapped native token
+CREATE UNIQUE INDEX IF NOT EXISTS idx_chain_constants_wrapped_native
+    ON chain_constants(chain_id) WHERE kind = 'wrapped_native';
+
+-- BNB Smart Chain
+INSERT INTO chain_constants (chain_id, kind, name, address) VALUES
+    (56, 'wrapped_native', 'WBNB', '\xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c'),
+    (56, 'stablecoin', 'BUSD', '\xe9e7cea3dedca5984780bafc599bd69add087d56'),
+    (56, 'factory', 'PancakeSwap V2', '\xca143ce32fe78f1f7019d7d551a6402fc5350c73'),
+    (56, 'router', 'PancakeSwap V2', '\x10ed43c718714eb63d5aa57b78b54704e256024e'),
+    (56, 'locker', 'unicrypt', '\xc765bddb93b0d1c1a88282ba0fa6b2d00e3e0c83'),
+    (56, 'locker', 'pinksale', '\x407993575c91ce7643a4d4ccacc9a98c36ee1bbe'),
+    (56, 'locker', 'mudra', '\xae34bd8a0d1153e51a11a59df23598c304dc5abc')
+ON CONFLICT (chain_id, kind, address) DO NOTHING;
@@ -0,0 +1,12 @@
+-- Trending list positions as of the last refresh, diffed against the next
+-- one to raise enter/leave alerts
+CREATE TABLE IF NOT EXISTS trending_ranks (
+    token_address BYTEA PRIMARY KEY,
+    rank INT NOT NULL,
+    -- Whether the token currently counts as in the top list (with hysteresis)
+    in_top BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT trending_ranks_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT trending_ranks_rank_positive CHECK (rank > 0)
+);
@@ -0,0 +1,9 @@
+-- Transaction-level context for swaps, used to flag probable MEV/sniper bots
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS tx_index INT;
+-- Share (0-100) of the block's transactions paying a lower gas price; whale swaps only
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS gas_price_percentile SMALLINT;
+-- top_of_block, high_gas, probable_mev
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS mev_flags TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE swaps ADD CONSTRAINT swaps_gas_price_percentile_range
+    CHECK (gas_price_percentile BETWEEN 0 AND 100);
@@ -0,0 +1,67 @@
+-- Holders entering (balance going above 0) and exiting (balance going to 0)
+-- per token per hour. Holder count alone hides heavy churn.
+CREATE TABLE IF NOT EXISTS token_holder_churn_hourly (
+    token_address BYTEA NOT NULL,
+    hour TIMESTAMPTZ NOT NULL,
+    entered INT NOT NULL DEFAULT 0,
+    exited INT NOT NULL DEFAULT 0,
+
+    PRIMARY KEY (token_address, hour),
+    CONSTRAINT token_holder_churn_hourly_address_len CHECK (octet_length(token_address) = 20)
+);
+
+-- Rolling 24h churn, refreshed from the hourly buckets
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS holders_entered_24h INT NOT NULL DEFAULT 0;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS holders_exited_24h INT NOT NULL DEFAULT 0;
+-- Exits over the last 24h per 100 holders at the start of the window
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS holder_churn_rate_24h DECIMAL(5, 2) NOT NULL DEFAULT 0;
+
+ALTER TABLE tokens ADD CONSTRAINT tokens_holder_churn_rate_range
+    CHECK (holder_churn_rate_24h >= 0 AND holder_churn_rate_24h <= 100);
+
+-- The token list views select tokens.*; rebuild them to carry the new columns.
+DROP MATERIALIZED VIEW IF EXISTS token_list_hot;
+DROP MATERIALIZED VIEW IF EXISTS token_list_new;
+DROP MATERIALIZED VIEW IF EXISTS token_list_trending;
+
+-- Hot: volume + BeeScore ranking
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_hot AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY (COALESCE(t.volume_1h_usd, 0) + COALESCE(t.bee_score, 0) * 100) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE t.volume_1h_usd > 0 OR t.bee_score > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_hot_address ON token_list_hot(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_hot_rank ON token_list_hot(rank);
+
+-- New: most recently created
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_new AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (ORDER BY t.created_at DESC NULLS LAST, t.id DESC) AS rank
+FROM tokens t
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_new_address ON token_list_new(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_new_rank ON token_list_new(rank);
+
+-- Trending: 1h price momentum among tokens that actually traded
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_trending AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY COALESCE(t.price_change_1h, 0) DESC, COALESCE(t.volume_1h_usd, 0) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE COALESCE(t.trades_1h, 0) > 0 OR COALESCE(t.volume_1h_usd, 0) > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_trending_address ON token_list_trending(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_trending_rank ON token_list_trending(rank);
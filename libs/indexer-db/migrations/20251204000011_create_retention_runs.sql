@@ -0,0 +1,18 @@
+-- One row per table per retention pass, so cleanup volume and cost can be
+-- tracked over time
+CREATE TABLE IF NOT EXISTS retention_runs (
+    id SERIAL PRIMARY KEY,
+    table_name VARCHAR(50) NOT NULL,
+    rows_deleted BIGINT NOT NULL DEFAULT 0,
+    batches INT NOT NULL DEFAULT 0,
+    duration_ms BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    ran_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_retention_runs_table ON retention_runs(table_name, ran_at DESC);
+
+-- Age scans for batched retention deletes
+CREATE INDEX IF NOT EXISTS idx_swaps_timestamp ON swaps(timestamp);
+CREATE INDEX IF NOT EXISTS idx_wallet_activity_timestamp ON wallet_activity(timestamp);
+CREATE INDEX IF NOT EXISTS idx_snapshots_timestamp ON price_snapshots(timestamp);
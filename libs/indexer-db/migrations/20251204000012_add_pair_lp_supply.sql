@@ -0,0 +1,10 @@
+-- LP token totalSupply, cached on the pair so lock events can be turned into
+-- a real locked percentage. Refreshed over RPC on every Mint/Burn.
+ALTER TABLE pairs ADD COLUMN IF NOT EXISTS lp_total_supply NUMERIC(78, 0);
+ALTER TABLE pairs ADD COLUMN IF NOT EXISTS lp_supply_updated_at TIMESTAMPTZ;
+
+-- Global Mint/Burn filters need a paid RPC like Swap/Sync, so they start disabled
+INSERT INTO listener_filters (name, topic, enabled, priority) VALUES
+    ('Mint', '\x4c209b5fc8ad50758f13e2e1088ba56a560dff690a1c6fef26394f4c03821c4f'::BYTEA, FALSE, 20),
+    ('Burn', '\xdccd412f0b1252819cb1fd330b93224ca42612892bb3f4f789976e6d81936496'::BYTEA, FALSE, 20)
+ON CONFLICT (name) DO NOTHING;
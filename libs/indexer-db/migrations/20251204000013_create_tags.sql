@@ -0,0 +1,23 @@
+-- Free-form tags ("insider", "cex", "team wallet", "gem") attached to
+-- wallets and tokens. Names are stored trimmed and lowercased.
+CREATE TABLE IF NOT EXISTS tags (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(50) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT tags_name_not_blank CHECK (length(trim(name)) > 0)
+);
+
+CREATE TABLE IF NOT EXISTS taggings (
+    tag_id INT NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    -- wallet or token
+    subject_kind VARCHAR(10) NOT NULL,
+    subject_address BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (tag_id, subject_kind, subject_address),
+    CONSTRAINT taggings_subject_kind CHECK (subject_kind IN ('wallet', 'token')),
+    CONSTRAINT taggings_address_len CHECK (octet_length(subject_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_taggings_subject ON taggings(subject_kind, subject_address);
@@ -0,0 +1,35 @@
+-- Directory of well-known non-holder addresses: exchange hot wallets,
+-- routers, bridges, lockers and burn addresses. Transfers to or from them
+-- don't make holders, and exchange transfers count as CEX inflows/outflows.
+CREATE TABLE IF NOT EXISTS known_addresses (
+    id SERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    -- cex, router, bridge, locker, burn
+    kind VARCHAR(10) NOT NULL,
+    -- Exchange or protocol, e.g. Binance
+    name VARCHAR(50) NOT NULL,
+    address BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT known_addresses_address_len CHECK (octet_length(address) = 20),
+    CONSTRAINT known_addresses_kind_valid CHECK (
+        kind IN ('cex', 'router', 'bridge', 'locker', 'burn')
+    ),
+    CONSTRAINT known_addresses_unique UNIQUE (chain_id, address)
+);
+
+-- BNB Smart Chain
+INSERT INTO known_addresses (chain_id, kind, name, address) VALUES
+    (56, 'burn', 'Zero address', '\x0000000000000000000000000000000000000000'),
+    (56, 'burn', 'Dead address', '\x000000000000000000000000000000000000dead'),
+    (56, 'cex', 'Binance', '\x8894e0a0c962cb723c1976a4421c95949be2d4e3'),
+    (56, 'cex', 'Binance', '\xf977814e90da44bfa03b6295a0616a897441acec'),
+    (56, 'cex', 'Gate.io', '\x0d0707963952f2fba59dd06f2b425ace40b492fe'),
+    (56, 'bridge', 'BSC TokenHub', '\x0000000000000000000000000000000000001004')
+ON CONFLICT (chain_id, address) DO NOTHING;
+
+-- Routers and lockers are already registered per chain
+INSERT INTO known_addresses (chain_id, kind, name, address)
+SELECT chain_id, kind, name, address FROM chain_constants
+WHERE kind IN ('router', 'locker')
+ON CONFLICT (chain_id, address) DO NOTHING;
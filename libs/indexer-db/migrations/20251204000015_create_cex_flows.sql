@@ -0,0 +1,29 @@
+-- Large token transfers into (inflow) or out of (outflow) known exchange
+-- wallets. A big deposit ahead of a sell is a classic pre-dump signal.
+CREATE TABLE IF NOT EXISTS cex_flows (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    -- Exchange name from known_addresses, e.g. Binance
+    exchange VARCHAR(50) NOT NULL,
+    exchange_address BYTEA NOT NULL,
+    -- Wallet on the other side of the transfer
+    wallet_address BYTEA NOT NULL,
+    -- inflow or outflow
+    direction VARCHAR(10) NOT NULL,
+    -- Raw token units
+    amount NUMERIC(78, 0) NOT NULL,
+    -- Share (0-100) of the total supply moved
+    supply_percent DECIMAL(7, 4),
+    tx_hash BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+
+    CONSTRAINT cex_flows_unique UNIQUE (tx_hash, token_address, exchange_address, direction),
+    CONSTRAINT cex_flows_direction_valid CHECK (direction IN ('inflow', 'outflow')),
+    CONSTRAINT cex_flows_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT cex_flows_exchange_address_len CHECK (octet_length(exchange_address) = 20),
+    CONSTRAINT cex_flows_wallet_address_len CHECK (octet_length(wallet_address) = 20),
+    CONSTRAINT cex_flows_tx_hash_len CHECK (octet_length(tx_hash) = 32)
+);
+
+CREATE INDEX IF NOT EXISTS idx_cex_flows_token ON cex_flows(token_address, timestamp DESC);
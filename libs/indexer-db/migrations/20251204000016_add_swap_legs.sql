@@ -0,0 +1,16 @@
+-- Both legs of a swap, so volume can be computed per side and token-token
+-- swaps are representable. Amounts are normalized by each token's decimals.
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS token_in BYTEA;
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS amount_in NUMERIC(60, 18);
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS token_out BYTEA;
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS amount_out NUMERIC(60, 18);
+-- LP fee taken from the input leg, in token_in units and in USD
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS fee_amount NUMERIC(60, 18);
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS fee_usd DECIMAL(30, 2);
+
+ALTER TABLE swaps
+    ADD CONSTRAINT swaps_token_in_len CHECK (octet_length(token_in) = 20),
+    ADD CONSTRAINT swaps_token_out_len CHECK (octet_length(token_out) = 20);
+
+CREATE INDEX IF NOT EXISTS idx_swaps_token_in ON swaps(token_in, timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_swaps_token_out ON swaps(token_out, timestamp DESC);
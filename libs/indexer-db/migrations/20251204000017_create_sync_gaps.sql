@@ -0,0 +1,37 @@
+-- Every block range a listener filter has fully fetched and saved. Ranges
+-- should tile each filter's history; any hole between them is a gap.
+CREATE TABLE IF NOT EXISTS evm_synced_ranges (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL REFERENCES evm_chains(id),
+    -- evm_sync_logs.address of the filter
+    sync_key BYTEA NOT NULL,
+    from_block BIGINT NOT NULL,
+    to_block BIGINT NOT NULL,
+    log_count INTEGER NOT NULL DEFAULT 0,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT evm_synced_ranges_order CHECK (from_block <= to_block),
+    CONSTRAINT evm_synced_ranges_sync_key_len CHECK (octet_length(sync_key) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_evm_synced_ranges_key
+    ON evm_synced_ranges(chain_id, sync_key, from_block);
+
+-- Holes found by the gap audit, re-fetched by the listener when idle
+CREATE TABLE IF NOT EXISTS evm_sync_gaps (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL REFERENCES evm_chains(id),
+    sync_key BYTEA NOT NULL,
+    from_block BIGINT NOT NULL,
+    to_block BIGINT NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Set once every block in the gap has been re-fetched
+    filled_at TIMESTAMPTZ,
+
+    CONSTRAINT evm_sync_gaps_unique UNIQUE (chain_id, sync_key, from_block, to_block),
+    CONSTRAINT evm_sync_gaps_order CHECK (from_block <= to_block),
+    CONSTRAINT evm_sync_gaps_sync_key_len CHECK (octet_length(sync_key) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_evm_sync_gaps_open
+    ON evm_sync_gaps(chain_id, sync_key, from_block) WHERE filled_at IS NULL;
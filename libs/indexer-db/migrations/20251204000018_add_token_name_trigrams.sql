@@ -0,0 +1,10 @@
+-- Trigram indexes for the similar-tokens lookup, which matches copycat
+-- launches by name/symbol (and by deployer)
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_tokens_name_trgm
+    ON tokens USING GIN (lower(name) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_tokens_symbol_trgm
+    ON tokens USING GIN (lower(symbol) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_tokens_creator_address
+    ON tokens(creator_address) WHERE creator_address IS NOT NULL;
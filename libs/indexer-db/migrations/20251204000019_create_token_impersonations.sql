@@ -0,0 +1,23 @@
+-- New tokens whose name or symbol closely matches an established token (top
+-- 100 by market cap, or on the trending list): probable impersonation scams.
+-- Flagged tokens lose safety points.
+CREATE TABLE IF NOT EXISTS token_impersonations (
+    token_address BYTEA PRIMARY KEY,
+    -- The token being impersonated
+    target_address BYTEA NOT NULL,
+    -- Why the target counts as established: top or trending
+    target_source VARCHAR(10) NOT NULL,
+    -- name or symbol
+    matched_field VARCHAR(10) NOT NULL,
+    -- 0-1, 1 being identical after normalization
+    similarity DECIMAL(5, 4) NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT token_impersonations_source_valid CHECK (target_source IN ('top', 'trending')),
+    CONSTRAINT token_impersonations_field_valid CHECK (matched_field IN ('name', 'symbol')),
+    CONSTRAINT token_impersonations_similarity_range CHECK (similarity BETWEEN 0 AND 1),
+    CONSTRAINT token_impersonations_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT token_impersonations_target_address_len CHECK (octet_length(target_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_impersonations_target ON token_impersonations(target_address);
@@ -0,0 +1,55 @@
+-- Names and symbols are sanitized when fetched (control and zero-width
+-- characters stripped, whitespace collapsed, length capped); keep what the
+-- contract actually returned alongside.
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS name_raw TEXT;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS symbol_raw TEXT;
+-- Raw name or symbol hid invisible characters (zero-width, bidi overrides)
+-- between visible ones, a common trick to pass as another token
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS name_spoofed BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The token list views select tokens.*; rebuild them to carry the new columns.
+DROP MATERIALIZED VIEW IF EXISTS token_list_hot;
+DROP MATERIALIZED VIEW IF EXISTS token_list_new;
+DROP MATERIALIZED VIEW IF EXISTS token_list_trending;
+
+-- Hot: volume + BeeScore ranking
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_hot AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY (COALESCE(t.volume_1h_usd, 0) + COALESCE(t.bee_score, 0) * 100) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE t.volume_1h_usd > 0 OR t.bee_score > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_hot_address ON token_list_hot(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_hot_rank ON token_list_hot(rank);
+
+-- New: most recently created
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_new AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (ORDER BY t.created_at DESC NULLS LAST, t.id DESC) AS rank
+FROM tokens t
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_new_address ON token_list_new(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_new_rank ON token_list_new(rank);
+
+-- Trending: 1h price momentum among tokens that actually traded
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_trending AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY COALESCE(t.price_change_1h, 0) DESC, COALESCE(t.volume_1h_usd, 0) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE COALESCE(t.trades_1h, 0) > 0 OR COALESCE(t.volume_1h_usd, 0) > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_trending_address ON token_list_trending(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_trending_rank ON token_list_trending(rank);
@@ -0,0 +1,10 @@
+-- Rolling 24h trading stats per pair, refreshed from swaps, so LPs can rank
+-- pools by volume and fees earned rather than by token hype
+ALTER TABLE pairs ADD COLUMN IF NOT EXISTS volume_24h_usd DECIMAL(30, 2) NOT NULL DEFAULT 0;
+ALTER TABLE pairs ADD COLUMN IF NOT EXISTS trades_24h INT NOT NULL DEFAULT 0;
+-- Estimated LP fees: the swap's recorded fee, else volume x the pair's fee rate
+ALTER TABLE pairs ADD COLUMN IF NOT EXISTS fees_24h_usd DECIMAL(30, 2) NOT NULL DEFAULT 0;
+ALTER TABLE pairs ADD COLUMN IF NOT EXISTS stats_updated_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_pairs_volume_24h ON pairs(volume_24h_usd DESC);
+CREATE INDEX IF NOT EXISTS idx_pairs_fees_24h ON pairs(fees_24h_usd DESC);
@@ -0,0 +1,25 @@
+-- End-to-end processing lag (block timestamp -> processed). The listener
+-- stamps each queued log with its block's timestamp; NULL for logs queued
+-- before this column existed.
+ALTER TABLE evm_logs ADD COLUMN IF NOT EXISTS block_timestamp TIMESTAMPTZ;
+
+-- Latest lag measurement window per event type, written by the processor,
+-- with the SLO target it is held to
+CREATE TABLE IF NOT EXISTS processing_lag (
+    -- new_pair, swap, transfer, sync or liquidity
+    event_type VARCHAR(20) PRIMARY KEY,
+    target_secs INT NOT NULL,
+    -- Logs measured in the window
+    samples INT NOT NULL,
+    p50_lag_ms BIGINT NOT NULL,
+    p95_lag_ms BIGINT NOT NULL,
+    max_lag_ms BIGINT NOT NULL,
+    -- Start of the current run of windows whose p95 missed the target
+    breached_since TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT processing_lag_event_type_valid CHECK (
+        event_type IN ('new_pair', 'swap', 'transfer', 'sync', 'liquidity')
+    ),
+    CONSTRAINT processing_lag_target_positive CHECK (target_secs > 0)
+);
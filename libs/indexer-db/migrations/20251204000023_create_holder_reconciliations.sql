@@ -0,0 +1,27 @@
+-- Balances are stored in raw token units, which overflow DECIMAL(30, 18) for
+-- an 18-decimal token past a trillion wei; widen to the full uint256 range
+ALTER TABLE token_holders ALTER COLUMN balance TYPE DECIMAL(78, 18);
+
+-- Corrections made when a stored holder balance disagreed with balanceOf on
+-- chain (missed logs, truncation), kept for observability of tracking drift
+CREATE TABLE IF NOT EXISTS holder_reconciliations (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    wallet_address BYTEA NOT NULL,
+    -- Raw token units
+    stored_balance DECIMAL(78, 18) NOT NULL,
+    onchain_balance DECIMAL(78, 18) NOT NULL,
+    -- onchain_balance - stored_balance
+    delta DECIMAL(78, 18) NOT NULL,
+    -- Block balanceOf was read at
+    block_number BIGINT NOT NULL,
+    reconciled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT holder_reconciliations_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT holder_reconciliations_wallet_address_len CHECK (octet_length(wallet_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_holder_reconciliations_token
+    ON holder_reconciliations(token_address, reconciled_at DESC);
+CREATE INDEX IF NOT EXISTS idx_holder_reconciliations_time
+    ON holder_reconciliations(reconciled_at DESC);
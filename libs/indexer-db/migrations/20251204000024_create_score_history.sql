@@ -0,0 +1,19 @@
+-- Every BeeScore computation with the top-10 holder set it saw, so a disputed
+-- score can be checked against the holder concentration at that moment
+CREATE TABLE IF NOT EXISTS score_history (
+    id BIGSERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    bee_score SMALLINT NOT NULL,
+    safety_score SMALLINT NOT NULL,
+    traction_score SMALLINT NOT NULL,
+    top_10_holder_percent DECIMAL(5, 2),
+    -- [{"address": "0x...", "balance": "...", "percent": 12.5}, ...], largest first
+    top_holders JSONB NOT NULL DEFAULT '[]',
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT score_history_token_address_len CHECK (octet_length(token_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_score_history_token ON score_history(token_address, computed_at DESC);
+-- Age scans for batched retention deletes
+CREATE INDEX IF NOT EXISTS idx_score_history_computed ON score_history(computed_at);
@@ -0,0 +1,4 @@
+-- Transfers recorded before they could be priced, for the USD backfill job
+CREATE INDEX IF NOT EXISTS idx_wallet_activity_unpriced_transfers
+    ON wallet_activity(id)
+    WHERE amount_usd IS NULL AND action IN ('transfer_in', 'transfer_out');
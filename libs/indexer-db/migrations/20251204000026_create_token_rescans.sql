@@ -0,0 +1,23 @@
+-- Operator-requested rescans of a single token. The API and CLI queue them;
+-- the processor claims pending ones, replays the token's logs from the RPC
+-- and records the outcome.
+CREATE TABLE IF NOT EXISTS token_rescans (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    -- NULL: from the token's creation block / to the chain head
+    from_block BIGINT,
+    to_block BIGINT,
+    status VARCHAR(10) NOT NULL DEFAULT 'pending',
+    logs_replayed INT NOT NULL DEFAULT 0,
+    error TEXT,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+
+    CONSTRAINT token_rescans_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT token_rescans_status CHECK (status IN ('pending', 'running', 'done', 'failed')),
+    CONSTRAINT token_rescans_block_range CHECK (from_block IS NULL OR to_block IS NULL OR from_block <= to_block)
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_rescans_pending ON token_rescans(id) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_token_rescans_token ON token_rescans(token_address, requested_at DESC);
@@ -0,0 +1,13 @@
+-- A listener that crashes between queueing logs and advancing its cursor
+-- fetches the same range again. Key queued logs by their position in the
+-- block so the refetch is dropped on insert instead of processed twice.
+-- evm_logs only ever holds the listener's CHAIN_ID, and block hashes don't
+-- repeat across chains, so the block hash stands in for the chain.
+DELETE FROM evm_logs a
+USING evm_logs b
+WHERE a.block_hash = b.block_hash
+    AND a.log_index = b.log_index
+    AND a.id > b.id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS evm_logs_unique_on_block_hash_log_index
+    ON evm_logs(block_hash, log_index);
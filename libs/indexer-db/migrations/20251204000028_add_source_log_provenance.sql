@@ -0,0 +1,25 @@
+-- Trace derived rows back to the raw log that produced them.
+--
+-- source_log_id is the log's evm_logs.id. Processed logs are deleted from
+-- evm_logs, so it isn't a foreign key; it matches the `log_id` the processor
+-- prints, and is NULL for logs that arrived through the Redis or NATS queue
+-- or a rescan. Swaps and wallet activity already carry their tx_hash; alerts
+-- and holder balances get a source_tx_hash alongside.
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS source_log_id INT;
+ALTER TABLE wallet_activity ADD COLUMN IF NOT EXISTS source_log_id INT;
+
+-- Last log that changed the balance
+ALTER TABLE token_holders
+    ADD COLUMN IF NOT EXISTS source_log_id INT,
+    ADD COLUMN IF NOT EXISTS source_tx_hash BYTEA,
+    ADD CONSTRAINT token_holders_source_tx_hash_len CHECK (octet_length(source_tx_hash) = 32);
+
+ALTER TABLE alert_events
+    ADD COLUMN IF NOT EXISTS source_log_id INT,
+    ADD COLUMN IF NOT EXISTS source_tx_hash BYTEA,
+    ADD CONSTRAINT alert_events_source_tx_hash_len CHECK (octet_length(source_tx_hash) = 32);
+
+CREATE INDEX IF NOT EXISTS idx_token_holders_source_tx ON token_holders(source_tx_hash)
+    WHERE source_tx_hash IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_alert_events_source_tx ON alert_events(source_tx_hash)
+    WHERE source_tx_hash IS NOT NULL;
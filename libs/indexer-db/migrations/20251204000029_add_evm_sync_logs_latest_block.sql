@@ -0,0 +1,3 @@
+-- Chain head each listener filter saw on its last poll, so readers can tell
+-- how many blocks the indexed data trails the chain without an RPC of their own
+ALTER TABLE evm_sync_logs ADD COLUMN IF NOT EXISTS latest_block_number BIGINT;
@@ -0,0 +1,19 @@
+-- Backoff state for the processor's metadata repair worker, which re-reads
+-- name/symbol for tokens left without them by failed RPC calls. A token with
+-- no row here is due now; each failed attempt pushes next_attempt_at out
+-- exponentially. Rows are deleted once the metadata is filled in.
+CREATE TABLE IF NOT EXISTS token_metadata_retries (
+    token_address BYTEA PRIMARY KEY,
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT token_metadata_retries_token_address_len CHECK (octet_length(token_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_metadata_retries_next_attempt ON token_metadata_retries(next_attempt_at);
+
+-- Tokens the worker scans for
+CREATE INDEX IF NOT EXISTS idx_tokens_missing_metadata ON tokens(created_at DESC)
+    WHERE name IS NULL OR symbol IS NULL;
@@ -0,0 +1,18 @@
+-- Per-token alert preferences, one set per client API key. Keys are stored
+-- as their SHA-256 so the table never holds a usable key. A token without a
+-- row gets every alert; 'critical' keeps only the risk alerts
+-- (AlertType::CRITICAL) and 'mute' drops them all.
+CREATE TABLE IF NOT EXISTS alert_preferences (
+    key_hash BYTEA NOT NULL,
+    token_address BYTEA NOT NULL,
+    level VARCHAR(10) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (key_hash, token_address),
+    CONSTRAINT alert_preferences_key_hash_len CHECK (octet_length(key_hash) = 32),
+    CONSTRAINT alert_preferences_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT alert_preferences_level CHECK (level IN ('mute', 'critical'))
+);
+
+-- Webhooks follow the preferences of the key that registered them
+ALTER TABLE alert_webhooks ADD COLUMN IF NOT EXISTS key_hash BYTEA;
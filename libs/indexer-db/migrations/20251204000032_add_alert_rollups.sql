@@ -0,0 +1,9 @@
+-- Bursts of one alert type on one token are collapsed into a single rollup
+-- alert (metadata.rollup holds the count and the ids of the alerts it
+-- replaces). The replaced alerts keep their row but point at the rollup and
+-- drop out of the feed and webhook deliveries.
+ALTER TABLE alert_events
+    ADD COLUMN IF NOT EXISTS rollup_id INT REFERENCES alert_events(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_alert_events_rollup ON alert_events(rollup_id)
+    WHERE rollup_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Filtered swap feeds (GET /api/tokens/:address/swaps?type=&whales_only=).
+-- Whale-only feeds read just the token's whale rows, and per-side feeds skip
+-- the other side's trades instead of filtering them out of the whole history.
+CREATE INDEX IF NOT EXISTS idx_swaps_token_whale_time
+    ON swaps(token_address, timestamp DESC) WHERE is_whale = TRUE;
+CREATE INDEX IF NOT EXISTS idx_swaps_token_type_time
+    ON swaps(token_address, trade_type, timestamp DESC) INCLUDE (amount_usd);
@@ -0,0 +1,24 @@
+-- Canonical wrapped-native/USD rate (BNB/USD on BSC), weighted by liquidity
+-- across the chain's major native/stablecoin pools so that moving one pool
+-- barely moves the rate. The pools are registered as 'price_pool' chain
+-- constants; the processor reads their reserves on a schedule and keeps one
+-- row per chain here.
+ALTER TABLE chain_constants DROP CONSTRAINT IF EXISTS chain_constants_kind_valid;
+ALTER TABLE chain_constants ADD CONSTRAINT chain_constants_kind_valid CHECK (
+    kind IN ('wrapped_native', 'stablecoin', 'factory', 'router', 'locker', 'price_pool')
+);
+
+-- PancakeSwap V2 WBNB/BUSD and WBNB/USDT
+INSERT INTO chain_constants (chain_id, kind, name, address) VALUES
+    (56, 'price_pool', 'WBNB/BUSD', '\x58f876857a02d6762e0101bb5c46a8c1ed44dc16'),
+    (56, 'price_pool', 'WBNB/USDT', '\x16b9a82891338f9ba80e2d6970fdda79d1eb0dae')
+ON CONFLICT (chain_id, kind, address) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS native_price_index (
+    chain_id BIGINT PRIMARY KEY,
+    price_usd NUMERIC(30, 8) NOT NULL,
+    -- Each pool's quote: address, priceUsd, liquidityUsd and whether it was
+    -- used (too shallow or too far from the others and it is left out)
+    pools JSONB NOT NULL DEFAULT '[]',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
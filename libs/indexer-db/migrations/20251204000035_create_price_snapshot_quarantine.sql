@@ -0,0 +1,31 @@
+-- Price points too far from the token's recent median to chart straight away
+-- (one manipulated Sync can put a 10^12% spike on the chart). They wait here
+-- until Syncs in later blocks either hold the new level, and they move into
+-- price_snapshots, or return to the median, and they are rejected.
+CREATE TABLE IF NOT EXISTS price_snapshot_quarantine (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    pair_address BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+
+    price_usd DECIMAL(30, 18),
+    price_bnb DECIMAL(30, 18) NOT NULL,
+    liquidity_usd DECIMAL(30, 2),
+    market_cap_usd DECIMAL(30, 2),
+    holder_count INT,
+
+    -- Median BNB price of the recent snapshots the point was measured against
+    reference_price_bnb DECIMAL(30, 18) NOT NULL,
+    change_percent DECIMAL(40, 4) NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ,
+
+    CONSTRAINT price_snapshot_quarantine_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT price_snapshot_quarantine_pair_address_len CHECK (octet_length(pair_address) = 20),
+    CONSTRAINT price_snapshot_quarantine_status CHECK (status IN ('pending', 'confirmed', 'rejected'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_snapshot_quarantine_pending
+    ON price_snapshot_quarantine(token_address, block_number) WHERE status = 'pending';
@@ -0,0 +1,6 @@
+-- Per-component points behind each BeeScore computation, so the score can be
+-- explained (and improvement suggestions drawn from it) without recomputing:
+-- [{"category": "safety", "name": "LP Lock", "score": 10, "maxScore": 15,
+--   "reason": "LP 50-90% locked - good"}, ...]
+ALTER TABLE score_history
+    ADD COLUMN IF NOT EXISTS breakdown JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,47 @@
+-- Known drainer and sweeper contracts (permit/approval phishing). Operators
+-- add and remove entries with `indexer-db-cli drainer`; the processor reloads
+-- them with every batch.
+CREATE TABLE IF NOT EXISTS drainer_addresses (
+    id SERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    address BYTEA NOT NULL,
+    -- Where the entry came from, e.g. a scam report or tracker
+    label VARCHAR(100) NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT drainer_addresses_address_len CHECK (octet_length(address) = 20),
+    CONSTRAINT drainer_addresses_unique UNIQUE (chain_id, address)
+);
+
+-- ERC-20 approvals of indexed tokens granted to a listed drainer
+CREATE TABLE IF NOT EXISTS risky_approvals (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    owner_address BYTEA NOT NULL,
+    spender_address BYTEA NOT NULL,
+    -- Drainer label at the time of the approval
+    spender_label VARCHAR(100) NOT NULL,
+    -- Raw token units approved
+    amount NUMERIC(78, 0) NOT NULL,
+    -- At least the token's supply, or the usual max-uint "infinite" approval
+    unlimited BOOLEAN NOT NULL DEFAULT FALSE,
+    tx_hash BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+
+    CONSTRAINT risky_approvals_unique UNIQUE (tx_hash, token_address, owner_address, spender_address),
+    CONSTRAINT risky_approvals_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT risky_approvals_owner_address_len CHECK (octet_length(owner_address) = 20),
+    CONSTRAINT risky_approvals_spender_address_len CHECK (octet_length(spender_address) = 20),
+    CONSTRAINT risky_approvals_tx_hash_len CHECK (octet_length(tx_hash) = 32)
+);
+
+CREATE INDEX IF NOT EXISTS idx_risky_approvals_owner ON risky_approvals(owner_address, timestamp DESC);
+CREATE INDEX IF NOT EXISTS idx_risky_approvals_token ON risky_approvals(token_address, timestamp DESC);
+
+-- Approval(address indexed owner, address indexed spender, uint256 value).
+-- Every ERC-20 emits it, so like Mint/Burn the global filter needs a paid RPC
+-- and starts disabled.
+INSERT INTO listener_filters (name, topic, enabled, priority) VALUES
+    ('Approval', '\x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925'::BYTEA, FALSE, 10)
+ON CONFLICT (name) DO NOTHING;
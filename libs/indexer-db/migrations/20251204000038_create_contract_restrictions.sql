@@ -0,0 +1,48 @@
+-- Bytecode scans of token contracts for owner-only trading restrictions
+-- (blacklist, whitelist, max transaction). One row per scanned token.
+CREATE TABLE IF NOT EXISTS token_contract_scans (
+    token_address BYTEA PRIMARY KEY,
+    -- Bytes of deployed code; 0 when the address has none
+    code_size INTEGER NOT NULL,
+    -- Signatures of the restriction functions whose selectors the code holds
+    restrictions TEXT[] NOT NULL DEFAULT '{}',
+    scanned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT token_contract_scans_token_address_len CHECK (octet_length(token_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_contract_scans_restricted
+    ON token_contract_scans(token_address) WHERE cardinality(restrictions) > 0;
+
+-- Restriction functions called on a scanned token, one row per targeted
+-- address (none for max transaction changes)
+CREATE TABLE IF NOT EXISTS restriction_calls (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    caller BYTEA NOT NULL,
+    function VARCHAR(100) NOT NULL,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('blacklist', 'whitelist', 'max_tx')),
+    target_address BYTEA,
+    -- FALSE when the call lifts the restriction, e.g. setBlacklist(a, false)
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    -- The target held the token when the call was seen
+    target_is_holder BOOLEAN NOT NULL DEFAULT FALSE,
+    timestamp TIMESTAMPTZ NOT NULL,
+
+    CONSTRAINT restriction_calls_unique UNIQUE NULLS NOT DISTINCT (tx_hash, token_address, target_address),
+    CONSTRAINT restriction_calls_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT restriction_calls_tx_hash_len CHECK (octet_length(tx_hash) = 32),
+    CONSTRAINT restriction_calls_caller_len CHECK (octet_length(caller) = 20),
+    CONSTRAINT restriction_calls_target_address_len CHECK (octet_length(target_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_restriction_calls_token ON restriction_calls(token_address, block_number DESC);
+
+-- Last block whose transactions were checked for restriction calls
+CREATE TABLE IF NOT EXISTS restriction_watch_cursors (
+    chain_id BIGINT PRIMARY KEY,
+    last_block BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
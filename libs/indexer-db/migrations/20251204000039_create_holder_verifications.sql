@@ -0,0 +1,27 @@
+-- Operator-requested verifications of a token's whole holder list. The API
+-- and CLI queue them; the processor re-reads every stored holder's balance
+-- with batched Multicall3 `balanceOf` calls, corrects drift, prunes wallets
+-- that hold nothing and recomputes the token's holder metrics.
+CREATE TABLE IF NOT EXISTS holder_verifications (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'pending',
+    holders_checked INT NOT NULL DEFAULT 0,
+    balances_corrected INT NOT NULL DEFAULT 0,
+    holders_pruned INT NOT NULL DEFAULT 0,
+    -- Block balances were read at
+    block_number BIGINT,
+    -- Token metrics once verified
+    holder_count INT,
+    top_10_holder_percent DECIMAL(10, 2),
+    error TEXT,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+
+    CONSTRAINT holder_verifications_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT holder_verifications_status CHECK (status IN ('pending', 'running', 'done', 'failed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_holder_verifications_pending ON holder_verifications(id) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_holder_verifications_token ON holder_verifications(token_address, requested_at DESC);
@@ -0,0 +1,19 @@
+-- Wallets worth tracking, rebuilt by the processor's nightly analysis: the
+-- best traders by USD PnL over the last days, and wallets that bought early
+-- into several tokens that went on to score well
+CREATE TABLE IF NOT EXISTS wallet_suggestions (
+    wallet_address BYTEA PRIMARY KEY,
+    -- top_pnl, early_buyer
+    reasons TEXT[] NOT NULL,
+    -- USD sold minus USD bought over the analysis window
+    pnl_usd DECIMAL(30, 2) NOT NULL DEFAULT 0,
+    -- Tokens bought within their first blocks that later scored high
+    early_win_tokens BYTEA[] NOT NULL DEFAULT '{}',
+    analyzed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT wallet_suggestions_wallet_address_len CHECK (octet_length(wallet_address) = 20)
+);
+
+-- Early buys are matched per wallet and token against the token's creation block
+CREATE INDEX IF NOT EXISTS idx_swaps_buys_wallet_token
+    ON swaps(wallet_address, token_address, block_number) WHERE trade_type = 'buy';
@@ -0,0 +1,23 @@
+-- When each wallet first appeared in a swap or a transfer of an indexed token.
+-- Wallets trading minutes after they were first seen are typical of sniper
+-- farms spreading one buyer over many fresh addresses.
+CREATE TABLE IF NOT EXISTS wallet_profiles (
+    wallet_address BYTEA PRIMARY KEY,
+    first_seen_block BIGINT NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL,
+
+    CONSTRAINT wallet_profiles_wallet_address_len CHECK (octet_length(wallet_address) = 20)
+);
+
+-- The earliest first sighting bounds how far back "fresh" can be told apart
+CREATE INDEX IF NOT EXISTS idx_wallet_profiles_first_seen ON wallet_profiles(first_seen_at);
+
+INSERT INTO wallet_profiles (wallet_address, first_seen_block, first_seen_at)
+SELECT wallet_address, MIN(block_number), MIN(timestamp)
+FROM (
+    SELECT wallet_address, block_number, timestamp FROM swaps
+    UNION ALL
+    SELECT wallet_address, block_number, timestamp FROM wallet_activity
+) seen
+GROUP BY wallet_address
+ON CONFLICT (wallet_address) DO NOTHING;
@@ -0,0 +1,19 @@
+-- Responses to mutating API requests sent with an `Idempotency-Key` header,
+-- replayed when a client retries with the same key
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    idempotency_key TEXT PRIMARY KEY,
+    -- SHA-256 of the method, path and body the key was first used with
+    request_hash BYTEA NOT NULL,
+    -- NULL while the first request is still being handled
+    response_status SMALLINT,
+    response_content_type TEXT,
+    response_body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+
+    CONSTRAINT idempotency_keys_key_len CHECK (length(idempotency_key) BETWEEN 1 AND 255),
+    CONSTRAINT idempotency_keys_request_hash_len CHECK (octet_length(request_hash) = 32)
+);
+
+-- Expired keys are trimmed by age
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created_at ON idempotency_keys(created_at);
@@ -0,0 +1,71 @@
+-- Tokens with no liquidity and no swaps for a month are archived by the
+-- processor's token GC: left out of the lists by default, with their holders
+-- and price snapshots moved to the cold tables below. A new swap or transfer
+-- brings them back.
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_tokens_archived ON tokens(archived_at) WHERE archived_at IS NOT NULL;
+
+-- Same columns as the live tables, so rows move with `SELECT *`; keep them in
+-- step when either changes
+CREATE TABLE IF NOT EXISTS token_holders_cold (
+    LIKE token_holders INCLUDING DEFAULTS INCLUDING CONSTRAINTS
+);
+CREATE INDEX IF NOT EXISTS idx_token_holders_cold_token ON token_holders_cold(token_address);
+
+CREATE TABLE IF NOT EXISTS price_snapshots_cold (
+    LIKE price_snapshots INCLUDING DEFAULTS INCLUDING CONSTRAINTS
+);
+CREATE INDEX IF NOT EXISTS idx_price_snapshots_cold_token ON price_snapshots_cold(token_address);
+
+-- The token list views select tokens.*; rebuild them to carry the new column.
+-- Archived tokens rank after every live one so they never crowd them out.
+DROP MATERIALIZED VIEW IF EXISTS token_list_hot;
+DROP MATERIALIZED VIEW IF EXISTS token_list_new;
+DROP MATERIALIZED VIEW IF EXISTS token_list_trending;
+
+-- Hot: volume + BeeScore ranking
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_hot AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL,
+            (COALESCE(t.volume_1h_usd, 0) + COALESCE(t.bee_score, 0) * 100) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE t.volume_1h_usd > 0 OR t.bee_score > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_hot_address ON token_list_hot(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_hot_rank ON token_list_hot(rank);
+
+-- New: most recently created
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_new AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL, t.created_at DESC NULLS LAST, t.id DESC
+    ) AS rank
+FROM tokens t
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_new_address ON token_list_new(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_new_rank ON token_list_new(rank);
+
+-- Trending: 1h price momentum among tokens that actually traded
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_trending AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL,
+            COALESCE(t.price_change_1h, 0) DESC, COALESCE(t.volume_1h_usd, 0) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE COALESCE(t.trades_1h, 0) > 0 OR COALESCE(t.volume_1h_usd, 0) > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_trending_address ON token_list_trending(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_trending_rank ON token_list_trending(rank);
@@ -0,0 +1,25 @@
+-- Per-topic processor counters, written by the processor after each batch.
+-- Counts are cumulative since the row was created, so scrapers take rates
+-- from the difference between two reads.
+CREATE TABLE IF NOT EXISTS processor_event_metrics (
+    -- new_pair, swap, transfer, sync, liquidity, approval or unknown
+    event_type VARCHAR(20) PRIMARY KEY,
+    -- Logs pulled from the queue
+    events BIGINT NOT NULL DEFAULT 0,
+    -- Logs whose data couldn't be decoded
+    decode_failures BIGINT NOT NULL DEFAULT 0,
+    -- Decoded logs whose handler returned an error
+    handler_errors BIGINT NOT NULL DEFAULT 0,
+    -- Handler latency histogram: counts per bucket of the processor's
+    -- bucket bounds, the last one open-ended
+    latency_buckets BIGINT[] NOT NULL DEFAULT '{}',
+    latency_sum_ms BIGINT NOT NULL DEFAULT 0,
+    -- Start of the current run of windows whose error rate was over the limit
+    failing_since TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT processor_event_metrics_counts_valid CHECK (
+        events >= 0 AND decode_failures >= 0 AND handler_errors >= 0
+            AND latency_sum_ms >= 0
+    )
+);
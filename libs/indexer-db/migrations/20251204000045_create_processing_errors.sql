@@ -0,0 +1,21 @@
+-- Logs the processor failed to decode or handle, kept for post-hoc debugging
+-- of dropped events and trimmed by age
+CREATE TABLE IF NOT EXISTS processing_errors (
+    id BIGSERIAL PRIMARY KEY,
+    -- Queue receipt of the log: evm_logs id, Redis stream id or NATS sequence
+    log_id TEXT NOT NULL,
+    -- topic0 of the log
+    topic VARCHAR(66) NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    -- decode or handler
+    stage VARCHAR(10) NOT NULL,
+    error TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT processing_errors_tx_hash_len CHECK (octet_length(tx_hash) = 32),
+    CONSTRAINT processing_errors_stage_valid CHECK (stage IN ('decode', 'handler'))
+);
+
+-- Polled by time, trimmed by age
+CREATE INDEX IF NOT EXISTS idx_processing_errors_created_at ON processing_errors(created_at);
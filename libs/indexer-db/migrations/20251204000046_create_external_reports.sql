@@ -0,0 +1,78 @@
+-- Risk reports pushed by integrations (community bots, GoPlus, TokenSniffer),
+-- one per token per source: a source re-reporting a token replaces its report
+CREATE TABLE IF NOT EXISTS external_reports (
+    id SERIAL PRIMARY KEY,
+    token_address BYTEA NOT NULL,
+    source VARCHAR(50) NOT NULL,
+    -- info, low, medium, high or critical
+    severity VARCHAR(10) NOT NULL,
+    -- What was found, e.g. honeypot or rug_pull
+    category VARCHAR(50),
+    description TEXT,
+    url TEXT,
+    -- Verified by the source; only confirmed reports lower the safety score
+    confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+    reported_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (token_address, source),
+    CONSTRAINT external_reports_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT external_reports_severity_valid
+        CHECK (severity IN ('info', 'low', 'medium', 'high', 'critical'))
+);
+
+-- Worst severity among the token's external reports, NULL without any
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS external_risk VARCHAR(10);
+
+-- The token list views select tokens.*; rebuild them to carry the new column.
+-- The definitions are otherwise unchanged.
+DROP MATERIALIZED VIEW IF EXISTS token_list_hot;
+DROP MATERIALIZED VIEW IF EXISTS token_list_new;
+DROP MATERIALIZED VIEW IF EXISTS token_list_trending;
+
+-- Hot: volume + BeeScore ranking
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_hot AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL,
+            (COALESCE(t.volume_1h_usd, 0) + COALESCE(t.bee_score, 0) * 100) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE t.volume_1h_usd > 0 OR t.bee_score > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_hot_address ON token_list_hot(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_hot_rank ON token_list_hot(rank);
+
+-- New: most recently created
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_new AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL, t.created_at DESC NULLS LAST, t.id DESC
+    ) AS rank
+FROM tokens t
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_new_address ON token_list_new(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_new_rank ON token_list_new(rank);
+
+-- Trending: 1h price momentum among tokens that actually traded
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_trending AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL,
+            COALESCE(t.price_change_1h, 0) DESC, COALESCE(t.volume_1h_usd, 0) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE COALESCE(t.trades_1h, 0) > 0 OR COALESCE(t.volume_1h_usd, 0) > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_trending_address ON token_list_trending(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_trending_rank ON token_list_trending(rank);
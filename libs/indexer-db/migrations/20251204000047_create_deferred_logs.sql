@@ -0,0 +1,36 @@
+-- The processor pulls queued logs in chain order
+CREATE INDEX IF NOT EXISTS idx_evm_logs_block_log_index ON evm_logs(block_number, log_index);
+
+-- Logs that referenced a pair the processor didn't know yet (e.g. a swap
+-- whose PairCreated is still queued), parked and retried after a few
+-- processing cycles instead of being dropped. Same columns as evm_logs so a
+-- parked log reads back as one; `id` is the log's evm_logs.id, 0 when it
+-- came through the Redis or NATS queue.
+CREATE TABLE IF NOT EXISTS deferred_logs (
+    id INT NOT NULL,
+    block_number NUMERIC NOT NULL,
+    block_hash BYTEA NOT NULL,
+    address BYTEA NOT NULL,
+    transaction_hash BYTEA NOT NULL,
+    transaction_index BIGINT NOT NULL,
+    log_index BIGINT NOT NULL,
+    removed BOOL DEFAULT FALSE,
+    data BYTEA,
+    event_signature BYTEA,
+    topics BYTEA[],
+    block_timestamp TIMESTAMPTZ,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+
+    -- The pair that wasn't known
+    pair_address BYTEA NOT NULL,
+    -- Retries so far
+    attempts INT NOT NULL DEFAULT 0,
+    -- Processing cycles left until the next retry
+    cycles_left INT NOT NULL,
+    deferred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (transaction_hash, log_index),
+    CONSTRAINT deferred_logs_pair_address_len CHECK (octet_length(pair_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_deferred_logs_due ON deferred_logs(cycles_left, block_number, log_index);
@@ -0,0 +1,30 @@
+-- Swaps on pairs the processor doesn't index, typically created before
+-- indexing began. Kept as raw logs until the pair is indexed (looked up from
+-- its factory, rescanned or imported), then attributed to it. Same columns
+-- as evm_logs; `id` is the log's evm_logs.id, 0 when it came through the
+-- Redis or NATS queue.
+CREATE TABLE IF NOT EXISTS pending_swaps (
+    id INT NOT NULL,
+    block_number NUMERIC NOT NULL,
+    block_hash BYTEA NOT NULL,
+    address BYTEA NOT NULL,
+    transaction_hash BYTEA NOT NULL,
+    transaction_index BIGINT NOT NULL,
+    log_index BIGINT NOT NULL,
+    removed BOOL DEFAULT FALSE,
+    data BYTEA,
+    event_signature BYTEA,
+    topics BYTEA[],
+    block_timestamp TIMESTAMPTZ,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+
+    pair_address BYTEA NOT NULL,
+    buffered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (transaction_hash, log_index),
+    CONSTRAINT pending_swaps_pair_address_len CHECK (octet_length(pair_address) = 20)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_swaps_pair ON pending_swaps(pair_address);
+-- Trimmed by age
+CREATE INDEX IF NOT EXISTS idx_pending_swaps_buffered_at ON pending_swaps(buffered_at);
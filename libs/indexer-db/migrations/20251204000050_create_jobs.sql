@@ -0,0 +1,31 @@
+-- Long-running tasks (token imports, re-scores, holder reconciliations) run
+-- by the processor's job worker. Requesters poll progress and may cancel;
+-- a running job stops at its next progress report once cancellation is
+-- requested. `dedupe_key` keeps one open job per subject, e.g. one import per
+-- token.
+CREATE TABLE IF NOT EXISTS jobs (
+    id SERIAL PRIMARY KEY,
+    kind VARCHAR(32) NOT NULL,
+    params JSONB NOT NULL DEFAULT '{}',
+    dedupe_key TEXT,
+    status VARCHAR(10) NOT NULL DEFAULT 'pending',
+    -- Units of work done, out of `progress_total` when known
+    progress_done INT NOT NULL DEFAULT 0,
+    progress_total INT,
+    result JSONB,
+    error TEXT,
+    cancel_requested BOOL NOT NULL DEFAULT FALSE,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ,
+
+    CONSTRAINT jobs_status CHECK (status IN ('pending', 'running', 'done', 'failed', 'cancelled'))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_open_dedupe
+    ON jobs(dedupe_key) WHERE status IN ('pending', 'running');
+CREATE INDEX IF NOT EXISTS idx_jobs_pending ON jobs(id) WHERE status = 'pending';
+-- Running jobs that stopped reporting progress are claimed again
+CREATE INDEX IF NOT EXISTS idx_jobs_running ON jobs(updated_at) WHERE status = 'running';
+CREATE INDEX IF NOT EXISTS idx_jobs_requested_at ON jobs(requested_at DESC);
@@ -0,0 +1,12 @@
+-- Tokens whose Transfers are indexed in sampling mode (TRANSFER_SAMPLING),
+-- reselected by the processor on an interval. The listener narrows its
+-- Transfer filter to these addresses; the processor skips Transfers of others.
+CREATE TABLE IF NOT EXISTS sampled_tokens (
+    token_address BYTEA PRIMARY KEY,
+    -- BeeScore and liquidity when last selected
+    bee_score SMALLINT,
+    liquidity_usd DECIMAL(30, 2),
+    selected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT sampled_tokens_address_len CHECK (octet_length(token_address) = 20)
+);
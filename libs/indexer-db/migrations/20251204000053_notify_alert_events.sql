@@ -0,0 +1,16 @@
+-- Wake long-polling alert feed requests (/api/alerts/feed?wait=) as soon as
+-- an alert is raised: every insert into alert_events is announced on the
+-- `alert_events` channel with the new id as payload.
+CREATE OR REPLACE FUNCTION notify_alert_event()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('alert_events', NEW.id::TEXT);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS alert_events_notify ON alert_events;
+CREATE TRIGGER alert_events_notify
+AFTER INSERT ON alert_events
+FOR EACH ROW
+EXECUTE FUNCTION notify_alert_event();
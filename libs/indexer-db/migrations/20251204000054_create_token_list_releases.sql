@@ -0,0 +1,16 @@
+-- Published versions of the curated token list (TokenList standard), served
+-- at /api/tokenlist.json. The processor adds a release whenever the tokens
+-- qualifying for the list change: removals bump the major version, additions
+-- the minor and changed token details the patch.
+CREATE TABLE IF NOT EXISTS token_list_releases (
+    id SERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    major INTEGER NOT NULL,
+    minor INTEGER NOT NULL,
+    patch INTEGER NOT NULL,
+    -- [{address, name, symbol, decimals}], by address
+    tokens JSONB NOT NULL,
+    published_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (chain_id, major, minor, patch)
+);
@@ -0,0 +1,75 @@
+-- Wallets caught buying launches like sniper bots: in the pair creation block,
+-- with a gas limit matching the gas used, or through their own contract
+-- instead of a router. The set is global: a wallet caught on one launch counts
+-- as a bot on every token.
+CREATE TABLE IF NOT EXISTS bot_wallets (
+    wallet_address BYTEA PRIMARY KEY,
+    -- creation_block, exact_gas_limit, contract_caller; every one seen so far
+    signals TEXT[] NOT NULL,
+    -- The buy the wallet was first caught on
+    token_address BYTEA NOT NULL,
+    tx_hash BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    first_detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT bot_wallets_address_len CHECK (octet_length(wallet_address) = 20),
+    CONSTRAINT bot_wallets_token_address_len CHECK (octet_length(token_address) = 20),
+    CONSTRAINT bot_wallets_tx_hash_len CHECK (octet_length(tx_hash) = 32)
+);
+
+-- Holders other than bot wallets, and the share of supply bots hold
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS organic_holder_count INTEGER;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS bot_holder_percent DECIMAL(5, 2);
+
+-- The token list views select tokens.*; rebuild them to carry the new columns.
+-- The definitions are otherwise unchanged.
+DROP MATERIALIZED VIEW IF EXISTS token_list_hot;
+DROP MATERIALIZED VIEW IF EXISTS token_list_new;
+DROP MATERIALIZED VIEW IF EXISTS token_list_trending;
+
+-- Hot: volume + BeeScore ranking
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_hot AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL,
+            (COALESCE(t.volume_1h_usd, 0) + COALESCE(t.bee_score, 0) * 100) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE t.volume_1h_usd > 0 OR t.bee_score > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_hot_address ON token_list_hot(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_hot_rank ON token_list_hot(rank);
+
+-- New: most recently created
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_new AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL, t.created_at DESC NULLS LAST, t.id DESC
+    ) AS rank
+FROM tokens t
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_new_address ON token_list_new(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_new_rank ON token_list_new(rank);
+
+-- Trending: 1h price momentum among tokens that actually traded
+CREATE MATERIALIZED VIEW IF NOT EXISTS token_list_trending AS
+SELECT
+    t.*,
+    ROW_NUMBER() OVER (
+        ORDER BY t.archived_at IS NOT NULL,
+            COALESCE(t.price_change_1h, 0) DESC, COALESCE(t.volume_1h_usd, 0) DESC, t.id DESC
+    ) AS rank
+FROM tokens t
+WHERE COALESCE(t.trades_1h, 0) > 0 OR COALESCE(t.volume_1h_usd, 0) > 0
+ORDER BY rank
+LIMIT 200;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_token_list_trending_address ON token_list_trending(address);
+CREATE INDEX IF NOT EXISTS idx_token_list_trending_rank ON token_list_trending(rank);
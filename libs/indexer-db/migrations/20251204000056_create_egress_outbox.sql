@@ -0,0 +1,14 @@
+-- Events the processor couldn't hand to the durable egress (NATS JetStream
+-- down or slow), kept in publish order and retried by the processor. Logs
+-- are acked once handled whatever egress does, so handlers never see them
+-- twice; `event_id` is the JetStream message id, so a retry that did land
+-- before is dropped there.
+CREATE TABLE IF NOT EXISTS egress_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    event_id TEXT NOT NULL UNIQUE,
+    payload TEXT NOT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
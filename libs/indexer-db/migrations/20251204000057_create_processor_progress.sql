@@ -0,0 +1,11 @@
+-- The block the processor has handled every queued log up to and including.
+-- Jobs that read chain state (holder reconciliation, verification, rescans)
+-- read it at this block, so what they write lines up with the logs still
+-- queued behind it. One row.
+CREATE TABLE IF NOT EXISTS processor_progress (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE,
+    last_processed_block BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT processor_progress_single_row CHECK (id)
+);
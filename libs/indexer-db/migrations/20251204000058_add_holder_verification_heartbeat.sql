@@ -0,0 +1,6 @@
+-- A running verification records its progress after every page of holders.
+-- One whose processor died stops being updated, and is claimed again once
+-- `updated_at` is older than HOLDER_VERIFY_STALE_SECS.
+ALTER TABLE holder_verifications ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX IF NOT EXISTS idx_holder_verifications_running ON holder_verifications(updated_at) WHERE status = 'running';
@@ -0,0 +1,6 @@
+-- Alerts collapsed into a rollup are marked, so they stay hidden from the feed
+-- and webhooks when retention deletes the rollup (`rollup_id` is then set to
+-- NULL).
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS rolled_up BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE alert_events SET rolled_up = TRUE WHERE rollup_id IS NOT NULL;
@@ -0,0 +1,8 @@
+-- Idempotency keys are scoped to the X-API-Key they were sent with (its
+-- SHA-256; that of the empty string for requests without one), so one client
+-- can't replay or hold up another's requests by guessing its keys
+ALTER TABLE idempotency_keys
+    ADD COLUMN IF NOT EXISTS client_hash BYTEA NOT NULL DEFAULT sha256(''::BYTEA);
+
+ALTER TABLE idempotency_keys DROP CONSTRAINT IF EXISTS idempotency_keys_pkey;
+ALTER TABLE idempotency_keys ADD PRIMARY KEY (client_hash, idempotency_key);
@@ -0,0 +1,4 @@
+-- Pairs found from one of their swaps rather than their PairCreated event are
+-- marked: their block_number is that swap's, not the creation block, so
+-- launch signals that compare against it don't apply to them.
+ALTER TABLE pairs ADD COLUMN IF NOT EXISTS discovered BOOLEAN NOT NULL DEFAULT FALSE;
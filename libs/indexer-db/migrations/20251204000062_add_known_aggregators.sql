@@ -0,0 +1,15 @@
+-- DEX aggregators route people's buys like routers do; buys sent through one
+-- aren't buys through a bot's own contract.
+ALTER TABLE known_addresses DROP CONSTRAINT IF EXISTS known_addresses_kind_valid;
+ALTER TABLE known_addresses ADD CONSTRAINT known_addresses_kind_valid CHECK (
+    kind IN ('cex', 'router', 'aggregator', 'bridge', 'locker', 'burn')
+);
+
+-- BNB Smart Chain
+INSERT INTO known_addresses (chain_id, kind, name, address) VALUES
+    (56, 'aggregator', '1inch', '\x1111111254eeb25477b68fb85ed929f73a960582'),
+    (56, 'aggregator', '0x', '\xdef1c0ded9bec7f1a1670819833240f027b25eff'),
+    (56, 'aggregator', 'ParaSwap', '\xdef171fe48cf0115b1d80b88dc8eab59176fee57'),
+    (56, 'aggregator', 'KyberSwap', '\x6131b5fae19ea4f9d964eac0408e4408b66337b5'),
+    (56, 'aggregator', 'OpenOcean', '\x6352a56caadc4f1e25cd6c75970fa768a3304e64')
+ON CONFLICT (chain_id, address) DO NOTHING;
@@ -0,0 +1,14 @@
+-- Logs the processor has handled, by (transaction_hash, log_index). Queues
+-- deliver at least once, so a log handed out again after it was handled is
+-- skipped instead of counted twice. Trimmed by retention past the window a
+-- queue could redeliver in.
+CREATE TABLE IF NOT EXISTS handled_logs (
+    transaction_hash BYTEA NOT NULL,
+    log_index BIGINT NOT NULL,
+    handled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (transaction_hash, log_index),
+    CONSTRAINT handled_logs_transaction_hash_len CHECK (octet_length(transaction_hash) = 32)
+);
+
+CREATE INDEX IF NOT EXISTS idx_handled_logs_handled_at ON handled_logs(handled_at);
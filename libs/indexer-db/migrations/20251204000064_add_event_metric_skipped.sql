@@ -0,0 +1,9 @@
+-- Logs of topics the processor has no decoder for are skipped, not failed:
+-- counted apart so they don't read as a broken decoder.
+ALTER TABLE processor_event_metrics ADD COLUMN IF NOT EXISTS skipped BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE processor_event_metrics DROP CONSTRAINT IF EXISTS processor_event_metrics_counts_valid;
+ALTER TABLE processor_event_metrics ADD CONSTRAINT processor_event_metrics_counts_valid CHECK (
+    events >= 0 AND decode_failures >= 0 AND handler_errors >= 0 AND skipped >= 0
+        AND latency_sum_ms >= 0
+);
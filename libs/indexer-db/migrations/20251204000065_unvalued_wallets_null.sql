@@ -0,0 +1,3 @@
+-- A wallet's value is written by the valuation job. Until its first run the
+-- value is unknown rather than zero, so listings can fall back to activity.
+ALTER TABLE wallets ALTER COLUMN estimated_value_usd DROP DEFAULT;
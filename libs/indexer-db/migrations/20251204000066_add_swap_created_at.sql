@@ -0,0 +1,4 @@
+-- When a swap was recorded, as opposed to when it traded. Readers paging the
+-- swaps forward by id hold back the newest rows for a moment, so a
+-- transaction committing late can't land below an id they already passed.
+ALTER TABLE swaps ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
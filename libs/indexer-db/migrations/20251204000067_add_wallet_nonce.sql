@@ -0,0 +1,10 @@
+-- How many transactions a recent buyer had sent on chain, read once after it
+-- is first seen. A handful marks a brand-new wallet, whatever the index saw
+-- before; a cluster of them buying one token is typical of a sniper farm.
+ALTER TABLE wallet_profiles ADD COLUMN IF NOT EXISTS nonce BIGINT;
+-- When the nonce was read
+ALTER TABLE wallet_profiles ADD COLUMN IF NOT EXISTS nonce_checked_at TIMESTAMPTZ;
+
+-- Recent wallets still waiting for their nonce
+CREATE INDEX IF NOT EXISTS idx_wallet_profiles_nonce_unchecked
+    ON wallet_profiles(first_seen_at) WHERE nonce_checked_at IS NULL;
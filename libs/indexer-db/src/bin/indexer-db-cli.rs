@@ -0,0 +1,331 @@
+//! Database maintenance CLI
+//!
+//! Connects with the same `PG*` environment (and `.env`) as the services.
+
+use std::{env, error::Error, process::ExitCode};
+
+use indexer_db::{
+    entity::{
+        token_rescan::{NewTokenRescan, TokenRescan},
+        DrainerAddress, HolderVerification, Token,
+    },
+    initialize_database,
+    maintenance::{self, PruneTable},
+    query_plan,
+    seed::{self, SeedFormat, SeedKind},
+    Address20,
+};
+
+const USAGE: &str = "\
+Usage: indexer-db-cli <command>
+
+Commands:
+  migrate                                   Apply pending migrations
+  verify-schema                             Compare applied migrations and index usage with this build
+  prune --table <table> --days <n> [--batch <n>]
+                                            Delete rows older than <n> days (price_snapshots are
+                                            downsampled to one per hour instead)
+  reindex-token <address>                   Recompute a token's trade, holder and churn metrics
+  rescan-token <address> [--from-block <n>] [--to-block <n>]
+                                            Queue a replay of the token's logs from the RPC (run by
+                                            the processor; defaults to creation block..head)
+  verify-holders <address>                  Queue a balanceOf check of every stored holder (run by
+                                            the processor; prunes emptied holders)
+  stats [--exact]                           Row counts and sizes per table (estimated unless --exact)
+  drainer add <address> <label>             List a drainer contract; approvals to it are flagged
+  drainer remove <address>                  Take a drainer off the list
+  drainer list                              Listed drainers of CHAIN_ID (default 56)
+  import-seed tokens|pairs <file> [--format csv|json] [--dry-run]
+                                            Bootstrap tokens or pairs from a dump; indexed rows
+                                            keep their values (format defaults to the extension)";
+
+/// Rows deleted per statement when pruning, unless `--batch` says otherwise
+const DEFAULT_PRUNE_BATCH: i64 = 5000;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(command) = args.first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    match run(command, &args[1..]).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run one command, returning whether it succeeded
+async fn run(command: &str, args: &[String]) -> Result<bool, Box<dyn Error>> {
+    match command {
+        "migrate" => {
+            let pool = initialize_database().await?;
+            maintenance::migrate(&pool).await?;
+            println!("Migrations are up to date");
+            Ok(true)
+        }
+        "verify-schema" => {
+            let pool = initialize_database().await?;
+            let issues = maintenance::verify_schema(&pool).await?;
+            for issue in &issues {
+                println!("Migration {}", issue);
+            }
+            let warnings = query_plan::check_query_plans(&pool).await?;
+            for w in &warnings {
+                println!(
+                    "Query `{}` needs a sequential scan on `{}`",
+                    w.query, w.relation
+                );
+            }
+            if issues.is_empty() && warnings.is_empty() {
+                println!("Schema matches this build");
+            }
+            Ok(issues.is_empty())
+        }
+        "prune" => {
+            let table = flag(args, "--table").ok_or("`--table` is required")?;
+            let table = PruneTable::parse(table).ok_or_else(|| {
+                let names: Vec<_> = PruneTable::ALL.iter().map(|t| t.name()).collect();
+                format!("`--table` must be one of {}", names.join(", "))
+            })?;
+            let days = flag(args, "--days")
+                .ok_or("`--days` is required")?
+                .parse::<i64>()
+                .map_err(|_| "`--days` must be a number of days")?;
+            let batch = match flag(args, "--batch") {
+                Some(batch) => batch
+                    .parse::<i64>()
+                    .map_err(|_| "`--batch` must be a number of rows")?,
+                None => DEFAULT_PRUNE_BATCH,
+            };
+
+            let pool = initialize_database().await?;
+            let removed = maintenance::prune(table, days, batch, &pool).await?;
+            println!("Removed {} rows from {}", removed, table.name());
+            Ok(true)
+        }
+        "reindex-token" => {
+            let address: Address20 = args
+                .first()
+                .ok_or("A token address is required")?
+                .parse()
+                .map_err(|_| "Invalid token address")?;
+
+            let pool = initialize_database().await?;
+            if maintenance::reindex_token(&address, &pool).await? {
+                println!("Reindexed {}", address);
+                Ok(true)
+            } else {
+                eprintln!("Token {} is not indexed", address);
+                Ok(false)
+            }
+        }
+        "rescan-token" => {
+            let address: Address20 = args
+                .first()
+                .ok_or("A token address is required")?
+                .parse()
+                .map_err(|_| "Invalid token address")?;
+            let block = |name: &str| -> Result<Option<i64>, String> {
+                flag(args, name)
+                    .map(|b| {
+                        b.parse::<i64>()
+                            .map_err(|_| format!("`{}` must be a block number", name))
+                    })
+                    .transpose()
+            };
+            let from_block = block("--from-block")?;
+            let to_block = block("--to-block")?;
+            if let (Some(from), Some(to)) = (from_block, to_block) {
+                if from > to {
+                    return Err("`--from-block` must not be after `--to-block`".into());
+                }
+            }
+
+            let pool = initialize_database().await?;
+            if Token::find_by_address(&address, &pool).await?.is_none() {
+                eprintln!("Token {} is not indexed", address);
+                return Ok(false);
+            }
+            let rescan = NewTokenRescan {
+                token_address: address,
+                from_block,
+                to_block,
+            };
+            let queued = TokenRescan::create(&rescan, &pool).await?;
+            println!(
+                "Queued rescan #{} of {}; track it at /api/admin/rescans/{}",
+                queued.id, address, queued.id
+            );
+            Ok(true)
+        }
+        "verify-holders" => {
+            let address: Address20 = args
+                .first()
+                .ok_or("A token address is required")?
+                .parse()
+                .map_err(|_| "Invalid token address")?;
+
+            let pool = initialize_database().await?;
+            if Token::find_by_address(&address, &pool).await?.is_none() {
+                eprintln!("Token {} is not indexed", address);
+                return Ok(false);
+            }
+            let queued = HolderVerification::create(&address, &pool).await?;
+            println!(
+                "Queued holder verification #{} of {}; track it at /api/admin/holder-verifications/{}",
+                queued.id, address, queued.id
+            );
+            Ok(true)
+        }
+        "stats" => {
+            let exact = args.iter().any(|a| a == "--exact");
+            let pool = initialize_database().await?;
+            let stats = maintenance::table_stats(exact, &pool).await?;
+
+            println!("{:<32} {:>14} {:>10}", "table", "rows", "size");
+            for t in &stats {
+                println!(
+                    "{:<32} {:>14} {:>10}",
+                    t.table_name,
+                    t.rows,
+                    human_bytes(t.total_bytes)
+                );
+            }
+            Ok(true)
+        }
+        "drainer" => {
+            let chain_id = env::var("CHAIN_ID")
+                .ok()
+                .and_then(|c| c.parse::<i64>().ok())
+                .unwrap_or(56);
+            let address = || -> Result<Address20, String> {
+                args.get(1)
+                    .ok_or("A drainer address is required")?
+                    .parse()
+                    .map_err(|_| "Invalid drainer address".to_string())
+            };
+
+            match args.first().map(String::as_str) {
+                Some("add") => {
+                    let address = address()?;
+                    let label = args.get(2..).unwrap_or_default().join(" ");
+                    if label.is_empty() {
+                        return Err("A label is required".into());
+                    }
+                    let pool = initialize_database().await?;
+                    let drainer = DrainerAddress::upsert(chain_id, &address, &label, &pool).await?;
+                    println!("Listed {} as `{}`", drainer.address, drainer.label);
+                    Ok(true)
+                }
+                Some("remove") => {
+                    let address = address()?;
+                    let pool = initialize_database().await?;
+                    if DrainerAddress::delete(chain_id, &address, &pool).await? {
+                        println!("Removed {}", address);
+                        Ok(true)
+                    } else {
+                        eprintln!("{} is not listed", address);
+                        Ok(false)
+                    }
+                }
+                Some("list") => {
+                    let pool = initialize_database().await?;
+                    for drainer in DrainerAddress::find_by_chain(chain_id, &pool).await? {
+                        println!(
+                            "{}  {}  {}",
+                            drainer.address,
+                            drainer.added_at.format("%Y-%m-%d"),
+                            drainer.label
+                        );
+                    }
+                    Ok(true)
+                }
+                _ => {
+                    eprintln!("Usage: indexer-db-cli drainer add|remove|list\n\n{}", USAGE);
+                    Ok(false)
+                }
+            }
+        }
+        "import-seed" => {
+            let kind = args
+                .first()
+                .and_then(|k| SeedKind::parse(k))
+                .ok_or("The first argument must be `tokens` or `pairs`")?;
+            let path = args.get(1).ok_or("A dump file is required")?;
+            let format = match flag(args, "--format") {
+                Some(format) => {
+                    SeedFormat::parse(format).ok_or("`--format` must be `csv` or `json`")?
+                }
+                None => SeedFormat::from_path(path)
+                    .ok_or("Can't tell the format from the file name; pass `--format`")?,
+            };
+            let dry_run = args.iter().any(|a| a == "--dry-run");
+            let chain_id = env::var("CHAIN_ID")
+                .ok()
+                .and_then(|c| c.parse::<i64>().ok())
+                .unwrap_or(56);
+
+            let file = std::fs::File::open(path)
+                .map_err(|e| format!("Can't open `{}`: {}", path, e))?;
+            let pool = initialize_database().await?;
+            let report = seed::import(
+                kind,
+                format,
+                std::io::BufReader::new(file),
+                chain_id,
+                dry_run,
+                &pool,
+            )
+            .await?;
+            for rejected in &report.rejected {
+                eprintln!("Skipped {}", rejected);
+            }
+            let inserted = if dry_run { "Valid" } else { "Inserted" };
+            println!(
+                "{} {}, already indexed {}, skipped {}",
+                inserted,
+                report.inserted,
+                report.existing,
+                report.rejected.len()
+            );
+            Ok(report.rejected.is_empty())
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        other => {
+            eprintln!("Unknown command `{}`\n\n{}", other, USAGE);
+            Ok(false)
+        }
+    }
+}
+
+/// Value following `name` in `args`
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// `1536` → `1.5 KB`
+fn human_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
@@ -1,19 +1,25 @@
-
 use serde_json::Value as JsonValue;
 use sqlx::{
     types::{chrono, BigDecimal, Json},
     Executor, Postgres,
 };
 
+use super::{
+    alert_metadata::{AlertMetadata, MetadataError, Rollup, RollupMetadata},
+    evm_logs::SourceLog,
+    COMMIT_LAG,
+};
+use crate::types::{Address20, Hash32};
+
 /// AlertEvent entity for notification queue
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct AlertEvent {
     pub id: i32,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     pub alert_type: String,
-    pub token_address: Option<String>,
+    pub token_address: Option<Address20>,
     pub token_symbol: Option<String>,
-    pub wallet_address: Option<String>,
+    pub wallet_address: Option<Address20>,
     pub title: String,
     pub message: Option<String>,
     pub bee_score: Option<i16>,
@@ -22,6 +28,65 @@ pub struct AlertEvent {
     pub metadata: Option<Json<JsonValue>>,
     pub processed: Option<bool>,
     pub processed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Log that raised the alert; `None` for alerts from scheduled jobs
+    pub source_log_id: Option<i32>,
+    pub source_tx_hash: Option<Hash32>,
+    /// Rollup alert this one was collapsed into, while the rollup is kept
+    pub rollup_id: Option<i32>,
+    /// Collapsed into a rollup; hidden from the feed and webhooks
+    pub rolled_up: bool,
+}
+
+/// Visible alerts of one type on one token, enough of them within the rollup
+/// window to collapse into a single rollup alert
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct AlertBurst {
+    pub token_address: Address20,
+    pub alert_type: String,
+    pub token_symbol: Option<String>,
+    /// Alerts to hide behind the rollup
+    pub member_ids: Vec<i32>,
+    pub count: i64,
+    pub total_usd: Option<BigDecimal>,
+    pub bee_score: Option<i16>,
+}
+
+impl AlertBurst {
+    /// The rollup alert replacing the burst, e.g. "3 whale buy alerts on
+    /// $PEPE in 10 min", with the covered alert ids in `metadata.rollup`
+    pub fn rollup_alert(&self, window_secs: i64) -> NewAlert {
+        let symbol = self.token_symbol.as_deref().unwrap_or("token");
+        let minutes = (window_secs + 59) / 60;
+
+        NewAlert {
+            alert_type: self.alert_type.clone(),
+            token_address: Some(self.token_address),
+            token_symbol: self.token_symbol.clone(),
+            wallet_address: None,
+            title: format!(
+                "{} {} alerts on ${} in {} min",
+                self.count,
+                self.alert_type.replace('_', " "),
+                symbol,
+                minutes
+            ),
+            message: self
+                .total_usd
+                .as_ref()
+                .map(|total| format!("${} in total across {} alerts", total, self.count)),
+            bee_score: self.bee_score,
+            amount_usd: self.total_usd.clone(),
+            change_percent: None,
+            metadata: Some(AlertMetadata::Rollup(RollupMetadata {
+                rollup: Rollup {
+                    count: self.count,
+                    alert_ids: self.member_ids.clone(),
+                    window_secs,
+                },
+            })),
+            source: None,
+        }
+    }
 }
 
 /// Alert types
@@ -36,9 +101,66 @@ pub enum AlertType {
     LpUnlocking,
     HighBeeScore,
     DevSell,
+    TrendingEnter,
+    TrendingExit,
+    /// Large transfer into an exchange wallet
+    CexInflow,
+    /// Large transfer out of an exchange wallet
+    CexOutflow,
+    /// New token copying an established token's name or symbol
+    Impersonation,
+    /// Processing lag over its SLO target for longer than allowed
+    LagSloBreach,
+    /// Tracked wallet approved a listed drainer to spend a token
+    RiskyApproval,
+    /// Token owner blacklisted holders after launch
+    HolderBlacklisted,
+    /// An event type's decode failures or handler errors over their limit
+    EventErrorRate,
 }
 
 impl AlertType {
+    pub const ALL: [AlertType; 18] = [
+        AlertType::NewToken,
+        AlertType::WhaleBuy,
+        AlertType::WhaleSell,
+        AlertType::PricePump,
+        AlertType::PriceDump,
+        AlertType::LpLocked,
+        AlertType::LpUnlocking,
+        AlertType::HighBeeScore,
+        AlertType::DevSell,
+        AlertType::TrendingEnter,
+        AlertType::TrendingExit,
+        AlertType::CexInflow,
+        AlertType::CexOutflow,
+        AlertType::Impersonation,
+        AlertType::LagSloBreach,
+        AlertType::RiskyApproval,
+        AlertType::HolderBlacklisted,
+        AlertType::EventErrorRate,
+    ];
+
+    /// Risk alerts still delivered for tokens set to `critical` only
+    pub const CRITICAL: [AlertType; 7] = [
+        AlertType::WhaleSell,
+        AlertType::PriceDump,
+        AlertType::LpUnlocking,
+        AlertType::DevSell,
+        AlertType::Impersonation,
+        AlertType::RiskyApproval,
+        AlertType::HolderBlacklisted,
+    ];
+
+    /// [`Self::CRITICAL`] as stored in `alert_events.alert_type`
+    pub fn critical_names() -> Vec<&'static str> {
+        Self::CRITICAL.iter().map(|t| t.as_str()).collect()
+    }
+
+    pub fn parse(name: &str) -> Option<AlertType> {
+        AlertType::ALL.into_iter().find(|t| t.as_str() == name)
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             AlertType::NewToken => "new_token",
@@ -50,6 +172,15 @@ impl AlertType {
             AlertType::LpUnlocking => "lp_unlocking",
             AlertType::HighBeeScore => "high_bee_score",
             AlertType::DevSell => "dev_sell",
+            AlertType::TrendingEnter => "trending_enter",
+            AlertType::TrendingExit => "trending_exit",
+            AlertType::CexInflow => "cex_inflow",
+            AlertType::CexOutflow => "cex_outflow",
+            AlertType::Impersonation => "impersonation",
+            AlertType::LagSloBreach => "lag_slo_breach",
+            AlertType::RiskyApproval => "risky_approval",
+            AlertType::HolderBlacklisted => "holder_blacklisted",
+            AlertType::EventErrorRate => "event_error_rate",
         }
     }
 }
@@ -58,18 +189,30 @@ impl AlertType {
 #[derive(Debug, Clone)]
 pub struct NewAlert {
     pub alert_type: String,
-    pub token_address: Option<String>,
+    pub token_address: Option<Address20>,
     pub token_symbol: Option<String>,
-    pub wallet_address: Option<String>,
+    pub wallet_address: Option<Address20>,
     pub title: String,
     pub message: Option<String>,
     pub bee_score: Option<i16>,
     pub amount_usd: Option<BigDecimal>,
     pub change_percent: Option<BigDecimal>,
-    pub metadata: Option<JsonValue>,
+    pub metadata: Option<AlertMetadata>,
+    pub source: Option<SourceLog>,
 }
 
 impl AlertEvent {
+    /// Channel every new alert's id is announced on (`pg_notify`)
+    pub const NOTIFY_CHANNEL: &'static str = "alert_events";
+
+    /// Stored metadata checked against the alert's type; `None` when there is
+    /// none
+    pub fn typed_metadata(&self) -> Option<Result<AlertMetadata, MetadataError>> {
+        self.metadata
+            .as_ref()
+            .map(|m| AlertMetadata::parse(&self.alert_type, &m.0))
+    }
+
     /// Create a new alert event
     pub async fn create<'c, E>(alert: &NewAlert, connection: E) -> Result<AlertEvent, sqlx::Error>
     where
@@ -78,30 +221,33 @@ impl AlertEvent {
         let query = r#"
             INSERT INTO alert_events (
                 alert_type, token_address, token_symbol, wallet_address,
-                title, message, bee_score, amount_usd, change_percent, metadata
+                title, message, bee_score, amount_usd, change_percent, metadata,
+                source_log_id, source_tx_hash
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             RETURNING *
         "#;
 
         sqlx::query_as::<_, AlertEvent>(query)
             .bind(&alert.alert_type)
-            .bind(&alert.token_address)
+            .bind(alert.token_address)
             .bind(&alert.token_symbol)
-            .bind(&alert.wallet_address)
+            .bind(alert.wallet_address)
             .bind(&alert.title)
             .bind(&alert.message)
             .bind(alert.bee_score)
             .bind(&alert.amount_usd)
             .bind(&alert.change_percent)
             .bind(alert.metadata.as_ref().map(Json))
+            .bind(alert.source.and_then(|s| s.log_id))
+            .bind(alert.source.map(|s| s.tx_hash))
             .fetch_one(connection)
             .await
     }
 
     /// Create a new token alert
     pub async fn create_new_token_alert<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         token_symbol: &str,
         connection: E,
     ) -> Result<AlertEvent, sqlx::Error>
@@ -110,7 +256,7 @@ impl AlertEvent {
     {
         let alert = NewAlert {
             alert_type: AlertType::NewToken.as_str().to_string(),
-            token_address: Some(token_address.to_string()),
+            token_address: Some(*token_address),
             token_symbol: Some(token_symbol.to_string()),
             wallet_address: None,
             title: format!("New Token: {}", token_symbol),
@@ -119,6 +265,7 @@ impl AlertEvent {
             amount_usd: None,
             change_percent: None,
             metadata: None,
+            source: None,
         };
 
         Self::create(&alert, connection).await
@@ -126,9 +273,9 @@ impl AlertEvent {
 
     /// Create a whale alert
     pub async fn create_whale_alert<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         token_symbol: &str,
-        wallet_address: &str,
+        wallet_address: &Address20,
         is_buy: bool,
         amount_usd: &BigDecimal,
         connection: E,
@@ -146,9 +293,9 @@ impl AlertEvent {
 
         let alert = NewAlert {
             alert_type: alert_type.as_str().to_string(),
-            token_address: Some(token_address.to_string()),
+            token_address: Some(*token_address),
             token_symbol: Some(token_symbol.to_string()),
-            wallet_address: Some(wallet_address.to_string()),
+            wallet_address: Some(*wallet_address),
             title: format!("Whale {} ${}", action, token_symbol),
             message: Some(format!(
                 "Whale {} ${} worth of {}",
@@ -158,6 +305,7 @@ impl AlertEvent {
             amount_usd: Some(amount_usd.clone()),
             change_percent: None,
             metadata: None,
+            source: None,
         };
 
         Self::create(&alert, connection).await
@@ -179,20 +327,50 @@ impl AlertEvent {
         .await
     }
 
-    /// Get recent alerts (for feed)
+    /// Get recent alerts (for feed), newest first
+    ///
+    /// With `after_id`, only alerts raised after that one: the oldest `limit`
+    /// of them, so a poller resuming from the largest id it has seen pages
+    /// through a backlog without skipping any. Alerts younger than
+    /// [`COMMIT_LAG`] wait for a later page.
     pub async fn find_recent<'c, E>(
+        after_id: Option<i32>,
         limit: i32,
         connection: E,
     ) -> Result<Vec<AlertEvent>, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
-        sqlx::query_as::<_, AlertEvent>(
-            "SELECT * FROM alert_events ORDER BY created_at DESC LIMIT $1",
-        )
-        .bind(limit)
-        .fetch_all(connection)
-        .await
+        let query = format!(
+            r#"
+            SELECT * FROM alert_events
+            WHERE $1::INT IS NULL
+               OR (id > $1 AND created_at <= NOW() - make_interval(secs => $3))
+            ORDER BY {}
+            LIMIT $2
+            "#,
+            Self::page_order(after_id)
+        );
+
+        let mut alerts = sqlx::query_as::<_, AlertEvent>(&query)
+            .bind(after_id)
+            .bind(limit)
+            .bind(COMMIT_LAG.as_secs_f64())
+            .fetch_all(connection)
+            .await?;
+        if after_id.is_some() {
+            alerts.reverse();
+        }
+        Ok(alerts)
+    }
+
+    /// Newest first for the latest page; oldest first after a known id, so
+    /// the page picks up right where the caller left off (flipped afterwards)
+    fn page_order(after_id: Option<i32>) -> &'static str {
+        match after_id {
+            Some(_) => "id",
+            None => "created_at DESC",
+        }
     }
 
     /// Get alerts by type
@@ -213,9 +391,118 @@ impl AlertEvent {
         .await
     }
 
+    /// Latest alerts for the feed, optionally of one type, leaving out what
+    /// the alert preferences stored under `api_key` silence. `after_id` pages
+    /// forward as in [`AlertEvent::find_recent`].
+    pub async fn find_feed<'c, E>(
+        alert_type: Option<&str>,
+        api_key: Option<&str>,
+        after_id: Option<i32>,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<AlertEvent>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = format!(
+            r#"
+            SELECT a.* FROM alert_events a
+            WHERE NOT a.rolled_up
+              AND ($1::TEXT IS NULL OR a.alert_type = $1)
+              AND ($4::INT IS NULL OR (
+                  a.id > $4 AND a.created_at <= NOW() - make_interval(secs => $6)
+              ))
+              AND NOT EXISTS (
+                  SELECT 1 FROM alert_preferences p
+                  WHERE p.key_hash = sha256(convert_to($2, 'UTF8'))
+                    AND p.token_address = a.token_address
+                    AND (p.level = 'mute' OR NOT a.alert_type = ANY($3))
+              )
+            ORDER BY a.{}
+            LIMIT $5
+            "#,
+            Self::page_order(after_id)
+        );
+
+        let mut alerts = sqlx::query_as::<_, AlertEvent>(&query)
+            .bind(alert_type)
+            .bind(api_key)
+            .bind(AlertType::critical_names())
+            .bind(after_id)
+            .bind(limit)
+            .bind(COMMIT_LAG.as_secs_f64())
+            .fetch_all(connection)
+            .await?;
+        if after_id.is_some() {
+            alerts.reverse();
+        }
+        Ok(alerts)
+    }
+
+    /// Bursts of `alert_types` alerts: at least `min_count` on one token
+    /// within the last `window_secs`, not yet rolled up. Rollups don't count,
+    /// so a burst that keeps going is rolled up again only once `min_count`
+    /// more alerts came in.
+    pub async fn find_bursts<'c, E>(
+        alert_types: &[&str],
+        window_secs: i64,
+        min_count: i64,
+        connection: E,
+    ) -> Result<Vec<AlertBurst>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, AlertBurst>(
+            r#"
+            SELECT a.token_address, a.alert_type,
+                   MAX(a.token_symbol) AS token_symbol,
+                   array_agg(a.id ORDER BY a.id) AS member_ids,
+                   COUNT(*) AS count,
+                   SUM(a.amount_usd) AS total_usd,
+                   MAX(a.bee_score) AS bee_score
+            FROM alert_events a
+            WHERE NOT a.rolled_up
+              AND NOT COALESCE(a.metadata ? 'rollup', FALSE)
+              AND a.token_address IS NOT NULL
+              AND a.alert_type = ANY($1)
+              AND a.created_at >= NOW() - make_interval(secs => $2)
+            GROUP BY a.token_address, a.alert_type
+            HAVING COUNT(*) >= $3
+            "#,
+        )
+        .bind(alert_types)
+        .bind(window_secs)
+        .bind(min_count)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Hide `member_ids` behind the rollup alert `rollup_id`
+    pub async fn roll_up<'c, E>(
+        rollup_id: i32,
+        member_ids: &[i32],
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            UPDATE alert_events SET rollup_id = $1, rolled_up = TRUE
+            WHERE id = ANY($2) AND id <> $1
+            "#,
+        )
+        .bind(rollup_id)
+        .bind(member_ids)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Get alerts for a token
     pub async fn find_by_token<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         limit: i32,
         connection: E,
     ) -> Result<Vec<AlertEvent>, sqlx::Error>
@@ -231,6 +518,29 @@ impl AlertEvent {
         .await
     }
 
+    /// Delete up to `limit` alerts older than `cutoff`, with their webhook deliveries
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM alert_events
+            WHERE id IN (SELECT id FROM alert_events WHERE created_at < $1 LIMIT $2)
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Mark alert as processed
     pub async fn mark_processed<'c, E>(id: i32, connection: E) -> Result<(), sqlx::Error>
     where
@@ -264,3 +574,296 @@ impl AlertEvent {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        alert_metadata::TrendingMetadata,
+        test_support::{address, clear_seed_data},
+    };
+
+    #[sqlx::test]
+    async fn helpers_fill_in_alert_fields(pool: PgPool) {
+        let launch = AlertEvent::create_new_token_alert(&address(1), "PEPE", &pool)
+            .await
+            .unwrap();
+        assert_eq!(launch.alert_type, "new_token");
+        assert_eq!(launch.title, "New Token: PEPE");
+        assert_eq!(launch.processed, Some(false));
+
+        let whale = AlertEvent::create_whale_alert(
+            &address(1),
+            "PEPE",
+            &address(2),
+            false,
+            &BigDecimal::from(9_000),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(whale.alert_type, AlertType::WhaleSell.as_str());
+        assert_eq!(whale.wallet_address, Some(address(2)));
+        assert_eq!(whale.amount_usd, Some(BigDecimal::from(9_000)));
+
+        let by_token = AlertEvent::find_by_token(&address(1), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(by_token.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn metadata_round_trips(pool: PgPool) {
+        let metadata = AlertMetadata::Trending(TrendingMetadata { rank: 4 });
+        let alert = NewAlert {
+            alert_type: AlertType::TrendingEnter.as_str().to_string(),
+            token_address: None,
+            token_symbol: None,
+            wallet_address: None,
+            title: "Matched".to_string(),
+            message: None,
+            bee_score: Some(80),
+            amount_usd: None,
+            change_percent: None,
+            metadata: Some(metadata.clone()),
+            source: None,
+        };
+
+        let created = AlertEvent::create(&alert, &pool).await.unwrap();
+        assert_eq!(
+            created.metadata.as_ref().map(|m| &m.0),
+            Some(&json!({ "rank": 4 }))
+        );
+        assert_eq!(created.typed_metadata().unwrap().unwrap(), metadata);
+    }
+
+    #[sqlx::test]
+    async fn processing_queue(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        let mut ids = Vec::new();
+        for n in 1..=3 {
+            ids.push(
+                AlertEvent::create_new_token_alert(&address(n), "TKN", &pool)
+                    .await
+                    .unwrap()
+                    .id,
+            );
+        }
+
+        AlertEvent::mark_processed(ids[0], &pool).await.unwrap();
+        let pending = AlertEvent::find_unprocessed(10, &pool).await.unwrap();
+        assert_eq!(
+            pending.iter().map(|a| a.id).collect::<Vec<_>>(),
+            ids[1..].to_vec()
+        );
+
+        AlertEvent::mark_many_processed(&ids[1..], &pool)
+            .await
+            .unwrap();
+        assert!(AlertEvent::find_unprocessed(10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let processed = AlertEvent::find_recent(None, 10, &pool).await.unwrap();
+        assert!(processed.iter().all(|a| a.processed_at.is_some()));
+        assert_eq!(
+            AlertEvent::find_by_type("new_token", 2, &pool)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    async fn whale_buy(n: u8, usd: i64, pool: &PgPool) -> AlertEvent {
+        AlertEvent::create_whale_alert(
+            &address(n),
+            "BRST",
+            &address(50),
+            true,
+            &BigDecimal::from(usd),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn roll_up(burst: &AlertBurst, pool: &PgPool) -> AlertEvent {
+        let rollup = AlertEvent::create(&burst.rollup_alert(600), pool)
+            .await
+            .unwrap();
+        AlertEvent::roll_up(rollup.id, &burst.member_ids, pool)
+            .await
+            .unwrap();
+        rollup
+    }
+
+    #[sqlx::test]
+    async fn bursts_collapse_into_one_rollup(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let types = [AlertType::WhaleBuy.as_str()];
+        let stale = whale_buy(1, 1_000, &pool).await;
+        sqlx::query("UPDATE alert_events SET created_at = NOW() - INTERVAL '1 hour' WHERE id = $1")
+            .bind(stale.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            ids.push(whale_buy(1, 5_000, &pool).await.id);
+        }
+        whale_buy(2, 5_000, &pool).await;
+        AlertEvent::create_whale_alert(
+            &address(1),
+            "BRST",
+            &address(50),
+            false,
+            &BigDecimal::from(5_000),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert!(AlertEvent::find_bursts(&types, 600, 3, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        ids.push(whale_buy(1, 2_500, &pool).await.id);
+        let bursts = AlertEvent::find_bursts(&types, 600, 3, &pool)
+            .await
+            .unwrap();
+        assert_eq!(bursts.len(), 1);
+        let burst = &bursts[0];
+        assert_eq!(burst.token_address, address(1));
+        assert_eq!(burst.count, 3);
+        assert_eq!(burst.member_ids, ids);
+        assert_eq!(burst.total_usd, Some(BigDecimal::from(12_500)));
+
+        let first = roll_up(burst, &pool).await;
+        assert_eq!(first.alert_type, "whale_buy");
+        assert_eq!(first.title, "3 whale buy alerts on $BRST in 10 min");
+        assert_eq!(first.amount_usd, Some(BigDecimal::from(12_500)));
+        let feed = AlertEvent::find_feed(Some("whale_buy"), None, None, 50, &pool)
+            .await
+            .unwrap();
+        let mut shown: Vec<_> = feed.iter().map(|a| a.id).collect();
+        shown.sort();
+        assert_eq!(shown.len(), 3, "stale alert, token 2 and the rollup");
+        assert!(shown.contains(&first.id) && !shown.contains(&ids[0]));
+        assert!(AlertEvent::find_bursts(&types, 600, 3, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The burst goes on: a second rollup needs three more alerts
+        for usd in [500, 700] {
+            ids.push(whale_buy(1, usd, &pool).await.id);
+        }
+        assert!(AlertEvent::find_bursts(&types, 600, 3, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+        ids.push(whale_buy(1, 800, &pool).await.id);
+        let bursts = AlertEvent::find_bursts(&types, 600, 3, &pool)
+            .await
+            .unwrap();
+        assert_eq!(bursts[0].count, 3);
+        assert_eq!(bursts[0].member_ids, ids[3..].to_vec());
+        let second = roll_up(&bursts[0], &pool).await;
+        assert_eq!(
+            second.metadata.unwrap().0["rollup"],
+            json!({ "count": 3, "alertIds": ids[3..], "windowSecs": 600 })
+        );
+        assert_eq!(second.amount_usd, Some(BigDecimal::from(2_000)));
+
+        // Deleting a rollup doesn't bring back the alerts it covered
+        sqlx::query("DELETE FROM alert_events WHERE id = $1")
+            .bind(first.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let feed = AlertEvent::find_feed(Some("whale_buy"), None, None, 50, &pool)
+            .await
+            .unwrap();
+        assert!(feed.iter().all(|a| !ids.contains(&a.id)));
+        let hidden: Vec<(i32, Option<i32>)> = sqlx::query_as(
+            "SELECT id, rollup_id FROM alert_events WHERE rolled_up ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(hidden.len(), 6);
+        assert!(hidden[..3].iter().all(|(_, rollup)| rollup.is_none()));
+        assert!(hidden[3..].iter().all(|(_, rollup)| *rollup == Some(second.id)));
+    }
+
+    #[sqlx::test]
+    async fn feed_pages_forward_after_a_known_id(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let mut ids = Vec::new();
+        for n in 1..=5 {
+            ids.push(whale_buy(n, 5_000, &pool).await.id);
+        }
+        sqlx::query("UPDATE alert_events SET created_at = created_at - INTERVAL '1 minute'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let latest = AlertEvent::find_feed(None, None, None, 2, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            latest.iter().map(|a| a.id).collect::<Vec<_>>(),
+            vec![ids[4], ids[3]]
+        );
+
+        // A poller that last saw ids[0] gets the next two, newest first, and
+        // then the rest
+        let page = AlertEvent::find_feed(None, None, Some(ids[0]), 2, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            page.iter().map(|a| a.id).collect::<Vec<_>>(),
+            vec![ids[2], ids[1]]
+        );
+        let page = AlertEvent::find_feed(None, None, Some(ids[2]), 2, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            page.iter().map(|a| a.id).collect::<Vec<_>>(),
+            vec![ids[4], ids[3]]
+        );
+        assert!(AlertEvent::find_feed(None, None, Some(ids[4]), 2, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let recent = AlertEvent::find_recent(Some(ids[3]), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].id, ids[4]);
+
+        // One raised just now may still have slower commits below it, so it
+        // is held back from forward pages but shows on the latest one
+        let fresh = whale_buy(6, 5_000, &pool).await.id;
+        assert!(AlertEvent::find_feed(None, None, Some(ids[4]), 2, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(AlertEvent::find_recent(Some(ids[4]), 2, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+        let latest = AlertEvent::find_feed(None, None, None, 1, &pool)
+            .await
+            .unwrap();
+        assert_eq!(latest[0].id, fresh);
+    }
+}
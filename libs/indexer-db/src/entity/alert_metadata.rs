@@ -0,0 +1,331 @@
+//! Typed alert metadata
+//!
+//! `alert_events.metadata` holds one payload shape per alert type, or a
+//! [`Rollup`] on the rollup alerts that stand for a burst of them. Writers
+//! build an [`AlertMetadata`]; readers check stored JSON against the alert's
+//! type with [`AlertMetadata::parse`]. Fields only ever get added, as options,
+//! so payloads stored before them still parse.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::types::chrono;
+
+use super::alert::AlertType;
+use crate::types::{Address20, Hash32};
+
+/// Why stored metadata doesn't match its alert
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    #[error("unknown alert type `{0}`")]
+    UnknownType(String),
+    #[error("invalid {alert_type} metadata: {source}")]
+    Invalid {
+        alert_type: &'static str,
+        source: serde_json::Error,
+    },
+}
+
+/// The alerts a rollup alert stands for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rollup {
+    pub count: i64,
+    pub alert_ids: Vec<i32>,
+    pub window_secs: i64,
+}
+
+/// `rollup` wrapper, so rollups are told apart from their type's own payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RollupMetadata {
+    pub rollup: Rollup,
+}
+
+/// `new_token`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewTokenMetadata {
+    pub pair_address: Address20,
+    /// WBNB or the stablecoin the token is paired with
+    pub base_token: Address20,
+    pub block_number: i64,
+}
+
+/// Known exchange wallet or router a whale trade went to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownCounterparty {
+    pub kind: String,
+    pub name: String,
+}
+
+/// `whale_buy`, `whale_sell`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhaleMetadata {
+    /// Missing on alerts raised before it was recorded
+    #[serde(default)]
+    pub tx_hash: Option<Hash32>,
+    /// How far the trade moved the token's price, in percent (negative on
+    /// sells); `None` while the pair's reserves are unknown
+    #[serde(default)]
+    pub price_impact_percent: Option<f64>,
+    /// Where the trade's gas price ranks in its block (0-100)
+    pub gas_price_percentile: Option<i16>,
+    pub mev_flags: Vec<String>,
+    pub known_counterparty: Option<KnownCounterparty>,
+}
+
+/// `price_pump`, `price_dump`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceMoveMetadata {
+    /// Swap that moved the price
+    pub tx_hash: Hash32,
+    pub previous_price_usd: f64,
+    pub price_usd: f64,
+}
+
+/// `lp_locked`, `lp_unlocking`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LpLockMetadata {
+    /// Locker name, e.g. `PinkLock`, or `unknown`
+    pub locker: String,
+    pub lock_contract: Address20,
+    pub lp_token: Address20,
+    pub unlock_date: chrono::DateTime<chrono::Utc>,
+    /// Share of the LP supply in the lock, when known
+    pub locked_percent: Option<String>,
+}
+
+/// `high_bee_score`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeeScoreMetadata {
+    /// `None` for a token scored for the first time
+    pub previous_score: Option<i16>,
+    pub safety_score: i16,
+    pub traction_score: i16,
+}
+
+/// `dev_sell`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevSellMetadata {
+    pub tx_hash: Hash32,
+    pub to: Address20,
+    /// Raw token amount sent
+    pub amount: String,
+}
+
+/// `trending_enter`, `trending_exit`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrendingMetadata {
+    /// Rank reached on entering; last tracked rank on leaving
+    pub rank: i32,
+}
+
+/// `cex_inflow`, `cex_outflow`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CexFlowMetadata {
+    pub exchange: String,
+    pub exchange_address: Address20,
+    /// `inflow` or `outflow`
+    pub direction: String,
+    pub supply_percent: String,
+}
+
+/// `impersonation`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationMetadata {
+    pub target_address: Address20,
+    /// `top` or `trending`
+    pub target_source: String,
+    /// `name` or `symbol`
+    pub matched_field: String,
+    /// 0-1, 1 being identical
+    pub similarity: f64,
+}
+
+/// `lag_slo_breach`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LagSloMetadata {
+    pub event_type: String,
+    pub target_secs: i32,
+    pub p50_lag_ms: i64,
+    pub p95_lag_ms: i64,
+    pub max_lag_ms: i64,
+    pub samples: i32,
+    pub breached_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// `risky_approval`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiskyApprovalMetadata {
+    pub spender: Address20,
+    pub spender_label: String,
+    /// Raw allowance
+    pub amount: String,
+    pub unlimited: bool,
+}
+
+/// `holder_blacklisted`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HolderBlacklistedMetadata {
+    /// Signature of the function called
+    pub function: String,
+    pub caller: Address20,
+    pub tx_hash: Hash32,
+    pub holders: Vec<Address20>,
+}
+
+/// `event_error_rate`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventErrorRateMetadata {
+    pub event_type: String,
+    pub events: i64,
+    pub decode_failures: i64,
+    pub handler_errors: i64,
+    pub max_percent: f64,
+    pub failing_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// An alert's metadata, serialized as the payload alone
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum AlertMetadata {
+    Rollup(RollupMetadata),
+    NewToken(NewTokenMetadata),
+    Whale(WhaleMetadata),
+    PriceMove(PriceMoveMetadata),
+    LpLock(LpLockMetadata),
+    BeeScore(BeeScoreMetadata),
+    DevSell(DevSellMetadata),
+    Trending(TrendingMetadata),
+    CexFlow(CexFlowMetadata),
+    Impersonation(ImpersonationMetadata),
+    LagSlo(LagSloMetadata),
+    RiskyApproval(RiskyApprovalMetadata),
+    HolderBlacklisted(HolderBlacklistedMetadata),
+    EventErrorRate(EventErrorRateMetadata),
+}
+
+impl AlertMetadata {
+    /// Check stored metadata against the type of its alert
+    pub fn parse(alert_type: &str, value: &JsonValue) -> Result<AlertMetadata, MetadataError> {
+        let kind = AlertType::parse(alert_type)
+            .ok_or_else(|| MetadataError::UnknownType(alert_type.to_string()))?;
+        let invalid = |source| MetadataError::Invalid {
+            alert_type: kind.as_str(),
+            source,
+        };
+        let value = value.clone();
+
+        if value.get("rollup").is_some() {
+            return serde_json::from_value(value)
+                .map(AlertMetadata::Rollup)
+                .map_err(invalid);
+        }
+        let parsed = match kind {
+            AlertType::NewToken => serde_json::from_value(value).map(AlertMetadata::NewToken),
+            AlertType::WhaleBuy | AlertType::WhaleSell => {
+                serde_json::from_value(value).map(AlertMetadata::Whale)
+            }
+            AlertType::PricePump | AlertType::PriceDump => {
+                serde_json::from_value(value).map(AlertMetadata::PriceMove)
+            }
+            AlertType::LpLocked | AlertType::LpUnlocking => {
+                serde_json::from_value(value).map(AlertMetadata::LpLock)
+            }
+            AlertType::HighBeeScore => serde_json::from_value(value).map(AlertMetadata::BeeScore),
+            AlertType::DevSell => serde_json::from_value(value).map(AlertMetadata::DevSell),
+            AlertType::TrendingEnter | AlertType::TrendingExit => {
+                serde_json::from_value(value).map(AlertMetadata::Trending)
+            }
+            AlertType::CexInflow | AlertType::CexOutflow => {
+                serde_json::from_value(value).map(AlertMetadata::CexFlow)
+            }
+            AlertType::Impersonation => {
+                serde_json::from_value(value).map(AlertMetadata::Impersonation)
+            }
+            AlertType::LagSloBreach => serde_json::from_value(value).map(AlertMetadata::LagSlo),
+            AlertType::RiskyApproval => {
+                serde_json::from_value(value).map(AlertMetadata::RiskyApproval)
+            }
+            AlertType::HolderBlacklisted => {
+                serde_json::from_value(value).map(AlertMetadata::HolderBlacklisted)
+            }
+            AlertType::EventErrorRate => {
+                serde_json::from_value(value).map(AlertMetadata::EventErrorRate)
+            }
+        };
+        parsed.map_err(invalid)
+    }
+
+    /// The rollup, on a rollup alert
+    pub fn rollup(&self) -> Option<&Rollup> {
+        match self {
+            AlertMetadata::Rollup(r) => Some(&r.rollup),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn payloads_are_checked_against_their_alert_type() {
+        let trending = json!({ "rank": 3 });
+        assert_eq!(
+            AlertMetadata::parse("trending_enter", &trending).unwrap(),
+            AlertMetadata::Trending(TrendingMetadata { rank: 3 })
+        );
+        assert!(matches!(
+            AlertMetadata::parse("dev_sell", &trending),
+            Err(MetadataError::Invalid {
+                alert_type: "dev_sell",
+                ..
+            })
+        ));
+        assert!(matches!(
+            AlertMetadata::parse("filter_match", &trending),
+            Err(MetadataError::UnknownType(_))
+        ));
+    }
+
+    #[test]
+    fn rollups_parse_on_any_type() {
+        let rollup = json!({
+            "rollup": { "count": 3, "alertIds": [1, 2, 3], "windowSecs": 600 }
+        });
+        let parsed = AlertMetadata::parse("whale_buy", &rollup).unwrap();
+        assert_eq!(parsed.rollup().map(|r| r.count), Some(3));
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), rollup);
+    }
+
+    #[test]
+    fn whale_payloads_from_before_tx_hashes_still_parse() {
+        let stored = json!({
+            "gasPricePercentile": 97,
+            "mevFlags": ["top_of_block"],
+            "knownCounterparty": null,
+        });
+        let AlertMetadata::Whale(whale) = AlertMetadata::parse("whale_sell", &stored).unwrap()
+        else {
+            panic!("not a whale payload");
+        };
+        assert_eq!(whale.tx_hash, None);
+        assert_eq!(whale.price_impact_percent, None);
+        assert_eq!(whale.mev_flags, vec!["top_of_block"]);
+    }
+}
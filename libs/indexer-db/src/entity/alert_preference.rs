@@ -0,0 +1,214 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// How much of a token's alerting a client wants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertLevel {
+    /// No alerts for the token
+    Mute,
+    /// Only [`super::alert::AlertType::CRITICAL`] alerts
+    Critical,
+    /// Every alert (the default, stored as no row)
+    All,
+}
+
+impl AlertLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertLevel::Mute => "mute",
+            AlertLevel::Critical => "critical",
+            AlertLevel::All => "all",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mute" => Some(AlertLevel::Mute),
+            "critical" => Some(AlertLevel::Critical),
+            "all" => Some(AlertLevel::All),
+            _ => None,
+        }
+    }
+}
+
+/// AlertPreference entity: one client's level for one token
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct AlertPreference {
+    pub token_address: Address20,
+    /// `mute` or `critical`
+    pub level: String,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AlertPreference {
+    /// Set the level for a token under `api_key`; `All` removes the preference
+    pub async fn set<'c, E>(
+        api_key: &str,
+        token_address: &Address20,
+        level: AlertLevel,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        if level == AlertLevel::All {
+            sqlx::query(
+                r#"
+                DELETE FROM alert_preferences
+                WHERE key_hash = sha256(convert_to($1, 'UTF8')) AND token_address = $2
+                "#,
+            )
+            .bind(api_key)
+            .bind(token_address)
+            .execute(connection)
+            .await?;
+        } else {
+            sqlx::query(
+                r#"
+                INSERT INTO alert_preferences (key_hash, token_address, level)
+                VALUES (sha256(convert_to($1, 'UTF8')), $2, $3)
+                ON CONFLICT (key_hash, token_address) DO UPDATE SET
+                    level = EXCLUDED.level,
+                    updated_at = NOW()
+                "#,
+            )
+            .bind(api_key)
+            .bind(token_address)
+            .bind(level.as_str())
+            .execute(connection)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Every preference stored under `api_key`, most recently changed first
+    pub async fn find_by_key<'c, E>(
+        api_key: &str,
+        connection: E,
+    ) -> Result<Vec<AlertPreference>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, AlertPreference>(
+            r#"
+            SELECT token_address, level, updated_at
+            FROM alert_preferences
+            WHERE key_hash = sha256(convert_to($1, 'UTF8'))
+            ORDER BY updated_at DESC
+            "#,
+        )
+        .bind(api_key)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::BigDecimal, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        alert::AlertEvent,
+        alert_webhook::{AlertWebhook, NewAlertWebhook},
+        test_support::{address, clear_seed_data},
+    };
+
+    const KEY: &str = "client-key-0123456789";
+
+    /// A new-token alert and a (critical) whale sell for token `n`
+    async fn alerts(n: u8, pool: &PgPool) {
+        AlertEvent::create_new_token_alert(&address(n), "TKN", pool)
+            .await
+            .unwrap();
+        AlertEvent::create_whale_alert(
+            &address(n),
+            "TKN",
+            &address(50),
+            false,
+            &BigDecimal::from(9_000),
+            pool,
+        )
+        .await
+        .unwrap();
+    }
+
+    fn kinds(alerts: &[AlertEvent]) -> Vec<(Address20, String)> {
+        let mut kinds: Vec<_> = alerts
+            .iter()
+            .map(|a| (a.token_address.unwrap(), a.alert_type.clone()))
+            .collect();
+        kinds.sort_by_key(|(token, kind)| (token.to_hex(), kind.clone()));
+        kinds
+    }
+
+    #[sqlx::test]
+    async fn preferences_filter_the_feed_and_webhooks(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let hook = NewAlertWebhook {
+            url: "https://example.com/hook".to_string(),
+            secret: "0123456789abcdef".to_string(),
+            alert_types: Vec::new(),
+            api_key: Some(KEY.to_string()),
+        };
+        AlertWebhook::create(&hook, &pool).await.unwrap();
+
+        AlertPreference::set(KEY, &address(1), AlertLevel::Mute, &pool)
+            .await
+            .unwrap();
+        AlertPreference::set(KEY, &address(2), AlertLevel::Mute, &pool)
+            .await
+            .unwrap();
+        AlertPreference::set(KEY, &address(2), AlertLevel::Critical, &pool)
+            .await
+            .unwrap();
+        AlertPreference::set(KEY, &address(3), AlertLevel::Mute, &pool)
+            .await
+            .unwrap();
+        AlertPreference::set(KEY, &address(3), AlertLevel::All, &pool)
+            .await
+            .unwrap();
+
+        let stored = AlertPreference::find_by_key(KEY, &pool).await.unwrap();
+        let mut stored: Vec<_> = stored
+            .iter()
+            .map(|p| (p.token_address, p.level.as_str()))
+            .collect();
+        stored.sort_by_key(|(token, _)| token.to_hex());
+        assert_eq!(stored, vec![(address(1), "mute"), (address(2), "critical")]);
+        assert!(AlertPreference::find_by_key("another-client-key", &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        for n in 1..=3 {
+            alerts(n, &pool).await;
+        }
+        let expected = vec![
+            (address(2), "whale_sell".to_string()),
+            (address(3), "new_token".to_string()),
+            (address(3), "whale_sell".to_string()),
+        ];
+
+        let feed = AlertEvent::find_feed(None, Some(KEY), None, 50, &pool)
+            .await
+            .unwrap();
+        assert_eq!(kinds(&feed), expected);
+        // Other clients and anonymous readers see everything
+        let anonymous = AlertEvent::find_feed(None, None, None, 50, &pool)
+            .await
+            .unwrap();
+        assert_eq!(anonymous.len(), 6);
+        let whales = AlertEvent::find_feed(Some("whale_sell"), Some(KEY), None, 50, &pool)
+            .await
+            .unwrap();
+        assert_eq!(whales.len(), 2);
+
+        assert_eq!(AlertWebhook::enqueue_deliveries(&[], 0, &pool).await.unwrap(), 3);
+        let due = AlertWebhook::find_due_deliveries(10, &pool).await.unwrap();
+        let delivered: Vec<_> = due.into_iter().map(|d| d.alert).collect();
+        assert_eq!(kinds(&delivered), expected);
+    }
+}
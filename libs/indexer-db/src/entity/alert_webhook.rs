@@ -0,0 +1,392 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use super::alert::{AlertEvent, AlertType};
+
+/// AlertWebhook entity: a generic HTTP sink for alert events
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct AlertWebhook {
+    pub id: i32,
+    pub url: String,
+    pub secret: String,
+    /// Alert types to deliver; empty means all
+    pub alert_types: Vec<String>,
+    pub active: bool,
+    pub last_alert_id: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for registering a webhook
+#[derive(Debug, Clone)]
+pub struct NewAlertWebhook {
+    pub url: String,
+    pub secret: String,
+    pub alert_types: Vec<String>,
+    /// Key that registered the webhook; its alert preferences apply
+    pub api_key: Option<String>,
+}
+
+/// Delivery states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Gave up after too many attempts
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+/// A delivery that is due, with what's needed to send it
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DueDelivery {
+    pub delivery_id: i32,
+    pub attempts: i32,
+    pub webhook_url: String,
+    pub webhook_secret: String,
+    #[sqlx(flatten)]
+    pub alert: AlertEvent,
+}
+
+impl AlertWebhook {
+    /// Register a webhook; it receives alerts created from now on
+    pub async fn create<'c, E>(
+        webhook: &NewAlertWebhook,
+        connection: E,
+    ) -> Result<AlertWebhook, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO alert_webhooks (url, secret, alert_types, key_hash, last_alert_id)
+            VALUES (
+                $1, $2, $3, sha256(convert_to($4, 'UTF8')),
+                (SELECT COALESCE(MAX(id), 0) FROM alert_events)
+            )
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, AlertWebhook>(query)
+            .bind(&webhook.url)
+            .bind(&webhook.secret)
+            .bind(&webhook.alert_types)
+            .bind(&webhook.api_key)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Get all webhooks
+    pub async fn find_all<'c, E>(connection: E) -> Result<Vec<AlertWebhook>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, AlertWebhook>("SELECT * FROM alert_webhooks ORDER BY id")
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Delete a webhook and its deliveries, returning whether it existed
+    pub async fn delete<'c, E>(id: i32, connection: E) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query("DELETE FROM alert_webhooks WHERE id = $1")
+            .bind(id)
+            .execute(connection)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Queue a delivery for every new alert matching an active webhook's
+    /// filters and its key's alert preferences, then move the webhooks'
+    /// cursors past them. Alerts rolled up are skipped, and alerts of
+    /// `held_types` (other than rollups) wait until they are `hold_secs`
+    /// old, so a burst is rolled up before any of it is sent; the cursors
+    /// stop before the first one waiting.
+    pub async fn enqueue_deliveries<'c, E>(
+        held_types: &[&str],
+        hold_secs: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            WITH held AS (
+                SELECT MIN(id) AS id FROM alert_events
+                WHERE alert_type = ANY($2)
+                  AND NOT COALESCE(metadata ? 'rollup', FALSE)
+                  AND created_at > NOW() - make_interval(secs => $3)
+            ),
+            latest AS (
+                SELECT COALESCE((SELECT id - 1 FROM held), MAX(id), 0) AS id FROM alert_events
+            ),
+            queued AS (
+                INSERT INTO alert_webhook_deliveries (webhook_id, alert_id)
+                SELECT w.id, a.id
+                FROM alert_webhooks w
+                JOIN alert_events a ON a.id > w.last_alert_id AND a.id <= (SELECT id FROM latest)
+                WHERE w.active
+                  AND NOT a.rolled_up
+                  AND (cardinality(w.alert_types) = 0 OR a.alert_type = ANY(w.alert_types))
+                  AND NOT EXISTS (
+                      SELECT 1 FROM alert_preferences p
+                      WHERE p.key_hash = w.key_hash
+                        AND p.token_address = a.token_address
+                        AND (p.level = 'mute' OR NOT a.alert_type = ANY($1))
+                  )
+                ON CONFLICT (webhook_id, alert_id) DO NOTHING
+                RETURNING 1
+            ),
+            advanced AS (
+                UPDATE alert_webhooks
+                SET last_alert_id = (SELECT id FROM latest)
+                WHERE active AND last_alert_id < (SELECT id FROM latest)
+            )
+            SELECT COUNT(*) FROM queued
+        "#;
+
+        let queued: i64 = sqlx::query_scalar(query)
+            .bind(AlertType::critical_names())
+            .bind(held_types)
+            .bind(hold_secs)
+            .fetch_one(connection)
+            .await?;
+        Ok(queued as u64)
+    }
+
+    /// Get pending deliveries whose next attempt is due, oldest first
+    pub async fn find_due_deliveries<'c, E>(
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<DueDelivery>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            SELECT d.id AS delivery_id, d.attempts,
+                   w.url AS webhook_url, w.secret AS webhook_secret,
+                   a.*
+            FROM alert_webhook_deliveries d
+            JOIN alert_webhooks w ON w.id = d.webhook_id
+            JOIN alert_events a ON a.id = d.alert_id
+            WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND w.active
+            ORDER BY d.next_attempt_at, d.id
+            LIMIT $1
+        "#;
+
+        sqlx::query_as::<_, DueDelivery>(query)
+            .bind(limit)
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Record a successful delivery
+    pub async fn mark_delivered<'c, E>(
+        delivery_id: i32,
+        status_code: i32,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE alert_webhook_deliveries
+            SET status = 'delivered', attempts = attempts + 1,
+                last_status_code = $2, last_error = NULL, delivered_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(status_code)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed attempt; `retry_at` of `None` gives up on the delivery
+    pub async fn mark_attempt_failed<'c, E>(
+        delivery_id: i32,
+        status_code: Option<i32>,
+        error: &str,
+        retry_at: Option<chrono::DateTime<chrono::Utc>>,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let status = if retry_at.is_some() {
+            DeliveryStatus::Pending
+        } else {
+            DeliveryStatus::Failed
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE alert_webhook_deliveries
+            SET status = $2, attempts = attempts + 1, last_status_code = $3,
+                last_error = $4, next_attempt_at = COALESCE($5, next_attempt_at)
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(status.as_str())
+        .bind(status_code)
+        .bind(error)
+        .bind(retry_at)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        alert::{AlertType, NewAlert},
+        test_support::clear_seed_data,
+    };
+
+    async fn alert(alert_type: AlertType, pool: &PgPool) -> AlertEvent {
+        let alert = NewAlert {
+            alert_type: alert_type.as_str().to_string(),
+            token_address: None,
+            token_symbol: None,
+            wallet_address: None,
+            title: "Alert".to_string(),
+            message: None,
+            bee_score: None,
+            amount_usd: None,
+            change_percent: None,
+            metadata: None,
+            source: None,
+        };
+        AlertEvent::create(&alert, pool).await.unwrap()
+    }
+
+    fn webhook(alert_types: &[AlertType]) -> NewAlertWebhook {
+        NewAlertWebhook {
+            url: "https://example.com/hook".to_string(),
+            secret: "0123456789abcdef".to_string(),
+            alert_types: alert_types.iter().map(|t| t.as_str().to_string()).collect(),
+            api_key: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn deliveries_follow_filters_and_retries(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        // Alerts from before registration are not delivered
+        alert(AlertType::NewToken, &pool).await;
+        let all = AlertWebhook::create(&webhook(&[]), &pool).await.unwrap();
+        let whales = AlertWebhook::create(&webhook(&[AlertType::WhaleBuy]), &pool)
+            .await
+            .unwrap();
+        assert_eq!(all.alert_types, Vec::<String>::new());
+
+        alert(AlertType::NewToken, &pool).await;
+        let whale = alert(AlertType::WhaleBuy, &pool).await;
+
+        assert_eq!(AlertWebhook::enqueue_deliveries(&[], 0, &pool).await.unwrap(), 3);
+        assert_eq!(AlertWebhook::enqueue_deliveries(&[], 0, &pool).await.unwrap(), 0);
+
+        let due = AlertWebhook::find_due_deliveries(10, &pool).await.unwrap();
+        assert_eq!(due.len(), 3);
+        let whale_delivery = due
+            .iter()
+            .find(|d| d.alert.id == whale.id && d.webhook_url == whales.url)
+            .unwrap();
+        assert_eq!(whale_delivery.alert.alert_type, "whale_buy");
+
+        // Deliver one, back off one, give up on the last
+        AlertWebhook::mark_delivered(due[0].delivery_id, 200, &pool)
+            .await
+            .unwrap();
+        let later = ::chrono::Utc::now() + ::chrono::Duration::minutes(5);
+        AlertWebhook::mark_attempt_failed(
+            due[1].delivery_id,
+            Some(500),
+            "boom",
+            Some(later),
+            &pool,
+        )
+        .await
+        .unwrap();
+        AlertWebhook::mark_attempt_failed(due[2].delivery_id, None, "refused", None, &pool)
+            .await
+            .unwrap();
+        assert!(AlertWebhook::find_due_deliveries(10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert!(AlertWebhook::delete(whales.id, &pool).await.unwrap());
+        assert!(!AlertWebhook::delete(whales.id, &pool).await.unwrap());
+        assert_eq!(AlertWebhook::find_all(&pool).await.unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn alerts_that_may_be_rolled_up_wait_out_the_window(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        AlertWebhook::create(&webhook(&[]), &pool).await.unwrap();
+        let held = ["whale_buy"];
+
+        let whale = alert(AlertType::WhaleBuy, &pool).await;
+        let new_token = alert(AlertType::NewToken, &pool).await;
+        assert_eq!(
+            AlertWebhook::enqueue_deliveries(&held, 600, &pool)
+                .await
+                .unwrap(),
+            0,
+            "the cursor stops before the whale alert"
+        );
+
+        // Its window is over; the alert after it isn't queued twice
+        sqlx::query("UPDATE alert_events SET created_at = NOW() - INTERVAL '11 minutes' WHERE id = $1")
+            .bind(whale.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            AlertWebhook::enqueue_deliveries(&held, 600, &pool)
+                .await
+                .unwrap(),
+            2
+        );
+        let second = alert(AlertType::WhaleBuy, &pool).await;
+        sqlx::query("UPDATE alert_events SET rolled_up = TRUE WHERE id = $1")
+            .bind(second.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            AlertWebhook::enqueue_deliveries(&[], 0, &pool).await.unwrap(),
+            0,
+            "rolled up"
+        );
+
+        let mut delivered: Vec<i32> = AlertWebhook::find_due_deliveries(10, &pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|d| d.alert.id)
+            .collect();
+        delivered.sort();
+        assert_eq!(delivered, vec![whale.id, new_token.id]);
+    }
+}
@@ -0,0 +1,168 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// Anomaly entity: a reserve update that breaks the pair's invariant
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Anomaly {
+    pub id: i32,
+    pub kind: String,
+    pub pair_address: Address20,
+    pub token_address: Option<Address20>,
+    pub block_number: i64,
+    pub prev_reserve0: BigDecimal,
+    pub prev_reserve1: BigDecimal,
+    pub reserve0: BigDecimal,
+    pub reserve1: BigDecimal,
+    pub deviation_percent: Option<BigDecimal>,
+    pub detected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Anomaly kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// More left the pool than x*y=k (with fee) allows for the amount that came in
+    KInvariantViolation,
+    /// Reserves moved in the same direction but the price ratio changed
+    /// (tokens sent straight to the pair, then synced)
+    UnbalancedReserveChange,
+}
+
+impl AnomalyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::KInvariantViolation => "k_invariant_violation",
+            AnomalyKind::UnbalancedReserveChange => "unbalanced_reserve_change",
+        }
+    }
+}
+
+/// Input for recording an anomaly
+#[derive(Debug, Clone)]
+pub struct NewAnomaly {
+    pub kind: AnomalyKind,
+    pub pair_address: Address20,
+    pub token_address: Option<Address20>,
+    pub block_number: i64,
+    pub prev_reserve0: BigDecimal,
+    pub prev_reserve1: BigDecimal,
+    pub reserve0: BigDecimal,
+    pub reserve1: BigDecimal,
+    pub deviation_percent: Option<BigDecimal>,
+}
+
+impl Anomaly {
+    /// Record an anomaly
+    pub async fn create<'c, E>(anomaly: &NewAnomaly, connection: E) -> Result<Anomaly, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO anomalies (
+                kind, pair_address, token_address, block_number,
+                prev_reserve0, prev_reserve1, reserve0, reserve1, deviation_percent
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, Anomaly>(query)
+            .bind(anomaly.kind.as_str())
+            .bind(anomaly.pair_address)
+            .bind(anomaly.token_address)
+            .bind(anomaly.block_number)
+            .bind(&anomaly.prev_reserve0)
+            .bind(&anomaly.prev_reserve1)
+            .bind(&anomaly.reserve0)
+            .bind(&anomaly.reserve1)
+            .bind(&anomaly.deviation_percent)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Whether any anomaly was recorded for a pair in a block
+    pub async fn exists_in_block<'c, E>(
+        pair_address: &Address20,
+        block_number: i64,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM anomalies WHERE pair_address = $1 AND block_number = $2)",
+        )
+        .bind(pair_address)
+        .bind(block_number)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Get recent anomalies for a token
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<Anomaly>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Anomaly>(
+            "SELECT * FROM anomalies WHERE token_address = $1 ORDER BY detected_at DESC, id DESC LIMIT $2",
+        )
+        .bind(token_address)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::test_support::address;
+
+    fn anomaly(block_number: i64) -> NewAnomaly {
+        NewAnomaly {
+            kind: AnomalyKind::UnbalancedReserveChange,
+            pair_address: address(10),
+            token_address: Some(address(1)),
+            block_number,
+            prev_reserve0: BigDecimal::from(100),
+            prev_reserve1: BigDecimal::from(100),
+            reserve0: BigDecimal::from(150),
+            reserve1: BigDecimal::from(100),
+            deviation_percent: Some(BigDecimal::from(50)),
+        }
+    }
+
+    #[sqlx::test]
+    async fn anomalies_are_found_by_block_and_token(pool: PgPool) {
+        let created = Anomaly::create(&anomaly(7), &pool).await.unwrap();
+        assert_eq!(created.kind, "unbalanced_reserve_change");
+        Anomaly::create(&anomaly(9), &pool).await.unwrap();
+
+        assert!(Anomaly::exists_in_block(&address(10), 7, &pool)
+            .await
+            .unwrap());
+        assert!(!Anomaly::exists_in_block(&address(10), 8, &pool)
+            .await
+            .unwrap());
+        assert!(!Anomaly::exists_in_block(&address(11), 7, &pool)
+            .await
+            .unwrap());
+
+        let recent = Anomaly::find_by_token(&address(1), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            recent.iter().map(|a| a.block_number).collect::<Vec<_>>(),
+            vec![9, 7]
+        );
+    }
+}
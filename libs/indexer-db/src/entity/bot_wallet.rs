@@ -0,0 +1,174 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::{Address20, Hash32};
+
+/// BotWallet entity: a wallet caught buying like a sniper bot
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct BotWallet {
+    pub wallet_address: Address20,
+    /// Every signal the wallet was caught with, sorted
+    pub signals: Vec<String>,
+    /// The buy it was first caught on
+    pub token_address: Address20,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+    pub first_detected_at: chrono::DateTime<chrono::Utc>,
+    pub last_detected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A buy that showed bot signals
+#[derive(Debug, Clone)]
+pub struct BotDetection {
+    pub wallet_address: Address20,
+    pub signals: Vec<String>,
+    pub token_address: Address20,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+}
+
+impl BotWallet {
+    /// Add the wallet to the bot set, or merge the signals into its entry
+    pub async fn record<'c, E>(
+        detection: &BotDetection,
+        connection: E,
+    ) -> Result<BotWallet, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO bot_wallets (wallet_address, signals, token_address, tx_hash, block_number)
+            VALUES ($1, ARRAY(SELECT DISTINCT s FROM UNNEST($2::TEXT[]) s ORDER BY s), $3, $4, $5)
+            ON CONFLICT (wallet_address) DO UPDATE SET
+                signals = ARRAY(
+                    SELECT DISTINCT s FROM UNNEST(bot_wallets.signals || EXCLUDED.signals) s
+                    ORDER BY s
+                ),
+                last_detected_at = NOW()
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, BotWallet>(query)
+            .bind(detection.wallet_address)
+            .bind(&detection.signals)
+            .bind(detection.token_address)
+            .bind(detection.tx_hash)
+            .bind(detection.block_number)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Get a wallet's bot entry
+    pub async fn find_by_address<'c, E>(
+        wallet_address: &Address20,
+        connection: E,
+    ) -> Result<Option<BotWallet>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, BotWallet>("SELECT * FROM bot_wallets WHERE wallet_address = $1")
+            .bind(wallet_address)
+            .fetch_optional(connection)
+            .await
+    }
+
+    /// Whether any of `addresses` is in the bot set
+    pub async fn contains_any<'c, E>(
+        addresses: &[Address20],
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM bot_wallets WHERE wallet_address = ANY($1))",
+        )
+        .bind(addresses)
+        .fetch_one(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::BigDecimal, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, hash},
+        token::{NewToken, Token},
+        token_holder::{NewTokenHolder, TokenHolder},
+    };
+
+    #[sqlx::test]
+    async fn bots_are_left_out_of_organic_holders(pool: PgPool) {
+        let token = NewToken {
+            address: address(1),
+            name: Some("Launch".to_string()),
+            symbol: Some("LNCH".to_string()),
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: Some(BigDecimal::from(1_000)),
+            pair_address: None,
+            creator_address: None,
+            block_number: Some(100),
+        };
+        Token::create(&token, &pool).await.unwrap();
+        for (wallet, balance) in [(10, 150), (11, 50), (12, 300)] {
+            let holder = NewTokenHolder {
+                token_address: address(1),
+                wallet_address: address(wallet),
+                balance: BigDecimal::from(balance),
+                is_dev: false,
+                is_sniper: false,
+                is_contract: false,
+                first_buy_block: Some(100),
+                source: None,
+            };
+            TokenHolder::upsert(&holder, &pool).await.unwrap();
+        }
+
+        let detection = BotDetection {
+            wallet_address: address(10),
+            signals: vec!["exact_gas_limit".to_string(), "creation_block".to_string()],
+            token_address: address(1),
+            tx_hash: hash(1),
+            block_number: 100,
+        };
+        BotWallet::record(&detection, &pool).await.unwrap();
+        // Caught again elsewhere: the signals merge, the first buy stays
+        let again = BotDetection {
+            signals: vec!["contract_caller".to_string(), "creation_block".to_string()],
+            token_address: address(2),
+            tx_hash: hash(2),
+            block_number: 200,
+            ..detection.clone()
+        };
+        let bot = BotWallet::record(&again, &pool).await.unwrap();
+        assert_eq!(
+            bot.signals,
+            vec!["contract_caller", "creation_block", "exact_gas_limit"]
+        );
+        assert_eq!((bot.token_address, bot.block_number), (address(1), 100));
+
+        assert!(BotWallet::contains_any(&[address(11), address(10)], &pool)
+            .await
+            .unwrap());
+        assert!(!BotWallet::contains_any(&[address(11)], &pool).await.unwrap());
+
+        // The tokens whose bot share a new bot changes
+        assert_eq!(
+            TokenHolder::find_tokens_held(&address(10), &pool).await.unwrap(),
+            vec![address(1)]
+        );
+
+        Token::refresh_holder_metrics(&address(1), &pool).await.unwrap();
+        let token = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.organic_holder_count, Some(2));
+        assert_eq!(token.bot_holder_percent, Some(BigDecimal::from(15)));
+    }
+}
@@ -0,0 +1,167 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::{Address20, Hash32};
+
+/// CexFlow entity: a large token transfer into or out of an exchange wallet
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct CexFlow {
+    pub id: i32,
+    pub token_address: Address20,
+    pub exchange: String,
+    pub exchange_address: Address20,
+    /// Wallet on the other side of the transfer
+    pub wallet_address: Address20,
+    pub direction: String, // "inflow", "outflow"
+    /// Raw token units
+    pub amount: BigDecimal,
+    /// Share (0-100) of the total supply moved, if the supply is known
+    pub supply_percent: Option<BigDecimal>,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for recording a CEX flow
+#[derive(Debug, Clone)]
+pub struct NewCexFlow {
+    pub token_address: Address20,
+    pub exchange: String,
+    pub exchange_address: Address20,
+    pub wallet_address: Address20,
+    pub direction: CexFlowDirection,
+    pub amount: BigDecimal,
+    pub supply_percent: Option<BigDecimal>,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Whether tokens went to or came from the exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CexFlowDirection {
+    /// Deposit into an exchange wallet
+    Inflow,
+    /// Withdrawal from an exchange wallet
+    Outflow,
+}
+
+impl CexFlowDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CexFlowDirection::Inflow => "inflow",
+            CexFlowDirection::Outflow => "outflow",
+        }
+    }
+}
+
+impl CexFlow {
+    /// Record a flow; `None` if this transfer was already recorded
+    pub async fn create<'c, E>(
+        flow: &NewCexFlow,
+        connection: E,
+    ) -> Result<Option<CexFlow>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, CexFlow>(
+            r#"
+            INSERT INTO cex_flows (
+                token_address, exchange, exchange_address, wallet_address, direction,
+                amount, supply_percent, tx_hash, block_number, timestamp
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (tx_hash, token_address, exchange_address, direction) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(flow.token_address)
+        .bind(&flow.exchange)
+        .bind(flow.exchange_address)
+        .bind(flow.wallet_address)
+        .bind(flow.direction.as_str())
+        .bind(&flow.amount)
+        .bind(&flow.supply_percent)
+        .bind(flow.tx_hash)
+        .bind(flow.block_number)
+        .bind(flow.timestamp)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Get the latest flows of a token
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<CexFlow>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, CexFlow>(
+            r#"
+            SELECT * FROM cex_flows
+            WHERE token_address = $1
+            ORDER BY timestamp DESC, id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(token_address)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::test_support::{address, hash};
+
+    fn flow(n: u8, direction: CexFlowDirection) -> NewCexFlow {
+        NewCexFlow {
+            token_address: address(10),
+            exchange: "Binance".to_string(),
+            exchange_address: address(1),
+            wallet_address: address(2),
+            direction,
+            amount: BigDecimal::from(2_000),
+            supply_percent: Some("2.5".parse().unwrap()),
+            tx_hash: hash(n),
+            block_number: n as i64,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[sqlx::test]
+    async fn flows_are_recorded_once_per_transfer(pool: PgPool) {
+        let created = CexFlow::create(&flow(1, CexFlowDirection::Inflow), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(created.direction, "inflow");
+        assert_eq!(created.supply_percent, Some("2.5".parse().unwrap()));
+
+        assert!(CexFlow::create(&flow(1, CexFlowDirection::Inflow), &pool)
+            .await
+            .unwrap()
+            .is_none());
+        CexFlow::create(&flow(2, CexFlowDirection::Outflow), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let flows = CexFlow::find_by_token(&address(10), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(flows.len(), 2);
+        assert!(CexFlow::find_by_token(&address(11), 10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use sqlx::{Executor, Postgres};
+
+use crate::types::Address20;
+
+/// ChainConstant entity: a well-known contract on a chain
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ChainConstant {
+    pub id: i32,
+    pub chain_id: i64,
+    pub kind: String,
+    pub name: String,
+    pub address: Address20,
+}
+
+/// Chain constant kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainConstantKind {
+    /// Wrapped native token (WBNB, WETH)
+    WrappedNative,
+    Stablecoin,
+    /// DEX factory emitting PairCreated
+    Factory,
+    Router,
+    /// LP locker contract
+    Locker,
+    /// Wrapped native/stablecoin pool feeding the native USD price index
+    PricePool,
+}
+
+impl ChainConstantKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChainConstantKind::WrappedNative => "wrapped_native",
+            ChainConstantKind::Stablecoin => "stablecoin",
+            ChainConstantKind::Factory => "factory",
+            ChainConstantKind::Router => "router",
+            ChainConstantKind::Locker => "locker",
+            ChainConstantKind::PricePool => "price_pool",
+        }
+    }
+}
+
+impl ChainConstant {
+    /// Get every constant registered for a chain
+    pub async fn find_by_chain<'c, E>(
+        chain_id: i64,
+        connection: E,
+    ) -> Result<Vec<ChainConstant>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ChainConstant>(
+            "SELECT * FROM chain_constants WHERE chain_id = $1 ORDER BY kind, id",
+        )
+        .bind(chain_id)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Find a constant of `kind` by address, on any chain
+    pub async fn find_by_address<'c, E>(
+        kind: ChainConstantKind,
+        address: &Address20,
+        connection: E,
+    ) -> Result<Option<ChainConstant>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ChainConstant>(
+            "SELECT * FROM chain_constants WHERE kind = $1 AND address = $2 ORDER BY id LIMIT 1",
+        )
+        .bind(kind.as_str())
+        .bind(address)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Whether this constant is of the given kind
+    pub fn is(&self, kind: ChainConstantKind) -> bool {
+        self.kind == kind.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn bsc_constants_are_seeded(pool: PgPool) {
+        let constants = ChainConstant::find_by_chain(56, &pool).await.unwrap();
+
+        let wrapped: Vec<_> = constants
+            .iter()
+            .filter(|c| c.is(ChainConstantKind::WrappedNative))
+            .collect();
+        assert_eq!(wrapped.len(), 1);
+        assert_eq!(
+            wrapped[0].address.to_hex(),
+            "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"
+        );
+        assert_eq!(
+            constants
+                .iter()
+                .filter(|c| c.is(ChainConstantKind::Locker))
+                .count(),
+            3
+        );
+        assert_eq!(
+            constants
+                .iter()
+                .filter(|c| c.is(ChainConstantKind::PricePool))
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["WBNB/BUSD", "WBNB/USDT"]
+        );
+
+        assert!(ChainConstant::find_by_chain(1, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
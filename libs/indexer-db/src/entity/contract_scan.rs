@@ -0,0 +1,161 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// ContractScan entity: restriction functions found in a token's bytecode
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ContractScan {
+    pub token_address: Address20,
+    /// Bytes of deployed code; 0 when the address has none
+    pub code_size: i32,
+    /// Signatures of the restriction functions the code can dispatch to
+    pub restrictions: Vec<String>,
+    pub scanned_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ContractScan {
+    /// Record (or replace) the scan of a token's code
+    pub async fn upsert<'c, E>(
+        token_address: &Address20,
+        code_size: i32,
+        restrictions: &[String],
+        connection: E,
+    ) -> Result<ContractScan, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ContractScan>(
+            r#"
+            INSERT INTO token_contract_scans (token_address, code_size, restrictions)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (token_address) DO UPDATE SET
+                code_size = EXCLUDED.code_size,
+                restrictions = EXCLUDED.restrictions,
+                scanned_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(token_address)
+        .bind(code_size)
+        .bind(restrictions)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Scan of a token, if it was scanned
+    pub async fn find<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<Option<ContractScan>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ContractScan>(
+            "SELECT * FROM token_contract_scans WHERE token_address = $1",
+        )
+        .bind(token_address)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Indexed tokens not scanned yet, newest first
+    pub async fn find_unscanned<'c, E>(
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar::<_, Address20>(
+            r#"
+            SELECT t.address FROM tokens t
+            WHERE NOT EXISTS (
+                SELECT 1 FROM token_contract_scans s WHERE s.token_address = t.address
+            )
+            ORDER BY t.created_at DESC NULLS LAST
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Scans that found at least one restriction function
+    pub async fn find_restricted<'c, E>(connection: E) -> Result<Vec<ContractScan>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ContractScan>(
+            "SELECT * FROM token_contract_scans WHERE cardinality(restrictions) > 0",
+        )
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+    };
+
+    #[sqlx::test]
+    async fn tokens_are_scanned_once(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        for n in [1, 2] {
+            let token = NewToken {
+                address: address(n),
+                name: None,
+                symbol: None,
+                name_raw: None,
+                symbol_raw: None,
+                name_spoofed: false,
+                decimals: None,
+                total_supply: None,
+                pair_address: None,
+                creator_address: None,
+                block_number: None,
+            };
+            Token::create(&token, &pool).await.unwrap();
+        }
+
+        let mut unscanned = ContractScan::find_unscanned(10, &pool).await.unwrap();
+        unscanned.sort();
+        assert_eq!(unscanned, vec![address(1), address(2)]);
+
+        ContractScan::upsert(
+            &address(1),
+            4_000,
+            &["blacklist(address)".to_string()],
+            &pool,
+        )
+        .await
+        .unwrap();
+        ContractScan::upsert(&address(2), 3_000, &[], &pool)
+            .await
+            .unwrap();
+        assert!(ContractScan::find_unscanned(10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let restricted = ContractScan::find_restricted(&pool).await.unwrap();
+        assert_eq!(restricted.len(), 1);
+        assert_eq!(restricted[0].token_address, address(1));
+        assert_eq!(restricted[0].restrictions, vec!["blacklist(address)"]);
+
+        let rescanned = ContractScan::upsert(&address(1), 4_000, &[], &pool)
+            .await
+            .unwrap();
+        assert!(rescanned.restrictions.is_empty());
+        assert!(ContractScan::find(&address(2), &pool)
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,331 @@
+use sqlx::{types::chrono, Executor, Postgres, QueryBuilder};
+
+use crate::{entity::evm_logs::EvmLogs, types::Address20};
+
+/// DeferredLog entity: a log parked until the pair it references is indexed,
+/// or until a rescan of its token is done. Retries are counted per pair, and a
+/// pair's logs are retried together in chain order.
+#[derive(sqlx::FromRow, Debug)]
+pub struct DeferredLog {
+    /// The log as it was queued
+    #[sqlx(flatten)]
+    pub log: EvmLogs,
+    pub pair_address: Address20,
+    /// Retries so far
+    pub attempts: i32,
+    /// Processing cycles left until the next retry
+    pub cycles_left: i32,
+    pub deferred_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DeferredLog {
+    /// Park logs until their emitter (`log.address`, the pair or held token
+    /// they are parked for) is ready, in one statement. A log that is
+    /// already parked (e.g. redelivered by the queue) keeps its retry state.
+    pub async fn defer<'c, E>(logs: &[&EvmLogs], cycles: i32, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        if logs.is_empty() {
+            return Ok(());
+        }
+
+        let mut query = QueryBuilder::<Postgres>::new(
+            r#"
+            INSERT INTO deferred_logs (id, block_number, block_hash, address, transaction_hash,
+                transaction_index, log_index, removed, data, event_signature, topics,
+                block_timestamp, created_at, pair_address, cycles_left)
+            "#,
+        );
+        query.push_values(logs, |mut row, log| {
+            row.push_bind(log.id)
+                .push_bind(&log.block_number)
+                .push_bind(log.block_hash.to_vec())
+                .push_bind(log.address.to_vec())
+                .push_bind(log.transaction_hash.to_vec())
+                .push_bind(log.transaction_index)
+                .push_bind(log.log_index)
+                .push_bind(log.removed)
+                .push_bind(&log.data)
+                .push_bind(log.event_signature.to_vec())
+                .push_bind(log.topics.iter().map(|t| t.to_vec()).collect::<Vec<_>>())
+                .push_bind(log.block_timestamp)
+                .push_bind(log.created_at)
+                .push_bind(Address20::from(log.address))
+                .push_bind(cycles);
+        });
+        query.push(" ON CONFLICT (transaction_hash, log_index) DO NOTHING");
+        query.build().execute(connection).await?;
+
+        Ok(())
+    }
+
+    /// Count down one processing cycle for every parked log
+    pub async fn tick<'c, E>(connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("UPDATE deferred_logs SET cycles_left = cycles_left - 1 WHERE cycles_left > 0")
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Parked logs due for a retry, in chain order. A pair's logs are due
+    /// together, once its earliest parked log is.
+    pub async fn find_due<'c, E>(limit: i64, connection: E) -> Result<Vec<DeferredLog>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, DeferredLog>(
+            r#"
+            SELECT d.* FROM deferred_logs d
+            JOIN (
+                SELECT DISTINCT ON (pair_address) pair_address, cycles_left
+                FROM deferred_logs
+                ORDER BY pair_address, block_number, log_index
+            ) head ON head.pair_address = d.pair_address
+            WHERE head.cycles_left <= 0
+            ORDER BY d.block_number, d.log_index
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Number of parked logs
+    pub async fn count<'c, E>(connection: E) -> Result<i64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT COUNT(*) FROM deferred_logs")
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Block of the earliest parked log, `None` with nothing parked
+    pub async fn lowest_block<'c, E>(connection: E) -> Result<Option<i64>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT MIN(block_number)::BIGINT FROM deferred_logs")
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Number of logs parked for each pair
+    pub async fn count_by_pair<'c, E>(connection: E) -> Result<Vec<(Address20, i64)>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as(
+            "SELECT pair_address, COUNT(*) FROM deferred_logs GROUP BY pair_address",
+        )
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Count a retry that still found the pair missing and wait another
+    /// `cycles` with all of its logs. Returns the retries so far.
+    pub async fn postpone<'c, E>(
+        pair_address: &Address20,
+        cycles: i32,
+        connection: E,
+    ) -> Result<i32, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            r#"
+            WITH postponed AS (
+                UPDATE deferred_logs
+                SET attempts = attempts + 1, cycles_left = $2
+                WHERE pair_address = $1
+                RETURNING attempts
+            )
+            SELECT COALESCE(MAX(attempts), 0) FROM postponed
+            "#,
+        )
+        .bind(pair_address)
+        .bind(cycles)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Park a pair's logs again for `cycles` without counting a retry, for
+    /// logs held back rather than missing their pair
+    pub async fn hold<'c, E>(
+        pair_address: &Address20,
+        cycles: i32,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("UPDATE deferred_logs SET cycles_left = $2 WHERE pair_address = $1")
+            .bind(pair_address)
+            .bind(cycles)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Drop a parked log once it was handled
+    pub async fn delete<'c, E>(log: &EvmLogs, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("DELETE FROM deferred_logs WHERE transaction_hash = $1 AND log_index = $2")
+            .bind(log.transaction_hash.to_vec())
+            .bind(log.log_index)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Give up on a pair, dropping all of its parked logs. Returns them, in
+    /// chain order.
+    pub async fn delete_pair<'c, E>(
+        pair_address: &Address20,
+        connection: E,
+    ) -> Result<Vec<DeferredLog>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let mut dropped = sqlx::query_as::<_, DeferredLog>(
+            "DELETE FROM deferred_logs WHERE pair_address = $1 RETURNING *",
+        )
+        .bind(pair_address)
+        .fetch_all(connection)
+        .await?;
+        dropped.sort_by(|a, b| {
+            (&a.log.block_number, a.log.log_index).cmp(&(&b.log.block_number, b.log.log_index))
+        });
+
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{b256, Address, Bytes, B256},
+        rpc::types::Log,
+    };
+    use sqlx::PgPool;
+
+    use super::*;
+
+    fn log(pair: u8, block: u64, log_index: u64) -> EvmLogs {
+        let log = Log {
+            inner: alloy::primitives::Log::new(
+                Address::repeat_byte(pair),
+                vec![b256!(
+                    "d78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"
+                )],
+                Bytes::from(vec![0xab; 32]),
+            )
+            .unwrap(),
+            block_hash: Some(B256::repeat_byte(block as u8)),
+            block_number: Some(block),
+            block_timestamp: Some(1_700_000_000),
+            transaction_hash: Some(B256::with_last_byte(block as u8)),
+            transaction_index: Some(0),
+            log_index: Some(log_index),
+            removed: false,
+        };
+        EvmLogs::from_rpc_log(&log).unwrap()
+    }
+
+    fn due(logs: &[DeferredLog]) -> Vec<(String, i64)> {
+        logs.iter()
+            .map(|d| (d.log.block_number.to_string(), d.log.log_index))
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn parked_logs_come_back_in_chain_order_after_their_cycles(pool: PgPool) {
+        let pair = Address20::new([1; 20]);
+        DeferredLog::defer(&[&log(1, 43, 0)], 1, &pool)
+            .await
+            .unwrap();
+        DeferredLog::defer(&[&log(2, 42, 5), &log(2, 42, 1)], 2, &pool)
+            .await
+            .unwrap();
+        // Redelivered; keeps its place
+        DeferredLog::defer(&[&log(1, 43, 0)], 9, &pool)
+            .await
+            .unwrap();
+
+        assert!(DeferredLog::find_due(10, &pool).await.unwrap().is_empty());
+        DeferredLog::tick(&pool).await.unwrap();
+        let first = DeferredLog::find_due(10, &pool).await.unwrap();
+        assert_eq!(due(&first), vec![("43".to_string(), 0)]);
+        assert_eq!(first[0].pair_address, pair);
+        assert_eq!(first[0].log.block_hash, [43; 32]);
+        assert_eq!(
+            first[0].log.block_timestamp.map(|ts| ts.timestamp()),
+            Some(1_700_000_000)
+        );
+
+        DeferredLog::tick(&pool).await.unwrap();
+        let all = DeferredLog::find_due(10, &pool).await.unwrap();
+        assert_eq!(
+            due(&all),
+            vec![
+                ("42".to_string(), 1),
+                ("42".to_string(), 5),
+                ("43".to_string(), 0)
+            ]
+        );
+
+        DeferredLog::delete(&log(2, 42, 5), &pool).await.unwrap();
+        let mut counts = DeferredLog::count_by_pair(&pool).await.unwrap();
+        counts.sort_by_key(|(pair, _)| pair.to_hex());
+        assert_eq!(counts, vec![(pair, 1), (Address20::new([2; 20]), 1)]);
+        assert_eq!(DeferredLog::count(&pool).await.unwrap(), 2);
+        assert_eq!(DeferredLog::lowest_block(&pool).await.unwrap(), Some(42));
+    }
+
+    #[sqlx::test]
+    async fn a_pair_is_retried_and_given_up_as_a_whole(pool: PgPool) {
+        let pair = Address20::new([1; 20]);
+        DeferredLog::defer(&[&log(1, 42, 0)], 0, &pool)
+            .await
+            .unwrap();
+        // Parked behind it later, with cycles of its own
+        DeferredLog::defer(&[&log(1, 44, 0)], 5, &pool)
+            .await
+            .unwrap();
+
+        // Due with the pair's first log
+        let all = DeferredLog::find_due(10, &pool).await.unwrap();
+        assert_eq!(
+            due(&all),
+            vec![("42".to_string(), 0), ("44".to_string(), 0)]
+        );
+
+        assert_eq!(DeferredLog::postpone(&pair, 2, &pool).await.unwrap(), 1);
+        assert!(DeferredLog::find_due(10, &pool).await.unwrap().is_empty());
+        DeferredLog::hold(&pair, 0, &pool).await.unwrap();
+        assert_eq!(DeferredLog::find_due(10, &pool).await.unwrap().len(), 2);
+        assert_eq!(
+            DeferredLog::postpone(&pair, 0, &pool).await.unwrap(),
+            2,
+            "holding doesn't count a retry"
+        );
+
+        let dropped = DeferredLog::delete_pair(&pair, &pool).await.unwrap();
+        assert_eq!(
+            due(&dropped),
+            vec![("42".to_string(), 0), ("44".to_string(), 0)]
+        );
+        assert!(DeferredLog::count_by_pair(&pool).await.unwrap().is_empty());
+        assert_eq!(DeferredLog::lowest_block(&pool).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,111 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// DrainerAddress entity: a known drainer or sweeper contract that approvals
+/// shouldn't go to
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DrainerAddress {
+    pub id: i32,
+    pub chain_id: i64,
+    pub address: Address20,
+    pub label: String,
+    pub added_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DrainerAddress {
+    /// List `address` as a drainer, or relabel it if it already is
+    pub async fn upsert<'c, E>(
+        chain_id: i64,
+        address: &Address20,
+        label: &str,
+        connection: E,
+    ) -> Result<DrainerAddress, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, DrainerAddress>(
+            r#"
+            INSERT INTO drainer_addresses (chain_id, address, label)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (chain_id, address) DO UPDATE SET label = EXCLUDED.label
+            RETURNING *
+            "#,
+        )
+        .bind(chain_id)
+        .bind(address)
+        .bind(label)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Take `address` off the list, returning whether it was listed
+    pub async fn delete<'c, E>(
+        chain_id: i64,
+        address: &Address20,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result =
+            sqlx::query("DELETE FROM drainer_addresses WHERE chain_id = $1 AND address = $2")
+                .bind(chain_id)
+                .bind(address)
+                .execute(connection)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Every drainer listed for a chain, most recently added first
+    pub async fn find_by_chain<'c, E>(
+        chain_id: i64,
+        connection: E,
+    ) -> Result<Vec<DrainerAddress>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, DrainerAddress>(
+            "SELECT * FROM drainer_addresses WHERE chain_id = $1 ORDER BY added_at DESC, id DESC",
+        )
+        .bind(chain_id)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::test_support::address;
+
+    #[sqlx::test]
+    async fn drainers_are_listed_per_chain(pool: PgPool) {
+        DrainerAddress::upsert(56, &address(1), "Inferno Drainer", &pool)
+            .await
+            .unwrap();
+        DrainerAddress::upsert(56, &address(2), "Angel Drainer", &pool)
+            .await
+            .unwrap();
+        DrainerAddress::upsert(1, &address(3), "Pink Drainer", &pool)
+            .await
+            .unwrap();
+        let relabeled = DrainerAddress::upsert(56, &address(1), "Inferno Drainer v2", &pool)
+            .await
+            .unwrap();
+        assert_eq!(relabeled.label, "Inferno Drainer v2");
+
+        let listed = DrainerAddress::find_by_chain(56, &pool).await.unwrap();
+        let mut labels: Vec<_> = listed.iter().map(|d| d.label.as_str()).collect();
+        labels.sort();
+        assert_eq!(labels, vec!["Angel Drainer", "Inferno Drainer v2"]);
+
+        assert!(DrainerAddress::delete(56, &address(2), &pool).await.unwrap());
+        assert!(!DrainerAddress::delete(56, &address(2), &pool).await.unwrap());
+        assert_eq!(DrainerAddress::find_by_chain(56, &pool).await.unwrap().len(), 1);
+        assert_eq!(DrainerAddress::find_by_chain(1, &pool).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,125 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+/// EgressOutbox entity: an event waiting to be published to the durable egress
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct EgressOutbox {
+    pub id: i64,
+    /// Redis channel of the event, mapped to its egress subject
+    pub channel: String,
+    /// Message id the egress dedupes on
+    pub event_id: String,
+    pub payload: String,
+    /// Failed publish attempts so far
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl EgressOutbox {
+    /// Keep an event for a later publish; one kept already is left as is
+    pub async fn enqueue<'c, E>(
+        channel: &str,
+        event_id: &str,
+        payload: &str,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO egress_outbox (channel, event_id, payload)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (event_id) DO NOTHING
+            "#,
+        )
+        .bind(channel)
+        .bind(event_id)
+        .bind(payload)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The oldest kept events, in the order they were kept
+    pub async fn find_oldest<'c, E>(
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<EgressOutbox>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, EgressOutbox>("SELECT * FROM egress_outbox ORDER BY id LIMIT $1")
+            .bind(limit)
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Drop an event once it is published
+    pub async fn delete<'c, E>(id: i64, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("DELETE FROM egress_outbox WHERE id = $1")
+            .bind(id)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Note a failed publish of an event
+    pub async fn record_failure<'c, E>(
+        id: i64,
+        error: &str,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            "UPDATE egress_outbox SET attempts = attempts + 1, last_error = $2 WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn events_are_kept_once_in_order(pool: PgPool) {
+        EgressOutbox::enqueue("chain:events:swap", "0xaa:1", "{}", &pool)
+            .await
+            .unwrap();
+        EgressOutbox::enqueue("chain:events:sync", "0xaa:2", "{}", &pool)
+            .await
+            .unwrap();
+        // A redelivered event isn't kept twice
+        EgressOutbox::enqueue("chain:events:swap", "0xaa:1", "{}", &pool)
+            .await
+            .unwrap();
+
+        let kept = EgressOutbox::find_oldest(10, &pool).await.unwrap();
+        let ids: Vec<_> = kept.iter().map(|e| e.event_id.as_str()).collect();
+        assert_eq!(ids, vec!["0xaa:1", "0xaa:2"]);
+
+        EgressOutbox::record_failure(kept[0].id, "timed out", &pool)
+            .await
+            .unwrap();
+        EgressOutbox::delete(kept[1].id, &pool).await.unwrap();
+        let kept = EgressOutbox::find_oldest(10, &pool).await.unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].attempts, 1);
+        assert_eq!(kept[0].last_error.as_deref(), Some("timed out"));
+    }
+}
@@ -47,3 +47,32 @@ impl EvmChains {
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn tracks_last_synced_block(pool: PgPool) {
+        sqlx::query("INSERT INTO evm_chains (id, name, block_time) VALUES (56, 'bsc', 3)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let chain = EvmChains::fetch_by_id(56, &pool).await.unwrap();
+        assert_eq!(chain.get_last_synced_block(), 0);
+
+        let chain = chain
+            .update_last_synced_block_number(1_234, &pool)
+            .await
+            .unwrap();
+        assert_eq!(chain.get_last_synced_block(), 1_234);
+
+        assert!(matches!(
+            EvmChains::fetch_by_id(1, &pool).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+    }
+}
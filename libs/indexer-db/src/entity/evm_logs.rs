@@ -8,6 +8,8 @@ use sqlx::{
 };
 use thiserror::Error;
 
+use crate::types::Hash32;
+
 #[derive(Error, Debug)]
 pub enum EvmLogsError {
     #[error("Failed to create a valid log data")]
@@ -31,6 +33,17 @@ pub struct EvmLogs {
     pub log_index: i64,
     pub removed: bool,
     pub created_at: chrono::NaiveDateTime,
+    /// Timestamp of the log's block, when the listener knew it
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The raw log a derived row was built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceLog {
+    /// `evm_logs.id`; `None` when the log didn't come through `evm_logs`
+    /// (the Redis and NATS queues, rescans)
+    pub log_id: Option<i32>,
+    pub tx_hash: Hash32,
 }
 
 impl TryInto<Log> for EvmLogs {
@@ -55,7 +68,9 @@ impl TryInto<Log> for EvmLogs {
             inner,
             block_number: Some(block_number),
             block_hash: None,
-            block_timestamp: None,
+            block_timestamp: self
+                .block_timestamp
+                .and_then(|ts| u64::try_from(ts.timestamp()).ok()),
             transaction_hash: Some(transaction_hash),
             transaction_index: None,
             log_index: None,
@@ -65,14 +80,14 @@ impl TryInto<Log> for EvmLogs {
 }
 
 impl EvmLogs {
-    pub async fn create<'c, E>(log: Log, connection: E) -> Result<EvmLogs, sqlx::Error>
-    where
-        E: Executor<'c, Database = Postgres>,
-    {
+    /// Row for an RPC log, as it would be stored (`id` 0, created now)
+    ///
+    /// Fails on pending logs, which have no block, transaction or index yet.
+    pub fn from_rpc_log(log: &Log) -> Result<EvmLogs, sqlx::Error> {
         let block_hash = log
             .block_hash
             .ok_or_else(|| sqlx::Error::Decode("Missing block hash".into()))?
-            .to_vec();
+            .0;
 
         let block_number: BigDecimal = <u64 as Into<BigDecimal>>::into(
             log.block_number
@@ -94,47 +109,83 @@ impl EvmLogs {
         let transaction_hash = log
             .transaction_hash
             .ok_or_else(|| sqlx::Error::Decode("Missing transaction hash".into()))?
-            .to_vec();
+            .0;
 
-        let address = log.address().to_vec();
+        let event_signature = log
+            .topics()
+            .first()
+            .ok_or_else(|| sqlx::Error::Decode("Missing event signature".into()))?
+            .0;
 
-        let event_signature: &[u8] = log.topics()[0].as_slice();
+        Ok(EvmLogs {
+            id: 0,
+            block_number,
+            block_hash,
+            address: log.address().into_array(),
+            transaction_hash,
+            data: log.inner.data.data.to_vec(),
+            event_signature,
+            topics: log.topics().iter().map(|topic| topic.0).collect(),
+            transaction_index,
+            log_index,
+            removed: log.removed,
+            created_at: chrono::Utc::now().naive_utc(),
+            block_timestamp: log
+                .block_timestamp
+                .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0)),
+        })
+    }
 
-        let topics: Vec<&[u8]> = log
-            .topics()
-            .iter()
-            .map(|topic| -> &[u8] { topic.as_slice() })
-            .collect();
+    /// Provenance to stamp on the rows this log produces
+    pub fn source(&self) -> SourceLog {
+        SourceLog {
+            // Logs built from an RPC response rather than read back have no id
+            log_id: (self.id > 0).then_some(self.id),
+            tx_hash: Hash32::new(self.transaction_hash),
+        }
+    }
 
-        let log_data: Vec<u8> = log.inner.data.data.to_vec();
+    /// Queue a log. Returns `None` when the log is already queued, e.g.
+    /// because the listener fetched an overlapping range after a restart.
+    pub async fn create<'c, E>(log: Log, connection: E) -> Result<Option<EvmLogs>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let row = Self::from_rpc_log(&log)?;
 
         // Insert log into the database and return the inserted row
         let query = r#"
-            INSERT INTO evm_logs (block_hash, block_number, address, transaction_hash, transaction_index, event_signature, topics, data, log_index, removed)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            INSERT INTO evm_logs (block_hash, block_number, address, transaction_hash, transaction_index, event_signature, topics, data, log_index, removed, block_timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT DO NOTHING
             RETURNING *
         "#;
 
         sqlx::query_as::<_, EvmLogs>(query)
-            .bind(block_hash)
-            .bind(block_number)
-            .bind(address)
-            .bind(transaction_hash)
-            .bind(transaction_index)
-            .bind(event_signature)
-            .bind(topics)
-            .bind(log_data)
-            .bind(log_index)
-            .bind(log.removed)
-            .fetch_one(connection)
+            .bind(row.block_hash.to_vec())
+            .bind(row.block_number)
+            .bind(row.address.to_vec())
+            .bind(row.transaction_hash.to_vec())
+            .bind(row.transaction_index)
+            .bind(row.event_signature.to_vec())
+            .bind(row.topics.iter().map(|t| t.to_vec()).collect::<Vec<_>>())
+            .bind(row.data)
+            .bind(row.log_index)
+            .bind(row.removed)
+            .bind(row.block_timestamp)
+            .fetch_optional(connection)
             .await
     }
 
+    /// The first `page_size` queued logs in chain order, so a pair's
+    /// creation is handled before its swaps and syncs
     pub async fn find_all<'c, E>(page_size: i32, connection: E) -> Result<Vec<EvmLogs>, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
-        sqlx::query_as::<_, EvmLogs>("SELECT * FROM evm_logs LIMIT $1")
+        sqlx::query_as::<_, EvmLogs>(
+            "SELECT * FROM evm_logs ORDER BY block_number, log_index LIMIT $1",
+        )
             .bind(page_size)
             .fetch_all(connection)
             .await
@@ -152,6 +203,18 @@ impl EvmLogs {
         Ok(())
     }
 
+    /// When the oldest log still in `evm_logs` was queued
+    pub async fn oldest_created_at<'c, E>(
+        connection: E,
+    ) -> Result<Option<chrono::NaiveDateTime>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT created_at FROM evm_logs ORDER BY id LIMIT 1")
+            .fetch_optional(connection)
+            .await
+    }
+
     pub async fn count<'c, E>(connection: E) -> Result<Option<i64>, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
@@ -167,3 +230,139 @@ impl EvmLogs {
         Ok(Some(count))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, b256, B256};
+    use sqlx::PgPool;
+
+    use super::*;
+
+    fn log(log_index: u64) -> Log {
+        let topics = vec![
+            b256!("d78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"),
+            B256::repeat_byte(1),
+        ];
+
+        Log {
+            inner: alloy::primitives::Log::new(
+                address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73"),
+                topics,
+                Bytes::from(vec![0xab; 64]),
+            )
+            .unwrap(),
+            block_hash: Some(B256::repeat_byte(2)),
+            block_number: Some(42),
+            block_timestamp: None,
+            transaction_hash: Some(B256::repeat_byte(3)),
+            transaction_index: Some(0),
+            log_index: Some(log_index),
+            removed: false,
+        }
+    }
+
+    #[sqlx::test]
+    async fn stored_logs_round_trip(pool: PgPool) {
+        let stored = EvmLogs::create(log(0), &pool).await.unwrap().unwrap();
+        assert_eq!(EvmLogs::count(&pool).await.unwrap(), Some(1));
+
+        let original = log(0);
+        let restored: Log = stored.try_into().unwrap();
+        assert_eq!(restored.inner, original.inner);
+        assert_eq!(restored.block_number, Some(42));
+        assert_eq!(restored.transaction_hash, original.transaction_hash);
+        assert_eq!(restored.block_timestamp, None);
+
+        let mut stamped = log(1);
+        stamped.block_timestamp = Some(1_700_000_000);
+        let stored = EvmLogs::create(stamped, &pool).await.unwrap().unwrap();
+        assert_eq!(
+            stored.block_timestamp.map(|ts| ts.timestamp()),
+            Some(1_700_000_000)
+        );
+        let restored: Log = stored.try_into().unwrap();
+        assert_eq!(restored.block_timestamp, Some(1_700_000_000));
+    }
+
+    #[sqlx::test]
+    async fn skips_duplicates_and_rejects_incomplete_logs(pool: PgPool) {
+        let stored = EvmLogs::create(log(0), &pool).await.unwrap().unwrap();
+        assert!(EvmLogs::create(log(0), &pool).await.unwrap().is_none());
+        let second = EvmLogs::create(log(1), &pool).await.unwrap().unwrap();
+
+        let mut pending = log(2);
+        pending.block_hash = None;
+        assert!(matches!(
+            EvmLogs::create(pending, &pool).await,
+            Err(sqlx::Error::Decode(_))
+        ));
+
+        assert_eq!(EvmLogs::find_all(10, &pool).await.unwrap().len(), 2);
+
+        EvmLogs::delete(stored.id, &pool).await.unwrap();
+        EvmLogs::delete(second.id, &pool).await.unwrap();
+        assert_eq!(EvmLogs::count(&pool).await.unwrap(), None);
+    }
+
+    #[sqlx::test]
+    async fn refetching_an_overlapping_range_queues_only_new_logs(pool: PgPool) {
+        let at = |block: u64, log_index: u64| {
+            let mut log = log(log_index);
+            log.block_number = Some(block);
+            log.block_hash = Some(B256::repeat_byte(block as u8));
+            log.transaction_hash = Some(B256::with_last_byte(block as u8));
+            log
+        };
+
+        // Blocks 40..=42 were queued, then the listener died before moving
+        // its cursor past them
+        for block in 40..=42 {
+            EvmLogs::create(at(block, 0), &pool).await.unwrap().unwrap();
+        }
+
+        // The restart fetches 41..=43 again
+        let mut queued = Vec::new();
+        for block in 41..=43 {
+            queued.extend(EvmLogs::create(at(block, 0), &pool).await.unwrap());
+        }
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].block_number, 43.into());
+        assert_eq!(EvmLogs::count(&pool).await.unwrap(), Some(4));
+
+        // Later logs in the last block still go in
+        assert!(EvmLogs::create(at(43, 1), &pool).await.unwrap().is_some());
+    }
+
+    #[sqlx::test]
+    async fn logs_are_pulled_in_chain_order(pool: PgPool) {
+        let at = |block: u64, log_index: u64| {
+            let mut log = log(log_index);
+            log.block_number = Some(block);
+            log.block_hash = Some(B256::repeat_byte(block as u8));
+            log
+        };
+
+        // Queued out of order, e.g. by overlapping fetches
+        for (block, log_index) in [(44, 0), (43, 7), (43, 2), (42, 9)] {
+            EvmLogs::create(at(block, log_index), &pool)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let pulled: Vec<_> = EvmLogs::find_all(3, &pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|l| (l.block_number.to_string(), l.log_index))
+            .collect();
+        assert_eq!(
+            pulled,
+            vec![
+                ("42".to_string(), 9),
+                ("43".to_string(), 2),
+                ("43".to_string(), 7)
+            ]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// A block range a listener filter fetched and saved in full
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct EvmSyncedRange {
+    pub id: i64,
+    pub chain_id: i64,
+    /// `evm_sync_logs.address` of the filter
+    pub sync_key: Address20,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub log_count: i32,
+    pub synced_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A hole between a filter's synced ranges
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct EvmSyncGap {
+    pub id: i64,
+    pub chain_id: i64,
+    pub sync_key: Address20,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub detected_at: chrono::DateTime<chrono::Utc>,
+    pub filled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl EvmSyncedRange {
+    /// Record that `from_block..=to_block` was saved for a filter
+    pub async fn create<'c, E>(
+        chain_id: u64,
+        sync_key: Address20,
+        from_block: u64,
+        to_block: u64,
+        log_count: usize,
+        connection: E,
+    ) -> Result<EvmSyncedRange, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, EvmSyncedRange>(
+            r#"
+            INSERT INTO evm_synced_ranges (chain_id, sync_key, from_block, to_block, log_count)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(sync_key)
+        .bind(from_block as i64)
+        .bind(to_block as i64)
+        .bind(log_count as i32)
+        .fetch_one(connection)
+        .await
+    }
+}
+
+impl EvmSyncGap {
+    /// Compare each filter's synced ranges on a chain and record new holes
+    ///
+    /// Returns only gaps not seen by an earlier audit. Blocks before a
+    /// filter's first range are not a gap: listeners start near the head.
+    pub async fn audit<'c, E>(chain_id: u64, connection: E) -> Result<Vec<EvmSyncGap>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, EvmSyncGap>(
+            r#"
+            INSERT INTO evm_sync_gaps (chain_id, sync_key, from_block, to_block)
+            SELECT chain_id, sync_key, covered_to + 1, from_block - 1
+            FROM (
+                SELECT
+                    chain_id,
+                    sync_key,
+                    from_block,
+                    MAX(to_block) OVER (
+                        PARTITION BY sync_key
+                        ORDER BY from_block
+                        ROWS BETWEEN UNBOUNDED PRECEDING AND 1 PRECEDING
+                    ) AS covered_to
+                FROM evm_synced_ranges
+                WHERE chain_id = $1
+            ) ranges
+            WHERE from_block > covered_to + 1
+            ON CONFLICT (chain_id, sync_key, from_block, to_block) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(chain_id as i64)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Oldest unfilled gap for a filter
+    pub async fn next_open<'c, E>(
+        chain_id: u64,
+        sync_key: Address20,
+        connection: E,
+    ) -> Result<Option<EvmSyncGap>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, EvmSyncGap>(
+            r#"
+            SELECT * FROM evm_sync_gaps
+            WHERE chain_id = $1 AND sync_key = $2 AND filled_at IS NULL
+            ORDER BY from_block
+            LIMIT 1
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(sync_key)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Unfilled gaps on a chain, oldest first
+    pub async fn find_open<'c, E>(
+        chain_id: u64,
+        connection: E,
+    ) -> Result<Vec<EvmSyncGap>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, EvmSyncGap>(
+            r#"
+            SELECT * FROM evm_sync_gaps
+            WHERE chain_id = $1 AND filled_at IS NULL
+            ORDER BY sync_key, from_block
+            "#,
+        )
+        .bind(chain_id as i64)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Mark blocks up to `block` as re-fetched: shrink the gap, or close it
+    /// once nothing is left
+    pub async fn fill_through<'c, E>(
+        &self,
+        block: u64,
+        connection: E,
+    ) -> Result<EvmSyncGap, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, EvmSyncGap>(
+            r#"
+            UPDATE evm_sync_gaps
+            SET from_block = CASE WHEN $2 >= to_block THEN from_block ELSE $2 + 1 END,
+                filled_at = CASE WHEN $2 >= to_block THEN NOW() ELSE NULL END
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(self.id)
+        .bind(block as i64)
+        .fetch_one(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::test_support::address;
+
+    #[sqlx::test]
+    async fn audit_records_holes_once_and_refetch_closes_them(pool: PgPool) {
+        sqlx::query("INSERT INTO evm_chains (id, name, block_time) VALUES (56, 'bsc', 3)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let key = address(1);
+        for (from, to) in [(100, 110), (111, 121), (140, 150), (151, 160), (200, 210)] {
+            EvmSyncedRange::create(56, key, from, to, 0, &pool)
+                .await
+                .unwrap();
+        }
+        // Another filter with contiguous ranges
+        for (from, to) in [(100, 110), (111, 121)] {
+            EvmSyncedRange::create(56, address(2), from, to, 0, &pool)
+                .await
+                .unwrap();
+        }
+
+        let gaps = EvmSyncGap::audit(56, &pool).await.unwrap();
+        let mut ranges: Vec<_> = gaps.iter().map(|g| (g.from_block, g.to_block)).collect();
+        ranges.sort();
+        assert_eq!(ranges, vec![(122, 139), (161, 199)]);
+        assert!(EvmSyncGap::audit(56, &pool).await.unwrap().is_empty());
+
+        let gap = EvmSyncGap::next_open(56, key, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((gap.from_block, gap.to_block), (122, 139));
+
+        // Re-fetch the gap in two chunks
+        EvmSyncedRange::create(56, key, 122, 132, 3, &pool)
+            .await
+            .unwrap();
+        let gap = gap.fill_through(132, &pool).await.unwrap();
+        assert_eq!((gap.from_block, gap.filled_at.is_some()), (133, false));
+        // The remainder matches what a fresh audit would find
+        assert!(EvmSyncGap::audit(56, &pool).await.unwrap().is_empty());
+
+        EvmSyncedRange::create(56, key, 133, 139, 0, &pool)
+            .await
+            .unwrap();
+        let gap = gap.fill_through(139, &pool).await.unwrap();
+        assert!(gap.filled_at.is_some());
+
+        let open = EvmSyncGap::find_open(56, &pool).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].from_block, 161);
+        assert!(EvmSyncGap::audit(56, &pool).await.unwrap().is_empty());
+    }
+}
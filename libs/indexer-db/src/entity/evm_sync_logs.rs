@@ -4,6 +4,8 @@ use sqlx::{types::chrono, Executor, Postgres};
 pub struct EvmSyncLogs {
     pub address: [u8; 20],
     pub last_synced_block_number: i64,
+    /// Chain head at the listener's last poll
+    pub latest_block_number: Option<i64>,
 
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
@@ -91,4 +93,113 @@ impl EvmSyncLogs {
             .fetch_one(connection)
             .await
     }
+
+    /// Record the chain head seen on this poll
+    pub async fn update_latest_block_number<'c, E>(
+        &self,
+        block_number: u64,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("UPDATE evm_sync_logs SET latest_block_number = $1 WHERE address = $2")
+            .bind(block_number as i64)
+            .bind(self.address)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The block every filter has synced up to, `None` before any has
+    pub async fn min_synced_block<'c, E>(connection: E) -> Result<Option<i64>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT MIN(last_synced_block_number) FROM evm_sync_logs")
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Blocks the furthest-behind filter trails the chain head it last saw,
+    /// `None` before any listener has polled
+    pub async fn max_lag_blocks<'c, E>(connection: E) -> Result<Option<i64>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            r#"
+            SELECT MAX(GREATEST(latest_block_number - last_synced_block_number, 0))
+            FROM evm_sync_logs
+            WHERE latest_block_number IS NOT NULL
+            "#,
+        )
+        .fetch_one(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    const CONTRACT: &str = "ca143ce32fe78f1f7019d7d551a6402fc5350c73";
+
+    #[sqlx::test]
+    async fn find_or_create_is_idempotent(pool: PgPool) {
+        sqlx::query("INSERT INTO evm_chains (id, name, block_time) VALUES (56, 'bsc', 3)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let created = EvmSyncLogs::find_or_create_by_address(CONTRACT, 56, &pool)
+            .await
+            .unwrap();
+        assert_eq!(created.last_synced_block_number, 0);
+
+        let updated = created
+            .update_last_synced_block_number(99, &pool)
+            .await
+            .unwrap();
+        assert_eq!(updated.last_synced_block_number, 99);
+
+        let found = EvmSyncLogs::find_or_create_by_address(CONTRACT, 56, &pool)
+            .await
+            .unwrap();
+        assert_eq!(found.address, created.address);
+        assert_eq!(found.last_synced_block_number, 99);
+        assert_eq!(EvmSyncLogs::find_all(&pool).await.unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn lag_is_measured_against_the_last_seen_head(pool: PgPool) {
+        sqlx::query("INSERT INTO evm_chains (id, name, block_time) VALUES (56, 'bsc', 3)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(EvmSyncLogs::max_lag_blocks(&pool).await.unwrap(), None);
+
+        let behind = EvmSyncLogs::create(CONTRACT, 56, Some(90), &pool)
+            .await
+            .unwrap();
+        let caught_up = EvmSyncLogs::create(&"ab".repeat(20), 56, Some(100), &pool)
+            .await
+            .unwrap();
+        behind.update_latest_block_number(100, &pool).await.unwrap();
+        caught_up
+            .update_latest_block_number(100, &pool)
+            .await
+            .unwrap();
+        assert_eq!(EvmSyncLogs::max_lag_blocks(&pool).await.unwrap(), Some(10));
+        assert_eq!(EvmSyncLogs::min_synced_block(&pool).await.unwrap(), Some(90));
+
+        behind
+            .update_last_synced_block_number(100, &pool)
+            .await
+            .unwrap();
+        assert_eq!(EvmSyncLogs::max_lag_blocks(&pool).await.unwrap(), Some(0));
+    }
 }
@@ -0,0 +1,244 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// How bad an external report says a token is, least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReportSeverity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl ReportSeverity {
+    pub const ALL: [ReportSeverity; 5] = [
+        ReportSeverity::Info,
+        ReportSeverity::Low,
+        ReportSeverity::Medium,
+        ReportSeverity::High,
+        ReportSeverity::Critical,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportSeverity::Info => "info",
+            ReportSeverity::Low => "low",
+            ReportSeverity::Medium => "medium",
+            ReportSeverity::High => "high",
+            ReportSeverity::Critical => "critical",
+        }
+    }
+
+    /// Parse a stored or submitted severity
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == value)
+    }
+}
+
+/// ExternalReport entity: a source's risk report for a token
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ExternalReport {
+    pub id: i32,
+    pub token_address: Address20,
+    pub source: String,
+    pub severity: String, // "info", "low", "medium", "high" or "critical"
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    /// Verified by the source
+    pub confirmed: bool,
+    pub reported_at: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ExternalReport {
+    pub fn severity(&self) -> Option<ReportSeverity> {
+        ReportSeverity::parse(&self.severity)
+    }
+}
+
+/// Input for recording a report
+#[derive(Debug, Clone)]
+pub struct NewExternalReport {
+    pub token_address: Address20,
+    pub source: String,
+    pub severity: ReportSeverity,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub confirmed: bool,
+    pub reported_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ExternalReport {
+    /// Record a report, replacing the source's earlier one for the token
+    pub async fn upsert<'c, E>(
+        report: &NewExternalReport,
+        connection: E,
+    ) -> Result<ExternalReport, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO external_reports (
+                token_address, source, severity, category, description, url, confirmed, reported_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (token_address, source) DO UPDATE SET
+                severity = EXCLUDED.severity,
+                category = EXCLUDED.category,
+                description = EXCLUDED.description,
+                url = EXCLUDED.url,
+                confirmed = EXCLUDED.confirmed,
+                reported_at = EXCLUDED.reported_at,
+                updated_at = NOW()
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ExternalReport>(query)
+            .bind(report.token_address)
+            .bind(&report.source)
+            .bind(report.severity.as_str())
+            .bind(&report.category)
+            .bind(&report.description)
+            .bind(&report.url)
+            .bind(report.confirmed)
+            .bind(report.reported_at)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// A token's reports, most severe first
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<Vec<ExternalReport>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ExternalReport>(
+            r#"
+            SELECT * FROM external_reports
+            WHERE token_address = $1
+            ORDER BY array_position(ARRAY['info', 'low', 'medium', 'high', 'critical'], severity) DESC,
+                reported_at DESC
+            "#,
+        )
+        .bind(token_address)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Set the token's `external_risk` to the worst severity of its reports
+    pub async fn update_token_risk<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE tokens SET external_risk = (
+                SELECT severity FROM external_reports
+                WHERE token_address = $1
+                ORDER BY array_position(ARRAY['info', 'low', 'medium', 'high', 'critical'], severity) DESC
+                LIMIT 1
+            )
+            WHERE address = $1
+            "#,
+        )
+        .bind(token_address)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        test_support::address,
+        token::{NewToken, Token},
+    };
+
+    fn report(source: &str, severity: ReportSeverity) -> NewExternalReport {
+        NewExternalReport {
+            token_address: address(1),
+            source: source.to_string(),
+            severity,
+            category: Some("honeypot".to_string()),
+            description: None,
+            url: None,
+            confirmed: false,
+            reported_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn severities_parse_and_order() {
+        assert_eq!(ReportSeverity::parse("high"), Some(ReportSeverity::High));
+        assert_eq!(ReportSeverity::parse("HIGH"), None);
+        assert!(ReportSeverity::Critical > ReportSeverity::Medium);
+    }
+
+    #[sqlx::test]
+    async fn worst_report_is_reflected_on_the_token(pool: PgPool) {
+        let token = NewToken {
+            address: address(1),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: None,
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: None,
+        };
+        Token::create(&token, &pool).await.unwrap();
+
+        ExternalReport::upsert(&report("goplus", ReportSeverity::Critical), &pool)
+            .await
+            .unwrap();
+        ExternalReport::upsert(&report("bot", ReportSeverity::Medium), &pool)
+            .await
+            .unwrap();
+        ExternalReport::update_token_risk(&address(1), &pool)
+            .await
+            .unwrap();
+        let token = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.external_risk.as_deref(), Some("critical"));
+
+        // A source re-reporting replaces its report
+        let downgraded = ExternalReport::upsert(&report("goplus", ReportSeverity::Low), &pool)
+            .await
+            .unwrap();
+        assert_eq!(downgraded.severity(), Some(ReportSeverity::Low));
+        ExternalReport::update_token_risk(&address(1), &pool)
+            .await
+            .unwrap();
+
+        let reports = ExternalReport::find_by_token(&address(1), &pool)
+            .await
+            .unwrap();
+        let found: Vec<_> = reports.iter().map(|r| r.source.as_str()).collect();
+        assert_eq!(found, vec!["bot", "goplus"]);
+        let token = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.external_risk.as_deref(), Some("medium"));
+    }
+}
@@ -0,0 +1,152 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::entity::evm_logs::EvmLogs;
+
+/// A log's identity across queues and redeliveries
+pub type LogKey = ([u8; 32], i64);
+
+/// HandledLog: the logs the processor has handled, so a log the queue hands
+/// out again is skipped
+pub struct HandledLog;
+
+impl HandledLog {
+    /// Key of `log`: its transaction hash and log index
+    pub fn key(log: &EvmLogs) -> LogKey {
+        (log.transaction_hash, log.log_index)
+    }
+
+    /// Of `logs`, the keys of those already handled
+    pub async fn find_handled<'c, E>(
+        logs: &[&EvmLogs],
+        connection: E,
+    ) -> Result<Vec<LogKey>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        if logs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (hashes, indexes): (Vec<Vec<u8>>, Vec<i64>) = logs
+            .iter()
+            .map(|log| (log.transaction_hash.to_vec(), log.log_index))
+            .unzip();
+
+        let rows: Vec<(Vec<u8>, i64)> = sqlx::query_as(
+            r#"
+            SELECT h.transaction_hash, h.log_index
+            FROM handled_logs h
+            JOIN UNNEST($1::BYTEA[], $2::BIGINT[]) AS l(transaction_hash, log_index)
+                ON l.transaction_hash = h.transaction_hash AND l.log_index = h.log_index
+            "#,
+        )
+        .bind(hashes)
+        .bind(indexes)
+        .fetch_all(connection)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(hash, index)| Some((hash.try_into().ok()?, index)))
+            .collect())
+    }
+
+    /// Mark `log` handled; false if it already was
+    pub async fn record<'c, E>(log: &EvmLogs, connection: E) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO handled_logs (transaction_hash, log_index)
+            VALUES ($1, $2)
+            ON CONFLICT (transaction_hash, log_index) DO NOTHING
+            "#,
+        )
+        .bind(log.transaction_hash.to_vec())
+        .bind(log.log_index)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete up to `limit` logs handled before `cutoff`. Returns the number
+    /// of rows removed.
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM handled_logs
+            WHERE (transaction_hash, log_index) IN (
+                SELECT transaction_hash, log_index FROM handled_logs
+                WHERE handled_at < $1
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, b256, Bytes, B256},
+        rpc::types::Log,
+    };
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+
+    fn log(tx: u8, log_index: u64) -> EvmLogs {
+        let log = Log {
+            inner: alloy::primitives::Log::new(
+                address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73"),
+                vec![b256!(
+                    "d78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"
+                )],
+                Bytes::from(vec![0xab; 128]),
+            )
+            .unwrap(),
+            block_hash: Some(B256::repeat_byte(1)),
+            block_number: Some(10),
+            block_timestamp: None,
+            transaction_hash: Some(B256::with_last_byte(tx)),
+            transaction_index: Some(0),
+            log_index: Some(log_index),
+            removed: false,
+        };
+        EvmLogs::from_rpc_log(&log).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn redelivered_logs_are_found_handled(pool: PgPool) {
+        let (first, second, other_tx) = (log(1, 0), log(1, 1), log(2, 0));
+
+        assert!(HandledLog::record(&first, &pool).await.unwrap());
+        // Handled again after a redelivery
+        assert!(!HandledLog::record(&first, &pool).await.unwrap());
+
+        let handled = HandledLog::find_handled(&[&first, &second, &other_tx], &pool)
+            .await
+            .unwrap();
+        assert_eq!(handled, vec![HandledLog::key(&first)]);
+        assert!(HandledLog::find_handled(&[], &pool).await.unwrap().is_empty());
+
+        let removed = HandledLog::delete_older_than(Utc::now(), 100, &pool)
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+    }
+}
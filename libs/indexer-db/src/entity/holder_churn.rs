@@ -0,0 +1,188 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// One hour of holders entering and exiting a token
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct HolderChurn {
+    pub token_address: Address20,
+    pub hour: chrono::DateTime<chrono::Utc>,
+    /// Wallets whose balance went from 0 to positive
+    pub entered: i32,
+    /// Wallets whose balance went to 0
+    pub exited: i32,
+}
+
+impl HolderChurn {
+    /// Count a holder entering and/or exiting in the hour containing `timestamp`
+    pub async fn record<'c, E>(
+        token_address: &Address20,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        entered: i32,
+        exited: i32,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO token_holder_churn_hourly (token_address, hour, entered, exited)
+            VALUES ($1, date_trunc('hour', $2), $3, $4)
+            ON CONFLICT (token_address, hour) DO UPDATE SET
+                entered = token_holder_churn_hourly.entered + EXCLUDED.entered,
+                exited = token_holder_churn_hourly.exited + EXCLUDED.exited
+            "#,
+        )
+        .bind(token_address)
+        .bind(timestamp)
+        .bind(entered)
+        .bind(exited)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get hourly buckets for a token since a given time
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Vec<HolderChurn>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, HolderChurn>(
+            r#"
+            SELECT * FROM token_holder_churn_hourly
+            WHERE token_address = $1 AND hour >= $2
+            ORDER BY hour ASC
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Delete up to `limit` buckets older than `cutoff`, returning how many
+    /// were removed
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM token_holder_churn_hourly
+            WHERE (token_address, hour) IN (
+                SELECT token_address, hour FROM token_holder_churn_hourly
+                WHERE hour < $1
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::{Duration, Utc};
+    use sqlx::{types::BigDecimal, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+        token_holder::{NewTokenHolder, TokenHolder},
+    };
+
+    #[sqlx::test]
+    async fn churn_is_bucketed_hourly_and_rolled_up_on_the_token(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        let token = NewToken {
+            address: address(1),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: None,
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: None,
+        };
+        Token::create(&token, &pool).await.unwrap();
+
+        // Ten holders remain after 4 entries and 2 exits in the last day
+        for n in 10..20 {
+            let holder = NewTokenHolder {
+                token_address: address(1),
+                wallet_address: address(n),
+                balance: BigDecimal::from(100),
+                is_dev: false,
+                is_sniper: false,
+                is_contract: false,
+                first_buy_block: None,
+                source: None,
+            };
+            TokenHolder::upsert(&holder, &pool).await.unwrap();
+        }
+        let now = Utc::now();
+        HolderChurn::record(&address(1), now, 1, 0, &pool)
+            .await
+            .unwrap();
+        HolderChurn::record(&address(1), now, 1, 1, &pool)
+            .await
+            .unwrap();
+        HolderChurn::record(&address(1), now - Duration::hours(2), 2, 1, &pool)
+            .await
+            .unwrap();
+        HolderChurn::record(&address(1), now - Duration::hours(30), 0, 5, &pool)
+            .await
+            .unwrap();
+
+        let buckets = HolderChurn::find_by_token(&address(1), now - Duration::hours(3), &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            buckets
+                .iter()
+                .map(|b| (b.entered, b.exited))
+                .collect::<Vec<_>>(),
+            vec![(2, 1), (2, 1)]
+        );
+
+        Token::refresh_holder_churn(&address(1), &pool)
+            .await
+            .unwrap();
+        let token = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.holders_entered_24h, Some(4));
+        assert_eq!(token.holders_exited_24h, Some(2));
+        // 10 - 4 + 2 = 8 holders a day ago, 2 of whom left; the older bucket is ignored
+        assert_eq!(
+            token.holder_churn_rate_24h,
+            Some("25.00".parse::<BigDecimal>().unwrap())
+        );
+
+        let removed = HolderChurn::delete_older_than(now - Duration::hours(24), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+    }
+}
@@ -0,0 +1,201 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// HolderReconciliation entity: a stored holder balance corrected to match
+/// `balanceOf` on chain
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct HolderReconciliation {
+    pub id: i32,
+    pub token_address: Address20,
+    pub wallet_address: Address20,
+    /// Raw token units
+    pub stored_balance: BigDecimal,
+    pub onchain_balance: BigDecimal,
+    /// `onchain_balance - stored_balance`
+    pub delta: BigDecimal,
+    /// Block `balanceOf` was read at
+    pub block_number: i64,
+    pub reconciled_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for recording a correction
+#[derive(Debug, Clone)]
+pub struct NewHolderReconciliation {
+    pub token_address: Address20,
+    pub wallet_address: Address20,
+    pub stored_balance: BigDecimal,
+    pub onchain_balance: BigDecimal,
+    pub block_number: i64,
+}
+
+impl HolderReconciliation {
+    /// Record a correction
+    pub async fn create<'c, E>(
+        correction: &NewHolderReconciliation,
+        connection: E,
+    ) -> Result<HolderReconciliation, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO holder_reconciliations (
+                token_address, wallet_address, stored_balance, onchain_balance, delta, block_number
+            )
+            VALUES ($1, $2, $3, $4, $4 - $3, $5)
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, HolderReconciliation>(query)
+            .bind(correction.token_address)
+            .bind(correction.wallet_address)
+            .bind(&correction.stored_balance)
+            .bind(&correction.onchain_balance)
+            .bind(correction.block_number)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Get a token's most recent corrections, newest first
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<HolderReconciliation>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, HolderReconciliation>(
+            r#"
+            SELECT * FROM holder_reconciliations
+            WHERE token_address = $1
+            ORDER BY reconciled_at DESC, id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(token_address)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// The `limit` tokens with holders that traded the most over 24h, the
+    /// ones whose holder lists are read the most
+    pub async fn find_tracked_tokens<'c, E>(
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar::<_, Address20>(
+            r#"
+            SELECT t.address FROM tokens t
+            WHERE t.volume_24h_usd > 0
+              AND EXISTS (SELECT 1 FROM token_holders h WHERE h.token_address = t.address)
+            ORDER BY t.volume_24h_usd DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+        token_holder::{NewTokenHolder, TokenHolder},
+    };
+
+    async fn token(pool: &PgPool, n: u8, volume: i64, with_holder: bool) {
+        let new = NewToken {
+            address: address(n),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: Some(100),
+        };
+        Token::create(&new, pool).await.unwrap();
+        sqlx::query("UPDATE tokens SET volume_24h_usd = $2 WHERE address = $1")
+            .bind(address(n))
+            .bind(BigDecimal::from(volume))
+            .execute(pool)
+            .await
+            .unwrap();
+        if with_holder {
+            let holder = NewTokenHolder {
+                token_address: address(n),
+                wallet_address: address(50),
+                balance: BigDecimal::from(1),
+                is_dev: false,
+                is_sniper: false,
+                is_contract: false,
+                first_buy_block: None,
+                source: None,
+            };
+            TokenHolder::upsert(&holder, pool).await.unwrap();
+        }
+    }
+
+    #[sqlx::test]
+    async fn corrections_are_recorded_with_their_delta(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        token(&pool, 1, 500, true).await;
+        token(&pool, 2, 9_000, true).await;
+        token(&pool, 3, 90_000, false).await;
+        token(&pool, 4, 0, true).await;
+
+        let tracked = HolderReconciliation::find_tracked_tokens(10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(tracked, vec![address(2), address(1)]);
+
+        // 2M tokens of 18 decimals: more than DECIMAL(30, 18) could hold
+        let onchain = BigDecimal::from_str("2000000000000000000000000").unwrap();
+        let correction = NewHolderReconciliation {
+            token_address: address(1),
+            wallet_address: address(50),
+            stored_balance: BigDecimal::from(1),
+            onchain_balance: onchain.clone(),
+            block_number: 1_000,
+        };
+        let created = HolderReconciliation::create(&correction, &pool)
+            .await
+            .unwrap();
+        assert_eq!(created.delta, &onchain - BigDecimal::from(1));
+
+        TokenHolder::update_balance(&address(1), &address(50), &onchain, None, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            TokenHolder::find_balance(&address(1), &address(50), &pool)
+                .await
+                .unwrap(),
+            Some(onchain)
+        );
+
+        let found = HolderReconciliation::find_by_token(&address(1), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].block_number, 1_000);
+    }
+}
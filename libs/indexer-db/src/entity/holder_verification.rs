@@ -0,0 +1,336 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// HolderVerification entity: an operator request to re-read every stored
+/// holder balance of one token on chain
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct HolderVerification {
+    pub id: i32,
+    pub token_address: Address20,
+    /// `pending`, `running`, `done` or `failed`
+    pub status: String,
+    pub holders_checked: i32,
+    pub balances_corrected: i32,
+    /// Wallets found holding nothing and removed
+    pub holders_pruned: i32,
+    /// Block balances were read at
+    pub block_number: Option<i64>,
+    /// Token metrics once verified
+    pub holder_count: Option<i32>,
+    pub top_10_holder_percent: Option<BigDecimal>,
+    pub error: Option<String>,
+    pub requested_at: chrono::DateTime<chrono::Utc>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Last claim or progress report of a running verification
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// What a verification found, so far or in full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerificationCounts {
+    pub holders_checked: i32,
+    pub balances_corrected: i32,
+    pub holders_pruned: i32,
+}
+
+impl HolderVerification {
+    /// Queue a verification
+    pub async fn create<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<HolderVerification, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, HolderVerification>(
+            "INSERT INTO holder_verifications (token_address) VALUES ($1) RETURNING *",
+        )
+        .bind(token_address)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Find a verification by id
+    pub async fn find_by_id<'c, E>(
+        id: i32,
+        connection: E,
+    ) -> Result<Option<HolderVerification>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, HolderVerification>("SELECT * FROM holder_verifications WHERE id = $1")
+            .bind(id)
+            .fetch_optional(connection)
+            .await
+    }
+
+    /// Mark the oldest pending verification as running and return it, or a
+    /// running one not updated for `stale_secs` (its processor stopped).
+    /// Concurrent processors never claim the same one.
+    pub async fn claim_next<'c, E>(
+        stale_secs: i64,
+        connection: E,
+    ) -> Result<Option<HolderVerification>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, HolderVerification>(
+            r#"
+            UPDATE holder_verifications SET
+                status = 'running',
+                holders_checked = 0,
+                balances_corrected = 0,
+                holders_pruned = 0,
+                started_at = NOW(),
+                updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM holder_verifications
+                WHERE status = 'pending'
+                   OR (status = 'running' AND updated_at < NOW() - make_interval(secs => $1))
+                ORDER BY id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(stale_secs as f64)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Record the progress of a running verification, which also keeps it
+    /// from being reclaimed
+    pub async fn record_progress<'c, E>(
+        id: i32,
+        counts: &VerificationCounts,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE holder_verifications SET
+                holders_checked = $2,
+                balances_corrected = $3,
+                holders_pruned = $4,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(counts.holders_checked)
+        .bind(counts.balances_corrected)
+        .bind(counts.holders_pruned)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a finished verification with the token's recomputed metrics
+    pub async fn finish<'c, E>(
+        id: i32,
+        counts: &VerificationCounts,
+        block_number: i64,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE holder_verifications v SET
+                status = 'done',
+                holders_checked = $2,
+                balances_corrected = $3,
+                holders_pruned = $4,
+                block_number = $5,
+                holder_count = t.holder_count,
+                top_10_holder_percent = t.top_10_holder_percent,
+                error = NULL,
+                finished_at = NOW(),
+                updated_at = NOW()
+            FROM tokens t
+            WHERE v.id = $1 AND t.address = v.token_address
+            "#,
+        )
+        .bind(id)
+        .bind(counts.holders_checked)
+        .bind(counts.balances_corrected)
+        .bind(counts.holders_pruned)
+        .bind(block_number)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a verification that stopped on an error, with what it got done
+    pub async fn fail<'c, E>(
+        id: i32,
+        counts: &VerificationCounts,
+        error: &str,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE holder_verifications SET
+                status = 'failed',
+                holders_checked = $2,
+                balances_corrected = $3,
+                holders_pruned = $4,
+                error = $5,
+                finished_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(counts.holders_checked)
+        .bind(counts.balances_corrected)
+        .bind(counts.holders_pruned)
+        .bind(error)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+    };
+
+    #[sqlx::test]
+    async fn verifications_are_claimed_once_and_record_metrics(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let token = NewToken {
+            address: address(1),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: None,
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: None,
+        };
+        Token::create(&token, &pool).await.unwrap();
+        let zero = BigDecimal::from(0);
+        Token::update_holder_metrics(&address(1), 42, &BigDecimal::from(35), &zero, &zero, &pool)
+            .await
+            .unwrap();
+
+        let first = HolderVerification::create(&address(1), &pool)
+            .await
+            .unwrap();
+        assert_eq!(first.status, "pending");
+        let second = HolderVerification::create(&address(1), &pool)
+            .await
+            .unwrap();
+
+        let counts = VerificationCounts {
+            holders_checked: 50,
+            balances_corrected: 3,
+            holders_pruned: 8,
+        };
+        let claimed = HolderVerification::claim_next(600, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((claimed.id, claimed.status.as_str()), (first.id, "running"));
+        HolderVerification::finish(claimed.id, &counts, 1_000, &pool)
+            .await
+            .unwrap();
+
+        let claimed = HolderVerification::claim_next(600, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(claimed.id, second.id);
+        HolderVerification::fail(claimed.id, &counts, "RPC unavailable", &pool)
+            .await
+            .unwrap();
+        assert!(HolderVerification::claim_next(600, &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        let done = HolderVerification::find_by_id(first.id, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(done.status, "done");
+        assert_eq!(done.holders_pruned, 8);
+        assert_eq!(done.block_number, Some(1_000));
+        assert_eq!(done.holder_count, Some(42));
+        assert_eq!(done.top_10_holder_percent, Some(BigDecimal::from(35)));
+
+        let failed = HolderVerification::find_by_id(second.id, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(failed.status, "failed");
+        assert_eq!(failed.error.as_deref(), Some("RPC unavailable"));
+    }
+
+    #[sqlx::test]
+    async fn stale_running_verifications_are_reclaimed(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let queued = HolderVerification::create(&address(1), &pool)
+            .await
+            .unwrap();
+        let claimed = HolderVerification::claim_next(600, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(claimed.id, queued.id);
+        let counts = VerificationCounts {
+            holders_checked: 200,
+            ..VerificationCounts::default()
+        };
+        HolderVerification::record_progress(claimed.id, &counts, &pool)
+            .await
+            .unwrap();
+        assert!(
+            HolderVerification::claim_next(600, &pool)
+                .await
+                .unwrap()
+                .is_none(),
+            "still reporting progress"
+        );
+
+        // Its processor stopped ten minutes ago
+        sqlx::query(
+            "UPDATE holder_verifications SET updated_at = NOW() - INTERVAL '11 minutes'",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let reclaimed = HolderVerification::claim_next(600, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reclaimed.id, queued.id);
+        assert_eq!(reclaimed.status, "running");
+        assert_eq!(reclaimed.holders_checked, 0, "counted again from the start");
+    }
+}
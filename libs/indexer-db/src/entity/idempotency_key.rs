@@ -0,0 +1,235 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+/// IdempotencyKey entity: a mutating API request sent with an
+/// `Idempotency-Key` header, and the response it got
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct IdempotencyKey {
+    pub idempotency_key: String,
+    /// Whether the key was first used with the request it was looked up with
+    pub same_request: bool,
+    /// `None` while the first request is still being handled
+    pub response_status: Option<i16>,
+    pub response_content_type: Option<String>,
+    pub response_body: Option<Vec<u8>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl IdempotencyKey {
+    /// Reserve `client`'s `key` for `request` (method, path and body),
+    /// `false` if it is already taken. `client` is the API key the request
+    /// was sent with, empty without one; keys of different clients never
+    /// clash. Keys created before `expired_before`, and keys still without a
+    /// response that were claimed before `abandoned_before`, are taken over.
+    pub async fn claim<'c, E>(
+        client: &str,
+        key: &str,
+        request: &[u8],
+        expired_before: chrono::DateTime<chrono::Utc>,
+        abandoned_before: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (client_hash, idempotency_key, request_hash)
+            VALUES (sha256($1), $2, sha256($3))
+            ON CONFLICT (client_hash, idempotency_key) DO UPDATE SET
+                request_hash = EXCLUDED.request_hash,
+                response_status = NULL,
+                response_content_type = NULL,
+                response_body = NULL,
+                created_at = NOW(),
+                completed_at = NULL
+            WHERE idempotency_keys.created_at < $4
+                OR (idempotency_keys.completed_at IS NULL AND idempotency_keys.created_at < $5)
+            "#,
+        )
+        .bind(client.as_bytes())
+        .bind(key)
+        .bind(request)
+        .bind(expired_before)
+        .bind(abandoned_before)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Find a client's key, comparing the request it was first used with to
+    /// `request`
+    pub async fn find_by_key<'c, E>(
+        client: &str,
+        key: &str,
+        request: &[u8],
+        connection: E,
+    ) -> Result<Option<IdempotencyKey>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, IdempotencyKey>(
+            r#"
+            SELECT idempotency_key, request_hash = sha256($3) AS same_request,
+                response_status, response_content_type, response_body, created_at, completed_at
+            FROM idempotency_keys
+            WHERE client_hash = sha256($1) AND idempotency_key = $2
+            "#,
+        )
+        .bind(client.as_bytes())
+        .bind(key)
+        .bind(request)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Store the response to a claimed key
+    pub async fn complete<'c, E>(
+        client: &str,
+        key: &str,
+        status: i16,
+        content_type: Option<&str>,
+        body: &[u8],
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys SET
+                response_status = $3,
+                response_content_type = $4,
+                response_body = $5,
+                completed_at = NOW()
+            WHERE client_hash = sha256($1) AND idempotency_key = $2
+            "#,
+        )
+        .bind(client.as_bytes())
+        .bind(key)
+        .bind(status)
+        .bind(content_type)
+        .bind(body)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Give up a claimed key without a response, so a retry runs again
+    pub async fn release<'c, E>(client: &str, key: &str, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            "DELETE FROM idempotency_keys WHERE client_hash = sha256($1) AND idempotency_key = $2",
+        )
+        .bind(client.as_bytes())
+        .bind(key)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete up to `limit` keys created before `cutoff`
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE (client_hash, idempotency_key) IN (
+                SELECT client_hash, idempotency_key FROM idempotency_keys
+                WHERE created_at < $1
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn keys_are_claimed_once_until_they_expire(pool: PgPool) {
+        let request = b"POST /api/wallets\n{}";
+        let expired_before = Utc::now() - Duration::hours(24);
+        let abandoned_before = Utc::now() - Duration::minutes(1);
+        let claim = |client: &'static str| {
+            IdempotencyKey::claim(client, "a", request, expired_before, abandoned_before, &pool)
+        };
+
+        assert!(claim("").await.unwrap());
+        assert!(!claim("").await.unwrap());
+        // Another client's key of the same name is its own
+        assert!(claim("client-key-0123456789").await.unwrap());
+
+        let pending = IdempotencyKey::find_by_key("", "a", request, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(pending.same_request);
+        assert_eq!(pending.response_status, None);
+
+        IdempotencyKey::complete("", "a", 201, Some("application/json"), b"{\"id\":1}", &pool)
+            .await
+            .unwrap();
+        let done = IdempotencyKey::find_by_key("", "a", b"POST /api/wallets\n{\"x\":1}", &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!done.same_request);
+        assert_eq!(done.response_status, Some(201));
+        assert_eq!(done.response_body.as_deref(), Some(&b"{\"id\":1}"[..]));
+
+        // A released key can be claimed again right away
+        IdempotencyKey::release("", "a", &pool).await.unwrap();
+        assert!(claim("").await.unwrap());
+
+        // A key left without a response is taken over once abandoned, a
+        // completed one only once expired
+        let later = Utc::now() + Duration::hours(1);
+        assert!(IdempotencyKey::claim("", "a", request, expired_before, later, &pool)
+            .await
+            .unwrap());
+        IdempotencyKey::complete("", "a", 201, None, b"", &pool)
+            .await
+            .unwrap();
+        assert!(!IdempotencyKey::claim("", "a", request, expired_before, later, &pool)
+            .await
+            .unwrap());
+        assert!(IdempotencyKey::claim("", "a", request, later, abandoned_before, &pool)
+            .await
+            .unwrap());
+
+        // Expired keys are trimmed
+        assert_eq!(
+            IdempotencyKey::delete_older_than(later, 100, &pool)
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(IdempotencyKey::find_by_key("", "a", request, &pool)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
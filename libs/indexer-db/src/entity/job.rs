@@ -0,0 +1,417 @@
+use serde_json::Value as JsonValue;
+use sqlx::{
+    types::{chrono, Json},
+    Executor, Postgres,
+};
+
+/// What a job does; the processor's worker runs each kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    /// Index a token launched before indexing and replay its history
+    /// (`{"address", "fromBlock"?, "toBlock"?}`)
+    TokenImport,
+    /// Recompute BeeScores (`{"tokens"?: [address]}`, every token when absent)
+    Rescore,
+    /// Check one token's top holders against the chain (`{"address"}`)
+    Reconcile,
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 3] = [JobKind::TokenImport, JobKind::Rescore, JobKind::Reconcile];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            JobKind::TokenImport => "token_import",
+            JobKind::Rescore => "rescore",
+            JobKind::Reconcile => "reconcile",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<JobKind> {
+        JobKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Job entity: a long-running task queued for the processor's worker
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Job {
+    pub id: i32,
+    pub kind: String,
+    pub params: Json<JsonValue>,
+    pub dedupe_key: Option<String>,
+    /// `pending`, `running`, `done`, `failed` or `cancelled`
+    pub status: String,
+    pub progress_done: i32,
+    pub progress_total: Option<i32>,
+    pub result: Option<Json<JsonValue>>,
+    pub error: Option<String>,
+    pub cancel_requested: bool,
+    pub requested_at: chrono::DateTime<chrono::Utc>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Input for queueing a job
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub kind: JobKind,
+    pub params: JsonValue,
+    /// Only one job per key is pending or running at a time
+    pub dedupe_key: Option<String>,
+}
+
+/// Filters for listing jobs
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter {
+    pub status: Option<String>,
+    pub kind: Option<String>,
+}
+
+impl Job {
+    /// Queue a job; `None` when one with the same dedupe key is already
+    /// pending or running
+    pub async fn create<'c, E>(job: &NewJob, connection: E) -> Result<Option<Job>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Job>(
+            r#"
+            INSERT INTO jobs (kind, params, dedupe_key) VALUES ($1, $2, $3)
+            ON CONFLICT (dedupe_key) WHERE status IN ('pending', 'running') DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(job.kind.name())
+        .bind(Json(&job.params))
+        .bind(&job.dedupe_key)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// The job pending or running under `dedupe_key`, if any
+    pub async fn find_open<'c, E>(
+        dedupe_key: &str,
+        connection: E,
+    ) -> Result<Option<Job>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Job>(
+            "SELECT * FROM jobs WHERE dedupe_key = $1 AND status IN ('pending', 'running')",
+        )
+        .bind(dedupe_key)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Number of jobs of `kind` pending or running
+    pub async fn count_open<'c, E>(kind: JobKind, connection: E) -> Result<i64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM jobs WHERE kind = $1 AND status IN ('pending', 'running')",
+        )
+        .bind(kind.name())
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Find a job by id
+    pub async fn find_by_id<'c, E>(id: i32, connection: E) -> Result<Option<Job>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Job>("SELECT * FROM jobs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(connection)
+            .await
+    }
+
+    /// Newest jobs first, optionally of one status and kind
+    pub async fn find_recent<'c, E>(
+        filter: &JobFilter,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<Job>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Job>(
+            r#"
+            SELECT * FROM jobs
+            WHERE ($1::VARCHAR IS NULL OR status = $1)
+              AND ($2::VARCHAR IS NULL OR kind = $2)
+            ORDER BY requested_at DESC, id DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(&filter.status)
+        .bind(&filter.kind)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Mark the oldest pending job as running and return it. A running job
+    /// whose progress wasn't reported for `stale_secs` was left by a
+    /// processor that stopped, and is claimed again from the start.
+    /// Concurrent processors never claim the same one.
+    pub async fn claim_next<'c, E>(stale_secs: i64, connection: E) -> Result<Option<Job>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Job>(
+            r#"
+            UPDATE jobs
+            SET status = 'running', progress_done = 0, progress_total = NULL,
+                started_at = NOW(), updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM jobs
+                WHERE status = 'pending'
+                    OR (status = 'running'
+                        AND updated_at < NOW() - make_interval(secs => $1))
+                ORDER BY id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(stale_secs as f64)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Record progress of a running job. Returns whether cancellation was
+    /// requested, in which case the job should stop.
+    pub async fn report_progress<'c, E>(
+        id: i32,
+        done: i32,
+        total: Option<i32>,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let cancel_requested: Option<bool> = sqlx::query_scalar(
+            r#"
+            UPDATE jobs
+            SET progress_done = $2, progress_total = COALESCE($3, progress_total),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING cancel_requested
+            "#,
+        )
+        .bind(id)
+        .bind(done)
+        .bind(total)
+        .fetch_optional(connection)
+        .await?;
+
+        Ok(cancel_requested.unwrap_or(true))
+    }
+
+    /// Record a job that ran to the end
+    pub async fn finish<'c, E>(
+        id: i32,
+        result: &JsonValue,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        Self::close(id, "done", Some(result), None, connection).await
+    }
+
+    /// Record a job that stopped on an error
+    pub async fn fail<'c, E>(id: i32, error: &str, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        Self::close(id, "failed", None, Some(error), connection).await
+    }
+
+    /// Record a running job that stopped on request, with what it did so far
+    pub async fn mark_cancelled<'c, E>(
+        id: i32,
+        result: &JsonValue,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        Self::close(id, "cancelled", Some(result), None, connection).await
+    }
+
+    async fn close<'c, E>(
+        id: i32,
+        status: &str,
+        result: Option<&JsonValue>,
+        error: Option<&str>,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = $2, result = $3, error = $4, updated_at = NOW(), finished_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(result.map(Json))
+        .bind(error)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Cancel a job: a pending one is cancelled at once, a running one is
+    /// asked to stop at its next progress report, a finished one is left
+    /// alone. `None` when there is no such job.
+    pub async fn cancel<'c, E>(id: i32, connection: E) -> Result<Option<Job>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Job>(
+            r#"
+            WITH cancelled AS (
+                UPDATE jobs
+                SET cancel_requested = TRUE,
+                    status = CASE WHEN status = 'pending' THEN 'cancelled' ELSE status END,
+                    finished_at = CASE WHEN status = 'pending' THEN NOW() ELSE finished_at END,
+                    updated_at = NOW()
+                WHERE id = $1 AND status IN ('pending', 'running')
+                RETURNING *
+            )
+            SELECT * FROM cancelled
+            UNION ALL
+            SELECT * FROM jobs WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM cancelled)
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+
+    fn import(address: &str) -> NewJob {
+        NewJob {
+            kind: JobKind::TokenImport,
+            params: json!({ "address": address }),
+            dedupe_key: Some(format!("token_import:{}", address)),
+        }
+    }
+
+    #[sqlx::test]
+    async fn jobs_are_deduplicated_claimed_and_closed(pool: PgPool) {
+        let first = Job::create(&import("0x01"), &pool).await.unwrap().unwrap();
+        assert_eq!(
+            (first.kind.as_str(), first.status.as_str()),
+            ("token_import", "pending")
+        );
+        assert!(Job::create(&import("0x01"), &pool).await.unwrap().is_none());
+        let open = Job::find_open("token_import:0x01", &pool).await.unwrap();
+        assert_eq!(open.map(|job| job.id), Some(first.id));
+        let rescore = NewJob {
+            kind: JobKind::Rescore,
+            params: json!({}),
+            dedupe_key: None,
+        };
+        let second = Job::create(&rescore, &pool).await.unwrap().unwrap();
+
+        let claimed = Job::claim_next(600, &pool).await.unwrap().unwrap();
+        assert_eq!((claimed.id, claimed.status.as_str()), (first.id, "running"));
+        assert_eq!(claimed.params.0["address"], "0x01");
+        assert!(!Job::report_progress(first.id, 1, Some(3), &pool)
+            .await
+            .unwrap());
+        Job::finish(first.id, &json!({ "pairsIndexed": 2 }), &pool)
+            .await
+            .unwrap();
+        let done = Job::find_by_id(first.id, &pool).await.unwrap().unwrap();
+        assert_eq!(done.status, "done");
+        assert_eq!((done.progress_done, done.progress_total), (1, Some(3)));
+        assert_eq!(done.result.unwrap().0["pairsIndexed"], 2);
+
+        // A finished import doesn't hold back a new one
+        let again = Job::create(&import("0x01"), &pool).await.unwrap().unwrap();
+
+        let claimed = Job::claim_next(600, &pool).await.unwrap().unwrap();
+        assert_eq!(claimed.id, second.id);
+        Job::fail(second.id, "RPC unavailable", &pool)
+            .await
+            .unwrap();
+
+        let filter = JobFilter {
+            status: Some("failed".to_string()),
+            kind: None,
+        };
+        let failed = Job::find_recent(&filter, 10, &pool).await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error.as_deref(), Some("RPC unavailable"));
+        let imports = JobFilter {
+            status: None,
+            kind: Some("token_import".to_string()),
+        };
+        assert_eq!(
+            Job::find_recent(&imports, 10, &pool).await.unwrap().len(),
+            2
+        );
+
+        // Pending: cancelled at once
+        let cancelled = Job::cancel(again.id, &pool).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, "cancelled");
+        assert!(Job::claim_next(600, &pool).await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    async fn running_jobs_stop_at_their_next_progress_report(pool: PgPool) {
+        let job = Job::create(&import("0x02"), &pool).await.unwrap().unwrap();
+        Job::claim_next(600, &pool).await.unwrap();
+
+        let asked = Job::cancel(job.id, &pool).await.unwrap().unwrap();
+        assert_eq!(asked.status, "running");
+        assert!(asked.cancel_requested);
+        assert!(Job::report_progress(job.id, 5, None, &pool).await.unwrap());
+        Job::mark_cancelled(job.id, &json!({ "logsReplayed": 5 }), &pool)
+            .await
+            .unwrap();
+
+        // Finished jobs are left as they are
+        let after = Job::cancel(job.id, &pool).await.unwrap().unwrap();
+        assert_eq!(after.status, "cancelled");
+        assert!(after.finished_at.is_some());
+        assert!(Job::cancel(999, &pool).await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    async fn stale_running_jobs_are_claimed_again(pool: PgPool) {
+        let job = Job::create(&import("0x03"), &pool).await.unwrap().unwrap();
+        Job::claim_next(600, &pool).await.unwrap();
+        Job::report_progress(job.id, 1, Some(2), &pool).await.unwrap();
+        assert!(Job::claim_next(600, &pool).await.unwrap().is_none());
+
+        sqlx::query("UPDATE jobs SET updated_at = NOW() - INTERVAL '20 minutes'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let reclaimed = Job::claim_next(600, &pool).await.unwrap().unwrap();
+        assert_eq!(reclaimed.id, job.id);
+        assert_eq!((reclaimed.progress_done, reclaimed.progress_total), (0, None));
+        assert!(Job::claim_next(600, &pool).await.unwrap().is_none());
+    }
+}
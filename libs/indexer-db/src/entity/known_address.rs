@@ -0,0 +1,96 @@
+use sqlx::{Executor, Postgres};
+
+use crate::types::Address20;
+
+/// KnownAddress entity: an exchange wallet, router, aggregator, bridge,
+/// locker or burn address that isn't an ordinary holder
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct KnownAddress {
+    pub id: i32,
+    pub chain_id: i64,
+    pub kind: String,
+    pub name: String,
+    pub address: Address20,
+}
+
+/// Known address kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownAddressKind {
+    /// Centralized exchange hot or deposit wallet
+    Cex,
+    Router,
+    /// DEX aggregator routing through several routers
+    Aggregator,
+    Bridge,
+    /// LP locker contract
+    Locker,
+    /// Zero, dead and other burn addresses
+    Burn,
+}
+
+impl KnownAddressKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KnownAddressKind::Cex => "cex",
+            KnownAddressKind::Router => "router",
+            KnownAddressKind::Aggregator => "aggregator",
+            KnownAddressKind::Bridge => "bridge",
+            KnownAddressKind::Locker => "locker",
+            KnownAddressKind::Burn => "burn",
+        }
+    }
+}
+
+impl KnownAddress {
+    /// Get every known address registered for a chain
+    pub async fn find_by_chain<'c, E>(
+        chain_id: i64,
+        connection: E,
+    ) -> Result<Vec<KnownAddress>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, KnownAddress>(
+            r#"
+            SELECT id, chain_id, kind, name, address
+            FROM known_addresses
+            WHERE chain_id = $1
+            ORDER BY kind, id
+            "#,
+        )
+        .bind(chain_id)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Whether this address is of the given kind
+    pub fn is(&self, kind: KnownAddressKind) -> bool {
+        self.kind == kind.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn bsc_directory_is_seeded(pool: PgPool) {
+        let known = KnownAddress::find_by_chain(56, &pool).await.unwrap();
+
+        let of_kind = |kind| known.iter().filter(|k| k.is(kind)).count();
+        assert_eq!(of_kind(KnownAddressKind::Burn), 2);
+        assert!(of_kind(KnownAddressKind::Cex) > 0);
+        // Routers and lockers come from chain_constants
+        assert!(known.iter().any(|k| k.is(KnownAddressKind::Router)
+            && k.address.to_hex() == "0x10ed43c718714eb63d5aa57b78b54704e256024e"));
+        assert_eq!(of_kind(KnownAddressKind::Locker), 3);
+        assert!(of_kind(KnownAddressKind::Aggregator) > 0);
+
+        assert!(KnownAddress::find_by_chain(1, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
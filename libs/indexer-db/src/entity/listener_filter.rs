@@ -0,0 +1,168 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::{Address20, Hash32};
+
+/// ListenerFilter entity: one log filter the listener polls, with its runtime controls
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ListenerFilter {
+    pub name: String,
+    /// Seconds between polls; `None` uses the chain's block time
+    pub poll_interval_secs: Option<i32>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Contract to match; `None` matches any emitter
+    pub address: Option<Address20>,
+    /// Event signature to match; `None` matches any event of `address`
+    pub topic: Option<Hash32>,
+    pub enabled: bool,
+    /// Higher priority filters are started first
+    pub priority: i32,
+}
+
+/// Changes to apply to a listener filter; `None` leaves a field as is
+#[derive(Debug, Clone, Default)]
+pub struct ListenerFilterUpdate {
+    pub enabled: Option<bool>,
+    /// `Some(0)` resets to the chain's block time
+    pub poll_interval_secs: Option<i32>,
+}
+
+impl ListenerFilter {
+    /// Get all listener filters, highest priority first
+    pub async fn find_all<'c, E>(connection: E) -> Result<Vec<ListenerFilter>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ListenerFilter>(
+            "SELECT * FROM listener_filters ORDER BY priority DESC, name",
+        )
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Get the enabled listener filters, highest priority first
+    pub async fn find_enabled<'c, E>(connection: E) -> Result<Vec<ListenerFilter>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ListenerFilter>(
+            "SELECT * FROM listener_filters WHERE enabled ORDER BY priority DESC, name",
+        )
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Get a listener filter by name
+    pub async fn find_by_name<'c, E>(
+        name: &str,
+        connection: E,
+    ) -> Result<Option<ListenerFilter>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ListenerFilter>("SELECT * FROM listener_filters WHERE name = $1")
+            .bind(name)
+            .fetch_optional(connection)
+            .await
+    }
+
+    /// Apply an update, returning the new state (`None` if the filter is unknown)
+    pub async fn update<'c, E>(
+        name: &str,
+        update: &ListenerFilterUpdate,
+        connection: E,
+    ) -> Result<Option<ListenerFilter>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            UPDATE listener_filters
+            SET enabled = COALESCE($2, enabled),
+                poll_interval_secs = CASE
+                    WHEN $3::INT IS NULL THEN poll_interval_secs
+                    WHEN $3 = 0 THEN NULL
+                    ELSE $3
+                END,
+                updated_at = NOW()
+            WHERE name = $1
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ListenerFilter>(query)
+            .bind(name)
+            .bind(update.enabled)
+            .bind(update.poll_interval_secs)
+            .fetch_optional(connection)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn global_filters_start_disabled_and_can_be_enabled(pool: PgPool) {
+        let enabled = ListenerFilter::find_enabled(&pool).await.unwrap();
+        assert_eq!(
+            enabled.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+            vec!["PairCreated"]
+        );
+        assert!(enabled[0].address.is_some() && enabled[0].topic.is_some());
+
+        let all = ListenerFilter::find_all(&pool).await.unwrap();
+        assert_eq!(
+            all.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+            vec![
+                "PairCreated",
+                "Swap",
+                "Sync",
+                "Transfer",
+                "Burn",
+                "Mint",
+                "Approval"
+            ]
+        );
+        assert!(all[1..].iter().all(|f| f.address.is_none()));
+
+        let resumed = ListenerFilter::update(
+            "Swap",
+            &ListenerFilterUpdate {
+                enabled: Some(true),
+                poll_interval_secs: Some(30),
+            },
+            &pool,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(resumed.enabled);
+        assert_eq!(resumed.poll_interval_secs, Some(30));
+
+        // Leaving fields out keeps them; 0 resets the interval
+        let reset = ListenerFilter::update(
+            "Swap",
+            &ListenerFilterUpdate {
+                enabled: None,
+                poll_interval_secs: Some(0),
+            },
+            &pool,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(reset.enabled);
+        assert_eq!(reset.poll_interval_secs, None);
+        assert_eq!(ListenerFilter::find_enabled(&pool).await.unwrap().len(), 2);
+
+        let unknown = ListenerFilter::update("Nope", &ListenerFilterUpdate::default(), &pool)
+            .await
+            .unwrap();
+        assert!(unknown.is_none());
+        assert!(ListenerFilter::find_by_name("Nope", &pool)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
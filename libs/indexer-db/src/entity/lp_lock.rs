@@ -4,19 +4,21 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use crate::types::{Address20, Hash32};
+
 /// LpLock entity representing a liquidity lock
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct LpLock {
     pub id: i32,
-    pub token_address: String,
-    pub pair_address: String,
-    pub lock_contract: String,
+    pub token_address: Address20,
+    pub pair_address: Address20,
+    pub lock_contract: Address20,
     pub lock_contract_name: Option<String>,
     pub locked_amount: Option<BigDecimal>,
     pub locked_percent: Option<BigDecimal>,
     pub lock_date: Option<chrono::DateTime<chrono::Utc>>,
     pub unlock_date: Option<chrono::DateTime<chrono::Utc>>,
-    pub tx_hash: Option<String>,
+    pub tx_hash: Option<Hash32>,
     pub block_number: Option<i64>,
     pub is_active: Option<bool>,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
@@ -26,15 +28,16 @@ pub struct LpLock {
 /// Input for creating a new LP lock
 #[derive(Debug, Clone)]
 pub struct NewLpLock {
-    pub token_address: String,
-    pub pair_address: String,
-    pub lock_contract: String,
+    pub token_address: Address20,
+    pub pair_address: Address20,
+    pub lock_contract: Address20,
     pub lock_contract_name: String,
     pub locked_amount: BigDecimal,
-    pub locked_percent: BigDecimal,
+    /// Share of the LP supply; `None` while the supply is unknown
+    pub locked_percent: Option<BigDecimal>,
     pub lock_date: chrono::DateTime<chrono::Utc>,
     pub unlock_date: chrono::DateTime<chrono::Utc>,
-    pub tx_hash: String,
+    pub tx_hash: Hash32,
     pub block_number: i64,
 }
 
@@ -54,15 +57,15 @@ impl LpLock {
         "#;
 
         sqlx::query_as::<_, LpLock>(query)
-            .bind(&lock.token_address)
-            .bind(&lock.pair_address)
-            .bind(&lock.lock_contract)
+            .bind(lock.token_address)
+            .bind(lock.pair_address)
+            .bind(lock.lock_contract)
             .bind(&lock.lock_contract_name)
             .bind(&lock.locked_amount)
             .bind(&lock.locked_percent)
             .bind(lock.lock_date)
             .bind(lock.unlock_date)
-            .bind(&lock.tx_hash)
+            .bind(lock.tx_hash)
             .bind(lock.block_number)
             .fetch_one(connection)
             .await
@@ -70,7 +73,7 @@ impl LpLock {
 
     /// Find locks by token address
     pub async fn find_by_token<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<Vec<LpLock>, sqlx::Error>
     where
@@ -86,7 +89,7 @@ impl LpLock {
 
     /// Find locks by pair address
     pub async fn find_by_pair<'c, E>(
-        pair_address: &str,
+        pair_address: &Address20,
         connection: E,
     ) -> Result<Vec<LpLock>, sqlx::Error>
     where
@@ -102,7 +105,7 @@ impl LpLock {
 
     /// Calculate total locked percent for a token
     pub async fn total_locked_percent<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<BigDecimal, sqlx::Error>
     where
@@ -120,7 +123,7 @@ impl LpLock {
 
     /// Get earliest unlock date for a token
     pub async fn earliest_unlock<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<Option<chrono::DateTime<chrono::Utc>>, sqlx::Error>
     where
@@ -134,6 +137,24 @@ impl LpLock {
         .await
     }
 
+    /// Set a lock's share of the LP supply, after the supply changed
+    pub async fn update_locked_percent<'c, E>(
+        id: i32,
+        locked_percent: Option<&BigDecimal>,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("UPDATE lp_locks SET locked_percent = $2, updated_at = NOW() WHERE id = $1")
+            .bind(id)
+            .bind(locked_percent)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
     /// Mark a lock as inactive (withdrawn)
     pub async fn deactivate<'c, E>(id: i32, connection: E) -> Result<(), sqlx::Error>
     where
@@ -169,3 +190,81 @@ impl LpLock {
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::test_support::{address, clear_seed_data, hash};
+
+    fn new_lock(n: u8, percent: i32, unlock_in_hours: i64) -> NewLpLock {
+        NewLpLock {
+            token_address: address(1),
+            pair_address: address(2),
+            lock_contract: address(3),
+            lock_contract_name: "PinkLock".to_string(),
+            locked_amount: BigDecimal::from(1_000),
+            locked_percent: Some(BigDecimal::from(percent)),
+            lock_date: Utc::now(),
+            unlock_date: Utc::now() + Duration::hours(unlock_in_hours),
+            tx_hash: hash(n),
+            block_number: n as i64,
+        }
+    }
+
+    #[sqlx::test]
+    async fn active_locks_drive_totals(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        let soon = LpLock::create(&new_lock(1, 30, 12), &pool).await.unwrap();
+        let later = LpLock::create(&new_lock(2, 50, 24 * 90), &pool)
+            .await
+            .unwrap();
+
+        // A lock whose share of the LP supply isn't known adds nothing
+        let mut unknown = new_lock(3, 0, 24 * 30);
+        unknown.locked_percent = None;
+        let unknown = LpLock::create(&unknown, &pool).await.unwrap();
+
+        let total = LpLock::total_locked_percent(&address(1), &pool)
+            .await
+            .unwrap();
+        assert_eq!(total, BigDecimal::from(80));
+        assert_eq!(
+            LpLock::find_by_pair(&address(2), &pool)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let earliest = LpLock::earliest_unlock(&address(1), &pool).await.unwrap();
+        assert_eq!(earliest, soon.unlock_date);
+
+        let expiring = LpLock::find_expiring_soon(24, &pool).await.unwrap();
+        assert_eq!(expiring.len(), 1);
+        assert_eq!(expiring[0].id, soon.id);
+
+        // The LP supply grew: the first lock now covers less of it
+        LpLock::update_locked_percent(soon.id, Some(&BigDecimal::from(20)), &pool)
+            .await
+            .unwrap();
+        let total = LpLock::total_locked_percent(&address(1), &pool)
+            .await
+            .unwrap();
+        assert_eq!(total, BigDecimal::from(70));
+
+        LpLock::deactivate(soon.id, &pool).await.unwrap();
+
+        let locks = LpLock::find_by_token(&address(1), &pool).await.unwrap();
+        assert_eq!(locks.len(), 2);
+        assert_eq!(locks[0].id, unknown.id);
+        assert_eq!(locks[1].id, later.id);
+        let total = LpLock::total_locked_percent(&address(1), &pool)
+            .await
+            .unwrap();
+        assert_eq!(total, BigDecimal::from(50));
+    }
+}
@@ -1,30 +1,127 @@
 // Existing EVM entities
+pub mod chain_constant;
 pub mod evm_chains;
 pub mod evm_logs;
+pub mod evm_sync_gaps;
 pub mod evm_sync_logs;
+pub mod listener_filter;
 
 // BeanBee entities
 pub mod alert;
+pub mod alert_metadata;
+pub mod alert_preference;
+pub mod alert_webhook;
+pub mod anomaly;
+pub mod bot_wallet;
+pub mod cex_flow;
+pub mod contract_scan;
+pub mod deferred_log;
+pub mod drainer_address;
+pub mod egress_outbox;
+pub mod external_report;
+pub mod handled_log;
+pub mod holder_churn;
+pub mod holder_reconciliation;
+pub mod holder_verification;
+pub mod idempotency_key;
+pub mod job;
+pub mod known_address;
 pub mod lp_lock;
+pub mod native_price;
 pub mod pair;
+pub mod pending_swap;
+pub mod price_quarantine;
 pub mod price_snapshot;
+pub mod processing_error;
+pub mod processing_lag;
+pub mod processor_event_metric;
+pub mod processor_progress;
+pub mod restriction_call;
+pub mod retention_run;
+pub mod risky_approval;
+pub mod sampled_token;
+pub mod score_history;
+pub mod social_metric;
 pub mod swap;
+pub mod tag;
 pub mod token;
 pub mod token_holder;
+pub mod token_impersonation;
+pub mod token_list;
+pub mod token_list_release;
+pub mod token_metadata_retry;
+pub mod token_metrics_minute;
+pub mod token_rescan;
+pub mod trending_rank;
 pub mod wallet;
 pub mod wallet_activity;
+pub mod wallet_profile;
+pub mod wallet_suggestion;
+
+#[cfg(test)]
+pub(crate) mod test_support;
+
+/// Forward pages (`after_id`) only hold rows recorded at least this long ago.
+/// Ids are handed out at insert, so a transaction committing late can land
+/// below an id a reader already paged past; holding back the newest rows
+/// gives it time to commit first.
+pub const COMMIT_LAG: std::time::Duration = std::time::Duration::from_secs(2);
 
 // Re-exports for convenience
+pub use chain_constant::ChainConstant;
 pub use evm_chains::EvmChains;
-pub use evm_logs::EvmLogs;
+pub use evm_logs::{EvmLogs, SourceLog};
+pub use evm_sync_gaps::{EvmSyncGap, EvmSyncedRange};
 pub use evm_sync_logs::EvmSyncLogs;
+pub use listener_filter::ListenerFilter;
 
 pub use alert::AlertEvent;
+pub use alert_metadata::AlertMetadata;
+pub use alert_preference::AlertPreference;
+pub use alert_webhook::AlertWebhook;
+pub use anomaly::Anomaly;
+pub use bot_wallet::BotWallet;
+pub use cex_flow::CexFlow;
+pub use contract_scan::ContractScan;
+pub use deferred_log::DeferredLog;
+pub use drainer_address::DrainerAddress;
+pub use egress_outbox::EgressOutbox;
+pub use external_report::ExternalReport;
+pub use handled_log::HandledLog;
+pub use holder_churn::HolderChurn;
+pub use holder_reconciliation::HolderReconciliation;
+pub use holder_verification::HolderVerification;
+pub use idempotency_key::IdempotencyKey;
+pub use job::Job;
+pub use known_address::KnownAddress;
 pub use lp_lock::LpLock;
+pub use native_price::NativePrice;
 pub use pair::Pair;
+pub use pending_swap::PendingSwap;
+pub use price_quarantine::QuarantinedSnapshot;
 pub use price_snapshot::PriceSnapshot;
+pub use processing_error::ProcessingError;
+pub use processing_lag::ProcessingLag;
+pub use processor_event_metric::ProcessorEventMetric;
+pub use processor_progress::ProcessorProgress;
+pub use restriction_call::RestrictionCall;
+pub use retention_run::RetentionRun;
+pub use risky_approval::RiskyApproval;
+pub use sampled_token::SampledToken;
+pub use score_history::ScoreHistory;
+pub use social_metric::SocialMetric;
 pub use swap::Swap;
+pub use tag::Tag;
 pub use token::Token;
 pub use token_holder::TokenHolder;
+pub use token_impersonation::TokenImpersonation;
+pub use token_list::TokenList;
+pub use token_list_release::TokenListRelease;
+pub use token_metadata_retry::TokenMetadataRetry;
+pub use token_metrics_minute::TokenMetricsMinute;
+pub use token_rescan::TokenRescan;
+pub use trending_rank::TrendingRank;
 pub use wallet::{Wallet, WalletWithStats};
 pub use wallet_activity::WalletActivity;
+pub use wallet_profile::WalletProfile;
+pub use wallet_suggestion::WalletSuggestion;
@@ -0,0 +1,86 @@
+use serde_json::Value as JsonValue;
+use sqlx::{
+    types::{chrono, BigDecimal, Json},
+    Executor, Postgres,
+};
+
+/// NativePrice entity: a chain's liquidity-weighted wrapped-native/USD rate
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct NativePrice {
+    pub chain_id: i64,
+    pub price_usd: BigDecimal,
+    /// Quote of each price pool, and whether it went into the rate
+    pub pools: Json<JsonValue>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl NativePrice {
+    /// Store the chain's current rate
+    pub async fn upsert<'c, E>(
+        chain_id: i64,
+        price_usd: &BigDecimal,
+        pools: &JsonValue,
+        connection: E,
+    ) -> Result<NativePrice, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, NativePrice>(
+            r#"
+            INSERT INTO native_price_index (chain_id, price_usd, pools)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (chain_id) DO UPDATE SET
+                price_usd = EXCLUDED.price_usd,
+                pools = EXCLUDED.pools,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(chain_id)
+        .bind(price_usd)
+        .bind(Json(pools))
+        .fetch_one(connection)
+        .await
+    }
+
+    /// The chain's rate, if one was ever stored
+    pub async fn find<'c, E>(
+        chain_id: i64,
+        connection: E,
+    ) -> Result<Option<NativePrice>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, NativePrice>("SELECT * FROM native_price_index WHERE chain_id = $1")
+            .bind(chain_id)
+            .fetch_optional(connection)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn rates_are_kept_per_chain(pool: PgPool) {
+        assert!(NativePrice::find(56, &pool).await.unwrap().is_none());
+
+        let pools = json!([{ "pool": "0x58f8", "priceUsd": 612.5, "included": true }]);
+        NativePrice::upsert(56, &"612.5".parse().unwrap(), &pools, &pool)
+            .await
+            .unwrap();
+        let updated = NativePrice::upsert(56, &"615.25".parse().unwrap(), &json!([]), &pool)
+            .await
+            .unwrap();
+        assert_eq!(updated.price_usd, "615.25".parse::<BigDecimal>().unwrap());
+
+        let stored = NativePrice::find(56, &pool).await.unwrap().unwrap();
+        assert_eq!(stored.price_usd, updated.price_usd);
+        assert_eq!(stored.pools.0, json!([]));
+        assert!(NativePrice::find(1, &pool).await.unwrap().is_none());
+    }
+}
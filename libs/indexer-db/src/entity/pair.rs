@@ -4,31 +4,110 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use crate::types::Address20;
+
 /// Pair entity representing a DEX trading pair
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Pair {
     pub id: i32,
-    pub address: String,
-    pub token0_address: String,
-    pub token1_address: String,
-    pub factory_address: String,
+    pub address: Address20,
+    pub token0_address: Address20,
+    pub token1_address: Address20,
+    pub factory_address: Address20,
     pub reserve0: Option<BigDecimal>,
     pub reserve1: Option<BigDecimal>,
     pub base_token_index: Option<i16>, // 0 or 1, indicating which token is WBNB/BUSD
     pub block_number: i64,
+    /// Found from one of its swaps rather than its PairCreated, so
+    /// `block_number` only stands in for its creation block
+    pub discovered: bool,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     pub last_updated: Option<chrono::DateTime<chrono::Utc>>,
+    /// LP token totalSupply, refreshed on Mint/Burn
+    pub lp_total_supply: Option<BigDecimal>,
+    pub lp_supply_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+
+    // Rolling 24h trading stats
+    pub volume_24h_usd: Option<BigDecimal>,
+    pub trades_24h: Option<i32>,
+    /// Estimated fees earned by LPs
+    pub fees_24h_usd: Option<BigDecimal>,
+    pub stats_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A pair with its 24h stats put in context, for ranking pools
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct PairStats {
+    #[sqlx(flatten)]
+    pub pair: Pair,
+    pub token_symbol: Option<String>,
+    /// Pool liquidity, when the token's main pair is this one
+    pub liquidity_usd: Option<BigDecimal>,
+    /// Share (0-100) of the 24h volume across all pairs
+    pub volume_share: f64,
+    /// 24h fees annualized over liquidity, in percent
+    pub fee_apr: Option<f64>,
+}
+
+/// Liquidity left, as a share (0-100) of its peak, under which a pair counts
+/// as rugged in [`FactoryStats`]
+pub const RUG_REMAINING_PERCENT: f64 = 10.0;
+
+/// Launch outcomes of the pairs one factory created in a window
+#[derive(sqlx::FromRow, Debug, Clone, Default)]
+pub struct FactoryStats {
+    pub pairs: i64,
+    /// Pairs with at least one LP lock recorded
+    pub locked_pairs: i64,
+    /// Median of each pair's first known liquidity
+    pub median_initial_liquidity_usd: Option<f64>,
+    /// Pairs that are their token's main pair and ever had liquidity; the
+    /// base for the rug rate
+    pub pairs_with_liquidity: i64,
+    /// Of those, pairs whose liquidity fell under [`RUG_REMAINING_PERCENT`]
+    /// of its peak
+    pub rugged_pairs: i64,
+}
+
+/// Pairs one factory created on one day
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct FactoryDay {
+    pub day: chrono::DateTime<chrono::Utc>,
+    pub pairs: i64,
+}
+
+/// Ranking for `Pair::find_top`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairSort {
+    Volume,
+    Fees,
+    /// Fee APR
+    Apr,
+    Liquidity,
+}
+
+impl PairSort {
+    fn order_by(&self) -> &'static str {
+        match self {
+            PairSort::Volume => "p.volume_24h_usd DESC",
+            PairSort::Fees => "p.fees_24h_usd DESC",
+            PairSort::Apr => "fee_apr DESC NULLS LAST",
+            PairSort::Liquidity => "liquidity_usd DESC NULLS LAST",
+        }
+    }
 }
 
 /// Input for creating a new pair
 #[derive(Debug, Clone)]
 pub struct NewPair {
-    pub address: String,
-    pub token0_address: String,
-    pub token1_address: String,
-    pub factory_address: String,
+    pub address: Address20,
+    pub token0_address: Address20,
+    pub token1_address: Address20,
+    pub factory_address: Address20,
     pub base_token_index: i16,
     pub block_number: i64,
+    /// See [`Pair::discovered`]
+    pub discovered: bool,
 }
 
 impl Pair {
@@ -38,26 +117,58 @@ impl Pair {
         E: Executor<'c, Database = Postgres>,
     {
         let query = r#"
-            INSERT INTO pairs (address, token0_address, token1_address, factory_address, base_token_index, block_number)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO pairs (address, token0_address, token1_address, factory_address, base_token_index, block_number, discovered)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             ON CONFLICT (address) DO NOTHING
             RETURNING *
         "#;
 
         sqlx::query_as::<_, Pair>(query)
-            .bind(&pair.address)
-            .bind(&pair.token0_address)
-            .bind(&pair.token1_address)
-            .bind(&pair.factory_address)
+            .bind(pair.address)
+            .bind(pair.token0_address)
+            .bind(pair.token1_address)
+            .bind(pair.factory_address)
             .bind(pair.base_token_index)
             .bind(pair.block_number)
+            .bind(pair.discovered)
             .fetch_one(connection)
             .await
     }
 
+    /// Insert a pair from a seed dump, created at `created_at` (now when
+    /// `None`); a pair already indexed is left alone. Returns whether it was
+    /// inserted.
+    pub async fn import<'c, E>(
+        pair: &NewPair,
+        created_at: Option<chrono::DateTime<chrono::Utc>>,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO pairs (address, token0_address, token1_address, factory_address, base_token_index, block_number, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, NOW()))
+            ON CONFLICT (address) DO NOTHING
+            "#,
+        )
+        .bind(pair.address)
+        .bind(pair.token0_address)
+        .bind(pair.token1_address)
+        .bind(pair.factory_address)
+        .bind(pair.base_token_index)
+        .bind(pair.block_number)
+        .bind(created_at)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Find pair by address
     pub async fn find_by_address<'c, E>(
-        address: &str,
+        address: &Address20,
         connection: E,
     ) -> Result<Option<Pair>, sqlx::Error>
     where
@@ -71,8 +182,8 @@ impl Pair {
 
     /// Find pair by token addresses
     pub async fn find_by_tokens<'c, E>(
-        token0: &str,
-        token1: &str,
+        token0: &Address20,
+        token1: &Address20,
         connection: E,
     ) -> Result<Option<Pair>, sqlx::Error>
     where
@@ -91,28 +202,73 @@ impl Pair {
         .await
     }
 
+    /// Pairs trading `token` against a base token, oldest first
+    pub async fn find_by_token<'c, E>(
+        token: &Address20,
+        connection: E,
+    ) -> Result<Vec<Pair>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Pair>(
+            r#"
+            SELECT * FROM pairs
+            WHERE (token0_address = $1 AND base_token_index = 1)
+               OR (token1_address = $1 AND base_token_index = 0)
+            ORDER BY block_number, address
+            "#,
+        )
+        .bind(token)
+        .fetch_all(connection)
+        .await
+    }
+
     /// Update reserves (from Sync events)
     pub async fn update_reserves<'c, E>(
-        address: &str,
+        address: &Address20,
         reserve0: &BigDecimal,
         reserve1: &BigDecimal,
         connection: E,
-    ) -> Result<(), sqlx::Error>
+    ) -> Result<Option<Pair>, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
-        sqlx::query(
+        sqlx::query_as::<_, Pair>(
             r#"
             UPDATE pairs SET
                 reserve0 = $2,
                 reserve1 = $3,
                 last_updated = NOW()
             WHERE address = $1
+            RETURNING *
             "#,
         )
         .bind(address)
         .bind(reserve0)
         .bind(reserve1)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Cache the LP token totalSupply
+    pub async fn update_lp_total_supply<'c, E>(
+        address: &Address20,
+        lp_total_supply: &BigDecimal,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE pairs SET
+                lp_total_supply = $2,
+                lp_supply_updated_at = NOW()
+            WHERE address = $1
+            "#,
+        )
+        .bind(address)
+        .bind(lp_total_supply)
         .execute(connection)
         .await?;
 
@@ -130,8 +286,192 @@ impl Pair {
             .await
     }
 
+    /// Recompute every pair's 24h volume, trade count and LP fees from swaps
+    ///
+    /// Swaps without a recorded fee are charged `fee_bps` of their volume.
+    /// Pairs that stopped trading decay to zero.
+    pub async fn refresh_volume_stats<'c, E>(
+        fee_bps: i32,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            UPDATE pairs SET
+                volume_24h_usd = COALESCE(s.volume_usd, 0),
+                trades_24h = COALESCE(s.trades, 0),
+                fees_24h_usd = COALESCE(s.fees_usd, 0),
+                stats_updated_at = NOW()
+            FROM pairs p
+            LEFT JOIN (
+                SELECT
+                    pair_address,
+                    SUM(COALESCE(amount_usd, 0)) AS volume_usd,
+                    COUNT(*) AS trades,
+                    SUM(COALESCE(fee_usd, amount_usd * $1 / 10000.0, 0)) AS fees_usd
+                FROM swaps
+                WHERE timestamp >= NOW() - INTERVAL '24 hours'
+                GROUP BY pair_address
+            ) s ON s.pair_address = p.address
+            WHERE pairs.id = p.id
+              AND (s.pair_address IS NOT NULL OR p.trades_24h > 0 OR p.volume_24h_usd > 0)
+            "#,
+        )
+        .bind(fee_bps)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Pairs traded over the last 24h, best first by `sort`
+    pub async fn find_top<'c, E>(
+        sort: PairSort,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<PairStats>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = format!(
+            r#"
+            SELECT
+                p.*,
+                t.symbol AS token_symbol,
+                CASE WHEN t.pair_address = p.address THEN t.liquidity_usd END AS liquidity_usd,
+                COALESCE(
+                    p.volume_24h_usd / NULLIF(SUM(p.volume_24h_usd) OVER (), 0) * 100,
+                    0
+                )::FLOAT8 AS volume_share,
+                (
+                    p.fees_24h_usd * 365
+                    / NULLIF(CASE WHEN t.pair_address = p.address THEN t.liquidity_usd END, 0)
+                    * 100
+                )::FLOAT8 AS fee_apr
+            FROM pairs p
+            LEFT JOIN tokens t ON t.address = CASE p.base_token_index
+                WHEN 0 THEN p.token1_address
+                ELSE p.token0_address
+            END
+            WHERE p.trades_24h > 0
+            ORDER BY {}, p.id DESC
+            LIMIT $1
+            "#,
+            sort.order_by()
+        );
+
+        sqlx::query_as::<_, PairStats>(&query)
+            .bind(limit)
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Launch outcomes of the pairs `factory` created since `since`
+    ///
+    /// Liquidity history comes from the token's price snapshots, archived
+    /// ones included, so dead tokens still count toward the rug rate.
+    pub async fn factory_stats<'c, E>(
+        factory: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<FactoryStats, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, FactoryStats>(
+            r#"
+            WITH factory_pairs AS (
+                SELECT
+                    p.address,
+                    t.liquidity_usd AS current_usd,
+                    -- The cold tier holds the older snapshots; each tier is
+                    -- probed on its own so both use their token index
+                    CASE
+                        WHEN cold_first.timestamp <= hot_first.timestamp
+                            OR hot_first.timestamp IS NULL
+                        THEN cold_first.liquidity_usd
+                        ELSE hot_first.liquidity_usd
+                    END AS initial_usd,
+                    GREATEST(hot_peak.liquidity_usd, cold_peak.liquidity_usd) AS peak_usd
+                FROM pairs p
+                LEFT JOIN tokens t ON t.pair_address = p.address
+                LEFT JOIN LATERAL (
+                    SELECT s.timestamp, s.liquidity_usd FROM price_snapshots s
+                    WHERE s.token_address = t.address AND s.liquidity_usd > 0
+                    ORDER BY s.timestamp
+                    LIMIT 1
+                ) hot_first ON TRUE
+                LEFT JOIN LATERAL (
+                    SELECT s.timestamp, s.liquidity_usd FROM price_snapshots_cold s
+                    WHERE s.token_address = t.address AND s.liquidity_usd > 0
+                    ORDER BY s.timestamp
+                    LIMIT 1
+                ) cold_first ON TRUE
+                LEFT JOIN LATERAL (
+                    SELECT MAX(s.liquidity_usd) AS liquidity_usd FROM price_snapshots s
+                    WHERE s.token_address = t.address
+                ) hot_peak ON TRUE
+                LEFT JOIN LATERAL (
+                    SELECT MAX(s.liquidity_usd) AS liquidity_usd FROM price_snapshots_cold s
+                    WHERE s.token_address = t.address
+                ) cold_peak ON TRUE
+                WHERE p.factory_address = $1 AND p.created_at >= $2
+            )
+            SELECT
+                COUNT(*) AS pairs,
+                COUNT(*) FILTER (
+                    WHERE EXISTS (SELECT 1 FROM lp_locks l WHERE l.pair_address = fp.address)
+                ) AS locked_pairs,
+                (percentile_cont(0.5) WITHIN GROUP (ORDER BY fp.initial_usd))::FLOAT8
+                    AS median_initial_liquidity_usd,
+                COUNT(*) FILTER (WHERE fp.peak_usd > 0) AS pairs_with_liquidity,
+                COUNT(*) FILTER (
+                    WHERE fp.peak_usd > 0
+                        AND COALESCE(fp.current_usd, 0) < fp.peak_usd * $3 / 100
+                ) AS rugged_pairs
+            FROM factory_pairs fp
+            "#,
+        )
+        .bind(factory)
+        .bind(since)
+        .bind(RUG_REMAINING_PERCENT)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Pairs `factory` created per day since `since`, days without any
+    /// included, oldest first
+    pub async fn factory_pairs_per_day<'c, E>(
+        factory: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Vec<FactoryDay>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, FactoryDay>(
+            r#"
+            SELECT d.day, COUNT(p.id) AS pairs
+            FROM generate_series(
+                date_trunc('day', $2::timestamptz), date_trunc('day', NOW()), INTERVAL '1 day'
+            ) AS d(day)
+            LEFT JOIN pairs p ON p.factory_address = $1
+                AND p.created_at >= GREATEST(d.day, $2)
+                AND p.created_at < d.day + INTERVAL '1 day'
+            GROUP BY d.day
+            ORDER BY d.day
+            "#,
+        )
+        .bind(factory)
+        .bind(since)
+        .fetch_all(connection)
+        .await
+    }
+
     /// Get the non-base token address (the memecoin, not WBNB)
-    pub fn get_token_address(&self) -> &str {
+    pub fn get_token_address(&self) -> &Address20 {
         match self.base_token_index {
             Some(0) => &self.token1_address,
             Some(1) => &self.token0_address,
@@ -140,11 +480,365 @@ impl Pair {
     }
 
     /// Get the base token address (WBNB/BUSD)
-    pub fn get_base_address(&self) -> &str {
+    pub fn get_base_address(&self) -> &Address20 {
         match self.base_token_index {
             Some(0) => &self.token0_address,
             Some(1) => &self.token1_address,
             _ => &self.token1_address, // Default fallback
         }
     }
+
+    /// Get (base reserve, token reserve), if both sides are known
+    pub fn get_reserves(&self) -> Option<(&BigDecimal, &BigDecimal)> {
+        let (base, token) = match self.base_token_index {
+            Some(0) => (&self.reserve0, &self.reserve1),
+            Some(1) => (&self.reserve1, &self.reserve0),
+            _ => return None,
+        };
+        base.as_ref().zip(token.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        swap::{NewSwap, Swap},
+        test_support::{address, clear_seed_data, hash},
+        token::{NewToken, Token},
+    };
+
+    fn new_pair(n: u8, base_token_index: i16) -> NewPair {
+        NewPair {
+            address: address(n),
+            token0_address: address(n + 1),
+            token1_address: address(n + 2),
+            factory_address: address(200),
+            base_token_index,
+            block_number: n as i64,
+            discovered: false,
+        }
+    }
+
+    #[sqlx::test]
+    async fn create_keeps_the_first_pair(pool: PgPool) {
+        Pair::create(&new_pair(10, 1), &pool).await.unwrap();
+
+        let again = Pair::create(&new_pair(10, 0), &pool).await;
+        assert!(matches!(again, Err(sqlx::Error::RowNotFound)));
+
+        let pair = Pair::find_by_address(&address(10), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pair.base_token_index, Some(1));
+    }
+
+    #[sqlx::test]
+    async fn lookups_ignore_the_address_spelling(pool: PgPool) {
+        // A pair stored from a checksummed source is found by a lowercase
+        // spelling, and the other way round: both parse to the same bytes
+        let checksummed: Address20 = "0x16b9a82891338f9bA80E2D6970FddA79D1eb0daE"
+            .parse()
+            .unwrap();
+        let pair = NewPair {
+            address: checksummed,
+            ..new_pair(10, 1)
+        };
+        Pair::create(&pair, &pool).await.unwrap();
+
+        for spelling in [
+            "0x16b9a82891338f9ba80e2d6970fdda79d1eb0dae",
+            "0X16B9A82891338F9BA80E2D6970FDDA79D1EB0DAE",
+        ] {
+            let address: Address20 = spelling.parse().unwrap();
+            let found = Pair::find_by_address(&address, &pool).await.unwrap();
+            assert_eq!(found.map(|p| p.address), Some(checksummed));
+        }
+        assert_eq!(
+            checksummed.to_string(),
+            "0x16b9a82891338f9ba80e2d6970fdda79d1eb0dae"
+        );
+    }
+
+    #[sqlx::test]
+    async fn find_by_tokens_matches_either_order(pool: PgPool) {
+        Pair::create(&new_pair(10, 1), &pool).await.unwrap();
+
+        let forward = Pair::find_by_tokens(&address(11), &address(12), &pool)
+            .await
+            .unwrap();
+        let backward = Pair::find_by_tokens(&address(12), &address(11), &pool)
+            .await
+            .unwrap();
+        assert_eq!(forward.map(|p| p.address), Some(address(10)));
+        assert_eq!(backward.map(|p| p.address), Some(address(10)));
+    }
+
+    #[sqlx::test]
+    async fn find_by_token_lists_the_tokens_base_pairs(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        // address(11) against address(12), then against address(30)
+        Pair::create(&new_pair(10, 1), &pool).await.unwrap();
+        let mut second = new_pair(20, 0);
+        second.token0_address = address(30);
+        second.token1_address = address(11);
+        Pair::create(&second, &pool).await.unwrap();
+        // address(11) as the base token of another pair
+        let mut based = new_pair(40, 0);
+        based.token0_address = address(11);
+        Pair::create(&based, &pool).await.unwrap();
+
+        let pairs = Pair::find_by_token(&address(11), &pool).await.unwrap();
+        let pairs: Vec<_> = pairs.iter().map(|p| p.address).collect();
+        assert_eq!(pairs, vec![address(10), address(20)]);
+    }
+
+    #[sqlx::test]
+    async fn update_reserves_and_recent(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        Pair::create(&new_pair(10, 1), &pool).await.unwrap();
+        Pair::create(&new_pair(20, 0), &pool).await.unwrap();
+
+        Pair::update_reserves(
+            &address(10),
+            &BigDecimal::from(5),
+            &BigDecimal::from(7),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let pair = Pair::find_by_address(&address(10), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pair.reserve0, Some(BigDecimal::from(5)));
+        assert_eq!(pair.reserve1, Some(BigDecimal::from(7)));
+
+        assert_eq!(Pair::find_recent(10, &pool).await.unwrap().len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn update_lp_total_supply_caches_the_supply(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        let pair = Pair::create(&new_pair(10, 1), &pool).await.unwrap();
+        assert_eq!(pair.lp_total_supply, None);
+
+        let supply: BigDecimal = "1000000000000000000000000".parse().unwrap();
+        Pair::update_lp_total_supply(&address(10), &supply, &pool)
+            .await
+            .unwrap();
+        let pair = Pair::find_by_address(&address(10), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pair.lp_total_supply, Some(supply));
+        assert!(pair.lp_supply_updated_at.is_some());
+    }
+
+    #[sqlx::test]
+    async fn volume_stats_rank_pairs_by_fees_and_apr(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        // Pair 10 trades token 11, pair 20 trades token 21
+        Pair::create(&new_pair(10, 1), &pool).await.unwrap();
+        Pair::create(&new_pair(20, 1), &pool).await.unwrap();
+        for (token, pair) in [(11, 10), (21, 20)] {
+            let new = NewToken {
+                address: address(token),
+                name: None,
+                symbol: Some(format!("T{}", token)),
+                name_raw: None,
+                symbol_raw: None,
+                name_spoofed: false,
+                decimals: Some(18),
+                total_supply: None,
+                pair_address: Some(address(pair)),
+                creator_address: None,
+                block_number: None,
+            };
+            Token::create(&new, &pool).await.unwrap();
+        }
+        sqlx::query("UPDATE tokens SET liquidity_usd = 100000 WHERE address = $1")
+            .bind(address(11))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for (n, pair, usd, hours_ago) in [
+            (1, 10, 1_000, 1),
+            (2, 10, 3_000, 2),
+            (3, 20, 1_000, 1),
+            (4, 10, 9_000, 30),
+        ] {
+            let swap = NewSwap {
+                tx_hash: hash(n),
+                block_number: n as i64,
+                log_index: 0,
+                timestamp: Utc::now() - Duration::hours(hours_ago),
+                pair_address: address(pair),
+                token_address: address(pair + 1),
+                wallet_address: address(50),
+                trade_type: "buy".to_string(),
+                amount_tokens: None,
+                amount_bnb: None,
+                amount_usd: Some(BigDecimal::from(usd)),
+                price_usd: None,
+                is_whale: false,
+                tx_index: None,
+                gas_price_percentile: None,
+                mev_flags: Vec::new(),
+                legs: None,
+                source_log_id: None,
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+
+        assert_eq!(Pair::refresh_volume_stats(25, &pool).await.unwrap(), 2);
+
+        let top = Pair::find_top(PairSort::Volume, 10, &pool).await.unwrap();
+        assert_eq!(top.len(), 2);
+        let busiest = &top[0];
+        assert_eq!(busiest.pair.address, address(10));
+        assert_eq!(busiest.pair.trades_24h, Some(2));
+        assert_eq!(busiest.pair.fees_24h_usd, Some(BigDecimal::from(10)));
+        assert_eq!(busiest.token_symbol.as_deref(), Some("T11"));
+        assert_eq!(busiest.volume_share, 80.0);
+        assert!((busiest.fee_apr.unwrap() - 3.65).abs() < 1e-9);
+        // No liquidity known for pair 20, so no APR
+        assert_eq!(top[1].fee_apr, None);
+
+        let by_apr = Pair::find_top(PairSort::Apr, 10, &pool).await.unwrap();
+        assert_eq!(by_apr[0].pair.address, address(10));
+    }
+
+    #[sqlx::test]
+    async fn factory_stats_cover_locks_liquidity_and_rugs(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        // Pairs 10, 20 and 30 trade tokens 11, 21 and 31
+        for n in [10, 20, 30] {
+            Pair::create(&new_pair(n, 1), &pool).await.unwrap();
+            let new = NewToken {
+                address: address(n + 1),
+                name: None,
+                symbol: None,
+                name_raw: None,
+                symbol_raw: None,
+                name_spoofed: false,
+                decimals: Some(18),
+                total_supply: None,
+                pair_address: Some(address(n)),
+                creator_address: None,
+                block_number: None,
+            };
+            Token::create(&new, &pool).await.unwrap();
+        }
+        // Another factory's pair stays out
+        Pair::create(
+            &NewPair {
+                factory_address: address(201),
+                ..new_pair(40, 1)
+            },
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        // Token 11 keeps its liquidity, token 21 is pulled to almost nothing;
+        // token 11's first snapshot has moved to the cold tier
+        for (table, token, hours_ago, usd) in [
+            ("price_snapshots_cold", 11, 3, 1_000),
+            ("price_snapshots", 11, 1, 4_000),
+            ("price_snapshots", 21, 2, 9_000),
+        ] {
+            sqlx::query(&format!(
+                "INSERT INTO {} (token_address, timestamp, liquidity_usd) VALUES ($1, $2, $3)",
+                table
+            ))
+            .bind(address(token))
+            .bind(Utc::now() - Duration::hours(hours_ago))
+            .bind(BigDecimal::from(usd))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        for (token, usd) in [(11, 3_500), (21, 50)] {
+            sqlx::query("UPDATE tokens SET liquidity_usd = $2 WHERE address = $1")
+                .bind(address(token))
+                .bind(BigDecimal::from(usd))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO lp_locks (token_address, pair_address, lock_contract) VALUES ($1, $2, $3)",
+        )
+        .bind(address(11))
+        .bind(address(10))
+        .bind(address(99))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let since = Utc::now() - Duration::days(7);
+        let stats = Pair::factory_stats(&address(200), since, &pool)
+            .await
+            .unwrap();
+        assert_eq!(stats.pairs, 3);
+        assert_eq!(stats.locked_pairs, 1);
+        assert_eq!(stats.median_initial_liquidity_usd, Some(5_000.0));
+        assert_eq!(stats.pairs_with_liquidity, 2);
+        assert_eq!(stats.rugged_pairs, 1);
+
+        let days = Pair::factory_pairs_per_day(&address(200), since, &pool)
+            .await
+            .unwrap();
+        assert_eq!(days.len(), 8);
+        assert_eq!(days.iter().map(|d| d.pairs).sum::<i64>(), 3);
+        assert_eq!(days.last().unwrap().pairs, 3);
+    }
+
+    #[test]
+    fn token_and_base_follow_base_token_index() {
+        let mut pair = Pair {
+            id: 1,
+            address: address(10),
+            token0_address: address(11),
+            token1_address: address(12),
+            factory_address: address(200),
+            reserve0: None,
+            reserve1: None,
+            base_token_index: Some(0),
+            block_number: 1,
+            discovered: false,
+            created_at: None,
+            last_updated: None,
+            lp_total_supply: None,
+            lp_supply_updated_at: None,
+            volume_24h_usd: None,
+            trades_24h: None,
+            fees_24h_usd: None,
+            stats_updated_at: None,
+        };
+        assert_eq!(pair.get_token_address(), &address(12));
+        assert_eq!(pair.get_base_address(), &address(11));
+
+        pair.base_token_index = Some(1);
+        assert_eq!(pair.get_token_address(), &address(11));
+        assert_eq!(pair.get_base_address(), &address(12));
+
+        assert_eq!(pair.get_reserves(), None);
+        pair.reserve0 = Some(BigDecimal::from(5));
+        pair.reserve1 = Some(BigDecimal::from(7));
+        assert_eq!(
+            pair.get_reserves(),
+            Some((&BigDecimal::from(7), &BigDecimal::from(5)))
+        );
+    }
 }
@@ -0,0 +1,210 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::{entity::evm_logs::EvmLogs, types::Address20};
+
+/// PendingSwap entity: a swap on a pair that isn't indexed, kept until it is
+#[derive(sqlx::FromRow, Debug)]
+pub struct PendingSwap {
+    /// The swap log as it was queued
+    #[sqlx(flatten)]
+    pub log: EvmLogs,
+    pub pair_address: Address20,
+    pub buffered_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PendingSwap {
+    /// Keep a swap log until its pair is indexed; one kept already (e.g.
+    /// redelivered by the queue) is left as is
+    pub async fn buffer<'c, E>(
+        log: &EvmLogs,
+        pair_address: &Address20,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO pending_swaps (id, block_number, block_hash, address, transaction_hash,
+                transaction_index, log_index, removed, data, event_signature, topics,
+                block_timestamp, created_at, pair_address)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            ON CONFLICT (transaction_hash, log_index) DO NOTHING
+            "#,
+        )
+        .bind(log.id)
+        .bind(&log.block_number)
+        .bind(log.block_hash.to_vec())
+        .bind(log.address.to_vec())
+        .bind(log.transaction_hash.to_vec())
+        .bind(log.transaction_index)
+        .bind(log.log_index)
+        .bind(log.removed)
+        .bind(&log.data)
+        .bind(log.event_signature.to_vec())
+        .bind(log.topics.iter().map(|t| t.to_vec()).collect::<Vec<_>>())
+        .bind(log.block_timestamp)
+        .bind(log.created_at)
+        .bind(pair_address)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Kept swaps whose pair has been indexed since, in chain order
+    pub async fn find_attributable<'c, E>(
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<PendingSwap>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, PendingSwap>(
+            r#"
+            SELECT ps.* FROM pending_swaps ps
+            JOIN pairs p ON p.address = ps.pair_address
+            ORDER BY ps.block_number, ps.log_index
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Drop a kept swap once it is attributed
+    pub async fn delete<'c, E>(log: &EvmLogs, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("DELETE FROM pending_swaps WHERE transaction_hash = $1 AND log_index = $2")
+            .bind(log.transaction_hash.to_vec())
+            .bind(log.log_index)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete up to `limit` swaps kept since before `cutoff`; their pairs
+    /// never turned up
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM pending_swaps
+            WHERE (transaction_hash, log_index) IN (
+                SELECT transaction_hash, log_index FROM pending_swaps
+                WHERE buffered_at < $1
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, b256, Bytes, B256},
+        rpc::types::Log,
+    };
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        pair::{NewPair, Pair},
+        test_support::{address, clear_seed_data},
+    };
+
+    fn swap(block: u64, log_index: u64) -> EvmLogs {
+        let log = Log {
+            inner: alloy::primitives::Log::new(
+                address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73"),
+                vec![b256!(
+                    "d78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"
+                )],
+                Bytes::from(vec![0xab; 128]),
+            )
+            .unwrap(),
+            block_hash: Some(B256::repeat_byte(block as u8)),
+            block_number: Some(block),
+            block_timestamp: None,
+            transaction_hash: Some(B256::with_last_byte(block as u8)),
+            transaction_index: Some(0),
+            log_index: Some(log_index),
+            removed: false,
+        };
+        EvmLogs::from_rpc_log(&log).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn swaps_are_kept_until_their_pair_is_indexed(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let (old, other) = (address(1), address(2));
+
+        PendingSwap::buffer(&swap(12, 0), &old, &pool).await.unwrap();
+        PendingSwap::buffer(&swap(10, 3), &old, &pool).await.unwrap();
+        // Redelivered
+        PendingSwap::buffer(&swap(12, 0), &old, &pool).await.unwrap();
+        PendingSwap::buffer(&swap(11, 0), &other, &pool).await.unwrap();
+        assert!(PendingSwap::find_attributable(10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let pair = NewPair {
+            address: old,
+            token0_address: address(10),
+            token1_address: address(11),
+            factory_address: address(200),
+            base_token_index: 0,
+            block_number: 9,
+            discovered: false,
+        };
+        Pair::create(&pair, &pool).await.unwrap();
+
+        let ready = PendingSwap::find_attributable(10, &pool).await.unwrap();
+        let ready: Vec<_> = ready
+            .iter()
+            .map(|s| {
+                (
+                    s.pair_address,
+                    s.log.block_number.to_string(),
+                    s.log.log_index,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ready,
+            vec![(old, "10".to_string(), 3), (old, "12".to_string(), 0)]
+        );
+
+        PendingSwap::delete(&swap(10, 3), &pool).await.unwrap();
+        assert_eq!(
+            PendingSwap::find_attributable(10, &pool)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let removed = PendingSwap::delete_older_than(Utc::now(), 100, &pool)
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+    }
+}
@@ -0,0 +1,249 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::{entity::price_snapshot::NewPriceSnapshot, types::Address20};
+
+/// QuarantinedSnapshot entity: a price point held back from the chart until
+/// later blocks confirm or reject it
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct QuarantinedSnapshot {
+    pub id: i32,
+    pub token_address: Address20,
+    pub pair_address: Address20,
+    pub block_number: i64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub price_usd: Option<BigDecimal>,
+    pub price_bnb: BigDecimal,
+    pub liquidity_usd: Option<BigDecimal>,
+    pub market_cap_usd: Option<BigDecimal>,
+    pub holder_count: Option<i32>,
+    /// Median BNB price of the recent snapshots it was measured against
+    pub reference_price_bnb: BigDecimal,
+    pub change_percent: BigDecimal,
+    /// `pending`, `confirmed` or `rejected`
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub resolved_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Input for quarantining a price point
+#[derive(Debug, Clone)]
+pub struct NewQuarantinedSnapshot {
+    pub snapshot: NewPriceSnapshot,
+    pub pair_address: Address20,
+    pub block_number: i64,
+    pub reference_price_bnb: BigDecimal,
+    pub change_percent: BigDecimal,
+}
+
+impl QuarantinedSnapshot {
+    /// Hold a price point back from the chart
+    pub async fn create<'c, E>(
+        quarantined: &NewQuarantinedSnapshot,
+        connection: E,
+    ) -> Result<QuarantinedSnapshot, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let snapshot = &quarantined.snapshot;
+        let query = r#"
+            INSERT INTO price_snapshot_quarantine (
+                token_address, pair_address, block_number, timestamp,
+                price_usd, price_bnb, liquidity_usd, market_cap_usd, holder_count,
+                reference_price_bnb, change_percent
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, QuarantinedSnapshot>(query)
+            .bind(snapshot.token_address)
+            .bind(quarantined.pair_address)
+            .bind(quarantined.block_number)
+            .bind(snapshot.timestamp)
+            .bind(&snapshot.price_usd)
+            .bind(snapshot.price_bnb.clone().unwrap_or_default())
+            .bind(&snapshot.liquidity_usd)
+            .bind(&snapshot.market_cap_usd)
+            .bind(snapshot.holder_count)
+            .bind(&quarantined.reference_price_bnb)
+            .bind(&quarantined.change_percent)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// A token's points awaiting confirmation, oldest block first
+    pub async fn find_pending<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<Vec<QuarantinedSnapshot>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, QuarantinedSnapshot>(
+            r#"
+            SELECT * FROM price_snapshot_quarantine
+            WHERE token_address = $1 AND status = 'pending'
+            ORDER BY block_number, id
+            "#,
+        )
+        .bind(token_address)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Chart a token's pending points: copy them into `price_snapshots` and
+    /// mark them confirmed. Returns how many were confirmed.
+    pub async fn confirm_pending<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            WITH confirmed AS (
+                UPDATE price_snapshot_quarantine
+                SET status = 'confirmed', resolved_at = NOW()
+                WHERE token_address = $1 AND status = 'pending'
+                RETURNING *
+            ),
+            -- The last point per timestamp, as create() would have kept
+            latest AS (
+                SELECT DISTINCT ON (timestamp) * FROM confirmed
+                ORDER BY timestamp, block_number DESC, id DESC
+            )
+            INSERT INTO price_snapshots (
+                token_address, timestamp, price_usd, price_bnb,
+                liquidity_usd, market_cap_usd, holder_count
+            )
+            SELECT token_address, timestamp, price_usd, price_bnb,
+                   liquidity_usd, market_cap_usd, holder_count
+            FROM latest
+            ON CONFLICT (token_address, timestamp) DO UPDATE SET
+                price_usd = EXCLUDED.price_usd,
+                price_bnb = EXCLUDED.price_bnb,
+                liquidity_usd = EXCLUDED.liquidity_usd,
+                market_cap_usd = EXCLUDED.market_cap_usd,
+                holder_count = EXCLUDED.holder_count
+            "#,
+        )
+        .bind(token_address)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Drop a token's pending points from consideration. Returns how many
+    /// were rejected.
+    pub async fn reject_pending<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            UPDATE price_snapshot_quarantine
+            SET status = 'rejected', resolved_at = NOW()
+            WHERE token_address = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(token_address)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{price_snapshot::PriceSnapshot, test_support::address};
+
+    fn quarantined(
+        token: u8,
+        block_number: i64,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        price: i32,
+    ) -> NewQuarantinedSnapshot {
+        NewQuarantinedSnapshot {
+            snapshot: NewPriceSnapshot {
+                token_address: address(token),
+                timestamp,
+                price_usd: Some(BigDecimal::from(price * 600)),
+                price_bnb: Some(BigDecimal::from(price)),
+                liquidity_usd: None,
+                volume_usd: None,
+                market_cap_usd: None,
+                holder_count: None,
+            },
+            pair_address: address(100 + token),
+            block_number,
+            reference_price_bnb: BigDecimal::from(1),
+            change_percent: BigDecimal::from((price - 1) * 100),
+        }
+    }
+
+    #[sqlx::test]
+    async fn pending_points_are_confirmed_or_rejected(pool: PgPool) {
+        let at = Utc::now() - Duration::minutes(10);
+        for (block, seconds, price) in [(100, 0, 50), (101, 3, 52), (101, 3, 51)] {
+            let point = quarantined(1, block, at + Duration::seconds(seconds), price);
+            QuarantinedSnapshot::create(&point, &pool).await.unwrap();
+        }
+        QuarantinedSnapshot::create(&quarantined(2, 100, at, 1_000_000), &pool)
+            .await
+            .unwrap();
+
+        let pending = QuarantinedSnapshot::find_pending(&address(1), &pool)
+            .await
+            .unwrap();
+        let blocks: Vec<_> = pending.iter().map(|p| p.block_number).collect();
+        assert_eq!(blocks, vec![100, 101, 101]);
+        assert!(PriceSnapshot::find_latest(&address(1), &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        // One snapshot per timestamp, the later Sync winning
+        let charted = QuarantinedSnapshot::confirm_pending(&address(1), &pool)
+            .await
+            .unwrap();
+        assert_eq!(charted, 2);
+        let chart = PriceSnapshot::find_by_token(&address(1), 10, &pool)
+            .await
+            .unwrap();
+        let mut prices: Vec<_> = chart.into_iter().filter_map(|s| s.price_bnb).collect();
+        prices.sort();
+        assert_eq!(prices, vec![BigDecimal::from(50), BigDecimal::from(51)]);
+        assert!(QuarantinedSnapshot::find_pending(&address(1), &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let rejected = QuarantinedSnapshot::reject_pending(&address(2), &pool)
+            .await
+            .unwrap();
+        assert_eq!(rejected, 1);
+        assert!(PriceSnapshot::find_latest(&address(2), &pool)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            QuarantinedSnapshot::confirm_pending(&address(2), &pool)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -4,11 +4,13 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use crate::types::Address20;
+
 /// PriceSnapshot entity for historical price charts
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct PriceSnapshot {
     pub id: i32,
-    pub token_address: String,
+    pub token_address: Address20,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub price_usd: Option<BigDecimal>,
     pub price_bnb: Option<BigDecimal>,
@@ -18,10 +20,32 @@ pub struct PriceSnapshot {
     pub holder_count: Option<i32>,
 }
 
+/// Prices, liquidity and volume aggregated over one chart interval
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct PriceBucket {
+    /// Start of the interval
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Last price in the interval
+    pub price_usd: Option<BigDecimal>,
+    pub avg_price_usd: Option<BigDecimal>,
+    /// Highest liquidity in the interval
+    pub liquidity_usd: Option<BigDecimal>,
+    pub volume_usd: BigDecimal,
+}
+
+/// A token's last price in one hour
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct HourlyClose {
+    pub token_address: Address20,
+    /// Start of the hour
+    pub hour: chrono::DateTime<chrono::Utc>,
+    pub price_usd: BigDecimal,
+}
+
 /// Input for creating a new price snapshot
 #[derive(Debug, Clone)]
 pub struct NewPriceSnapshot {
-    pub token_address: String,
+    pub token_address: Address20,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub price_usd: Option<BigDecimal>,
     pub price_bnb: Option<BigDecimal>,
@@ -57,7 +81,7 @@ impl PriceSnapshot {
         "#;
 
         sqlx::query_as::<_, PriceSnapshot>(query)
-            .bind(&snapshot.token_address)
+            .bind(snapshot.token_address)
             .bind(snapshot.timestamp)
             .bind(&snapshot.price_usd)
             .bind(&snapshot.price_bnb)
@@ -71,7 +95,7 @@ impl PriceSnapshot {
 
     /// Get price history for a token
     pub async fn find_by_token<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         limit: i32,
         connection: E,
     ) -> Result<Vec<PriceSnapshot>, sqlx::Error>
@@ -89,7 +113,7 @@ impl PriceSnapshot {
 
     /// Get price history within a time range
     pub async fn find_in_range<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         start: chrono::DateTime<chrono::Utc>,
         end: chrono::DateTime<chrono::Utc>,
         connection: E,
@@ -111,9 +135,75 @@ impl PriceSnapshot {
         .await
     }
 
+    /// Get price history within a time range in intervals of `bucket_secs`
+    pub async fn find_bucketed<'c, E>(
+        token_address: &Address20,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        bucket_secs: i64,
+        connection: E,
+    ) -> Result<Vec<PriceBucket>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, PriceBucket>(
+            r#"
+            SELECT
+                to_timestamp(floor(extract(epoch FROM timestamp) / $4) * $4) AS timestamp,
+                (array_agg(price_usd ORDER BY timestamp DESC)
+                    FILTER (WHERE price_usd IS NOT NULL))[1] AS price_usd,
+                round(AVG(price_usd), 18) AS avg_price_usd,
+                MAX(liquidity_usd) AS liquidity_usd,
+                COALESCE(SUM(volume_usd), 0) AS volume_usd
+            FROM price_snapshots
+            WHERE token_address = $1 AND timestamp >= $2 AND timestamp <= $3
+            GROUP BY 1
+            ORDER BY 1 ASC
+            "#,
+        )
+        .bind(token_address)
+        .bind(start)
+        .bind(end)
+        .bind(bucket_secs)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Hourly closing prices of each of `addresses` from `start` to `end`,
+    /// by token then hour. Hours without a priced snapshot are left out.
+    pub async fn find_hourly_closes<'c, E>(
+        addresses: &[Address20],
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Vec<HourlyClose>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, HourlyClose>(
+            r#"
+            SELECT
+                token_address,
+                to_timestamp(floor(extract(epoch FROM timestamp) / 3600) * 3600) AS hour,
+                (array_agg(price_usd ORDER BY timestamp DESC))[1] AS price_usd
+            FROM price_snapshots
+            WHERE token_address = ANY($1)
+              AND timestamp >= $2 AND timestamp <= $3
+              AND price_usd IS NOT NULL
+            GROUP BY 1, 2
+            ORDER BY 1, 2
+            "#,
+        )
+        .bind(addresses)
+        .bind(start)
+        .bind(end)
+        .fetch_all(connection)
+        .await
+    }
+
     /// Get latest snapshot for a token
     pub async fn find_latest<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<Option<PriceSnapshot>, sqlx::Error>
     where
@@ -127,9 +217,35 @@ impl PriceSnapshot {
         .await
     }
 
+    /// Median BNB price of a token's `window` most recent priced snapshots
+    pub async fn find_recent_median_bnb<'c, E>(
+        token_address: &Address20,
+        window: i64,
+        connection: E,
+    ) -> Result<Option<f64>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar::<_, Option<f64>>(
+            r#"
+            SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY price_bnb::FLOAT8)
+            FROM (
+                SELECT price_bnb FROM price_snapshots
+                WHERE token_address = $1 AND price_bnb > 0
+                ORDER BY timestamp DESC
+                LIMIT $2
+            ) recent
+            "#,
+        )
+        .bind(token_address)
+        .bind(window)
+        .fetch_one(connection)
+        .await
+    }
+
     /// Get 1 hour ago snapshot for price change calculation
     pub async fn find_1h_ago<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<Option<PriceSnapshot>, sqlx::Error>
     where
@@ -148,21 +264,288 @@ impl PriceSnapshot {
         .await
     }
 
-    /// Delete old snapshots (for cleanup)
-    pub async fn delete_old<'c, E>(
-        older_than_days: i32,
+    /// Get the priced snapshot nearest to `at`, before or after, at most
+    /// `max_gap_secs` away
+    pub async fn find_closest<'c, E>(
+        token_address: &Address20,
+        at: chrono::DateTime<chrono::Utc>,
+        max_gap_secs: i64,
+        connection: E,
+    ) -> Result<Option<PriceSnapshot>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, PriceSnapshot>(
+            r#"
+            SELECT * FROM (
+                (SELECT * FROM price_snapshots
+                 WHERE token_address = $1 AND timestamp <= $2 AND price_usd IS NOT NULL
+                 ORDER BY timestamp DESC LIMIT 1)
+                UNION ALL
+                (SELECT * FROM price_snapshots
+                 WHERE token_address = $1 AND timestamp > $2 AND price_usd IS NOT NULL
+                 ORDER BY timestamp ASC LIMIT 1)
+            ) nearest
+            WHERE ABS(EXTRACT(EPOCH FROM nearest.timestamp - $2)) <= $3
+            ORDER BY ABS(EXTRACT(EPOCH FROM nearest.timestamp - $2))
+            LIMIT 1
+            "#,
+        )
+        .bind(token_address)
+        .bind(at)
+        .bind(max_gap_secs)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Downsample snapshots older than `cutoff` to the latest one per token per
+    /// hour, deleting up to `limit` of the others. Only hours from `since` on
+    /// are ranked, e.g. the ones past the cutoff of the last pass; every hour
+    /// before `cutoff` when `None`.
+    pub async fn downsample_older_than<'c, E>(
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
         connection: E,
     ) -> Result<u64, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
         let result = sqlx::query(
-            "DELETE FROM price_snapshots WHERE timestamp < NOW() - ($1 || ' days')::INTERVAL",
+            r#"
+            DELETE FROM price_snapshots
+            WHERE id IN (
+                SELECT id FROM (
+                    SELECT id, ROW_NUMBER() OVER (
+                        PARTITION BY token_address, date_trunc('hour', timestamp)
+                        ORDER BY timestamp DESC
+                    ) AS rn
+                    FROM price_snapshots
+                    WHERE timestamp < date_trunc('hour', $1::TIMESTAMPTZ)
+                        AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= date_trunc('hour', $3))
+                ) ranked
+                WHERE rn > 1
+                LIMIT $2
+            )
+            "#,
         )
-        .bind(older_than_days)
+        .bind(cutoff)
+        .bind(limit)
+        .bind(since)
         .execute(connection)
         .await?;
 
         Ok(result.rows_affected())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::test_support::address;
+
+    fn snapshot(timestamp: chrono::DateTime<chrono::Utc>, price: i32) -> NewPriceSnapshot {
+        NewPriceSnapshot {
+            token_address: address(1),
+            timestamp,
+            price_usd: Some(BigDecimal::from(price)),
+            price_bnb: None,
+            liquidity_usd: None,
+            volume_usd: None,
+            market_cap_usd: None,
+            holder_count: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn create_overwrites_same_timestamp(pool: PgPool) {
+        let at = Utc::now();
+        let first = PriceSnapshot::create(&snapshot(at, 1), &pool)
+            .await
+            .unwrap();
+        let second = PriceSnapshot::create(&snapshot(at, 2), &pool)
+            .await
+            .unwrap();
+
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.price_usd, Some(BigDecimal::from(2)));
+        assert_eq!(
+            PriceSnapshot::find_by_token(&address(1), 10, &pool)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[sqlx::test]
+    async fn lookups_by_age(pool: PgPool) {
+        let now = Utc::now();
+        for (hours_ago, price) in [(0, 10), (2, 8), (24 * 40, 1)] {
+            PriceSnapshot::create(&snapshot(now - Duration::hours(hours_ago), price), &pool)
+                .await
+                .unwrap();
+        }
+
+        let latest = PriceSnapshot::find_latest(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.price_usd, Some(BigDecimal::from(10)));
+
+        let hour_ago = PriceSnapshot::find_1h_ago(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hour_ago.price_usd, Some(BigDecimal::from(8)));
+
+        let range = PriceSnapshot::find_in_range(&address(1), now - Duration::hours(3), now, &pool)
+            .await
+            .unwrap();
+        let prices: Vec<_> = range.into_iter().filter_map(|s| s.price_usd).collect();
+        assert_eq!(prices, vec![BigDecimal::from(8), BigDecimal::from(10)]);
+
+        let closest = |at, max_gap_secs| {
+            let pool = pool.clone();
+            async move {
+                PriceSnapshot::find_closest(&address(1), at, max_gap_secs, &pool)
+                    .await
+                    .unwrap()
+                    .and_then(|s| s.price_usd)
+            }
+        };
+        // Nearest on either side of the block time
+        assert_eq!(
+            closest(now - Duration::minutes(50), 3600).await,
+            Some(BigDecimal::from(10))
+        );
+        assert_eq!(
+            closest(now - Duration::minutes(80), 3600).await,
+            Some(BigDecimal::from(8))
+        );
+        // Too far from any snapshot
+        assert_eq!(closest(now - Duration::hours(12), 3600).await, None);
+    }
+
+    #[sqlx::test]
+    async fn recent_median_covers_the_latest_window(pool: PgPool) {
+        assert_eq!(
+            PriceSnapshot::find_recent_median_bnb(&address(1), 3, &pool)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let now = Utc::now();
+        for (minutes_ago, price) in [(40, 100), (30, 4), (20, 2), (10, 3), (0, 1_000)] {
+            let priced = NewPriceSnapshot {
+                price_bnb: Some(BigDecimal::from(price)),
+                ..snapshot(now - Duration::minutes(minutes_ago), price)
+            };
+            PriceSnapshot::create(&priced, &pool).await.unwrap();
+        }
+
+        let median = PriceSnapshot::find_recent_median_bnb(&address(1), 4, &pool)
+            .await
+            .unwrap();
+        assert_eq!(median, Some(3.5));
+    }
+
+    #[sqlx::test]
+    async fn hourly_closes_take_the_last_price_per_hour(pool: PgPool) {
+        let hour = ::chrono::DurationRound::duration_trunc(
+            Utc::now() - Duration::hours(5),
+            Duration::hours(1),
+        )
+        .unwrap();
+        for (minutes, price) in [(5, 1), (50, 2), (130, 3)] {
+            PriceSnapshot::create(&snapshot(hour + Duration::minutes(minutes), price), &pool)
+                .await
+                .unwrap();
+        }
+        let other = NewPriceSnapshot {
+            token_address: address(2),
+            ..snapshot(hour + Duration::minutes(10), 9)
+        };
+        PriceSnapshot::create(&other, &pool).await.unwrap();
+        let unpriced = NewPriceSnapshot {
+            price_usd: None,
+            ..snapshot(hour + Duration::minutes(70), 0)
+        };
+        PriceSnapshot::create(&unpriced, &pool).await.unwrap();
+
+        let addresses = [address(1), address(2)];
+        let closes: Vec<_> = PriceSnapshot::find_hourly_closes(&addresses, hour, Utc::now(), &pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.token_address, c.hour, c.price_usd))
+            .collect();
+        assert_eq!(
+            closes,
+            vec![
+                (address(1), hour, BigDecimal::from(2)),
+                (address(1), hour + Duration::hours(2), BigDecimal::from(3)),
+                (address(2), hour, BigDecimal::from(9)),
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn downsampling_keeps_the_latest_snapshot_per_old_hour(pool: PgPool) {
+        let old_hour = ::chrono::DurationRound::duration_trunc(
+            Utc::now() - Duration::days(10),
+            Duration::hours(1),
+        )
+        .unwrap();
+        for (minutes, price) in [(5, 1), (20, 2), (50, 3), (70, 4)] {
+            PriceSnapshot::create(&snapshot(old_hour + Duration::minutes(minutes), price), &pool)
+                .await
+                .unwrap();
+        }
+        let now = Utc::now();
+        for (minutes_ago, price) in [(1, 5), (2, 6)] {
+            PriceSnapshot::create(&snapshot(now - Duration::minutes(minutes_ago), price), &pool)
+                .await
+                .unwrap();
+        }
+
+        let cutoff = now - Duration::days(7);
+        // Hours before `since` aren't looked at
+        let since = old_hour + Duration::hours(1);
+        assert_eq!(
+            PriceSnapshot::downsample_older_than(Some(since), cutoff, 100, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+        // Batches stop at `limit`
+        assert_eq!(
+            PriceSnapshot::downsample_older_than(Some(old_hour), cutoff, 1, &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            PriceSnapshot::downsample_older_than(None, cutoff, 100, &pool)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let prices: Vec<_> = PriceSnapshot::find_by_token(&address(1), 10, &pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|s| s.price_usd)
+            .collect();
+        assert_eq!(
+            prices,
+            [5, 6, 4, 3].map(BigDecimal::from).to_vec(),
+            "recent snapshots and the last of each old hour survive"
+        );
+    }
+}
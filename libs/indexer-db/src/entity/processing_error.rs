@@ -0,0 +1,188 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Hash32;
+
+/// Where processing a log failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorStage {
+    /// The log's data couldn't be decoded
+    Decode,
+    /// The decoded event's handler returned an error
+    Handler,
+}
+
+impl ErrorStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorStage::Decode => "decode",
+            ErrorStage::Handler => "handler",
+        }
+    }
+}
+
+/// ProcessingError entity: a log the processor failed to decode or handle
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ProcessingError {
+    pub id: i64,
+    pub log_id: String,
+    pub topic: String,
+    pub tx_hash: Hash32,
+    pub log_index: i64,
+    pub stage: String, // "decode" or "handler"
+    pub error: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for recording a failed log
+#[derive(Debug, Clone)]
+pub struct NewProcessingError {
+    pub log_id: String,
+    pub topic: String,
+    pub tx_hash: Hash32,
+    pub log_index: i64,
+    pub stage: ErrorStage,
+    pub error: String,
+}
+
+impl ProcessingError {
+    /// Record a failed log
+    pub async fn create<'c, E>(
+        error: &NewProcessingError,
+        connection: E,
+    ) -> Result<ProcessingError, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO processing_errors (log_id, topic, tx_hash, log_index, stage, error)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ProcessingError>(query)
+            .bind(&error.log_id)
+            .bind(&error.topic)
+            .bind(error.tx_hash)
+            .bind(error.log_index)
+            .bind(error.stage.as_str())
+            .bind(&error.error)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Errors recorded after `since`, oldest first so the last one's time
+    /// pages forward; the latest errors, newest first, when `None`
+    pub async fn find_since<'c, E>(
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<ProcessingError>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let order = if since.is_some() { "ASC" } else { "DESC" };
+        let query = format!(
+            r#"
+            SELECT * FROM processing_errors
+            WHERE $1::timestamptz IS NULL OR created_at > $1
+            ORDER BY created_at {order}, id {order}
+            LIMIT $2
+            "#,
+        );
+
+        sqlx::query_as::<_, ProcessingError>(&query)
+            .bind(since)
+            .bind(limit)
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Delete up to `limit` errors recorded before `cutoff`
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM processing_errors
+            WHERE id IN (SELECT id FROM processing_errors WHERE created_at < $1 LIMIT $2)
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+
+    fn failed(log_id: &str, stage: ErrorStage) -> NewProcessingError {
+        NewProcessingError {
+            log_id: log_id.to_string(),
+            topic: format!("0x{}", "d7".repeat(32)),
+            tx_hash: Hash32::new([1; 32]),
+            log_index: 3,
+            stage,
+            error: "Insufficient data".to_string(),
+        }
+    }
+
+    #[sqlx::test]
+    async fn errors_are_listed_since_a_time_and_trimmed(pool: PgPool) {
+        let first = ProcessingError::create(&failed("1", ErrorStage::Decode), &pool)
+            .await
+            .unwrap();
+        assert_eq!(first.stage, "decode");
+        assert_eq!(first.tx_hash, Hash32::new([1; 32]));
+        ProcessingError::create(&failed("2", ErrorStage::Handler), &pool)
+            .await
+            .unwrap();
+
+        let all = ProcessingError::find_since(None, 10, &pool).await.unwrap();
+        let ids: Vec<_> = all.iter().map(|e| e.log_id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "1"]);
+
+        let newer = ProcessingError::find_since(Some(first.created_at), 10, &pool)
+            .await
+            .unwrap();
+        assert!(newer.iter().all(|e| e.log_id == "2"));
+
+        // Paging forward returns the oldest errors past `since` first
+        let third = ProcessingError::create(&failed("3", ErrorStage::Handler), &pool)
+            .await
+            .unwrap();
+        let since = first.created_at - Duration::seconds(1);
+        let page = ProcessingError::find_since(Some(since), 2, &pool)
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.iter().map(|e| e.log_id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2"]);
+        let next = ProcessingError::find_since(Some(page[1].created_at), 2, &pool)
+            .await
+            .unwrap();
+        assert!(next.iter().any(|e| e.id == third.id));
+
+        assert_eq!(
+            ProcessingError::delete_older_than(Utc::now() + Duration::hours(1), 100, &pool)
+                .await
+                .unwrap(),
+            3
+        );
+        assert!(ProcessingError::find_since(None, 10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+/// ProcessingLag entity: the latest lag measurement window of one event type
+/// against its SLO target
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ProcessingLag {
+    pub event_type: String, // "new_pair", "swap", "transfer", "sync", "liquidity"
+    pub target_secs: i32,
+    /// Logs measured in the window
+    pub samples: i32,
+    pub p50_lag_ms: i64,
+    pub p95_lag_ms: i64,
+    pub max_lag_ms: i64,
+    /// Start of the current run of windows whose p95 missed the target
+    pub breached_since: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for recording a measurement window
+#[derive(Debug, Clone)]
+pub struct NewProcessingLag {
+    pub event_type: String,
+    pub target_secs: i32,
+    pub samples: i32,
+    pub p50_lag_ms: i64,
+    pub p95_lag_ms: i64,
+    pub max_lag_ms: i64,
+    pub breached_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ProcessingLag {
+    /// Replace the event type's window with a newer one
+    pub async fn upsert<'c, E>(
+        lag: &NewProcessingLag,
+        connection: E,
+    ) -> Result<ProcessingLag, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO processing_lag (
+                event_type, target_secs, samples, p50_lag_ms, p95_lag_ms, max_lag_ms,
+                breached_since, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+            ON CONFLICT (event_type) DO UPDATE SET
+                target_secs = EXCLUDED.target_secs,
+                samples = EXCLUDED.samples,
+                p50_lag_ms = EXCLUDED.p50_lag_ms,
+                p95_lag_ms = EXCLUDED.p95_lag_ms,
+                max_lag_ms = EXCLUDED.max_lag_ms,
+                breached_since = EXCLUDED.breached_since,
+                updated_at = NOW()
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ProcessingLag>(query)
+            .bind(&lag.event_type)
+            .bind(lag.target_secs)
+            .bind(lag.samples)
+            .bind(lag.p50_lag_ms)
+            .bind(lag.p95_lag_ms)
+            .bind(lag.max_lag_ms)
+            .bind(lag.breached_since)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Every event type's latest window, by event type
+    pub async fn find_all<'c, E>(connection: E) -> Result<Vec<ProcessingLag>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ProcessingLag>("SELECT * FROM processing_lag ORDER BY event_type")
+            .fetch_all(connection)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+
+    fn window(event_type: &str, p95_lag_ms: i64) -> NewProcessingLag {
+        NewProcessingLag {
+            event_type: event_type.to_string(),
+            target_secs: 30,
+            samples: 10,
+            p50_lag_ms: p95_lag_ms / 2,
+            p95_lag_ms,
+            max_lag_ms: p95_lag_ms * 2,
+            breached_since: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn latest_window_replaces_the_previous_one(pool: PgPool) {
+        ProcessingLag::upsert(&window("swap", 4_000), &pool)
+            .await
+            .unwrap();
+        ProcessingLag::upsert(&window("new_pair", 2_000), &pool)
+            .await
+            .unwrap();
+
+        let since = Utc::now();
+        let breached = NewProcessingLag {
+            breached_since: Some(since),
+            ..window("swap", 90_000)
+        };
+        let updated = ProcessingLag::upsert(&breached, &pool).await.unwrap();
+        assert_eq!(updated.p95_lag_ms, 90_000);
+        assert!(updated.breached_since.is_some());
+
+        let all = ProcessingLag::find_all(&pool).await.unwrap();
+        let found: Vec<_> = all
+            .iter()
+            .map(|l| (l.event_type.as_str(), l.p95_lag_ms))
+            .collect();
+        assert_eq!(found, vec![("new_pair", 2_000), ("swap", 90_000)]);
+
+        assert!(ProcessingLag::upsert(&window("approval", 1), &pool)
+            .await
+            .is_err());
+    }
+}
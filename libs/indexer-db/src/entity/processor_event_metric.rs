@@ -0,0 +1,157 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+/// Upper bounds (ms) of the handler latency buckets; counts carry one more
+/// bucket for anything slower
+pub const LATENCY_BUCKETS_MS: [i64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1_000, 5_000];
+
+/// ProcessorEventMetric entity: an event type's processing counters since
+/// they were first recorded
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ProcessorEventMetric {
+    pub event_type: String, // "new_pair", "swap", ..., "unknown"
+    pub events: i64,
+    pub decode_failures: i64,
+    pub handler_errors: i64,
+    /// Logs of topics without a decoder
+    pub skipped: i64,
+    /// Handler latency counts per [`LATENCY_BUCKETS_MS`] bucket, then the
+    /// open-ended one
+    pub latency_buckets: Vec<i64>,
+    pub latency_sum_ms: i64,
+    /// Start of the current run of windows over the error rate limit
+    pub failing_since: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Counts to add to an event type's totals
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventCounts {
+    pub event_type: String,
+    pub events: i64,
+    pub decode_failures: i64,
+    pub handler_errors: i64,
+    pub skipped: i64,
+    pub latency_buckets: Vec<i64>,
+    pub latency_sum_ms: i64,
+    pub failing_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ProcessorEventMetric {
+    /// Add `counts` to the event type's totals, bucket by bucket, and replace
+    /// its `failing_since`
+    pub async fn add<'c, E>(
+        counts: &EventCounts,
+        connection: E,
+    ) -> Result<ProcessorEventMetric, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO processor_event_metrics AS m (
+                event_type, events, decode_failures, handler_errors, skipped,
+                latency_buckets, latency_sum_ms, failing_since, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            ON CONFLICT (event_type) DO UPDATE SET
+                events = m.events + EXCLUDED.events,
+                decode_failures = m.decode_failures + EXCLUDED.decode_failures,
+                handler_errors = m.handler_errors + EXCLUDED.handler_errors,
+                skipped = m.skipped + EXCLUDED.skipped,
+                latency_buckets = ARRAY(
+                    SELECT COALESCE(old, 0) + COALESCE(new, 0)
+                    FROM unnest(m.latency_buckets, EXCLUDED.latency_buckets) AS b(old, new)
+                ),
+                latency_sum_ms = m.latency_sum_ms + EXCLUDED.latency_sum_ms,
+                failing_since = EXCLUDED.failing_since,
+                updated_at = NOW()
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ProcessorEventMetric>(query)
+            .bind(&counts.event_type)
+            .bind(counts.events)
+            .bind(counts.decode_failures)
+            .bind(counts.handler_errors)
+            .bind(counts.skipped)
+            .bind(&counts.latency_buckets)
+            .bind(counts.latency_sum_ms)
+            .bind(counts.failing_since)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Every event type's totals, by event type
+    pub async fn find_all<'c, E>(connection: E) -> Result<Vec<ProcessorEventMetric>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ProcessorEventMetric>(
+            "SELECT * FROM processor_event_metrics ORDER BY event_type",
+        )
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+
+    fn counts(event_type: &str, events: i64, errors: i64) -> EventCounts {
+        EventCounts {
+            event_type: event_type.to_string(),
+            events,
+            decode_failures: 0,
+            handler_errors: errors,
+            skipped: 0,
+            latency_buckets: vec![events - 1, 1, 0],
+            latency_sum_ms: events * 10,
+            failing_since: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn counts_accumulate_per_event_type(pool: PgPool) {
+        ProcessorEventMetric::add(&counts("swap", 10, 0), &pool)
+            .await
+            .unwrap();
+        ProcessorEventMetric::add(&counts("approval", 2, 0), &pool)
+            .await
+            .unwrap();
+
+        let since = Utc::now();
+        let failing = EventCounts {
+            decode_failures: 3,
+            failing_since: Some(since),
+            ..counts("swap", 5, 2)
+        };
+        let swap = ProcessorEventMetric::add(&failing, &pool).await.unwrap();
+        assert_eq!(swap.events, 15);
+        assert_eq!(swap.decode_failures, 3);
+        assert_eq!(swap.handler_errors, 2);
+        assert_eq!(swap.latency_buckets, vec![13, 2, 0]);
+        assert_eq!(swap.latency_sum_ms, 150);
+        assert!(swap.failing_since.is_some());
+
+        // Skipped logs add up apart from failures
+        for _ in 0..2 {
+            let skipped = EventCounts {
+                skipped: 4,
+                ..counts("unknown", 4, 0)
+            };
+            ProcessorEventMetric::add(&skipped, &pool).await.unwrap();
+        }
+
+        let all = ProcessorEventMetric::find_all(&pool).await.unwrap();
+        let found: Vec<_> = all
+            .iter()
+            .map(|m| (m.event_type.as_str(), m.events, m.skipped))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("approval", 2, 0), ("swap", 15, 0), ("unknown", 8, 8)]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use sqlx::{Executor, Postgres};
+
+/// ProcessorProgress: how far the processor has handled the chain
+pub struct ProcessorProgress;
+
+impl ProcessorProgress {
+    /// Move the last processed block forward to `block`; it never moves back
+    pub async fn advance<'c, E>(block: i64, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO processor_progress (id, last_processed_block)
+            VALUES (TRUE, $1)
+            ON CONFLICT (id) DO UPDATE SET
+                last_processed_block = GREATEST(processor_progress.last_processed_block, EXCLUDED.last_processed_block),
+                updated_at = NOW()
+            "#,
+        )
+        .bind(block)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The block every queued log up to has been handled, `None` before the
+    /// processor has handled any
+    pub async fn last_processed_block<'c, E>(connection: E) -> Result<Option<i64>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT last_processed_block FROM processor_progress")
+            .fetch_optional(connection)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn progress_only_moves_forward(pool: PgPool) {
+        assert_eq!(
+            ProcessorProgress::last_processed_block(&pool)
+                .await
+                .unwrap(),
+            None
+        );
+
+        ProcessorProgress::advance(1_000, &pool).await.unwrap();
+        ProcessorProgress::advance(990, &pool).await.unwrap();
+        assert_eq!(
+            ProcessorProgress::last_processed_block(&pool)
+                .await
+                .unwrap(),
+            Some(1_000)
+        );
+
+        ProcessorProgress::advance(1_010, &pool).await.unwrap();
+        assert_eq!(
+            ProcessorProgress::last_processed_block(&pool)
+                .await
+                .unwrap(),
+            Some(1_010)
+        );
+    }
+}
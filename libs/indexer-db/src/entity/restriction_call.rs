@@ -0,0 +1,222 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::{Address20, Hash32};
+
+/// RestrictionCall entity: a blacklist, whitelist or max transaction function
+/// called on a token contract
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct RestrictionCall {
+    pub id: i32,
+    pub token_address: Address20,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+    pub caller: Address20,
+    /// Function signature, e.g. `blacklist(address)`
+    pub function: String,
+    pub kind: String, // "blacklist", "whitelist", "max_tx"
+    /// Address the call restricts, if it takes one
+    pub target_address: Option<Address20>,
+    /// False when the call lifts the restriction
+    pub enabled: bool,
+    /// The target held the token when the call was seen
+    pub target_is_holder: bool,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for recording a restriction call
+#[derive(Debug, Clone)]
+pub struct NewRestrictionCall {
+    pub token_address: Address20,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+    pub caller: Address20,
+    pub function: String,
+    pub kind: String,
+    pub target_address: Option<Address20>,
+    pub enabled: bool,
+    pub target_is_holder: bool,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl RestrictionCall {
+    /// Record a call; `None` if it was already recorded
+    pub async fn create<'c, E>(
+        call: &NewRestrictionCall,
+        connection: E,
+    ) -> Result<Option<RestrictionCall>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, RestrictionCall>(
+            r#"
+            INSERT INTO restriction_calls (
+                token_address, tx_hash, block_number, caller, function, kind,
+                target_address, enabled, target_is_holder, timestamp
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (tx_hash, token_address, target_address) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(call.token_address)
+        .bind(call.tx_hash)
+        .bind(call.block_number)
+        .bind(call.caller)
+        .bind(&call.function)
+        .bind(&call.kind)
+        .bind(call.target_address)
+        .bind(call.enabled)
+        .bind(call.target_is_holder)
+        .bind(call.timestamp)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Restriction calls on a token, newest first
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<RestrictionCall>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, RestrictionCall>(
+            r#"
+            SELECT * FROM restriction_calls
+            WHERE token_address = $1
+            ORDER BY block_number DESC, id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(token_address)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Last block checked for restriction calls on a chain
+    pub async fn last_watched_block<'c, E>(
+        chain_id: i64,
+        connection: E,
+    ) -> Result<Option<i64>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT last_block FROM restriction_watch_cursors WHERE chain_id = $1",
+        )
+        .bind(chain_id)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Move the watch cursor of a chain to `block`
+    pub async fn set_last_watched_block<'c, E>(
+        chain_id: i64,
+        block: i64,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO restriction_watch_cursors (chain_id, last_block)
+            VALUES ($1, $2)
+            ON CONFLICT (chain_id) DO UPDATE SET
+                last_block = EXCLUDED.last_block,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(chain_id)
+        .bind(block)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::test_support::{address, hash};
+
+    #[sqlx::test]
+    async fn calls_are_recorded_once_per_target(pool: PgPool) {
+        let call = NewRestrictionCall {
+            token_address: address(1),
+            tx_hash: hash(2),
+            block_number: 100,
+            caller: address(3),
+            function: "addBots(address[])".to_string(),
+            kind: "blacklist".to_string(),
+            target_address: Some(address(4)),
+            enabled: true,
+            target_is_holder: true,
+            timestamp: Utc::now(),
+        };
+        assert!(RestrictionCall::create(&call, &pool)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(RestrictionCall::create(&call, &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        let second = NewRestrictionCall {
+            target_address: Some(address(5)),
+            ..call.clone()
+        };
+        assert!(RestrictionCall::create(&second, &pool)
+            .await
+            .unwrap()
+            .is_some());
+
+        // Calls without a target are unique per transaction too
+        let max_tx = NewRestrictionCall {
+            tx_hash: hash(6),
+            function: "setMaxTxAmount(uint256)".to_string(),
+            kind: "max_tx".to_string(),
+            target_address: None,
+            target_is_holder: false,
+            ..call.clone()
+        };
+        assert!(RestrictionCall::create(&max_tx, &pool)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(RestrictionCall::create(&max_tx, &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        let calls = RestrictionCall::find_by_token(&address(1), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(calls.len(), 3);
+
+        assert_eq!(
+            RestrictionCall::last_watched_block(56, &pool)
+                .await
+                .unwrap(),
+            None
+        );
+        RestrictionCall::set_last_watched_block(56, 1_000, &pool)
+            .await
+            .unwrap();
+        RestrictionCall::set_last_watched_block(56, 1_050, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            RestrictionCall::last_watched_block(56, &pool)
+                .await
+                .unwrap(),
+            Some(1_050)
+        );
+    }
+}
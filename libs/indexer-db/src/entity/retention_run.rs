@@ -0,0 +1,90 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+/// RetentionRun entity: what one retention pass removed from one table
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct RetentionRun {
+    pub id: i32,
+    pub table_name: String,
+    pub rows_deleted: i64,
+    pub batches: i32,
+    pub duration_ms: i64,
+    /// Set when the pass stopped early on an error
+    pub error: Option<String>,
+    pub ran_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for recording a retention pass
+#[derive(Debug, Clone)]
+pub struct NewRetentionRun {
+    pub table_name: String,
+    pub rows_deleted: i64,
+    pub batches: i32,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+impl RetentionRun {
+    /// Record a retention pass
+    pub async fn create<'c, E>(
+        run: &NewRetentionRun,
+        connection: E,
+    ) -> Result<RetentionRun, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO retention_runs (table_name, rows_deleted, batches, duration_ms, error)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, RetentionRun>(query)
+            .bind(&run.table_name)
+            .bind(run.rows_deleted)
+            .bind(run.batches)
+            .bind(run.duration_ms)
+            .bind(&run.error)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Get the most recent passes, newest first
+    pub async fn find_recent<'c, E>(
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<RetentionRun>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, RetentionRun>(
+            "SELECT * FROM retention_runs ORDER BY ran_at DESC, id DESC LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Delete up to `limit` passes recorded before `cutoff`, returning how
+    /// many were removed
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM retention_runs
+            WHERE id IN (SELECT id FROM retention_runs WHERE ran_at < $1 LIMIT $2)
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
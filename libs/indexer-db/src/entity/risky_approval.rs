@@ -0,0 +1,143 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::{Address20, Hash32};
+
+/// RiskyApproval entity: an approval of an indexed token granted to a listed
+/// drainer
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct RiskyApproval {
+    pub id: i32,
+    pub token_address: Address20,
+    pub owner_address: Address20,
+    pub spender_address: Address20,
+    /// Drainer label at the time of the approval
+    pub spender_label: String,
+    /// Raw token units approved
+    pub amount: BigDecimal,
+    /// At least the token's supply, or the usual max-uint approval
+    pub unlimited: bool,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for recording a risky approval
+#[derive(Debug, Clone)]
+pub struct NewRiskyApproval {
+    pub token_address: Address20,
+    pub owner_address: Address20,
+    pub spender_address: Address20,
+    pub spender_label: String,
+    pub amount: BigDecimal,
+    pub unlimited: bool,
+    pub tx_hash: Hash32,
+    pub block_number: i64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl RiskyApproval {
+    /// Record an approval; `None` if it was already recorded
+    pub async fn create<'c, E>(
+        approval: &NewRiskyApproval,
+        connection: E,
+    ) -> Result<Option<RiskyApproval>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, RiskyApproval>(
+            r#"
+            INSERT INTO risky_approvals (
+                token_address, owner_address, spender_address, spender_label,
+                amount, unlimited, tx_hash, block_number, timestamp
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (tx_hash, token_address, owner_address, spender_address) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(approval.token_address)
+        .bind(approval.owner_address)
+        .bind(approval.spender_address)
+        .bind(&approval.spender_label)
+        .bind(&approval.amount)
+        .bind(approval.unlimited)
+        .bind(approval.tx_hash)
+        .bind(approval.block_number)
+        .bind(approval.timestamp)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Approvals a wallet granted to drainers, newest first
+    pub async fn find_by_owner<'c, E>(
+        owner_address: &Address20,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<RiskyApproval>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, RiskyApproval>(
+            r#"
+            SELECT * FROM risky_approvals
+            WHERE owner_address = $1
+            ORDER BY timestamp DESC, id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(owner_address)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::test_support::{address, hash};
+
+    #[sqlx::test]
+    async fn approvals_are_recorded_once(pool: PgPool) {
+        let approval = NewRiskyApproval {
+            token_address: address(1),
+            owner_address: address(2),
+            spender_address: address(3),
+            spender_label: "Inferno Drainer".to_string(),
+            amount: "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+                .parse()
+                .unwrap(),
+            unlimited: true,
+            tx_hash: hash(4),
+            block_number: 100,
+            timestamp: Utc::now(),
+        };
+
+        let created = RiskyApproval::create(&approval, &pool).await.unwrap();
+        assert_eq!(created.unwrap().amount, approval.amount);
+        assert!(RiskyApproval::create(&approval, &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        let other = NewRiskyApproval {
+            token_address: address(5),
+            ..approval.clone()
+        };
+        RiskyApproval::create(&other, &pool).await.unwrap();
+
+        let granted = RiskyApproval::find_by_owner(&address(2), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(granted.len(), 2);
+        assert!(RiskyApproval::find_by_owner(&address(3), 10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
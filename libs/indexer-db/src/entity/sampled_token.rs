@@ -0,0 +1,206 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// SampledToken entity: a token whose Transfers are indexed in sampling mode
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct SampledToken {
+    pub token_address: Address20,
+    /// BeeScore when selected
+    pub bee_score: Option<i16>,
+    /// Liquidity when selected
+    pub liquidity_usd: Option<BigDecimal>,
+    pub selected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Which tokens make the sample. Only market data counts: BeeScores of
+/// tokens outside the sample are scored without their holders.
+#[derive(Debug, Clone)]
+pub struct SamplingCriteria {
+    /// Lowest liquidity in USD; 0 lets tokens without liquidity in
+    pub min_liquidity_usd: BigDecimal,
+    /// Lowest 24h volume in USD; 0 lets tokens without trades in
+    pub min_volume_24h_usd: BigDecimal,
+    /// Most tokens sampled, those with the most liquidity first
+    pub max_tokens: i64,
+}
+
+/// Outcome of a reselection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reselection {
+    pub selected: i64,
+    /// Tokens that weren't in the sample before
+    pub entered: Vec<Address20>,
+}
+
+impl SampledToken {
+    /// Get the sampled tokens, most liquid first
+    pub async fn find_all<'c, E>(connection: E) -> Result<Vec<SampledToken>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, SampledToken>(
+            "SELECT * FROM sampled_tokens ORDER BY liquidity_usd DESC NULLS LAST, token_address",
+        )
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Get the addresses of the sampled tokens
+    pub async fn find_addresses<'c, E>(connection: E) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT token_address FROM sampled_tokens ORDER BY token_address")
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Whether `token_address` is in the sample
+    pub async fn contains<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM sampled_tokens WHERE token_address = $1)")
+            .bind(token_address)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Replace the sample with the live tokens meeting `criteria`. Tokens
+    /// staying in keep their `selected_at`.
+    pub async fn reselect<'c, E>(
+        criteria: &SamplingCriteria,
+        connection: E,
+    ) -> Result<Reselection, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            WITH selected AS (
+                SELECT address, bee_score, liquidity_usd FROM tokens
+                WHERE archived_at IS NULL
+                    AND COALESCE(liquidity_usd, 0) >= $1
+                    AND COALESCE(volume_24h_usd, 0) >= $2
+                ORDER BY liquidity_usd DESC NULLS LAST, volume_24h_usd DESC NULLS LAST, id
+                LIMIT $3
+            ),
+            upserted AS (
+                INSERT INTO sampled_tokens (token_address, bee_score, liquidity_usd)
+                SELECT * FROM selected
+                ON CONFLICT (token_address) DO UPDATE
+                SET bee_score = EXCLUDED.bee_score,
+                    liquidity_usd = EXCLUDED.liquidity_usd
+                RETURNING token_address, (xmax = 0) AS inserted
+            ),
+            dropped AS (
+                DELETE FROM sampled_tokens
+                WHERE token_address NOT IN (SELECT address FROM selected)
+            )
+            SELECT
+                (SELECT COUNT(*) FROM selected),
+                ARRAY(SELECT token_address FROM upserted WHERE inserted ORDER BY token_address)
+        "#;
+
+        let (selected, entered) = sqlx::query_as::<_, (i64, Vec<Address20>)>(query)
+            .bind(&criteria.min_liquidity_usd)
+            .bind(&criteria.min_volume_24h_usd)
+            .bind(criteria.max_tokens)
+            .fetch_one(connection)
+            .await?;
+
+        Ok(Reselection { selected, entered })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+    };
+
+    async fn token(n: u8, volume_usd: i64, liquidity_usd: i64, pool: &PgPool) {
+        let new = NewToken {
+            address: address(n),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: None,
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: None,
+        };
+        Token::create(&new, pool).await.unwrap();
+        sqlx::query("UPDATE tokens SET liquidity_usd = $2, volume_24h_usd = $3 WHERE address = $1")
+            .bind(address(n))
+            .bind(BigDecimal::from(liquidity_usd))
+            .bind(BigDecimal::from(volume_usd))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn reselect_keeps_the_most_liquid_qualifying_tokens(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        token(1, 8_000, 50_000, &pool).await;
+        token(2, 9_000, 5_000, &pool).await;
+        token(3, 400, 90_000, &pool).await;
+        token(4, 7_000, 20_000, &pool).await;
+        token(5, 7_500, 30_000, &pool).await;
+
+        let criteria = SamplingCriteria {
+            min_liquidity_usd: BigDecimal::from(10_000),
+            min_volume_24h_usd: BigDecimal::from(1_000),
+            max_tokens: 2,
+        };
+        let first = SampledToken::reselect(&criteria, &pool).await.unwrap();
+        assert_eq!(
+            first,
+            Reselection {
+                selected: 2,
+                entered: vec![address(1), address(5)],
+            }
+        );
+        let sampled = SampledToken::find_all(&pool).await.unwrap();
+        assert_eq!(
+            sampled.iter().map(|s| s.token_address).collect::<Vec<_>>(),
+            vec![address(1), address(5)]
+        );
+        let first_selected = sampled[0].selected_at;
+
+        // Token 5 drops out, token 4 takes its place, token 1 stays selected
+        sqlx::query("UPDATE tokens SET liquidity_usd = 0 WHERE address = $1")
+            .bind(address(5))
+            .execute(&pool)
+            .await
+            .unwrap();
+        let second = SampledToken::reselect(&criteria, &pool).await.unwrap();
+        assert_eq!(second.entered, vec![address(4)]);
+        let sampled = SampledToken::find_all(&pool).await.unwrap();
+        assert_eq!(
+            sampled.iter().map(|s| s.token_address).collect::<Vec<_>>(),
+            vec![address(1), address(4)]
+        );
+        assert_eq!(sampled[0].selected_at, first_selected);
+        assert!(SampledToken::contains(&address(4), &pool).await.unwrap());
+        assert!(!SampledToken::contains(&address(5), &pool).await.unwrap());
+        assert_eq!(
+            SampledToken::find_addresses(&pool).await.unwrap(),
+            vec![address(1), address(4)]
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    types::{chrono, BigDecimal, Json},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// One of the top-10 holders at the time a score was computed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HolderShare {
+    pub address: Address20,
+    /// Raw token units, as a decimal string
+    pub balance: String,
+    /// Share (%) of total supply; `None` when the supply is unknown
+    pub percent: Option<f64>,
+}
+
+/// Points one BeeScore component contributed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreComponent {
+    /// `safety` or `traction`
+    pub category: String,
+    pub name: String,
+    pub score: i16,
+    pub max_score: i16,
+    pub reason: String,
+}
+
+/// ScoreHistory entity: a BeeScore computation and the holder set it saw
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ScoreHistory {
+    pub id: i64,
+    pub token_address: Address20,
+    pub bee_score: i16,
+    pub safety_score: i16,
+    pub traction_score: i16,
+    pub top_10_holder_percent: Option<BigDecimal>,
+    /// Largest first
+    pub top_holders: Json<Vec<HolderShare>>,
+    pub computed_at: chrono::DateTime<chrono::Utc>,
+    /// Safety components first, then traction
+    pub breakdown: Json<Vec<ScoreComponent>>,
+}
+
+/// Input for recording a score computation
+#[derive(Debug, Clone)]
+pub struct NewScoreHistory {
+    pub token_address: Address20,
+    pub bee_score: i16,
+    pub safety_score: i16,
+    pub traction_score: i16,
+    pub breakdown: Vec<ScoreComponent>,
+}
+
+impl ScoreHistory {
+    /// Record a score along with the token's current top-10 holders and
+    /// concentration, read in the same statement
+    pub async fn create<'c, E>(
+        score: &NewScoreHistory,
+        connection: E,
+    ) -> Result<ScoreHistory, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO score_history (
+                token_address, bee_score, safety_score, traction_score,
+                top_10_holder_percent, top_holders, breakdown
+            )
+            SELECT
+                $1, $2, $3, $4,
+                t.top_10_holder_percent,
+                COALESCE(
+                    (
+                        SELECT jsonb_agg(
+                            jsonb_build_object(
+                                'address', '0x' || encode(h.wallet_address, 'hex'),
+                                'balance', TRIM_SCALE(COALESCE(h.balance, 0))::TEXT,
+                                'percent', CASE
+                                    WHEN t.total_supply > 0
+                                        THEN ROUND(COALESCE(h.balance, 0) / t.total_supply * 100, 6)::FLOAT8
+                                    ELSE h.percent_of_supply::FLOAT8
+                                END
+                            )
+                            ORDER BY h.balance DESC NULLS LAST
+                        )
+                        FROM (
+                            SELECT wallet_address, balance, percent_of_supply
+                            FROM token_holders
+                            WHERE token_address = $1
+                            ORDER BY balance DESC NULLS LAST
+                            LIMIT 10
+                        ) h
+                    ),
+                    '[]'::JSONB
+                ),
+                $5
+            FROM (SELECT $1::BYTEA AS address) wanted
+            LEFT JOIN tokens t ON t.address = wanted.address
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ScoreHistory>(query)
+            .bind(score.token_address)
+            .bind(score.bee_score)
+            .bind(score.safety_score)
+            .bind(score.traction_score)
+            .bind(Json(&score.breakdown))
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Get a token's score computations, newest first
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<ScoreHistory>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ScoreHistory>(
+            r#"
+            SELECT * FROM score_history
+            WHERE token_address = $1
+            ORDER BY computed_at DESC, id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(token_address)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Get a token's last computation at or before `at`
+    pub async fn find_at_or_before<'c, E>(
+        token_address: &Address20,
+        at: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Option<ScoreHistory>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ScoreHistory>(
+            r#"
+            SELECT * FROM score_history
+            WHERE token_address = $1 AND computed_at <= $2
+            ORDER BY computed_at DESC, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(token_address)
+        .bind(at)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Delete up to `limit` computations older than `cutoff`, returning how
+    /// many were removed
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM score_history
+            WHERE id IN (SELECT id FROM score_history WHERE computed_at < $1 LIMIT $2)
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+        token_holder::{NewTokenHolder, TokenHolder},
+    };
+
+    fn score(bee_score: i16) -> NewScoreHistory {
+        NewScoreHistory {
+            token_address: address(1),
+            bee_score,
+            safety_score: bee_score / 2,
+            traction_score: bee_score / 2,
+            breakdown: vec![ScoreComponent {
+                category: "safety".to_string(),
+                name: "LP Lock".to_string(),
+                score: bee_score / 4,
+                max_score: 15,
+                reason: "LP 50-90% locked - good".to_string(),
+            }],
+        }
+    }
+
+    async fn holder(pool: &PgPool, wallet: u8, balance: i64) {
+        let holder = NewTokenHolder {
+            token_address: address(1),
+            wallet_address: address(wallet),
+            balance: BigDecimal::from(balance),
+            is_dev: false,
+            is_sniper: false,
+            is_contract: false,
+            first_buy_block: None,
+            source: None,
+        };
+        TokenHolder::upsert(&holder, pool).await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn scores_keep_the_holder_set_they_saw(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let token = NewToken {
+            address: address(1),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: Some(BigDecimal::from(1_000)),
+            pair_address: None,
+            creator_address: None,
+            block_number: Some(100),
+        };
+        Token::create(&token, &pool).await.unwrap();
+        for wallet in 10..22 {
+            holder(&pool, wallet, wallet as i64).await;
+        }
+
+        let first = ScoreHistory::create(&score(40), &pool).await.unwrap();
+        assert_eq!(first.top_holders.0.len(), 10);
+        assert_eq!(
+            first.top_holders.0[0],
+            HolderShare {
+                address: address(21),
+                balance: "21".to_string(),
+                percent: Some(2.1),
+            }
+        );
+
+        // The holder set moves on; the recorded snapshot doesn't
+        holder(&pool, 30, 500).await;
+        let second = ScoreHistory::create(&score(60), &pool).await.unwrap();
+        assert_eq!(second.top_holders.0[0].address, address(30));
+
+        let history = ScoreHistory::find_by_token(&address(1), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].bee_score, 60);
+        assert_eq!(history[1].top_holders.0[0].address, address(21));
+        assert_eq!(history[0].breakdown.0, score(60).breakdown);
+
+        let before_second = ScoreHistory::find_at_or_before(&address(1), first.computed_at, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(before_second.id, first.id);
+        let too_early = first.computed_at - Duration::minutes(1);
+        let missing = ScoreHistory::find_at_or_before(&address(1), too_early, &pool)
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+
+        // A token without holders records an empty set
+        let lonely = NewScoreHistory {
+            token_address: address(2),
+            ..score(10)
+        };
+        let created = ScoreHistory::create(&lonely, &pool).await.unwrap();
+        assert!(created.top_holders.0.is_empty());
+        assert_eq!(created.top_10_holder_percent, None);
+
+        let removed = ScoreHistory::delete_older_than(Utc::now() + Duration::minutes(1), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(removed, 3);
+    }
+}
@@ -0,0 +1,158 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// SocialMetric entity: one reading of a token's social reach from an external collector
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct SocialMetric {
+    pub id: i32,
+    pub token_address: Address20,
+    pub source: String,
+    pub recorded_at: chrono::DateTime<chrono::Utc>,
+    pub telegram_members: Option<i32>,
+    pub x_mentions_24h: Option<i32>,
+}
+
+/// Input for recording a social metric reading
+#[derive(Debug, Clone)]
+pub struct NewSocialMetric {
+    pub token_address: Address20,
+    pub source: String,
+    pub recorded_at: chrono::DateTime<chrono::Utc>,
+    pub telegram_members: Option<i32>,
+    pub x_mentions_24h: Option<i32>,
+}
+
+impl SocialMetric {
+    /// Record a new reading
+    pub async fn create<'c, E>(
+        metric: &NewSocialMetric,
+        connection: E,
+    ) -> Result<SocialMetric, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO social_metrics (token_address, source, recorded_at, telegram_members, x_mentions_24h)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, SocialMetric>(query)
+            .bind(metric.token_address)
+            .bind(&metric.source)
+            .bind(metric.recorded_at)
+            .bind(metric.telegram_members)
+            .bind(metric.x_mentions_24h)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Latest reading for a token recorded at or after `since`, each field
+    /// taken from the latest reading in that window that has it: collectors
+    /// report Telegram and X separately
+    pub async fn find_latest<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Option<SocialMetric>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, SocialMetric>(
+            r#"
+            SELECT
+                id,
+                token_address,
+                source,
+                recorded_at,
+                (
+                    SELECT telegram_members FROM social_metrics
+                    WHERE token_address = $1 AND recorded_at >= $2
+                        AND telegram_members IS NOT NULL
+                    ORDER BY recorded_at DESC, id DESC
+                    LIMIT 1
+                ) AS telegram_members,
+                (
+                    SELECT x_mentions_24h FROM social_metrics
+                    WHERE token_address = $1 AND recorded_at >= $2
+                        AND x_mentions_24h IS NOT NULL
+                    ORDER BY recorded_at DESC, id DESC
+                    LIMIT 1
+                ) AS x_mentions_24h
+            FROM social_metrics
+            WHERE token_address = $1 AND recorded_at >= $2
+            ORDER BY recorded_at DESC, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .fetch_optional(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::test_support::address;
+
+    fn reading(hours_ago: i64, telegram_members: i32) -> NewSocialMetric {
+        NewSocialMetric {
+            token_address: address(1),
+            source: "telegram-bot".to_string(),
+            recorded_at: Utc::now() - Duration::hours(hours_ago),
+            telegram_members: Some(telegram_members),
+            x_mentions_24h: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn find_latest_ignores_stale_readings(pool: PgPool) {
+        for (hours_ago, members) in [(30, 50), (5, 900), (1, 1_200)] {
+            SocialMetric::create(&reading(hours_ago, members), &pool)
+                .await
+                .unwrap();
+        }
+
+        let day_ago = Utc::now() - Duration::hours(24);
+        let latest = SocialMetric::find_latest(&address(1), day_ago, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.telegram_members, Some(1_200));
+        assert_eq!(latest.x_mentions_24h, None);
+
+        // A newer X-only reading keeps the last Telegram count
+        let mentions = NewSocialMetric {
+            source: "x-bot".to_string(),
+            telegram_members: None,
+            x_mentions_24h: Some(340),
+            ..reading(0, 0)
+        };
+        SocialMetric::create(&mentions, &pool).await.unwrap();
+        let merged = SocialMetric::find_latest(&address(1), day_ago, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.source, "x-bot");
+        assert_eq!(merged.telegram_members, Some(1_200));
+        assert_eq!(merged.x_mentions_24h, Some(340));
+
+        let future = Utc::now() + Duration::minutes(10);
+        assert!(SocialMetric::find_latest(&address(1), future, &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        let negative = NewSocialMetric {
+            telegram_members: Some(-1),
+            ..reading(0, 0)
+        };
+        assert!(SocialMetric::create(&negative, &pool).await.is_err());
+    }
+}
@@ -4,41 +4,140 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use super::{price_snapshot::PriceBucket, COMMIT_LAG};
+use crate::types::{Address20, Hash32};
+
 /// Swap entity representing a DEX trade
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Swap {
     pub id: i32,
-    pub tx_hash: String,
+    pub tx_hash: Hash32,
     pub block_number: i64,
     pub log_index: i32,
     pub timestamp: chrono::DateTime<chrono::Utc>,
-    pub pair_address: String,
-    pub token_address: String,
-    pub wallet_address: String,
+    pub pair_address: Address20,
+    pub token_address: Address20,
+    pub wallet_address: Address20,
     pub trade_type: String, // "buy" or "sell"
     pub amount_tokens: Option<BigDecimal>,
     pub amount_bnb: Option<BigDecimal>,
     pub amount_usd: Option<BigDecimal>,
     pub price_usd: Option<BigDecimal>,
     pub is_whale: Option<bool>,
+    /// Position of the transaction in its block
+    pub tx_index: Option<i32>,
+    /// Share (0-100) of the block's transactions paying a lower gas price
+    pub gas_price_percentile: Option<i16>,
+    /// Bot indicators: `top_of_block`, `high_gas`, `probable_mev`
+    pub mev_flags: Vec<String>,
+    /// Token sent into the pair
+    pub token_in: Option<Address20>,
+    /// Input amount, normalized by the token's decimals
+    pub amount_in: Option<BigDecimal>,
+    /// Token received from the pair
+    pub token_out: Option<Address20>,
+    /// Output amount, normalized by the token's decimals
+    pub amount_out: Option<BigDecimal>,
+    /// Estimated LP fee, in `token_in` units
+    pub fee_amount: Option<BigDecimal>,
+    pub fee_usd: Option<BigDecimal>,
+    /// `evm_logs.id` of the Swap log, when it came through `evm_logs`
+    pub source_log_id: Option<i32>,
+}
+
+/// Volume breakdown used to spot wash trading
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct WashTradingStats {
+    pub trades: i64,
+    pub volume_usd: BigDecimal,
+    /// Buy volume matched by the same wallet's sells (and vice versa)
+    pub round_trip_usd: BigDecimal,
+    /// Volume from wallets that passed the token back and forth between each other
+    pub circular_usd: BigDecimal,
+    /// Volume from the two most active wallets
+    pub top2_usd: BigDecimal,
+}
+
+/// A token's trades in one day-of-week and hour-of-day slot (UTC)
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ActivityCell {
+    /// ISO day of week, 1 (Monday) to 7 (Sunday)
+    pub day_of_week: i32,
+    /// 0-23
+    pub hour: i32,
+    pub trades: i64,
+    pub volume_usd: BigDecimal,
+}
+
+/// A wallet's trading across every token
+///
+/// A token's PnL is realized against its cost basis: the tokens sold, up to
+/// the tokens bought, priced at the average sell price less the average buy
+/// price. Tokens still held whole, or sold without a recorded buy, are
+/// neither wins nor losses. Only swaps kept by retention count
+/// (`SWAP_RETENTION_DAYS`, 90 days by default), so "lifetime" means the last
+/// 90 days unless retention is turned off.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct WalletTradeSummary {
+    pub trades: i64,
+    pub volume_usd: BigDecimal,
+    pub tokens_traded: i64,
+    pub whale_trades: i64,
+    /// Mean time from a token's first buy to the first sell after it
+    pub avg_hold_secs: Option<f64>,
+    /// Tokens the wallet bought and then sold some of
+    pub tokens_sold: i64,
+    /// Of those, tokens sold above their average buy price
+    pub tokens_won: i64,
+    pub biggest_win_token: Option<Address20>,
+    pub biggest_win_usd: Option<BigDecimal>,
+    pub biggest_loss_token: Option<Address20>,
+    /// Negative
+    pub biggest_loss_usd: Option<BigDecimal>,
+}
+
+/// Narrows [`Swap::find_by_token`]; the default matches every swap
+#[derive(Debug, Clone, Default)]
+pub struct SwapFilter {
+    /// `buy` or `sell`
+    pub trade_type: Option<String>,
+    pub min_usd: Option<BigDecimal>,
+    pub whales_only: bool,
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Input for creating a new swap
 #[derive(Debug, Clone)]
 pub struct NewSwap {
-    pub tx_hash: String,
+    pub tx_hash: Hash32,
     pub block_number: i64,
     pub log_index: i32,
     pub timestamp: chrono::DateTime<chrono::Utc>,
-    pub pair_address: String,
-    pub token_address: String,
-    pub wallet_address: String,
+    pub pair_address: Address20,
+    pub token_address: Address20,
+    pub wallet_address: Address20,
     pub trade_type: String,
     pub amount_tokens: Option<BigDecimal>,
     pub amount_bnb: Option<BigDecimal>,
     pub amount_usd: Option<BigDecimal>,
     pub price_usd: Option<BigDecimal>,
     pub is_whale: bool,
+    pub tx_index: Option<i32>,
+    pub gas_price_percentile: Option<i16>,
+    pub mev_flags: Vec<String>,
+    pub legs: Option<SwapLegs>,
+    pub source_log_id: Option<i32>,
+}
+
+/// Both sides of a swap, amounts normalized by each token's decimals
+#[derive(Debug, Clone)]
+pub struct SwapLegs {
+    pub token_in: Address20,
+    pub amount_in: BigDecimal,
+    pub token_out: Address20,
+    pub amount_out: BigDecimal,
+    pub fee_amount: Option<BigDecimal>,
+    pub fee_usd: Option<BigDecimal>,
 }
 
 impl Swap {
@@ -51,34 +150,73 @@ impl Swap {
             INSERT INTO swaps (
                 tx_hash, block_number, log_index, timestamp, pair_address,
                 token_address, wallet_address, trade_type, amount_tokens,
-                amount_bnb, amount_usd, price_usd, is_whale
+                amount_bnb, amount_usd, price_usd, is_whale, tx_index,
+                gas_price_percentile, mev_flags, token_in, amount_in,
+                token_out, amount_out, fee_amount, fee_usd, source_log_id
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16,
+                $17, $18, $19, $20, $21, $22, $23
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             ON CONFLICT (tx_hash, log_index) DO NOTHING
             RETURNING *
         "#;
 
         sqlx::query_as::<_, Swap>(query)
-            .bind(&swap.tx_hash)
+            .bind(swap.tx_hash)
             .bind(swap.block_number)
             .bind(swap.log_index)
             .bind(swap.timestamp)
-            .bind(&swap.pair_address)
-            .bind(&swap.token_address)
-            .bind(&swap.wallet_address)
+            .bind(swap.pair_address)
+            .bind(swap.token_address)
+            .bind(swap.wallet_address)
             .bind(&swap.trade_type)
             .bind(&swap.amount_tokens)
             .bind(&swap.amount_bnb)
             .bind(&swap.amount_usd)
             .bind(&swap.price_usd)
             .bind(swap.is_whale)
+            .bind(swap.tx_index)
+            .bind(swap.gas_price_percentile)
+            .bind(&swap.mev_flags)
+            .bind(swap.legs.as_ref().map(|l| l.token_in))
+            .bind(swap.legs.as_ref().map(|l| &l.amount_in))
+            .bind(swap.legs.as_ref().map(|l| l.token_out))
+            .bind(swap.legs.as_ref().map(|l| &l.amount_out))
+            .bind(swap.legs.as_ref().and_then(|l| l.fee_amount.as_ref()))
+            .bind(swap.legs.as_ref().and_then(|l| l.fee_usd.as_ref()))
+            .bind(swap.source_log_id)
             .fetch_one(connection)
             .await
     }
 
-    /// Find swaps by token address
+    /// Delete up to `limit` swaps older than `cutoff`, returning how many went
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM swaps
+            WHERE id IN (SELECT id FROM swaps WHERE timestamp < $1 LIMIT $2)
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Find a token's swaps matching `filter`, newest first
     pub async fn find_by_token<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
+        filter: &SwapFilter,
         limit: i32,
         connection: E,
     ) -> Result<Vec<Swap>, sqlx::Error>
@@ -86,17 +224,67 @@ impl Swap {
         E: Executor<'c, Database = Postgres>,
     {
         sqlx::query_as::<_, Swap>(
-            "SELECT * FROM swaps WHERE token_address = $1 ORDER BY timestamp DESC LIMIT $2",
+            r#"
+            SELECT * FROM swaps
+            WHERE token_address = $1
+              AND ($2::TEXT IS NULL OR trade_type = $2)
+              AND ($3::NUMERIC IS NULL OR amount_usd >= $3)
+              AND (NOT $4 OR is_whale = TRUE)
+              AND ($5::TIMESTAMPTZ IS NULL OR timestamp >= $5)
+            ORDER BY timestamp DESC
+            LIMIT $6
+            "#,
         )
         .bind(token_address)
+        .bind(&filter.trade_type)
+        .bind(&filter.min_usd)
+        .bind(filter.whales_only)
+        .bind(filter.since)
         .bind(limit)
         .fetch_all(connection)
         .await
     }
 
+    /// The latest `limit` swaps, newest first
+    ///
+    /// With `after_id`, only swaps recorded after that one: the oldest
+    /// `limit` of them, so a reader resuming from the largest id it has seen
+    /// pages through a backlog without skipping any. Swaps recorded less than
+    /// [`COMMIT_LAG`] ago wait for a later page.
+    pub async fn find_after<'c, E>(
+        after_id: Option<i32>,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<Swap>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = format!(
+            r#"
+            SELECT * FROM swaps
+            WHERE $1::INT IS NULL
+               OR (id > $1 AND created_at <= NOW() - make_interval(secs => $3))
+            ORDER BY id {}
+            LIMIT $2
+            "#,
+            if after_id.is_some() { "ASC" } else { "DESC" }
+        );
+
+        let mut swaps = sqlx::query_as::<_, Swap>(&query)
+            .bind(after_id)
+            .bind(limit)
+            .bind(COMMIT_LAG.as_secs_f64())
+            .fetch_all(connection)
+            .await?;
+        if after_id.is_some() {
+            swaps.reverse();
+        }
+        Ok(swaps)
+    }
+
     /// Find swaps by wallet address
     pub async fn find_by_wallet<'c, E>(
-        wallet_address: &str,
+        wallet_address: &Address20,
         limit: i32,
         connection: E,
     ) -> Result<Vec<Swap>, sqlx::Error>
@@ -130,7 +318,7 @@ impl Swap {
 
     /// Get recent swaps for a token (for live feed)
     pub async fn find_recent_by_token<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         since: chrono::DateTime<chrono::Utc>,
         connection: E,
     ) -> Result<Vec<Swap>, sqlx::Error>
@@ -148,7 +336,7 @@ impl Swap {
 
     /// Count trades in last hour for a token
     pub async fn count_trades_1h<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<(i64, i64, i64), sqlx::Error>
     where
@@ -171,9 +359,116 @@ impl Swap {
         Ok(row)
     }
 
+    /// Tokens with at least one swap since a given time
+    pub async fn traded_tokens_since<'c, E>(
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar("SELECT DISTINCT token_address FROM swaps WHERE timestamp >= $1")
+            .bind(since)
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Break down a token's volume since a given time for wash trading analysis
+    pub async fn wash_trading_stats<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<WashTradingStats, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, WashTradingStats>(
+            r#"
+            WITH per_wallet AS (
+                SELECT
+                    wallet_address,
+                    COUNT(*) AS trades,
+                    COALESCE(SUM(amount_usd), 0) AS volume,
+                    COALESCE(SUM(amount_usd) FILTER (WHERE trade_type = 'buy'), 0) AS buy_usd,
+                    COALESCE(SUM(amount_usd) FILTER (WHERE trade_type = 'sell'), 0) AS sell_usd
+                FROM swaps
+                WHERE token_address = $1 AND timestamp >= $2
+                GROUP BY wallet_address
+            ),
+            -- Direct wallet-to-wallet transfers, leaving out the pair itself
+            transfers AS (
+                SELECT DISTINCT o.wallet_address AS sender, i.wallet_address AS recipient
+                FROM wallet_activity o
+                JOIN wallet_activity i
+                    ON i.tx_hash = o.tx_hash
+                    AND i.token_address = o.token_address
+                    AND i.action = 'transfer_in'
+                    AND i.wallet_address <> o.wallet_address
+                LEFT JOIN tokens t ON t.address = o.token_address
+                WHERE o.token_address = $1
+                    AND o.action = 'transfer_out'
+                    AND o.timestamp >= $2
+                    AND o.wallet_address IS DISTINCT FROM t.pair_address
+                    AND i.wallet_address IS DISTINCT FROM t.pair_address
+            ),
+            circular AS (
+                SELECT a.sender AS wallet_address
+                FROM transfers a
+                JOIN transfers b ON b.sender = a.recipient AND b.recipient = a.sender
+            )
+            SELECT
+                COALESCE(SUM(trades), 0)::BIGINT AS trades,
+                COALESCE(SUM(volume), 0) AS volume_usd,
+                COALESCE(SUM(2 * LEAST(buy_usd, sell_usd)), 0) AS round_trip_usd,
+                COALESCE(SUM(volume) FILTER (
+                    WHERE wallet_address IN (SELECT wallet_address FROM circular)
+                ), 0) AS circular_usd,
+                COALESCE((
+                    SELECT SUM(volume) FROM (
+                        SELECT volume FROM per_wallet ORDER BY volume DESC LIMIT 2
+                    ) top2
+                ), 0) AS top2_usd
+            FROM per_wallet
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// A token's trades since `since` by day of week and hour of day (UTC),
+    /// leaving out slots without trades
+    pub async fn activity_by_hour<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Vec<ActivityCell>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ActivityCell>(
+            r#"
+            SELECT
+                extract(isodow FROM timestamp AT TIME ZONE 'UTC')::INT AS day_of_week,
+                extract(hour FROM timestamp AT TIME ZONE 'UTC')::INT AS hour,
+                COUNT(*) AS trades,
+                COALESCE(SUM(amount_usd), 0) AS volume_usd
+            FROM swaps
+            WHERE token_address = $1 AND timestamp >= $2
+            GROUP BY 1, 2
+            ORDER BY 1, 2
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .fetch_all(connection)
+        .await
+    }
+
     /// Calculate volume in last hour for a token
     pub async fn volume_1h<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<BigDecimal, sqlx::Error>
     where
@@ -192,4 +487,526 @@ impl Swap {
 
         Ok(volume.unwrap_or_else(|| BigDecimal::from(0)))
     }
+
+    /// Price points in intervals of `bucket_secs` derived from a token's swaps,
+    /// for charts without snapshots; liquidity isn't known from trades
+    pub async fn price_points<'c, E>(
+        token_address: &Address20,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        bucket_secs: i64,
+        connection: E,
+    ) -> Result<Vec<PriceBucket>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, PriceBucket>(
+            r#"
+            SELECT
+                to_timestamp(floor(extract(epoch FROM timestamp) / $4) * $4) AS timestamp,
+                (array_agg(price_usd ORDER BY timestamp DESC, log_index DESC))[1] AS price_usd,
+                round(AVG(price_usd), 18) AS avg_price_usd,
+                NULL::NUMERIC AS liquidity_usd,
+                COALESCE(SUM(amount_usd), 0) AS volume_usd
+            FROM swaps
+            WHERE token_address = $1
+              AND timestamp >= $2
+              AND timestamp <= $3
+              AND price_usd > 0
+            GROUP BY 1
+            ORDER BY 1 ASC
+            "#,
+        )
+        .bind(token_address)
+        .bind(start)
+        .bind(end)
+        .bind(bucket_secs)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Amount of a token swapped into and out of pairs since `since`, as
+    /// (sold, bought) in normalized units, across every pair it trades in
+    pub async fn leg_volumes<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<(BigDecimal, BigDecimal), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let row: (Option<BigDecimal>, Option<BigDecimal>) = sqlx::query_as(
+            r#"
+            SELECT
+                SUM(amount_in) FILTER (WHERE token_in = $1) AS sold,
+                SUM(amount_out) FILTER (WHERE token_out = $1) AS bought
+            FROM swaps
+            WHERE (token_in = $1 OR token_out = $1) AND timestamp >= $2
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .fetch_one(connection)
+        .await?;
+
+        Ok((
+            row.0.unwrap_or_else(|| BigDecimal::from(0)),
+            row.1.unwrap_or_else(|| BigDecimal::from(0)),
+        ))
+    }
+
+    /// Lifetime trading summary of a wallet
+    pub async fn wallet_summary<'c, E>(
+        wallet_address: &Address20,
+        connection: E,
+    ) -> Result<WalletTradeSummary, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, WalletTradeSummary>(
+            r#"
+            WITH trades AS (
+                SELECT token_address, trade_type, timestamp, amount_tokens, amount_usd, is_whale
+                FROM swaps
+                WHERE wallet_address = $1
+            ),
+            legs AS (
+                SELECT
+                    token_address,
+                    SUM(amount_tokens) FILTER (WHERE trade_type = 'buy' AND amount_usd IS NOT NULL) AS bought,
+                    SUM(amount_usd) FILTER (WHERE trade_type = 'buy' AND amount_tokens IS NOT NULL) AS bought_usd,
+                    SUM(amount_tokens) FILTER (WHERE trade_type = 'sell' AND amount_usd IS NOT NULL) AS sold,
+                    SUM(amount_usd) FILTER (WHERE trade_type = 'sell' AND amount_tokens IS NOT NULL) AS sold_usd,
+                    MIN(timestamp) FILTER (WHERE trade_type = 'buy') AS first_buy
+                FROM trades
+                GROUP BY token_address
+            ),
+            per_token AS (
+                SELECT
+                    token_address,
+                    first_buy,
+                    -- Tokens sold out of what was bought, at the average sell
+                    -- price less the average buy price
+                    ROUND(
+                        LEAST(sold, bought)
+                            * (sold_usd / NULLIF(sold, 0) - bought_usd / NULLIF(bought, 0)),
+                        2
+                    ) AS pnl_usd,
+                    COALESCE(sold > 0 AND bought > 0, FALSE) AS sold
+                FROM legs
+            ),
+            holds AS (
+                SELECT EXTRACT(EPOCH FROM MIN(t.timestamp) - p.first_buy) AS secs
+                FROM per_token p
+                JOIN trades t ON t.token_address = p.token_address
+                    AND t.trade_type = 'sell'
+                    AND t.timestamp >= p.first_buy
+                GROUP BY p.token_address, p.first_buy
+            )
+            SELECT
+                (SELECT COUNT(*) FROM trades) AS trades,
+                (SELECT COALESCE(SUM(amount_usd), 0) FROM trades) AS volume_usd,
+                (SELECT COUNT(*) FROM per_token) AS tokens_traded,
+                (SELECT COUNT(*) FROM trades WHERE is_whale) AS whale_trades,
+                (SELECT AVG(secs)::DOUBLE PRECISION FROM holds) AS avg_hold_secs,
+                (SELECT COUNT(*) FROM per_token WHERE sold) AS tokens_sold,
+                (SELECT COUNT(*) FROM per_token WHERE sold AND pnl_usd > 0) AS tokens_won,
+                win.token_address AS biggest_win_token,
+                win.pnl_usd AS biggest_win_usd,
+                loss.token_address AS biggest_loss_token,
+                loss.pnl_usd AS biggest_loss_usd
+            FROM (SELECT 1) AS one
+            LEFT JOIN LATERAL (
+                SELECT token_address, pnl_usd FROM per_token
+                WHERE sold AND pnl_usd > 0
+                ORDER BY pnl_usd DESC LIMIT 1
+            ) win ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT token_address, pnl_usd FROM per_token
+                WHERE sold AND pnl_usd < 0
+                ORDER BY pnl_usd ASC LIMIT 1
+            ) loss ON TRUE
+            "#,
+        )
+        .bind(wallet_address)
+        .fetch_one(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data, hash},
+        wallet_activity::{NewWalletActivity, WalletActivity},
+    };
+
+    fn new_swap(n: u8, minutes_ago: i64, trade_type: &str, usd: i32) -> NewSwap {
+        NewSwap {
+            tx_hash: hash(n),
+            block_number: 1_000 + n as i64,
+            log_index: 0,
+            timestamp: Utc::now() - Duration::minutes(minutes_ago),
+            pair_address: address(100),
+            token_address: address(1),
+            wallet_address: address(50 + n),
+            trade_type: trade_type.to_string(),
+            amount_tokens: Some(BigDecimal::from(1)),
+            amount_bnb: Some(BigDecimal::from(1)),
+            amount_usd: Some(BigDecimal::from(usd)),
+            price_usd: Some(BigDecimal::from(1)),
+            is_whale: usd >= 5_000,
+            tx_index: None,
+            gas_price_percentile: None,
+            mev_flags: Vec::new(),
+            legs: None,
+            source_log_id: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn create_ignores_replayed_logs(pool: PgPool) {
+        let swap = new_swap(1, 0, "buy", 10);
+        Swap::create(&swap, &pool).await.unwrap();
+
+        let replay = Swap::create(&swap, &pool).await;
+        assert!(matches!(replay, Err(sqlx::Error::RowNotFound)));
+
+        let swaps = Swap::find_by_token(&address(1), &SwapFilter::default(), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(swaps.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn swaps_page_forward_after_a_known_id(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let mut ids = Vec::new();
+        for n in 1..=4 {
+            let swap = Swap::create(&new_swap(n, 5, "buy", 10), &pool)
+                .await
+                .unwrap();
+            ids.push(swap.id);
+        }
+        sqlx::query("UPDATE swaps SET created_at = created_at - INTERVAL '1 minute'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let found = |after_id: Option<i32>| {
+            let pool = pool.clone();
+            async move {
+                Swap::find_after(after_id, 2, &pool)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|s| s.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(found(None).await, vec![ids[3], ids[2]]);
+        assert_eq!(found(Some(ids[0])).await, vec![ids[2], ids[1]]);
+        assert_eq!(found(Some(ids[2])).await, vec![ids[3]]);
+        assert!(found(Some(ids[3])).await.is_empty());
+
+        // Recorded just now: only the latest page shows it until it settles
+        let fresh = Swap::create(&new_swap(5, 5, "buy", 10), &pool)
+            .await
+            .unwrap();
+        assert!(found(Some(ids[3])).await.is_empty());
+        assert_eq!(found(None).await[0], fresh.id);
+    }
+
+    #[sqlx::test]
+    async fn token_swaps_filter_by_type_size_and_time(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        for swap in [
+            new_swap(1, 5, "buy", 100),
+            new_swap(2, 10, "sell", 2_000),
+            new_swap(3, 20, "buy", 6_000),
+            new_swap(4, 120, "buy", 9_000),
+        ] {
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+        let found = |filter: SwapFilter| {
+            let pool = pool.clone();
+            async move {
+                Swap::find_by_token(&address(1), &filter, 10, &pool)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|s| s.wallet_address)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(found(SwapFilter::default()).await.len(), 4);
+        let buys = SwapFilter {
+            trade_type: Some("buy".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            found(buys).await,
+            vec![address(51), address(53), address(54)]
+        );
+        let large = SwapFilter {
+            min_usd: Some(BigDecimal::from(2_000)),
+            ..Default::default()
+        };
+        assert_eq!(
+            found(large).await,
+            vec![address(52), address(53), address(54)]
+        );
+        let recent_whales = SwapFilter {
+            whales_only: true,
+            since: Some(Utc::now() - Duration::hours(1)),
+            ..Default::default()
+        };
+        assert_eq!(found(recent_whales).await, vec![address(53)]);
+    }
+
+    #[sqlx::test]
+    async fn hourly_windows_only_count_recent_trades(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        for swap in [
+            new_swap(1, 5, "buy", 100),
+            new_swap(2, 30, "sell", 40),
+            new_swap(3, 50, "buy", 6_000),
+            new_swap(4, 90, "buy", 1_000),
+        ] {
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+
+        let (total, buys, sells) = Swap::count_trades_1h(&address(1), &pool).await.unwrap();
+        assert_eq!((total, buys, sells), (3, 2, 1));
+
+        let volume = Swap::volume_1h(&address(1), &pool).await.unwrap();
+        assert_eq!(volume, BigDecimal::from(6_140));
+
+        let since = Utc::now() - Duration::minutes(40);
+        let recent = Swap::find_recent_by_token(&address(1), since, &pool)
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 2);
+
+        let whales = Swap::find_whale_trades(10, &pool).await.unwrap();
+        assert_eq!(whales.len(), 1);
+        assert_eq!(whales[0].tx_hash, hash(3));
+
+        let by_wallet = Swap::find_by_wallet(&address(52), 10, &pool).await.unwrap();
+        assert_eq!(by_wallet.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn activity_is_bucketed_by_weekday_and_hour(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let at = |s: &str| s.parse::<chrono::DateTime<Utc>>().unwrap();
+        // Two trades on Monday at 09:xx, one on Sunday at 23:59, one too old
+        for (n, time, usd) in [
+            (1, "2025-12-01T09:05:00Z", 100),
+            (2, "2025-12-01T09:55:00Z", 300),
+            (3, "2025-12-07T23:59:00Z", 50),
+            (4, "2025-11-01T09:00:00Z", 1_000),
+        ] {
+            let swap = NewSwap {
+                timestamp: at(time),
+                ..new_swap(n, 0, "buy", usd)
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+
+        let cells = Swap::activity_by_hour(&address(1), at("2025-11-20T00:00:00Z"), &pool)
+            .await
+            .unwrap();
+        let slots: Vec<_> = cells
+            .iter()
+            .map(|c| (c.day_of_week, c.hour, c.trades))
+            .collect();
+        assert_eq!(slots, vec![(1, 9, 2), (7, 23, 1)]);
+        assert_eq!(cells[0].volume_usd, BigDecimal::from(400));
+    }
+
+    #[sqlx::test]
+    async fn wash_trading_stats_break_down_volume(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        // Wallet 60 round-trips; 61 and 62 pass tokens back and forth
+        for (n, wallet, trade_type, usd) in [
+            (1, 60, "buy", 100),
+            (2, 60, "sell", 100),
+            (3, 61, "buy", 50),
+            (4, 62, "buy", 30),
+        ] {
+            let swap = NewSwap {
+                wallet_address: address(wallet),
+                ..new_swap(n, 5, trade_type, usd)
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+        for (n, from, to) in [(10, 61, 62), (11, 62, 61)] {
+            for (wallet, action) in [(from, "transfer_out"), (to, "transfer_in")] {
+                let activity = NewWalletActivity {
+                    wallet_address: address(wallet),
+                    tx_hash: hash(n),
+                    block_number: 1_000,
+                    timestamp: Utc::now(),
+                    action: action.to_string(),
+                    token_address: address(1),
+                    token_symbol: None,
+                    amount_tokens: Some(BigDecimal::from(1)),
+                    amount_usd: None,
+                    source_log_id: None,
+                };
+                WalletActivity::create(&activity, &pool).await.unwrap();
+            }
+        }
+
+        let since = Utc::now() - Duration::hours(1);
+        let stats = Swap::wash_trading_stats(&address(1), since, &pool)
+            .await
+            .unwrap();
+        assert_eq!(stats.trades, 4);
+        assert_eq!(stats.volume_usd, BigDecimal::from(280));
+        assert_eq!(stats.round_trip_usd, BigDecimal::from(200));
+        assert_eq!(stats.circular_usd, BigDecimal::from(80));
+        assert_eq!(stats.top2_usd, BigDecimal::from(250));
+
+        assert_eq!(
+            Swap::traded_tokens_since(since, &pool).await.unwrap(),
+            vec![address(1)]
+        );
+    }
+
+    #[sqlx::test]
+    async fn legs_give_per_side_volume(pool: PgPool) {
+        // Buys send the base token in, sells send the token in; swap 3 is token-token
+        for (n, token_in, amount_in, token_out, amount_out) in [
+            (1, 200, "0.5", 1, "1000"),
+            (2, 1, "400.25", 200, "0.2"),
+            (3, 2, "10", 1, "60"),
+        ] {
+            let swap = NewSwap {
+                legs: Some(SwapLegs {
+                    token_in: address(token_in),
+                    amount_in: amount_in.parse().unwrap(),
+                    token_out: address(token_out),
+                    amount_out: amount_out.parse().unwrap(),
+                    fee_amount: None,
+                    fee_usd: None,
+                }),
+                ..new_swap(n, 5, "buy", 10)
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+        Swap::create(&new_swap(4, 5, "buy", 10), &pool)
+            .await
+            .unwrap();
+
+        let since = Utc::now() - Duration::hours(1);
+        let (sold, bought) = Swap::leg_volumes(&address(1), since, &pool).await.unwrap();
+        assert_eq!(sold, "400.25".parse::<BigDecimal>().unwrap());
+        assert_eq!(bought, BigDecimal::from(1_060));
+
+        let swaps = Swap::find_by_token(&address(1), &SwapFilter::default(), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(swaps.iter().filter(|s| s.token_in.is_some()).count(), 3);
+    }
+
+    #[sqlx::test]
+    async fn price_points_keep_the_last_price_per_interval(pool: PgPool) {
+        // Start of a 5 minute interval, so all three trades fall in it
+        let minute = Utc::now() - Duration::minutes(15);
+        let minute = minute - Duration::seconds(minute.timestamp() % 300);
+        for (n, seconds, price, usd) in [(1, 5, 1, 10), (2, 40, 3, 20), (3, 70, 2, 5)] {
+            let swap = NewSwap {
+                timestamp: minute + Duration::seconds(seconds),
+                price_usd: Some(BigDecimal::from(price)),
+                ..new_swap(n, 0, "buy", usd)
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+
+        let since = minute - Duration::hours(1);
+        let points = Swap::price_points(&address(1), since, Utc::now(), 60, &pool)
+            .await
+            .unwrap();
+        let points: Vec<_> = points
+            .iter()
+            .map(|p| (p.price_usd.clone().unwrap(), p.volume_usd.clone()))
+            .collect();
+        assert_eq!(
+            points,
+            vec![
+                (BigDecimal::from(3), BigDecimal::from(30)),
+                (BigDecimal::from(2), BigDecimal::from(5)),
+            ]
+        );
+
+        let points = Swap::price_points(&address(1), since, Utc::now(), 300, &pool)
+            .await
+            .unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].avg_price_usd, Some(BigDecimal::from(2)));
+    }
+    #[sqlx::test]
+    async fn wallet_summary_spans_every_token(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let now = Utc::now();
+        for (n, token, minutes_ago, trade_type, usd) in [
+            (1, 1, 120, "buy", 1_000),
+            (2, 1, 60, "sell", 1_500),
+            (3, 2, 90, "buy", 6_000),
+            (4, 2, 30, "sell", 2_000),
+            // Still held
+            (5, 3, 10, "buy", 500),
+        ] {
+            let swap = NewSwap {
+                timestamp: now - Duration::minutes(minutes_ago),
+                token_address: address(token),
+                wallet_address: address(77),
+                ..new_swap(n, minutes_ago, trade_type, usd)
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+
+        let summary = Swap::wallet_summary(&address(77), &pool).await.unwrap();
+        assert_eq!(
+            (summary.trades, summary.tokens_traded, summary.whale_trades),
+            (5, 3, 1)
+        );
+        assert_eq!(summary.volume_usd, BigDecimal::from(11_000));
+        assert_eq!(summary.avg_hold_secs, Some(3_600.0));
+        assert_eq!((summary.tokens_sold, summary.tokens_won), (2, 1));
+        assert_eq!(summary.biggest_win_token, Some(address(1)));
+        assert_eq!(summary.biggest_win_usd, Some(BigDecimal::from(500)));
+        assert_eq!(summary.biggest_loss_token, Some(address(2)));
+        assert_eq!(summary.biggest_loss_usd, Some(BigDecimal::from(-4_000)));
+
+        // Half of what was bought, sold above the buy price: a realized win,
+        // though fewer dollars came back than went in
+        for (n, trade_type, tokens, usd) in [(6, "buy", 10, 1_000), (7, "sell", 4, 600)] {
+            let swap = NewSwap {
+                token_address: address(4),
+                wallet_address: address(79),
+                amount_tokens: Some(BigDecimal::from(tokens)),
+                ..new_swap(n, 10, trade_type, usd)
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+        let partial = Swap::wallet_summary(&address(79), &pool).await.unwrap();
+        assert_eq!((partial.tokens_sold, partial.tokens_won), (1, 1));
+        assert_eq!(partial.biggest_win_usd, Some(BigDecimal::from(200)));
+
+        let idle = Swap::wallet_summary(&address(78), &pool).await.unwrap();
+        assert_eq!((idle.trades, idle.tokens_traded), (0, 0));
+        assert_eq!(idle.volume_usd, BigDecimal::from(0));
+        assert_eq!(idle.avg_hold_secs, None);
+        assert_eq!(idle.biggest_win_token, None);
+    }
 }
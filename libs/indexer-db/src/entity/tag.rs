@@ -0,0 +1,200 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// Tag entity: a free-form label shared by wallets and tokens
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Tag {
+    pub id: i32,
+    /// Trimmed and lowercased
+    pub name: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Tag with how many wallets and tokens carry it
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagWithCounts {
+    pub name: String,
+    pub wallets: i64,
+    pub tokens: i64,
+}
+
+/// What a tag is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagSubject {
+    Wallet,
+    Token,
+}
+
+impl TagSubject {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagSubject::Wallet => "wallet",
+            TagSubject::Token => "token",
+        }
+    }
+}
+
+impl Tag {
+    /// Attach a tag to a wallet or token, creating the tag if needed
+    pub async fn attach<'c, E>(
+        subject: TagSubject,
+        address: &Address20,
+        name: &str,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            WITH tag AS (
+                INSERT INTO tags (name) VALUES (lower(trim($3)))
+                ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
+                RETURNING id
+            )
+            INSERT INTO taggings (tag_id, subject_kind, subject_address)
+            SELECT id, $1, $2 FROM tag
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(subject.as_str())
+        .bind(address)
+        .bind(name)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a tag from a wallet or token; false if it wasn't attached
+    pub async fn detach<'c, E>(
+        subject: TagSubject,
+        address: &Address20,
+        name: &str,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM taggings tg
+            USING tags t
+            WHERE t.id = tg.tag_id
+              AND t.name = lower(trim($3))
+              AND tg.subject_kind = $1
+              AND tg.subject_address = $2
+            "#,
+        )
+        .bind(subject.as_str())
+        .bind(address)
+        .bind(name)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Tag names on each of `addresses`, sorted by name
+    pub async fn find_names<'c, E>(
+        subject: TagSubject,
+        addresses: &[Address20],
+        connection: E,
+    ) -> Result<Vec<(Address20, String)>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, (Address20, String)>(
+            r#"
+            SELECT tg.subject_address, t.name
+            FROM taggings tg
+            JOIN tags t ON t.id = tg.tag_id
+            WHERE tg.subject_kind = $1 AND tg.subject_address = ANY($2)
+            ORDER BY t.name
+            "#,
+        )
+        .bind(subject.as_str())
+        .bind(addresses)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Get every tag with its usage counts, most used first
+    pub async fn find_all_with_counts<'c, E>(
+        connection: E,
+    ) -> Result<Vec<TagWithCounts>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TagWithCounts>(
+            r#"
+            SELECT
+                t.name,
+                COUNT(*) FILTER (WHERE tg.subject_kind = 'wallet') AS wallets,
+                COUNT(*) FILTER (WHERE tg.subject_kind = 'token') AS tokens
+            FROM tags t
+            LEFT JOIN taggings tg ON tg.tag_id = t.id
+            GROUP BY t.id
+            ORDER BY COUNT(tg.tag_id) DESC, t.name
+            "#,
+        )
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::test_support::address;
+
+    #[sqlx::test]
+    async fn tags_attach_once_and_normalize_names(pool: PgPool) {
+        Tag::attach(TagSubject::Wallet, &address(1), " Insider ", &pool)
+            .await
+            .unwrap();
+        Tag::attach(TagSubject::Wallet, &address(1), "insider", &pool)
+            .await
+            .unwrap();
+        Tag::attach(TagSubject::Wallet, &address(1), "CEX", &pool)
+            .await
+            .unwrap();
+        Tag::attach(TagSubject::Token, &address(1), "gem", &pool)
+            .await
+            .unwrap();
+
+        let names = Tag::find_names(TagSubject::Wallet, &[address(1), address(2)], &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            names,
+            vec![
+                (address(1), "cex".to_string()),
+                (address(1), "insider".to_string())
+            ]
+        );
+
+        let counts = Tag::find_all_with_counts(&pool).await.unwrap();
+        assert_eq!(
+            counts
+                .iter()
+                .map(|t| (t.name.as_str(), t.wallets, t.tokens))
+                .collect::<Vec<_>>(),
+            vec![("cex", 1, 0), ("gem", 0, 1), ("insider", 1, 0)]
+        );
+
+        assert!(
+            Tag::detach(TagSubject::Wallet, &address(1), "INSIDER", &pool)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !Tag::detach(TagSubject::Token, &address(1), "insider", &pool)
+                .await
+                .unwrap()
+        );
+    }
+}
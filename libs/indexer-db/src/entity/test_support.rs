@@ -0,0 +1,30 @@
+//! Shared helpers for the `#[sqlx::test]` entity tests
+
+use sqlx::PgPool;
+
+use crate::types::{Address20, Hash32};
+
+/// Address with every byte set to `n`
+pub fn address(n: u8) -> Address20 {
+    Address20::new([n; 20])
+}
+
+/// Hash with every byte set to `n`
+pub fn hash(n: u8) -> Hash32 {
+    Hash32::new([n; 32])
+}
+
+/// Drop the demo rows from the seed migration, for tests of table-wide queries
+pub async fn clear_seed_data(pool: &PgPool) {
+    sqlx::query(
+        r#"
+        TRUNCATE tokens, swaps, lp_locks, price_snapshots, wallet_activity,
+                 token_holders, alert_events, alert_webhook_deliveries, pairs, wallets,
+                 token_metrics_minute
+        RESTART IDENTITY
+        "#,
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
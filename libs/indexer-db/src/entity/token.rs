@@ -4,17 +4,25 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use crate::types::Address20;
+
 /// Token entity representing a BEP-20 token tracked by BeanBee
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Token {
     pub id: i32,
-    pub address: String,
+    pub address: Address20,
+    /// Sanitized for display; see `name_raw`
     pub name: Option<String>,
     pub symbol: Option<String>,
+    /// Name and symbol exactly as the contract returned them
+    pub name_raw: Option<String>,
+    pub symbol_raw: Option<String>,
+    /// The raw name or symbol hid invisible characters between visible ones
+    pub name_spoofed: Option<bool>,
     pub decimals: Option<i16>,
     pub total_supply: Option<BigDecimal>,
-    pub pair_address: Option<String>,
-    pub creator_address: Option<String>,
+    pub pair_address: Option<Address20>,
+    pub creator_address: Option<Address20>,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     pub block_number: Option<i64>,
 
@@ -32,6 +40,7 @@ pub struct Token {
     pub trades_24h: Option<i32>,
     pub buys_1h: Option<i32>,
     pub sells_1h: Option<i32>,
+    pub wash_trading_score: Option<BigDecimal>,
 
     // Holder metrics
     pub holder_count: Option<i32>,
@@ -39,6 +48,14 @@ pub struct Token {
     pub top_10_holder_percent: Option<BigDecimal>,
     pub dev_holdings_percent: Option<BigDecimal>,
     pub sniper_ratio: Option<BigDecimal>,
+    pub holders_entered_24h: Option<i32>,
+    pub holders_exited_24h: Option<i32>,
+    /// Exits over the last 24h per 100 holders at the start of the window
+    pub holder_churn_rate_24h: Option<BigDecimal>,
+    /// Holders other than wallets in `bot_wallets`
+    pub organic_holder_count: Option<i32>,
+    /// Share (%) of total supply held by wallets in `bot_wallets`
+    pub bot_holder_percent: Option<BigDecimal>,
 
     // Safety flags
     pub lp_locked: Option<bool>,
@@ -53,18 +70,27 @@ pub struct Token {
 
     pub last_updated: Option<chrono::DateTime<chrono::Utc>>,
     pub indexed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Set while the token is archived as dead, see [`Token::archive_dead`]
+    pub archived_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Worst severity of the token's external risk reports
+    pub external_risk: Option<String>,
+    /// Block time of the latest transfer out of a dev wallet
+    pub dev_sold_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Input for creating a new token
 #[derive(Debug, Clone)]
 pub struct NewToken {
-    pub address: String,
+    pub address: Address20,
     pub name: Option<String>,
     pub symbol: Option<String>,
+    pub name_raw: Option<String>,
+    pub symbol_raw: Option<String>,
+    pub name_spoofed: bool,
     pub decimals: Option<i16>,
     pub total_supply: Option<BigDecimal>,
-    pub pair_address: Option<String>,
-    pub creator_address: Option<String>,
+    pub pair_address: Option<Address20>,
+    pub creator_address: Option<Address20>,
     pub block_number: Option<i64>,
 }
 
@@ -86,6 +112,57 @@ pub struct TokenMetrics {
     pub sells_1h: i32,
 }
 
+/// A token resembling another one's launch profile, with what matched
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct SimilarToken {
+    #[sqlx(flatten)]
+    pub token: Token,
+    /// Trigram similarity (0-1) of the name or symbol, whichever is closer
+    pub name_similarity: f32,
+    /// Deployed by the same wallet
+    pub same_deployer: bool,
+    /// Liquidity within the same order of magnitude
+    pub same_liquidity_band: bool,
+    /// Holder count within the same order of magnitude
+    pub same_holder_band: bool,
+    /// Launched within a day of the other token
+    pub similar_age: bool,
+    /// Weighted match (0-1): name 0.4, deployer 0.25, liquidity 0.15,
+    /// holders 0.1, age 0.1
+    pub score: f64,
+}
+
+/// Weights of a client-defined ranking. Volume, liquidity, holders and
+/// momentum are ranked 0-1 against every token that traded in the last 24h;
+/// BeeScore counts as is (0-100 scaled to 0-1).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankWeights {
+    pub volume: f64,
+    pub liquidity: f64,
+    pub holders: f64,
+    pub bee_score: f64,
+    /// 24h price change
+    pub momentum: f64,
+}
+
+/// A token with its score under client-supplied weights
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct RankedToken {
+    #[sqlx(flatten)]
+    pub token: Token,
+    /// Weighted average of the ranked metrics, 0-100
+    pub custom_score: f64,
+}
+
+/// What one garbage collection pass archived
+#[derive(sqlx::FromRow, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchivedTokens {
+    pub tokens: i64,
+    /// Holder rows and price snapshots taken out of the live tables
+    pub holders: i64,
+    pub snapshots: i64,
+}
+
 impl Token {
     /// Create a new token record
     pub async fn create<'c, E>(token: &NewToken, connection: E) -> Result<Token, sqlx::Error>
@@ -93,11 +170,14 @@ impl Token {
         E: Executor<'c, Database = Postgres>,
     {
         let query = r#"
-            INSERT INTO tokens (address, name, symbol, decimals, total_supply, pair_address, creator_address, block_number, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            INSERT INTO tokens (address, name, symbol, name_raw, symbol_raw, name_spoofed, decimals, total_supply, pair_address, creator_address, block_number, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
             ON CONFLICT (address) DO UPDATE SET
                 name = COALESCE(EXCLUDED.name, tokens.name),
                 symbol = COALESCE(EXCLUDED.symbol, tokens.symbol),
+                name_raw = COALESCE(EXCLUDED.name_raw, tokens.name_raw),
+                symbol_raw = COALESCE(EXCLUDED.symbol_raw, tokens.symbol_raw),
+                name_spoofed = EXCLUDED.name_spoofed OR tokens.name_spoofed,
                 decimals = COALESCE(EXCLUDED.decimals, tokens.decimals),
                 total_supply = COALESCE(EXCLUDED.total_supply, tokens.total_supply),
                 pair_address = COALESCE(EXCLUDED.pair_address, tokens.pair_address),
@@ -106,21 +186,69 @@ impl Token {
         "#;
 
         sqlx::query_as::<_, Token>(query)
-            .bind(&token.address)
+            .bind(token.address)
+            .bind(&token.name)
+            .bind(&token.symbol)
+            .bind(&token.name_raw)
+            .bind(&token.symbol_raw)
+            .bind(token.name_spoofed)
+            .bind(token.decimals)
+            .bind(&token.total_supply)
+            .bind(token.pair_address)
+            .bind(token.creator_address)
+            .bind(token.block_number)
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Insert a token from a seed dump, created at `created_at` (now when
+    /// `None`). A token already indexed only has its missing fields filled
+    /// in, so indexed data always wins and re-imports change nothing. Returns
+    /// whether it was inserted.
+    pub async fn import<'c, E>(
+        token: &NewToken,
+        created_at: Option<chrono::DateTime<chrono::Utc>>,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO tokens (address, name, symbol, name_raw, symbol_raw, name_spoofed, decimals, total_supply, pair_address, creator_address, block_number, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, COALESCE($12, NOW()))
+            ON CONFLICT (address) DO UPDATE SET
+                name = COALESCE(tokens.name, EXCLUDED.name),
+                symbol = COALESCE(tokens.symbol, EXCLUDED.symbol),
+                name_raw = COALESCE(tokens.name_raw, EXCLUDED.name_raw),
+                symbol_raw = COALESCE(tokens.symbol_raw, EXCLUDED.symbol_raw),
+                decimals = COALESCE(tokens.decimals, EXCLUDED.decimals),
+                total_supply = COALESCE(tokens.total_supply, EXCLUDED.total_supply),
+                pair_address = COALESCE(tokens.pair_address, EXCLUDED.pair_address),
+                creator_address = COALESCE(tokens.creator_address, EXCLUDED.creator_address),
+                block_number = COALESCE(tokens.block_number, EXCLUDED.block_number)
+            RETURNING (xmax = 0) AS inserted
+        "#;
+
+        sqlx::query_scalar::<_, bool>(query)
+            .bind(token.address)
             .bind(&token.name)
             .bind(&token.symbol)
+            .bind(&token.name_raw)
+            .bind(&token.symbol_raw)
+            .bind(token.name_spoofed)
             .bind(token.decimals)
             .bind(&token.total_supply)
-            .bind(&token.pair_address)
-            .bind(&token.creator_address)
+            .bind(token.pair_address)
+            .bind(token.creator_address)
             .bind(token.block_number)
+            .bind(created_at)
             .fetch_one(connection)
             .await
     }
 
     /// Find token by address
     pub async fn find_by_address<'c, E>(
-        address: &str,
+        address: &Address20,
         connection: E,
     ) -> Result<Option<Token>, sqlx::Error>
     where
@@ -132,9 +260,75 @@ impl Token {
             .await
     }
 
+    /// Up to `limit` token addresses after `after`, in address order, for
+    /// walking every token a page at a time
+    pub async fn find_addresses_after<'c, E>(
+        after: Option<&Address20>,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            r#"
+            SELECT address FROM tokens
+            WHERE $1::BYTEA IS NULL OR address > $1
+            ORDER BY address
+            LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Point a token at the pair its price and liquidity come from
+    pub async fn set_pair<'c, E>(
+        address: &Address20,
+        pair_address: &Address20,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("UPDATE tokens SET pair_address = $2, last_updated = NOW() WHERE address = $1")
+            .bind(address)
+            .bind(pair_address)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Store decimals read from the chain, unless the token has them already.
+    /// Returns the token's decimals.
+    pub async fn set_decimals<'c, E>(
+        address: &Address20,
+        decimals: i16,
+        connection: E,
+    ) -> Result<Option<i16>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar::<_, Option<i16>>(
+            r#"
+            UPDATE tokens SET decimals = COALESCE(decimals, $2), last_updated = NOW()
+            WHERE address = $1
+            RETURNING decimals
+            "#,
+        )
+        .bind(address)
+        .bind(decimals)
+        .fetch_optional(connection)
+        .await
+        .map(Option::flatten)
+    }
+
     /// Find token by pair address
     pub async fn find_by_pair_address<'c, E>(
-        pair_address: &str,
+        pair_address: &Address20,
         connection: E,
     ) -> Result<Option<Token>, sqlx::Error>
     where
@@ -185,17 +379,17 @@ impl Token {
 
     /// Update token price and volume metrics
     pub async fn update_price_metrics<'c, E>(
-        address: &str,
+        address: &Address20,
         price_usd: &BigDecimal,
         price_bnb: &BigDecimal,
         liquidity_usd: &BigDecimal,
         liquidity_bnb: &BigDecimal,
         connection: E,
-    ) -> Result<(), sqlx::Error>
+    ) -> Result<Option<Token>, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
-        sqlx::query(
+        sqlx::query_as::<_, Token>(
             r#"
             UPDATE tokens SET
                 price_usd = $2,
@@ -204,6 +398,7 @@ impl Token {
                 liquidity_bnb = $5,
                 last_updated = NOW()
             WHERE address = $1
+            RETURNING *
             "#,
         )
         .bind(address)
@@ -211,58 +406,95 @@ impl Token {
         .bind(price_bnb)
         .bind(liquidity_usd)
         .bind(liquidity_bnb)
-        .execute(connection)
-        .await?;
-
-        Ok(())
+        .fetch_optional(connection)
+        .await
     }
 
-    /// Increment trade counters
-    pub async fn increment_trade_count<'c, E>(
-        address: &str,
-        is_buy: bool,
-        amount_usd: &BigDecimal,
+    /// Recompute the cached 1h/24h trade counters from the minute buckets
+    pub async fn refresh_trade_rollup<'c, E>(
+        address: &Address20,
         connection: E,
-    ) -> Result<(), sqlx::Error>
+    ) -> Result<Option<Token>, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
-        let query = if is_buy {
+        sqlx::query_as::<_, Token>(
             r#"
             UPDATE tokens SET
-                trades_1h = COALESCE(trades_1h, 0) + 1,
-                trades_24h = COALESCE(trades_24h, 0) + 1,
-                buys_1h = COALESCE(buys_1h, 0) + 1,
-                volume_1h_usd = COALESCE(volume_1h_usd, 0) + $2,
-                volume_24h_usd = COALESCE(volume_24h_usd, 0) + $2,
+                volume_1h_usd = m.volume_1h_usd,
+                volume_24h_usd = m.volume_24h_usd,
+                trades_1h = m.trades_1h,
+                trades_24h = m.trades_24h,
+                buys_1h = m.buys_1h,
+                sells_1h = m.sells_1h,
                 last_updated = NOW()
-            WHERE address = $1
-            "#
-        } else {
+            FROM (
+                SELECT
+                    COALESCE(SUM(volume_usd) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour'), 0) AS volume_1h_usd,
+                    COALESCE(SUM(volume_usd), 0) AS volume_24h_usd,
+                    COALESCE(SUM(trades) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour'), 0) AS trades_1h,
+                    COALESCE(SUM(trades), 0) AS trades_24h,
+                    COALESCE(SUM(buys) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour'), 0) AS buys_1h,
+                    COALESCE(SUM(sells) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour'), 0) AS sells_1h
+                FROM token_metrics_minute
+                WHERE token_address = $1 AND minute >= NOW() - INTERVAL '24 hours'
+            ) m
+            WHERE tokens.address = $1
+            RETURNING tokens.*
+            "#,
+        )
+        .bind(address)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Recompute the cached trade counters for every token whose windows changed,
+    /// so tokens without new swaps still age out of the 1h/24h windows
+    pub async fn refresh_all_trade_rollups<'c, E>(connection: E) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
             r#"
             UPDATE tokens SET
-                trades_1h = COALESCE(trades_1h, 0) + 1,
-                trades_24h = COALESCE(trades_24h, 0) + 1,
-                sells_1h = COALESCE(sells_1h, 0) + 1,
-                volume_1h_usd = COALESCE(volume_1h_usd, 0) + $2,
-                volume_24h_usd = COALESCE(volume_24h_usd, 0) + $2,
+                volume_1h_usd = COALESCE(m.volume_1h_usd, 0),
+                volume_24h_usd = COALESCE(m.volume_24h_usd, 0),
+                trades_1h = COALESCE(m.trades_1h, 0),
+                trades_24h = COALESCE(m.trades_24h, 0),
+                buys_1h = COALESCE(m.buys_1h, 0),
+                sells_1h = COALESCE(m.sells_1h, 0),
                 last_updated = NOW()
-            WHERE address = $1
-            "#
-        };
-
-        sqlx::query(query)
-            .bind(address)
-            .bind(amount_usd)
-            .execute(connection)
-            .await?;
+            FROM tokens t
+            LEFT JOIN (
+                SELECT
+                    token_address,
+                    SUM(volume_usd) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour') AS volume_1h_usd,
+                    SUM(volume_usd) AS volume_24h_usd,
+                    SUM(trades) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour') AS trades_1h,
+                    SUM(trades) AS trades_24h,
+                    SUM(buys) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour') AS buys_1h,
+                    SUM(sells) FILTER (WHERE minute >= NOW() - INTERVAL '1 hour') AS sells_1h
+                FROM token_metrics_minute
+                WHERE minute >= NOW() - INTERVAL '24 hours'
+                GROUP BY token_address
+            ) m ON m.token_address = t.address
+            WHERE tokens.id = t.id
+              AND (
+                  m.token_address IS NOT NULL
+                  OR COALESCE(t.trades_24h, 0) > 0
+                  OR COALESCE(t.volume_24h_usd, 0) > 0
+              )
+            "#,
+        )
+        .execute(connection)
+        .await?;
 
-        Ok(())
+        Ok(result.rows_affected())
     }
 
     /// Update BeeScore
     pub async fn update_bee_score<'c, E>(
-        address: &str,
+        address: &Address20,
         bee_score: i16,
         safety_score: i16,
         traction_score: i16,
@@ -293,7 +525,7 @@ impl Token {
 
     /// Update holder metrics
     pub async fn update_holder_metrics<'c, E>(
-        address: &str,
+        address: &Address20,
         holder_count: i32,
         top_10_percent: &BigDecimal,
         dev_percent: &BigDecimal,
@@ -325,11 +557,147 @@ impl Token {
         Ok(())
     }
 
-    /// Update LP lock status
+    /// Recompute holder count, top-10 concentration, dev and bot holdings
+    /// from `token_holders`. Bot holdings count wallets in `bot_wallets`, so
+    /// this is rerun when bot-held supply moves or a holder turns out a bot.
+    pub async fn refresh_holder_metrics<'c, E>(
+        address: &Address20,
+        connection: E,
+    ) -> Result<Option<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Token>(
+            r#"
+            UPDATE tokens t SET
+                holder_count = h.holders,
+                top_10_holder_percent =
+                    COALESCE(LEAST(ROUND(h.top_10 / NULLIF(t.total_supply, 0) * 100, 2), 100), 0),
+                dev_holdings_percent =
+                    COALESCE(LEAST(ROUND(h.dev / NULLIF(t.total_supply, 0) * 100, 2), 100), 0),
+                organic_holder_count = h.holders - h.bots,
+                bot_holder_percent =
+                    COALESCE(LEAST(ROUND(h.bot / NULLIF(t.total_supply, 0) * 100, 2), 100), 0),
+                last_updated = NOW()
+            FROM (
+                SELECT
+                    COUNT(*) FILTER (WHERE th.balance > 0)::INT AS holders,
+                    COALESCE(SUM(th.balance) FILTER (WHERE th.is_dev = TRUE AND th.balance > 0), 0) AS dev,
+                    COUNT(b.wallet_address) FILTER (WHERE th.balance > 0)::INT AS bots,
+                    COALESCE(SUM(th.balance) FILTER (WHERE b.wallet_address IS NOT NULL AND th.balance > 0), 0) AS bot,
+                    (
+                        SELECT COALESCE(SUM(balance), 0) FROM (
+                            SELECT balance FROM token_holders
+                            WHERE token_address = $1 AND balance > 0
+                            ORDER BY balance DESC
+                            LIMIT 10
+                        ) top
+                    ) AS top_10
+                FROM token_holders th
+                LEFT JOIN bot_wallets b ON b.wallet_address = th.wallet_address
+                WHERE th.token_address = $1
+            ) h
+            WHERE t.address = $1
+            RETURNING t.*
+            "#,
+        )
+        .bind(address)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Recompute `sniper_ratio` as the percent of total supply held by sniper wallets
+    pub async fn refresh_sniper_ratio<'c, E>(
+        address: &Address20,
+        connection: E,
+    ) -> Result<Option<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Token>(
+            r#"
+            UPDATE tokens t SET
+                sniper_ratio = COALESCE(LEAST(ROUND(s.held / NULLIF(t.total_supply, 0) * 100, 2), 100), 0),
+                last_updated = NOW()
+            FROM (
+                SELECT COALESCE(SUM(balance), 0) AS held
+                FROM token_holders
+                WHERE token_address = $1 AND is_sniper = TRUE AND balance > 0
+            ) s
+            WHERE t.address = $1
+            RETURNING t.*
+            "#,
+        )
+        .bind(address)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Update the wash trading score (0-100)
+    pub async fn update_wash_trading_score<'c, E>(
+        address: &Address20,
+        score: &BigDecimal,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            "UPDATE tokens SET wash_trading_score = $2, last_updated = NOW() WHERE address = $1",
+        )
+        .bind(address)
+        .bind(score)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Recompute the 24h holder churn from the hourly buckets and current holders
+    pub async fn refresh_holder_churn<'c, E>(
+        address: &Address20,
+        connection: E,
+    ) -> Result<Option<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Token>(
+            r#"
+            UPDATE tokens t SET
+                holders_entered_24h = c.entered,
+                holders_exited_24h = c.exited,
+                -- Holders at the window start: current ones, minus entries, plus exits
+                holder_churn_rate_24h = COALESCE(LEAST(ROUND(
+                    c.exited::NUMERIC
+                        / NULLIF(GREATEST(h.holders - c.entered + c.exited, c.exited), 0)
+                        * 100,
+                    2), 100), 0),
+                last_updated = NOW()
+            FROM (
+                SELECT COALESCE(SUM(entered), 0)::INT AS entered,
+                       COALESCE(SUM(exited), 0)::INT AS exited
+                FROM token_holder_churn_hourly
+                WHERE token_address = $1 AND hour >= date_trunc('hour', NOW()) - INTERVAL '23 hours'
+            ) c,
+            (
+                SELECT COUNT(*)::INT AS holders
+                FROM token_holders
+                WHERE token_address = $1 AND balance > 0
+            ) h
+            WHERE t.address = $1
+            RETURNING t.*
+            "#,
+        )
+        .bind(address)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Update LP lock status; a `None` percent keeps the current one
     pub async fn update_lp_lock<'c, E>(
-        address: &str,
+        address: &Address20,
         lp_locked: bool,
-        lp_lock_percent: &BigDecimal,
+        lp_lock_percent: Option<&BigDecimal>,
         unlock_date: Option<chrono::DateTime<chrono::Utc>>,
         connection: E,
     ) -> Result<(), sqlx::Error>
@@ -340,7 +708,7 @@ impl Token {
             r#"
             UPDATE tokens SET
                 lp_locked = $2,
-                lp_lock_percent = $3,
+                lp_lock_percent = COALESCE($3, lp_lock_percent),
                 lp_unlock_date = $4,
                 last_updated = NOW()
             WHERE address = $1
@@ -356,6 +724,276 @@ impl Token {
         Ok(())
     }
 
+    /// Record a transfer out of a dev wallet at block time `at`; an older one
+    /// (replayed by a rescan) leaves the latest in place
+    pub async fn record_dev_sell<'c, E>(
+        address: &Address20,
+        at: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Option<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Token>(
+            "UPDATE tokens SET dev_sold_at = GREATEST(dev_sold_at, $2) WHERE address = $1 RETURNING *",
+        )
+        .bind(address)
+        .bind(at)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Tokens with a launch profile like `address`'s, best match first
+    ///
+    /// Candidates share a trigram-similar name or symbol, or the deployer;
+    /// they are then ranked on name, deployer, liquidity and holder bands
+    /// and launch time. Empty if `address` is unknown.
+    pub async fn find_similar<'c, E>(
+        address: &Address20,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<SimilarToken>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, SimilarToken>(
+            r#"
+            WITH target AS (SELECT * FROM tokens WHERE address = $1),
+            candidates AS (
+                SELECT
+                    t.*,
+                    GREATEST(
+                        COALESCE(similarity(lower(t.name), lower(target.name)), 0),
+                        COALESCE(similarity(lower(t.symbol), lower(target.symbol)), 0)
+                    )::REAL AS name_similarity,
+                    COALESCE(t.creator_address = target.creator_address, FALSE) AS same_deployer,
+                    COALESCE(
+                        floor(log(t.liquidity_usd + 1)) = floor(log(target.liquidity_usd + 1)),
+                        FALSE
+                    ) AS same_liquidity_band,
+                    COALESCE(
+                        floor(log(t.holder_count + 1.0)) = floor(log(target.holder_count + 1.0)),
+                        FALSE
+                    ) AS same_holder_band,
+                    COALESCE(
+                        abs(extract(epoch FROM t.created_at - target.created_at)) <= 86400,
+                        FALSE
+                    ) AS similar_age
+                FROM tokens t, target
+                WHERE t.address <> target.address
+                  AND (
+                      lower(t.name) % lower(target.name)
+                      OR lower(t.symbol) % lower(target.symbol)
+                      OR t.creator_address = target.creator_address
+                  )
+            )
+            SELECT
+                *,
+                (
+                    0.4 * name_similarity
+                    + 0.25 * same_deployer::INT
+                    + 0.15 * same_liquidity_band::INT
+                    + 0.1 * same_holder_band::INT
+                    + 0.1 * similar_age::INT
+                )::FLOAT8 AS score
+            FROM candidates
+            ORDER BY score DESC, created_at DESC NULLS LAST
+            LIMIT $2
+            "#,
+        )
+        .bind(address)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Tokens that traded in the last 24h, best first under `weights`
+    ///
+    /// The weights are bound as parameters, never spliced into the query, and
+    /// must not all be zero.
+    pub async fn find_ranked<'c, E>(
+        weights: &RankWeights,
+        limit: i32,
+        connection: E,
+    ) -> Result<Vec<RankedToken>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, RankedToken>(
+            r#"
+            WITH candidates AS (
+                SELECT
+                    t.*,
+                    percent_rank() OVER (ORDER BY COALESCE(t.volume_24h_usd, 0)) AS volume_rank,
+                    percent_rank() OVER (ORDER BY COALESCE(t.liquidity_usd, 0)) AS liquidity_rank,
+                    percent_rank() OVER (ORDER BY COALESCE(t.holder_count, 0)) AS holders_rank,
+                    percent_rank() OVER (ORDER BY COALESCE(t.price_change_24h, 0)) AS momentum_rank
+                FROM tokens t
+                WHERE COALESCE(t.trades_24h, 0) > 0 OR COALESCE(t.volume_24h_usd, 0) > 0
+            )
+            SELECT
+                *,
+                (
+                    100 * (
+                        $1 * volume_rank
+                        + $2 * liquidity_rank
+                        + $3 * holders_rank
+                        + $4 * COALESCE(bee_score, 0) / 100.0
+                        + $5 * momentum_rank
+                    ) / ($1 + $2 + $3 + $4 + $5)
+                )::FLOAT8 AS custom_score
+            FROM candidates
+            ORDER BY custom_score DESC, COALESCE(volume_24h_usd, 0) DESC, id DESC
+            LIMIT $6
+            "#,
+        )
+        .bind(weights.volume)
+        .bind(weights.liquidity)
+        .bind(weights.holders)
+        .bind(weights.bee_score)
+        .bind(weights.momentum)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Archive up to `limit` dead tokens: no liquidity and no swaps or
+    /// transfers for `idle_days`, and created before that
+    ///
+    /// Their holders and price snapshots leave the live tables, moved to
+    /// `token_holders_cold` and `price_snapshots_cold` when `keep_cold` is set
+    /// and deleted otherwise. Everything happens in one statement, so a token
+    /// is never flagged with its rows half moved.
+    pub async fn archive_dead<'c, E>(
+        idle_days: i32,
+        limit: i64,
+        keep_cold: bool,
+        connection: E,
+    ) -> Result<ArchivedTokens, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ArchivedTokens>(
+            r#"
+            WITH dead AS (
+                SELECT t.address FROM tokens t
+                WHERE t.archived_at IS NULL
+                    AND COALESCE(t.liquidity_usd, 0) = 0
+                    AND COALESCE(t.created_at, '-infinity') < NOW() - make_interval(days => $1)
+                    AND NOT EXISTS (
+                        SELECT 1 FROM swaps s
+                        WHERE s.token_address = t.address
+                            AND s.timestamp >= NOW() - make_interval(days => $1)
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1 FROM wallet_activity w
+                        WHERE w.token_address = t.address
+                            AND w.timestamp >= NOW() - make_interval(days => $1)
+                    )
+                ORDER BY t.id
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            ),
+            flagged AS (
+                UPDATE tokens SET archived_at = NOW()
+                WHERE address IN (SELECT address FROM dead)
+                RETURNING address
+            ),
+            holders AS (
+                DELETE FROM token_holders
+                WHERE token_address IN (SELECT address FROM flagged)
+                RETURNING *
+            ),
+            cold_holders AS (
+                INSERT INTO token_holders_cold SELECT * FROM holders WHERE $3
+            ),
+            snapshots AS (
+                DELETE FROM price_snapshots
+                WHERE token_address IN (SELECT address FROM flagged)
+                RETURNING *
+            ),
+            cold_snapshots AS (
+                INSERT INTO price_snapshots_cold SELECT * FROM snapshots WHERE $3
+            )
+            SELECT
+                (SELECT COUNT(*) FROM flagged) AS tokens,
+                (SELECT COUNT(*) FROM holders) AS holders,
+                (SELECT COUNT(*) FROM snapshots) AS snapshots
+            "#,
+        )
+        .bind(idle_days)
+        .bind(limit)
+        .bind(keep_cold)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Get a token and lock its row until the transaction ends, so an
+    /// archive or revival of it running meanwhile is seen whole
+    pub async fn find_for_update<'c, E>(
+        address: &Address20,
+        connection: E,
+    ) -> Result<Option<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Token>("SELECT * FROM tokens WHERE address = $1 FOR UPDATE")
+            .bind(address)
+            .fetch_optional(connection)
+            .await
+    }
+
+    /// Bring an archived token back, with the holders and snapshots kept in
+    /// the cold tables. `false` if it wasn't archived. Run it after
+    /// [`Token::find_for_update`] in the same transaction: on its own it may
+    /// not see the cold rows of an archive that committed while it waited.
+    ///
+    /// Holders written to the live table after the token was archived were
+    /// counted from a zero balance, so the cold balance is added to theirs;
+    /// a live snapshot at the same time as a cold one is the newer reading
+    /// and is kept.
+    pub async fn unarchive<'c, E>(address: &Address20, connection: E) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar::<_, bool>(
+            r#"
+            WITH revived AS (
+                UPDATE tokens SET archived_at = NULL
+                WHERE address = $1 AND archived_at IS NOT NULL
+                RETURNING address
+            ),
+            holders AS (
+                DELETE FROM token_holders_cold
+                WHERE token_address IN (SELECT address FROM revived)
+                RETURNING *
+            ),
+            restored_holders AS (
+                INSERT INTO token_holders SELECT * FROM holders
+                ON CONFLICT (token_address, wallet_address) DO UPDATE SET
+                    balance = COALESCE(token_holders.balance, 0) + COALESCE(EXCLUDED.balance, 0),
+                    is_dev = token_holders.is_dev OR EXCLUDED.is_dev,
+                    is_sniper = token_holders.is_sniper OR EXCLUDED.is_sniper,
+                    is_contract = token_holders.is_contract OR EXCLUDED.is_contract,
+                    first_buy_block = LEAST(token_holders.first_buy_block, EXCLUDED.first_buy_block)
+            ),
+            snapshots AS (
+                DELETE FROM price_snapshots_cold
+                WHERE token_address IN (SELECT address FROM revived)
+                RETURNING *
+            ),
+            restored_snapshots AS (
+                INSERT INTO price_snapshots SELECT * FROM snapshots
+                ON CONFLICT (token_address, timestamp) DO NOTHING
+            )
+            SELECT EXISTS (SELECT 1 FROM revived)
+            "#,
+        )
+        .bind(address)
+        .fetch_one(connection)
+        .await
+    }
+
     /// Convert to TokenMetrics for BeeScore calculation
     pub fn to_metrics(&self) -> TokenMetrics {
         TokenMetrics {
@@ -399,3 +1037,539 @@ impl Token {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        price_snapshot::{NewPriceSnapshot, PriceSnapshot},
+        swap::{NewSwap, Swap},
+        test_support::{address, clear_seed_data, hash},
+        token_holder::{NewTokenHolder, TokenHolder},
+        token_metrics_minute::TokenMetricsMinute,
+    };
+
+    fn new_token(n: u8, name: Option<&str>) -> NewToken {
+        NewToken {
+            address: address(n),
+            name: name.map(str::to_string),
+            symbol: Some(format!("T{}", n)),
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: Some(BigDecimal::from(1_000_000)),
+            pair_address: None,
+            creator_address: Some(address(200)),
+            block_number: Some(100),
+        }
+    }
+
+    #[sqlx::test]
+    async fn create_upserts_without_clobbering_known_fields(pool: PgPool) {
+        let mut spoofed = new_token(1, Some("First"));
+        spoofed.name_raw = Some("Fi\u{200B}rst".to_string());
+        spoofed.name_spoofed = true;
+        let first = Token::create(&spoofed, &pool).await.unwrap();
+
+        let mut update = new_token(1, None);
+        update.pair_address = Some(address(101));
+        update.creator_address = Some(address(201));
+        let second = Token::create(&update, &pool).await.unwrap();
+
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.name.as_deref(), Some("First"));
+        assert_eq!(second.name_raw.as_deref(), Some("Fi\u{200B}rst"));
+        assert_eq!(second.name_spoofed, Some(true));
+        assert_eq!(second.pair_address, Some(address(101)));
+        // Creator is only set on first insert
+        assert_eq!(second.creator_address, Some(address(200)));
+
+        let by_pair = Token::find_by_pair_address(&address(101), &pool)
+            .await
+            .unwrap();
+        assert_eq!(by_pair.map(|t| t.id), Some(first.id));
+        assert!(Token::find_by_address(&address(2), &pool)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[sqlx::test]
+    async fn addresses_are_paged_in_order(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        for n in [3, 1, 2] {
+            Token::create(&new_token(n, None), &pool).await.unwrap();
+        }
+
+        let first = Token::find_addresses_after(None, 2, &pool).await.unwrap();
+        assert_eq!(first, vec![address(1), address(2)]);
+        let rest = Token::find_addresses_after(first.last(), 2, &pool)
+            .await
+            .unwrap();
+        assert_eq!(rest, vec![address(3)]);
+    }
+
+    #[sqlx::test]
+    async fn decimals_are_only_filled_in(pool: PgPool) {
+        let mut unknown = new_token(1, None);
+        unknown.decimals = None;
+        Token::create(&unknown, &pool).await.unwrap();
+        Token::create(&new_token(2, None), &pool).await.unwrap();
+
+        assert_eq!(Token::set_decimals(&address(1), 9, &pool).await.unwrap(), Some(9));
+        assert_eq!(Token::set_decimals(&address(2), 9, &pool).await.unwrap(), Some(18));
+        assert_eq!(Token::set_decimals(&address(3), 9, &pool).await.unwrap(), None);
+    }
+
+    #[sqlx::test]
+    async fn dev_sells_keep_the_latest_time(pool: PgPool) {
+        Token::create(&new_token(1, None), &pool).await.unwrap();
+        let latest = Utc::now() - Duration::hours(1);
+
+        Token::record_dev_sell(&address(1), latest, &pool)
+            .await
+            .unwrap();
+        Token::record_dev_sell(&address(1), latest - Duration::days(1), &pool)
+            .await
+            .unwrap();
+
+        let token = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            token.dev_sold_at.map(|at| at.timestamp()),
+            Some(latest.timestamp())
+        );
+    }
+
+    #[sqlx::test]
+    async fn similar_tokens_match_names_and_deployers(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        let mut original = new_token(1, Some("Pepe Moon"));
+        original.symbol = Some("PEPEMOON".to_string());
+        original.creator_address = Some(address(201));
+        Token::create(&original, &pool).await.unwrap();
+
+        // Copycat name from another deployer
+        let mut copycat = new_token(2, Some("Pepe Moon 2.0"));
+        copycat.symbol = Some("PEPEMOON2".to_string());
+        Token::create(&copycat, &pool).await.unwrap();
+
+        // Unrelated name, same deployer
+        let mut sibling = new_token(3, Some("Shiba Rocket"));
+        sibling.creator_address = Some(address(201));
+        Token::create(&sibling, &pool).await.unwrap();
+
+        // Unrelated
+        Token::create(&new_token(4, Some("Doge Classic")), &pool)
+            .await
+            .unwrap();
+
+        let similar = Token::find_similar(&address(1), 10, &pool).await.unwrap();
+        let found: Vec<_> = similar.iter().map(|s| s.token.address).collect();
+        assert_eq!(found, vec![address(2), address(3)]);
+
+        let copycat = &similar[0];
+        assert!(copycat.name_similarity > 0.5);
+        assert!(!copycat.same_deployer);
+        assert!(copycat.similar_age);
+        assert!(similar[1].same_deployer);
+        assert!(copycat.score > similar[1].score);
+
+        assert!(Token::find_similar(&address(9), 10, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test]
+    async fn trade_rollup_sums_minute_windows(pool: PgPool) {
+        let token = Token::create(&new_token(1, None), &pool)
+            .await
+            .unwrap()
+            .address;
+        let now = Utc::now();
+
+        for (minutes_ago, is_buy, usd) in [
+            (0, true, 100),
+            (30, false, 50),
+            (180, true, 1_000),
+            (1_800, true, 9_999),
+        ] {
+            TokenMetricsMinute::record_trade(
+                &token,
+                now - Duration::minutes(minutes_ago),
+                is_buy,
+                &BigDecimal::from(usd),
+                &pool,
+            )
+            .await
+            .unwrap();
+        }
+
+        Token::refresh_trade_rollup(&token, &pool).await.unwrap();
+        let token = Token::find_by_address(&token, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(token.trades_1h, Some(2));
+        assert_eq!(token.buys_1h, Some(1));
+        assert_eq!(token.sells_1h, Some(1));
+        assert_eq!(token.volume_1h_usd, Some(BigDecimal::from(150)));
+        assert_eq!(token.trades_24h, Some(3));
+        assert_eq!(token.volume_24h_usd, Some(BigDecimal::from(1_150)));
+    }
+
+    #[sqlx::test]
+    async fn refresh_all_trade_rollups_decays_idle_tokens(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        let idle = Token::create(&new_token(1, None), &pool)
+            .await
+            .unwrap()
+            .address;
+        let active = Token::create(&new_token(2, None), &pool)
+            .await
+            .unwrap()
+            .address;
+        Token::create(&new_token(3, None), &pool).await.unwrap();
+
+        sqlx::query("UPDATE tokens SET trades_24h = 7, volume_24h_usd = 700 WHERE address = $1")
+            .bind(idle)
+            .execute(&pool)
+            .await
+            .unwrap();
+        TokenMetricsMinute::record_trade(&active, Utc::now(), true, &BigDecimal::from(5), &pool)
+            .await
+            .unwrap();
+
+        // The untouched third token has nothing to refresh
+        let refreshed = Token::refresh_all_trade_rollups(&pool).await.unwrap();
+        assert_eq!(refreshed, 2);
+
+        let idle = Token::find_by_address(&idle, &pool).await.unwrap().unwrap();
+        assert_eq!(idle.trades_24h, Some(0));
+        assert_eq!(idle.volume_24h_usd, Some(BigDecimal::from(0)));
+
+        let active = Token::find_by_address(&active, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(active.trades_1h, Some(1));
+    }
+
+    #[sqlx::test]
+    async fn find_hot_ranks_by_volume_and_score(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        for n in 1..=3 {
+            Token::create(&new_token(n, None), &pool).await.unwrap();
+        }
+        Token::update_bee_score(&address(1), 20, 10, 10, &pool)
+            .await
+            .unwrap();
+        Token::update_bee_score(&address(2), 10, 5, 5, &pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE tokens SET volume_1h_usd = 5000 WHERE address = $1")
+            .bind(address(2))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let hot = Token::find_hot(10, &pool).await.unwrap();
+        let ranked: Vec<_> = hot.iter().map(|t| t.address).collect();
+        assert_eq!(ranked, vec![address(2), address(1)]);
+
+        let newest = Token::find_newest(2, &pool).await.unwrap();
+        assert_eq!(newest.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn ranking_follows_client_weights(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        // Token 1 trades the most, token 2 has the deepest liquidity and most
+        // holders, token 3 never traded
+        for n in 1..=3 {
+            Token::create(&new_token(n, None), &pool).await.unwrap();
+        }
+        for (n, volume, liquidity, holders) in [(1, 90_000, 1_000, 10), (2, 1_000, 50_000, 500)] {
+            sqlx::query(
+                r#"
+                UPDATE tokens SET trades_24h = 5, volume_24h_usd = $2, liquidity_usd = $3,
+                    holder_count = $4
+                WHERE address = $1
+                "#,
+            )
+            .bind(address(n))
+            .bind(volume)
+            .bind(liquidity)
+            .bind(holders)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let weights = RankWeights {
+            volume: 2.0,
+            liquidity: 0.0,
+            holders: 0.0,
+            bee_score: 0.0,
+            momentum: 0.0,
+        };
+        let ranked = Token::find_ranked(&weights, 10, &pool).await.unwrap();
+        let order: Vec<_> = ranked.iter().map(|r| r.token.address).collect();
+        assert_eq!(order, vec![address(1), address(2)]);
+        assert_eq!(ranked[0].custom_score, 100.0);
+        assert_eq!(ranked[1].custom_score, 0.0);
+
+        let weights = RankWeights {
+            volume: 1.0,
+            liquidity: 0.5,
+            holders: 1.0,
+            ..weights
+        };
+        let ranked = Token::find_ranked(&weights, 10, &pool).await.unwrap();
+        assert_eq!(ranked[0].token.address, address(2));
+        assert_eq!(ranked[0].custom_score, 60.0);
+    }
+
+    #[sqlx::test]
+    async fn sniper_ratio_is_percent_of_supply_held_by_snipers(pool: PgPool) {
+        let token = Token::create(&new_token(1, None), &pool)
+            .await
+            .unwrap()
+            .address;
+
+        for (wallet, balance, is_sniper) in
+            [(10, 50_000, true), (11, 25_000, true), (12, 100_000, false)]
+        {
+            let holder = NewTokenHolder {
+                token_address: token,
+                wallet_address: address(wallet),
+                balance: BigDecimal::from(balance),
+                is_dev: false,
+                is_sniper,
+                is_contract: false,
+                first_buy_block: Some(101),
+                source: None,
+            };
+            TokenHolder::upsert(&holder, &pool).await.unwrap();
+        }
+
+        Token::refresh_sniper_ratio(&token, &pool).await.unwrap();
+        let refreshed = Token::find_by_address(&token, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refreshed.sniper_ratio, Some("7.50".parse().unwrap()));
+    }
+
+    #[sqlx::test]
+    async fn holder_metrics_are_recomputed_from_holders(pool: PgPool) {
+        let token = Token::create(&new_token(1, None), &pool)
+            .await
+            .unwrap()
+            .address;
+
+        // Twelve holders of 10k each, one of them the dev, and one that sold out
+        for wallet in 10..23 {
+            let holder = NewTokenHolder {
+                token_address: token,
+                wallet_address: address(wallet),
+                balance: BigDecimal::from(if wallet == 22 { 0 } else { 10_000 }),
+                is_dev: wallet == 10,
+                is_sniper: false,
+                is_contract: false,
+                first_buy_block: Some(101),
+                source: None,
+            };
+            TokenHolder::upsert(&holder, &pool).await.unwrap();
+        }
+
+        Token::refresh_holder_metrics(&token, &pool).await.unwrap();
+        let refreshed = Token::find_by_address(&token, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refreshed.holder_count, Some(12));
+        assert_eq!(
+            refreshed.top_10_holder_percent,
+            Some("10.00".parse().unwrap())
+        );
+        assert_eq!(
+            refreshed.dev_holdings_percent,
+            Some("1.00".parse().unwrap())
+        );
+    }
+
+    #[sqlx::test]
+    async fn dead_tokens_are_archived_to_cold_tables_and_revived(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        for n in 1..=3 {
+            Token::create(&new_token(n, None), &pool).await.unwrap();
+            let holder = NewTokenHolder {
+                token_address: address(n),
+                wallet_address: address(10),
+                balance: BigDecimal::from(500),
+                is_dev: false,
+                is_sniper: false,
+                is_contract: false,
+                first_buy_block: Some(101),
+                source: None,
+            };
+            TokenHolder::upsert(&holder, &pool).await.unwrap();
+            let snapshot = NewPriceSnapshot {
+                token_address: address(n),
+                timestamp: Utc::now() - Duration::days(40),
+                price_usd: Some(BigDecimal::from(1)),
+                price_bnb: None,
+                liquidity_usd: None,
+                volume_usd: None,
+                market_cap_usd: None,
+                holder_count: Some(1),
+            };
+            PriceSnapshot::create(&snapshot, &pool).await.unwrap();
+        }
+        sqlx::query("UPDATE tokens SET created_at = NOW() - INTERVAL '60 days'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        // Token 2 still has liquidity and token 3 still trades
+        sqlx::query("UPDATE tokens SET liquidity_usd = 1000 WHERE address = $1")
+            .bind(address(2))
+            .execute(&pool)
+            .await
+            .unwrap();
+        let swap = NewSwap {
+            tx_hash: hash(1),
+            block_number: 1_000,
+            log_index: 0,
+            timestamp: Utc::now() - Duration::days(2),
+            pair_address: address(100),
+            token_address: address(3),
+            wallet_address: address(10),
+            trade_type: "buy".to_string(),
+            amount_tokens: Some(BigDecimal::from(1)),
+            amount_bnb: Some(BigDecimal::from(1)),
+            amount_usd: Some(BigDecimal::from(1)),
+            price_usd: Some(BigDecimal::from(1)),
+            is_whale: false,
+            tx_index: None,
+            gas_price_percentile: None,
+            mev_flags: Vec::new(),
+            legs: None,
+            source_log_id: None,
+        };
+        Swap::create(&swap, &pool).await.unwrap();
+
+        let archived = Token::archive_dead(30, 100, true, &pool).await.unwrap();
+        assert_eq!(
+            archived,
+            ArchivedTokens {
+                tokens: 1,
+                holders: 1,
+                snapshots: 1
+            }
+        );
+        let dead = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(dead.archived_at.is_some());
+        assert_eq!(
+            TokenHolder::count_holders(&address(1), &pool)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            TokenHolder::count_holders(&address(3), &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        // Already archived tokens aren't counted again
+        assert_eq!(
+            Token::archive_dead(30, 100, true, &pool).await.unwrap(),
+            ArchivedTokens::default()
+        );
+
+        // A transfer handled while it was archived counted from zero
+        let mut late = NewTokenHolder {
+            token_address: address(1),
+            wallet_address: address(10),
+            balance: BigDecimal::from(20),
+            is_dev: false,
+            is_sniper: true,
+            is_contract: false,
+            first_buy_block: Some(900),
+            source: None,
+        };
+        TokenHolder::upsert(&late, &pool).await.unwrap();
+        late.wallet_address = address(11);
+        TokenHolder::upsert(&late, &pool).await.unwrap();
+
+        assert!(Token::unarchive(&address(1), &pool).await.unwrap());
+        assert!(!Token::unarchive(&address(1), &pool).await.unwrap());
+        assert_eq!(
+            TokenHolder::count_holders(&address(1), &pool)
+                .await
+                .unwrap(),
+            2
+        );
+        let merged = TokenHolder::find_balance(&address(1), &address(10), &pool)
+            .await
+            .unwrap();
+        assert_eq!(merged, Some(BigDecimal::from(520)));
+        assert_eq!(
+            PriceSnapshot::find_by_token(&address(1), 10, &pool)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Transfers count as activity too
+        sqlx::query(
+            "INSERT INTO wallet_activity (wallet_address, tx_hash, block_number, timestamp, action, token_address)
+             VALUES ($1, $2, 1000, NOW() - INTERVAL '2 days', 'transfer_in', $3)",
+        )
+        .bind(address(10))
+        .bind(hash(2))
+        .bind(address(3))
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("DELETE FROM swaps WHERE token_address = $1")
+            .bind(address(3))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Without cold storage the rows are gone for good
+        let archived = Token::archive_dead(30, 100, false, &pool).await.unwrap();
+        assert_eq!(archived.tokens, 1);
+        assert!(Token::unarchive(&address(1), &pool).await.unwrap());
+        assert_eq!(
+            TokenHolder::count_holders(&address(1), &pool)
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(Token::find_by_address(&address(3), &pool)
+            .await
+            .unwrap()
+            .unwrap()
+            .archived_at
+            .is_none());
+    }
+}
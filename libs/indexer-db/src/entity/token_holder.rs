@@ -4,12 +4,15 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use super::evm_logs::SourceLog;
+use crate::types::{Address20, Hash32};
+
 /// TokenHolder entity representing a wallet holding a token
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct TokenHolder {
     pub id: i32,
-    pub token_address: String,
-    pub wallet_address: String,
+    pub token_address: Address20,
+    pub wallet_address: Address20,
     pub balance: Option<BigDecimal>,
     pub percent_of_supply: Option<BigDecimal>,
     pub is_dev: Option<bool>,
@@ -17,18 +20,23 @@ pub struct TokenHolder {
     pub is_contract: Option<bool>,
     pub first_buy_block: Option<i64>,
     pub last_updated: Option<chrono::DateTime<chrono::Utc>>,
+    /// Last log to change the balance; both `None` after an on-chain
+    /// correction
+    pub source_log_id: Option<i32>,
+    pub source_tx_hash: Option<Hash32>,
 }
 
 /// Input for creating/updating a token holder
 #[derive(Debug, Clone)]
 pub struct NewTokenHolder {
-    pub token_address: String,
-    pub wallet_address: String,
+    pub token_address: Address20,
+    pub wallet_address: Address20,
     pub balance: BigDecimal,
     pub is_dev: bool,
     pub is_sniper: bool,
     pub is_contract: bool,
     pub first_buy_block: Option<i64>,
+    pub source: Option<SourceLog>,
 }
 
 impl TokenHolder {
@@ -38,61 +46,106 @@ impl TokenHolder {
         E: Executor<'c, Database = Postgres>,
     {
         let query = r#"
-            INSERT INTO token_holders (token_address, wallet_address, balance, is_dev, is_sniper, is_contract, first_buy_block)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO token_holders (token_address, wallet_address, balance, is_dev, is_sniper, is_contract, first_buy_block, source_log_id, source_tx_hash)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             ON CONFLICT (token_address, wallet_address) DO UPDATE SET
                 balance = EXCLUDED.balance,
                 is_dev = token_holders.is_dev OR EXCLUDED.is_dev,
                 is_sniper = token_holders.is_sniper OR EXCLUDED.is_sniper,
                 is_contract = token_holders.is_contract OR EXCLUDED.is_contract,
                 first_buy_block = COALESCE(token_holders.first_buy_block, EXCLUDED.first_buy_block),
+                source_log_id = EXCLUDED.source_log_id,
+                source_tx_hash = EXCLUDED.source_tx_hash,
                 last_updated = NOW()
             RETURNING *
         "#;
 
         sqlx::query_as::<_, TokenHolder>(query)
-            .bind(&holder.token_address)
-            .bind(&holder.wallet_address)
+            .bind(holder.token_address)
+            .bind(holder.wallet_address)
             .bind(&holder.balance)
             .bind(holder.is_dev)
             .bind(holder.is_sniper)
             .bind(holder.is_contract)
             .bind(holder.first_buy_block)
+            .bind(holder.source.and_then(|s| s.log_id))
+            .bind(holder.source.map(|s| s.tx_hash))
             .fetch_one(connection)
             .await
     }
 
-    /// Update holder balance
+    /// Update holder balance, recording the log that moved it (`None` for
+    /// corrections from on-chain reads). Returns the updated holder.
     pub async fn update_balance<'c, E>(
-        token_address: &str,
-        wallet_address: &str,
+        token_address: &Address20,
+        wallet_address: &Address20,
         balance: &BigDecimal,
+        source: Option<SourceLog>,
         connection: E,
-    ) -> Result<(), sqlx::Error>
+    ) -> Result<TokenHolder, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
-        sqlx::query(
+        sqlx::query_as::<_, TokenHolder>(
             r#"
-            INSERT INTO token_holders (token_address, wallet_address, balance)
-            VALUES ($1, $2, $3)
+            INSERT INTO token_holders (token_address, wallet_address, balance, source_log_id, source_tx_hash)
+            VALUES ($1, $2, $3, $4, $5)
             ON CONFLICT (token_address, wallet_address) DO UPDATE SET
                 balance = EXCLUDED.balance,
+                source_log_id = EXCLUDED.source_log_id,
+                source_tx_hash = EXCLUDED.source_tx_hash,
                 last_updated = NOW()
+            RETURNING *
             "#,
         )
         .bind(token_address)
         .bind(wallet_address)
         .bind(balance)
-        .execute(connection)
+        .bind(source.and_then(|s| s.log_id))
+        .bind(source.map(|s| s.tx_hash))
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Get a wallet's current balance of a token (`None` if never seen)
+    pub async fn find_balance<'c, E>(
+        token_address: &Address20,
+        wallet_address: &Address20,
+        connection: E,
+    ) -> Result<Option<BigDecimal>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let balance: Option<Option<BigDecimal>> = sqlx::query_scalar(
+            "SELECT balance FROM token_holders WHERE token_address = $1 AND wallet_address = $2",
+        )
+        .bind(token_address)
+        .bind(wallet_address)
+        .fetch_optional(connection)
         .await?;
 
-        Ok(())
+        Ok(balance.flatten())
+    }
+
+    /// Tokens a wallet holds a balance of
+    pub async fn find_tokens_held<'c, E>(
+        wallet_address: &Address20,
+        connection: E,
+    ) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            "SELECT token_address FROM token_holders WHERE wallet_address = $1 AND balance > 0",
+        )
+        .bind(wallet_address)
+        .fetch_all(connection)
+        .await
     }
 
     /// Get top holders for a token
     pub async fn find_top_holders<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         limit: i32,
         connection: E,
     ) -> Result<Vec<TokenHolder>, sqlx::Error>
@@ -108,9 +161,59 @@ impl TokenHolder {
         .await
     }
 
+    /// Holders of a token in id order, `limit` at a time after `after_id`
+    pub async fn find_page<'c, E>(
+        token_address: &Address20,
+        after_id: i32,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<TokenHolder>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenHolder>(
+            r#"
+            SELECT * FROM token_holders
+            WHERE token_address = $1 AND id > $2
+            ORDER BY id
+            LIMIT $3
+            "#,
+        )
+        .bind(token_address)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Delete a wallet's holding of a token that it no longer holds. Dev
+    /// wallets are kept at a zero balance so they stay flagged if they buy
+    /// back. Returns whether the row was deleted.
+    pub async fn prune<'c, E>(
+        token_address: &Address20,
+        wallet_address: &Address20,
+        connection: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM token_holders
+            WHERE token_address = $1 AND wallet_address = $2 AND is_dev IS NOT TRUE
+            "#,
+        )
+        .bind(token_address)
+        .bind(wallet_address)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Count holders for a token
     pub async fn count_holders<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<i64, sqlx::Error>
     where
@@ -128,7 +231,7 @@ impl TokenHolder {
 
     /// Get dev holders for a token
     pub async fn find_dev_holders<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<Vec<TokenHolder>, sqlx::Error>
     where
@@ -144,23 +247,30 @@ impl TokenHolder {
 
     /// Get sniper holders for a token
     pub async fn find_sniper_holders<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
+        limit: i32,
         connection: E,
     ) -> Result<Vec<TokenHolder>, sqlx::Error>
     where
         E: Executor<'c, Database = Postgres>,
     {
         sqlx::query_as::<_, TokenHolder>(
-            "SELECT * FROM token_holders WHERE token_address = $1 AND is_sniper = TRUE",
+            r#"
+            SELECT * FROM token_holders
+            WHERE token_address = $1 AND is_sniper = TRUE
+            ORDER BY balance DESC NULLS LAST
+            LIMIT $2
+            "#,
         )
         .bind(token_address)
+        .bind(limit)
         .fetch_all(connection)
         .await
     }
 
     /// Calculate top 10 holders percentage
     pub async fn calculate_top_10_percent<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         connection: E,
     ) -> Result<BigDecimal, sqlx::Error>
     where
@@ -187,8 +297,8 @@ impl TokenHolder {
 
     /// Mark holder as dev
     pub async fn mark_as_dev<'c, E>(
-        token_address: &str,
-        wallet_address: &str,
+        token_address: &Address20,
+        wallet_address: &Address20,
         connection: E,
     ) -> Result<(), sqlx::Error>
     where
@@ -207,8 +317,8 @@ impl TokenHolder {
 
     /// Mark holder as sniper
     pub async fn mark_as_sniper<'c, E>(
-        token_address: &str,
-        wallet_address: &str,
+        token_address: &Address20,
+        wallet_address: &Address20,
         connection: E,
     ) -> Result<(), sqlx::Error>
     where
@@ -227,7 +337,7 @@ impl TokenHolder {
 
     /// Update percent of supply for all holders of a token
     pub async fn recalculate_percentages<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         total_supply: &BigDecimal,
         connection: E,
     ) -> Result<(), sqlx::Error>
@@ -250,3 +360,180 @@ impl TokenHolder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::test_support::{address, hash};
+
+    fn holder(wallet: u8, balance: i32) -> NewTokenHolder {
+        NewTokenHolder {
+            token_address: address(1),
+            wallet_address: address(wallet),
+            balance: BigDecimal::from(balance),
+            is_dev: false,
+            is_sniper: false,
+            is_contract: false,
+            first_buy_block: None,
+            source: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn upsert_keeps_flags_and_first_buy_block(pool: PgPool) {
+        let mut first = holder(10, 100);
+        first.is_sniper = true;
+        first.first_buy_block = Some(5);
+        let created = TokenHolder::upsert(&first, &pool).await.unwrap();
+
+        let mut second = holder(10, 40);
+        second.is_dev = true;
+        second.first_buy_block = Some(9);
+        let updated = TokenHolder::upsert(&second, &pool).await.unwrap();
+
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.balance, Some(BigDecimal::from(40)));
+        assert_eq!(updated.is_sniper, Some(true));
+        assert_eq!(updated.is_dev, Some(true));
+        assert_eq!(updated.first_buy_block, Some(5));
+
+        assert_eq!(
+            TokenHolder::find_dev_holders(&address(1), &pool)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            TokenHolder::find_sniper_holders(&address(1), 10, &pool)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[sqlx::test]
+    async fn balances_record_the_log_that_moved_them(pool: PgPool) {
+        let received = SourceLog {
+            log_id: Some(7),
+            tx_hash: hash(1),
+        };
+        let mut first = holder(10, 100);
+        first.source = Some(received);
+        let created = TokenHolder::upsert(&first, &pool).await.unwrap();
+        assert_eq!(created.source_log_id, Some(7));
+        assert_eq!(created.source_tx_hash, Some(hash(1)));
+
+        // A log from another queue has no evm_logs id
+        let sent = SourceLog {
+            log_id: None,
+            tx_hash: hash(2),
+        };
+        let balance = BigDecimal::from(60);
+        TokenHolder::update_balance(&address(1), &address(10), &balance, Some(sent), &pool)
+            .await
+            .unwrap();
+        let top = TokenHolder::find_top_holders(&address(1), 1, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            (top[0].source_log_id, top[0].source_tx_hash),
+            (None, Some(hash(2)))
+        );
+
+        // On-chain corrections have no log behind them
+        TokenHolder::update_balance(&address(1), &address(10), &balance, None, &pool)
+            .await
+            .unwrap();
+        let top = TokenHolder::find_top_holders(&address(1), 1, &pool)
+            .await
+            .unwrap();
+        assert_eq!(top[0].source_tx_hash, None);
+    }
+
+    #[sqlx::test]
+    async fn supply_percentages_and_top_holders(pool: PgPool) {
+        for (wallet, balance) in [(10, 500), (11, 300), (12, 200), (13, 0)] {
+            TokenHolder::upsert(&holder(wallet, balance), &pool)
+                .await
+                .unwrap();
+        }
+        let balance = BigDecimal::from(100);
+        TokenHolder::update_balance(&address(1), &address(12), &balance, None, &pool)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            TokenHolder::count_holders(&address(1), &pool)
+                .await
+                .unwrap(),
+            3
+        );
+
+        let top = TokenHolder::find_top_holders(&address(1), 2, &pool)
+            .await
+            .unwrap();
+        let wallets: Vec<_> = top.iter().map(|h| h.wallet_address).collect();
+        assert_eq!(wallets, vec![address(10), address(11)]);
+
+        TokenHolder::recalculate_percentages(&address(1), &BigDecimal::from(1_000), &pool)
+            .await
+            .unwrap();
+        let top10 = TokenHolder::calculate_top_10_percent(&address(1), &pool)
+            .await
+            .unwrap();
+        assert_eq!(top10, BigDecimal::from(90));
+
+        TokenHolder::mark_as_dev(&address(1), &address(11), &pool)
+            .await
+            .unwrap();
+        TokenHolder::mark_as_sniper(&address(1), &address(12), &pool)
+            .await
+            .unwrap();
+        let devs = TokenHolder::find_dev_holders(&address(1), &pool)
+            .await
+            .unwrap();
+        assert_eq!(devs[0].wallet_address, address(11));
+        let snipers = TokenHolder::find_sniper_holders(&address(1), 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(snipers[0].wallet_address, address(12));
+    }
+
+    #[sqlx::test]
+    async fn holders_are_paged_and_pruned(pool: PgPool) {
+        for wallet in 10..15 {
+            TokenHolder::upsert(&holder(wallet, 100), &pool)
+                .await
+                .unwrap();
+        }
+        TokenHolder::mark_as_dev(&address(1), &address(11), &pool)
+            .await
+            .unwrap();
+
+        let first = TokenHolder::find_page(&address(1), 0, 3, &pool)
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 3);
+        let rest = TokenHolder::find_page(&address(1), first[2].id, 3, &pool)
+            .await
+            .unwrap();
+        let wallets: Vec<_> = rest.iter().map(|h| h.wallet_address).collect();
+        assert_eq!(wallets, vec![address(13), address(14)]);
+
+        assert!(TokenHolder::prune(&address(1), &address(10), &pool)
+            .await
+            .unwrap());
+        // Devs are kept
+        assert!(!TokenHolder::prune(&address(1), &address(11), &pool)
+            .await
+            .unwrap());
+        let left = TokenHolder::find_page(&address(1), 0, 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(left.len(), 4);
+    }
+}
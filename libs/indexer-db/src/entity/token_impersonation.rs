@@ -0,0 +1,207 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// TokenImpersonation entity: a token whose name or symbol copies an
+/// established token's
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TokenImpersonation {
+    pub token_address: Address20,
+    /// The token being impersonated
+    pub target_address: Address20,
+    pub target_source: String, // "top", "trending"
+    pub matched_field: String, // "name", "symbol"
+    /// 0-1, 1 being identical after normalization
+    pub similarity: BigDecimal,
+    pub detected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for flagging an impersonation
+#[derive(Debug, Clone)]
+pub struct NewTokenImpersonation {
+    pub token_address: Address20,
+    pub target_address: Address20,
+    pub target_source: String,
+    pub matched_field: String,
+    pub similarity: BigDecimal,
+}
+
+/// An established token new launches are compared against
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ImpersonationTarget {
+    pub address: Address20,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    /// `top` (by market cap) or `trending`
+    pub source: String,
+}
+
+impl TokenImpersonation {
+    /// Record a flag; `None` if the token was already flagged
+    pub async fn create<'c, E>(
+        flag: &NewTokenImpersonation,
+        connection: E,
+    ) -> Result<Option<TokenImpersonation>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenImpersonation>(
+            r#"
+            INSERT INTO token_impersonations (
+                token_address, target_address, target_source, matched_field, similarity
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (token_address) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(flag.token_address)
+        .bind(flag.target_address)
+        .bind(&flag.target_source)
+        .bind(&flag.matched_field)
+        .bind(&flag.similarity)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// The flag on a token, if any
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        connection: E,
+    ) -> Result<Option<TokenImpersonation>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenImpersonation>(
+            "SELECT * FROM token_impersonations WHERE token_address = $1",
+        )
+        .bind(token_address)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// The `top_n` tokens by market cap plus those in the trending top list,
+    /// each once (as `top` if it is both)
+    pub async fn find_targets<'c, E>(
+        top_n: i64,
+        connection: E,
+    ) -> Result<Vec<ImpersonationTarget>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, ImpersonationTarget>(
+            r#"
+            SELECT DISTINCT ON (address) address, name, symbol, source
+            FROM (
+                (
+                    SELECT address, name, symbol, 'top' AS source
+                    FROM tokens
+                    WHERE market_cap_usd > 0
+                    ORDER BY market_cap_usd DESC
+                    LIMIT $1
+                )
+                UNION ALL
+                SELECT t.address, t.name, t.symbol, 'trending' AS source
+                FROM trending_ranks r
+                JOIN tokens t ON t.address = r.token_address
+                WHERE r.in_top
+            ) targets
+            ORDER BY address, source
+            "#,
+        )
+        .bind(top_n)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+        trending_rank::{NewTrendingRank, TrendingRank},
+    };
+
+    async fn token(pool: &PgPool, n: u8, symbol: &str, market_cap: Option<i64>) {
+        let new = NewToken {
+            address: address(n),
+            name: Some(format!("{} Token", symbol)),
+            symbol: Some(symbol.to_string()),
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: Some(100),
+        };
+        Token::create(&new, pool).await.unwrap();
+        sqlx::query("UPDATE tokens SET market_cap_usd = $2 WHERE address = $1")
+            .bind(address(n))
+            .bind(market_cap.map(BigDecimal::from))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn targets_are_top_tokens_and_trending_ones(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        token(&pool, 1, "BIG", Some(9_000_000)).await;
+        token(&pool, 2, "MID", Some(5_000_000)).await;
+        token(&pool, 3, "HOT", None).await;
+        token(&pool, 4, "NEW", None).await;
+        TrendingRank::replace_all(
+            &[
+                NewTrendingRank {
+                    token_address: address(1),
+                    rank: 1,
+                    in_top: true,
+                },
+                NewTrendingRank {
+                    token_address: address(3),
+                    rank: 2,
+                    in_top: true,
+                },
+            ],
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let targets = TokenImpersonation::find_targets(1, &pool).await.unwrap();
+        let found: Vec<_> = targets
+            .iter()
+            .map(|t| (t.address, t.source.as_str()))
+            .collect();
+        assert_eq!(found, vec![(address(1), "top"), (address(3), "trending")]);
+
+        let flag = NewTokenImpersonation {
+            token_address: address(4),
+            target_address: address(1),
+            target_source: "top".to_string(),
+            matched_field: "symbol".to_string(),
+            similarity: BigDecimal::from_str("0.9").unwrap(),
+        };
+        let created = TokenImpersonation::create(&flag, &pool).await.unwrap();
+        assert_eq!(created.unwrap().target_address, address(1));
+        assert!(TokenImpersonation::create(&flag, &pool)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(TokenImpersonation::find_by_token(&address(4), &pool)
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,196 @@
+use sqlx::{Executor, Postgres};
+
+use super::token::Token;
+
+/// Precomputed token lists backed by materialized views
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenList {
+    Hot,
+    New,
+    Trending,
+}
+
+impl TokenList {
+    pub const ALL: [TokenList; 3] = [TokenList::Hot, TokenList::New, TokenList::Trending];
+
+    /// Materialized view backing this list
+    pub fn view_name(&self) -> &'static str {
+        match self {
+            TokenList::Hot => "token_list_hot",
+            TokenList::New => "token_list_new",
+            TokenList::Trending => "token_list_trending",
+        }
+    }
+
+    /// Read the top `limit` tokens of this list, in rank order. Archived
+    /// tokens are left out unless `include_archived` is set.
+    pub async fn find<'c, E>(
+        &self,
+        limit: i32,
+        include_archived: bool,
+        connection: E,
+    ) -> Result<Vec<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = format!(
+            "SELECT * FROM {} WHERE $2 OR archived_at IS NULL ORDER BY rank LIMIT $1",
+            self.view_name()
+        );
+
+        sqlx::query_as::<_, Token>(&query)
+            .bind(limit)
+            .bind(include_archived)
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Read the top `limit` tokens of this list carrying `tag`, in rank order
+    pub async fn find_tagged<'c, E>(
+        &self,
+        tag: &str,
+        limit: i32,
+        include_archived: bool,
+        connection: E,
+    ) -> Result<Vec<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = format!(
+            r#"
+            SELECT l.* FROM {} l
+            WHERE ($3 OR l.archived_at IS NULL)
+              AND EXISTS (
+                SELECT 1 FROM taggings tg
+                JOIN tags t ON t.id = tg.tag_id
+                WHERE t.name = lower(trim($2))
+                  AND tg.subject_kind = 'token'
+                  AND tg.subject_address = l.address
+            )
+            ORDER BY l.rank
+            LIMIT $1
+            "#,
+            self.view_name()
+        );
+
+        sqlx::query_as::<_, Token>(&query)
+            .bind(limit)
+            .bind(tag)
+            .bind(include_archived)
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Recompute this list without blocking readers
+    pub async fn refresh<'c, E>(&self, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = format!(
+            "REFRESH MATERIALIZED VIEW CONCURRENTLY {}",
+            self.view_name()
+        );
+
+        sqlx::query(&query).execute(connection).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::BigDecimal, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        tag::{Tag, TagSubject},
+        test_support::{address, clear_seed_data},
+        token::NewToken,
+    };
+
+    #[sqlx::test]
+    async fn lists_reflect_tokens_after_refresh(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        for n in 1..=3 {
+            let token = NewToken {
+                address: address(n),
+                name: None,
+                symbol: None,
+                name_raw: None,
+                symbol_raw: None,
+                name_spoofed: false,
+                decimals: None,
+                total_supply: None,
+                pair_address: None,
+                creator_address: None,
+                block_number: None,
+            };
+            Token::create(&token, &pool).await.unwrap();
+        }
+        sqlx::query("UPDATE tokens SET trades_1h = 1, price_change_1h = $2 WHERE address = $1")
+            .bind(address(2))
+            .bind(BigDecimal::from(25))
+            .execute(&pool)
+            .await
+            .unwrap();
+        Token::update_bee_score(&address(3), 40, 20, 20, &pool)
+            .await
+            .unwrap();
+
+        // Views are stale until refreshed
+        let stale = TokenList::Trending.find(10, false, &pool).await.unwrap();
+        assert!(stale.iter().all(|t| t.address != address(2)));
+
+        for list in TokenList::ALL {
+            list.refresh(&pool).await.unwrap();
+        }
+
+        assert_eq!(
+            TokenList::New.find(10, false, &pool).await.unwrap().len(),
+            3
+        );
+        assert_eq!(TokenList::New.find(2, false, &pool).await.unwrap().len(), 2);
+
+        let hot = TokenList::Hot.find(10, false, &pool).await.unwrap();
+        assert_eq!(
+            hot.iter().map(|t| t.address).collect::<Vec<_>>(),
+            vec![address(3)]
+        );
+
+        let trending = TokenList::Trending.find(10, false, &pool).await.unwrap();
+        assert_eq!(
+            trending.iter().map(|t| t.address).collect::<Vec<_>>(),
+            vec![address(2)]
+        );
+
+        Tag::attach(TagSubject::Token, &address(3), "gem", &pool)
+            .await
+            .unwrap();
+        let gems = TokenList::New
+            .find_tagged("Gem", 10, false, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            gems.iter().map(|t| t.address).collect::<Vec<_>>(),
+            vec![address(3)]
+        );
+        assert!(TokenList::Hot
+            .find_tagged("insider", 10, false, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Archived tokens are left out unless asked for, and ranked last
+        sqlx::query("UPDATE tokens SET archived_at = NOW() WHERE address = $1")
+            .bind(address(3))
+            .execute(&pool)
+            .await
+            .unwrap();
+        TokenList::New.refresh(&pool).await.unwrap();
+        let live = TokenList::New.find(10, false, &pool).await.unwrap();
+        assert_eq!(live.len(), 2);
+        let all = TokenList::New.find(10, true, &pool).await.unwrap();
+        assert_eq!(all.last().map(|t| t.address), Some(address(3)));
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    types::{chrono, Json},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// A token as published on the token list
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListedToken {
+    pub address: Address20,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: i16,
+}
+
+/// Semantic version of a token list release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ListVersion {
+    pub major: i32,
+    pub minor: i32,
+    pub patch: i32,
+}
+
+/// TokenListRelease entity: one published version of the token list
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TokenListRelease {
+    pub id: i32,
+    pub chain_id: i64,
+    pub major: i32,
+    pub minor: i32,
+    pub patch: i32,
+    /// By address
+    pub tokens: Json<Vec<ListedToken>>,
+    pub published_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TokenListRelease {
+    pub fn version(&self) -> ListVersion {
+        ListVersion {
+            major: self.major,
+            minor: self.minor,
+            patch: self.patch,
+        }
+    }
+
+    /// Get the latest release
+    pub async fn find_latest<'c, E>(connection: E) -> Result<Option<TokenListRelease>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenListRelease>(
+            "SELECT * FROM token_list_releases ORDER BY id DESC LIMIT 1",
+        )
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Publish `tokens` as `version`
+    pub async fn create<'c, E>(
+        chain_id: i64,
+        version: ListVersion,
+        tokens: &[ListedToken],
+        connection: E,
+    ) -> Result<TokenListRelease, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO token_list_releases (chain_id, major, minor, patch, tokens)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+        "#;
+
+        sqlx::query_as::<_, TokenListRelease>(query)
+            .bind(chain_id)
+            .bind(version.major)
+            .bind(version.minor)
+            .bind(version.patch)
+            .bind(Json(tokens))
+            .fetch_one(connection)
+            .await
+    }
+
+    /// Delete all but the `keep` latest releases; returns how many went
+    pub async fn prune<'c, E>(keep: i64, connection: E) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            DELETE FROM token_list_releases
+            WHERE id < (
+                SELECT MIN(id) FROM (
+                    SELECT id FROM token_list_releases ORDER BY id DESC LIMIT $1
+                ) kept
+            )
+        "#;
+
+        let result = sqlx::query(query)
+            .bind(keep.max(1))
+            .execute(connection)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get the live tokens qualifying for the list: a BeeScore of at least
+    /// `min_score` (`exit_score` for the `listed` ones, so a token hovering
+    /// around the bar doesn't flap on and off), complete unspoofed metadata
+    /// and no high or critical external risk report. The `limit` highest
+    /// scored are returned, by address.
+    pub async fn find_candidates<'c, E>(
+        min_score: i16,
+        exit_score: i16,
+        listed: &[Address20],
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<ListedToken>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            SELECT address, name, symbol, decimals FROM (
+                SELECT address, name, symbol, decimals FROM tokens
+                WHERE archived_at IS NULL
+                    AND bee_score >= CASE WHEN address = ANY($3) THEN $2 ELSE $1 END
+                    AND NULLIF(TRIM(name), '') IS NOT NULL
+                    AND NULLIF(TRIM(symbol), '') IS NOT NULL
+                    AND decimals IS NOT NULL
+                    AND NOT COALESCE(name_spoofed, FALSE)
+                    AND COALESCE(external_risk, '') NOT IN ('high', 'critical')
+                ORDER BY bee_score DESC, id
+                LIMIT $4
+            ) listed
+            ORDER BY address
+        "#;
+
+        sqlx::query_as::<_, ListedToken>(query)
+            .bind(min_score)
+            .bind(exit_score)
+            .bind(listed)
+            .bind(limit)
+            .fetch_all(connection)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::{NewToken, Token},
+    };
+
+    async fn token(n: u8, symbol: Option<&str>, bee_score: i16, pool: &PgPool) {
+        let new = NewToken {
+            address: address(n),
+            name: symbol.map(|s| format!("{} Token", s)),
+            symbol: symbol.map(str::to_string),
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: None,
+        };
+        Token::create(&new, pool).await.unwrap();
+        Token::update_bee_score(&address(n), bee_score, 0, 0, pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn candidates_are_the_top_scored_complete_tokens(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        token(1, Some("ONE"), 90, &pool).await;
+        token(2, Some("TWO"), 50, &pool).await;
+        token(3, None, 95, &pool).await;
+        token(4, Some("FOUR"), 85, &pool).await;
+        token(5, Some("FIVE"), 80, &pool).await;
+        token(6, Some("SIX"), 99, &pool).await;
+        sqlx::query("UPDATE tokens SET external_risk = 'critical' WHERE address = $1")
+            .bind(address(6))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(TokenListRelease::find_latest(&pool).await.unwrap().is_none());
+        let listed = TokenListRelease::find_candidates(70, 70, &[], 2, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            listed.iter().map(|t| t.address).collect::<Vec<_>>(),
+            vec![address(1), address(4)]
+        );
+        assert_eq!(listed[0].name, "ONE Token");
+
+        let version = ListVersion {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+        TokenListRelease::create(56, version, &listed, &pool)
+            .await
+            .unwrap();
+        let next = ListVersion { minor: 1, ..version };
+        TokenListRelease::create(56, next, &listed[..1], &pool)
+            .await
+            .unwrap();
+        let latest = TokenListRelease::find_latest(&pool).await.unwrap().unwrap();
+        assert_eq!(latest.version(), next);
+        assert_eq!(latest.tokens.0, listed[..1]);
+
+        // Listed tokens stay down to the exit score
+        let kept = TokenListRelease::find_candidates(85, 50, &[address(2)], 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            kept.iter().map(|t| t.address).collect::<Vec<_>>(),
+            vec![address(1), address(2), address(4)]
+        );
+
+        assert_eq!(TokenListRelease::prune(1, &pool).await.unwrap(), 1);
+        assert_eq!(TokenListRelease::prune(1, &pool).await.unwrap(), 0);
+        let latest = TokenListRelease::find_latest(&pool).await.unwrap().unwrap();
+        assert_eq!(latest.version(), next);
+    }
+}
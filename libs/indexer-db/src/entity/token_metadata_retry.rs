@@ -0,0 +1,207 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::{entity::token::Token, types::Address20};
+
+/// TokenMetadataRetry entity: backoff state for re-reading a token's
+/// missing name/symbol
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TokenMetadataRetry {
+    pub token_address: Address20,
+    /// Failed repair attempts so far
+    pub attempts: i32,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    pub last_error: Option<String>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TokenMetadataRetry {
+    /// Tokens missing a name, symbol or decimals that are due for another attempt,
+    /// least retried first, then the most traded over the last hour (new
+    /// pairs with early swaps before dead-on-arrival ones), then newest
+    pub async fn find_due<'c, E>(limit: i64, connection: E) -> Result<Vec<Token>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Token>(
+            r#"
+            SELECT t.*
+            FROM tokens t
+            LEFT JOIN token_metadata_retries r ON r.token_address = t.address
+            WHERE (t.name IS NULL OR t.symbol IS NULL OR t.decimals IS NULL)
+              AND (r.next_attempt_at IS NULL OR r.next_attempt_at <= NOW())
+            ORDER BY COALESCE(r.attempts, 0), COALESCE(t.trades_1h, 0) DESC,
+                t.created_at DESC NULLS LAST
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Record a failed attempt. The next one waits `base_secs` doubled for
+    /// every earlier failure, at most `max_secs`.
+    pub async fn record_failure<'c, E>(
+        token_address: &Address20,
+        error: &str,
+        base_secs: i64,
+        max_secs: i64,
+        connection: E,
+    ) -> Result<TokenMetadataRetry, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenMetadataRetry>(
+            r#"
+            INSERT INTO token_metadata_retries (token_address, attempts, next_attempt_at, last_error)
+            VALUES ($1, 1, NOW() + make_interval(secs => LEAST($3, $4)), $2)
+            ON CONFLICT (token_address) DO UPDATE SET
+                attempts = token_metadata_retries.attempts + 1,
+                next_attempt_at = NOW() + make_interval(
+                    secs => LEAST($3 * POWER(2, token_metadata_retries.attempts), $4)
+                ),
+                last_error = EXCLUDED.last_error,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(token_address)
+        .bind(error)
+        .bind(base_secs)
+        .bind(max_secs)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Forget a token's failures once its metadata is complete
+    pub async fn clear<'c, E>(token_address: &Address20, connection: E) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query("DELETE FROM token_metadata_retries WHERE token_address = $1")
+            .bind(token_address)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::NewToken,
+    };
+
+    async fn token(pool: &PgPool, n: u8, name: Option<&str>) {
+        let new = NewToken {
+            address: address(n),
+            name: name.map(str::to_string),
+            symbol: name.map(str::to_string),
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: Some(100),
+        };
+        Token::create(&new, pool).await.unwrap();
+    }
+
+    fn due(tokens: &[Token]) -> Vec<Address20> {
+        tokens.iter().map(|t| t.address).collect()
+    }
+
+    #[sqlx::test]
+    async fn failures_back_off_until_cleared(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        token(&pool, 1, Some("Named")).await;
+        token(&pool, 2, None).await;
+        token(&pool, 3, None).await;
+
+        let found = TokenMetadataRetry::find_due(10, &pool).await.unwrap();
+        let mut found = due(&found);
+        found.sort_by_key(|a| a.to_hex());
+        assert_eq!(found, vec![address(2), address(3)]);
+
+        let first = TokenMetadataRetry::record_failure(&address(2), "timeout", 60, 600, &pool)
+            .await
+            .unwrap();
+        assert_eq!(first.attempts, 1);
+        assert_eq!(first.last_error.as_deref(), Some("timeout"));
+        let wait = |r: &TokenMetadataRetry| (r.next_attempt_at - r.updated_at).num_seconds();
+        assert_eq!(wait(&first), 60);
+
+        let found = TokenMetadataRetry::find_due(10, &pool).await.unwrap();
+        assert_eq!(due(&found), vec![address(3)]);
+
+        let second = TokenMetadataRetry::record_failure(&address(2), "reverted", 60, 600, &pool)
+            .await
+            .unwrap();
+        assert_eq!(second.attempts, 2);
+        assert_eq!(wait(&second), 120);
+        for _ in 0..5 {
+            TokenMetadataRetry::record_failure(&address(2), "reverted", 60, 600, &pool)
+                .await
+                .unwrap();
+        }
+        let capped = TokenMetadataRetry::record_failure(&address(2), "reverted", 60, 600, &pool)
+            .await
+            .unwrap();
+        assert_eq!(wait(&capped), 600);
+
+        // Due again once the wait is over
+        sqlx::query(
+            "UPDATE token_metadata_retries SET next_attempt_at = NOW() - INTERVAL '1 second'",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let found = TokenMetadataRetry::find_due(1, &pool).await.unwrap();
+        assert_eq!(due(&found), vec![address(3)], "fewest attempts first");
+
+        TokenMetadataRetry::clear(&address(2), &pool).await.unwrap();
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM token_metadata_retries")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+
+    #[sqlx::test]
+    async fn tokens_missing_decimals_are_due(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        token(&pool, 1, Some("Named")).await;
+        token(&pool, 2, Some("Discovered")).await;
+        sqlx::query("UPDATE tokens SET decimals = NULL WHERE address = $1")
+            .bind(address(2))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let found = TokenMetadataRetry::find_due(10, &pool).await.unwrap();
+        assert_eq!(due(&found), vec![address(2)]);
+    }
+
+    #[sqlx::test]
+    async fn tokens_already_trading_are_repaired_first(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        for n in 1..=3 {
+            token(&pool, n, None).await;
+        }
+        sqlx::query("UPDATE tokens SET trades_1h = 12 WHERE address = $1")
+            .bind(address(2))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let found = TokenMetadataRetry::find_due(1, &pool).await.unwrap();
+        assert_eq!(due(&found), vec![address(2)]);
+    }
+}
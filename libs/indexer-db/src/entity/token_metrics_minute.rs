@@ -0,0 +1,160 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// One minute of trade activity for a token
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TokenMetricsMinute {
+    pub token_address: Address20,
+    pub minute: chrono::DateTime<chrono::Utc>,
+    pub volume_usd: BigDecimal,
+    pub buys: i32,
+    pub sells: i32,
+    pub trades: i32,
+}
+
+impl TokenMetricsMinute {
+    /// Add a trade to the bucket for the minute containing `timestamp`
+    pub async fn record_trade<'c, E>(
+        token_address: &Address20,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        is_buy: bool,
+        amount_usd: &BigDecimal,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO token_metrics_minute (token_address, minute, volume_usd, buys, sells, trades)
+            VALUES ($1, date_trunc('minute', $2), $3, $4, $5, 1)
+            ON CONFLICT (token_address, minute) DO UPDATE SET
+                volume_usd = token_metrics_minute.volume_usd + EXCLUDED.volume_usd,
+                buys = token_metrics_minute.buys + EXCLUDED.buys,
+                sells = token_metrics_minute.sells + EXCLUDED.sells,
+                trades = token_metrics_minute.trades + 1
+            "#,
+        )
+        .bind(token_address)
+        .bind(timestamp)
+        .bind(amount_usd)
+        .bind(i32::from(is_buy))
+        .bind(i32::from(!is_buy))
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get minute buckets for a token since a given time
+    pub async fn find_by_token<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<Vec<TokenMetricsMinute>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenMetricsMinute>(
+            r#"
+            SELECT * FROM token_metrics_minute
+            WHERE token_address = $1 AND minute >= $2
+            ORDER BY minute ASC
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Delete up to `limit` buckets older than `cutoff`, returning how many
+    /// were removed
+    pub async fn delete_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM token_metrics_minute
+            WHERE (token_address, minute) IN (
+                SELECT token_address, minute FROM token_metrics_minute
+                WHERE minute < $1
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::test_support::{address, clear_seed_data};
+
+    #[sqlx::test]
+    async fn record_trade_accumulates_per_minute(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let token = address(1);
+        let minute = "2025-01-01T12:00:00Z"
+            .parse::<chrono::DateTime<Utc>>()
+            .unwrap();
+
+        for (seconds, is_buy, usd) in [(5, true, 10), (50, false, 15)] {
+            let at = minute + Duration::seconds(seconds);
+            TokenMetricsMinute::record_trade(&token, at, is_buy, &BigDecimal::from(usd), &pool)
+                .await
+                .unwrap();
+        }
+        let next_minute = minute + Duration::seconds(61);
+        TokenMetricsMinute::record_trade(&token, next_minute, true, &BigDecimal::from(1), &pool)
+            .await
+            .unwrap();
+
+        let buckets = TokenMetricsMinute::find_by_token(&token, minute, &pool)
+            .await
+            .unwrap();
+        assert_eq!(buckets.len(), 2);
+
+        let first = &buckets[0];
+        assert_eq!(first.minute, minute);
+        assert_eq!(first.trades, 2);
+        assert_eq!(first.buys, 1);
+        assert_eq!(first.sells, 1);
+        assert_eq!(first.volume_usd, BigDecimal::from(25));
+
+        assert_eq!(buckets[1].trades, 1);
+
+        let later = TokenMetricsMinute::find_by_token(&token, next_minute, &pool)
+            .await
+            .unwrap();
+        assert!(later.is_empty());
+
+        let removed =
+            TokenMetricsMinute::delete_older_than(minute + Duration::minutes(1), 10, &pool)
+                .await
+                .unwrap();
+        assert_eq!(removed, 1);
+        let left = TokenMetricsMinute::find_by_token(&token, minute, &pool)
+            .await
+            .unwrap();
+        assert_eq!(left.len(), 1);
+    }
+}
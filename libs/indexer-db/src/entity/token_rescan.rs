@@ -0,0 +1,394 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// TokenRescan entity: an operator request to replay one token's logs
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TokenRescan {
+    pub id: i32,
+    pub token_address: Address20,
+    /// `None` starts at the token's creation block
+    pub from_block: Option<i64>,
+    /// `None` ends at the processor's last processed block when the rescan
+    /// starts; a later block is capped at it
+    pub to_block: Option<i64>,
+    /// `pending`, `running`, `done` or `failed`
+    pub status: String,
+    pub logs_replayed: i32,
+    pub error: Option<String>,
+    pub requested_at: chrono::DateTime<chrono::Utc>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Input for queueing a rescan
+#[derive(Debug, Clone)]
+pub struct NewTokenRescan {
+    pub token_address: Address20,
+    pub from_block: Option<i64>,
+    pub to_block: Option<i64>,
+}
+
+/// What a rescan clears before replaying
+#[derive(Debug, Clone)]
+pub struct RescanRange {
+    pub from_block: i64,
+    pub to_block: i64,
+    /// Timestamps of `from_block` and `to_block`, for the time-keyed tables
+    pub from_time: chrono::DateTime<chrono::Utc>,
+    pub to_time: chrono::DateTime<chrono::Utc>,
+    /// Holder balances are cumulative, so they are only rebuilt when the
+    /// range starts at the token's creation
+    pub rebuild_holders: bool,
+}
+
+impl TokenRescan {
+    /// Queue a rescan
+    pub async fn create<'c, E>(
+        rescan: &NewTokenRescan,
+        connection: E,
+    ) -> Result<TokenRescan, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenRescan>(
+            r#"
+            INSERT INTO token_rescans (token_address, from_block, to_block)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(rescan.token_address)
+        .bind(rescan.from_block)
+        .bind(rescan.to_block)
+        .fetch_one(connection)
+        .await
+    }
+
+    /// Find a rescan by id
+    pub async fn find_by_id<'c, E>(
+        id: i32,
+        connection: E,
+    ) -> Result<Option<TokenRescan>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenRescan>("SELECT * FROM token_rescans WHERE id = $1")
+            .bind(id)
+            .fetch_optional(connection)
+            .await
+    }
+
+    /// Mark the oldest pending rescan as running and return it. Concurrent
+    /// processors never claim the same one.
+    pub async fn claim_next<'c, E>(connection: E) -> Result<Option<TokenRescan>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenRescan>(
+            r#"
+            UPDATE token_rescans SET status = 'running', started_at = NOW()
+            WHERE id = (
+                SELECT id FROM token_rescans
+                WHERE status = 'pending'
+                ORDER BY id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Mark one pending rescan as running and return it; `None` when another
+    /// processor claimed it first
+    pub async fn claim<'c, E>(id: i32, connection: E) -> Result<Option<TokenRescan>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TokenRescan>(
+            r#"
+            UPDATE token_rescans SET status = 'running', started_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Record a finished rescan
+    pub async fn finish<'c, E>(
+        id: i32,
+        logs_replayed: i32,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE token_rescans
+            SET status = 'done', logs_replayed = $2, error = NULL, finished_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(logs_replayed)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a rescan that stopped on an error
+    pub async fn fail<'c, E>(
+        id: i32,
+        logs_replayed: i32,
+        error: &str,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE token_rescans
+            SET status = 'failed', logs_replayed = $2, error = $3, finished_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(logs_replayed)
+        .bind(error)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Addresses whose logs the live processor holds back while a rescan
+    /// replays them: tokens being rescanned and every pair they trade in
+    pub async fn paused_emitters<'c, E>(connection: E) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            r#"
+            SELECT token_address FROM token_rescans WHERE status = 'running'
+            UNION
+            SELECT p.address FROM token_rescans r
+            JOIN pairs p ON r.token_address IN (p.token0_address, p.token1_address)
+            WHERE r.status = 'running'
+            "#,
+        )
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Delete the rows a replay of `range` recreates: the token's swaps,
+    /// transfer activity, CEX flows and reserve anomalies in the block range,
+    /// its snapshots and minute buckets in the time range, and, when
+    /// rebuilding holders, its holders and churn. Returns the number of rows
+    /// deleted.
+    pub async fn clear_range<'c, E>(
+        token_address: &Address20,
+        range: &RescanRange,
+        connection: E,
+    ) -> Result<i64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            r#"
+            WITH
+            swaps_deleted AS (
+                DELETE FROM swaps
+                WHERE token_address = $1 AND block_number BETWEEN $2 AND $3
+                RETURNING 1
+            ),
+            activity_deleted AS (
+                DELETE FROM wallet_activity
+                WHERE token_address = $1 AND block_number BETWEEN $2 AND $3
+                    AND action IN ('transfer_in', 'transfer_out')
+                RETURNING 1
+            ),
+            flows_deleted AS (
+                DELETE FROM cex_flows
+                WHERE token_address = $1 AND block_number BETWEEN $2 AND $3
+                RETURNING 1
+            ),
+            anomalies_deleted AS (
+                DELETE FROM anomalies
+                WHERE token_address = $1 AND block_number BETWEEN $2 AND $3
+                RETURNING 1
+            ),
+            snapshots_deleted AS (
+                DELETE FROM price_snapshots
+                WHERE token_address = $1 AND timestamp BETWEEN $4 AND $5
+                RETURNING 1
+            ),
+            minutes_deleted AS (
+                DELETE FROM token_metrics_minute
+                WHERE token_address = $1 AND minute BETWEEN date_trunc('minute', $4) AND $5
+                RETURNING 1
+            ),
+            holders_deleted AS (
+                DELETE FROM token_holders
+                WHERE token_address = $1 AND $6
+                RETURNING 1
+            ),
+            churn_deleted AS (
+                DELETE FROM token_holder_churn_hourly
+                WHERE token_address = $1 AND $6
+                RETURNING 1
+            )
+            SELECT
+                (SELECT COUNT(*) FROM swaps_deleted)
+                + (SELECT COUNT(*) FROM activity_deleted)
+                + (SELECT COUNT(*) FROM flows_deleted)
+                + (SELECT COUNT(*) FROM anomalies_deleted)
+                + (SELECT COUNT(*) FROM snapshots_deleted)
+                + (SELECT COUNT(*) FROM minutes_deleted)
+                + (SELECT COUNT(*) FROM holders_deleted)
+                + (SELECT COUNT(*) FROM churn_deleted)
+            "#,
+        )
+        .bind(token_address)
+        .bind(range.from_block)
+        .bind(range.to_block)
+        .bind(range.from_time)
+        .bind(range.to_time)
+        .bind(range.rebuild_holders)
+        .fetch_one(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::{Duration, Utc};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        price_snapshot::{NewPriceSnapshot, PriceSnapshot},
+        test_support::{address, clear_seed_data},
+        token_holder::{NewTokenHolder, TokenHolder},
+    };
+
+    #[sqlx::test]
+    async fn rescans_are_claimed_once_in_order(pool: PgPool) {
+        let first = TokenRescan::create(
+            &NewTokenRescan {
+                token_address: address(1),
+                from_block: None,
+                to_block: None,
+            },
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.status, "pending");
+        let second = TokenRescan::create(
+            &NewTokenRescan {
+                token_address: address(2),
+                from_block: Some(100),
+                to_block: Some(200),
+            },
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let claimed = TokenRescan::claim_next(&pool).await.unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        assert_eq!(claimed.status, "running");
+        assert!(claimed.started_at.is_some());
+        // Live logs of the token are held back while it runs
+        assert_eq!(
+            TokenRescan::paused_emitters(&pool).await.unwrap(),
+            vec![address(1)]
+        );
+        TokenRescan::finish(claimed.id, 42, &pool).await.unwrap();
+        assert!(TokenRescan::paused_emitters(&pool).await.unwrap().is_empty());
+
+        let claimed = TokenRescan::claim_next(&pool).await.unwrap().unwrap();
+        assert_eq!(claimed.id, second.id);
+        // Already running
+        assert!(TokenRescan::claim(second.id, &pool).await.unwrap().is_none());
+        TokenRescan::fail(claimed.id, 3, "RPC unavailable", &pool)
+            .await
+            .unwrap();
+        assert!(TokenRescan::claim_next(&pool).await.unwrap().is_none());
+
+        let done = TokenRescan::find_by_id(first.id, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((done.status.as_str(), done.logs_replayed), ("done", 42));
+        let failed = TokenRescan::find_by_id(second.id, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(failed.status, "failed");
+        assert_eq!(failed.error.as_deref(), Some("RPC unavailable"));
+    }
+
+    #[sqlx::test]
+    async fn clearing_a_range_keeps_rows_outside_it(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let now = Utc::now();
+        for (minutes_ago, token) in [(120, 1), (30, 1), (30, 2)] {
+            let snapshot = NewPriceSnapshot {
+                token_address: address(token),
+                timestamp: now - Duration::minutes(minutes_ago),
+                price_usd: Some(1.into()),
+                price_bnb: None,
+                liquidity_usd: None,
+                volume_usd: None,
+                market_cap_usd: None,
+                holder_count: None,
+            };
+            PriceSnapshot::create(&snapshot, &pool).await.unwrap();
+        }
+        let holder = NewTokenHolder {
+            token_address: address(1),
+            wallet_address: address(10),
+            balance: 100.into(),
+            is_dev: false,
+            is_sniper: false,
+            is_contract: false,
+            first_buy_block: None,
+            source: None,
+        };
+        TokenHolder::upsert(&holder, &pool).await.unwrap();
+
+        let mut range = RescanRange {
+            from_block: 100,
+            to_block: 200,
+            from_time: now - Duration::hours(1),
+            to_time: now,
+            rebuild_holders: false,
+        };
+        let deleted = TokenRescan::clear_range(&address(1), &range, &pool)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        range.rebuild_holders = true;
+        let deleted = TokenRescan::clear_range(&address(1), &range, &pool)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM price_snapshots")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 2);
+    }
+}
@@ -0,0 +1,102 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// TrendingRank entity: a token's trending position as of the last refresh
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TrendingRank {
+    pub token_address: Address20,
+    pub rank: i32,
+    /// Whether the token counts as in the top list
+    pub in_top: bool,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Input for one trending position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewTrendingRank {
+    pub token_address: Address20,
+    pub rank: i32,
+    pub in_top: bool,
+}
+
+impl TrendingRank {
+    /// Get the stored positions, in rank order
+    pub async fn find_all<'c, E>(connection: E) -> Result<Vec<TrendingRank>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, TrendingRank>("SELECT * FROM trending_ranks ORDER BY rank")
+            .fetch_all(connection)
+            .await
+    }
+
+    /// Replace the stored positions with `ranks`
+    pub async fn replace_all<'c, E>(
+        ranks: &[NewTrendingRank],
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            WITH upserted AS (
+                INSERT INTO trending_ranks (token_address, rank, in_top)
+                SELECT * FROM UNNEST($1::BYTEA[], $2::INT[], $3::BOOLEAN[])
+                ON CONFLICT (token_address) DO UPDATE
+                SET rank = EXCLUDED.rank,
+                    in_top = EXCLUDED.in_top,
+                    updated_at = NOW()
+            )
+            DELETE FROM trending_ranks
+            WHERE token_address <> ALL($1::BYTEA[])
+        "#;
+
+        sqlx::query(query)
+            .bind(ranks.iter().map(|r| r.token_address).collect::<Vec<_>>())
+            .bind(ranks.iter().map(|r| r.rank).collect::<Vec<_>>())
+            .bind(ranks.iter().map(|r| r.in_top).collect::<Vec<_>>())
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::test_support::address;
+
+    fn rank(n: u8, rank: i32, in_top: bool) -> NewTrendingRank {
+        NewTrendingRank {
+            token_address: address(n),
+            rank,
+            in_top,
+        }
+    }
+
+    #[sqlx::test]
+    async fn replace_all_upserts_and_drops_missing_tokens(pool: PgPool) {
+        TrendingRank::replace_all(&[rank(1, 1, true), rank(2, 2, true)], &pool)
+            .await
+            .unwrap();
+        TrendingRank::replace_all(&[rank(3, 1, true), rank(1, 2, false)], &pool)
+            .await
+            .unwrap();
+
+        let stored = TrendingRank::find_all(&pool).await.unwrap();
+        assert_eq!(
+            stored
+                .iter()
+                .map(|r| (r.token_address, r.rank, r.in_top))
+                .collect::<Vec<_>>(),
+            vec![(address(3), 1, true), (address(1), 2, false)]
+        );
+
+        TrendingRank::replace_all(&[], &pool).await.unwrap();
+        assert!(TrendingRank::find_all(&pool).await.unwrap().is_empty());
+    }
+}
@@ -3,11 +3,13 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use crate::types::Address20;
+
 /// Wallet entity for tracking wallets with labels and computed stats
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Wallet {
     pub id: i32,
-    pub address: String,
+    pub address: Address20,
     pub label: Option<String>,
     pub token_count: Option<i32>,
     pub estimated_value_usd: Option<BigDecimal>,
@@ -19,20 +21,32 @@ pub struct Wallet {
 /// Input for creating a new wallet
 #[derive(Debug, Clone)]
 pub struct NewWallet {
-    pub address: String,
+    pub address: Address20,
     pub label: Option<String>,
 }
 
 /// Wallet with computed statistics from wallet_activity
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct WalletWithStats {
-    pub address: String,
+    pub address: Address20,
     pub label: Option<String>,
     pub token_count: i64,
     pub estimated_value_usd: Option<BigDecimal>,
     pub last_activity: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// A tracked wallet's current holdings, valued at current token prices
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct WalletValuation {
+    pub id: i32,
+    pub address: Address20,
+    /// Tokens held with a positive balance
+    pub token_count: i32,
+    /// Sum of balance × price over those tokens; unpriced tokens count as 0
+    pub estimated_value_usd: BigDecimal,
+    pub last_activity: Option<chrono::DateTime<chrono::Utc>>,
+}
+
 impl Wallet {
     /// Create a new wallet record
     pub async fn create<'c, E>(wallet: &NewWallet, connection: E) -> Result<Wallet, sqlx::Error>
@@ -49,15 +63,42 @@ impl Wallet {
         "#;
 
         sqlx::query_as::<_, Wallet>(query)
-            .bind(&wallet.address)
+            .bind(wallet.address)
             .bind(&wallet.label)
             .fetch_one(connection)
             .await
     }
 
+    /// Upsert many wallets in one statement, like [`Wallet::create`]
+    ///
+    /// Addresses must be unique within `wallets`. Returns each address with
+    /// whether it was newly inserted, in no particular order.
+    pub async fn create_many<'c, E>(
+        wallets: &[NewWallet],
+        connection: E,
+    ) -> Result<Vec<(Address20, bool)>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let query = r#"
+            INSERT INTO wallets (address, label)
+            SELECT * FROM UNNEST($1::BYTEA[], $2::VARCHAR[])
+            ON CONFLICT (address) DO UPDATE SET
+                label = COALESCE(EXCLUDED.label, wallets.label),
+                updated_at = NOW()
+            RETURNING address, (xmax = 0) AS inserted
+        "#;
+
+        sqlx::query_as::<_, (Address20, bool)>(query)
+            .bind(wallets.iter().map(|w| w.address).collect::<Vec<_>>())
+            .bind(wallets.iter().map(|w| w.label.clone()).collect::<Vec<_>>())
+            .fetch_all(connection)
+            .await
+    }
+
     /// Find wallet by address
     pub async fn find_by_address<'c, E>(
-        address: &str,
+        address: &Address20,
         connection: E,
     ) -> Result<Option<Wallet>, sqlx::Error>
     where
@@ -85,9 +126,12 @@ impl Wallet {
         .await
     }
 
-    /// Get all wallets with computed stats from wallet_activity
+    /// Get all wallets with computed stats from wallet_activity, optionally
+    /// only those carrying `tag`. The valuation at current prices is preferred;
+    /// wallets not valued yet show their net USD flow instead.
     pub async fn find_all_with_stats<'c, E>(
         limit: i32,
+        tag: Option<&str>,
         connection: E,
     ) -> Result<Vec<WalletWithStats>, sqlx::Error>
     where
@@ -98,31 +142,39 @@ impl Wallet {
                 w.address,
                 w.label,
                 COALESCE(stats.token_count, 0) as token_count,
-                COALESCE(stats.total_value, w.estimated_value_usd) as estimated_value_usd,
+                COALESCE(w.estimated_value_usd, stats.total_value) as estimated_value_usd,
                 COALESCE(stats.last_activity, w.last_activity) as last_activity
             FROM wallets w
             LEFT JOIN (
                 SELECT 
                     wallet_address,
                     COUNT(DISTINCT token_address) as token_count,
-                    SUM(CASE WHEN action = 'buy' THEN amount_usd ELSE -amount_usd END) as total_value,
+                    SUM(CASE WHEN action IN ('buy', 'transfer_in') THEN amount_usd ELSE -amount_usd END) as total_value,
                     MAX(timestamp) as last_activity
                 FROM wallet_activity
                 GROUP BY wallet_address
             ) stats ON w.address = stats.wallet_address
+            WHERE $2::TEXT IS NULL OR EXISTS (
+                SELECT 1 FROM taggings tg
+                JOIN tags t ON t.id = tg.tag_id
+                WHERE t.name = lower(trim($2))
+                  AND tg.subject_kind = 'wallet'
+                  AND tg.subject_address = w.address
+            )
             ORDER BY estimated_value_usd DESC NULLS LAST, w.created_at DESC
             LIMIT $1
         "#;
 
         sqlx::query_as::<_, WalletWithStats>(query)
             .bind(limit)
+            .bind(tag)
             .fetch_all(connection)
             .await
     }
 
     /// Delete wallet by address
     pub async fn delete_by_address<'c, E>(
-        address: &str,
+        address: &Address20,
         connection: E,
     ) -> Result<bool, sqlx::Error>
     where
@@ -138,7 +190,7 @@ impl Wallet {
 
     /// Update wallet label
     pub async fn update_label<'c, E>(
-        address: &str,
+        address: &Address20,
         label: Option<&str>,
         connection: E,
     ) -> Result<Option<Wallet>, sqlx::Error>
@@ -156,7 +208,7 @@ impl Wallet {
 
     /// Update wallet computed stats (can be called periodically)
     pub async fn update_stats<'c, E>(
-        address: &str,
+        address: &Address20,
         token_count: i32,
         estimated_value: &BigDecimal,
         last_activity: Option<chrono::DateTime<chrono::Utc>>,
@@ -185,6 +237,56 @@ impl Wallet {
         Ok(())
     }
 
+    /// Value up to `limit` tracked wallets with id above `after_id` from their
+    /// token_holders balances and the tokens' current prices, in id order
+    pub async fn valuations<'c, E>(
+        after_id: i32,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<WalletValuation>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, WalletValuation>(
+            r#"
+            SELECT
+                w.id,
+                w.address,
+                COALESCE(held.token_count, 0)::INT AS token_count,
+                COALESCE(held.value_usd, 0) AS estimated_value_usd,
+                activity.last_activity
+            FROM wallets w
+            LEFT JOIN LATERAL (
+                SELECT
+                    COUNT(*) AS token_count,
+                    ROUND(SUM(holding.value_usd), 2) AS value_usd
+                FROM (
+                    SELECT
+                        h.balance / POWER(10::NUMERIC, COALESCE(t.decimals, 18))
+                            * COALESCE(t.price_usd, 0) AS value_usd
+                    FROM token_holders h
+                    JOIN tokens t ON t.address = h.token_address
+                    WHERE h.wallet_address = w.address AND h.balance > 0
+                ) holding
+                -- Junk prices on spam tokens would overflow DECIMAL(30, 2)
+                WHERE holding.value_usd < 1e26
+            ) held ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT MAX(timestamp) AS last_activity
+                FROM wallet_activity
+                WHERE wallet_address = w.address
+            ) activity ON TRUE
+            WHERE w.id > $1
+            ORDER BY w.id
+            LIMIT $2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
     /// Count total wallets
     pub async fn count<'c, E>(connection: E) -> Result<i64, sqlx::Error>
     where
@@ -198,3 +300,200 @@ impl Wallet {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        tag::{Tag, TagSubject},
+        test_support::{address, clear_seed_data, hash},
+        token::{NewToken, Token},
+        token_holder::{NewTokenHolder, TokenHolder},
+        wallet_activity::{NewWalletActivity, WalletActivity},
+    };
+
+    fn new_wallet(n: u8, label: Option<&str>) -> NewWallet {
+        NewWallet {
+            address: address(n),
+            label: label.map(str::to_string),
+        }
+    }
+
+    #[sqlx::test]
+    async fn create_upserts_and_keeps_label(pool: PgPool) {
+        let first = Wallet::create(&new_wallet(1, Some("Whale")), &pool)
+            .await
+            .unwrap();
+        let second = Wallet::create(&new_wallet(1, None), &pool).await.unwrap();
+
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.label.as_deref(), Some("Whale"));
+
+        let relabeled = Wallet::update_label(&address(1), Some("Dev"), &pool)
+            .await
+            .unwrap();
+        assert_eq!(relabeled.and_then(|w| w.label).as_deref(), Some("Dev"));
+        assert!(Wallet::update_label(&address(2), None, &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(Wallet::delete_by_address(&address(1), &pool).await.unwrap());
+        assert!(!Wallet::delete_by_address(&address(1), &pool).await.unwrap());
+        assert!(Wallet::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[sqlx::test]
+    async fn create_many_reports_new_and_existing_wallets(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        Wallet::create(&new_wallet(1, Some("Whale")), &pool)
+            .await
+            .unwrap();
+
+        let mut results =
+            Wallet::create_many(&[new_wallet(1, None), new_wallet(2, Some("Dev"))], &pool)
+                .await
+                .unwrap();
+        results.sort();
+        assert_eq!(results, vec![(address(1), false), (address(2), true)]);
+
+        let kept = Wallet::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(kept.label.as_deref(), Some("Whale"));
+        assert_eq!(Wallet::count(&pool).await.unwrap(), 2);
+    }
+
+    #[sqlx::test]
+    async fn stats_are_computed_from_activity(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        Wallet::create(&new_wallet(1, None), &pool).await.unwrap();
+        Wallet::create(&new_wallet(2, None), &pool).await.unwrap();
+        Wallet::update_stats(&address(2), 4, &BigDecimal::from(50), None, &pool)
+            .await
+            .unwrap();
+
+        for (n, action, usd) in [(1, "buy", 300), (2, "buy", 200), (3, "sell", 100)] {
+            let activity = NewWalletActivity {
+                wallet_address: address(1),
+                tx_hash: hash(n),
+                block_number: n as i64,
+                timestamp: Utc::now(),
+                action: action.to_string(),
+                token_address: address(10 + n % 2),
+                token_symbol: None,
+                amount_tokens: None,
+                amount_usd: Some(BigDecimal::from(usd)),
+                source_log_id: None,
+            };
+            WalletActivity::create(&activity, &pool).await.unwrap();
+        }
+
+        let wallets = Wallet::find_all_with_stats(10, None, &pool).await.unwrap();
+        assert_eq!(wallets.len(), 2);
+
+        // Not valued yet: the net flow stands in
+        let active = &wallets[0];
+        assert_eq!(active.address, address(1));
+        assert_eq!(active.token_count, 2);
+        assert_eq!(active.estimated_value_usd, Some(BigDecimal::from(400)));
+        assert!(active.last_activity.is_some());
+
+        // Valued wallets show their valuation
+        assert_eq!(wallets[1].estimated_value_usd, Some(BigDecimal::from(50)));
+        Wallet::update_stats(&address(1), 2, &BigDecimal::from(30), None, &pool)
+            .await
+            .unwrap();
+        let wallets = Wallet::find_all_with_stats(10, None, &pool).await.unwrap();
+        assert_eq!(wallets[0].address, address(2));
+        assert_eq!(wallets[1].estimated_value_usd, Some(BigDecimal::from(30)));
+
+        assert_eq!(Wallet::count(&pool).await.unwrap(), 2);
+        assert_eq!(Wallet::find_all(1, &pool).await.unwrap().len(), 1);
+
+        Tag::attach(TagSubject::Wallet, &address(2), "cex", &pool)
+            .await
+            .unwrap();
+        let tagged = Wallet::find_all_with_stats(10, Some("CEX"), &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            tagged.iter().map(|w| w.address).collect::<Vec<_>>(),
+            vec![address(2)]
+        );
+    }
+
+    #[sqlx::test]
+    async fn valuations_price_current_holdings(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        for (n, decimals, price) in [(10, 18, Some("0.5")), (11, 6, Some("2")), (12, 18, None)] {
+            let token = NewToken {
+                address: address(n),
+                name: None,
+                symbol: None,
+                name_raw: None,
+                symbol_raw: None,
+                name_spoofed: false,
+                decimals: Some(decimals),
+                total_supply: None,
+                pair_address: None,
+                creator_address: None,
+                block_number: Some(100),
+            };
+            Token::create(&token, &pool).await.unwrap();
+            sqlx::query("UPDATE tokens SET price_usd = $2::NUMERIC WHERE address = $1")
+                .bind(address(n))
+                .bind(price)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        // Wallet 1: 3 × $0.5 + 4 × $2 and an unpriced token; wallet 2 sold out
+        for (wallet, token, balance) in [
+            (1, 10, "3000000000000000000"),
+            (1, 11, "4000000"),
+            (1, 12, "1"),
+            (2, 10, "0"),
+        ] {
+            let holder = NewTokenHolder {
+                token_address: address(token),
+                wallet_address: address(wallet),
+                balance: balance.parse().unwrap(),
+                is_dev: false,
+                is_sniper: false,
+                is_contract: false,
+                first_buy_block: None,
+                source: None,
+            };
+            TokenHolder::upsert(&holder, &pool).await.unwrap();
+        }
+        for n in 1..=3 {
+            Wallet::create(&new_wallet(n, None), &pool).await.unwrap();
+        }
+
+        let valuations = Wallet::valuations(0, 10, &pool).await.unwrap();
+        assert_eq!(valuations.len(), 3);
+        let first = &valuations[0];
+        assert_eq!(first.address, address(1));
+        assert_eq!(first.token_count, 3);
+        assert_eq!(
+            first.estimated_value_usd,
+            "9.50".parse::<BigDecimal>().unwrap()
+        );
+        assert_eq!(valuations[1].token_count, 0);
+        assert_eq!(valuations[1].estimated_value_usd, BigDecimal::from(0));
+
+        // Paged by id
+        let rest = Wallet::valuations(first.id, 10, &pool).await.unwrap();
+        assert_eq!(rest.len(), 2);
+    }
+}
@@ -4,33 +4,38 @@ use sqlx::{
     Executor, Postgres,
 };
 
+use crate::types::{Address20, Hash32};
+
 /// WalletActivity entity for tracking wallet transactions
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct WalletActivity {
     pub id: i32,
-    pub wallet_address: String,
-    pub tx_hash: String,
+    pub wallet_address: Address20,
+    pub tx_hash: Hash32,
     pub block_number: i64,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub action: String, // "buy", "sell", "transfer_in", "transfer_out"
-    pub token_address: String,
+    pub token_address: Address20,
     pub token_symbol: Option<String>,
     pub amount_tokens: Option<BigDecimal>,
     pub amount_usd: Option<BigDecimal>,
+    /// `evm_logs.id` of the log recorded, when it came through `evm_logs`
+    pub source_log_id: Option<i32>,
 }
 
 /// Input for creating new wallet activity
 #[derive(Debug, Clone)]
 pub struct NewWalletActivity {
-    pub wallet_address: String,
-    pub tx_hash: String,
+    pub wallet_address: Address20,
+    pub tx_hash: Hash32,
     pub block_number: i64,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub action: String,
-    pub token_address: String,
+    pub token_address: Address20,
     pub token_symbol: Option<String>,
     pub amount_tokens: Option<BigDecimal>,
     pub amount_usd: Option<BigDecimal>,
+    pub source_log_id: Option<i32>,
 }
 
 impl WalletActivity {
@@ -45,30 +50,60 @@ impl WalletActivity {
         let query = r#"
             INSERT INTO wallet_activity (
                 wallet_address, tx_hash, block_number, timestamp,
-                action, token_address, token_symbol, amount_tokens, amount_usd
+                action, token_address, token_symbol, amount_tokens, amount_usd,
+                source_log_id
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             ON CONFLICT (tx_hash, wallet_address, token_address, action) DO NOTHING
             RETURNING *
         "#;
 
         sqlx::query_as::<_, WalletActivity>(query)
-            .bind(&activity.wallet_address)
-            .bind(&activity.tx_hash)
+            .bind(activity.wallet_address)
+            .bind(activity.tx_hash)
             .bind(activity.block_number)
             .bind(activity.timestamp)
             .bind(&activity.action)
-            .bind(&activity.token_address)
+            .bind(activity.token_address)
             .bind(&activity.token_symbol)
             .bind(&activity.amount_tokens)
             .bind(&activity.amount_usd)
+            .bind(activity.source_log_id)
             .fetch_one(connection)
             .await
     }
 
+    /// Delete up to `limit` rows older than `cutoff` for wallets not in `wallets`
+    pub async fn delete_untracked_older_than<'c, E>(
+        cutoff: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM wallet_activity
+            WHERE id IN (
+                SELECT a.id FROM wallet_activity a
+                WHERE a.timestamp < $1
+                  AND NOT EXISTS (SELECT 1 FROM wallets w WHERE w.address = a.wallet_address)
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Get activity for a wallet
     pub async fn find_by_wallet<'c, E>(
-        wallet_address: &str,
+        wallet_address: &Address20,
         limit: i32,
         connection: E,
     ) -> Result<Vec<WalletActivity>, sqlx::Error>
@@ -86,7 +121,7 @@ impl WalletActivity {
 
     /// Get activity for a token
     pub async fn find_by_token<'c, E>(
-        token_address: &str,
+        token_address: &Address20,
         limit: i32,
         connection: E,
     ) -> Result<Vec<WalletActivity>, sqlx::Error>
@@ -104,8 +139,8 @@ impl WalletActivity {
 
     /// Get recent activity for a wallet on a specific token
     pub async fn find_by_wallet_and_token<'c, E>(
-        wallet_address: &str,
-        token_address: &str,
+        wallet_address: &Address20,
+        token_address: &Address20,
         limit: i32,
         connection: E,
     ) -> Result<Vec<WalletActivity>, sqlx::Error>
@@ -129,7 +164,7 @@ impl WalletActivity {
 
     /// Count unique tokens a wallet has interacted with
     pub async fn count_unique_tokens<'c, E>(
-        wallet_address: &str,
+        wallet_address: &Address20,
         connection: E,
     ) -> Result<i64, sqlx::Error>
     where
@@ -145,10 +180,114 @@ impl WalletActivity {
         Ok(count)
     }
 
+    /// Tokens moved into and out of exchange wallets since `since`, as
+    /// (inflow, outflow) in raw token units
+    pub async fn cex_flows<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<(BigDecimal, BigDecimal), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let row: (Option<BigDecimal>, Option<BigDecimal>) = sqlx::query_as(
+            r#"
+            SELECT
+                SUM(wa.amount_tokens) FILTER (WHERE wa.action = 'transfer_in') AS inflow,
+                SUM(wa.amount_tokens) FILTER (WHERE wa.action = 'transfer_out') AS outflow
+            FROM wallet_activity wa
+            WHERE wa.token_address = $1
+              AND wa.timestamp >= $2
+              AND EXISTS (
+                  SELECT 1 FROM known_addresses k
+                  WHERE k.address = wa.wallet_address AND k.kind = 'cex'
+              )
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .fetch_one(connection)
+        .await?;
+
+        Ok((
+            row.0.unwrap_or_else(|| BigDecimal::from(0)),
+            row.1.unwrap_or_else(|| BigDecimal::from(0)),
+        ))
+    }
+
+    /// Price up to `limit` unpriced transfers with id above `after_id` from the
+    /// snapshot nearest their timestamp (at most `max_gap_secs` away). Returns
+    /// how many were priced and the last id scanned, `None` once none are left.
+    pub async fn backfill_transfer_usd<'c, E>(
+        after_id: i32,
+        limit: i64,
+        max_gap_secs: i64,
+        connection: E,
+    ) -> Result<(u64, Option<i32>), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let row: (i64, Option<i32>) = sqlx::query_as(
+            r#"
+            WITH batch AS (
+                SELECT id, token_address, timestamp, amount_tokens
+                FROM wallet_activity
+                WHERE id > $1
+                  AND action IN ('transfer_in', 'transfer_out')
+                  AND amount_usd IS NULL
+                  AND amount_tokens IS NOT NULL
+                ORDER BY id
+                LIMIT $2
+            ),
+            priced AS (
+                SELECT
+                    b.id,
+                    ROUND(
+                        b.amount_tokens / POWER(10::NUMERIC, COALESCE(t.decimals, 18)) * s.price_usd,
+                        2
+                    ) AS amount_usd
+                FROM batch b
+                LEFT JOIN tokens t ON t.address = b.token_address
+                CROSS JOIN LATERAL (
+                    SELECT nearest.price_usd FROM (
+                        (SELECT timestamp, price_usd FROM price_snapshots
+                         WHERE token_address = b.token_address AND timestamp <= b.timestamp
+                           AND price_usd IS NOT NULL
+                         ORDER BY timestamp DESC LIMIT 1)
+                        UNION ALL
+                        (SELECT timestamp, price_usd FROM price_snapshots
+                         WHERE token_address = b.token_address AND timestamp > b.timestamp
+                           AND price_usd IS NOT NULL
+                         ORDER BY timestamp ASC LIMIT 1)
+                    ) nearest
+                    WHERE ABS(EXTRACT(EPOCH FROM nearest.timestamp - b.timestamp)) <= $3
+                    ORDER BY ABS(EXTRACT(EPOCH FROM nearest.timestamp - b.timestamp))
+                    LIMIT 1
+                ) s
+            ),
+            updated AS (
+                UPDATE wallet_activity a SET amount_usd = p.amount_usd
+                FROM priced p
+                -- Junk prices on spam tokens would overflow DECIMAL(30, 2)
+                WHERE a.id = p.id AND p.amount_usd < 1e28
+                RETURNING a.id
+            )
+            SELECT (SELECT COUNT(*) FROM updated), (SELECT MAX(id) FROM batch)
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .bind(max_gap_secs)
+        .fetch_one(connection)
+        .await?;
+
+        Ok((row.0 as u64, row.1))
+    }
+
     /// Get wallet's profit/loss summary for a token
     pub async fn calculate_pnl<'c, E>(
-        wallet_address: &str,
-        token_address: &str,
+        wallet_address: &Address20,
+        token_address: &Address20,
         connection: E,
     ) -> Result<(BigDecimal, BigDecimal), sqlx::Error>
     where
@@ -174,3 +313,233 @@ impl WalletActivity {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        price_snapshot::{NewPriceSnapshot, PriceSnapshot},
+        test_support::{address, clear_seed_data, hash},
+        token::{NewToken, Token},
+        wallet::{NewWallet, Wallet},
+    };
+
+    fn activity(n: u8, token: u8, action: &str, usd: i32) -> NewWalletActivity {
+        NewWalletActivity {
+            wallet_address: address(1),
+            tx_hash: hash(n),
+            block_number: n as i64,
+            timestamp: Utc::now() - Duration::minutes(n as i64),
+            action: action.to_string(),
+            token_address: address(token),
+            token_symbol: Some("TKN".to_string()),
+            amount_tokens: Some(BigDecimal::from(1)),
+            amount_usd: Some(BigDecimal::from(usd)),
+            source_log_id: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn create_ignores_duplicate_actions(pool: PgPool) {
+        WalletActivity::create(&activity(1, 10, "buy", 5), &pool)
+            .await
+            .unwrap();
+
+        let again = WalletActivity::create(&activity(1, 10, "buy", 5), &pool).await;
+        assert!(matches!(again, Err(sqlx::Error::RowNotFound)));
+
+        // Same transaction, different action is a separate row
+        WalletActivity::create(&activity(1, 10, "transfer_out", 5), &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            WalletActivity::find_by_wallet(&address(1), 10, &pool)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[sqlx::test]
+    async fn per_token_summaries(pool: PgPool) {
+        for (n, token, action, usd) in [
+            (1, 10, "buy", 100),
+            (2, 10, "buy", 50),
+            (3, 10, "sell", 400),
+            (4, 11, "buy", 7),
+        ] {
+            WalletActivity::create(&activity(n, token, action, usd), &pool)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            WalletActivity::count_unique_tokens(&address(1), &pool)
+                .await
+                .unwrap(),
+            2
+        );
+
+        let (sold, bought) = WalletActivity::calculate_pnl(&address(1), &address(10), &pool)
+            .await
+            .unwrap();
+        assert_eq!(sold, BigDecimal::from(400));
+        assert_eq!(bought, BigDecimal::from(150));
+
+        let recent = WalletActivity::find_by_wallet_and_token(&address(1), &address(10), 2, &pool)
+            .await
+            .unwrap();
+        let hashes: Vec<_> = recent.iter().map(|a| a.tx_hash).collect();
+        assert_eq!(hashes, vec![hash(1), hash(2)]);
+
+        assert_eq!(
+            WalletActivity::find_by_token(&address(11), 10, &pool)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[sqlx::test]
+    async fn retention_keeps_tracked_wallets_and_recent_rows(pool: PgPool) {
+        for (wallet, n, days_ago) in [(1, 1, 40), (2, 2, 40), (2, 3, 1)] {
+            let row = NewWalletActivity {
+                wallet_address: address(wallet),
+                timestamp: Utc::now() - Duration::days(days_ago),
+                ..activity(n, 10, "buy", 5)
+            };
+            WalletActivity::create(&row, &pool).await.unwrap();
+        }
+        let tracked = NewWallet {
+            address: address(1),
+            label: None,
+        };
+        Wallet::create(&tracked, &pool).await.unwrap();
+
+        let cutoff = Utc::now() - Duration::days(30);
+        let deleted = WalletActivity::delete_untracked_older_than(cutoff, 100, &pool)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        for wallet in [1, 2] {
+            let rows = WalletActivity::find_by_wallet(&address(wallet), 10, &pool)
+                .await
+                .unwrap();
+            assert_eq!(rows.len(), 1);
+        }
+    }
+
+    #[sqlx::test]
+    async fn cex_flows_count_exchange_wallets_only(pool: PgPool) {
+        sqlx::query(
+            "INSERT INTO known_addresses (chain_id, kind, name, address) VALUES (56, 'cex', 'Binance', $1)",
+        )
+        .bind(address(1))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        for (wallet, n, action, tokens, hours_ago) in [
+            (1, 1, "transfer_in", 500, 1),
+            (1, 2, "transfer_out", 200, 2),
+            (1, 3, "transfer_in", 900, 30),
+            (2, 4, "transfer_in", 50, 1),
+        ] {
+            let row = NewWalletActivity {
+                wallet_address: address(wallet),
+                timestamp: Utc::now() - Duration::hours(hours_ago),
+                amount_tokens: Some(BigDecimal::from(tokens)),
+                ..activity(n, 10, action, 0)
+            };
+            WalletActivity::create(&row, &pool).await.unwrap();
+        }
+
+        let since = Utc::now() - Duration::hours(24);
+        let (inflow, outflow) = WalletActivity::cex_flows(&address(10), since, &pool)
+            .await
+            .unwrap();
+        assert_eq!(inflow, BigDecimal::from(500));
+        assert_eq!(outflow, BigDecimal::from(200));
+    }
+
+    #[sqlx::test]
+    async fn backfill_prices_transfers_from_the_nearest_snapshot(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let token = NewToken {
+            address: address(10),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(6),
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: Some(100),
+        };
+        Token::create(&token, &pool).await.unwrap();
+        let at = Utc::now() - Duration::hours(3);
+        let snapshot = NewPriceSnapshot {
+            token_address: address(10),
+            timestamp: at,
+            price_usd: Some(BigDecimal::from(2)),
+            price_bnb: None,
+            liquidity_usd: None,
+            volume_usd: None,
+            market_cap_usd: None,
+            holder_count: None,
+        };
+        PriceSnapshot::create(&snapshot, &pool).await.unwrap();
+
+        for (n, action, minutes_after) in [
+            (1, "transfer_in", 10),
+            (2, "transfer_out", -20),
+            // Nothing priced near it
+            (3, "transfer_in", 600),
+            // Swaps are priced when recorded
+            (4, "buy", 0),
+        ] {
+            let row = NewWalletActivity {
+                timestamp: at + Duration::minutes(minutes_after),
+                // 5 tokens of 6 decimals
+                amount_tokens: Some(BigDecimal::from(5_000_000)),
+                amount_usd: None,
+                ..activity(n, 10, action, 0)
+            };
+            WalletActivity::create(&row, &pool).await.unwrap();
+        }
+
+        let (priced, last) = WalletActivity::backfill_transfer_usd(0, 1, 3600, &pool)
+            .await
+            .unwrap();
+        assert_eq!(priced, 1);
+        let last = last.unwrap();
+        let (priced, rest) = WalletActivity::backfill_transfer_usd(last, 10, 3600, &pool)
+            .await
+            .unwrap();
+        assert_eq!(priced, 1);
+        let (priced, done) = WalletActivity::backfill_transfer_usd(rest.unwrap(), 10, 3600, &pool)
+            .await
+            .unwrap();
+        assert_eq!((priced, done), (0, None));
+
+        let usd: Vec<_> = WalletActivity::find_by_wallet(&address(1), 10, &pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| (a.tx_hash, a.amount_usd))
+            .collect();
+        let ten = BigDecimal::from(10).with_scale(2);
+        assert!(usd.contains(&(hash(1), Some(ten.clone()))));
+        assert!(usd.contains(&(hash(2), Some(ten))));
+        assert!(usd.contains(&(hash(3), None)));
+        assert!(usd.contains(&(hash(4), None)));
+    }
+}
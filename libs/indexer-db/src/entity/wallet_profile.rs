@@ -0,0 +1,281 @@
+use sqlx::{types::chrono, Executor, Postgres};
+
+use crate::types::Address20;
+
+/// WalletProfile entity: when a wallet first appeared in a swap or a transfer
+///
+/// This is the first sighting by the index, not the wallet's on-chain age:
+/// wallets active before the indexed range look as new as their first
+/// indexed trade. The on-chain nonce, read for recent buyers, tells those
+/// apart from wallets that really are new.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct WalletProfile {
+    pub wallet_address: Address20,
+    pub first_seen_block: i64,
+    pub first_seen_at: chrono::DateTime<chrono::Utc>,
+    /// Transactions the wallet had sent when its nonce was read
+    pub nonce: Option<i64>,
+    /// When the nonce was read
+    pub nonce_checked_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A token's recent buyers whose nonce is known, and how many of them were
+/// brand-new wallets
+#[derive(sqlx::FromRow, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FreshBuyers {
+    pub buyers: i64,
+    /// Buyers first seen shortly before their first buy of the token, with
+    /// hardly any transactions sent
+    pub fresh: i64,
+}
+
+impl WalletProfile {
+    /// Record a wallet seen at `block_number`, keeping the earliest sighting
+    pub async fn record_seen<'c, E>(
+        wallet_address: &Address20,
+        block_number: i64,
+        seen_at: chrono::DateTime<chrono::Utc>,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO wallet_profiles (wallet_address, first_seen_block, first_seen_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (wallet_address) DO UPDATE SET
+                first_seen_block = EXCLUDED.first_seen_block,
+                first_seen_at = EXCLUDED.first_seen_at
+            WHERE EXCLUDED.first_seen_block < wallet_profiles.first_seen_block
+            "#,
+        )
+        .bind(wallet_address)
+        .bind(block_number)
+        .bind(seen_at)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Profiles of the given wallets; wallets never seen are left out
+    pub async fn find_by_addresses<'c, E>(
+        addresses: &[Address20],
+        connection: E,
+    ) -> Result<Vec<WalletProfile>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, WalletProfile>(
+            "SELECT * FROM wallet_profiles WHERE wallet_address = ANY($1)",
+        )
+        .bind(addresses)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Wallets first seen since `since` that bought something since, whose
+    /// nonce hasn't been read yet, newest first
+    pub async fn find_unchecked_buyers<'c, E>(
+        since: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<Address20>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_scalar(
+            r#"
+            SELECT p.wallet_address FROM wallet_profiles p
+            WHERE p.nonce_checked_at IS NULL
+              AND p.first_seen_at >= $1
+              AND EXISTS (
+                  SELECT 1 FROM swaps s
+                  WHERE s.wallet_address = p.wallet_address
+                    AND s.trade_type = 'buy'
+                    AND s.timestamp >= $1
+              )
+            ORDER BY p.first_seen_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+
+    /// Store a wallet's nonce
+    pub async fn record_nonce<'c, E>(
+        wallet_address: &Address20,
+        nonce: i64,
+        connection: E,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query(
+            "UPDATE wallet_profiles SET nonce = $2, nonce_checked_at = NOW() WHERE wallet_address = $1",
+        )
+        .bind(wallet_address)
+        .bind(nonce)
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Wallets with a known nonce that bought a token since `since`, and how
+    /// many of them were first seen at most `max_age_hours` before their
+    /// first buy of it and had sent at most `max_nonce` transactions
+    pub async fn fresh_buyers<'c, E>(
+        token_address: &Address20,
+        since: chrono::DateTime<chrono::Utc>,
+        max_age_hours: i32,
+        max_nonce: i64,
+        connection: E,
+    ) -> Result<FreshBuyers, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, FreshBuyers>(
+            r#"
+            WITH buyers AS (
+                SELECT wallet_address, MIN(timestamp) AS first_buy
+                FROM swaps
+                WHERE token_address = $1 AND trade_type = 'buy' AND timestamp >= $2
+                GROUP BY wallet_address
+            )
+            SELECT
+                COUNT(*) AS buyers,
+                COUNT(*) FILTER (
+                    WHERE p.first_seen_at >= b.first_buy - make_interval(hours => $3)
+                        AND p.nonce <= $4
+                ) AS fresh
+            FROM buyers b
+            JOIN wallet_profiles p ON p.wallet_address = b.wallet_address
+            WHERE p.nonce IS NOT NULL
+            "#,
+        )
+        .bind(token_address)
+        .bind(since)
+        .bind(max_age_hours)
+        .bind(max_nonce)
+        .fetch_one(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::chrono::Duration;
+    use sqlx::{
+        types::{chrono::Utc, BigDecimal},
+        PgPool,
+    };
+
+    use super::*;
+    use crate::entity::{
+        swap::{NewSwap, Swap},
+        test_support::{address, clear_seed_data, hash},
+    };
+
+    #[sqlx::test]
+    async fn first_sightings_are_kept(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let now = Utc::now();
+
+        // Wallet 1 has been around for weeks; it also shows up earlier later on
+        WalletProfile::record_seen(&address(1), 500, now - Duration::days(20), &pool)
+            .await
+            .unwrap();
+        WalletProfile::record_seen(&address(1), 400, now - Duration::days(30), &pool)
+            .await
+            .unwrap();
+        WalletProfile::record_seen(&address(1), 900, now, &pool)
+            .await
+            .unwrap();
+
+        let profiles = WalletProfile::find_by_addresses(&[address(1), address(9)], &pool)
+            .await
+            .unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].first_seen_block, 400);
+        assert_eq!(profiles[0].nonce, None);
+    }
+
+    #[sqlx::test]
+    async fn fresh_buyers_are_new_on_chain_and_to_the_index(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        let now = Utc::now();
+
+        // Wallet 1 was seen weeks ago; 2-4 appeared minutes before buying
+        WalletProfile::record_seen(&address(1), 400, now - Duration::days(20), &pool)
+            .await
+            .unwrap();
+        for n in 2..=4 {
+            WalletProfile::record_seen(&address(n), 1_000, now - Duration::minutes(30), &pool)
+                .await
+                .unwrap();
+        }
+        for n in 1..=4 {
+            let swap = NewSwap {
+                tx_hash: hash(n),
+                block_number: 1_001,
+                log_index: 0,
+                timestamp: now - Duration::minutes(10),
+                pair_address: address(100),
+                token_address: address(50),
+                wallet_address: address(n),
+                trade_type: "buy".to_string(),
+                amount_tokens: Some(BigDecimal::from(1)),
+                amount_bnb: Some(BigDecimal::from(1)),
+                amount_usd: Some(BigDecimal::from(100)),
+                price_usd: Some(BigDecimal::from(1)),
+                is_whale: false,
+                tx_index: None,
+                gas_price_percentile: None,
+                mev_flags: Vec::new(),
+                legs: None,
+                source_log_id: None,
+            };
+            Swap::create(&swap, &pool).await.unwrap();
+        }
+
+        // Only the recent buyers wait for their nonce
+        let since = now - Duration::hours(24);
+        let unchecked = WalletProfile::find_unchecked_buyers(since, 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(unchecked.len(), 3);
+        assert!(!unchecked.contains(&address(1)));
+
+        // Wallet 3 is new to the index but has traded for years on chain;
+        // wallet 4's nonce hasn't been read yet
+        WalletProfile::record_nonce(&address(1), 0, &pool)
+            .await
+            .unwrap();
+        WalletProfile::record_nonce(&address(2), 1, &pool)
+            .await
+            .unwrap();
+        WalletProfile::record_nonce(&address(3), 5_000, &pool)
+            .await
+            .unwrap();
+        let unchecked = WalletProfile::find_unchecked_buyers(since, 10, &pool)
+            .await
+            .unwrap();
+        assert_eq!(unchecked, vec![address(4)]);
+
+        let counted = WalletProfile::fresh_buyers(&address(50), since, 24, 3, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            counted,
+            FreshBuyers {
+                buyers: 3,
+                fresh: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use sqlx::{
+    types::{chrono, BigDecimal},
+    Executor, Postgres,
+};
+
+use crate::types::Address20;
+
+/// WalletSuggestion entity: an untracked wallet the nightly analysis found
+/// worth tracking
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct WalletSuggestion {
+    pub wallet_address: Address20,
+    /// `top_pnl` and/or `early_buyer`
+    pub reasons: Vec<String>,
+    /// PnL realized by sells in the analysis window, against the average
+    /// buy price of the tokens sold
+    pub pnl_usd: BigDecimal,
+    /// Tokens bought within their first blocks that later scored high
+    pub early_win_tokens: Vec<Address20>,
+    pub analyzed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// What makes a wallet worth suggesting
+#[derive(Debug, Clone)]
+pub struct SuggestionCriteria {
+    /// Days of sells PnL is realized over
+    pub pnl_days: i32,
+    /// Most profitable wallets suggested
+    pub top_pnl: i64,
+    /// Blocks after a token's creation block that still count as an early buy
+    pub early_blocks: i64,
+    /// Early buys of high scoring tokens needed to be suggested
+    pub min_early_wins: i64,
+    /// BeeScore a token has to reach after the buy
+    pub min_score: i16,
+}
+
+impl WalletSuggestion {
+    /// Remove every suggestion ahead of a new analysis
+    pub async fn clear<'c, E>(connection: E) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query("DELETE FROM wallet_suggestions")
+            .execute(connection)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Find wallets matching `criteria` in the stored swaps and record them,
+    /// returning how many were suggested
+    ///
+    /// Tracked wallets, known addresses (exchanges, routers, ...), pairs and
+    /// token contracts are never suggested, nor are creators for buys of their
+    /// own tokens. Run after [`WalletSuggestion::clear`].
+    pub async fn analyze<'c, E>(
+        criteria: &SuggestionCriteria,
+        connection: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let result = sqlx::query(
+            r#"
+            WITH trades AS (
+                SELECT * FROM swaps s
+                WHERE NOT EXISTS (SELECT 1 FROM wallets w WHERE w.address = s.wallet_address)
+                    AND NOT EXISTS (
+                        SELECT 1 FROM known_addresses k WHERE k.address = s.wallet_address
+                    )
+                    AND NOT EXISTS (SELECT 1 FROM pairs p WHERE p.address = s.wallet_address)
+                    AND NOT EXISTS (SELECT 1 FROM tokens t WHERE t.address = s.wallet_address)
+            ),
+            legs AS (
+                SELECT
+                    wallet_address,
+                    token_address,
+                    SUM(amount_tokens) FILTER (WHERE trade_type = 'buy' AND amount_usd IS NOT NULL) AS bought,
+                    SUM(amount_usd) FILTER (WHERE trade_type = 'buy' AND amount_tokens IS NOT NULL) AS bought_usd,
+                    SUM(amount_tokens) FILTER (
+                        WHERE trade_type = 'sell' AND amount_usd IS NOT NULL
+                            AND timestamp > NOW() - make_interval(days => $1)
+                    ) AS sold,
+                    SUM(amount_usd) FILTER (
+                        WHERE trade_type = 'sell' AND amount_tokens IS NOT NULL
+                            AND timestamp > NOW() - make_interval(days => $1)
+                    ) AS sold_usd
+                FROM trades
+                GROUP BY wallet_address, token_address
+            ),
+            pnl AS (
+                -- Tokens sold in the window out of what was bought, at the
+                -- average sell price less the average buy price
+                SELECT
+                    wallet_address,
+                    SUM(ROUND(
+                        LEAST(sold, bought)
+                            * (sold_usd / NULLIF(sold, 0) - bought_usd / NULLIF(bought, 0)),
+                        2
+                    )) AS pnl_usd
+                FROM legs
+                WHERE sold > 0 AND bought > 0
+                GROUP BY wallet_address
+            ),
+            top_pnl AS (
+                SELECT wallet_address FROM pnl
+                WHERE pnl_usd > 0
+                ORDER BY pnl_usd DESC
+                LIMIT $2
+            ),
+            first_buys AS (
+                SELECT wallet_address, token_address,
+                    MIN(block_number) AS block_number, MIN(timestamp) AS bought_at
+                FROM trades
+                WHERE trade_type = 'buy'
+                GROUP BY wallet_address, token_address
+            ),
+            early AS (
+                SELECT b.wallet_address, array_agg(t.address ORDER BY t.address) AS tokens
+                FROM first_buys b
+                JOIN tokens t ON t.address = b.token_address
+                WHERE b.block_number <= t.block_number + $3
+                    AND b.wallet_address IS DISTINCT FROM t.creator_address
+                    AND (
+                        t.bee_score >= $5
+                        OR EXISTS (
+                            SELECT 1 FROM score_history h
+                            WHERE h.token_address = t.address
+                                AND h.bee_score >= $5
+                                AND h.computed_at > b.bought_at
+                        )
+                    )
+                GROUP BY b.wallet_address
+                HAVING COUNT(*) >= $4
+            )
+            INSERT INTO wallet_suggestions (wallet_address, reasons, pnl_usd, early_win_tokens)
+            SELECT
+                c.wallet_address,
+                array_remove(ARRAY[
+                    CASE WHEN c.wallet_address IN (SELECT wallet_address FROM top_pnl)
+                        THEN 'top_pnl' END,
+                    CASE WHEN e.wallet_address IS NOT NULL THEN 'early_buyer' END
+                ], NULL),
+                COALESCE(p.pnl_usd, 0),
+                COALESCE(e.tokens, '{}')
+            FROM (
+                SELECT wallet_address FROM top_pnl
+                UNION
+                SELECT wallet_address FROM early
+            ) c
+            LEFT JOIN pnl p ON p.wallet_address = c.wallet_address
+            LEFT JOIN early e ON e.wallet_address = c.wallet_address
+            "#,
+        )
+        .bind(criteria.pnl_days)
+        .bind(criteria.top_pnl)
+        .bind(criteria.early_blocks)
+        .bind(criteria.min_early_wins)
+        .bind(criteria.min_score)
+        .execute(connection)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Suggestions that still aren't tracked, wallets matching both reasons
+    /// first, then by PnL
+    pub async fn find<'c, E>(
+        limit: i64,
+        connection: E,
+    ) -> Result<Vec<WalletSuggestion>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query_as::<_, WalletSuggestion>(
+            r#"
+            SELECT s.* FROM wallet_suggestions s
+            WHERE NOT EXISTS (SELECT 1 FROM wallets w WHERE w.address = s.wallet_address)
+            ORDER BY cardinality(s.reasons) DESC, cardinality(s.early_win_tokens) DESC,
+                s.pnl_usd DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{types::chrono::Utc, PgPool};
+
+    use super::*;
+    use crate::entity::{
+        swap::{NewSwap, Swap},
+        test_support::{address, clear_seed_data, hash},
+        token::{NewToken, Token},
+        wallet::{NewWallet, Wallet},
+    };
+
+    fn swap(n: u8, token: u8, wallet: u8, block: i64, trade_type: &str, usd: i32) -> NewSwap {
+        NewSwap {
+            tx_hash: hash(n),
+            block_number: block,
+            log_index: 0,
+            timestamp: Utc::now(),
+            pair_address: address(200),
+            token_address: address(token),
+            wallet_address: address(wallet),
+            trade_type: trade_type.to_string(),
+            amount_tokens: Some(BigDecimal::from(1)),
+            amount_bnb: Some(BigDecimal::from(1)),
+            amount_usd: Some(BigDecimal::from(usd)),
+            price_usd: Some(BigDecimal::from(1)),
+            is_whale: false,
+            tx_index: None,
+            gas_price_percentile: None,
+            mev_flags: Vec::new(),
+            legs: None,
+            source_log_id: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn profitable_traders_and_early_winners_are_suggested(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        // Tokens 1-3 scored high, token 4 didn't; token 3 was created by wallet 60
+        for (token, score, creator) in [(1, 85, 99), (2, 90, 99), (3, 80, 60), (4, 40, 99)] {
+            let new_token = NewToken {
+                address: address(token),
+                name: None,
+                symbol: None,
+                name_raw: None,
+                symbol_raw: None,
+                name_spoofed: false,
+                decimals: None,
+                total_supply: None,
+                pair_address: None,
+                creator_address: Some(address(creator)),
+                block_number: Some(1_000),
+            };
+            Token::create(&new_token, &pool).await.unwrap();
+            Token::update_bee_score(&address(token), score, score.min(60), score.min(40), &pool)
+                .await
+                .unwrap();
+        }
+
+        let swaps = [
+            // Wallet 50 bought three high scorers within their first blocks
+            swap(1, 1, 50, 1_002, "buy", 100),
+            swap(2, 2, 50, 1_005, "buy", 100),
+            swap(3, 3, 50, 1_010, "buy", 100),
+            // Wallet 60 created token 3 and bought it late elsewhere
+            swap(4, 1, 60, 1_001, "buy", 100),
+            swap(5, 2, 60, 1_001, "buy", 100),
+            swap(6, 3, 60, 1_001, "buy", 100),
+            swap(7, 4, 60, 1_001, "buy", 100),
+            // Wallet 70 traded at a profit, wallet 80 at a loss
+            swap(8, 4, 70, 5_000, "buy", 1_000),
+            swap(9, 4, 70, 5_001, "sell", 3_000),
+            swap(10, 4, 80, 5_000, "buy", 1_000),
+            swap(11, 4, 80, 5_001, "sell", 500),
+            // Wallet 90 made the most but is already tracked
+            swap(12, 4, 90, 5_000, "sell", 9_000),
+            // Wallet 95 sold without a recorded buy: nothing realized
+            swap(13, 4, 95, 5_000, "sell", 9_000),
+        ];
+        for s in &swaps {
+            Swap::create(s, &pool).await.unwrap();
+        }
+        let tracked = NewWallet {
+            address: address(90),
+            label: None,
+        };
+        Wallet::create(&tracked, &pool).await.unwrap();
+
+        let criteria = SuggestionCriteria {
+            pnl_days: 7,
+            top_pnl: 2,
+            early_blocks: 20,
+            min_early_wins: 3,
+            min_score: 80,
+        };
+        assert_eq!(
+            WalletSuggestion::analyze(&criteria, &pool).await.unwrap(),
+            2
+        );
+
+        let suggested = WalletSuggestion::find(10, &pool).await.unwrap();
+        let early = &suggested[0];
+        assert_eq!(early.wallet_address, address(50));
+        assert_eq!(early.reasons, vec!["early_buyer"]);
+        assert_eq!(
+            early.early_win_tokens,
+            vec![address(1), address(2), address(3)]
+        );
+        let trader = &suggested[1];
+        assert_eq!(trader.wallet_address, address(70));
+        assert_eq!(trader.reasons, vec!["top_pnl"]);
+        assert_eq!(trader.pnl_usd, BigDecimal::from(2_000));
+        assert_eq!(suggested.len(), 2);
+
+        // Tracking a suggestion hides it until the next analysis drops it
+        let added = NewWallet {
+            address: address(70),
+            label: None,
+        };
+        Wallet::create(&added, &pool).await.unwrap();
+        assert_eq!(WalletSuggestion::find(10, &pool).await.unwrap().len(), 1);
+
+        assert_eq!(WalletSuggestion::clear(&pool).await.unwrap(), 2);
+        assert!(WalletSuggestion::find(10, &pool).await.unwrap().is_empty());
+    }
+}
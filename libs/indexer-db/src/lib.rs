@@ -6,12 +6,20 @@ use sqlx::{
 };
 
 pub mod entity;
+pub mod logging;
+pub mod maintenance;
+pub mod net;
+pub mod query_plan;
+pub mod queue;
+pub mod seed;
+pub mod types;
 
 // Re-export commonly used types
 pub use entity::{
     AlertEvent, EvmChains, EvmLogs, EvmSyncLogs, LpLock, Pair, PriceSnapshot, Swap, Token,
     TokenHolder, Wallet, WalletActivity, WalletWithStats,
 };
+pub use types::{Address20, Hash32};
 
 mod defaults {
     pub const DATABASE_MAX_CONNECTIONS: &str = "5";
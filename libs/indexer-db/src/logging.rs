@@ -0,0 +1,50 @@
+//! Log output of the listener and processor
+//!
+//! `RUST_LOG` sets the levels, with per-module overrides such as
+//! `info,processor::handlers=debug`. `LOG_FORMAT=json` writes one JSON object
+//! per line for log collectors (Loki, Datadog); `pretty`, the default, writes
+//! human-readable lines.
+
+use std::env;
+
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// Install the global subscriber, with `default_levels` when `RUST_LOG` is
+/// unset. Unusable settings fall back to the defaults with a warning once
+/// logging is up.
+pub fn init(default_levels: &str) {
+    let mut warnings = Vec::new();
+
+    let filter = match env::var("RUST_LOG") {
+        Ok(directives) => EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+            warnings.push(format!("Ignoring invalid RUST_LOG {:?}: {}", directives, e));
+            EnvFilter::new(default_levels)
+        }),
+        Err(_) => EnvFilter::new(default_levels),
+    };
+
+    let json = match env::var("LOG_FORMAT").as_deref() {
+        Ok("json") => true,
+        Ok("pretty") | Err(_) => false,
+        Ok(other) => {
+            warnings.push(format!(
+                "Ignoring LOG_FORMAT {:?}: expected json or pretty",
+                other
+            ));
+            false
+        }
+    };
+
+    let registry = tracing_subscriber::registry().with(filter);
+    if json {
+        registry
+            .with(fmt::layer().json().flatten_event(true))
+            .init();
+    } else {
+        registry.with(fmt::layer()).init();
+    }
+
+    for warning in warnings {
+        tracing::warn!("{}", warning);
+    }
+}
@@ -0,0 +1,409 @@
+//! Database maintenance
+//!
+//! The operations behind `indexer-db-cli`: applying and verifying migrations,
+//! pruning old rows, recomputing one token's derived columns from the rows
+//! they summarize, and reporting table sizes.
+
+use std::{fmt, time::Duration};
+
+use sqlx::{
+    migrate::{MigrateError, Migrator},
+    types::chrono::Utc,
+    Pool, Postgres,
+};
+
+use crate::{
+    entity::{
+        alert::AlertEvent, handled_log::HandledLog, holder_churn::HolderChurn,
+        idempotency_key::IdempotencyKey, pending_swap::PendingSwap, price_snapshot::PriceSnapshot,
+        processing_error::ProcessingError, retention_run::RetentionRun,
+        score_history::ScoreHistory, swap::Swap, token::Token, token_holder::TokenHolder,
+        token_metrics_minute::TokenMetricsMinute, wallet_activity::WalletActivity,
+    },
+    types::Address20,
+};
+
+/// The migrations this build ships with
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Apply pending migrations
+pub async fn migrate(pool: &Pool<Postgres>) -> Result<(), MigrateError> {
+    MIGRATOR.run(pool).await
+}
+
+/// A difference between the shipped migrations and the applied ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaIssue {
+    /// Shipped but not applied yet
+    Pending { version: i64, description: String },
+    /// Applied, but the file has changed since
+    Modified { version: i64, description: String },
+    /// Started but never finished
+    Failed { version: i64, description: String },
+    /// Applied, but not shipped with this build
+    Unknown { version: i64, description: String },
+}
+
+impl fmt::Display for SchemaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (state, version, description) = match self {
+            SchemaIssue::Pending {
+                version,
+                description,
+            } => ("pending", version, description),
+            SchemaIssue::Modified {
+                version,
+                description,
+            } => ("modified after it was applied", version, description),
+            SchemaIssue::Failed {
+                version,
+                description,
+            } => ("failed", version, description),
+            SchemaIssue::Unknown {
+                version,
+                description,
+            } => ("applied but unknown to this build", version, description),
+        };
+        write!(f, "{} {}: {}", version, description, state)
+    }
+}
+
+/// Compare `_sqlx_migrations` against the shipped migrations
+pub async fn verify_schema(pool: &Pool<Postgres>) -> Result<Vec<SchemaIssue>, sqlx::Error> {
+    let tracked: Option<String> =
+        sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations')::TEXT")
+            .fetch_one(pool)
+            .await?;
+    let applied: Vec<(i64, String, bool, Vec<u8>)> = match tracked {
+        Some(_) => sqlx::query_as(
+            "SELECT version, description, success, checksum FROM _sqlx_migrations ORDER BY version",
+        )
+        .fetch_all(pool)
+        .await?,
+        None => Vec::new(),
+    };
+
+    let mut issues = Vec::new();
+    for migration in MIGRATOR.iter() {
+        if migration.migration_type.is_down_migration() {
+            continue;
+        }
+        let version = migration.version;
+        let description = migration.description.to_string();
+        match applied.iter().find(|(v, ..)| *v == version) {
+            None => issues.push(SchemaIssue::Pending {
+                version,
+                description,
+            }),
+            Some((_, _, false, _)) => issues.push(SchemaIssue::Failed {
+                version,
+                description,
+            }),
+            Some((.., checksum)) if *checksum != *migration.checksum => {
+                issues.push(SchemaIssue::Modified {
+                    version,
+                    description,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for (version, description, ..) in &applied {
+        if !MIGRATOR.version_exists(*version) {
+            issues.push(SchemaIssue::Unknown {
+                version: *version,
+                description: description.clone(),
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Tables trimmed by age, by `prune` here and by the processor's retention
+/// policies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneTable {
+    Swaps,
+    /// Only activity of wallets that aren't tracked in `wallets`
+    WalletActivity,
+    AlertEvents,
+    /// Downsampled to the latest snapshot per token per hour
+    PriceSnapshots,
+    ScoreHistory,
+    /// Responses stored for `Idempotency-Key` retries of API mutations
+    IdempotencyKeys,
+    /// Logs the processor failed to decode or handle
+    ProcessingErrors,
+    /// Swaps kept for pairs that aren't indexed
+    PendingSwaps,
+    /// Logs the processor handled, kept to skip queue redeliveries
+    HandledLogs,
+    /// Per-minute trade buckets behind the 1h/24h counters on `tokens`
+    TokenMetricsMinute,
+    /// Hourly holder entries and exits behind the 24h churn on `tokens`
+    HolderChurn,
+    /// The record of past retention passes
+    RetentionRuns,
+}
+
+impl PruneTable {
+    pub const ALL: [PruneTable; 12] = [
+        PruneTable::Swaps,
+        PruneTable::WalletActivity,
+        PruneTable::AlertEvents,
+        PruneTable::PriceSnapshots,
+        PruneTable::ScoreHistory,
+        PruneTable::IdempotencyKeys,
+        PruneTable::ProcessingErrors,
+        PruneTable::PendingSwaps,
+        PruneTable::HandledLogs,
+        PruneTable::TokenMetricsMinute,
+        PruneTable::HolderChurn,
+        PruneTable::RetentionRuns,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PruneTable::Swaps => "swaps",
+            PruneTable::WalletActivity => "wallet_activity",
+            PruneTable::AlertEvents => "alert_events",
+            PruneTable::PriceSnapshots => "price_snapshots",
+            PruneTable::ScoreHistory => "score_history",
+            PruneTable::IdempotencyKeys => "idempotency_keys",
+            PruneTable::ProcessingErrors => "processing_errors",
+            PruneTable::PendingSwaps => "pending_swaps",
+            PruneTable::HandledLogs => "handled_logs",
+            PruneTable::TokenMetricsMinute => "token_metrics_minute",
+            PruneTable::HolderChurn => "token_holder_churn_hourly",
+            PruneTable::RetentionRuns => "retention_runs",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// Remove up to `limit` rows older than `cutoff`. Price snapshots are
+    /// downsampled from `since` on only (see
+    /// [`PriceSnapshot::downsample_older_than`]); deletes by age ignore it.
+    pub async fn trim_batch(
+        &self,
+        since: Option<sqlx::types::chrono::DateTime<Utc>>,
+        cutoff: sqlx::types::chrono::DateTime<Utc>,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<u64, sqlx::Error> {
+        match self {
+            PruneTable::Swaps => Swap::delete_older_than(cutoff, limit, pool).await,
+            PruneTable::WalletActivity => {
+                WalletActivity::delete_untracked_older_than(cutoff, limit, pool).await
+            }
+            PruneTable::AlertEvents => AlertEvent::delete_older_than(cutoff, limit, pool).await,
+            PruneTable::PriceSnapshots => {
+                PriceSnapshot::downsample_older_than(since, cutoff, limit, pool).await
+            }
+            PruneTable::ScoreHistory => ScoreHistory::delete_older_than(cutoff, limit, pool).await,
+            PruneTable::IdempotencyKeys => {
+                IdempotencyKey::delete_older_than(cutoff, limit, pool).await
+            }
+            PruneTable::ProcessingErrors => {
+                ProcessingError::delete_older_than(cutoff, limit, pool).await
+            }
+            PruneTable::PendingSwaps => PendingSwap::delete_older_than(cutoff, limit, pool).await,
+            PruneTable::HandledLogs => HandledLog::delete_older_than(cutoff, limit, pool).await,
+            PruneTable::TokenMetricsMinute => {
+                TokenMetricsMinute::delete_older_than(cutoff, limit, pool).await
+            }
+            PruneTable::HolderChurn => HolderChurn::delete_older_than(cutoff, limit, pool).await,
+            PruneTable::RetentionRuns => RetentionRun::delete_older_than(cutoff, limit, pool).await,
+        }
+    }
+}
+
+/// Remove rows of `table` older than `days` in batches of `batch`, returning
+/// how many were removed
+pub async fn prune(
+    table: PruneTable,
+    days: i64,
+    batch: i64,
+    pool: &Pool<Postgres>,
+) -> Result<u64, sqlx::Error> {
+    let cutoff = Utc::now() - Duration::from_secs(days.max(0) as u64 * 86_400);
+    let batch = batch.max(1);
+    let mut removed = 0;
+
+    loop {
+        let count = table.trim_batch(None, cutoff, batch, pool).await?;
+        removed += count;
+        if (count as i64) < batch {
+            return Ok(removed);
+        }
+    }
+}
+
+/// Recompute a token's trade counters, holder shares and metrics, sniper
+/// ratio and churn from the rows they summarize. `false` for unknown tokens.
+pub async fn reindex_token(
+    address: &Address20,
+    pool: &Pool<Postgres>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let Some(token) = Token::find_by_address(address, &mut *tx).await? else {
+        return Ok(false);
+    };
+
+    Token::refresh_trade_rollup(address, &mut *tx).await?;
+    if let Some(total_supply) = token.total_supply.filter(|s| *s > 0.into()) {
+        TokenHolder::recalculate_percentages(address, &total_supply, &mut *tx).await?;
+    }
+    Token::refresh_holder_metrics(address, &mut *tx).await?;
+    Token::refresh_sniper_ratio(address, &mut *tx).await?;
+    Token::refresh_holder_churn(address, &mut *tx).await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Size of one table (or materialized view)
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TableStats {
+    pub table_name: String,
+    /// Planner estimate unless counted exactly
+    pub rows: i64,
+    /// Including indexes and TOAST
+    pub total_bytes: i64,
+}
+
+/// Every table and materialized view in the current schema, largest first.
+/// `exact` counts rows instead of using the planner's estimate.
+pub async fn table_stats(
+    exact: bool,
+    pool: &Pool<Postgres>,
+) -> Result<Vec<TableStats>, sqlx::Error> {
+    let mut stats = sqlx::query_as::<_, TableStats>(
+        r#"
+        SELECT
+            c.relname::TEXT AS table_name,
+            GREATEST(c.reltuples, 0)::BIGINT AS rows,
+            pg_total_relation_size(c.oid) AS total_bytes
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = current_schema() AND c.relkind IN ('r', 'p', 'm')
+        ORDER BY total_bytes DESC, table_name
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if exact {
+        for table in &mut stats {
+            let query = format!(
+                "SELECT COUNT(*) FROM \"{}\"",
+                table.table_name.replace('"', "\"\"")
+            );
+            table.rows = sqlx::query_scalar(&query).fetch_one(pool).await?;
+        }
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::{
+        test_support::{address, clear_seed_data},
+        token::NewToken,
+        token_holder::NewTokenHolder,
+    };
+
+    #[sqlx::test]
+    async fn a_migrated_database_verifies_clean(pool: PgPool) {
+        assert_eq!(verify_schema(&pool).await.unwrap(), Vec::new());
+
+        sqlx::query(
+            "UPDATE _sqlx_migrations SET checksum = '\\x00' WHERE version = 20251127000001",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let issues = verify_schema(&pool).await.unwrap();
+        assert!(matches!(
+            issues.as_slice(),
+            [SchemaIssue::Modified {
+                version: 20251127000001,
+                ..
+            }]
+        ));
+
+        let stats = table_stats(true, &pool).await.unwrap();
+        assert!(stats.iter().any(|t| t.table_name == "tokens" && t.rows > 0));
+    }
+
+    #[sqlx::test]
+    async fn reindexing_recomputes_holder_metrics(pool: PgPool) {
+        clear_seed_data(&pool).await;
+        assert!(!reindex_token(&address(1), &pool).await.unwrap());
+
+        let token = NewToken {
+            address: address(1),
+            name: None,
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: Some(18),
+            total_supply: Some(1_000.into()),
+            pair_address: None,
+            creator_address: None,
+            block_number: Some(100),
+        };
+        Token::create(&token, &pool).await.unwrap();
+        for (wallet, balance) in [(10, 600), (11, 100)] {
+            let holder = NewTokenHolder {
+                token_address: address(1),
+                wallet_address: address(wallet),
+                balance: balance.into(),
+                is_dev: false,
+                is_sniper: wallet == 11,
+                is_contract: false,
+                first_buy_block: None,
+                source: None,
+            };
+            TokenHolder::upsert(&holder, &pool).await.unwrap();
+        }
+
+        assert!(reindex_token(&address(1), &pool).await.unwrap());
+        let token = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.holder_count, Some(2));
+        assert_eq!(token.top_10_holder_percent, Some("70.00".parse().unwrap()));
+        assert_eq!(token.sniper_ratio, Some("10.00".parse().unwrap()));
+
+        assert_eq!(
+            PruneTable::parse("score_history"),
+            Some(PruneTable::ScoreHistory)
+        );
+        assert_eq!(
+            PruneTable::parse("handled_logs"),
+            Some(PruneTable::HandledLogs)
+        );
+        assert_eq!(
+            PruneTable::parse("retention_runs"),
+            Some(PruneTable::RetentionRuns)
+        );
+        assert_eq!(PruneTable::parse("tokens"), None);
+        assert_eq!(prune(PruneTable::Swaps, 30, 10, &pool).await.unwrap(), 0);
+        assert_eq!(
+            prune(PruneTable::RetentionRuns, 30, 10, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Public address checks for outbound requests
+//!
+//! Webhook URLs come from API clients, so the API checks them when they are
+//! registered and the processor checks where they resolve to when it
+//! delivers. Both refuse the same hosts: loopback, private, link-local and
+//! other non-routable addresses, and single-label or internal-only names.
+
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Name suffixes that only resolve inside a private network
+const INTERNAL_SUFFIXES: &[&str] = &["localhost", "local", "internal", "localdomain", "home.arpa"];
+
+/// Whether `domain` may name a host on the public internet
+pub fn is_public_domain(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    domain.contains('.')
+        && !INTERNAL_SUFFIXES
+            .iter()
+            .any(|suffix| domain == *suffix || domain.ends_with(&format!(".{}", suffix)))
+}
+
+/// Whether `ip` is routable on the public internet
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_ipv4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ipv4(v4);
+            }
+            let first = v6.segments()[0];
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // 0.0.0.0/8, carrier-grade NAT 100.64.0.0/10, 198.18.0.0/15 and 240.0.0.0/4
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_routable_addresses_are_public() {
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn internal_names_are_not_public() {
+        assert!(is_public_domain("hooks.example.com"));
+        assert!(is_public_domain("example.com."));
+        for domain in [
+            "localhost",
+            "intranet",
+            "db.internal",
+            "printer.local",
+            "LOCALHOST.",
+        ] {
+            assert!(!is_public_domain(domain), "{}", domain);
+        }
+    }
+}
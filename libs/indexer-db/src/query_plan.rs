@@ -0,0 +1,117 @@
+//! Query plan guardrails
+//!
+//! EXPLAINs the hot read queries with sequential scans disabled. If a plan
+//! still contains a `Seq Scan`, no index is able to serve that query.
+
+use serde_json::Value as JsonValue;
+use sqlx::{types::Json, Pool, Postgres};
+
+use crate::types::Address20;
+
+/// A hot query whose plan fell back to a sequential scan
+#[derive(Debug, Clone)]
+pub struct PlanWarning {
+    pub query: &'static str,
+    pub relation: String,
+}
+
+struct HotQuery {
+    name: &'static str,
+    sql: &'static str,
+    binds_address: bool,
+}
+
+const HOT_QUERIES: &[HotQuery] = &[
+    HotQuery {
+        name: "swaps_by_token",
+        sql: "SELECT * FROM swaps WHERE token_address = $1 ORDER BY timestamp DESC LIMIT 50",
+        binds_address: true,
+    },
+    HotQuery {
+        name: "top_holders",
+        sql: "SELECT * FROM token_holders WHERE token_address = $1 ORDER BY balance DESC NULLS LAST LIMIT 20",
+        binds_address: true,
+    },
+    HotQuery {
+        name: "alert_feed",
+        sql: "SELECT * FROM alert_events ORDER BY created_at DESC LIMIT 50",
+        binds_address: false,
+    },
+    HotQuery {
+        name: "wallet_activity",
+        sql: "SELECT * FROM wallet_activity WHERE wallet_address = $1 ORDER BY timestamp DESC LIMIT 50",
+        binds_address: true,
+    },
+    HotQuery {
+        name: "price_history",
+        sql: "SELECT * FROM price_snapshots WHERE token_address = $1 ORDER BY timestamp DESC LIMIT 100",
+        binds_address: true,
+    },
+    HotQuery {
+        name: "hot_tokens",
+        sql: "SELECT * FROM tokens ORDER BY (COALESCE(volume_1h_usd, 0) + COALESCE(bee_score, 0) * 100) DESC LIMIT 20",
+        binds_address: false,
+    },
+];
+
+/// EXPLAIN each hot query and report any that still need a sequential scan
+pub async fn check_query_plans(pool: &Pool<Postgres>) -> Result<Vec<PlanWarning>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Only affects this transaction; forces the planner to prefer any usable index
+    sqlx::query("SET LOCAL enable_seqscan = off")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut warnings = Vec::new();
+
+    for hot in HOT_QUERIES {
+        let explain = format!("EXPLAIN (FORMAT JSON) {}", hot.sql);
+        let mut query = sqlx::query_scalar::<_, Json<JsonValue>>(&explain);
+        if hot.binds_address {
+            query = query.bind(Address20::ZERO);
+        }
+
+        let Json(plan) = query.fetch_one(&mut *tx).await?;
+
+        let mut relations = Vec::new();
+        collect_seq_scans(&plan, &mut relations);
+
+        warnings.extend(relations.into_iter().map(|relation| PlanWarning {
+            query: hot.name,
+            relation,
+        }));
+    }
+
+    tx.rollback().await?;
+
+    Ok(warnings)
+}
+
+/// Walk an EXPLAIN JSON tree collecting relations read by `Seq Scan` nodes
+fn collect_seq_scans(node: &JsonValue, relations: &mut Vec<String>) {
+    match node {
+        JsonValue::Array(items) => {
+            for item in items {
+                collect_seq_scans(item, relations);
+            }
+        }
+        JsonValue::Object(map) => {
+            if map.get("Node Type").and_then(JsonValue::as_str) == Some("Seq Scan") {
+                let relation = map
+                    .get("Relation Name")
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or("unknown");
+                relations.push(relation.to_string());
+            }
+
+            if let Some(plan) = map.get("Plan") {
+                collect_seq_scans(plan, relations);
+            }
+            if let Some(plans) = map.get("Plans") {
+                collect_seq_scans(plans, relations);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,236 @@
+//! Listener → processor log queue
+//!
+//! The listener pushes raw logs and the processor pulls, handles and acks
+//! them. `evm_logs` in Postgres is the default queue; high-throughput
+//! deployments can move it off the main database with `LOG_QUEUE=redis`
+//! (a Redis Stream at `REDIS_URL`) or `LOG_QUEUE=nats` (a NATS JetStream
+//! stream at `NATS_URL`).
+//!
+//! Delivery is at least once: a log pulled but never acked is handed out
+//! again, and overlapping fetches queue a log twice. The processor keeps the
+//! logs it handled in `handled_logs` and skips repeats.
+
+use std::{env, fmt, future::Future};
+
+use alloy::rpc::types::Log;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+use thiserror::Error;
+
+use crate::entity::evm_logs::EvmLogs;
+
+pub mod nats;
+pub mod postgres;
+pub mod redis;
+
+pub use self::{nats::NatsQueue, postgres::PostgresQueue, redis::RedisStreamQueue};
+
+mod defaults {
+    pub const LOG_QUEUE: &str = "postgres";
+    pub const NATS_URL: &str = "nats://127.0.0.1:4222";
+    /// Consumer name within the Redis consumer group / NATS durable consumer
+    pub const LOG_QUEUE_CONSUMER: &str = "processor";
+}
+
+#[derive(Error, Debug)]
+pub enum QueueError {
+    #[error("Unknown LOG_QUEUE `{0}`, expected postgres, redis or nats")]
+    UnknownBackend(String),
+
+    #[error("Missing `{0}` environment variable")]
+    MissingEnvVar(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Redis error: {0}")]
+    Redis(#[from] ::redis::RedisError),
+
+    #[error("NATS error: {0}")]
+    Nats(String),
+
+    #[error("Invalid queued log: {0}")]
+    Encoding(#[from] serde_json::Error),
+}
+
+/// A queue of raw logs between the listener and the processor
+pub trait LogQueue {
+    /// Enqueue fetched logs, returning how many were queued. Logs already
+    /// queued count as queued; logs the backend rejects one by one (e.g.
+    /// pending ones) are skipped.
+    fn push(&self, logs: Vec<Log>) -> impl Future<Output = Result<usize, QueueError>> + Send;
+
+    /// Up to `limit` logs that have not been acked yet
+    fn pull(&self, limit: usize)
+        -> impl Future<Output = Result<Vec<QueuedLog>, QueueError>> + Send;
+
+    /// Remove a processed log from the queue
+    fn ack(&self, log: QueuedLog) -> impl Future<Output = Result<(), QueueError>> + Send;
+
+    /// Logs waiting to be processed
+    fn pending(&self) -> impl Future<Output = Result<u64, QueueError>> + Send;
+
+    /// When the log at the head of the queue was queued; `None` when empty
+    fn oldest_queued_at(
+        &self,
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>, QueueError>> + Send;
+}
+
+/// A log handed out by [`LogQueue::pull`], to be acked once handled
+#[derive(Debug)]
+pub struct QueuedLog {
+    pub log: EvmLogs,
+    receipt: Receipt,
+}
+
+/// What the backend needs to ack a log
+#[derive(Debug)]
+enum Receipt {
+    /// `evm_logs.id`
+    Postgres(i32),
+    /// Stream entry id
+    Redis(String),
+    Nats(Box<async_nats::jetstream::Message>),
+}
+
+impl QueuedLog {
+    /// Backend id of the log, for diagnostics
+    pub fn id(&self) -> String {
+        match &self.receipt {
+            Receipt::Postgres(id) => id.to_string(),
+            Receipt::Redis(id) => id.clone(),
+            Receipt::Nats(message) => message
+                .info()
+                .map(|info| info.stream_sequence.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Which queue implementation `LOG_QUEUE` selects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueKind {
+    Postgres,
+    Redis,
+    Nats,
+}
+
+impl QueueKind {
+    pub fn parse(value: &str) -> Result<Self, QueueError> {
+        match value.trim().to_lowercase().as_str() {
+            "postgres" | "pg" => Ok(QueueKind::Postgres),
+            "redis" => Ok(QueueKind::Redis),
+            "nats" => Ok(QueueKind::Nats),
+            other => Err(QueueError::UnknownBackend(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for QueueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            QueueKind::Postgres => "postgres",
+            QueueKind::Redis => "redis",
+            QueueKind::Nats => "nats",
+        })
+    }
+}
+
+/// The queue selected by `LOG_QUEUE`
+#[derive(Clone)]
+pub enum QueueBackend {
+    Postgres(PostgresQueue),
+    Redis(Box<RedisStreamQueue>),
+    Nats(Box<NatsQueue>),
+}
+
+impl QueueBackend {
+    /// Connect to the queue named by `LOG_QUEUE` (Postgres by default)
+    pub async fn from_env(db_pool: Pool<Postgres>) -> Result<Self, QueueError> {
+        let kind = QueueKind::parse(
+            &env::var("LOG_QUEUE").unwrap_or_else(|_| defaults::LOG_QUEUE.to_string()),
+        )?;
+        let consumer = env::var("LOG_QUEUE_CONSUMER")
+            .unwrap_or_else(|_| defaults::LOG_QUEUE_CONSUMER.to_string());
+
+        Ok(match kind {
+            QueueKind::Postgres => QueueBackend::Postgres(PostgresQueue::new(db_pool)),
+            QueueKind::Redis => {
+                let url = env::var("REDIS_URL")
+                    .map_err(|_| QueueError::MissingEnvVar("REDIS_URL".into()))?;
+                QueueBackend::Redis(Box::new(RedisStreamQueue::connect(&url, &consumer).await?))
+            }
+            QueueKind::Nats => {
+                let url = env::var("NATS_URL").unwrap_or_else(|_| defaults::NATS_URL.to_string());
+                QueueBackend::Nats(Box::new(NatsQueue::connect(&url, &consumer).await?))
+            }
+        })
+    }
+
+    pub fn kind(&self) -> QueueKind {
+        match self {
+            QueueBackend::Postgres(_) => QueueKind::Postgres,
+            QueueBackend::Redis(_) => QueueKind::Redis,
+            QueueBackend::Nats(_) => QueueKind::Nats,
+        }
+    }
+}
+
+impl LogQueue for QueueBackend {
+    async fn push(&self, logs: Vec<Log>) -> Result<usize, QueueError> {
+        match self {
+            QueueBackend::Postgres(queue) => queue.push(logs).await,
+            QueueBackend::Redis(queue) => queue.push(logs).await,
+            QueueBackend::Nats(queue) => queue.push(logs).await,
+        }
+    }
+
+    async fn pull(&self, limit: usize) -> Result<Vec<QueuedLog>, QueueError> {
+        match self {
+            QueueBackend::Postgres(queue) => queue.pull(limit).await,
+            QueueBackend::Redis(queue) => queue.pull(limit).await,
+            QueueBackend::Nats(queue) => queue.pull(limit).await,
+        }
+    }
+
+    async fn ack(&self, log: QueuedLog) -> Result<(), QueueError> {
+        match self {
+            QueueBackend::Postgres(queue) => queue.ack(log).await,
+            QueueBackend::Redis(queue) => queue.ack(log).await,
+            QueueBackend::Nats(queue) => queue.ack(log).await,
+        }
+    }
+
+    async fn pending(&self) -> Result<u64, QueueError> {
+        match self {
+            QueueBackend::Postgres(queue) => queue.pending().await,
+            QueueBackend::Redis(queue) => queue.pending().await,
+            QueueBackend::Nats(queue) => queue.pending().await,
+        }
+    }
+
+    async fn oldest_queued_at(&self) -> Result<Option<DateTime<Utc>>, QueueError> {
+        match self {
+            QueueBackend::Postgres(queue) => queue.oldest_queued_at().await,
+            QueueBackend::Redis(queue) => queue.oldest_queued_at().await,
+            QueueBackend::Nats(queue) => queue.oldest_queued_at().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_is_chosen_by_name() {
+        assert_eq!(QueueKind::parse("postgres").unwrap(), QueueKind::Postgres);
+        assert_eq!(QueueKind::parse(" Redis ").unwrap(), QueueKind::Redis);
+        assert_eq!(QueueKind::parse("nats").unwrap(), QueueKind::Nats);
+        assert!(matches!(
+            QueueKind::parse("kafka"),
+            Err(QueueError::UnknownBackend(name)) if name == "kafka"
+        ));
+        assert_eq!(QueueKind::Nats.to_string(), "nats");
+    }
+}
@@ -0,0 +1,142 @@
+//! NATS JetStream queue
+//!
+//! Logs are published as JSON to a work-queue stream and read through one
+//! durable pull consumer. JetStream redelivers anything not acked within the
+//! ack wait, and drops messages from the stream once acked.
+
+use std::time::Duration;
+
+use alloy::rpc::types::Log;
+use async_nats::jetstream::{
+    self,
+    consumer::{pull, AckPolicy, PullConsumer},
+    stream::{self, RetentionPolicy},
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+
+use super::{LogQueue, QueueError, QueuedLog, Receipt};
+use crate::entity::evm_logs::EvmLogs;
+
+/// Stream holding queued logs
+pub const STREAM: &str = "CHAIN_LOGS";
+/// Subject logs are published on
+pub const SUBJECT: &str = "chain.logs";
+
+/// How long a pull waits for messages when the stream is empty
+const FETCH_WAIT: Duration = Duration::from_secs(1);
+
+/// Queue backed by a NATS JetStream stream
+#[derive(Clone)]
+pub struct NatsQueue {
+    context: jetstream::Context,
+    consumer: PullConsumer,
+}
+
+fn nats_error(err: impl std::fmt::Display) -> QueueError {
+    QueueError::Nats(err.to_string())
+}
+
+impl NatsQueue {
+    /// Connect and make sure the stream and durable consumer exist
+    pub async fn connect(url: &str, consumer: &str) -> Result<Self, QueueError> {
+        let client = async_nats::connect(url).await.map_err(nats_error)?;
+        let context = jetstream::new(client);
+
+        let stream = context
+            .get_or_create_stream(stream::Config {
+                name: STREAM.to_string(),
+                subjects: vec![SUBJECT.to_string()],
+                retention: RetentionPolicy::WorkQueue,
+                ..Default::default()
+            })
+            .await
+            .map_err(nats_error)?;
+
+        let consumer = stream
+            .get_or_create_consumer(
+                consumer,
+                pull::Config {
+                    durable_name: Some(consumer.to_string()),
+                    ack_policy: AckPolicy::Explicit,
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(nats_error)?;
+
+        Ok(Self { context, consumer })
+    }
+}
+
+impl LogQueue for NatsQueue {
+    async fn push(&self, logs: Vec<Log>) -> Result<usize, QueueError> {
+        let mut acks = Vec::with_capacity(logs.len());
+        for log in &logs {
+            // Same check as the Postgres queue: pending logs are skipped
+            if EvmLogs::from_rpc_log(log).is_err() {
+                continue;
+            }
+            let payload = serde_json::to_vec(log)?;
+            acks.push(
+                self.context
+                    .publish(SUBJECT, payload.into())
+                    .await
+                    .map_err(nats_error)?,
+            );
+        }
+
+        // Queued only once JetStream has stored each one
+        let queued = acks.len();
+        for ack in acks {
+            ack.await.map_err(nats_error)?;
+        }
+        Ok(queued)
+    }
+
+    async fn pull(&self, limit: usize) -> Result<Vec<QueuedLog>, QueueError> {
+        let mut batch = self
+            .consumer
+            .fetch()
+            .max_messages(limit)
+            .expires(FETCH_WAIT)
+            .messages()
+            .await
+            .map_err(nats_error)?;
+
+        let mut logs = Vec::new();
+        while let Some(message) = batch.next().await {
+            let message = message.map_err(nats_error)?;
+            let log: Log = serde_json::from_slice(&message.payload)?;
+            logs.push(QueuedLog {
+                log: EvmLogs::from_rpc_log(&log)?,
+                receipt: Receipt::Nats(Box::new(message)),
+            });
+        }
+        Ok(logs)
+    }
+
+    async fn ack(&self, log: QueuedLog) -> Result<(), QueueError> {
+        if let Receipt::Nats(message) = log.receipt {
+            message.ack().await.map_err(nats_error)?;
+        }
+        Ok(())
+    }
+
+    async fn pending(&self) -> Result<u64, QueueError> {
+        let mut consumer = self.consumer.clone();
+        let info = consumer.info().await.map_err(nats_error)?;
+        Ok(info.num_pending + info.num_ack_pending as u64)
+    }
+
+    async fn oldest_queued_at(&self) -> Result<Option<DateTime<Utc>>, QueueError> {
+        // Acked messages leave the work-queue stream, so its first is the head
+        let mut stream = self.context.get_stream(STREAM).await.map_err(nats_error)?;
+        let state = &stream.info().await.map_err(nats_error)?.state;
+        if state.messages == 0 {
+            return Ok(None);
+        }
+        let nanos = state.first_timestamp.unix_timestamp_nanos();
+        Ok(Some(DateTime::from_timestamp_nanos(nanos as i64)))
+    }
+}
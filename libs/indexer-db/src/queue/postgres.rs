@@ -0,0 +1,113 @@
+//! `evm_logs` as the queue (the default)
+
+use alloy::rpc::types::Log;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+
+use super::{LogQueue, QueueError, QueuedLog, Receipt};
+use crate::entity::evm_logs::EvmLogs;
+
+/// Queue backed by the `evm_logs` table
+#[derive(Clone)]
+pub struct PostgresQueue {
+    db_pool: Pool<Postgres>,
+}
+
+impl PostgresQueue {
+    pub fn new(db_pool: Pool<Postgres>) -> Self {
+        Self { db_pool }
+    }
+}
+
+impl LogQueue for PostgresQueue {
+    async fn push(&self, logs: Vec<Log>) -> Result<usize, QueueError> {
+        // Row by row, so one rejected log doesn't take the batch down with it.
+        // Logs already queued by an overlapping fetch count as queued, or
+        // their range would be reported as a gap.
+        let mut queued = 0;
+        for log in logs {
+            if EvmLogs::create(log, &self.db_pool).await.is_ok() {
+                queued += 1;
+            }
+        }
+        Ok(queued)
+    }
+
+    async fn pull(&self, limit: usize) -> Result<Vec<QueuedLog>, QueueError> {
+        let logs = EvmLogs::find_all(limit as i32, &self.db_pool).await?;
+        Ok(logs
+            .into_iter()
+            .map(|log| QueuedLog {
+                receipt: Receipt::Postgres(log.id),
+                log,
+            })
+            .collect())
+    }
+
+    async fn ack(&self, log: QueuedLog) -> Result<(), QueueError> {
+        if let Receipt::Postgres(id) = log.receipt {
+            EvmLogs::delete(id, &self.db_pool).await?;
+        }
+        Ok(())
+    }
+
+    async fn pending(&self) -> Result<u64, QueueError> {
+        Ok(EvmLogs::count(&self.db_pool).await?.unwrap_or(0) as u64)
+    }
+
+    async fn oldest_queued_at(&self) -> Result<Option<DateTime<Utc>>, QueueError> {
+        let created_at = EvmLogs::oldest_created_at(&self.db_pool).await?;
+        Ok(created_at.map(|at| at.and_utc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, b256, Bytes, B256};
+    use sqlx::PgPool;
+
+    use super::*;
+
+    fn log(log_index: u64) -> Log {
+        Log {
+            inner: alloy::primitives::Log::new(
+                address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73"),
+                vec![b256!(
+                    "d78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"
+                )],
+                Bytes::from(vec![0xab; 32]),
+            )
+            .unwrap(),
+            block_hash: Some(B256::repeat_byte(2)),
+            block_number: Some(42),
+            block_timestamp: None,
+            transaction_hash: Some(B256::repeat_byte(3)),
+            transaction_index: Some(0),
+            log_index: Some(log_index),
+            removed: false,
+        }
+    }
+
+    #[sqlx::test]
+    async fn logs_stay_queued_until_acked(pool: PgPool) {
+        let queue = PostgresQueue::new(pool);
+
+        // The duplicate is queued once and counted as queued
+        assert!(queue.oldest_queued_at().await.unwrap().is_none());
+        assert_eq!(queue.push(vec![log(0), log(1), log(0)]).await.unwrap(), 3);
+        assert_eq!(queue.pending().await.unwrap(), 2);
+        assert!(queue.oldest_queued_at().await.unwrap().is_some());
+
+        let pulled = queue.pull(10).await.unwrap();
+        assert_eq!(pulled.len(), 2);
+        assert_eq!(pulled[0].log.block_number, 42.into());
+        // Pulling alone doesn't dequeue
+        assert_eq!(queue.pull(10).await.unwrap().len(), 2);
+
+        for log in pulled {
+            queue.ack(log).await.unwrap();
+        }
+        assert_eq!(queue.pending().await.unwrap(), 0);
+        assert!(queue.oldest_queued_at().await.unwrap().is_none());
+    }
+}
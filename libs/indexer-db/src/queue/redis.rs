@@ -0,0 +1,136 @@
+//! Redis Streams queue
+//!
+//! Logs are appended to one stream as JSON and read through a consumer group,
+//! so several processors can share the work. Entries are deleted once acked;
+//! entries read but never acked are re-read first on the next pull.
+
+use alloy::rpc::types::Log;
+use chrono::{DateTime, Utc};
+use redis::{
+    aio::ConnectionManager,
+    streams::{StreamRangeReply, StreamReadOptions, StreamReadReply},
+    AsyncCommands, Client,
+};
+
+use super::{LogQueue, QueueError, QueuedLog, Receipt};
+use crate::entity::evm_logs::EvmLogs;
+
+/// Stream holding queued logs
+pub const STREAM: &str = "chain:logs";
+/// Consumer group the processors read through
+pub const GROUP: &str = "processor";
+/// Field of each entry holding the log JSON
+const FIELD: &str = "log";
+
+/// Queue backed by a Redis Stream
+#[derive(Clone)]
+pub struct RedisStreamQueue {
+    connection: ConnectionManager,
+    consumer: String,
+}
+
+impl RedisStreamQueue {
+    /// Connect and make sure the stream and consumer group exist
+    pub async fn connect(url: &str, consumer: &str) -> Result<Self, QueueError> {
+        let client = Client::open(url)?;
+        let mut connection = ConnectionManager::new(client).await?;
+
+        let created: Result<(), redis::RedisError> =
+            connection.xgroup_create_mkstream(STREAM, GROUP, "0").await;
+        match created {
+            Ok(()) => {}
+            Err(err) if err.code() == Some("BUSYGROUP") => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(Self {
+            connection,
+            consumer: consumer.to_string(),
+        })
+    }
+
+    /// Read entries after `id`: `0` for this consumer's unacked entries,
+    /// `>` for new ones
+    async fn read(&self, id: &str, limit: usize) -> Result<Vec<QueuedLog>, QueueError> {
+        let options = StreamReadOptions::default()
+            .group(GROUP, &self.consumer)
+            .count(limit);
+        let reply: StreamReadReply = self
+            .connection
+            .clone()
+            .xread_options(&[STREAM], &[id], &options)
+            .await?;
+
+        let mut logs = Vec::new();
+        for entry in reply.keys.into_iter().flat_map(|key| key.ids) {
+            let json: String = entry.get(FIELD).unwrap_or_default();
+            let log: Log = serde_json::from_str(&json)?;
+            logs.push(QueuedLog {
+                log: EvmLogs::from_rpc_log(&log)?,
+                receipt: Receipt::Redis(entry.id),
+            });
+        }
+        Ok(logs)
+    }
+}
+
+impl LogQueue for RedisStreamQueue {
+    async fn push(&self, logs: Vec<Log>) -> Result<usize, QueueError> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let mut queued = 0;
+        for log in &logs {
+            // Same check as the Postgres queue: pending logs are skipped
+            if EvmLogs::from_rpc_log(log).is_err() {
+                continue;
+            }
+            pipe.xadd(STREAM, "*", &[(FIELD, serde_json::to_string(log)?)])
+                .ignore();
+            queued += 1;
+        }
+
+        pipe.query_async::<()>(&mut self.connection.clone()).await?;
+        Ok(queued)
+    }
+
+    async fn pull(&self, limit: usize) -> Result<Vec<QueuedLog>, QueueError> {
+        let unacked = self.read("0", limit).await?;
+        if !unacked.is_empty() {
+            return Ok(unacked);
+        }
+        self.read(">", limit).await
+    }
+
+    async fn ack(&self, log: QueuedLog) -> Result<(), QueueError> {
+        if let Receipt::Redis(id) = log.receipt {
+            redis::pipe()
+                .atomic()
+                .xack(STREAM, GROUP, &[&id])
+                .ignore()
+                .xdel(STREAM, &[&id])
+                .ignore()
+                .query_async::<()>(&mut self.connection.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn pending(&self) -> Result<u64, QueueError> {
+        // Acked entries are deleted, so the stream length is the backlog
+        Ok(self.connection.clone().xlen(STREAM).await?)
+    }
+
+    async fn oldest_queued_at(&self) -> Result<Option<DateTime<Utc>>, QueueError> {
+        let reply: StreamRangeReply = self
+            .connection
+            .clone()
+            .xrange_count(STREAM, "-", "+", 1)
+            .await?;
+        // Entry ids start with the millisecond they were added at
+        Ok(reply
+            .ids
+            .first()
+            .and_then(|entry| entry.id.split('-').next()?.parse::<i64>().ok())
+            .and_then(DateTime::from_timestamp_millis))
+    }
+}
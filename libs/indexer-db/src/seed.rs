@@ -0,0 +1,506 @@
+//! Cold-start seed import
+//!
+//! Bootstraps tokens and pairs from a CSV or JSON dump, e.g. a subgraph
+//! export, so a new deployment doesn't start from an empty universe. Every
+//! row is validated on its own: bad rows are reported and skipped, good ones
+//! are upserted with [`Token::import`] and [`Pair::import`], which never
+//! overwrite indexed data, so importing the same dump twice is harmless.
+//!
+//! Columns (CSV headers or JSON keys), snake_case:
+//! - tokens: `address`, and optionally `name`, `symbol`, `decimals`,
+//!   `total_supply`, `pair_address`, `creator_address`, `block_number`,
+//!   `created_at`
+//! - pairs: `address`, `token0_address`, `token1_address`, `factory_address`,
+//!   `block_number`, and optionally `base_token_index` (0 or 1, otherwise
+//!   taken from the chain's wrapped native token and stablecoins) and
+//!   `created_at`
+//!
+//! `created_at` is a unix timestamp in seconds or an RFC 3339 date.
+
+use std::{collections::HashSet, fmt, io::Read, str::FromStr};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use sqlx::{
+    types::{
+        chrono::{self, DateTime, TimeZone, Utc},
+        BigDecimal,
+    },
+    Pool, Postgres,
+};
+
+use crate::{
+    entity::{
+        chain_constant::{ChainConstant, ChainConstantKind},
+        pair::{NewPair, Pair},
+        token::{NewToken, Token},
+    },
+    types::Address20,
+};
+
+/// What a dump holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedKind {
+    Tokens,
+    Pairs,
+}
+
+impl SeedKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "tokens" => Some(SeedKind::Tokens),
+            "pairs" => Some(SeedKind::Pairs),
+            _ => None,
+        }
+    }
+}
+
+/// How a dump is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedFormat {
+    /// Comma-separated with a header row
+    Csv,
+    /// An array of objects
+    Json,
+}
+
+impl SeedFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "csv" => Some(SeedFormat::Csv),
+            "json" => Some(SeedFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Format implied by a file name's extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        let (_, extension) = path.rsplit_once('.')?;
+        Self::parse(&extension.to_ascii_lowercase())
+    }
+}
+
+/// A token row
+#[derive(Debug, Clone, Deserialize)]
+struct SeedToken {
+    address: Address20,
+    name: Option<String>,
+    symbol: Option<String>,
+    decimals: Option<u8>,
+    total_supply: Option<String>,
+    pair_address: Option<Address20>,
+    creator_address: Option<Address20>,
+    block_number: Option<i64>,
+    created_at: Option<String>,
+}
+
+/// A pair row
+#[derive(Debug, Clone, Deserialize)]
+struct SeedPair {
+    address: Address20,
+    token0_address: Address20,
+    token1_address: Address20,
+    factory_address: Address20,
+    block_number: i64,
+    base_token_index: Option<i16>,
+    created_at: Option<String>,
+}
+
+/// A row that was skipped, numbered from 1 (the first data row)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedRow {
+    pub row: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for RejectedRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.reason)
+    }
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeedReport {
+    /// Rows that weren't indexed yet
+    pub inserted: usize,
+    /// Rows already indexed, at most filling in missing fields
+    pub existing: usize,
+    pub rejected: Vec<RejectedRow>,
+}
+
+/// Longest name and symbol the tokens table holds
+const MAX_NAME_LEN: usize = 255;
+const MAX_SYMBOL_LEN: usize = 50;
+
+/// Import a dump of `kind` read from `reader`. With `dry_run` rows are only
+/// validated; nothing is written and every valid row counts as inserted.
+///
+/// `chain_id` picks the wrapped native token and stablecoins that tell a
+/// pair's base token when a row doesn't say.
+pub async fn import<R: Read>(
+    kind: SeedKind,
+    format: SeedFormat,
+    reader: R,
+    chain_id: i64,
+    dry_run: bool,
+    pool: &Pool<Postgres>,
+) -> Result<SeedReport, Box<dyn std::error::Error>> {
+    match kind {
+        SeedKind::Tokens => {
+            let rows = read_rows::<SeedToken, R>(format, reader)?;
+            import_tokens(rows, dry_run, pool).await
+        }
+        SeedKind::Pairs => {
+            let rows = read_rows::<SeedPair, R>(format, reader)?;
+            let bases: HashSet<Address20> = ChainConstant::find_by_chain(chain_id, pool)
+                .await?
+                .into_iter()
+                .filter(|c| {
+                    c.is(ChainConstantKind::WrappedNative) || c.is(ChainConstantKind::Stablecoin)
+                })
+                .map(|c| c.address)
+                .collect();
+            import_pairs(rows, &bases, dry_run, pool).await
+        }
+    }
+}
+
+/// Decode every row, keeping the ones that don't decode as errors. Only a
+/// dump that can't be read at all fails as a whole.
+fn read_rows<T: DeserializeOwned, R: Read>(
+    format: SeedFormat,
+    reader: R,
+) -> Result<Vec<Result<T, String>>, Box<dyn std::error::Error>> {
+    match format {
+        SeedFormat::Csv => {
+            let mut csv = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(reader);
+            Ok(csv
+                .deserialize::<T>()
+                .map(|row| row.map_err(|e| e.to_string()))
+                .collect())
+        }
+        SeedFormat::Json => {
+            let values: Vec<serde_json::Value> = serde_json::from_reader(reader)?;
+            Ok(values
+                .into_iter()
+                .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+                .collect())
+        }
+    }
+}
+
+async fn import_tokens(
+    rows: Vec<Result<SeedToken, String>>,
+    dry_run: bool,
+    pool: &Pool<Postgres>,
+) -> Result<SeedReport, Box<dyn std::error::Error>> {
+    let mut report = SeedReport::default();
+    for (i, row) in rows.into_iter().enumerate() {
+        let token = match row.and_then(validate_token) {
+            Ok(token) => token,
+            Err(reason) => {
+                report.rejected.push(RejectedRow { row: i + 1, reason });
+                continue;
+            }
+        };
+        if dry_run || Token::import(&token.0, token.1, pool).await? {
+            report.inserted += 1;
+        } else {
+            report.existing += 1;
+        }
+    }
+    Ok(report)
+}
+
+async fn import_pairs(
+    rows: Vec<Result<SeedPair, String>>,
+    bases: &HashSet<Address20>,
+    dry_run: bool,
+    pool: &Pool<Postgres>,
+) -> Result<SeedReport, Box<dyn std::error::Error>> {
+    let mut report = SeedReport::default();
+    for (i, row) in rows.into_iter().enumerate() {
+        let pair = match row.and_then(|p| validate_pair(p, bases)) {
+            Ok(pair) => pair,
+            Err(reason) => {
+                report.rejected.push(RejectedRow { row: i + 1, reason });
+                continue;
+            }
+        };
+        if dry_run || Pair::import(&pair.0, pair.1, pool).await? {
+            report.inserted += 1;
+        } else {
+            report.existing += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// A token row as a token to import and its creation time
+fn validate_token(row: SeedToken) -> Result<(NewToken, Option<DateTime<Utc>>), String> {
+    let name = non_empty(row.name);
+    let symbol = non_empty(row.symbol);
+    if name.as_ref().is_some_and(|n| n.len() > MAX_NAME_LEN) {
+        return Err(format!("`name` is longer than {} bytes", MAX_NAME_LEN));
+    }
+    if symbol.as_ref().is_some_and(|s| s.len() > MAX_SYMBOL_LEN) {
+        return Err(format!("`symbol` is longer than {} bytes", MAX_SYMBOL_LEN));
+    }
+    let total_supply = non_empty(row.total_supply)
+        .map(|supply| {
+            BigDecimal::from_str(&supply)
+                .ok()
+                .filter(|s| *s >= BigDecimal::from(0))
+                .ok_or_else(|| format!("`total_supply` is not a non-negative number: `{}`", supply))
+        })
+        .transpose()?;
+    if row.block_number.is_some_and(|b| b < 0) {
+        return Err("`block_number` is negative".to_string());
+    }
+
+    let token = NewToken {
+        address: row.address,
+        name_raw: name.clone(),
+        symbol_raw: symbol.clone(),
+        name,
+        symbol,
+        name_spoofed: false,
+        decimals: row.decimals.map(i16::from),
+        total_supply,
+        pair_address: row.pair_address,
+        creator_address: row.creator_address,
+        block_number: row.block_number,
+    };
+    Ok((token, parse_created_at(row.created_at)?))
+}
+
+/// A pair row as a pair to import and its creation time
+fn validate_pair(
+    row: SeedPair,
+    bases: &HashSet<Address20>,
+) -> Result<(NewPair, Option<DateTime<Utc>>), String> {
+    if row.token0_address == row.token1_address {
+        return Err("`token0_address` and `token1_address` are the same".to_string());
+    }
+    if row.block_number < 0 {
+        return Err("`block_number` is negative".to_string());
+    }
+    let base_token_index = match row.base_token_index {
+        Some(index @ (0 | 1)) => index,
+        Some(other) => return Err(format!("`base_token_index` must be 0 or 1, got {}", other)),
+        None if bases.contains(&row.token0_address) => 0,
+        None if bases.contains(&row.token1_address) => 1,
+        None => {
+            return Err("neither token is a known base token; set `base_token_index`".to_string())
+        }
+    };
+
+    let pair = NewPair {
+        address: row.address,
+        token0_address: row.token0_address,
+        token1_address: row.token1_address,
+        factory_address: row.factory_address,
+        base_token_index,
+        block_number: row.block_number,
+        discovered: false,
+    };
+    Ok((pair, parse_created_at(row.created_at)?))
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Unix seconds or an RFC 3339 date
+fn parse_created_at(value: Option<String>) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(value) = non_empty(value) else {
+        return Ok(None);
+    };
+    let parsed = match value.parse::<i64>() {
+        Ok(secs) => Utc.timestamp_opt(secs, 0).single(),
+        Err(_) => chrono::DateTime::parse_from_rfc3339(&value)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc)),
+    };
+    parsed
+        .filter(|dt| *dt <= Utc::now())
+        .map(Some)
+        .ok_or_else(|| {
+            format!(
+                "`created_at` is not a past unix time or RFC 3339 date: `{}`",
+                value
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::entity::test_support::{address, clear_seed_data};
+
+    #[test]
+    fn formats_follow_the_file_extension() {
+        assert_eq!(SeedFormat::from_path("export.CSV"), Some(SeedFormat::Csv));
+        assert_eq!(
+            SeedFormat::from_path("dump/pairs.json"),
+            Some(SeedFormat::Json)
+        );
+        assert_eq!(SeedFormat::from_path("pairs"), None);
+    }
+
+    #[test]
+    fn created_at_takes_unix_seconds_or_rfc3339() {
+        let at = |v: &str| parse_created_at(Some(v.to_string()));
+        assert_eq!(
+            at("1700000000").unwrap(),
+            Utc.timestamp_opt(1_700_000_000, 0).single()
+        );
+        assert_eq!(
+            at("2023-11-14T22:13:20Z").unwrap(),
+            Utc.timestamp_opt(1_700_000_000, 0).single()
+        );
+        assert_eq!(at(" ").unwrap(), None);
+        assert!(at("yesterday").is_err());
+        assert!(at("99999999999").is_err());
+    }
+
+    #[sqlx::test]
+    async fn tokens_are_validated_and_upserted_idempotently(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        // The token is already indexed with a name but no supply
+        let indexed = NewToken {
+            address: address(1),
+            name: Some("Indexed".to_string()),
+            symbol: None,
+            name_raw: None,
+            symbol_raw: None,
+            name_spoofed: false,
+            decimals: None,
+            total_supply: None,
+            pair_address: None,
+            creator_address: None,
+            block_number: None,
+        };
+        Token::create(&indexed, &pool).await.unwrap();
+
+        let dump = format!(
+            "address,name,symbol,decimals,total_supply,created_at\n\
+             {},Dumped,DMP,18,1000000,1700000000\n\
+             {},Fresh,FRS,9,,2023-11-14T22:13:20Z\n\
+             0x1234,Bad,BAD,18,,\n\
+             {},Negative,NEG,18,-5,\n",
+            address(1),
+            address(2),
+            address(3)
+        );
+        let import_csv = || {
+            import(
+                SeedKind::Tokens,
+                SeedFormat::Csv,
+                dump.as_bytes(),
+                56,
+                false,
+                &pool,
+            )
+        };
+
+        let report = import_csv().await.unwrap();
+        assert_eq!(report.inserted, 1);
+        assert_eq!(report.existing, 1);
+        let rejected: Vec<_> = report.rejected.iter().map(|r| r.row).collect();
+        assert_eq!(rejected, vec![3, 4]);
+
+        let existing = Token::find_by_address(&address(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(existing.name.as_deref(), Some("Indexed"));
+        assert_eq!(existing.total_supply, Some(BigDecimal::from(1_000_000)));
+        let fresh = Token::find_by_address(&address(2), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fresh.symbol.as_deref(), Some("FRS"));
+        assert_eq!(fresh.decimals, Some(9));
+        assert_eq!(
+            fresh.created_at,
+            Utc.timestamp_opt(1_700_000_000, 0).single()
+        );
+
+        let again = import_csv().await.unwrap();
+        assert_eq!((again.inserted, again.existing), (0, 2));
+    }
+
+    #[sqlx::test]
+    async fn pairs_take_their_base_token_from_the_chain(pool: PgPool) {
+        clear_seed_data(&pool).await;
+
+        let wbnb = "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c";
+        let dump = serde_json::json!([
+            {
+                "address": address(10).to_string(),
+                "token0_address": wbnb,
+                "token1_address": address(11).to_string(),
+                "factory_address": address(200).to_string(),
+                "block_number": 100,
+            },
+            {
+                "address": address(20).to_string(),
+                "token0_address": address(21).to_string(),
+                "token1_address": address(22).to_string(),
+                "factory_address": address(200).to_string(),
+                "block_number": 101,
+            },
+            {
+                "address": address(30).to_string(),
+                "token0_address": address(31).to_string(),
+                "token1_address": address(32).to_string(),
+                "factory_address": address(200).to_string(),
+                "block_number": 102,
+                "base_token_index": 1,
+            },
+        ])
+        .to_string();
+
+        let dry = import(
+            SeedKind::Pairs,
+            SeedFormat::Json,
+            dump.as_bytes(),
+            56,
+            true,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(dry.inserted, 2);
+        assert!(Pair::find_recent(10, &pool).await.unwrap().is_empty());
+
+        let report = import(
+            SeedKind::Pairs,
+            SeedFormat::Json,
+            dump.as_bytes(),
+            56,
+            false,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.inserted, 2);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].row, 2);
+
+        let pair = Pair::find_by_address(&address(10), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pair.base_token_index, Some(0));
+        assert_eq!(pair.get_token_address(), &address(11));
+    }
+}
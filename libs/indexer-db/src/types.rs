@@ -0,0 +1,212 @@
+//! Fixed-size byte newtypes stored as BYTEA
+//!
+//! Addresses and hashes live in the database as raw bytes so lookups are
+//! exact and case-insensitive by construction. In Rust and over JSON they
+//! render as lowercase `0x`-prefixed hex.
+
+use std::{fmt, str::FromStr};
+
+use alloy::primitives::{Address, B256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    postgres::{PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueRef},
+    Decode, Encode, Postgres, Type,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParseBytesError {
+    #[error("missing 0x prefix")]
+    MissingPrefix,
+
+    #[error("expected {expected} hex characters, got {actual}")]
+    InvalidLength { expected: usize, actual: usize },
+
+    #[error("non-hex character `{0}`")]
+    InvalidCharacter(char),
+}
+
+macro_rules! fixed_bytes {
+    ($(#[$meta:meta])* $name:ident, $len:expr, $alloy:ty) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+        pub struct $name(pub [u8; $len]);
+
+        impl $name {
+            pub const LEN: usize = $len;
+
+            pub const fn new(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+
+            /// Build from a slice, `None` if the length is wrong
+            pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+                <[u8; $len]>::try_from(bytes).ok().map(Self)
+            }
+
+            /// Lowercase `0x`-prefixed hex
+            pub fn to_hex(&self) -> String {
+                format!("0x{}", alloy::hex::encode(self.0))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.to_hex())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.to_hex())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseBytesError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let hex = s
+                    .strip_prefix("0x")
+                    .or_else(|| s.strip_prefix("0X"))
+                    .ok_or(ParseBytesError::MissingPrefix)?;
+
+                if hex.len() != $len * 2 {
+                    return Err(ParseBytesError::InvalidLength {
+                        expected: $len * 2,
+                        actual: hex.len(),
+                    });
+                }
+
+                if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+                    return Err(ParseBytesError::InvalidCharacter(c));
+                }
+
+                let mut bytes = [0u8; $len];
+                alloy::hex::decode_to_slice(hex, &mut bytes)
+                    .map_err(|_| ParseBytesError::InvalidCharacter('?'))?;
+
+                Ok(Self(bytes))
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl From<$alloy> for $name {
+            fn from(value: $alloy) -> Self {
+                Self(value.0.into())
+            }
+        }
+
+        impl From<$name> for $alloy {
+            fn from(value: $name) -> Self {
+                <$alloy>::from(value.0)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_hex())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl Type<Postgres> for $name {
+            fn type_info() -> PgTypeInfo {
+                <[u8; $len] as Type<Postgres>>::type_info()
+            }
+        }
+
+        impl PgHasArrayType for $name {
+            fn array_type_info() -> PgTypeInfo {
+                <[u8; $len] as PgHasArrayType>::array_type_info()
+            }
+        }
+
+        impl Encode<'_, Postgres> for $name {
+            fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+                <[u8; $len] as Encode<Postgres>>::encode_by_ref(&self.0, buf)
+            }
+        }
+
+        impl<'r> Decode<'r, Postgres> for $name {
+            fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+                <[u8; $len] as Decode<Postgres>>::decode(value).map(Self)
+            }
+        }
+    };
+}
+
+fixed_bytes!(
+    /// 20-byte account or contract address
+    Address20,
+    20,
+    Address
+);
+
+fixed_bytes!(
+    /// 32-byte transaction or block hash
+    Hash32,
+    32,
+    B256
+);
+
+impl Address20 {
+    pub const ZERO: Address20 = Address20([0u8; 20]);
+
+    /// Leading `0x` plus four bytes, used as a fallback label
+    pub fn short(&self) -> String {
+        self.to_hex()[..10].to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mixed_case_and_renders_lowercase() {
+        let addr: Address20 = "0xBB4cdB9CBd36B01bD1cBaEBF2De08d9173bc095c"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            addr.to_string(),
+            "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(
+            "bb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c".parse::<Address20>(),
+            Err(ParseBytesError::MissingPrefix)
+        );
+        assert!(matches!(
+            "0x1234".parse::<Hash32>(),
+            Err(ParseBytesError::InvalidLength {
+                expected: 64,
+                actual: 4
+            })
+        ));
+        assert_eq!(
+            "0xzz4cdb9cbd36b01bd1cbaebf2de08d9173bc095c".parse::<Address20>(),
+            Err(ParseBytesError::InvalidCharacter('z'))
+        );
+    }
+}
@@ -9,12 +9,14 @@ pub enum AppError {
     #[error("Invalid ChainID: `{0}`")]
     InvalidChainID(String),
 
+    #[allow(dead_code)]
     #[error("Rate limited by RPC (429), will retry")]
     RateLimited,
 
     #[error("Max retries ({0}) exceeded")]
     MaxRetriesExceeded(u32),
 
+    #[allow(dead_code)]
     #[error("RPC error: {0}")]
     RpcError(String),
 }
@@ -3,41 +3,55 @@
 //! Captures raw blockchain logs from BSC and stores them in PostgreSQL
 //! for processing by the processor service.
 //!
-//! Events tracked:
+//! Events tracked (seeded in `listener_filters`):
 //! - PairCreated: New token launches on PancakeSwap
 //! - Swap: Price/volume updates (requires paid RPC for full chain)
-//! - Transfer: Holder tracking (requires paid RPC for full chain)
+//! - Transfer: Holder tracking (requires paid RPC for full chain, or
+//!   `TRANSFER_SAMPLING=true` to only fetch the tokens in `sampled_tokens`)
+//! - Sync: Pair reserves (requires paid RPC for full chain)
+//!
+//! Filters are loaded from the `listener_filters` table and reloaded on an
+//! interval: new or enabled filters get a task, removed, disabled or edited
+//! ones have theirs stopped. See `/api/admin/listeners`.
+//!
+//! Every saved block range is recorded in `evm_synced_ranges`. A gap audit
+//! compares each filter's ranges on an interval and records holes in
+//! `evm_sync_gaps`; listeners re-fetch those when they have caught up.
 
-use std::{env, time::Duration};
+use std::{collections::HashMap, env, time::Duration};
 
 use error::AppError;
-use indexer_db::{entity::evm_chains::EvmChains, initialize_database};
+use indexer_db::{
+    entity::{evm_chains::EvmChains, evm_sync_gaps::EvmSyncGap, listener_filter::ListenerFilter},
+    initialize_database, logging,
+    queue::QueueBackend,
+};
 use service::{fetch_and_save_logs, FilterMode};
-use tokio::time::sleep;
+use sqlx::{Pool, Postgres};
+use tokio::{
+    task::JoinHandle,
+    time::{interval, sleep, MissedTickBehavior},
+};
 
 mod error;
 mod service;
 
-/// Default addresses and topics for BSC
 mod defaults {
-    /// PancakeSwap V2 Factory on BSC
-    pub const PANCAKE_FACTORY: &str = "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73";
-    /// PairCreated event topic
-    pub const TOPIC_PAIR_CREATED: &str = "0x0d3648bd0f6ba80134a33ba9275ac585d9d315f0ad8355cddefde31afa28d0e9";
-    /// Swap event topic
-    pub const TOPIC_SWAP: &str = "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822";
-    /// Transfer event topic
-    pub const TOPIC_TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    /// Seconds between reloads of `listener_filters`
+    pub const LISTENER_RELOAD_INTERVAL: &str = "60";
+    /// Seconds between gap audits of the synced block ranges
+    pub const GAP_AUDIT_INTERVAL: &str = "300";
+    /// Log levels when `RUST_LOG` is unset
+    pub const RUST_LOG: &str = "info";
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("============================================");
-    println!("   BeanBee BSC Listener - Alpha Discovery   ");
-    println!("============================================");
+    logging::init(defaults::RUST_LOG);
+    tracing::info!("Starting BeanBee BSC Listener - Alpha Discovery");
 
     let db_pool = initialize_database().await?;
-    println!("Connected to PostgreSQL");
+    tracing::info!("Connected to PostgreSQL");
 
     let chain_id_env =
         env::var("CHAIN_ID").map_err(|_| AppError::MissingEnvVar("CHAIN_ID".into()))?;
@@ -46,106 +60,233 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .map_err(|_| AppError::InvalidChainID(chain_id_env))?;
 
     let evm_chain = EvmChains::fetch_by_id(chain_id, &db_pool).await?;
-    println!("Chain: {} (ID: {})", evm_chain.name, chain_id);
-
-    // Get configuration from environment
-    let topic_pair_created = env::var("TOPIC_PAIR_CREATED")
-        .unwrap_or_else(|_| defaults::TOPIC_PAIR_CREATED.to_string());
-    
-    let topic_swap = env::var("TOPIC_SWAP")
-        .unwrap_or_else(|_| defaults::TOPIC_SWAP.to_string());
-        
-    let topic_transfer = env::var("TOPIC_TRANSFER")
-        .unwrap_or_else(|_| defaults::TOPIC_TRANSFER.to_string());
-
-    let pancake_factory = env::var("PANCAKESWAP_FACTORY")
-        .or_else(|_| env::var("PANCAKE_FACTORY"))
-        .unwrap_or_else(|_| defaults::PANCAKE_FACTORY.to_string());
+    tracing::info!("Chain: {} (ID: {})", evm_chain.name, chain_id);
+
+    // At least 1: tokio's `interval` panics on a zero period
+    let reload_secs = env::var("LISTENER_RELOAD_INTERVAL")
+        .unwrap_or_else(|_| defaults::LISTENER_RELOAD_INTERVAL.to_string())
+        .parse::<u64>()
+        .unwrap_or(60)
+        .max(1);
+
+    let gap_audit_secs = env::var("GAP_AUDIT_INTERVAL")
+        .unwrap_or_else(|_| defaults::GAP_AUDIT_INTERVAL.to_string())
+        .parse::<u64>()
+        .unwrap_or(300)
+        .max(1);
+
+    let queue = QueueBackend::from_env(db_pool.clone()).await?;
 
     let poll_delay = Duration::from_secs(evm_chain.block_time as u64);
 
-    println!("");
-    println!("Starting event listeners...");
-    println!("  Poll Interval: {}s", poll_delay.as_secs());
-    println!("");
-
-    // 1. PairCreated Listener (New Tokens)
-    let db_pool_1 = db_pool.clone();
-    let filter_pair = FilterMode::ByAddressAndTopic {
-        address: pancake_factory.clone(),
-        topic: topic_pair_created.clone(),
-        name: "PairCreated".to_string(),
-    };
-    
-    let handle_pair = tokio::spawn(async move {
-        println!("Started PairCreated listener");
-        loop {
-            match fetch_and_save_logs(chain_id, db_pool_1.clone(), filter_pair.clone()).await {
-                Ok(()) => {}
-                Err(err) => {
-                    eprintln!("PairCreated listener error: {:?}", err);
-                    sleep(Duration::from_secs(5)).await;
-                }
+    tracing::info!(
+        "Starting event listeners (poll interval {}s, filter reload interval {}s, gap audit interval {}s, {} log queue)",
+        poll_delay.as_secs(),
+        reload_secs,
+        gap_audit_secs,
+        queue.kind()
+    );
+    tracing::info!(
+        "Swap, Transfer and Sync filters are disabled by default to prevent RPC rate limits. \
+         With a paid RPC provider, enable them in `listener_filters` \
+         (PATCH /api/admin/listeners/:name); they start on the next reload."
+    );
+
+    tokio::spawn(run_gap_audit(
+        chain_id,
+        db_pool.clone(),
+        Duration::from_secs(gap_audit_secs),
+    ));
+
+    let mut running: HashMap<String, (FilterMode, JoinHandle<()>)> = HashMap::new();
+    let mut ticker = interval(Duration::from_secs(reload_secs));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+
+        let desired: Vec<FilterMode> = match ListenerFilter::find_enabled(&db_pool).await {
+            Ok(filters) => filters
+                .iter()
+                .filter_map(|filter| {
+                    let mode = FilterMode::from_db(filter);
+                    if mode.is_none() {
+                        tracing::error!(
+                            "Skipping listener filter {}: no address or topic",
+                            filter.name
+                        );
+                    }
+                    mode
+                })
+                .collect(),
+            Err(err) => {
+                tracing::error!("Failed to load listener filters: {err}");
+                continue;
+            }
+        };
+
+        // Tasks stop themselves when their filter is disabled
+        running.retain(|_, (_, handle)| !handle.is_finished());
+
+        let current: HashMap<String, FilterMode> = running
+            .iter()
+            .map(|(name, (mode, _))| (name.clone(), mode.clone()))
+            .collect();
+        let (stop, start) = reload_plan(&current, &desired);
+
+        for name in stop {
+            if let Some((_, handle)) = running.remove(&name) {
+                handle.abort();
+                tracing::info!("Stopped {name} listener");
             }
-            sleep(poll_delay).await;
         }
-    });
-
-    // 2. Swap Listener (Price, Volume, Whales)
-    // UNCOMMENT FOR PRODUCTION WITH PAID RPC
-    /*
-    let db_pool_2 = db_pool.clone();
-    let filter_swap = FilterMode::ByTopic {
-        topic: topic_swap.clone(),
-        name: "Swap".to_string(),
-    };
-
-    let handle_swap = tokio::spawn(async move {
-        println!("Started Swap listener (Global)");
-        loop {
-            match fetch_and_save_logs(chain_id, db_pool_2.clone(), filter_swap.clone()).await {
-                Ok(()) => {}
-                Err(err) => {
-                    eprintln!("Swap listener error: {:?}", err);
-                    sleep(Duration::from_secs(5)).await;
-                }
+        for filter in start {
+            let handle = tokio::spawn(run_listener(
+                chain_id,
+                db_pool.clone(),
+                queue.clone(),
+                filter.clone(),
+                poll_delay,
+            ));
+            running.insert(filter.name().to_string(), (filter, handle));
+        }
+    }
+}
+
+/// Which running filters to stop (by name) and which filters to start so the
+/// running set matches `desired`. Edited filters are restarted.
+fn reload_plan(
+    running: &HashMap<String, FilterMode>,
+    desired: &[FilterMode],
+) -> (Vec<String>, Vec<FilterMode>) {
+    let mut stop: Vec<String> = running
+        .iter()
+        .filter(|(_, mode)| !desired.contains(mode))
+        .map(|(name, _)| name.clone())
+        .collect();
+    stop.sort();
+
+    let start = desired
+        .iter()
+        .filter(|d| running.get(d.name()) != Some(*d))
+        .cloned()
+        .collect();
+
+    (stop, start)
+}
+
+/// Poll one filter until it is disabled, honouring its runtime controls
+///
+/// The filter's `listener_filters` row is re-read before every poll, so
+/// disabling it or changing the interval takes effect on the next tick.
+async fn run_listener(
+    chain_id: u64,
+    db_pool: Pool<Postgres>,
+    queue: QueueBackend,
+    filter: FilterMode,
+    default_delay: Duration,
+) {
+    let name = filter.name().to_string();
+    let mut announced = false;
+
+    loop {
+        let row = match ListenerFilter::find_by_name(&name, &db_pool).await {
+            Ok(row) => row,
+            Err(err) => {
+                tracing::error!("{name} listener: failed to read controls: {err}");
+                sleep(default_delay).await;
+                continue;
             }
-            sleep(poll_delay).await;
+        };
+
+        let Some(row) = row.filter(|r| r.enabled) else {
+            tracing::info!("{name} listener disabled, stopping");
+            return;
+        };
+
+        let delay = row
+            .poll_interval_secs
+            .map(|secs| Duration::from_secs(secs as u64))
+            .unwrap_or(default_delay);
+
+        if !announced {
+            tracing::info!(
+                "Started {name} listener (polling every {}s)",
+                delay.as_secs()
+            );
+            announced = true;
+        }
+
+        if let Err(err) =
+            fetch_and_save_logs(chain_id, db_pool.clone(), queue.clone(), filter.clone()).await
+        {
+            tracing::error!("{name} listener error: {:?}", err);
+            sleep(Duration::from_secs(5)).await;
         }
-    });
-    */
-
-    // 3. Transfer Listener (Holders)
-    // UNCOMMENT FOR PRODUCTION WITH PAID RPC
-    /*
-    let db_pool_3 = db_pool.clone();
-    let filter_transfer = FilterMode::ByTopic {
-        topic: topic_transfer.clone(),
-        name: "Transfer".to_string(),
-    };
-
-    let handle_transfer = tokio::spawn(async move {
-        println!("Started Transfer listener (Global)");
-        loop {
-            match fetch_and_save_logs(chain_id, db_pool_3.clone(), filter_transfer.clone()).await {
-                Ok(()) => {}
-                Err(err) => {
-                    eprintln!("Transfer listener error: {:?}", err);
-                    sleep(Duration::from_secs(5)).await;
+
+        sleep(delay).await;
+    }
+}
+
+/// Record holes between synced ranges; listeners re-fetch them when idle
+async fn run_gap_audit(chain_id: u64, db_pool: Pool<Postgres>, every: Duration) {
+    let mut ticker = interval(every);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+
+        match EvmSyncGap::audit(chain_id, &db_pool).await {
+            Ok(gaps) => {
+                for gap in gaps {
+                    tracing::info!(
+                        "Sync gap found for {}: blocks {} to {}",
+                        gap.sync_key, gap.from_block, gap.to_block
+                    );
                 }
             }
-            sleep(poll_delay).await;
+            Err(err) => tracing::error!("Gap audit failed: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topic_filter(name: &str, topic: &str) -> FilterMode {
+        FilterMode::ByTopic {
+            topic: topic.to_string(),
+            name: name.to_string(),
         }
-    });
-    */
-    
-    println!("NOTE: Swap and Transfer listeners are disabled by default to prevent RPC rate limits.");
-    println!("      To enable full 'Live Feed' data (Whales, Scores, Pumps), uncomment the listeners in listener/src/main.rs");
-    println!("      and ensure you are using a paid RPC provider.");
-
-    // Wait for all tasks (they run forever)
-    // let _ = tokio::join!(handle_pair, handle_swap, handle_transfer);
-    let _ = tokio::join!(handle_pair);
-
-    Ok(())
+    }
+
+    #[test]
+    fn reload_starts_new_stops_removed_and_restarts_edited() {
+        let running: HashMap<String, FilterMode> = [
+            ("Swap".to_string(), topic_filter("Swap", "0x01")),
+            ("Sync".to_string(), topic_filter("Sync", "0x02")),
+            ("Transfer".to_string(), topic_filter("Transfer", "0x03")),
+        ]
+        .into_iter()
+        .collect();
+        let desired = vec![
+            topic_filter("Swap", "0x01"),
+            topic_filter("Sync", "0x22"),
+            topic_filter("PairCreated", "0x04"),
+        ];
+
+        let (stop, start) = reload_plan(&running, &desired);
+        assert_eq!(stop, vec!["Sync".to_string(), "Transfer".to_string()]);
+        assert_eq!(
+            start,
+            vec![
+                topic_filter("Sync", "0x22"),
+                topic_filter("PairCreated", "0x04")
+            ]
+        );
+
+        let (stop, start) = reload_plan(&HashMap::new(), &[]);
+        assert!(stop.is_empty() && start.is_empty());
+    }
 }
@@ -12,9 +12,18 @@ use alloy::{
     eips::BlockNumberOrTag,
     primitives::{Address, FixedBytes},
     providers::{Provider, ProviderBuilder},
-    rpc::types::{Filter, Log},
+    rpc::types::{BlockTransactionsKind, Filter, Log},
+};
+use indexer_db::{
+    entity::{
+        evm_sync_gaps::{EvmSyncGap, EvmSyncedRange},
+        evm_sync_logs::EvmSyncLogs,
+        listener_filter::ListenerFilter,
+        sampled_token::SampledToken,
+    },
+    queue::{LogQueue, QueueBackend},
+    Address20,
 };
-use indexer_db::entity::{evm_logs::EvmLogs, evm_sync_logs::EvmSyncLogs};
 use sqlx::{Pool, Postgres};
 use tokio::time::sleep;
 use tower::Service;
@@ -22,26 +31,69 @@ use tower::Service;
 use crate::error::AppError;
 
 mod defaults {
-    pub const RPC_DELAY_MS: &str = "5000";  // 5 seconds between calls for public BSC RPC
+    pub const RPC_DELAY_MS: &str = "5000"; // 5 seconds between calls for public BSC RPC
     pub const MAX_RETRIES: &str = "10";
     pub const BLOCK_RANGE: u64 = 10; // Extremely conservative for public RPCs
+    pub const TRANSFER_SAMPLING: &str = "false";
 }
 
+/// Event signature of ERC20 `Transfer`
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
 /// Filter mode for the listener
-#[derive(Clone)]
+#[allow(dead_code, clippy::enum_variant_names)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FilterMode {
-    /// Filter by specific contract address
-    ByAddress(String),
+    /// Filter by specific contract address (every event it emits)
+    ByAddress { address: String, name: String },
     /// Filter by event topic (for tracking all events of a type)
     ByTopic { topic: String, name: String },
     /// Filter by address AND topic
-    ByAddressAndTopic { address: String, topic: String, name: String },
+    ByAddressAndTopic {
+        address: String,
+        topic: String,
+        name: String,
+    },
 }
 
+impl FilterMode {
+    /// Build from a `listener_filters` row, `None` if it has nothing to match on
+    pub fn from_db(filter: &ListenerFilter) -> Option<Self> {
+        let name = filter.name.clone();
+        match (filter.address, filter.topic) {
+            (Some(address), Some(topic)) => Some(FilterMode::ByAddressAndTopic {
+                address: address.to_hex(),
+                topic: topic.to_hex(),
+                name,
+            }),
+            (None, Some(topic)) => Some(FilterMode::ByTopic {
+                topic: topic.to_hex(),
+                name,
+            }),
+            (Some(address), None) => Some(FilterMode::ByAddress {
+                address: address.to_hex(),
+                name,
+            }),
+            (None, None) => None,
+        }
+    }
+
+    /// Display name, also the key for the filter's runtime controls
+    pub fn name(&self) -> &str {
+        match self {
+            FilterMode::ByAddress { name, .. } => name,
+            FilterMode::ByTopic { name, .. } => name,
+            FilterMode::ByAddressAndTopic { name, .. } => name,
+        }
+    }
+}
+
+#[allow(dead_code)]
 pub struct ListenerService {
     pub chain_id: u64,
     pub filter_mode: FilterMode,
     pub db_pool: Pool<Postgres>,
+    pub queue: QueueBackend,
 }
 
 impl Service<()> for ListenerService {
@@ -56,9 +108,10 @@ impl Service<()> for ListenerService {
     fn call(&mut self, _: ()) -> Self::Future {
         let db_pool = self.db_pool.clone();
         let chain_id = self.chain_id;
+        let queue = self.queue.clone();
         let filter_mode = self.filter_mode.clone();
 
-        Box::pin(async move { fetch_and_save_logs(chain_id, db_pool, filter_mode).await })
+        Box::pin(async move { fetch_and_save_logs(chain_id, db_pool, queue, filter_mode).await })
     }
 }
 
@@ -89,7 +142,7 @@ async fn fetch_logs_with_retry<P: Provider>(
             Err(e) => {
                 if is_rate_limited(&e) {
                     let backoff_ms = base_delay_ms * (2_u64.pow(attempt));
-                    eprintln!(
+                    tracing::warn!(
                         "Rate limited (attempt {}/{}), backing off for {}ms",
                         attempt + 1,
                         max_retries,
@@ -107,13 +160,108 @@ async fn fetch_logs_with_retry<P: Provider>(
     Err(Box::new(AppError::MaxRetriesExceeded(max_retries)))
 }
 
+/// Timestamp of a block, `None` if the RPC can't tell
+async fn block_timestamp<P: Provider>(provider: &P, block_number: u64) -> Option<u64> {
+    match provider
+        .get_block_by_number(
+            BlockNumberOrTag::Number(block_number),
+            BlockTransactionsKind::Hashes,
+        )
+        .await
+    {
+        Ok(block) => block.map(|block| block.header.timestamp),
+        Err(e) => {
+            tracing::error!("Failed to fetch block {block_number} header: {e}");
+            None
+        }
+    }
+}
+
+/// Fill in the block timestamps most RPCs leave out of `eth_getLogs`, so the
+/// processor can measure end-to-end lag. Only the range's first and last
+/// headers are fetched; blocks in between are interpolated.
+async fn stamp_block_timestamps<P: Provider>(
+    provider: &P,
+    logs: &mut [Log],
+    from_block: u64,
+    to_block: u64,
+) {
+    if logs.iter().all(|log| log.block_timestamp.is_some()) {
+        return;
+    }
+
+    let Some(from_ts) = block_timestamp(provider, from_block).await else {
+        return;
+    };
+    let to_ts = match to_block > from_block {
+        true => match block_timestamp(provider, to_block).await {
+            Some(ts) => ts,
+            None => return,
+        },
+        false => from_ts,
+    };
+
+    for log in logs.iter_mut().filter(|log| log.block_timestamp.is_none()) {
+        let Some(block_number) = log.block_number else {
+            continue;
+        };
+        let offset = block_number.saturating_sub(from_block);
+        let span = to_block.saturating_sub(from_block).max(1);
+        log.block_timestamp = Some(from_ts + to_ts.saturating_sub(from_ts) * offset / span);
+    }
+}
+
+/// Contracts to narrow the filter to: with `TRANSFER_SAMPLING=true`, the
+/// Transfer filter only matches the tokens the processor sampled. `None`
+/// leaves the filter as it is.
+async fn sampled_emitters(
+    filter_mode: &FilterMode,
+    db_pool: &Pool<Postgres>,
+) -> Result<Option<Vec<Address20>>, Box<dyn Error + Send + Sync>> {
+    let sampling = env::var("TRANSFER_SAMPLING")
+        .unwrap_or_else(|_| defaults::TRANSFER_SAMPLING.to_string())
+        .parse::<bool>()
+        .unwrap_or(false);
+    let is_transfer = matches!(
+        filter_mode,
+        FilterMode::ByTopic { topic, .. } if topic.eq_ignore_ascii_case(TRANSFER_TOPIC)
+    );
+    if !(sampling && is_transfer) {
+        return Ok(None);
+    }
+
+    Ok(Some(SampledToken::find_addresses(db_pool).await?))
+}
+
+/// Fetch a block range of the filter's logs, with their block timestamps.
+/// An empty sample matches nothing, so no call is made.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_range<P: Provider>(
+    provider: &P,
+    filter_mode: &FilterMode,
+    emitters: Option<&[Address20]>,
+    from_block: u64,
+    to_block: u64,
+    max_retries: u32,
+    rpc_delay_ms: u64,
+) -> Result<Vec<Log>, Box<dyn Error + Send + Sync>> {
+    if emitters.is_some_and(|emitters| emitters.is_empty()) {
+        return Ok(Vec::new());
+    }
+
+    let filter = build_filter(filter_mode, emitters, from_block, to_block)?;
+    let mut logs = fetch_logs_with_retry(provider, &filter, max_retries, rpc_delay_ms).await?;
+    stamp_block_timestamps(provider, &mut logs, from_block, to_block).await;
+    Ok(logs)
+}
+
 /// Get the sync key for a filter mode (used to track sync progress)
 /// Returns a hex string (without 0x prefix) that can be used as an address in the sync log
 fn get_sync_key(filter_mode: &FilterMode) -> String {
     match filter_mode {
-        FilterMode::ByAddress(addr) => {
+        FilterMode::ByAddress { address, .. } => {
             // Strip 0x prefix if present
-            addr.strip_prefix("0x").unwrap_or(addr).to_lowercase()
+            address.strip_prefix("0x").unwrap_or(address).to_lowercase()
         }
         FilterMode::ByTopic { topic, .. } => {
             // Use first 20 bytes of topic hash as sync key
@@ -129,36 +277,53 @@ fn get_sync_key(filter_mode: &FilterMode) -> String {
 pub async fn fetch_and_save_logs(
     chain_id: u64,
     db_pool: Pool<Postgres>,
+    queue: QueueBackend,
     filter_mode: FilterMode,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     let rpc_url = env::var("RPC_URL").map_err(|_| AppError::MissingEnvVar("RPC_URL".into()))?;
-    
+
     let rpc_delay_ms = env::var("RPC_DELAY_MS")
         .unwrap_or_else(|_| defaults::RPC_DELAY_MS.to_string())
         .parse::<u64>()
         .unwrap_or(500);
-    
+
     let max_retries = env::var("MAX_RETRIES")
         .unwrap_or_else(|_| defaults::MAX_RETRIES.to_string())
         .parse::<u32>()
         .unwrap_or(3);
 
     let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
-    
+
     let sync_key = get_sync_key(&filter_mode);
     let sync_log = EvmSyncLogs::find_or_create_by_address(&sync_key, chain_id, &db_pool).await?;
 
     // Fetch latest block with retry
     let latest_block = provider.get_block_number().await?;
-    
+    let _ = sync_log
+        .update_latest_block_number(latest_block, &db_pool)
+        .await
+        .inspect_err(|error| tracing::error!("Error recording latest block: {error}"));
+
+    let range_key = Address20::new(sync_log.address);
+    let emitters = sampled_emitters(&filter_mode, &db_pool).await?;
+
     if latest_block == sync_log.last_synced_block_number as u64 {
-        let display_name = match &filter_mode {
-            FilterMode::ByAddress(addr) => addr.clone(),
-            FilterMode::ByTopic { name, .. } => name.clone(),
-            FilterMode::ByAddressAndTopic { name, .. } => name.clone(),
-        };
-        println!("Fully indexed: {display_name}");
-        return Ok(());
+        let display_name = filter_mode.name();
+        tracing::debug!("Fully indexed: {display_name}");
+
+        // Spend the idle cycle on a range the gap audit found missing
+        return refetch_gap(
+            chain_id,
+            &db_pool,
+            &queue,
+            &provider,
+            &filter_mode,
+            emitters.as_deref(),
+            range_key,
+            max_retries,
+            rpc_delay_ms,
+        )
+        .await;
     }
 
     let from_block_number = match sync_log.last_synced_block_number as u64 {
@@ -172,46 +337,123 @@ pub async fn fetch_and_save_logs(
     // Conservative block range for public RPCs
     let to_block_number = std::cmp::min(from_block_number + defaults::BLOCK_RANGE, latest_block);
 
-    // Build filter based on mode
-    let filter = build_filter(&filter_mode, from_block_number, to_block_number)?;
-
     // Fetch logs with retry logic
-    let logs = fetch_logs_with_retry(&provider, &filter, max_retries, rpc_delay_ms).await?;
+    let logs = fetch_range(
+        &provider,
+        &filter_mode,
+        emitters.as_deref(),
+        from_block_number,
+        to_block_number,
+        max_retries,
+        rpc_delay_ms,
+    )
+    .await?;
 
     let log_count = logs.len();
+    let queued = queue.push(logs).await?;
+    if queued < log_count {
+        tracing::error!("Error queueing {} logs", log_count - queued);
+    }
+
     let mut tx = db_pool.begin().await?;
-    
-    for log in logs {
-        let _ = EvmLogs::create(log, &mut *tx)
-            .await
-            .inspect_err(|error| eprintln!("Error saving log: {error}"));
+
+    // A range with unqueued logs is left out of the synced ranges, so the gap
+    // audit picks it up for a re-fetch
+    if queued == log_count {
+        let _ = EvmSyncedRange::create(
+            chain_id,
+            range_key,
+            from_block_number,
+            to_block_number,
+            log_count,
+            &mut *tx,
+        )
+        .await
+        .inspect_err(|error| tracing::error!("Error recording synced range: {error}"));
     }
 
     let _ = sync_log
         .update_last_synced_block_number(to_block_number, &mut *tx)
         .await
-        .inspect_err(|error| eprintln!("Error updating last_synced_block_number: {error}"));
+        .inspect_err(|error| tracing::error!("Error updating last_synced_block_number: {error}"));
 
     match tx.commit().await {
         Ok(_) => {
-            let display_name = match &filter_mode {
-                FilterMode::ByAddress(addr) => addr.clone(),
-                FilterMode::ByTopic { name, .. } => name.clone(),
-                FilterMode::ByAddressAndTopic { name, .. } => name.clone(),
-            };
-            println!(
+            let display_name = filter_mode.name();
+            tracing::info!(
                 "Saved {log_count} logs for {display_name}, blocks: {from_block_number} to {to_block_number}"
             );
         }
-        Err(err) => eprintln!("Transaction commit error: {err}"),
+        Err(err) => tracing::error!("Transaction commit error: {err}"),
     }
 
     Ok(())
 }
 
-/// Build a filter based on the filter mode
+/// Re-fetch the next chunk of the filter's oldest open gap, if it has one
+#[allow(clippy::too_many_arguments)]
+async fn refetch_gap<P: Provider>(
+    chain_id: u64,
+    db_pool: &Pool<Postgres>,
+    queue: &QueueBackend,
+    provider: &P,
+    filter_mode: &FilterMode,
+    emitters: Option<&[Address20]>,
+    range_key: Address20,
+    max_retries: u32,
+    rpc_delay_ms: u64,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(gap) = EvmSyncGap::next_open(chain_id, range_key, db_pool).await? else {
+        return Ok(());
+    };
+
+    let from_block_number = gap.from_block as u64;
+    let to_block_number = std::cmp::min(
+        from_block_number + defaults::BLOCK_RANGE,
+        gap.to_block as u64,
+    );
+
+    let logs = fetch_range(
+        provider,
+        filter_mode,
+        emitters,
+        from_block_number,
+        to_block_number,
+        max_retries,
+        rpc_delay_ms,
+    )
+    .await?;
+
+    // Logs the queue rejects here were most likely saved before the gap
+    // opened; a queue outage is an error and leaves the gap open
+    let log_count = logs.len();
+    queue.push(logs).await?;
+
+    let mut tx = db_pool.begin().await?;
+    EvmSyncedRange::create(
+        chain_id,
+        range_key,
+        from_block_number,
+        to_block_number,
+        log_count,
+        &mut *tx,
+    )
+    .await?;
+    gap.fill_through(to_block_number, &mut *tx).await?;
+    tx.commit().await?;
+
+    let display_name = filter_mode.name();
+    tracing::info!(
+        "Re-fetched {log_count} logs for {display_name} gap, blocks: {from_block_number} to {to_block_number}"
+    );
+
+    Ok(())
+}
+
+/// Build a filter based on the filter mode, matching only `emitters` when set
 fn build_filter(
     filter_mode: &FilterMode,
+    emitters: Option<&[Address20]>,
     from_block: u64,
     to_block: u64,
 ) -> Result<Filter, Box<dyn Error + Send + Sync>> {
@@ -220,7 +462,7 @@ fn build_filter(
         .to_block(BlockNumberOrTag::Number(to_block));
 
     match filter_mode {
-        FilterMode::ByAddress(address) => {
+        FilterMode::ByAddress { address, .. } => {
             filter = filter.address(Address::from_str(address)?);
         }
         FilterMode::ByTopic { topic, .. } => {
@@ -234,6 +476,58 @@ fn build_filter(
                 .event_signature(topic_hash);
         }
     }
+    if let Some(emitters) = emitters {
+        filter = filter.address(
+            emitters
+                .iter()
+                .map(|&emitter| Address::from(emitter))
+                .collect::<Vec<_>>(),
+        );
+    }
 
     Ok(filter)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampled_filters_only_match_the_sampled_tokens() {
+        let transfer = FilterMode::ByTopic {
+            topic: TRANSFER_TOPIC.to_string(),
+            name: "Transfer".to_string(),
+        };
+        let sampled = Address20::new([1; 20]);
+        let other = Address::from(Address20::new([2; 20]));
+
+        let narrowed = build_filter(&transfer, Some(&[sampled]), 100, 110).unwrap();
+        assert!(narrowed.address.matches(&Address::from(sampled)));
+        assert!(!narrowed.address.matches(&other));
+
+        let everything = build_filter(&transfer, None, 100, 110).unwrap();
+        assert!(everything.address.matches(&other));
+    }
+
+    #[test]
+    fn address_only_filters_keep_their_name() {
+        let contract = Address20::new([3; 20]);
+        let row = ListenerFilter {
+            name: "Router".to_string(),
+            poll_interval_secs: None,
+            updated_at: sqlx::types::chrono::Utc::now(),
+            address: Some(contract),
+            topic: None,
+            enabled: true,
+            priority: 0,
+        };
+        let mode = FilterMode::from_db(&row).unwrap();
+        // The name finds the filter's row again for its runtime controls
+        assert_eq!(mode.name(), "Router");
+        assert_eq!(get_sync_key(&mode), "0303030303030303030303030303030303030303");
+
+        let filter = build_filter(&mode, None, 100, 110).unwrap();
+        assert!(filter.address.matches(&Address::from(contract)));
+        assert!(!filter.address.matches(&Address::from(Address20::new([2; 20]))));
+    }
+}
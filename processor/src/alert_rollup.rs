@@ -0,0 +1,147 @@
+//! Alert rollups
+//!
+//! A token drawing several alerts of one type within a few minutes ("3 whale
+//! buys in 10 min") is reported once. Before each webhook run, every
+//! `ALERT_ROLLUP_TYPES` type with at least `ALERT_ROLLUP_MIN_COUNT` alerts on
+//! one token in the last `ALERT_ROLLUP_WINDOW_SECS` is replaced by a rollup
+//! alert carrying the count and total USD. The replaced alerts are marked
+//! rolled up, hidden from the feed and webhooks, and listed in the rollup's
+//! `metadata.rollup.alertIds`. A burst that goes on is rolled up again only
+//! once `ALERT_ROLLUP_MIN_COUNT` more alerts came in.
+//!
+//! Webhooks only send alerts of these types once they are
+//! `ALERT_ROLLUP_WINDOW_SECS` old, so none of a burst goes out before its
+//! rollup; rollups themselves are sent right away.
+
+use std::env;
+
+use indexer_db::entity::alert::{AlertBurst, AlertEvent, AlertType};
+use sqlx::{Pool, Postgres};
+
+use crate::defaults;
+
+/// Which alerts are rolled up, and when, from the environment
+#[derive(Debug, Clone)]
+pub struct RollupConfig {
+    pub alert_types: Vec<&'static str>,
+    pub window_secs: i64,
+    pub min_count: i64,
+}
+
+impl RollupConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: i64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<i64>()
+                .unwrap_or(fallback)
+                .max(0)
+        };
+        let types = env::var("ALERT_ROLLUP_TYPES")
+            .unwrap_or_else(|_| defaults::ALERT_ROLLUP_TYPES.to_string());
+
+        Self {
+            alert_types: parse_types(&types),
+            window_secs: read(
+                "ALERT_ROLLUP_WINDOW_SECS",
+                defaults::ALERT_ROLLUP_WINDOW_SECS,
+                600,
+            ),
+            min_count: read(
+                "ALERT_ROLLUP_MIN_COUNT",
+                defaults::ALERT_ROLLUP_MIN_COUNT,
+                3,
+            ),
+        }
+    }
+
+    /// Rollups are off without types, a window, or a count above one
+    fn enabled(&self) -> bool {
+        !self.alert_types.is_empty() && self.window_secs > 0 && self.min_count > 1
+    }
+
+    /// Alert types webhooks hold back, and for how many seconds
+    pub fn delivery_hold(&self) -> (&[&'static str], i64) {
+        if self.enabled() {
+            (&self.alert_types, self.window_secs)
+        } else {
+            (&[], 0)
+        }
+    }
+}
+
+/// Comma-separated alert type names, skipping unknown ones
+fn parse_types(value: &str) -> Vec<&'static str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let known = AlertType::ALL.iter().find(|t| t.as_str() == name);
+            if known.is_none() {
+                tracing::warn!("ALERT_ROLLUP_TYPES: unknown alert type {}", name);
+            }
+            known.map(AlertType::as_str)
+        })
+        .collect()
+}
+
+/// Raise the rollup for one burst and hide the alerts it replaces
+async fn roll_up(
+    burst: &AlertBurst,
+    config: &RollupConfig,
+    db_pool: &Pool<Postgres>,
+) -> Result<AlertEvent, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    let rollup = AlertEvent::create(&burst.rollup_alert(config.window_secs), &mut *tx).await?;
+    AlertEvent::roll_up(rollup.id, &burst.member_ids, &mut *tx).await?;
+    tx.commit().await?;
+
+    Ok(rollup)
+}
+
+/// Collapse every burst currently over the threshold
+pub async fn run(db_pool: &Pool<Postgres>, config: &RollupConfig) {
+    if !config.enabled() {
+        return;
+    }
+
+    let bursts = match AlertEvent::find_bursts(
+        &config.alert_types,
+        config.window_secs,
+        config.min_count,
+        db_pool,
+    )
+    .await
+    {
+        Ok(bursts) => bursts,
+        Err(e) => {
+            tracing::error!("Alert rollups: failed to find bursts: {}", e);
+            return;
+        }
+    };
+
+    for burst in &bursts {
+        match roll_up(burst, config, db_pool).await {
+            Ok(rollup) => tracing::info!("Alert rollups: {}", rollup.title),
+            Err(e) => tracing::error!(
+                "Alert rollups: failed to roll up {} alerts on {}: {}",
+                burst.alert_type, burst.token_address, e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn types_parse_from_a_comma_list() {
+        assert_eq!(
+            parse_types(" whale_buy, whale_sell,,nope,cex_inflow "),
+            vec!["whale_buy", "whale_sell", "cex_inflow"]
+        );
+        assert!(parse_types("").is_empty());
+    }
+}
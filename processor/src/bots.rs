@@ -0,0 +1,105 @@
+//! Sniper bot signatures
+//!
+//! Launch buys are checked for what sets bots apart from people buying
+//! through a wallet: landing in the very block the pair was created in, a
+//! gas limit set to what the simulated transaction used instead of a wallet's
+//! padded estimate, and going through their own contract rather than a
+//! router or aggregator. Pairs found from one of their swaps have no known
+//! creation block, so the first signal is only checked for pairs seen
+//! through their PairCreated. A buyer showing at least [`MIN_SIGNALS`] of them joins the global
+//! `bot_wallets` set, which is left out of organic holder counts.
+
+use indexer_db::Address20;
+
+pub const CREATION_BLOCK: &str = "creation_block";
+pub const EXACT_GAS_LIMIT: &str = "exact_gas_limit";
+pub const CONTRACT_CALLER: &str = "contract_caller";
+
+/// Signals a buy needs for its buyer to count as a bot
+pub const MIN_SIGNALS: usize = 2;
+
+/// Gas limit headroom over the gas used, in basis points, at or below which
+/// the limit counts as exact. Wallets pad their estimates by 10% or more.
+pub const EXACT_GAS_HEADROOM_BPS: u64 = 50;
+
+/// Gas and target of a buy's transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxGas {
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    /// Contract called; `None` for contract creations
+    pub to: Option<Address20>,
+}
+
+/// Bot signals of a launch buy; `is_router` tells the contracts people buy
+/// through
+pub fn signals(
+    in_creation_block: bool,
+    tx: &TxGas,
+    is_router: impl Fn(&Address20) -> bool,
+) -> Vec<String> {
+    let mut signals = Vec::new();
+    if in_creation_block {
+        signals.push(CREATION_BLOCK.to_string());
+    }
+    if tx.gas_used > 0
+        && tx.gas_limit >= tx.gas_used
+        && (tx.gas_limit - tx.gas_used) * 10_000 <= tx.gas_used * EXACT_GAS_HEADROOM_BPS
+    {
+        signals.push(EXACT_GAS_LIMIT.to_string());
+    }
+    if tx.to.is_some_and(|to| !is_router(&to)) {
+        signals.push(CONTRACT_CALLER.to_string());
+    }
+    signals
+}
+
+/// Whether `signals` mark the buyer as a bot
+pub fn is_bot(signals: &[String]) -> bool {
+    signals.len() >= MIN_SIGNALS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTER: Address20 = Address20::new([1; 20]);
+    const BOT_CONTRACT: Address20 = Address20::new([2; 20]);
+
+    fn router(address: &Address20) -> bool {
+        *address == ROUTER
+    }
+
+    fn tx(gas_limit: u64, gas_used: u64, to: Address20) -> TxGas {
+        TxGas {
+            gas_limit,
+            gas_used,
+            to: Some(to),
+        }
+    }
+
+    #[test]
+    fn wallet_buys_through_a_router_are_not_bots() {
+        // A wallet's estimate padded by 20%, sent to the router
+        let signals = signals(false, &tx(180_000, 150_000, ROUTER), router);
+        assert!(signals.is_empty());
+
+        // Even in the creation block, one signal isn't enough
+        let signals = super::signals(true, &tx(180_000, 150_000, ROUTER), router);
+        assert_eq!(signals, vec![CREATION_BLOCK]);
+        assert!(!is_bot(&signals));
+    }
+
+    #[test]
+    fn exact_limits_and_own_contracts_mark_bots() {
+        let signals = signals(false, &tx(150_500, 150_000, BOT_CONTRACT), router);
+        assert_eq!(signals, vec![EXACT_GAS_LIMIT, CONTRACT_CALLER]);
+        assert!(is_bot(&signals));
+
+        let signals = super::signals(true, &tx(150_000, 150_000, ROUTER), router);
+        assert_eq!(signals, vec![CREATION_BLOCK, EXACT_GAS_LIMIT]);
+
+        // Just over the headroom
+        assert!(super::signals(false, &tx(150_751, 150_000, ROUTER), router).is_empty());
+    }
+}
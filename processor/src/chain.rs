@@ -0,0 +1,120 @@
+//! Per-chain contract registry
+//!
+//! Wrapped native token, stablecoins, DEX factories/routers, LP lockers and
+//! the pools behind the native USD price index are loaded from the
+//! `chain_constants` table, so supporting another chain means inserting its
+//! rows rather than changing code.
+
+use indexer_db::{
+    entity::chain_constant::{ChainConstant, ChainConstantKind},
+    Address20,
+};
+use sqlx::{Executor, Postgres};
+
+use crate::error::AppError;
+
+/// Well-known contracts of one chain
+#[derive(Debug, Clone)]
+pub struct ChainConstants {
+    pub chain_id: i64,
+    pub wrapped_native: Address20,
+    pub stablecoins: Vec<Address20>,
+    pub factories: Vec<Address20>,
+    pub routers: Vec<Address20>,
+    /// Locker name and contract
+    pub lockers: Vec<(String, Address20)>,
+    /// Wrapped native/stablecoin pools priced into the native USD rate
+    pub price_pools: Vec<Address20>,
+}
+
+impl ChainConstants {
+    /// Load the constants of `chain_id` from `chain_constants`
+    pub async fn load<'c, E>(chain_id: i64, connection: E) -> Result<Self, AppError>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let rows = ChainConstant::find_by_chain(chain_id, connection).await?;
+        Self::from_rows(chain_id, &rows).ok_or(AppError::UnknownChain(chain_id))
+    }
+
+    /// Group registry rows; `None` if the chain has no wrapped native token
+    pub fn from_rows(chain_id: i64, rows: &[ChainConstant]) -> Option<Self> {
+        let of_kind = |kind: ChainConstantKind| {
+            rows.iter()
+                .filter(move |row| row.is(kind))
+                .map(|row| row.address)
+        };
+
+        Some(Self {
+            chain_id,
+            wrapped_native: of_kind(ChainConstantKind::WrappedNative).next()?,
+            stablecoins: of_kind(ChainConstantKind::Stablecoin).collect(),
+            factories: of_kind(ChainConstantKind::Factory).collect(),
+            routers: of_kind(ChainConstantKind::Router).collect(),
+            lockers: rows
+                .iter()
+                .filter(|row| row.is(ChainConstantKind::Locker))
+                .map(|row| (row.name.clone(), row.address))
+                .collect(),
+            price_pools: of_kind(ChainConstantKind::PricePool).collect(),
+        })
+    }
+
+    /// Check if address is the wrapped native token (WBNB on BSC)
+    pub fn is_wrapped_native(&self, address: &Address20) -> bool {
+        *address == self.wrapped_native
+    }
+
+    /// Check if address is a registered stablecoin
+    pub fn is_stablecoin(&self, address: &Address20) -> bool {
+        self.stablecoins.contains(address)
+    }
+
+    /// Name of the LP locker at `address`, if it is one
+    pub fn locker_name(&self, address: &Address20) -> Option<&str> {
+        self.lockers
+            .iter()
+            .find(|(_, locker)| locker == address)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(kind: ChainConstantKind, name: &str, byte: u8) -> ChainConstant {
+        ChainConstant {
+            id: byte as i32,
+            chain_id: 56,
+            kind: kind.as_str().to_string(),
+            name: name.to_string(),
+            address: Address20::new([byte; 20]),
+        }
+    }
+
+    #[test]
+    fn rows_are_grouped_by_kind() {
+        let rows = vec![
+            row(ChainConstantKind::Locker, "unicrypt", 5),
+            row(ChainConstantKind::Stablecoin, "BUSD", 2),
+            row(ChainConstantKind::Stablecoin, "USDT", 3),
+            row(ChainConstantKind::WrappedNative, "WBNB", 1),
+            row(ChainConstantKind::Factory, "PancakeSwap V2", 4),
+            row(ChainConstantKind::PricePool, "WBNB/BUSD", 6),
+        ];
+
+        let chain = ChainConstants::from_rows(56, &rows).unwrap();
+        assert!(chain.is_wrapped_native(&Address20::new([1; 20])));
+        assert!(chain.is_stablecoin(&Address20::new([3; 20])));
+        assert!(!chain.is_stablecoin(&Address20::new([1; 20])));
+        assert_eq!(chain.factories, vec![Address20::new([4; 20])]);
+        assert!(chain.routers.is_empty());
+        assert_eq!(chain.price_pools, vec![Address20::new([6; 20])]);
+        assert_eq!(chain.locker_name(&Address20::new([5; 20])), Some("unicrypt"));
+        assert_eq!(chain.locker_name(&Address20::new([2; 20])), None);
+
+        // A chain without a wrapped native token can't price anything
+        assert!(ChainConstants::from_rows(56, &rows[..2]).is_none());
+    }
+}
@@ -47,17 +47,17 @@ impl ContractHandler for UniswapV3Factory {
 // Implement Handlers here
 impl UniswapV3Factory {
     async fn pool_created_handler(&self, _log: &Log) -> Result<(), AppError> {
-        println!("pool_created_handler called");
+        tracing::debug!("pool_created_handler called");
         Ok(())
     }
 
     async fn owner_changed_handler(&self, _log: &Log) -> Result<(), AppError> {
-        println!("owner_changed_handler called");
+        tracing::debug!("owner_changed_handler called");
         Ok(())
     }
 
     async fn fee_amount_enabled_handler(&self, _log: &Log) -> Result<(), AppError> {
-        println!("owner_changed_handler called");
+        tracing::debug!("owner_changed_handler called");
         Ok(())
     }
 }
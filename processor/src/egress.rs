@@ -0,0 +1,185 @@
+//! Durable event egress
+//!
+//! Redis pub/sub only reaches subscribers connected at publish time. With
+//! `EVENT_EGRESS=nats` every decoded event is also published to a NATS
+//! JetStream stream, one subject per event type (`chain.events.swap`,
+//! `chain.events.transfer`, ...), so downstream analytics consumers can read
+//! at their own pace and catch up after downtime.
+//!
+//! Delivery is at least once without holding up the log queue: an event
+//! JetStream doesn't take is kept in the `egress_outbox` table, and events
+//! after it join it there until the outbox is drained again, so they still go
+//! out in order. Each event carries `Nats-Msg-Id` set to its tx hash and log
+//! index, so JetStream drops a republished repeat.
+
+use std::{
+    env,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use async_nats::jetstream::{self, context::Publish, stream};
+use indexer_db::entity::egress_outbox::EgressOutbox;
+use sqlx::{Pool, Postgres};
+
+use crate::error::AppError;
+
+mod defaults {
+    pub const EVENT_EGRESS: &str = "none";
+    pub const NATS_URL: &str = "nats://127.0.0.1:4222";
+}
+
+/// Stream holding egressed events
+pub const STREAM: &str = "CHAIN_EVENTS";
+/// Subjects the stream captures
+const SUBJECTS: &str = "chain.events.>";
+
+/// Outbox events republished per drain
+const DRAIN_BATCH: i64 = 500;
+
+/// JetStream publisher for decoded events
+pub struct EventEgress {
+    context: jetstream::Context,
+    /// Events may be waiting in the outbox; new ones queue up behind them
+    backlog: AtomicBool,
+}
+
+impl EventEgress {
+    /// Connect when `EVENT_EGRESS=nats`; `None` when egress is off
+    pub async fn from_env() -> Result<Option<Self>, AppError> {
+        let kind = env::var("EVENT_EGRESS").unwrap_or_else(|_| defaults::EVENT_EGRESS.to_string());
+
+        match kind.trim().to_lowercase().as_str() {
+            "" | "none" => Ok(None),
+            "nats" => {
+                let url = env::var("EVENT_EGRESS_NATS_URL")
+                    .or_else(|_| env::var("NATS_URL"))
+                    .unwrap_or_else(|_| defaults::NATS_URL.to_string());
+                let egress = Self::connect(&url).await?;
+                tracing::info!(
+                    "Publishing events to NATS JetStream stream {} at {}",
+                    STREAM, url
+                );
+                Ok(Some(egress))
+            }
+            other => Err(AppError::Egress(format!(
+                "unknown EVENT_EGRESS `{}`, expected none or nats",
+                other
+            ))),
+        }
+    }
+
+    /// Connect and make sure the stream exists
+    async fn connect(url: &str) -> Result<Self, AppError> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|e| AppError::Egress(e.to_string()))?;
+        let context = jetstream::new(client);
+
+        context
+            .get_or_create_stream(stream::Config {
+                name: STREAM.to_string(),
+                subjects: vec![SUBJECTS.to_string()],
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| AppError::Egress(e.to_string()))?;
+
+        Ok(Self {
+            context,
+            // Left over from before a restart, until the first drain says otherwise
+            backlog: AtomicBool::new(true),
+        })
+    }
+
+    /// Publish an event, or keep it in the outbox when JetStream doesn't take
+    /// it or older events are still waiting there. Only fails when the event
+    /// can't be kept either.
+    pub async fn send(
+        &self,
+        channel: &str,
+        event_id: &str,
+        payload: &str,
+        db_pool: &Pool<Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        if !self.backlog.load(Ordering::Relaxed) {
+            match self.publish(channel, event_id, payload).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!("Egress publish of {} failed, keeping it: {}", event_id, e);
+                    self.backlog.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        EgressOutbox::enqueue(channel, event_id, payload, db_pool).await
+    }
+
+    /// Republish kept events in order, up to the first that fails again
+    pub async fn drain(&self, db_pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+        if !self.backlog.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        loop {
+            let kept = EgressOutbox::find_oldest(DRAIN_BATCH, db_pool).await?;
+            if kept.is_empty() {
+                self.backlog.store(false, Ordering::Relaxed);
+                return Ok(());
+            }
+            for event in kept {
+                if let Err(e) = self
+                    .publish(&event.channel, &event.event_id, &event.payload)
+                    .await
+                {
+                    EgressOutbox::record_failure(event.id, &e.to_string(), db_pool).await?;
+                    tracing::warn!(
+                        "Egress still failing, {} kept (attempt {}): {}",
+                        event.event_id,
+                        event.attempts + 1,
+                        e
+                    );
+                    return Ok(());
+                }
+                EgressOutbox::delete(event.id, db_pool).await?;
+            }
+        }
+    }
+
+    /// Publish one event and wait until JetStream has stored it
+    pub async fn publish(
+        &self,
+        channel: &str,
+        event_id: &str,
+        payload: &str,
+    ) -> Result<(), AppError> {
+        let publish = Publish::build()
+            .payload(payload.to_string().into())
+            .message_id(event_id);
+
+        self.context
+            .send_publish(subject(channel), publish)
+            .await
+            .map_err(|e| AppError::Egress(e.to_string()))?
+            .await
+            .map_err(|e| AppError::Egress(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// JetStream subject for a Redis channel: `chain:events:swap` → `chain.events.swap`
+pub fn subject(channel: &str) -> String {
+    channel.replace(':', ".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis_client::channels;
+
+    #[test]
+    fn each_event_type_gets_its_own_subject() {
+        assert_eq!(subject(channels::SWAP), "chain.events.swap");
+        assert_eq!(subject(channels::NEW_PAIR), "chain.events.new_pair");
+        assert_eq!(subject(channels::LIQUIDITY), "chain.events.liquidity");
+    }
+}
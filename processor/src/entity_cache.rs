@@ -0,0 +1,244 @@
+//! Cached pair and token lookups
+//!
+//! Every swap, sync and transfer looks up its pair or token, most of them
+//! for the same few hot pairs. The rows are kept in process-wide LRU caches
+//! for a few seconds. Handlers cache the row their writes return, or drop the
+//! entry when a write doesn't return it, so what they read is never older
+//! than their own last write. Writes made outside the handlers, by scheduled
+//! jobs, show once the entry expires.
+
+use std::{
+    env,
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use indexer_db::{
+    entity::{pair::Pair, token::Token},
+    Address20,
+};
+use lru::LruCache;
+use sqlx::{Pool, Postgres};
+
+use crate::defaults;
+
+/// Values kept for a fixed time, least recently used first out when full
+struct Entries<K, V> {
+    ttl: Duration,
+    /// `None` when caching is disabled
+    entries: Option<Mutex<LruCache<K, (Instant, V)>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> Entries<K, V> {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        let entries = NonZeroUsize::new(capacity)
+            .filter(|_| !ttl.is_zero())
+            .map(|capacity| Mutex::new(LruCache::new(capacity)));
+        Self { ttl, entries }
+    }
+
+    /// The value cached for `key`, unless it is older than the TTL
+    fn get(&self, key: &K, now: Instant) -> Option<V> {
+        let mut entries = self
+            .entries
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some((at, value)) if now.duration_since(*at) < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache `value` for `key`, read at `now`
+    fn insert(&self, key: K, value: V, now: Instant) {
+        if let Some(entries) = &self.entries {
+            let mut entries = entries.lock().unwrap_or_else(|e| e.into_inner());
+            entries.put(key, (now, value));
+        }
+    }
+
+    fn remove(&self, key: &K) {
+        if let Some(entries) = &self.entries {
+            entries.lock().unwrap_or_else(|e| e.into_inner()).pop(key);
+        }
+    }
+}
+
+/// Recently read pairs and tokens, by address
+pub struct EntityCache {
+    pairs: Entries<Address20, Pair>,
+    tokens: Entries<Address20, Token>,
+}
+
+impl EntityCache {
+    /// Caches holding up to `capacity` pairs and as many tokens, each for
+    /// `ttl`; a zero capacity or TTL disables caching
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            pairs: Entries::new(capacity, ttl),
+            tokens: Entries::new(capacity, ttl),
+        }
+    }
+
+    /// Lookups always go to the database
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: u64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<u64>()
+                .unwrap_or(fallback)
+        };
+
+        Self::new(
+            read(
+                "ENTITY_CACHE_CAPACITY",
+                defaults::ENTITY_CACHE_CAPACITY,
+                10_000,
+            ) as usize,
+            Duration::from_millis(read(
+                "ENTITY_CACHE_TTL_MS",
+                defaults::ENTITY_CACHE_TTL_MS,
+                5_000,
+            )),
+        )
+    }
+
+    /// The process-wide caches, read from the environment on first use
+    pub fn shared() -> Arc<EntityCache> {
+        static SHARED: OnceLock<Arc<EntityCache>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| Arc::new(EntityCache::from_env()))
+            .clone()
+    }
+
+    /// Get a pair, from the cache when it was read recently. Pairs that
+    /// aren't indexed aren't cached, so they show as soon as they are.
+    pub async fn pair(
+        &self,
+        address: &Address20,
+        db_pool: &Pool<Postgres>,
+    ) -> Result<Option<Pair>, sqlx::Error> {
+        if let Some(pair) = self.pairs.get(address, Instant::now()) {
+            return Ok(Some(pair));
+        }
+        let pair = Pair::find_by_address(address, db_pool).await?;
+        if let Some(pair) = &pair {
+            self.pairs.insert(*address, pair.clone(), Instant::now());
+        }
+        Ok(pair)
+    }
+
+    /// Get a token, from the cache when it was read recently
+    pub async fn token(
+        &self,
+        address: &Address20,
+        db_pool: &Pool<Postgres>,
+    ) -> Result<Option<Token>, sqlx::Error> {
+        if let Some(token) = self.tokens.get(address, Instant::now()) {
+            return Ok(Some(token));
+        }
+        let token = Token::find_by_address(address, db_pool).await?;
+        if let Some(token) = &token {
+            self.tokens.insert(*address, token.clone(), Instant::now());
+        }
+        Ok(token)
+    }
+
+    /// Cache the pair row a write returned. A failed write, or one that
+    /// returned no row, drops the entry instead; its error is passed on.
+    pub fn store_pair(
+        &self,
+        address: &Address20,
+        written: Result<Option<Pair>, sqlx::Error>,
+    ) -> Result<(), sqlx::Error> {
+        match written {
+            Ok(Some(pair)) => self.pairs.insert(*address, pair, Instant::now()),
+            Ok(None) => self.pairs.remove(address),
+            Err(e) => {
+                self.pairs.remove(address);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Cache the token row a write returned, like [`EntityCache::store_pair`]
+    pub fn store_token(
+        &self,
+        address: &Address20,
+        written: Result<Option<Token>, sqlx::Error>,
+    ) -> Result<(), sqlx::Error> {
+        match written {
+            Ok(Some(token)) => self.tokens.insert(*address, token, Instant::now()),
+            Ok(None) => self.tokens.remove(address),
+            Err(e) => {
+                self.tokens.remove(address);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop a pair whose row was just written
+    pub fn invalidate_pair(&self, address: &Address20) {
+        self.pairs.remove(address);
+    }
+
+    /// Drop a token whose row was just written
+    pub fn invalidate_token(&self, address: &Address20) {
+        self.tokens.remove(address);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire_and_can_be_dropped() {
+        let entries = Entries::new(10, Duration::from_secs(5));
+        let now = Instant::now();
+
+        entries.insert("a", 1, now);
+        entries.insert("b", 2, now);
+        assert_eq!(entries.get(&"a", now + Duration::from_secs(4)), Some(1));
+        assert_eq!(entries.get(&"a", now + Duration::from_secs(5)), None);
+
+        entries.remove(&"b");
+        assert_eq!(entries.get(&"b", now), None);
+
+        let disabled = Entries::new(10, Duration::ZERO);
+        disabled.insert("a", 1, now);
+        assert_eq!(disabled.get(&"a", now), None);
+        let disabled = Entries::new(0, Duration::from_secs(5));
+        disabled.insert("a", 1, now);
+        assert_eq!(disabled.get(&"a", now), None);
+    }
+
+    #[test]
+    fn least_recently_used_entries_go_first() {
+        let entries = Entries::new(2, Duration::from_secs(5));
+        let now = Instant::now();
+
+        entries.insert("a", 1, now);
+        entries.insert("b", 2, now);
+        // Reading "a" leaves "b" as the least recently used
+        assert_eq!(entries.get(&"a", now), Some(1));
+        entries.insert("c", 3, now);
+
+        assert_eq!(entries.get(&"a", now), Some(1));
+        assert_eq!(entries.get(&"b", now), None);
+        assert_eq!(entries.get(&"c", now), Some(3));
+    }
+}
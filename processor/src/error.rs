@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use indexer_db::entity::evm_logs::EvmLogsError;
+use indexer_db::{entity::evm_logs::EvmLogsError, Address20};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -26,6 +26,12 @@ pub enum AppError {
     #[error("Contract: `{0}` does not have event handler for: `{1}`")]
     MissingEventHandler(String, String),
 
+    #[error("No wrapped native token in `chain_constants` for chain {0}")]
+    UnknownChain(i64),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
     #[error("Invalid address: `{0}`")]
     InvalidAddress(String),
 
@@ -42,6 +48,9 @@ pub enum AppError {
     #[error("Redis publish error: {0}")]
     RedisPublish(String),
 
+    #[error("Event egress error: {0}")]
+    Egress(String),
+
     #[error("Event decoding error: {0}")]
     EventDecode(String),
 
@@ -53,4 +62,14 @@ pub enum AppError {
 
     #[error("Handler error: {0}")]
     Handler(String),
+
+    /// The log references a pair that isn't indexed (yet); the processor
+    /// parks it and retries later
+    #[error("Unknown pair: `{0}`")]
+    UnknownPair(Address20),
+
+    /// The decimals of a token the log moves couldn't be read from the
+    /// chain; the processor parks the log and retries later
+    #[error("Unknown decimals of token: `{0}`")]
+    UnknownDecimals(Address20),
 }
@@ -0,0 +1,410 @@
+//! Processor event metrics per topic
+//!
+//! Every log pulled from the queue is counted under its event type (as in
+//! [`lag::EVENT_TYPES`](crate::lag::EVENT_TYPES), `unknown` for any other
+//! topic), along with decode failures, handler errors, logs skipped for want
+//! of a decoder and a histogram of handler latency. The counts are added to `processor_event_metrics` after
+//! each batch for export. Every `EVENT_ERROR_WINDOW_SECONDS` each event type's
+//! share of failed logs is checked against `EVENT_ERROR_RATE_PERCENT`; the
+//! first window over it (with at least `EVENT_ERROR_MIN_EVENTS` logs) raises
+//! an alert, so a decoder broken for one topic shows up right away.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    env,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use indexer_db::entity::{
+    alert::{AlertEvent, AlertType, NewAlert},
+    alert_metadata::{AlertMetadata, EventErrorRateMetadata},
+    processor_event_metric::{EventCounts, ProcessorEventMetric, LATENCY_BUCKETS_MS},
+};
+use sqlx::{Pool, Postgres};
+
+use crate::{defaults, events::topics};
+
+/// Event type of a log's topic0
+pub fn event_type_of(topic0: &str) -> &'static str {
+    match topic0 {
+        topics::PAIR_CREATED => "new_pair",
+        topics::SWAP => "swap",
+        topics::TRANSFER => "transfer",
+        topics::SYNC => "sync",
+        topics::MINT | topics::BURN => "liquidity",
+        topics::APPROVAL => "approval",
+        _ => "unknown",
+    }
+}
+
+/// How processing a log went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Handled,
+    DecodeFailed,
+    HandlerFailed,
+    /// No decoder for the topic; not a failure
+    Skipped,
+}
+
+/// Failure share an event type may reach in a window before alerting
+#[derive(Debug, Clone)]
+pub struct ErrorRateLimit {
+    window: Duration,
+    max_percent: f64,
+    /// Fewer logs than this in a window say too little to alert on
+    min_events: i64,
+}
+
+impl ErrorRateLimit {
+    pub fn from_env() -> Self {
+        let window = env::var("EVENT_ERROR_WINDOW_SECONDS")
+            .unwrap_or_else(|_| defaults::EVENT_ERROR_WINDOW_SECONDS.to_string())
+            .parse::<i64>()
+            .unwrap_or(300)
+            .max(1);
+        let max_percent = env::var("EVENT_ERROR_RATE_PERCENT")
+            .unwrap_or_else(|_| defaults::EVENT_ERROR_RATE_PERCENT.to_string())
+            .parse::<f64>()
+            .unwrap_or(5.0)
+            .clamp(0.0, 100.0);
+        let min_events = env::var("EVENT_ERROR_MIN_EVENTS")
+            .unwrap_or_else(|_| defaults::EVENT_ERROR_MIN_EVENTS.to_string())
+            .parse::<i64>()
+            .unwrap_or(20)
+            .max(1);
+
+        Self {
+            window: Duration::seconds(window),
+            max_percent,
+            min_events,
+        }
+    }
+}
+
+/// Counts not yet written out
+#[derive(Debug, Clone, Default)]
+struct Tally {
+    events: i64,
+    decode_failures: i64,
+    handler_errors: i64,
+    skipped: i64,
+    latency_buckets: [i64; LATENCY_BUCKETS_MS.len() + 1],
+    latency_sum_ms: i64,
+}
+
+impl Tally {
+    fn failures(&self) -> i64 {
+        self.decode_failures + self.handler_errors
+    }
+}
+
+/// An event type's run of failing windows
+#[derive(Debug, Clone, Copy, Default)]
+struct Failing {
+    since: Option<DateTime<Utc>>,
+    alerted: bool,
+}
+
+/// A closed window of one event type
+#[derive(Debug, Clone)]
+pub struct WindowCheck {
+    pub event_type: &'static str,
+    pub events: i64,
+    pub decode_failures: i64,
+    pub handler_errors: i64,
+    pub failing_since: Option<DateTime<Utc>>,
+    /// The failure rate just went over the limit
+    pub alert: bool,
+    /// The event type was alerted on and is back under the limit
+    pub recovered: bool,
+}
+
+impl WindowCheck {
+    fn failure_percent(&self) -> f64 {
+        (self.decode_failures + self.handler_errors) as f64 * 100.0 / self.events.max(1) as f64
+    }
+}
+
+/// Counts processed logs per event type across batches
+#[derive(Debug)]
+pub struct EventMetrics {
+    limit: ErrorRateLimit,
+    /// Counts since the last flush
+    pending: HashMap<&'static str, Tally>,
+    window_start: DateTime<Utc>,
+    /// Counts in the open window
+    window: HashMap<&'static str, Tally>,
+    failing: HashMap<&'static str, Failing>,
+}
+
+impl EventMetrics {
+    pub fn new(limit: ErrorRateLimit, now: DateTime<Utc>) -> Self {
+        Self {
+            limit,
+            pending: HashMap::new(),
+            window_start: now,
+            window: HashMap::new(),
+            failing: HashMap::new(),
+        }
+    }
+
+    /// Count one log; `latency` is how long its handler took, if it ran
+    pub fn record(
+        &mut self,
+        event_type: &'static str,
+        outcome: Outcome,
+        latency: Option<std::time::Duration>,
+    ) {
+        for tally in [
+            self.pending.entry(event_type).or_default(),
+            self.window.entry(event_type).or_default(),
+        ] {
+            tally.events += 1;
+            match outcome {
+                Outcome::Handled => {}
+                Outcome::DecodeFailed => tally.decode_failures += 1,
+                Outcome::HandlerFailed => tally.handler_errors += 1,
+                Outcome::Skipped => tally.skipped += 1,
+            }
+            if let Some(latency) = latency {
+                let ms = latency.as_millis() as i64;
+                let bucket = LATENCY_BUCKETS_MS
+                    .iter()
+                    .position(|&bound| ms <= bound)
+                    .unwrap_or(LATENCY_BUCKETS_MS.len());
+                tally.latency_buckets[bucket] += 1;
+                tally.latency_sum_ms += ms;
+            }
+        }
+    }
+
+    /// Close the open window if it has run its length. Event types without
+    /// logs keep their state: no logs says nothing about failures.
+    pub fn close_window(&mut self, now: DateTime<Utc>) -> Vec<WindowCheck> {
+        if now - self.window_start < self.limit.window {
+            return Vec::new();
+        }
+        let window_start = self.window_start;
+        self.window_start = now;
+
+        let mut checks = Vec::new();
+        for (event_type, tally) in self.window.drain() {
+            let failing = self.failing.entry(event_type).or_default();
+            let percent = tally.failures() as f64 * 100.0 / tally.events.max(1) as f64;
+
+            let mut alert = false;
+            let mut recovered = false;
+            if tally.events >= self.limit.min_events && percent > self.limit.max_percent {
+                failing.since.get_or_insert(window_start);
+                alert = !failing.alerted;
+                failing.alerted = true;
+            } else {
+                recovered = failing.alerted;
+                *failing = Failing::default();
+            }
+
+            checks.push(WindowCheck {
+                event_type,
+                events: tally.events,
+                decode_failures: tally.decode_failures,
+                handler_errors: tally.handler_errors,
+                failing_since: failing.since,
+                alert,
+                recovered,
+            });
+        }
+
+        checks.sort_by_key(|c| c.event_type);
+        checks
+    }
+
+    /// Write out the counts since the last flush, close the window if due
+    /// and alert on event types whose failures just went over the limit
+    pub async fn flush(&mut self, db_pool: &Pool<Postgres>) {
+        let checks = self.close_window(Utc::now());
+
+        let event_types: BTreeSet<_> = self
+            .pending
+            .keys()
+            .copied()
+            .chain(checks.iter().map(|c| c.event_type))
+            .collect();
+        for event_type in event_types {
+            let tally = self.pending.remove(event_type).unwrap_or_default();
+            let counts = EventCounts {
+                event_type: event_type.to_string(),
+                events: tally.events,
+                decode_failures: tally.decode_failures,
+                handler_errors: tally.handler_errors,
+                skipped: tally.skipped,
+                latency_buckets: tally.latency_buckets.to_vec(),
+                latency_sum_ms: tally.latency_sum_ms,
+                failing_since: self.failing.get(event_type).and_then(|f| f.since),
+            };
+            if let Err(e) = ProcessorEventMetric::add(&counts, db_pool).await {
+                tracing::error!("Failed to record {} event metrics: {}", event_type, e);
+            }
+        }
+
+        for check in &checks {
+            if check.recovered {
+                tracing::info!(
+                    "{} events are back under the {}% failure limit ({:.1}% of {})",
+                    check.event_type,
+                    self.limit.max_percent,
+                    check.failure_percent(),
+                    check.events
+                );
+            }
+            if check.alert {
+                tracing::error!(
+                    "{} events failing: {} decode failures and {} handler errors in {}",
+                    check.event_type, check.decode_failures, check.handler_errors, check.events
+                );
+                let alert = failing_alert(check, self.limit.max_percent);
+                if let Err(e) = AlertEvent::create(&alert, db_pool).await {
+                    tracing::error!("Failed to create event error rate alert: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Alert for an event type whose logs are failing over the limit
+fn failing_alert(check: &WindowCheck, max_percent: f64) -> NewAlert {
+    NewAlert {
+        alert_type: AlertType::EventErrorRate.as_str().to_string(),
+        token_address: None,
+        token_symbol: None,
+        wallet_address: None,
+        title: format!(
+            "{} events failing: {:.1}%",
+            check.event_type,
+            check.failure_percent()
+        ),
+        message: Some(format!(
+            "{} of {} {} logs failed in the last window ({} decode failures, {} handler errors), over the {}% limit",
+            check.decode_failures + check.handler_errors,
+            check.events,
+            check.event_type,
+            check.decode_failures,
+            check.handler_errors,
+            max_percent
+        )),
+        bee_score: None,
+        amount_usd: None,
+        change_percent: None,
+        metadata: Some(AlertMetadata::EventErrorRate(EventErrorRateMetadata {
+            event_type: check.event_type.to_string(),
+            events: check.events,
+            decode_failures: check.decode_failures,
+            handler_errors: check.handler_errors,
+            max_percent,
+            failing_since: check.failing_since,
+        })),
+        source: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
+
+    use super::*;
+
+    fn limit() -> ErrorRateLimit {
+        ErrorRateLimit {
+            window: Duration::seconds(60),
+            max_percent: 10.0,
+            min_events: 5,
+        }
+    }
+
+    /// Record `ok` handled and `failed` undecodable swaps, then close the
+    /// window a minute after `start`
+    fn window(
+        metrics: &mut EventMetrics,
+        start: DateTime<Utc>,
+        ok: usize,
+        failed: usize,
+    ) -> Vec<WindowCheck> {
+        for _ in 0..ok {
+            metrics.record("swap", Outcome::Handled, Some(StdDuration::from_millis(3)));
+        }
+        for _ in 0..failed {
+            metrics.record("swap", Outcome::DecodeFailed, None);
+        }
+        metrics.close_window(start + Duration::seconds(60))
+    }
+
+    #[test]
+    fn topics_map_to_event_types() {
+        assert_eq!(event_type_of(topics::SWAP), "swap");
+        assert_eq!(event_type_of(topics::BURN), "liquidity");
+        assert_eq!(event_type_of("0x00"), "unknown");
+    }
+
+    #[test]
+    fn latency_lands_in_its_bucket() {
+        let mut metrics = EventMetrics::new(limit(), Utc::now());
+        for ms in [0, 3, 7, 60_000] {
+            metrics.record(
+                "sync",
+                Outcome::Handled,
+                Some(StdDuration::from_millis(ms)),
+            );
+        }
+        metrics.record("sync", Outcome::HandlerFailed, Some(StdDuration::from_millis(3)));
+
+        let tally = &metrics.pending["sync"];
+        assert_eq!(tally.events, 5);
+        assert_eq!(tally.handler_errors, 1);
+        assert_eq!(&tally.latency_buckets[..3], &[1, 2, 1]);
+        assert_eq!(tally.latency_buckets[LATENCY_BUCKETS_MS.len()], 1);
+        assert_eq!(tally.latency_sum_ms, 60_013);
+    }
+
+    #[test]
+    fn alerts_when_failures_go_over_the_limit() {
+        let start = Utc::now();
+        let mut metrics = EventMetrics::new(limit(), start);
+        let minute = |n: i64| start + Duration::minutes(n);
+
+        // Not due yet
+        metrics.record("swap", Outcome::DecodeFailed, None);
+        assert!(metrics.close_window(start + Duration::seconds(59)).is_empty());
+
+        // Too few logs to tell, then healthy
+        assert!(!window(&mut metrics, minute(0), 0, 1)[0].alert);
+        assert!(!window(&mut metrics, minute(1), 20, 1)[0].alert);
+
+        let check = &window(&mut metrics, minute(2), 8, 2)[0];
+        assert!(check.alert);
+        assert_eq!(check.decode_failures, 2);
+        assert_eq!(check.failing_since, Some(minute(2)));
+        // Only once per run
+        assert!(!window(&mut metrics, minute(3), 8, 2)[0].alert);
+        // A quiet minute leaves it failing
+        assert!(metrics.close_window(minute(5)).is_empty());
+
+        let check = &window(&mut metrics, minute(5), 10, 0)[0];
+        assert!(check.recovered);
+        assert_eq!(check.failing_since, None);
+    }
+
+    #[test]
+    fn skipped_topics_are_not_failures() {
+        let start = Utc::now();
+        let mut metrics = EventMetrics::new(limit(), start);
+        for _ in 0..10 {
+            metrics.record("unknown", Outcome::Skipped, None);
+        }
+        assert_eq!(metrics.pending["unknown"].skipped, 10);
+
+        let check = &metrics.close_window(start + Duration::seconds(60))[0];
+        assert_eq!(check.events, 10);
+        assert_eq!(check.decode_failures, 0);
+        assert!(!check.alert);
+    }
+}
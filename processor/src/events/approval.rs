@@ -0,0 +1,86 @@
+//! Approval event decoder
+//!
+//! Event signature: Approval(address indexed owner, address indexed spender, uint256 value)
+//! Topic0: 0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925
+
+use chrono::{DateTime, Utc};
+use indexer_db::{
+    entity::evm_logs::{EvmLogs, SourceLog},
+    Address20, Hash32,
+};
+use serde::Serialize;
+
+use crate::{error::AppError, utils};
+
+/// Decoded Approval event payload
+#[derive(Debug, Serialize)]
+pub struct ApprovalEvent {
+    /// Token contract address
+    pub token: Address20,
+    /// Address granting the allowance
+    pub owner: Address20,
+    /// Address allowed to spend
+    pub spender: Address20,
+    /// Allowance (hex string to preserve precision for large values)
+    pub value: String,
+    /// Block number
+    pub block: String,
+    /// Transaction hash
+    pub tx_hash: Hash32,
+    /// Time of the block, when the listener stamped it
+    #[serde(skip)]
+    pub block_timestamp: Option<DateTime<Utc>>,
+    /// Log the event was decoded from
+    #[serde(skip)]
+    pub source: SourceLog,
+}
+
+/// Decode an Approval event from raw log data
+///
+/// Topics layout:
+/// - topics[0]: event signature
+/// - topics[1]: owner (indexed)
+/// - topics[2]: spender (indexed)
+///
+/// Data layout:
+/// - bytes 0-32: value (uint256)
+pub fn decode(log: &EvmLogs) -> Result<ApprovalEvent, AppError> {
+    if log.topics.len() < 3 {
+        return Err(AppError::EventDecode(format!(
+            "Approval: expected 3 topics, got {}",
+            log.topics.len()
+        )));
+    }
+
+    if log.data.len() < 32 {
+        return Err(AppError::EventDecode(format!(
+            "Approval: expected at least 32 bytes of data, got {}",
+            log.data.len()
+        )));
+    }
+
+    // Token address is the log emitter
+    let token = Address20::new(log.address);
+
+    let owner = utils::word_to_address(&log.topics[1])
+        .ok_or_else(|| AppError::EventDecode("Approval: malformed owner topic".to_string()))?;
+
+    let spender = utils::word_to_address(&log.topics[2])
+        .ok_or_else(|| AppError::EventDecode("Approval: malformed spender topic".to_string()))?;
+
+    let value = format!("0x{}", utils::vec_to_hex(log.data[0..32].to_vec()));
+
+    let block = log.block_number.to_string();
+    let tx_hash = Hash32::new(log.transaction_hash);
+
+    Ok(ApprovalEvent {
+        token,
+        owner,
+        spender,
+        value,
+        block,
+        tx_hash,
+        block_timestamp: log.block_timestamp,
+        source: log.source(),
+    })
+}
@@ -0,0 +1,103 @@
+//! Mint/Burn event decoder
+//!
+//! Event signatures:
+//! - Mint(address indexed sender, uint amount0, uint amount1)
+//!   Topic0: 0x4c209b5fc8ad50758f13e2e1088ba56a560dff690a1c6fef26394f4c03821c4f
+//! - Burn(address indexed sender, uint amount0, uint amount1, address indexed to)
+//!   Topic0: 0xdccd412f0b1252819cb1fd330b93224ca42612892bb3f4f789976e6d81936496
+//!
+//! Both change the pair's LP token supply, so they share one decoder.
+
+use indexer_db::{entity::evm_logs::EvmLogs, Address20, Hash32};
+use serde::Serialize;
+
+use crate::{error::AppError, utils};
+
+use super::topics;
+
+/// Whether liquidity was added or removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiquidityKind {
+    Mint,
+    Burn,
+}
+
+/// Decoded Mint/Burn event payload
+#[derive(Debug, Serialize)]
+pub struct LiquidityEvent {
+    /// Pair contract address whose LP supply changed
+    pub pair: Address20,
+    pub kind: LiquidityKind,
+    /// Router or account that added/removed the liquidity
+    pub sender: Address20,
+    /// Amount of token0 (hex string)
+    pub amount0: String,
+    /// Amount of token1 (hex string)
+    pub amount1: String,
+    /// Block number
+    pub block: String,
+    /// Transaction hash
+    pub tx_hash: Hash32,
+}
+
+/// Decode a Mint or Burn event from raw log data
+///
+/// Topics layout:
+/// - topics[0]: event signature
+/// - topics[1]: sender (indexed)
+/// - topics[2]: to (indexed, Burn only)
+///
+/// Data layout (each 32 bytes):
+/// - bytes 0-32: amount0
+/// - bytes 32-64: amount1
+pub fn decode(log: &EvmLogs) -> Result<LiquidityEvent, AppError> {
+    let topic0 = format!("0x{}", utils::vec_to_hex(log.event_signature.to_vec()));
+    let kind = if topic0 == topics::BURN {
+        LiquidityKind::Burn
+    } else {
+        LiquidityKind::Mint
+    };
+
+    // Ensure we have enough topics
+    if log.topics.len() < 2 {
+        return Err(AppError::EventDecode(format!(
+            "{:?}: expected at least 2 topics, got {}",
+            kind,
+            log.topics.len()
+        )));
+    }
+
+    // Ensure data is long enough (2 x 32 bytes = 64 bytes)
+    if log.data.len() < 64 {
+        return Err(AppError::EventDecode(format!(
+            "{:?}: expected at least 64 bytes of data, got {}",
+            kind,
+            log.data.len()
+        )));
+    }
+
+    // Pair address is the log emitter
+    let pair = Address20::new(log.address);
+
+    // Extract sender address from topics[1]
+    let sender = utils::word_to_address(&log.topics[1])
+        .ok_or_else(|| AppError::EventDecode(format!("{:?}: malformed sender topic", kind)))?;
+
+    // Extract amounts from data (as hex strings to preserve precision)
+    let amount0 = format!("0x{}", utils::vec_to_hex(log.data[0..32].to_vec()));
+    let amount1 = format!("0x{}", utils::vec_to_hex(log.data[32..64].to_vec()));
+
+    let block = log.block_number.to_string();
+    let tx_hash = Hash32::new(log.transaction_hash);
+
+    Ok(LiquidityEvent {
+        pair,
+        kind,
+        sender,
+        amount0,
+        amount1,
+        block,
+        tx_hash,
+    })
+}
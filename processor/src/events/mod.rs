@@ -1,12 +1,18 @@
 //! Event decoders for BeanBee BSC indexer
 //! 
-//! This module contains decoders for the three critical events:
+//! This module contains decoders for the critical events:
 //! - PairCreated: New token launches on PancakeSwap
 //! - Swap: Price updates from DEX trades
+//! - Sync: Pair reserve updates
 //! - Transfer: Wallet activity (ERC20 transfers)
+//! - Mint/Burn: LP token supply changes
+//! - Approval: ERC20 allowances (drainer approvals)
 
+pub mod approval;
+pub mod liquidity;
 pub mod pair_created;
 pub mod swap;
+pub mod sync;
 pub mod transfer;
 
 use indexer_db::entity::evm_logs::EvmLogs;
@@ -21,6 +27,14 @@ pub mod topics {
     pub const SWAP: &str = "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822";
     /// Transfer(address indexed from, address indexed to, uint256 value)
     pub const TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    /// Sync(uint112 reserve0, uint112 reserve1)
+    pub const SYNC: &str = "0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1";
+    /// Mint(address indexed sender, uint amount0, uint amount1)
+    pub const MINT: &str = "0x4c209b5fc8ad50758f13e2e1088ba56a560dff690a1c6fef26394f4c03821c4f";
+    /// Burn(address indexed sender, uint amount0, uint amount1, address indexed to)
+    pub const BURN: &str = "0xdccd412f0b1252819cb1fd330b93224ca42612892bb3f4f789976e6d81936496";
+    /// Approval(address indexed owner, address indexed spender, uint256 value)
+    pub const APPROVAL: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
 }
 
 /// Result of decoding an event - contains channel and JSON payload
@@ -58,7 +72,219 @@ pub fn decode_event(log: &EvmLogs) -> Result<DecodedEvent, AppError> {
                     .map_err(|e| AppError::EventDecode(e.to_string()))?,
             })
         }
+        topics::SYNC => {
+            let event = sync::decode(log)?;
+            Ok(DecodedEvent {
+                channel: channels::SYNC,
+                payload: serde_json::to_string(&event)
+                    .map_err(|e| AppError::EventDecode(e.to_string()))?,
+            })
+        }
+        topics::MINT | topics::BURN => {
+            let event = liquidity::decode(log)?;
+            Ok(DecodedEvent {
+                channel: channels::LIQUIDITY,
+                payload: serde_json::to_string(&event)
+                    .map_err(|e| AppError::EventDecode(e.to_string()))?,
+            })
+        }
+        topics::APPROVAL => {
+            let event = approval::decode(log)?;
+            Ok(DecodedEvent {
+                channel: channels::APPROVAL,
+                payload: serde_json::to_string(&event)
+                    .map_err(|e| AppError::EventDecode(e.to_string()))?,
+            })
+        }
         _ => Err(AppError::UnknownEventTopic(topic0)),
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use indexer_db::Address20;
+    use proptest::prelude::*;
+    use sqlx::types::{chrono::NaiveDateTime, BigDecimal};
+
+    use super::*;
+
+    fn topic_hex(topic: &str) -> [u8; 32] {
+        alloy::hex::decode(topic).unwrap().try_into().unwrap()
+    }
+
+    fn padded(address: [u8; 20]) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(&address);
+        word
+    }
+
+    fn evm_log(signature: [u8; 32], topics: Vec<[u8; 32]>, data: Vec<u8>, block: u64) -> EvmLogs {
+        EvmLogs {
+            id: 1,
+            block_number: BigDecimal::from(block),
+            block_hash: [0; 32],
+            address: [0xaa; 20],
+            transaction_hash: [0xbb; 32],
+            data,
+            event_signature: signature,
+            topics: std::iter::once(signature).chain(topics).collect(),
+            transaction_index: 0,
+            log_index: 0,
+            removed: false,
+            created_at: NaiveDateTime::default(),
+            block_timestamp: None,
+        }
+    }
+
+    /// A 32-byte word that is sometimes a correctly padded address
+    fn word() -> impl Strategy<Value = [u8; 32]> {
+        prop_oneof![any::<[u8; 20]>().prop_map(padded), any::<[u8; 32]>()]
+    }
+
+    /// Any of the known signatures, or an arbitrary one
+    fn signature() -> impl Strategy<Value = [u8; 32]> {
+        prop_oneof![
+            Just(topic_hex(topics::PAIR_CREATED)),
+            Just(topic_hex(topics::SWAP)),
+            Just(topic_hex(topics::TRANSFER)),
+            Just(topic_hex(topics::SYNC)),
+            Just(topic_hex(topics::MINT)),
+            Just(topic_hex(topics::BURN)),
+            Just(topic_hex(topics::APPROVAL)),
+            any::<[u8; 32]>(),
+        ]
+    }
+
+    fn is_padded_address(word: &[u8]) -> bool {
+        word.len() == 32 && word[..12].iter().all(|b| *b == 0)
+    }
+
+    #[test]
+    fn liquidity_topics_match_their_signatures() {
+        use alloy::primitives::keccak256;
+
+        assert_eq!(
+            keccak256("Mint(address,uint256,uint256)").0,
+            topic_hex(topics::MINT)
+        );
+        assert_eq!(
+            keccak256("Burn(address,uint256,uint256,address)").0,
+            topic_hex(topics::BURN)
+        );
+    }
+
+    #[test]
+    fn approval_topic_matches_its_signature() {
+        use alloy::primitives::keccak256;
+
+        assert_eq!(
+            keccak256("Approval(address,address,uint256)").0,
+            topic_hex(topics::APPROVAL)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn decoders_never_panic_and_reject_malformed_logs(
+            signature in signature(),
+            topics in prop::collection::vec(word(), 0..5),
+            data in prop::collection::vec(any::<u8>(), 0..200),
+            block in any::<u64>(),
+        ) {
+            let log = evm_log(signature, topics, data, block);
+            let indexed_ok = log.topics.len() >= 3
+                && is_padded_address(&log.topics[1])
+                && is_padded_address(&log.topics[2]);
+
+            let pair = pair_created::decode(&log);
+            prop_assert_eq!(
+                pair.is_ok(),
+                indexed_ok && log.data.len() >= 32 && is_padded_address(&log.data[..32])
+            );
+
+            let swap = swap::decode(&log);
+            prop_assert_eq!(swap.is_ok(), indexed_ok && log.data.len() >= 128);
+
+            let transfer = transfer::decode(&log);
+            prop_assert_eq!(transfer.is_ok(), indexed_ok && log.data.len() >= 32);
+
+            let approval = approval::decode(&log);
+            prop_assert_eq!(approval.is_ok(), indexed_ok && log.data.len() >= 32);
+
+            // Sync has no indexed params
+            let sync = sync::decode(&log);
+            prop_assert_eq!(sync.is_ok(), log.data.len() >= 64);
+
+            let liquidity = liquidity::decode(&log);
+            prop_assert_eq!(
+                liquidity.is_ok(),
+                log.topics.len() >= 2 && is_padded_address(&log.topics[1]) && log.data.len() >= 64
+            );
+
+            let known = [
+                topics::PAIR_CREATED,
+                topics::SWAP,
+                topics::TRANSFER,
+                topics::SYNC,
+                topics::MINT,
+                topics::BURN,
+                topics::APPROVAL,
+            ]
+                .iter()
+                .any(|t| topic_hex(t) == signature);
+            match decode_event(&log) {
+                Ok(_) => prop_assert!(known),
+                Err(AppError::UnknownEventTopic(_)) => prop_assert!(!known),
+                Err(AppError::EventDecode(_)) => prop_assert!(known),
+                Err(other) => prop_assert!(false, "unexpected error: {}", other),
+            }
+        }
+
+        #[test]
+        fn well_formed_swaps_decode_their_fields(
+            sender in any::<[u8; 20]>(),
+            to in any::<[u8; 20]>(),
+            amounts in any::<[[u8; 32]; 4]>(),
+            block in any::<u64>(),
+        ) {
+            let data = amounts.concat();
+            let log = evm_log(topic_hex(topics::SWAP), vec![padded(sender), padded(to)], data, block);
+
+            let event = swap::decode(&log).unwrap();
+            prop_assert_eq!(event.sender, Address20::new(sender));
+            prop_assert_eq!(event.to, Address20::new(to));
+            prop_assert_eq!(event.pair, Address20::new(log.address));
+            prop_assert_eq!(event.amount0_in, format!("0x{}", utils::vec_to_hex(amounts[0].to_vec())));
+            prop_assert_eq!(event.amount1_out, format!("0x{}", utils::vec_to_hex(amounts[3].to_vec())));
+            prop_assert_eq!(event.block, block.to_string());
+        }
+
+        #[test]
+        fn well_formed_pairs_and_transfers_decode_their_fields(
+            first in any::<[u8; 20]>(),
+            second in any::<[u8; 20]>(),
+            third in any::<[u8; 20]>(),
+            value in any::<[u8; 32]>(),
+        ) {
+            let data = [padded(third).as_slice(), value.as_slice()].concat();
+
+            let log = evm_log(topic_hex(topics::PAIR_CREATED), vec![padded(first), padded(second)], data.clone(), 1);
+            let pair = pair_created::decode(&log).unwrap();
+            prop_assert_eq!(pair.token0, Address20::new(first));
+            prop_assert_eq!(pair.token1, Address20::new(second));
+            prop_assert_eq!(pair.pair, Address20::new(third));
+
+            let log = evm_log(topic_hex(topics::TRANSFER), vec![padded(first), padded(second)], value.to_vec(), 1);
+            let transfer = transfer::decode(&log).unwrap();
+            prop_assert_eq!(transfer.from, Address20::new(first));
+            prop_assert_eq!(transfer.to, Address20::new(second));
+            prop_assert_eq!(transfer.value, format!("0x{}", utils::vec_to_hex(value.to_vec())));
+
+            let log = evm_log(topic_hex(topics::APPROVAL), vec![padded(first), padded(second)], value.to_vec(), 1);
+            let approval = approval::decode(&log).unwrap();
+            prop_assert_eq!(approval.owner, Address20::new(first));
+            prop_assert_eq!(approval.spender, Address20::new(second));
+            prop_assert_eq!(approval.value, format!("0x{}", utils::vec_to_hex(value.to_vec())));
+        }
+    }
+}
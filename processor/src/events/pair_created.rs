@@ -3,7 +3,10 @@
 //! Event signature: PairCreated(address indexed token0, address indexed token1, address pair, uint)
 //! Topic0: 0x0d3648bd0f6ba80134a33ba9275ac585d9d315f0ad8355cddefde31afa28d0e9
 
-use indexer_db::entity::evm_logs::EvmLogs;
+use indexer_db::{
+    entity::evm_logs::{EvmLogs, SourceLog},
+    Address20,
+};
 use serde::Serialize;
 
 use crate::{error::AppError, utils};
@@ -12,15 +15,18 @@ use crate::{error::AppError, utils};
 #[derive(Debug, Serialize)]
 pub struct PairCreatedEvent {
     /// First token address in the pair
-    pub token0: String,
+    pub token0: Address20,
     /// Second token address in the pair
-    pub token1: String,
+    pub token1: Address20,
     /// Address of the newly created pair contract
-    pub pair: String,
+    pub pair: Address20,
     /// Block number where the pair was created
     pub block: String,
     /// Factory address that created the pair
-    pub factory: String,
+    pub factory: Address20,
+    /// Log the event was decoded from
+    #[serde(skip)]
+    pub source: SourceLog,
 }
 
 /// Decode a PairCreated event from raw log data
@@ -43,20 +49,23 @@ pub fn decode(log: &EvmLogs) -> Result<PairCreatedEvent, AppError> {
     }
 
     // Extract token0 from topics[1] (last 20 bytes of 32-byte topic)
-    let token0 = format!("0x{}", utils::vec_to_hex(log.topics[1][12..32].to_vec()));
+    let token0 = utils::word_to_address(&log.topics[1])
+        .ok_or_else(|| AppError::EventDecode("PairCreated: malformed token0 topic".to_string()))?;
     
     // Extract token1 from topics[2] (last 20 bytes of 32-byte topic)
-    let token1 = format!("0x{}", utils::vec_to_hex(log.topics[2][12..32].to_vec()));
+    let token1 = utils::word_to_address(&log.topics[2])
+        .ok_or_else(|| AppError::EventDecode("PairCreated: malformed token1 topic".to_string()))?;
 
     // Extract pair address from data (first 32 bytes, address in last 20)
     let pair = if log.data.len() >= 32 {
-        format!("0x{}", utils::vec_to_hex(log.data[12..32].to_vec()))
+        utils::word_to_address(&log.data[0..32])
+            .ok_or_else(|| AppError::EventDecode("PairCreated: malformed pair address".to_string()))?
     } else {
         return Err(AppError::EventDecode("PairCreated: data too short for pair address".to_string()));
     };
 
     // Factory address is the log emitter
-    let factory = format!("0x{}", utils::vec_to_hex(log.address.to_vec()));
+    let factory = Address20::new(log.address);
 
     // Block number
     let block = log.block_number.to_string();
@@ -67,6 +76,7 @@ pub fn decode(log: &EvmLogs) -> Result<PairCreatedEvent, AppError> {
         pair,
         block,
         factory,
+        source: log.source(),
     })
 }
 
@@ -3,7 +3,11 @@
 //! Event signature: Swap(address indexed sender, uint amount0In, uint amount1In, uint amount0Out, uint amount1Out, address indexed to)
 //! Topic0: 0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822
 
-use indexer_db::entity::evm_logs::EvmLogs;
+use chrono::{DateTime, Utc};
+use indexer_db::{
+    entity::evm_logs::{EvmLogs, SourceLog},
+    Address20, Hash32,
+};
 use serde::Serialize;
 
 use crate::{error::AppError, utils};
@@ -12,9 +16,9 @@ use crate::{error::AppError, utils};
 #[derive(Debug, Serialize)]
 pub struct SwapEvent {
     /// Pair contract address where the swap occurred
-    pub pair: String,
+    pub pair: Address20,
     /// Sender address (who initiated the swap)
-    pub sender: String,
+    pub sender: Address20,
     /// Amount of token0 swapped in
     pub amount0_in: String,
     /// Amount of token1 swapped in
@@ -24,9 +28,21 @@ pub struct SwapEvent {
     /// Amount of token1 received
     pub amount1_out: String,
     /// Recipient address
-    pub to: String,
+    pub to: Address20,
     /// Block number
     pub block: String,
+    /// Transaction hash
+    pub tx_hash: Hash32,
+    /// Position of the transaction in its block
+    pub transaction_index: i64,
+    /// Position of the log in its block
+    pub log_index: i64,
+    /// Time of the block, when the listener stamped it
+    #[serde(skip)]
+    pub block_timestamp: Option<DateTime<Utc>>,
+    /// Log the event was decoded from
+    #[serde(skip)]
+    pub source: SourceLog,
 }
 
 /// Decode a Swap event from raw log data
@@ -59,13 +75,15 @@ pub fn decode(log: &EvmLogs) -> Result<SwapEvent, AppError> {
     }
 
     // Pair address is the log emitter
-    let pair = format!("0x{}", utils::vec_to_hex(log.address.to_vec()));
+    let pair = Address20::new(log.address);
 
     // Extract sender from topics[1]
-    let sender = format!("0x{}", utils::vec_to_hex(log.topics[1][12..32].to_vec()));
+    let sender = utils::word_to_address(&log.topics[1])
+        .ok_or_else(|| AppError::EventDecode("Swap: malformed sender topic".to_string()))?;
 
     // Extract to from topics[2]
-    let to = format!("0x{}", utils::vec_to_hex(log.topics[2][12..32].to_vec()));
+    let to = utils::word_to_address(&log.topics[2])
+        .ok_or_else(|| AppError::EventDecode("Swap: malformed to topic".to_string()))?;
 
     // Extract amounts from data (as hex strings to preserve precision)
     let amount0_in = format!("0x{}", utils::vec_to_hex(log.data[0..32].to_vec()));
@@ -74,6 +92,7 @@ pub fn decode(log: &EvmLogs) -> Result<SwapEvent, AppError> {
     let amount1_out = format!("0x{}", utils::vec_to_hex(log.data[96..128].to_vec()));
 
     let block = log.block_number.to_string();
+    let tx_hash = Hash32::new(log.transaction_hash);
 
     Ok(SwapEvent {
         pair,
@@ -84,6 +103,11 @@ pub fn decode(log: &EvmLogs) -> Result<SwapEvent, AppError> {
         amount1_out,
         to,
         block,
+        tx_hash,
+        transaction_index: log.transaction_index,
+        log_index: log.log_index,
+        block_timestamp: log.block_timestamp,
+        source: log.source(),
     })
 }
 
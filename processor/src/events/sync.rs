@@ -0,0 +1,61 @@
+//! Sync event decoder
+//! 
+//! Event signature: Sync(uint112 reserve0, uint112 reserve1)
+//! Topic0: 0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1
+
+use chrono::{DateTime, Utc};
+use indexer_db::{entity::evm_logs::EvmLogs, Address20};
+use serde::Serialize;
+
+use crate::{error::AppError, utils};
+
+/// Decoded Sync event payload
+#[derive(Debug, Serialize)]
+pub struct SyncEvent {
+    /// Pair contract address whose reserves changed
+    pub pair: Address20,
+    /// New reserve of token0 (hex string)
+    pub reserve0: String,
+    /// New reserve of token1 (hex string)
+    pub reserve1: String,
+    /// Block number
+    pub block: String,
+    /// Time of the block, when the listener stamped it
+    #[serde(skip)]
+    pub block_timestamp: Option<DateTime<Utc>>,
+}
+
+/// Decode a Sync event from raw log data
+/// 
+/// Topics layout:
+/// - topics[0]: event signature
+/// 
+/// Data layout (each 32 bytes):
+/// - bytes 0-32: reserve0
+/// - bytes 32-64: reserve1
+pub fn decode(log: &EvmLogs) -> Result<SyncEvent, AppError> {
+    // Ensure data is long enough (2 x 32 bytes = 64 bytes)
+    if log.data.len() < 64 {
+        return Err(AppError::EventDecode(format!(
+            "Sync: expected at least 64 bytes of data, got {}",
+            log.data.len()
+        )));
+    }
+
+    // Pair address is the log emitter
+    let pair = Address20::new(log.address);
+
+    // Extract reserves from data (as hex strings to preserve precision)
+    let reserve0 = format!("0x{}", utils::vec_to_hex(log.data[0..32].to_vec()));
+    let reserve1 = format!("0x{}", utils::vec_to_hex(log.data[32..64].to_vec()));
+
+    let block = log.block_number.to_string();
+
+    Ok(SyncEvent {
+        pair,
+        reserve0,
+        reserve1,
+        block,
+        block_timestamp: log.block_timestamp,
+    })
+}
@@ -3,7 +3,11 @@
 //! Event signature: Transfer(address indexed from, address indexed to, uint256 value)
 //! Topic0: 0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef
 
-use indexer_db::entity::evm_logs::EvmLogs;
+use chrono::{DateTime, Utc};
+use indexer_db::{
+    entity::evm_logs::{EvmLogs, SourceLog},
+    Address20, Hash32,
+};
 use serde::Serialize;
 
 use crate::{error::AppError, utils};
@@ -12,17 +16,23 @@ use crate::{error::AppError, utils};
 #[derive(Debug, Serialize)]
 pub struct TransferEvent {
     /// Token contract address
-    pub token: String,
+    pub token: Address20,
     /// Sender address
-    pub from: String,
+    pub from: Address20,
     /// Recipient address
-    pub to: String,
+    pub to: Address20,
     /// Transfer amount (hex string to preserve precision for large values)
     pub value: String,
     /// Block number
     pub block: String,
     /// Transaction hash
-    pub tx_hash: String,
+    pub tx_hash: Hash32,
+    /// Time of the block, when the listener stamped it
+    #[serde(skip)]
+    pub block_timestamp: Option<DateTime<Utc>>,
+    /// Log the event was decoded from
+    #[serde(skip)]
+    pub source: SourceLog,
 }
 
 /// Decode a Transfer event from raw log data
@@ -52,19 +62,21 @@ pub fn decode(log: &EvmLogs) -> Result<TransferEvent, AppError> {
     }
 
     // Token address is the log emitter
-    let token = format!("0x{}", utils::vec_to_hex(log.address.to_vec()));
+    let token = Address20::new(log.address);
 
     // Extract from address from topics[1]
-    let from = format!("0x{}", utils::vec_to_hex(log.topics[1][12..32].to_vec()));
+    let from = utils::word_to_address(&log.topics[1])
+        .ok_or_else(|| AppError::EventDecode("Transfer: malformed from topic".to_string()))?;
 
     // Extract to address from topics[2]
-    let to = format!("0x{}", utils::vec_to_hex(log.topics[2][12..32].to_vec()));
+    let to = utils::word_to_address(&log.topics[2])
+        .ok_or_else(|| AppError::EventDecode("Transfer: malformed to topic".to_string()))?;
 
     // Extract value from data (as hex string to preserve precision)
     let value = format!("0x{}", utils::vec_to_hex(log.data[0..32].to_vec()));
 
     let block = log.block_number.to_string();
-    let tx_hash = format!("0x{}", utils::vec_to_hex(log.transaction_hash.to_vec()));
+    let tx_hash = Hash32::new(log.transaction_hash);
 
     Ok(TransferEvent {
         token,
@@ -73,6 +85,8 @@ pub fn decode(log: &EvmLogs) -> Result<TransferEvent, AppError> {
         value,
         block,
         tx_hash,
+        block_timestamp: log.block_timestamp,
+        source: log.source(),
     })
 }
 
@@ -0,0 +1,161 @@
+//! Approval event handler
+//!
+//! Approvals of indexed tokens granted to a listed drainer or sweeper contract
+//! are recorded in `risky_approvals`. When the owner is a tracked wallet, the
+//! approval is alerted so its holder can revoke it before the funds are swept.
+
+use std::str::FromStr;
+
+use alloy::primitives::U256;
+use chrono::Utc;
+use sqlx::types::BigDecimal;
+
+use indexer_db::entity::{
+    alert::{AlertType, NewAlert},
+    alert_metadata::{AlertMetadata, RiskyApprovalMetadata},
+    risky_approval::{NewRiskyApproval, RiskyApproval},
+    wallet::Wallet,
+};
+
+use crate::events::approval::ApprovalEvent;
+
+use super::{HandlerContext, HandlerResult};
+
+/// Parse a hex uint256 (0x...); allowances are often max-uint, past u128
+fn hex_to_u256(hex: &str) -> U256 {
+    U256::from_str_radix(hex.trim_start_matches("0x"), 16).unwrap_or_default()
+}
+
+/// Whether an allowance lets the spender take every token the owner has
+///
+/// Wallets approve max-uint (or anything past 2^255) for "infinite"
+/// allowances; an allowance covering the whole supply is just as open.
+pub fn is_unlimited(value: &U256, total_supply: Option<&BigDecimal>) -> bool {
+    if value.bit(255) {
+        return true;
+    }
+    total_supply.is_some_and(|supply| {
+        *supply > BigDecimal::from(0)
+            && BigDecimal::from_str(&value.to_string()).is_ok_and(|value| value >= *supply)
+    })
+}
+
+/// Process an Approval event
+///
+/// 1. Skip other spenders, revocations and tokens we don't index
+/// 2. Record approvals to listed drainers
+/// 3. Alert when the owner is a tracked wallet
+pub async fn handle(ctx: &HandlerContext, event: &ApprovalEvent) -> HandlerResult<()> {
+    let Some(label) = ctx.drainers.get(&event.spender) else {
+        return Ok(());
+    };
+
+    // Setting the allowance to zero revokes it
+    let value = hex_to_u256(&event.value);
+    if value.is_zero() {
+        return Ok(());
+    }
+
+    let Some(token) = ctx.find_token(&event.token).await? else {
+        return Ok(());
+    };
+
+    let block_number = event.block.parse::<i64>().unwrap_or(0);
+    let unlimited = is_unlimited(&value, token.total_supply.as_ref());
+    let amount = BigDecimal::from_str(&value.to_string()).unwrap_or_default();
+
+    let approval = NewRiskyApproval {
+        token_address: event.token,
+        owner_address: event.owner,
+        spender_address: event.spender,
+        spender_label: label.clone(),
+        amount: amount.clone(),
+        unlimited,
+        tx_hash: event.tx_hash,
+        block_number,
+        timestamp: event.block_timestamp.unwrap_or_else(Utc::now),
+    };
+
+    // Already recorded, so already alerted
+    if RiskyApproval::create(&approval, &ctx.db_pool)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    let token_symbol = token.symbol.clone().unwrap_or_else(|| event.token.short());
+    tracing::info!(
+        "Risky approval: {} approved {} ({}) for {} {}",
+        event.owner.short(),
+        label,
+        event.spender.short(),
+        if unlimited {
+            "unlimited".to_string()
+        } else {
+            amount.to_string()
+        },
+        token_symbol
+    );
+
+    if Wallet::find_by_address(&event.owner, &ctx.db_pool)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    let alert = NewAlert {
+        alert_type: AlertType::RiskyApproval.as_str().to_string(),
+        token_address: Some(event.token),
+        token_symbol: Some(token_symbol.clone()),
+        wallet_address: Some(event.owner),
+        title: format!("{} approved a known drainer", event.owner.short()),
+        message: Some(format!(
+            "{} granted {} ({}) {} allowance of {} at block {}. Revoke it before the tokens are swept.",
+            event.owner,
+            label,
+            event.spender,
+            if unlimited { "an unlimited" } else { "an" },
+            token_symbol,
+            block_number
+        )),
+        bee_score: token.bee_score,
+        amount_usd: None,
+        change_percent: None,
+        metadata: Some(AlertMetadata::RiskyApproval(RiskyApprovalMetadata {
+            spender: event.spender,
+            spender_label: label.to_string(),
+            amount: amount.to_string(),
+            unlimited,
+        })),
+        source: Some(event.source),
+    };
+
+    if let Err(e) = ctx.create_alert(&alert).await {
+        tracing::error!("Failed to create risky approval alert: {}", e);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_uint_and_whole_supply_allowances_are_unlimited() {
+        let max = hex_to_u256(&format!("0x{}", "f".repeat(64)));
+        assert_eq!(max, U256::MAX);
+        assert!(is_unlimited(&max, None));
+
+        let supply = BigDecimal::from(1_000_000);
+        assert!(is_unlimited(&U256::from(1_000_000), Some(&supply)));
+        assert!(!is_unlimited(&U256::from(999_999), Some(&supply)));
+        assert!(!is_unlimited(&U256::from(5), None));
+        assert!(!is_unlimited(&U256::from(5), Some(&BigDecimal::from(0))));
+
+        assert!(hex_to_u256("0x").is_zero());
+        assert_eq!(hex_to_u256("0x0a"), U256::from(10));
+    }
+}
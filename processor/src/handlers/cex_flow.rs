@@ -0,0 +1,203 @@
+//! CEX flow detection
+//!
+//! Transfers into or out of known exchange wallets that move a large share of
+//! a token's supply are recorded in `cex_flows` and alerted. A big deposit
+//! ahead of a sell is a classic pre-dump signal.
+
+use std::str::FromStr;
+
+use chrono::Utc;
+use sqlx::types::BigDecimal;
+
+use indexer_db::{
+    entity::{
+        alert::{AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, CexFlowMetadata},
+        cex_flow::{CexFlow, CexFlowDirection, NewCexFlow},
+        known_address::{KnownAddress, KnownAddressKind},
+        token::Token,
+    },
+    Address20,
+};
+
+use crate::{defaults, events::transfer::TransferEvent, known_addresses::KnownAddresses};
+
+use super::{HandlerContext, HandlerResult};
+
+/// Direction and exchange of a transfer, if exactly one side is a CEX wallet
+///
+/// Moves between two exchange wallets are internal and don't count.
+pub fn classify<'a>(
+    known: &'a KnownAddresses,
+    from: &Address20,
+    to: &Address20,
+) -> Option<(CexFlowDirection, &'a KnownAddress)> {
+    let exchange = |address| known.get(address).filter(|k| k.is(KnownAddressKind::Cex));
+
+    match (exchange(from), exchange(to)) {
+        (None, Some(cex)) => Some((CexFlowDirection::Inflow, cex)),
+        (Some(cex), None) => Some((CexFlowDirection::Outflow, cex)),
+        _ => None,
+    }
+}
+
+/// Share (0-100) of `total_supply` moved by `amount`, to 4 decimals
+pub fn supply_percent(amount: &BigDecimal, total_supply: &BigDecimal) -> Option<BigDecimal> {
+    if *total_supply <= BigDecimal::from(0) {
+        return None;
+    }
+
+    let percent = amount * BigDecimal::from(100) / total_supply;
+    Some(percent.min(BigDecimal::from(100)).round(4))
+}
+
+/// Record and alert a transfer to/from an exchange wallet above the threshold
+///
+/// Transfers of tokens without a known supply are never large enough.
+pub async fn detect(
+    ctx: &HandlerContext,
+    token: &Token,
+    event: &TransferEvent,
+    value: &BigDecimal,
+    block_number: i64,
+) -> HandlerResult<()> {
+    let Some((direction, cex)) = classify(&ctx.known, &event.from, &event.to) else {
+        return Ok(());
+    };
+
+    let Some(percent) = token
+        .total_supply
+        .as_ref()
+        .and_then(|supply| supply_percent(value, supply))
+    else {
+        return Ok(());
+    };
+    // The builder only accepts finite thresholds; the documented default
+    // otherwise, never 0, which would alert on every exchange transfer
+    let threshold = BigDecimal::try_from(ctx.cex_flow_threshold_percent)
+        .ok()
+        .or_else(|| BigDecimal::from_str(defaults::CEX_FLOW_THRESHOLD_PERCENT).ok())
+        .unwrap_or_else(|| BigDecimal::from(1));
+    if percent < threshold {
+        return Ok(());
+    }
+
+    let wallet_address = match direction {
+        CexFlowDirection::Inflow => event.from,
+        CexFlowDirection::Outflow => event.to,
+    };
+
+    let flow = NewCexFlow {
+        token_address: event.token,
+        exchange: cex.name.clone(),
+        exchange_address: cex.address,
+        wallet_address,
+        direction,
+        amount: value.clone(),
+        supply_percent: Some(percent.clone()),
+        tx_hash: event.tx_hash,
+        block_number,
+        timestamp: Utc::now(),
+    };
+
+    // Already recorded, so already alerted
+    if CexFlow::create(&flow, &ctx.db_pool).await?.is_none() {
+        return Ok(());
+    }
+
+    let token_symbol = token.symbol.clone().unwrap_or_else(|| event.token.short());
+    let (alert_type, moved) = match direction {
+        CexFlowDirection::Inflow => (AlertType::CexInflow, "moved to"),
+        CexFlowDirection::Outflow => (AlertType::CexOutflow, "withdrawn from"),
+    };
+
+    let alert = NewAlert {
+        alert_type: alert_type.as_str().to_string(),
+        token_address: Some(event.token),
+        token_symbol: Some(token_symbol.clone()),
+        wallet_address: Some(wallet_address),
+        title: format!(
+            "{}% of {} supply {} {}",
+            percent.round(2).normalized(),
+            token_symbol,
+            moved,
+            cex.name
+        ),
+        message: Some(format!(
+            "{} {} tokens {} {} ({}) at block {}",
+            value,
+            token_symbol,
+            moved,
+            cex.name,
+            cex.address.short(),
+            block_number
+        )),
+        bee_score: token.bee_score,
+        amount_usd: None,
+        change_percent: Some(percent.clone()),
+        metadata: Some(AlertMetadata::CexFlow(CexFlowMetadata {
+            exchange: cex.name.clone(),
+            exchange_address: cex.address,
+            direction: direction.as_str().to_string(),
+            supply_percent: percent.to_string(),
+        })),
+        source: Some(event.source),
+    };
+
+    if let Err(e) = ctx.create_alert(&alert).await {
+        tracing::error!("Failed to create CEX flow alert: {}", e);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(kind: KnownAddressKind, name: &str, byte: u8) -> KnownAddress {
+        KnownAddress {
+            id: byte as i32,
+            chain_id: 56,
+            kind: kind.as_str().to_string(),
+            name: name.to_string(),
+            address: Address20::new([byte; 20]),
+        }
+    }
+
+    fn dec(s: &str) -> BigDecimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn only_one_sided_exchange_transfers_are_flows() {
+        let known = KnownAddresses::from_rows(vec![
+            row(KnownAddressKind::Cex, "Binance", 1),
+            row(KnownAddressKind::Cex, "Gate.io", 2),
+            row(KnownAddressKind::Router, "PancakeSwap V2", 3),
+        ]);
+        let wallet = Address20::new([9; 20]);
+        let binance = Address20::new([1; 20]);
+
+        let (direction, cex) = classify(&known, &wallet, &binance).unwrap();
+        assert_eq!(direction, CexFlowDirection::Inflow);
+        assert_eq!(cex.name, "Binance");
+
+        let (direction, cex) = classify(&known, &Address20::new([2; 20]), &wallet).unwrap();
+        assert_eq!(direction, CexFlowDirection::Outflow);
+        assert_eq!(cex.name, "Gate.io");
+
+        assert!(classify(&known, &binance, &Address20::new([2; 20])).is_none());
+        assert!(classify(&known, &wallet, &Address20::new([3; 20])).is_none());
+    }
+
+    #[test]
+    fn supply_percent_of_the_total_supply() {
+        assert_eq!(
+            supply_percent(&dec("20"), &dec("1000")),
+            Some(dec("2.0000"))
+        );
+        assert_eq!(supply_percent(&dec("1"), &dec("3")), Some(dec("33.3333")));
+        assert_eq!(supply_percent(&dec("5"), &dec("0")), None);
+    }
+}
@@ -0,0 +1,105 @@
+//! Mint/Burn event handler
+//!
+//! Adding or removing liquidity changes the pair's LP token supply. The new
+//! totalSupply is read over RPC and cached on the pair, so LP locks can be
+//! expressed as a share of the supply rather than assumed to cover all of it.
+//! The pair's active locks are re-expressed against the new supply and the
+//! token's lock status consolidated again.
+
+use sqlx::types::BigDecimal;
+
+use indexer_db::entity::{lp_lock::LpLock, pair::Pair};
+
+use crate::{error::AppError, events::liquidity::LiquidityEvent};
+
+use super::{lp_lock, HandlerContext, HandlerResult};
+
+/// Share (0-100) of the LP supply covered by `locked_amount`, to 2 decimals
+///
+/// `None` when the supply is unknown or zero. Capped at 100 since a lock
+/// recorded before a burn may exceed what's left.
+pub fn locked_percent(
+    locked_amount: &BigDecimal,
+    lp_total_supply: &BigDecimal,
+) -> Option<BigDecimal> {
+    if *lp_total_supply <= BigDecimal::from(0) {
+        return None;
+    }
+
+    let percent = locked_amount * BigDecimal::from(100) / lp_total_supply;
+    Some(percent.min(BigDecimal::from(100)).round(2))
+}
+
+/// The pair's LP totalSupply, fetched and cached on first use
+pub async fn lp_total_supply(ctx: &HandlerContext, pair: &Pair) -> Option<BigDecimal> {
+    if let Some(supply) = &pair.lp_total_supply {
+        return Some(supply.clone());
+    }
+
+    let supply = ctx.fetch_total_supply(&pair.address).await?;
+    if let Err(e) = Pair::update_lp_total_supply(&pair.address, &supply, &ctx.db_pool).await {
+        tracing::error!("Failed to cache LP supply for {}: {}", pair.address, e);
+    }
+    ctx.entities.invalidate_pair(&pair.address);
+    Some(supply)
+}
+
+/// Process a Mint or Burn event
+///
+/// 1. Look up the pair (`AppError::UnknownPair` if it isn't indexed)
+/// 2. Fetch the LP token totalSupply
+/// 3. Cache it on the pair
+/// 4. Recompute the share of each active lock and the token's lock status
+pub async fn handle(ctx: &HandlerContext, event: &LiquidityEvent) -> HandlerResult<()> {
+    let pair = match ctx.find_pair(&event.pair).await? {
+        Some(pair) => pair,
+        None => return Err(AppError::UnknownPair(event.pair)),
+    };
+
+    let supply = match ctx.fetch_total_supply(&event.pair).await {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    Pair::update_lp_total_supply(&event.pair, &supply, &ctx.db_pool).await?;
+    ctx.entities.invalidate_pair(&event.pair);
+
+    let locks = LpLock::find_by_pair(&event.pair, &ctx.db_pool).await?;
+    if !locks.is_empty() {
+        for lock in &locks {
+            let percent = lock
+                .locked_amount
+                .as_ref()
+                .and_then(|amount| locked_percent(amount, &supply));
+            LpLock::update_locked_percent(lock.id, percent.as_ref(), &ctx.db_pool).await?;
+        }
+        lp_lock::consolidate(ctx, pair.get_token_address()).await?;
+    }
+
+    tracing::debug!(
+        "Processed {:?}: {} LP supply now {}",
+        event.kind, event.pair, supply
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> BigDecimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn locked_percent_is_a_share_of_the_lp_supply() {
+        assert_eq!(
+            locked_percent(&dec("250"), &dec("1000")),
+            Some(dec("25.00"))
+        );
+        assert_eq!(locked_percent(&dec("1"), &dec("3")), Some(dec("33.33")));
+        assert_eq!(locked_percent(&dec("1500"), &dec("1000")), Some(dec("100")));
+        assert_eq!(locked_percent(&dec("10"), &dec("0")), None);
+    }
+}
@@ -1,38 +1,32 @@
 //! LP Lock event handler
 //!
-//! Handles LP lock events from Unicrypt, PinkSale, and Mudra
+//! Handles LP lock events from the chain's registered lockers
+//! (Unicrypt, PinkSale and Mudra on BSC)
 //! to track liquidity lock status for tokens.
 
 use chrono::{TimeZone, Utc};
 use sqlx::types::BigDecimal;
-use std::str::FromStr;
 
-use indexer_db::entity::{
-    alert::{AlertEvent, AlertType, NewAlert},
-    lp_lock::{LpLock, NewLpLock},
-    pair::Pair,
-    token::Token,
+use indexer_db::{
+    entity::{
+        alert::{AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, LpLockMetadata},
+        evm_logs::SourceLog,
+        lp_lock::{LpLock, NewLpLock},
+        token::Token,
+    },
+    Address20, Hash32,
 };
 
-use super::{HandlerContext, HandlerResult};
-
-/// Known LP locker contract addresses on BSC
-pub mod lockers {
-    /// Unicrypt locker
-    pub const UNICRYPT: &str = "0xc765bddb93b0d1c1a88282ba0fa6b2d00e3e0c83";
-    /// PinkSale locker
-    pub const PINKSALE: &str = "0x407993575c91ce7643a4d4ccacc9a98c36ee1bbe";
-    /// Mudra locker
-    pub const MUDRA: &str = "0xae34bd8a0d1153e51a11a59df23598c304dc5abc";
-}
+use super::{liquidity, HandlerContext, HandlerResult};
 
 /// LP Lock event decoded structure
 #[derive(Debug)]
 pub struct LpLockEvent {
     /// LP token (pair) address
-    pub lp_token: String,
+    pub lp_token: Address20,
     /// User who locked
-    pub user: String,
+    pub user: Address20,
     /// Amount locked
     pub amount: String, // hex
     /// Lock timestamp
@@ -42,9 +36,9 @@ pub struct LpLockEvent {
     /// Block number
     pub block: String,
     /// Transaction hash
-    pub tx_hash: String,
+    pub tx_hash: Hash32,
     /// Locker contract address
-    pub locker_address: String,
+    pub locker_address: Address20,
 }
 
 /// Parse a hex string to BigDecimal
@@ -60,18 +54,25 @@ fn hex_to_bigdecimal(hex: &str) -> BigDecimal {
     }
 }
 
-/// Get locker name from address
-fn get_locker_name(address: &str) -> &'static str {
-    let addr_lower = address.to_lowercase();
-    if addr_lower == lockers::UNICRYPT {
-        "unicrypt"
-    } else if addr_lower == lockers::PINKSALE {
-        "pinksale"
-    } else if addr_lower == lockers::MUDRA {
-        "mudra"
-    } else {
-        "unknown"
-    }
+/// Roll every active lock of a token up into its lock status
+///
+/// A token may be partially locked on several lockers: the percentages add
+/// up (capped at 100) and the token unlocks when the first lock expires.
+pub async fn consolidate(ctx: &HandlerContext, token_address: &Address20) -> HandlerResult<()> {
+    let total = LpLock::total_locked_percent(token_address, &ctx.db_pool).await?;
+    let earliest_unlock = LpLock::earliest_unlock(token_address, &ctx.db_pool).await?;
+
+    Token::update_lp_lock(
+        token_address,
+        earliest_unlock.is_some(),
+        Some(&total.min(BigDecimal::from(100))),
+        earliest_unlock,
+        &ctx.db_pool,
+    )
+    .await?;
+    ctx.entities.invalidate_token(token_address);
+
+    Ok(())
 }
 
 /// Process an LP Lock event
@@ -79,20 +80,20 @@ fn get_locker_name(address: &str) -> &'static str {
 /// 1. Look up the pair from LP token address
 /// 2. Find the associated token
 /// 3. Create LP lock record
-/// 4. Update token's lock status
+/// 4. Consolidate the token's lock status across all active locks
 /// 5. Create alert
 pub async fn handle(ctx: &HandlerContext, event: &LpLockEvent) -> HandlerResult<()> {
     // Look up the pair (LP token is the pair address)
-    let pair = match Pair::find_by_address(&event.lp_token, &ctx.db_pool).await? {
+    let pair = match ctx.find_pair(&event.lp_token).await? {
         Some(p) => p,
         None => {
-            println!("Unknown LP token for lock: {}", event.lp_token);
+            tracing::debug!("Unknown LP token for lock: {}", event.lp_token);
             return Ok(());
         }
     };
 
     // Get the memecoin address from the pair
-    let token_address = pair.get_token_address().to_string();
+    let token_address = *pair.get_token_address();
 
     // Parse amounts and dates
     let locked_amount = hex_to_bigdecimal(&event.amount);
@@ -104,65 +105,59 @@ pub async fn handle(ctx: &HandlerContext, event: &LpLockEvent) -> HandlerResult<
         .unwrap_or_else(Utc::now);
     let block_number = event.block.parse::<i64>().unwrap_or(0);
 
-    // Calculate locked percent (would need total LP supply)
-    // For now, use a placeholder - in production, query pair.totalSupply()
-    let locked_percent = BigDecimal::from(100); // Placeholder
+    // Share of the LP supply, unknown if the supply can't be read
+    let locked_percent = match liquidity::lp_total_supply(ctx, &pair).await {
+        Some(supply) => liquidity::locked_percent(&locked_amount, &supply),
+        None => None,
+    };
 
-    let locker_name = get_locker_name(&event.locker_address);
+    let locker_name = ctx.chain.locker_name(&event.locker_address).unwrap_or("unknown");
 
     // Create LP lock record
     let new_lock = NewLpLock {
-        token_address: token_address.clone(),
-        pair_address: event.lp_token.clone(),
-        lock_contract: event.locker_address.clone(),
+        token_address,
+        pair_address: event.lp_token,
+        lock_contract: event.locker_address,
         lock_contract_name: locker_name.to_string(),
         locked_amount: locked_amount.clone(),
         locked_percent: locked_percent.clone(),
         lock_date,
         unlock_date,
-        tx_hash: event.tx_hash.clone(),
+        tx_hash: event.tx_hash,
         block_number,
     };
 
     match LpLock::create(&new_lock, &ctx.db_pool).await {
         Ok(lock) => {
-            println!(
+            tracing::info!(
                 "Created LP lock: id={}, token={}, locker={}",
                 lock.id, token_address, locker_name
             );
         }
         Err(e) => {
-            eprintln!("Failed to create LP lock: {}", e);
+            tracing::error!("Failed to create LP lock: {}", e);
         }
     }
 
-    // Update token's LP lock status
-    if let Err(e) = Token::update_lp_lock(
-        &token_address,
-        true,
-        &locked_percent,
-        Some(unlock_date),
-        &ctx.db_pool,
-    )
-    .await
-    {
-        eprintln!("Failed to update token LP lock: {}", e);
+    // Update token's LP lock status from all of its locks
+    if let Err(e) = consolidate(ctx, &token_address).await {
+        tracing::error!("Failed to update token LP lock: {}", e);
     }
 
     // Get token info for alert
-    let token = Token::find_by_address(&token_address, &ctx.db_pool).await?;
+    let token = ctx.find_token(&token_address).await?;
     let token_symbol = token
         .as_ref()
         .and_then(|t| t.symbol.clone())
-        .unwrap_or_else(|| token_address[..10].to_string());
+        .unwrap_or_else(|| token_address.short());
 
     // Create alert
     let days_locked = (unlock_date - lock_date).num_days();
     let alert = NewAlert {
         alert_type: AlertType::LpLocked.as_str().to_string(),
-        token_address: Some(token_address.clone()),
+        token_address: Some(token_address),
         token_symbol: Some(token_symbol.clone()),
-        wallet_address: Some(event.user.clone()),
+        wallet_address: Some(event.user),
         title: format!("LP Locked: {} ({} days)", token_symbol, days_locked),
         message: Some(format!(
             "LP tokens for {} locked on {} until {}",
@@ -172,26 +167,28 @@ pub async fn handle(ctx: &HandlerContext, event: &LpLockEvent) -> HandlerResult<
         )),
         bee_score: token.as_ref().and_then(|t| t.bee_score),
         amount_usd: None,
-        change_percent: Some(locked_percent.clone()),
-        metadata: None,
+        change_percent: locked_percent.clone(),
+        metadata: Some(AlertMetadata::LpLock(LpLockMetadata {
+            locker: locker_name.to_string(),
+            lock_contract: event.locker_address,
+            lp_token: event.lp_token,
+            unlock_date,
+            locked_percent: locked_percent.as_ref().map(|p| p.to_string()),
+        })),
+        source: Some(SourceLog {
+            log_id: None,
+            tx_hash: event.tx_hash,
+        }),
     };
 
-    if let Err(e) = AlertEvent::create(&alert, &ctx.db_pool).await {
-        eprintln!("Failed to create LP lock alert: {}", e);
+    if let Err(e) = ctx.create_alert(&alert).await {
+        tracing::error!("Failed to create LP lock alert: {}", e);
     }
 
-    println!(
+    tracing::debug!(
         "Processed LP Lock: {} locked for {} days ({})",
         token_symbol, days_locked, locker_name
     );
 
     Ok(())
 }
-
-/// Check if an address is a known LP locker
-pub fn is_locker_contract(address: &str) -> bool {
-    let addr_lower = address.to_lowercase();
-    addr_lower == lockers::UNICRYPT
-        || addr_lower == lockers::PINKSALE
-        || addr_lower == lockers::MUDRA
-}
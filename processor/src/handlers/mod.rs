@@ -3,19 +3,47 @@
 //! Handlers process decoded events and persist them to the database,
 //! including business logic for token tracking, whale detection, etc.
 
+pub mod approval;
+pub mod cex_flow;
+pub mod liquidity;
 pub mod pair_created;
 pub mod swap;
 pub mod sync;
 pub mod transfer;
 pub mod lp_lock;
 
-use alloy::primitives::Address;
-use alloy::providers::{Provider, ProviderBuilder};
+use alloy::consensus::Transaction as _;
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
+use alloy::rpc::types::BlockTransactionsKind;
 use alloy::sol;
-use sqlx::{Pool, Postgres};
-use std::str::FromStr;
+use indexer_db::{
+    entity::{
+        alert::{AlertEvent, NewAlert},
+        pair::Pair,
+        token::Token,
+    },
+    Address20, Hash32,
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use crate::error::AppError;
+use crate::{
+    bots::TxGas,
+    chain::ChainConstants,
+    defaults,
+    entity_cache::EntityCache,
+    error::AppError,
+    known_addresses::KnownAddresses,
+    rpc::{Rpc, RpcBudget},
+    sampling::SampleCache,
+    sanitize::{sanitize, MAX_NAME_CHARS, MAX_SYMBOL_CHARS},
+};
 
 // Define ERC20 ABI for metadata calls
 sol! {
@@ -31,131 +59,630 @@ sol! {
 /// Token metadata fetched from blockchain
 #[derive(Debug, Clone, Default)]
 pub struct TokenMetadata {
+    /// Sanitized name and symbol, safe to display
     pub name: Option<String>,
     pub symbol: Option<String>,
+    /// Name and symbol exactly as returned
+    pub name_raw: Option<String>,
+    pub symbol_raw: Option<String>,
+    /// The raw name or symbol hid invisible characters between visible ones
+    pub name_spoofed: bool,
     pub decimals: Option<i16>,
     pub total_supply: Option<String>,
 }
 
+impl TokenMetadata {
+    /// Keep a name read from the chain, sanitized for display
+    pub fn set_name(&mut self, name: String) {
+        if !name.is_empty() {
+            let clean = sanitize(&name, MAX_NAME_CHARS);
+            self.name = clean.text;
+            self.name_spoofed |= clean.spoofed;
+            self.name_raw = Some(name);
+        }
+    }
+
+    /// Keep a symbol read from the chain, sanitized for display
+    pub fn set_symbol(&mut self, symbol: String) {
+        if !symbol.is_empty() {
+            let clean = sanitize(&symbol, MAX_SYMBOL_CHARS);
+            self.symbol = clean.text;
+            self.name_spoofed |= clean.spoofed;
+            self.symbol_raw = Some(symbol);
+        }
+    }
+}
+
+/// Average BSC block time, used to turn a sniper window in seconds into blocks
+const BSC_BLOCK_TIME_SECS: u64 = 3;
+
+/// RPC calls a token metadata fetch makes: name, symbol, decimals and supply
+const METADATA_CALLS: u32 = 4;
+
+/// How long after pair creation a buy still counts as sniping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SniperWindow {
+    pub blocks: i64,
+}
+
+impl SniperWindow {
+    pub fn from_blocks(blocks: u64) -> Self {
+        Self {
+            blocks: blocks as i64,
+        }
+    }
+
+    /// Window covering at least `seconds` of blocks after creation
+    pub fn from_seconds(seconds: u64) -> Self {
+        Self::from_blocks(seconds.div_ceil(BSC_BLOCK_TIME_SECS))
+    }
+
+    /// Whether `block` falls within the window opened at `creation_block`
+    pub fn contains(&self, creation_block: i64, block: i64) -> bool {
+        block >= creation_block && block <= creation_block + self.blocks
+    }
+}
+
+/// Historical logs being replayed by a token rescan, rather than live ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Replay {
+    /// Apply Transfer balance changes to holders. Off when the replayed range
+    /// starts after the token's creation, where balances would count twice.
+    pub holders: bool,
+}
+
 /// Context passed to handlers containing database pool and config
 pub struct HandlerContext {
     pub db_pool: Pool<Postgres>,
-    pub wbnb_address: String,
-    pub busd_address: String,
+    pub chain: ChainConstants,
+    /// Exchange, router, bridge, locker and burn addresses
+    pub known: KnownAddresses,
     pub bnb_price_usd: f64,
     pub whale_threshold_usd: f64,
-    pub rpc_url: String,
+    /// Share (%) of supply a CEX transfer must move to be recorded and alerted
+    pub cex_flow_threshold_percent: f64,
+    pub sniper_window: SniperWindow,
+    /// Share (0-1) of the traction score taken from social metrics; 0 disables it
+    pub social_traction_weight: f64,
+    /// Shared provider, failing over between the configured endpoints
+    pub rpc: Arc<Rpc>,
+    /// Calls handlers may still make before they have to wait or defer
+    pub rpc_budget: Arc<RpcBudget>,
+    /// Recently read pairs and tokens; drop an entry after writing its row
+    pub entities: Arc<EntityCache>,
+    /// Bounds past which Sync prices are quarantined rather than charted
+    pub snapshot_bounds: sync::SnapshotBounds,
+    /// Listed drainer and sweeper contracts, with their labels
+    pub drainers: HashMap<Address20, String>,
+    /// Only handle Transfers of the tokens in `sampled_tokens`
+    pub transfer_sampling: bool,
+    /// The tokens in `sampled_tokens`, when sampling
+    pub sample: Arc<SampleCache>,
+    /// Set while a rescan replays logs; alerts are not raised for them
+    pub replay: Option<Replay>,
+    /// Reserves of the last Sync replayed for each pair, which replayed
+    /// Syncs are checked against instead of the pair's current reserves
+    replay_reserves: Mutex<HashMap<Address20, (BigDecimal, BigDecimal)>>,
+}
+
+/// Builds a [`HandlerContext`], refusing configuration handlers can't run with
+pub struct HandlerContextBuilder {
+    db_pool: Pool<Postgres>,
+    chain: ChainConstants,
+    known: KnownAddresses,
+    bnb_price_usd: f64,
+    whale_threshold_usd: f64,
+    cex_flow_threshold_percent: f64,
+    sniper_window: SniperWindow,
+    social_traction_weight: f64,
+    rpc: Option<Arc<Rpc>>,
+    rpc_budget: Arc<RpcBudget>,
+    entities: Arc<EntityCache>,
+    snapshot_bounds: sync::SnapshotBounds,
+    drainers: HashMap<Address20, String>,
+    transfer_sampling: bool,
+    sample: Arc<SampleCache>,
+}
+
+impl HandlerContextBuilder {
+    pub fn bnb_price_usd(mut self, bnb_price_usd: f64) -> Self {
+        self.bnb_price_usd = bnb_price_usd;
+        self
+    }
+
+    pub fn whale_threshold_usd(mut self, whale_threshold_usd: f64) -> Self {
+        self.whale_threshold_usd = whale_threshold_usd;
+        self
+    }
+
+    pub fn cex_flow_threshold_percent(mut self, cex_flow_threshold_percent: f64) -> Self {
+        self.cex_flow_threshold_percent = cex_flow_threshold_percent;
+        self
+    }
+
+    pub fn sniper_window(mut self, sniper_window: SniperWindow) -> Self {
+        self.sniper_window = sniper_window;
+        self
+    }
+
+    pub fn social_traction_weight(mut self, social_traction_weight: f64) -> Self {
+        self.social_traction_weight = social_traction_weight;
+        self
+    }
+
+    pub fn rpc(mut self, rpc: Arc<Rpc>) -> Self {
+        self.rpc = Some(rpc);
+        self
+    }
+
+    pub fn rpc_budget(mut self, rpc_budget: Arc<RpcBudget>) -> Self {
+        self.rpc_budget = rpc_budget;
+        self
+    }
+
+    pub fn entities(mut self, entities: Arc<EntityCache>) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    pub fn snapshot_bounds(mut self, snapshot_bounds: sync::SnapshotBounds) -> Self {
+        self.snapshot_bounds = snapshot_bounds;
+        self
+    }
+
+    pub fn drainers(mut self, drainers: HashMap<Address20, String>) -> Self {
+        self.drainers = drainers;
+        self
+    }
+
+    pub fn transfer_sampling(mut self, transfer_sampling: bool) -> Self {
+        self.transfer_sampling = transfer_sampling;
+        self
+    }
+
+    pub fn sample(mut self, sample: Arc<SampleCache>) -> Self {
+        self.sample = sample;
+        self
+    }
+
+    /// Check the configuration and build the context
+    ///
+    /// An RPC endpoint is required, the BNB price and the whale and CEX flow
+    /// thresholds must be positive, and the CEX flow share at most 100%.
+    pub fn build(self) -> Result<HandlerContext, AppError> {
+        let rpc = self.rpc.ok_or_else(|| {
+            AppError::InvalidConfig("no RPC endpoint configured, set `RPC_URL`".to_string())
+        })?;
+        for (var, value) in [
+            ("BNB_PRICE_USD", self.bnb_price_usd),
+            ("WHALE_THRESHOLD_USD", self.whale_threshold_usd),
+            (
+                "CEX_FLOW_THRESHOLD_PERCENT",
+                self.cex_flow_threshold_percent,
+            ),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(AppError::InvalidConfig(format!(
+                    "`{}` must be positive, got {}",
+                    var, value
+                )));
+            }
+        }
+        if self.cex_flow_threshold_percent > 100.0 {
+            return Err(AppError::InvalidConfig(format!(
+                "`CEX_FLOW_THRESHOLD_PERCENT` is a share of supply, at most 100, got {}",
+                self.cex_flow_threshold_percent
+            )));
+        }
+
+        Ok(HandlerContext {
+            db_pool: self.db_pool,
+            chain: self.chain,
+            known: self.known,
+            bnb_price_usd: self.bnb_price_usd,
+            whale_threshold_usd: self.whale_threshold_usd,
+            cex_flow_threshold_percent: self.cex_flow_threshold_percent,
+            sniper_window: self.sniper_window,
+            social_traction_weight: self.social_traction_weight.clamp(0.0, 1.0),
+            rpc,
+            rpc_budget: self.rpc_budget,
+            entities: self.entities,
+            snapshot_bounds: self.snapshot_bounds,
+            drainers: self.drainers,
+            transfer_sampling: self.transfer_sampling,
+            sample: self.sample,
+            replay: None,
+            replay_reserves: Mutex::default(),
+        })
+    }
 }
 
 impl HandlerContext {
-    pub fn new(
+    /// Start building a context for a chain; everything else has the
+    /// processor's defaults except the RPC endpoint, which must be set. RPC
+    /// calls are unlimited unless a budget is set, and pairs, tokens and the
+    /// sample are read from the database each time unless a cache is set.
+    pub fn builder(
         db_pool: Pool<Postgres>,
-        wbnb_address: String,
-        busd_address: String,
-        bnb_price_usd: f64,
-        whale_threshold_usd: f64,
-        rpc_url: String,
-    ) -> Self {
-        Self {
+        chain: ChainConstants,
+        known: KnownAddresses,
+    ) -> HandlerContextBuilder {
+        HandlerContextBuilder {
             db_pool,
-            wbnb_address,
-            busd_address,
-            bnb_price_usd,
-            whale_threshold_usd,
-            rpc_url,
+            chain,
+            known,
+            bnb_price_usd: defaults::BNB_PRICE_USD
+                .parse()
+                .expect("default BNB_PRICE_USD is valid"),
+            whale_threshold_usd: defaults::WHALE_THRESHOLD_USD
+                .parse()
+                .expect("default WHALE_THRESHOLD_USD is valid"),
+            cex_flow_threshold_percent: defaults::CEX_FLOW_THRESHOLD_PERCENT
+                .parse()
+                .expect("default CEX_FLOW_THRESHOLD_PERCENT is valid"),
+            sniper_window: SniperWindow::from_blocks(
+                defaults::SNIPER_WINDOW_BLOCKS
+                    .parse()
+                    .expect("default SNIPER_WINDOW_BLOCKS is valid"),
+            ),
+            social_traction_weight: defaults::SOCIAL_TRACTION_WEIGHT
+                .parse()
+                .expect("default SOCIAL_TRACTION_WEIGHT is valid"),
+            rpc: None,
+            rpc_budget: Arc::new(RpcBudget::new(0, 1)),
+            entities: Arc::new(EntityCache::disabled()),
+            snapshot_bounds: sync::SnapshotBounds::default(),
+            drainers: HashMap::new(),
+            transfer_sampling: false,
+            sample: Arc::new(SampleCache::new(Duration::ZERO)),
         }
     }
 
-    /// Check if address is WBNB
-    pub fn is_wbnb(&self, address: &str) -> bool {
-        address.to_lowercase() == self.wbnb_address.to_lowercase()
+    /// Whether Transfer logs should move holder balances
+    pub fn tracks_holders(&self) -> bool {
+        self.replay.is_none_or(|r| r.holders)
     }
 
-    /// Check if address is BUSD
-    pub fn is_busd(&self, address: &str) -> bool {
-        address.to_lowercase() == self.busd_address.to_lowercase()
+    /// Reserves of the Sync replayed before this one on `pair`, zero for the
+    /// first, keeping `reserves` for the next
+    pub fn replayed_reserves(
+        &self,
+        pair: &Address20,
+        reserves: (BigDecimal, BigDecimal),
+    ) -> (BigDecimal, BigDecimal) {
+        let mut replayed = self
+            .replay_reserves
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        replayed
+            .insert(*pair, reserves)
+            .unwrap_or_else(|| (BigDecimal::from(0), BigDecimal::from(0)))
     }
 
-    /// Check if address is a base token (WBNB or BUSD)
-    pub fn is_base_token(&self, address: &str) -> bool {
-        self.is_wbnb(address) || self.is_busd(address)
+    /// Raise an alert, unless replaying history
+    pub async fn create_alert(&self, alert: &NewAlert) -> Result<(), sqlx::Error> {
+        if self.replay.is_none() {
+            AlertEvent::create(alert, &self.db_pool).await?;
+        }
+        Ok(())
     }
 
-    /// Fetch ERC20 token metadata from the blockchain
-    pub async fn fetch_token_metadata(&self, token_address: &str) -> TokenMetadata {
-        let mut metadata = TokenMetadata::default();
+    /// Bring a token archived as dead back, holders included, now that it
+    /// has activity again
+    pub async fn revive(&self, token: &Token) {
+        if token.archived_at.is_none() {
+            return;
+        }
+        match self.unarchive(&token.address).await {
+            Ok(true) => {
+                self.entities.invalidate_token(&token.address);
+                tracing::info!("Revived archived token {}", token.address)
+            }
+            Ok(false) => {}
+            Err(e) => tracing::error!("Failed to revive archived token {}: {}", token.address, e),
+        }
+    }
+
+    async fn unarchive(&self, address: &Address20) -> Result<bool, sqlx::Error> {
+        let mut tx = self.db_pool.begin().await?;
+        let archived = Token::find_for_update(address, &mut *tx)
+            .await?
+            .is_some_and(|t| t.archived_at.is_some());
+        let revived = archived && Token::unarchive(address, &mut *tx).await?;
+        tx.commit().await?;
+        Ok(revived)
+    }
+
+    /// Get a pair, cached for a few seconds
+    pub async fn find_pair(&self, address: &Address20) -> Result<Option<Pair>, sqlx::Error> {
+        self.entities.pair(address, &self.db_pool).await
+    }
+
+    /// Get a token, cached for a few seconds
+    pub async fn find_token(&self, address: &Address20) -> Result<Option<Token>, sqlx::Error> {
+        self.entities.token(address, &self.db_pool).await
+    }
 
-        // Parse the address
-        let address = match Address::from_str(token_address) {
-            Ok(addr) => addr,
+    /// A token's decimals, read from the chain and stored when the token
+    /// doesn't have them yet. Amounts are never scaled by a guess, so a
+    /// token whose decimals can't be read is `AppError::UnknownDecimals`
+    /// and its log is parked until they can.
+    pub async fn token_decimals(
+        &self,
+        address: &Address20,
+        token: Option<&Token>,
+    ) -> Result<i16, AppError> {
+        if let Some(decimals) = token.and_then(|t| t.decimals) {
+            return Ok(decimals);
+        }
+
+        self.rpc_budget.take(1).await;
+        let contract = (*address).into();
+        let decimals = match self
+            .rpc
+            .call(|p| async move { IERC20Metadata::new(contract, &p).decimals().call().await })
+            .await
+        {
+            Ok(result) => result._0 as i16,
             Err(e) => {
-                eprintln!("Invalid token address {}: {}", token_address, e);
-                return metadata;
+                tracing::warn!("Failed to fetch decimals for {}: {}", address, e);
+                return Err(AppError::UnknownDecimals(*address));
             }
         };
 
-        // Create provider
-        let provider = match ProviderBuilder::new().on_http(self.rpc_url.parse().unwrap()) {
-            provider => provider,
-        };
+        let stored = Token::set_decimals(address, decimals, &self.db_pool).await?;
+        self.entities.invalidate_token(address);
+        Ok(stored.unwrap_or(decimals))
+    }
+
+    /// Check if address is a base token (the wrapped native token or a stablecoin)
+    pub fn is_base_token(&self, address: &Address20) -> bool {
+        self.chain.is_wrapped_native(address) || self.chain.is_stablecoin(address)
+    }
+
+    /// Fetch ERC20 token metadata, unless the RPC budget is spent. The token
+    /// is then left for the metadata repair job.
+    pub async fn try_fetch_token_metadata(
+        &self,
+        token_address: &Address20,
+    ) -> Option<TokenMetadata> {
+        if !self.rpc_budget.try_take(METADATA_CALLS) {
+            return None;
+        }
+        Some(self.read_token_metadata(token_address).await)
+    }
+
+    /// Fetch ERC20 token metadata from the blockchain
+    pub async fn fetch_token_metadata(&self, token_address: &Address20) -> TokenMetadata {
+        self.rpc_budget.take(METADATA_CALLS).await;
+        self.read_token_metadata(token_address).await
+    }
 
-        // Create contract instance
-        let contract = IERC20Metadata::new(address, &provider);
+    async fn read_token_metadata(&self, token_address: &Address20) -> TokenMetadata {
+        let mut metadata = TokenMetadata::default();
+        let address = (*token_address).into();
 
         // Fetch name
-        match contract.name().call().await {
-            Ok(result) => {
-                let name = result._0;
-                if !name.is_empty() {
-                    metadata.name = Some(name);
-                }
-            }
+        match self
+            .rpc
+            .call(|p| async move { IERC20Metadata::new(address, &p).name().call().await })
+            .await
+        {
+            Ok(result) => metadata.set_name(result._0),
             Err(e) => {
-                eprintln!("Failed to fetch name for {}: {}", token_address, e);
+                tracing::error!("Failed to fetch name for {}: {}", token_address, e);
             }
         }
 
         // Fetch symbol
-        match contract.symbol().call().await {
-            Ok(result) => {
-                let symbol = result._0;
-                if !symbol.is_empty() {
-                    metadata.symbol = Some(symbol);
-                }
-            }
+        match self
+            .rpc
+            .call(|p| async move { IERC20Metadata::new(address, &p).symbol().call().await })
+            .await
+        {
+            Ok(result) => metadata.set_symbol(result._0),
             Err(e) => {
-                eprintln!("Failed to fetch symbol for {}: {}", token_address, e);
+                tracing::error!("Failed to fetch symbol for {}: {}", token_address, e);
             }
         }
 
         // Fetch decimals
-        match contract.decimals().call().await {
+        match self
+            .rpc
+            .call(|p| async move { IERC20Metadata::new(address, &p).decimals().call().await })
+            .await
+        {
             Ok(result) => {
                 metadata.decimals = Some(result._0 as i16);
             }
             Err(e) => {
-                eprintln!("Failed to fetch decimals for {}: {}", token_address, e);
+                tracing::error!("Failed to fetch decimals for {}: {}", token_address, e);
             }
         }
 
         // Fetch total supply
-        match contract.totalSupply().call().await {
+        match self
+            .rpc
+            .call(|p| async move { IERC20Metadata::new(address, &p).totalSupply().call().await })
+            .await
+        {
             Ok(result) => {
                 metadata.total_supply = Some(result._0.to_string());
             }
             Err(e) => {
-                eprintln!("Failed to fetch totalSupply for {}: {}", token_address, e);
+                tracing::error!("Failed to fetch totalSupply for {}: {}", token_address, e);
             }
         }
 
-        println!(
+        tracing::info!(
             "Fetched metadata for {}: name={:?}, symbol={:?}, decimals={:?}",
             token_address, metadata.name, metadata.symbol, metadata.decimals
         );
 
         metadata
     }
+
+    /// Fetch an ERC20 (or LP token) totalSupply from the blockchain
+    pub async fn fetch_total_supply(&self, token_address: &Address20) -> Option<BigDecimal> {
+        let address = (*token_address).into();
+        self.rpc_budget.take(1).await;
+
+        match self
+            .rpc
+            .call(|p| async move { IERC20Metadata::new(address, &p).totalSupply().call().await })
+            .await
+        {
+            Ok(result) => BigDecimal::from_str(&result._0.to_string()).ok(),
+            Err(e) => {
+                tracing::error!("Failed to fetch totalSupply for {}: {}", token_address, e);
+                None
+            }
+        }
+    }
+
+    /// Fetch the gas price paid by each transaction of a block, in block order
+    pub async fn fetch_block_gas_prices(&self, block_number: u64) -> Option<Vec<u128>> {
+        self.rpc_budget.take(1).await;
+        let block = match self
+            .rpc
+            .call(|p| async move {
+                p.get_block_by_number(
+                    BlockNumberOrTag::Number(block_number),
+                    BlockTransactionsKind::Full,
+                )
+                .await
+            })
+            .await
+        {
+            Ok(Some(block)) => block,
+            Ok(None) => return None,
+            Err(e) => {
+                tracing::error!("Failed to fetch block {}: {}", block_number, e);
+                return None;
+            }
+        };
+
+        Some(
+            block
+                .transactions
+                .txns()
+                .map(|tx| {
+                    tx.effective_gas_price
+                        .or_else(|| tx.gas_price())
+                        .unwrap_or_else(|| tx.max_fee_per_gas())
+                })
+                .collect(),
+        )
+    }
+
+    /// Fetch the gas limit, gas used and target of a transaction, unless the
+    /// RPC budget is spent
+    pub async fn try_fetch_tx_gas(&self, tx_hash: &Hash32) -> Option<TxGas> {
+        if !self.rpc_budget.try_take(2) {
+            return None;
+        }
+        let hash = (*tx_hash).into();
+
+        let tx = match self
+            .rpc
+            .call(|p| async move { p.get_transaction_by_hash(hash).await })
+            .await
+        {
+            Ok(Some(tx)) => tx,
+            Ok(None) => return None,
+            Err(e) => {
+                tracing::error!("Failed to fetch transaction {}: {}", tx_hash, e);
+                return None;
+            }
+        };
+        let receipt = match self
+            .rpc
+            .call(|p| async move { p.get_transaction_receipt(hash).await })
+            .await
+        {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) => return None,
+            Err(e) => {
+                tracing::error!("Failed to fetch receipt of {}: {}", tx_hash, e);
+                return None;
+            }
+        };
+
+        Some(TxGas {
+            gas_limit: tx.gas_limit(),
+            gas_used: receipt.gas_used as u64,
+            to: tx.to().map(Address20::from),
+        })
+    }
 }
 
 /// Result type for handlers
 pub type HandlerResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniper_window_spans_blocks_after_creation() {
+        let window = SniperWindow::from_blocks(2);
+        assert!(window.contains(100, 100));
+        assert!(window.contains(100, 102));
+        assert!(!window.contains(100, 103));
+        assert!(!window.contains(100, 99));
+    }
+
+    #[test]
+    fn sniper_window_rounds_seconds_up_to_whole_blocks() {
+        assert_eq!(SniperWindow::from_seconds(9).blocks, 3);
+        assert_eq!(SniperWindow::from_seconds(10).blocks, 4);
+        assert_eq!(SniperWindow::from_seconds(0).blocks, 0);
+    }
+
+    #[tokio::test]
+    async fn builder_refuses_missing_rpc_and_non_positive_thresholds() {
+        let builder = || {
+            let db_pool = sqlx::PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+            let chain = ChainConstants {
+                chain_id: 56,
+                wrapped_native: Address20::new([1; 20]),
+                stablecoins: Vec::new(),
+                factories: Vec::new(),
+                routers: Vec::new(),
+                lockers: Vec::new(),
+                price_pools: Vec::new(),
+            };
+            HandlerContext::builder(db_pool, chain, KnownAddresses::default())
+        };
+        let rpc = Arc::new(Rpc::new(&["https://bsc-dataseed.binance.org"]));
+
+        let error = builder().build().err().unwrap();
+        assert!(error.to_string().contains("RPC_URL"));
+
+        let error = builder()
+            .rpc(rpc.clone())
+            .whale_threshold_usd(0.0)
+            .build()
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("WHALE_THRESHOLD_USD"));
+        assert!(builder()
+            .rpc(rpc.clone())
+            .cex_flow_threshold_percent(150.0)
+            .build()
+            .is_err());
+
+        let ctx = builder()
+            .rpc(rpc)
+            .whale_threshold_usd(10_000.0)
+            .social_traction_weight(3.0)
+            .build()
+            .unwrap();
+        assert_eq!(ctx.whale_threshold_usd, 10_000.0);
+        assert_eq!(ctx.social_traction_weight, 1.0);
+        assert!(ctx.replay.is_none());
+        // The rest keeps the processor's defaults
+        assert_eq!(ctx.bnb_price_usd, 600.0);
+        assert_eq!(ctx.sniper_window, SniperWindow::from_blocks(2));
+    }
+}
@@ -3,21 +3,23 @@
 //! Handles new token pair creation from PancakeSwap Factory.
 //! - Identifies which token is the new memecoin (vs WBNB/BUSD)
 //! - Creates token and pair records in database
-//! - Fetches token metadata (name, symbol, decimals) from blockchain
+//! - Fetches token metadata (name, symbol, decimals) from blockchain, or
+//!   leaves it to the metadata repair job when the RPC budget is spent
 //! - Creates alert for new token launch
 
 use sqlx::types::BigDecimal;
 use std::str::FromStr;
 
 use indexer_db::entity::{
-    alert::{AlertEvent, NewAlert, AlertType},
+    alert::{NewAlert, AlertType},
+    alert_metadata::{AlertMetadata, NewTokenMetadata},
     pair::{NewPair, Pair},
     token::{NewToken, Token},
 };
 
-use crate::events::pair_created::PairCreatedEvent;
+use crate::{events::pair_created::PairCreatedEvent, impersonation};
 
-use super::{HandlerContext, HandlerResult};
+use super::{HandlerContext, HandlerResult, TokenMetadata};
 
 /// Process a PairCreated event
 ///
@@ -27,7 +29,7 @@ use super::{HandlerContext, HandlerResult};
 /// 4. Create a new token record (or update if exists)
 /// 5. Create an alert for the new token launch
 pub async fn handle(ctx: &HandlerContext, event: &PairCreatedEvent) -> HandlerResult<()> {
-    println!(
+    tracing::debug!(
         "Processing PairCreated: pair={}, token0={}, token1={}",
         event.pair, event.token0, event.token1
     );
@@ -39,7 +41,7 @@ pub async fn handle(ctx: &HandlerContext, event: &PairCreatedEvent) -> HandlerRe
         (&event.token1, &event.token0, 1i16)
     } else {
         // Neither token is a base token - this is a token/token pair, skip for MVP
-        println!(
+        tracing::debug!(
             "Skipping non-base pair: {} / {} (no WBNB/BUSD)",
             event.token0, event.token1
         );
@@ -50,46 +52,62 @@ pub async fn handle(ctx: &HandlerContext, event: &PairCreatedEvent) -> HandlerRe
 
     // Create the pair record
     let new_pair = NewPair {
-        address: event.pair.clone(),
-        token0_address: event.token0.clone(),
-        token1_address: event.token1.clone(),
-        factory_address: event.factory.clone(),
+        address: event.pair,
+        token0_address: event.token0,
+        token1_address: event.token1,
+        factory_address: event.factory,
         base_token_index: base_index,
         block_number,
+        discovered: false,
     };
 
     match Pair::create(&new_pair, &ctx.db_pool).await {
         Ok(pair) => {
-            println!("Created pair: {} (id={})", pair.address, pair.id);
+            tracing::debug!("Created pair: {} (id={})", pair.address, pair.id);
         }
         Err(e) => {
             // Pair might already exist (idempotent)
-            println!("Pair create result: {}", e);
+            tracing::debug!("Pair create result: {}", e);
         }
     }
 
-    // Fetch token metadata from blockchain
-    println!("Fetching metadata for token: {}", new_token);
-    let metadata = ctx.fetch_token_metadata(new_token).await;
+    // Fetch token metadata from blockchain; during a launch storm the repair
+    // job picks it up later, pairs that start trading first
+    tracing::debug!("Fetching metadata for token: {}", new_token);
+    let metadata = match ctx.try_fetch_token_metadata(new_token).await {
+        Some(metadata) => metadata,
+        None => {
+            tracing::warn!(
+                "RPC budget spent, deferring metadata for {} to the repair job",
+                new_token
+            );
+            TokenMetadata::default()
+        }
+    };
 
     // Parse total supply as BigDecimal if available
     let total_supply = metadata.total_supply.as_ref().and_then(|s| BigDecimal::from_str(s).ok());
 
     // Create or update the token record with metadata
     let new_token_record = NewToken {
-        address: new_token.clone(),
+        address: *new_token,
         name: metadata.name.clone(),
         symbol: metadata.symbol.clone(),
-        decimals: metadata.decimals.or(Some(18)),
+        name_raw: metadata.name_raw.clone(),
+        symbol_raw: metadata.symbol_raw.clone(),
+        name_spoofed: metadata.name_spoofed,
+        decimals: metadata.decimals,
         total_supply,
-        pair_address: Some(event.pair.clone()),
+        pair_address: Some(event.pair),
         creator_address: None, // Would need to trace transaction to get creator
         block_number: Some(block_number),
     };
 
     match Token::create(&new_token_record, &ctx.db_pool).await {
         Ok(token) => {
-            println!(
+            // A token listed again may have had its metadata filled in
+            ctx.entities.invalidate_token(&token.address);
+            tracing::info!(
                 "Created token: {} - {} ({}) (id={}, pair={})",
                 token.address,
                 token.name.as_deref().unwrap_or("Unknown"),
@@ -100,11 +118,12 @@ pub async fn handle(ctx: &HandlerContext, event: &PairCreatedEvent) -> HandlerRe
 
             // Create alert for new token
             let token_name = token.name.as_deref().unwrap_or("Unknown Token");
-            let token_symbol = token.symbol.as_deref().unwrap_or(&token.address[..10]);
+            let short_address = token.address.short();
+            let token_symbol = token.symbol.as_deref().unwrap_or(&short_address);
             
             let alert = NewAlert {
                 alert_type: AlertType::NewToken.as_str().to_string(),
-                token_address: Some(token.address.clone()),
+                token_address: Some(token.address),
                 token_symbol: token.symbol.clone(),
                 wallet_address: None,
                 title: format!("New Token: {} ({})", token_name, token_symbol),
@@ -115,19 +134,37 @@ pub async fn handle(ctx: &HandlerContext, event: &PairCreatedEvent) -> HandlerRe
                 bee_score: None,
                 amount_usd: None,
                 change_percent: None,
-                metadata: None,
+                metadata: Some(AlertMetadata::NewToken(NewTokenMetadata {
+                    pair_address: event.pair,
+                    base_token: *base_token,
+                    block_number,
+                })),
+                source: Some(event.source),
             };
 
-            if let Err(e) = AlertEvent::create(&alert, &ctx.db_pool).await {
-                eprintln!("Failed to create new token alert: {}", e);
+            if let Err(e) = ctx.create_alert(&alert).await {
+                tracing::error!("Failed to create new token alert: {}", e);
+            }
+
+            // Flag launches copying an established token's name or symbol
+            match impersonation::check(&token, &ctx.db_pool).await {
+                Ok(Some(found)) => tracing::info!(
+                    "Token {} impersonates {} ({} {:.0}% similar)",
+                    token.address,
+                    found.target_address,
+                    found.field,
+                    found.similarity * 100.0
+                ),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to check token for impersonation: {}", e),
             }
         }
         Err(e) => {
-            eprintln!("Failed to create token record: {}", e);
+            tracing::error!("Failed to create token record: {}", e);
         }
     }
 
-    println!(
+    tracing::debug!(
         "Processed PairCreated: new_token={} ({:?}), base={}, pair={}",
         new_token, metadata.symbol, base_token, event.pair
     );
@@ -137,8 +174,6 @@ pub async fn handle(ctx: &HandlerContext, event: &PairCreatedEvent) -> HandlerRe
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_base_token_detection() {
         // Test would require mock context
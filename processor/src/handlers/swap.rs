@@ -2,24 +2,98 @@
 //!
 //! Handles swap events from DEX pairs to:
 //! - Track price, volume, and trade metrics
-//! - Detect whale transactions
+//! - Record both legs of the trade with an LP fee estimate
+//! - Detect whale transactions and flag probable MEV/sniper bots among them
+//! - Add launch buyers showing sniper bot signatures to the bot set
 //! - Update token statistics
 
 use chrono::Utc;
 use sqlx::types::BigDecimal;
 use std::str::FromStr;
 
-use indexer_db::entity::{
-    alert::{AlertEvent, AlertType, NewAlert},
-    pair::Pair,
-    swap::{NewSwap, Swap},
-    token::Token,
+use indexer_db::{
+    entity::{
+        alert::{AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, KnownCounterparty, PriceMoveMetadata, WhaleMetadata},
+        bot_wallet::{BotDetection, BotWallet},
+        pair::Pair,
+        swap::{NewSwap, Swap, SwapLegs},
+        token::Token,
+        token_holder::TokenHolder,
+        token_metrics_minute::TokenMetricsMinute,
+        wallet_profile::WalletProfile,
+    },
+    Address20,
 };
 
-use crate::events::swap::SwapEvent;
+use crate::{bots, error::AppError, events::swap::SwapEvent, mev};
 
 use super::{HandlerContext, HandlerResult};
 
+/// Check a launch buy for sniper bot signatures, adding the buyer to the bot
+/// set when it shows enough of them. Skipped when the RPC budget is spent.
+async fn detect_bot(
+    ctx: &HandlerContext,
+    pair: &Pair,
+    token_address: &Address20,
+    event: &SwapEvent,
+    block_number: i64,
+) {
+    let Some(tx) = ctx.try_fetch_tx_gas(&event.tx_hash).await else {
+        return;
+    };
+    let is_router =
+        |address: &Address20| ctx.chain.routers.contains(address) || ctx.known.is_router(address);
+    // A discovered pair's block is one of its swaps, not its creation block
+    let in_creation_block = !pair.discovered && block_number == pair.block_number;
+    let signals = bots::signals(in_creation_block, &tx, is_router);
+    if !bots::is_bot(&signals) {
+        return;
+    }
+
+    let detection = BotDetection {
+        wallet_address: event.to,
+        signals,
+        token_address: *token_address,
+        tx_hash: event.tx_hash,
+        block_number,
+    };
+    match BotWallet::record(&detection, &ctx.db_pool).await {
+        Ok(bot) => {
+            tracing::info!(
+                "Sniper bot {} bought {} at block {} ({})",
+                bot.wallet_address,
+                token_address,
+                block_number,
+                detection.signals.join(", ")
+            );
+            refresh_bot_holdings(ctx, &bot.wallet_address, token_address).await;
+        }
+        Err(e) => tracing::error!("Failed to record bot wallet: {}", e),
+    }
+}
+
+/// Recompute the bot share of `token_address` and of every other token a
+/// newly recorded bot holds
+async fn refresh_bot_holdings(ctx: &HandlerContext, bot: &Address20, token_address: &Address20) {
+    let mut tokens = match TokenHolder::find_tokens_held(bot, &ctx.db_pool).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            tracing::error!("Failed to look up tokens held by bot {}: {}", bot, e);
+            Vec::new()
+        }
+    };
+    if !tokens.contains(token_address) {
+        tokens.push(*token_address);
+    }
+    for token in tokens {
+        let written = Token::refresh_holder_metrics(&token, &ctx.db_pool).await;
+        if let Err(e) = ctx.entities.store_token(&token, written) {
+            tracing::error!("Failed to refresh bot holdings of {}: {}", token, e);
+        }
+    }
+}
+
 /// Parse a hex string (0x...) to BigDecimal
 fn hex_to_bigdecimal(hex: &str) -> BigDecimal {
     let hex_str = hex.trim_start_matches("0x");
@@ -43,28 +117,105 @@ fn to_decimal_amount(raw: &BigDecimal, decimals: u8) -> f64 {
     raw.to_string().parse::<f64>().unwrap_or(0.0) / divisor
 }
 
+/// PancakeSwap V2 LP fee, in basis points of the input amount
+pub const PANCAKE_V2_FEE_BPS: u32 = 25;
+
+/// Decimals of the base tokens (WBNB and the BSC stablecoins)
+const BASE_TOKEN_DECIMALS: i16 = 18;
+
+/// Scale a raw on-chain amount down by `decimals`, without rounding
+pub fn normalize(raw: &BigDecimal, decimals: i16) -> BigDecimal {
+    let (digits, scale) = raw.as_bigint_and_exponent();
+    BigDecimal::new(digits, scale + decimals as i64)
+}
+
+/// USD and BNB price of one whole token, from the raw token amount of a
+/// trade and what it was worth
+fn unit_prices(
+    amount_tokens: &BigDecimal,
+    token_decimals: i16,
+    amount_bnb: f64,
+    amount_usd: f64,
+) -> (f64, f64) {
+    let tokens = normalize(amount_tokens, token_decimals)
+        .to_string()
+        .parse::<f64>()
+        .unwrap_or(0.0);
+    if tokens > 0.0 {
+        (amount_usd / tokens, amount_bnb / tokens)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+/// Both legs of a base/token swap, with the LP fee taken from the input leg
+fn swap_legs(
+    pair: &Pair,
+    is_buy: bool,
+    amount_tokens: &BigDecimal,
+    amount_bnb: &BigDecimal,
+    token_decimals: i16,
+    amount_usd: &BigDecimal,
+) -> SwapLegs {
+    let base = (
+        *pair.get_base_address(),
+        normalize(amount_bnb, BASE_TOKEN_DECIMALS),
+    );
+    let token = (
+        *pair.get_token_address(),
+        normalize(amount_tokens, token_decimals),
+    );
+    let ((token_in, amount_in), (token_out, amount_out)) =
+        if is_buy { (base, token) } else { (token, base) };
+
+    let fee_share = BigDecimal::from(PANCAKE_V2_FEE_BPS) / BigDecimal::from(10_000);
+    SwapLegs {
+        token_in,
+        fee_amount: Some(&amount_in * &fee_share),
+        amount_in,
+        token_out,
+        amount_out,
+        fee_usd: Some((amount_usd * &fee_share).round(2)),
+    }
+}
+
+/// How far a swap moved the token's price, in percent (negative on sells)
+///
+/// Read off the pair's base reserve, which the swap's Sync (emitted just
+/// before it) left at its post-trade value: the token's price goes with the
+/// square of the base reserve on a constant-product pair.
+fn price_impact_percent(pair: &Pair, is_buy: bool, amount_base: &BigDecimal) -> Option<f64> {
+    let (base_after, _) = pair.get_reserves()?;
+    let after = base_after.to_string().parse::<f64>().ok()?;
+    let amount = amount_base.to_string().parse::<f64>().ok()?;
+    let before = if is_buy { after - amount } else { after + amount };
+    if before <= 0.0 || after <= 0.0 {
+        return None;
+    }
+
+    let impact = ((after / before).powi(2) - 1.0) * 100.0;
+    Some((impact * 100.0).round() / 100.0)
+}
+
 /// Process a Swap event
 ///
-/// 1. Look up the pair to identify tokens
+/// 1. Look up the pair to identify tokens (`AppError::UnknownPair` if it isn't indexed)
 /// 2. Determine trade direction (buy/sell based on WBNB flow)
-/// 3. Calculate USD value
+/// 3. Calculate USD value, scaled by the token's decimals (`AppError::UnknownDecimals`
+///    if they can't be read)
 /// 4. Create swap record
-/// 5. Update token metrics
+/// 5. Update token metrics; the price only for live swaps, not replayed ones
 /// 6. Check for whale transaction
 pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()> {
     // Look up the pair
-    let pair = match Pair::find_by_address(&event.pair, &ctx.db_pool).await? {
+    let pair = match ctx.find_pair(&event.pair).await? {
         Some(p) => p,
-        None => {
-            // Pair not in our database - might be from before we started indexing
-            println!("Unknown pair: {}, skipping swap", event.pair);
-            return Ok(());
-        }
+        // Its PairCreated may still be queued, or it predates indexing
+        None => return Err(AppError::UnknownPair(event.pair)),
     };
 
     // Get the non-base token address (the memecoin)
-    let token_address = pair.get_token_address().to_string();
-    let base_address = pair.get_base_address().to_string();
+    let token_address = *pair.get_token_address();
 
     // Parse amounts
     let amount0_in = hex_to_bigdecimal(&event.amount0_in);
@@ -85,7 +236,7 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
                 // tokens in -> BNB out = SELL
                 (false, amount1_in.clone(), amount0_out.clone())
             } else {
-                println!("Ambiguous swap direction, skipping");
+                tracing::info!("Ambiguous swap direction, skipping");
                 return Ok(());
             }
         }
@@ -98,12 +249,12 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
                 // tokens in -> BNB out = SELL
                 (false, amount0_in.clone(), amount1_out.clone())
             } else {
-                println!("Ambiguous swap direction, skipping");
+                tracing::info!("Ambiguous swap direction, skipping");
                 return Ok(());
             }
         }
         _ => {
-            println!("Unknown base token index for pair {}", event.pair);
+            tracing::debug!("Unknown base token index for pair {}", event.pair);
             return Ok(());
         }
     };
@@ -117,37 +268,72 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
     let is_whale = amount_usd >= ctx.whale_threshold_usd;
 
     let block_number = event.block.parse::<i64>().unwrap_or(0);
+    let timestamp = event.block_timestamp.unwrap_or_else(Utc::now);
     let trade_type = if is_buy { "buy" } else { "sell" };
 
-    // Calculate price (USD per token)
-    let tokens_decimal = to_decimal_amount(&amount_tokens, 18);
-    let price_usd = if tokens_decimal > 0.0 {
-        amount_usd / tokens_decimal
+    // Get previous token state for price comparison, and its decimals
+    let old_token = ctx.find_token(&token_address).await?;
+    if let Some(token) = &old_token {
+        ctx.revive(token).await;
+    }
+    let decimals = ctx.token_decimals(&token_address, old_token.as_ref()).await?;
+
+    // Calculate price per whole token, in USD and BNB
+    let (price_usd, price_bnb) =
+        unit_prices(&amount_tokens, decimals, bnb_amount_decimal, amount_usd);
+    let price_usd_bd = BigDecimal::from_str(&format!("{:.18}", price_usd)).unwrap_or(BigDecimal::from(0));
+
+    // Whale swaps get the block's gas prices to spot bots outbidding the rest
+    let gas_price_percentile = if is_whale {
+        ctx.fetch_block_gas_prices(block_number as u64)
+            .await
+            .and_then(|prices| {
+                let own = *prices.get(event.transaction_index as usize)?;
+                Some(mev::gas_price_percentile(own, &prices))
+            })
     } else {
-        0.0
+        None
+    };
+    let mev_flags = if is_whale {
+        mev::flags(event.transaction_index, gas_price_percentile)
+    } else {
+        Vec::new()
     };
-    let price_usd_bd = BigDecimal::from_str(&format!("{:.18}", price_usd)).unwrap_or(BigDecimal::from(0));
+
+    let legs = swap_legs(
+        &pair,
+        is_buy,
+        &amount_tokens,
+        &amount_bnb,
+        decimals,
+        &amount_usd_bd,
+    );
 
     // Create swap record
     let new_swap = NewSwap {
-        tx_hash: format!("0x{}", "0".repeat(64)), // We don't have tx_hash from the event struct, would need from log
+        tx_hash: event.tx_hash,
         block_number,
-        log_index: 0, // Would need from log
-        timestamp: Utc::now(),
-        pair_address: event.pair.clone(),
-        token_address: token_address.clone(),
-        wallet_address: event.to.clone(), // Recipient is the trader
+        log_index: event.log_index as i32,
+        timestamp,
+        pair_address: event.pair,
+        token_address,
+        wallet_address: event.to, // Recipient is the trader
         trade_type: trade_type.to_string(),
         amount_tokens: Some(amount_tokens.clone()),
         amount_bnb: Some(amount_bnb.clone()),
         amount_usd: Some(amount_usd_bd.clone()),
         price_usd: Some(price_usd_bd.clone()),
         is_whale,
+        tx_index: Some(event.transaction_index as i32),
+        gas_price_percentile,
+        mev_flags: mev_flags.clone(),
+        legs: Some(legs),
+        source_log_id: event.source.log_id,
     };
 
     match Swap::create(&new_swap, &ctx.db_pool).await {
         Ok(swap) => {
-            println!(
+            tracing::info!(
                 "Created swap: {} {} ${:.2} of {} (whale={})",
                 trade_type.to_uppercase(),
                 swap.id,
@@ -158,43 +344,56 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
         }
         Err(e) => {
             // Might be duplicate (idempotent)
-            println!("Swap create result: {}", e);
+            tracing::info!("Swap create result: {}", e);
         }
     }
 
-    // Update token metrics
-    if let Err(e) = Token::increment_trade_count(
+    // First sighting of the trader, for wallet age
+    if let Err(e) =
+        WalletProfile::record_seen(&event.to, block_number, timestamp, &ctx.db_pool).await
+    {
+        tracing::error!("Failed to record wallet profile: {}", e);
+    }
+
+    if is_buy && ctx.sniper_window.contains(pair.block_number, block_number) {
+        detect_bot(ctx, &pair, &token_address, event, block_number).await;
+    }
+
+    // Record the trade in its minute bucket, then refresh the token's rollup
+    if let Err(e) = TokenMetricsMinute::record_trade(
         &token_address,
+        timestamp,
         is_buy,
         &amount_usd_bd,
         &ctx.db_pool,
     )
     .await
     {
-        eprintln!("Failed to update token trade count: {}", e);
+        tracing::error!("Failed to record token minute metrics: {}", e);
+    } else {
+        let written = Token::refresh_trade_rollup(&token_address, &ctx.db_pool).await;
+        if let Err(e) = ctx.entities.store_token(&token_address, written) {
+            tracing::error!("Failed to refresh token trade rollup: {}", e);
+        }
     }
 
     // Update token price
-    let price_bnb = if tokens_decimal > 0.0 {
-        bnb_amount_decimal / tokens_decimal
-    } else {
-        0.0
-    };
     let price_bnb_bd = BigDecimal::from_str(&format!("{:.18}", price_bnb)).unwrap_or(BigDecimal::from(0));
 
-    // Get previous token state for price comparison
-    let old_token = Token::find_by_address(&token_address, &ctx.db_pool).await?;
-
-    // Update price in DB
-    if let Err(e) = Token::update_price_metrics(
-        &token_address,
-        &price_usd_bd,
-        &price_bnb_bd,
-        &BigDecimal::from(0), // Liquidity TODO
-        &BigDecimal::from(0), // Liquidity BNB TODO
-        &ctx.db_pool,
-    ).await {
-        eprintln!("Failed to update token price: {}", e);
+    // Update price in DB. A replayed swap is older than the price already
+    // there, so it is left alone.
+    if ctx.replay.is_none() {
+        let written = Token::update_price_metrics(
+            &token_address,
+            &price_usd_bd,
+            &price_bnb_bd,
+            &BigDecimal::from(0), // Liquidity TODO
+            &BigDecimal::from(0), // Liquidity BNB TODO
+            &ctx.db_pool,
+        ).await;
+        if let Err(e) = ctx.entities.store_token(&token_address, written) {
+            tracing::error!("Failed to update token price: {}", e);
+        }
     }
 
     // Check for Price Pump/Dump
@@ -208,7 +407,7 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
                 if price_change_percent > 50.0 {
                      let alert = NewAlert {
                         alert_type: AlertType::PricePump.as_str().to_string(),
-                        token_address: Some(token_address.clone()),
+                        token_address: Some(token_address),
                         token_symbol: token.symbol.clone(),
                         wallet_address: None,
                         title: format!("Price Pump: +{:.0}%", price_change_percent),
@@ -221,17 +420,22 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
                         bee_score: token.bee_score,
                         amount_usd: None,
                         change_percent: Some(BigDecimal::from_str(&format!("{:.2}", price_change_percent)).unwrap_or(BigDecimal::from(0))),
-                        metadata: None,
+                        metadata: Some(AlertMetadata::PriceMove(PriceMoveMetadata {
+                            tx_hash: event.tx_hash,
+                            previous_price_usd: old_price_f64,
+                            price_usd,
+                        })),
+                        source: Some(event.source),
                     };
-                    if let Err(e) = AlertEvent::create(&alert, &ctx.db_pool).await {
-                        eprintln!("Failed to create pump alert: {}", e);
+                    if let Err(e) = ctx.create_alert(&alert).await {
+                        tracing::error!("Failed to create pump alert: {}", e);
                     }
                 }
                 // Dump: > 50% decrease
                 else if price_change_percent < -50.0 {
                      let alert = NewAlert {
                         alert_type: AlertType::PriceDump.as_str().to_string(),
-                        token_address: Some(token_address.clone()),
+                        token_address: Some(token_address),
                         token_symbol: token.symbol.clone(),
                         wallet_address: None,
                         title: format!("Price Dump: {:.0}%", price_change_percent),
@@ -244,10 +448,15 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
                         bee_score: token.bee_score,
                         amount_usd: None,
                         change_percent: Some(BigDecimal::from_str(&format!("{:.2}", price_change_percent)).unwrap_or(BigDecimal::from(0))),
-                        metadata: None,
+                        metadata: Some(AlertMetadata::PriceMove(PriceMoveMetadata {
+                            tx_hash: event.tx_hash,
+                            previous_price_usd: old_price_f64,
+                            price_usd,
+                        })),
+                        source: Some(event.source),
                     };
-                    if let Err(e) = AlertEvent::create(&alert, &ctx.db_pool).await {
-                        eprintln!("Failed to create dump alert: {}", e);
+                    if let Err(e) = ctx.create_alert(&alert).await {
+                        tracing::error!("Failed to create dump alert: {}", e);
                     }
                 }
             }
@@ -257,20 +466,26 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
     // Create whale alert if applicable
     if is_whale {
         // Try to get token symbol
-        let token_symbol = match Token::find_by_address(&token_address, &ctx.db_pool).await {
-            Ok(Some(t)) => t.symbol.unwrap_or_else(|| token_address[..10].to_string()),
-            _ => token_address[..10].to_string(),
+        let token_symbol = match ctx.find_token(&token_address).await {
+            Ok(Some(t)) => t.symbol.unwrap_or_else(|| token_address.short()),
+            _ => token_address.short(),
         };
 
+        // Recipient may be an exchange wallet or router rather than a trader
+        let counterparty = ctx.known.get(&event.to).map(|known| KnownCounterparty {
+            kind: known.kind.clone(),
+            name: known.name.clone(),
+        });
+
         let alert = NewAlert {
             alert_type: if is_buy {
                 AlertType::WhaleBuy.as_str().to_string()
             } else {
                 AlertType::WhaleSell.as_str().to_string()
             },
-            token_address: Some(token_address.clone()),
+            token_address: Some(token_address),
             token_symbol: Some(token_symbol.clone()),
-            wallet_address: Some(event.to.clone()),
+            wallet_address: Some(event.to),
             title: format!(
                 "Whale {}: ${:.0} {}",
                 if is_buy { "Buy" } else { "Sell" },
@@ -287,15 +502,22 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
             bee_score: None,
             amount_usd: Some(amount_usd_bd),
             change_percent: None,
-            metadata: None,
+            metadata: Some(AlertMetadata::Whale(WhaleMetadata {
+                tx_hash: Some(event.tx_hash),
+                price_impact_percent: price_impact_percent(&pair, is_buy, &amount_bnb),
+                gas_price_percentile,
+                mev_flags,
+                known_counterparty: counterparty,
+            })),
+            source: Some(event.source),
         };
 
-        if let Err(e) = AlertEvent::create(&alert, &ctx.db_pool).await {
-            eprintln!("Failed to create whale alert: {}", e);
+        if let Err(e) = ctx.create_alert(&alert).await {
+            tracing::error!("Failed to create whale alert: {}", e);
         }
     }
 
-    println!(
+    tracing::debug!(
         "Processed Swap: {} {} ${:.2} of {} (price=${:.10})",
         trade_type.to_uppercase(),
         if is_whale { "[WHALE]" } else { "" },
@@ -306,3 +528,95 @@ pub async fn handle(ctx: &HandlerContext, event: &SwapEvent) -> HandlerResult<()
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use indexer_db::Address20;
+
+    use super::*;
+
+    fn dec(s: &str) -> BigDecimal {
+        s.parse().unwrap()
+    }
+
+    fn pair() -> Pair {
+        Pair {
+            id: 1,
+            address: Address20::new([9; 20]),
+            token0_address: Address20::new([1; 20]),
+            token1_address: Address20::new([2; 20]),
+            factory_address: Address20::new([3; 20]),
+            reserve0: None,
+            reserve1: None,
+            base_token_index: Some(0),
+            block_number: 1,
+            discovered: false,
+            created_at: None,
+            last_updated: None,
+            lp_total_supply: None,
+            lp_supply_updated_at: None,
+            volume_24h_usd: None,
+            trades_24h: None,
+            fees_24h_usd: None,
+            stats_updated_at: None,
+        }
+    }
+
+    #[test]
+    fn legs_follow_the_trade_direction() {
+        // 0.5 WBNB in for 1,000 of a 9-decimal token
+        let raw_bnb = dec("500000000000000000");
+        let raw_tokens = dec("1000000000000");
+
+        let buy = swap_legs(&pair(), true, &raw_tokens, &raw_bnb, 9, &dec("300"));
+        assert_eq!(buy.token_in, Address20::new([1; 20]));
+        assert_eq!(buy.amount_in, dec("0.5"));
+        assert_eq!(buy.token_out, Address20::new([2; 20]));
+        assert_eq!(buy.amount_out, dec("1000"));
+        assert_eq!(buy.fee_amount, Some(dec("0.00125")));
+        assert_eq!(buy.fee_usd, Some(dec("0.75")));
+
+        let sell = swap_legs(&pair(), false, &raw_tokens, &raw_bnb, 9, &dec("300"));
+        assert_eq!(sell.token_in, Address20::new([2; 20]));
+        assert_eq!(sell.fee_amount, Some(dec("2.5")));
+        assert_eq!(sell.amount_out, dec("0.5"));
+    }
+
+    #[test]
+    fn prices_are_per_whole_token() {
+        // 0.5 BNB ($300) for 1,000 of a 9-decimal token
+        let (usd, bnb) = unit_prices(&dec("1000000000000"), 9, 0.5, 300.0);
+        assert!((usd - 0.3).abs() < 1e-12);
+        assert!((bnb - 0.0005).abs() < 1e-12);
+
+        // The same trade of an 18-decimal token
+        let (usd, _) = unit_prices(&dec("1000000000000000000000"), 18, 0.5, 300.0);
+        assert!((usd - 0.3).abs() < 1e-12);
+
+        assert_eq!(unit_prices(&dec("0"), 9, 0.5, 300.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn price_impact_follows_the_base_reserve() {
+        let mut after_buy = pair();
+        after_buy.reserve0 = Some(dec("100"));
+        after_buy.reserve1 = Some(dec("900"));
+        // 90 -> 100 base: the token's price rises by (100/90)^2
+        assert_eq!(
+            price_impact_percent(&after_buy, true, &dec("10")),
+            Some(23.46)
+        );
+        // 100 -> 90 base on a sell
+        let mut after_sell = pair();
+        after_sell.reserve0 = Some(dec("90"));
+        after_sell.reserve1 = Some(dec("1000"));
+        assert_eq!(
+            price_impact_percent(&after_sell, false, &dec("10")),
+            Some(-19.0)
+        );
+
+        // Reserves not synced yet, or behind the trade itself
+        assert_eq!(price_impact_percent(&pair(), true, &dec("10")), None);
+        assert_eq!(price_impact_percent(&after_buy, true, &dec("100")), None);
+    }
+}
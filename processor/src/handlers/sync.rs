@@ -4,26 +4,147 @@
 //! - Reserve amounts
 //! - Liquidity calculations
 //! - Price snapshots
+//!
+//! Each update is also checked against the pair's x*y=k invariant. Updates no
+//! swap or balanced mint/burn could produce are recorded as anomalies, and
+//! price snapshots from that pair/block are suppressed.
+//!
+//! Prices further than `SNAPSHOT_QUARANTINE_MAX_CHANGE_PERCENT` from the median
+//! of the token's recent snapshots go to `price_snapshot_quarantine` instead of
+//! the chart. Once Syncs in `SNAPSHOT_QUARANTINE_CONFIRM_BLOCKS` later blocks
+//! hold the new level they are charted; a Sync back near the median rejects
+//! them.
 
 use chrono::Utc;
 use sqlx::types::BigDecimal;
-use std::str::FromStr;
+use std::{collections::BTreeSet, env, str::FromStr};
 
 use indexer_db::entity::{
+    anomaly::{Anomaly, AnomalyKind, NewAnomaly},
     pair::Pair,
+    price_quarantine::{NewQuarantinedSnapshot, QuarantinedSnapshot},
     price_snapshot::{NewPriceSnapshot, PriceSnapshot},
     token::Token,
 };
 
+use crate::{defaults, error::AppError, events::sync::SyncEvent};
+
 use super::{HandlerContext, HandlerResult};
 
-/// Decoded Sync event (would come from event decoder)
-#[derive(Debug)]
-pub struct SyncEvent {
-    pub pair: String,
-    pub reserve0: String, // hex
-    pub reserve1: String, // hex
-    pub block: String,
+/// PancakeSwap V2 swap fee, in basis points
+const PANCAKE_V2_FEE_BPS: u32 = 25;
+
+/// Slack on the swap output bound, covering forks with lower fees than Pancake
+const K_TOLERANCE_PERCENT: f64 = 0.5;
+
+/// How far the price ratio may drift on a mint/burn before it's flagged
+const RATIO_TOLERANCE_PERCENT: f64 = 1.0;
+
+/// Sanity bounds a Sync's price must meet to be charted straight away
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapshotBounds {
+    /// How far (%) a price may sit from the recent median, either way
+    pub max_change_percent: f64,
+    /// Recent snapshots the median is taken over
+    pub window: i64,
+    /// Later blocks that must hold a quarantined level before it's charted
+    pub confirm_blocks: usize,
+}
+
+impl Default for SnapshotBounds {
+    fn default() -> Self {
+        Self {
+            max_change_percent: 1000.0,
+            window: 5,
+            confirm_blocks: 2,
+        }
+    }
+}
+
+impl SnapshotBounds {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: f64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<f64>()
+                .unwrap_or(fallback)
+                .max(0.0)
+        };
+
+        Self {
+            max_change_percent: read(
+                "SNAPSHOT_QUARANTINE_MAX_CHANGE_PERCENT",
+                defaults::SNAPSHOT_QUARANTINE_MAX_CHANGE_PERCENT,
+                1000.0,
+            ),
+            window: read(
+                "SNAPSHOT_QUARANTINE_WINDOW",
+                defaults::SNAPSHOT_QUARANTINE_WINDOW,
+                5.0,
+            )
+            .max(1.0) as i64,
+            confirm_blocks: read(
+                "SNAPSHOT_QUARANTINE_CONFIRM_BLOCKS",
+                defaults::SNAPSHOT_QUARANTINE_CONFIRM_BLOCKS,
+                2.0,
+            ) as usize,
+        }
+    }
+}
+
+/// How far (%) `price` is from `reference`, the same either way: 10x and
+/// 1/10x are both 900%. `None` when either isn't positive.
+fn price_change_percent(price: f64, reference: f64) -> Option<f64> {
+    (price > 0.0 && reference > 0.0)
+        .then(|| (price.max(reference) / price.min(reference) - 1.0) * 100.0)
+}
+
+/// Hold back a price point too far from the recent median, charting the
+/// token's quarantined points once enough later blocks hold the new level.
+/// One transaction, so a failure never leaves the pending points half
+/// rejected or confirmed.
+async fn quarantine(
+    ctx: &HandlerContext,
+    quarantined: &NewQuarantinedSnapshot,
+    above: bool,
+) -> Result<(), sqlx::Error> {
+    let token_address = &quarantined.snapshot.token_address;
+    let mut tx = ctx.db_pool.begin().await?;
+    let mut pending = QuarantinedSnapshot::find_pending(token_address, &mut *tx).await?;
+
+    // A spike the other way doesn't confirm the earlier ones
+    if pending
+        .iter()
+        .any(|p| (p.price_bnb > p.reference_price_bnb) != above)
+    {
+        QuarantinedSnapshot::reject_pending(token_address, &mut *tx).await?;
+        pending.clear();
+    }
+
+    QuarantinedSnapshot::create(quarantined, &mut *tx).await?;
+
+    let blocks: BTreeSet<i64> = pending
+        .iter()
+        .map(|p| p.block_number)
+        .chain([quarantined.block_number])
+        .collect();
+    let charted = if blocks.len() > ctx.snapshot_bounds.confirm_blocks {
+        Some(QuarantinedSnapshot::confirm_pending(token_address, &mut *tx).await?)
+    } else {
+        None
+    };
+    tx.commit().await?;
+
+    if let Some(charted) = charted {
+        tracing::info!(
+            "Confirmed {} quarantined price snapshot(s) for {} over {} blocks",
+            charted,
+            token_address,
+            blocks.len()
+        );
+    }
+
+    Ok(())
 }
 
 /// Parse a hex string (0x...) to BigDecimal
@@ -39,50 +160,141 @@ fn hex_to_bigdecimal(hex: &str) -> BigDecimal {
     }
 }
 
+fn decimal(value: f64, scale: usize) -> BigDecimal {
+    BigDecimal::from_str(&format!("{:.*}", scale, value)).unwrap_or_default()
+}
+
 /// Convert token amount to human-readable format
 fn to_decimal_amount(raw: &BigDecimal, decimals: u8) -> f64 {
     let divisor = 10u128.pow(decimals as u32) as f64;
     raw.to_string().parse::<f64>().unwrap_or(0.0) / divisor
 }
 
+/// Check a reserve update against the constant-product invariant
+///
+/// - One side up, the other down: a swap. The output may not exceed what
+///   `getAmountOut` allows for the input (K-invariant).
+/// - Both sides moved the same way (or only one moved): a mint/burn, which
+///   must keep the price ratio. A single-sided change is a direct transfer.
+///
+/// Returns the anomaly kind and how far past the bound the update went, in percent.
+fn check_reserves(prev: (f64, f64), next: (f64, f64), fee_bps: u32) -> Option<(AnomalyKind, f64)> {
+    let (p0, p1) = prev;
+    let (n0, n1) = next;
+
+    // Nothing to compare against before the first Sync
+    if p0 <= 0.0 || p1 <= 0.0 {
+        return None;
+    }
+
+    let (d0, d1) = (n0 - p0, n1 - p1);
+    let is_swap = (d0 > 0.0 && d1 < 0.0) || (d0 < 0.0 && d1 > 0.0);
+
+    if is_swap {
+        let (amount_in, reserve_in, reserve_out, amount_out) = if d0 > 0.0 {
+            (d0, p0, p1, -d1)
+        } else {
+            (d1, p1, p0, -d0)
+        };
+        let amount_in_with_fee = amount_in * (10_000 - fee_bps) as f64 / 10_000.0;
+        let max_out = amount_in_with_fee * reserve_out / (reserve_in + amount_in_with_fee);
+
+        let excess = (amount_out / max_out - 1.0) * 100.0;
+        return (excess > K_TOLERANCE_PERCENT).then_some((AnomalyKind::KInvariantViolation, excess));
+    }
+
+    if n0 <= 0.0 || n1 <= 0.0 {
+        // Fully drained on one side only
+        return (n0 > 0.0 || n1 > 0.0).then_some((AnomalyKind::UnbalancedReserveChange, 100.0));
+    }
+
+    let drift = ((n0 / n1) / (p0 / p1) - 1.0).abs() * 100.0;
+    (drift > RATIO_TOLERANCE_PERCENT).then_some((AnomalyKind::UnbalancedReserveChange, drift))
+}
+
 /// Process a Sync event
 ///
-/// 1. Look up the pair
+/// 1. Look up the pair (`AppError::UnknownPair` if it isn't indexed)
 /// 2. Update reserves
 /// 3. Calculate liquidity in USD
 /// 4. Update token price and liquidity
-/// 5. Create price snapshot (throttled)
+/// 5. Create price snapshot (throttled), or quarantine it if it's far from
+///    the recent median
+///
+/// A replayed Sync is checked against the Sync replayed before it and only
+/// charted: the pair's reserves and the token's price stay as they are now.
 pub async fn handle(ctx: &HandlerContext, event: &SyncEvent) -> HandlerResult<()> {
     // Look up the pair
-    let pair = match Pair::find_by_address(&event.pair, &ctx.db_pool).await? {
+    let pair = match ctx.find_pair(&event.pair).await? {
         Some(p) => p,
-        None => {
-            println!("Unknown pair for Sync: {}", event.pair);
-            return Ok(());
-        }
+        None => return Err(AppError::UnknownPair(event.pair)),
     };
 
     // Parse reserves
     let reserve0 = hex_to_bigdecimal(&event.reserve0);
     let reserve1 = hex_to_bigdecimal(&event.reserve1);
+    let block_number = event.block.parse::<i64>().unwrap_or(0);
+
+    // Check the update against the previous reserves before overwriting them
+    let (prev_reserve0, prev_reserve1) = if ctx.replay.is_some() {
+        ctx.replayed_reserves(&event.pair, (reserve0.clone(), reserve1.clone()))
+    } else {
+        (
+            pair.reserve0.clone().unwrap_or_else(|| BigDecimal::from(0)),
+            pair.reserve1.clone().unwrap_or_else(|| BigDecimal::from(0)),
+        )
+    };
+    let anomaly = check_reserves(
+        (to_decimal_amount(&prev_reserve0, 0), to_decimal_amount(&prev_reserve1, 0)),
+        (to_decimal_amount(&reserve0, 0), to_decimal_amount(&reserve1, 0)),
+        PANCAKE_V2_FEE_BPS,
+    );
+
+    if let Some((kind, deviation)) = anomaly {
+        tracing::info!(
+            "Reserve anomaly on {} at block {}: {} ({:.2}% past bound)",
+            event.pair,
+            block_number,
+            kind.as_str(),
+            deviation
+        );
+
+        let new_anomaly = NewAnomaly {
+            kind,
+            pair_address: event.pair,
+            token_address: Some(*pair.get_token_address()),
+            block_number,
+            prev_reserve0,
+            prev_reserve1,
+            reserve0: reserve0.clone(),
+            reserve1: reserve1.clone(),
+            deviation_percent: BigDecimal::from_str(&format!("{:.4}", deviation.min(1e15))).ok(),
+        };
+        if let Err(e) = Anomaly::create(&new_anomaly, &ctx.db_pool).await {
+            tracing::error!("Failed to record anomaly: {}", e);
+        }
+    }
 
     // Update pair reserves
-    if let Err(e) = Pair::update_reserves(&event.pair, &reserve0, &reserve1, &ctx.db_pool).await {
-        eprintln!("Failed to update pair reserves: {}", e);
+    if ctx.replay.is_none() {
+        let written = Pair::update_reserves(&event.pair, &reserve0, &reserve1, &ctx.db_pool).await;
+        if let Err(e) = ctx.entities.store_pair(&event.pair, written) {
+            tracing::error!("Failed to update pair reserves: {}", e);
+        }
     }
 
     // Determine which reserve is BNB and which is the token
     let (bnb_reserve, token_reserve, token_address) = match pair.base_token_index {
         Some(0) => {
             // token0 is WBNB
-            (reserve0.clone(), reserve1.clone(), pair.token1_address.clone())
+            (reserve0.clone(), reserve1.clone(), pair.token1_address)
         }
         Some(1) => {
             // token1 is WBNB
-            (reserve1.clone(), reserve0.clone(), pair.token0_address.clone())
+            (reserve1.clone(), reserve0.clone(), pair.token0_address)
         }
         _ => {
-            println!("Unknown base token index for pair {}", event.pair);
+            tracing::debug!("Unknown base token index for pair {}", event.pair);
             return Ok(());
         }
     };
@@ -108,25 +320,40 @@ pub async fn handle(ctx: &HandlerContext, event: &SyncEvent) -> HandlerResult<()
     let liquidity_usd_bd = BigDecimal::from_str(&format!("{:.2}", liquidity_usd)).unwrap_or(BigDecimal::from(0));
     let liquidity_bnb_bd = BigDecimal::from_str(&format!("{:.18}", liquidity_bnb)).unwrap_or(BigDecimal::from(0));
 
-    if let Err(e) = Token::update_price_metrics(
-        &token_address,
-        &price_usd_bd,
-        &price_bnb_bd,
-        &liquidity_usd_bd,
-        &liquidity_bnb_bd,
-        &ctx.db_pool,
-    )
-    .await
-    {
-        eprintln!("Failed to update token price metrics: {}", e);
+    if ctx.replay.is_none() {
+        let written = Token::update_price_metrics(
+            &token_address,
+            &price_usd_bd,
+            &price_bnb_bd,
+            &liquidity_usd_bd,
+            &liquidity_bnb_bd,
+            &ctx.db_pool,
+        )
+        .await;
+        if let Err(e) = ctx.entities.store_token(&token_address, written) {
+            tracing::error!("Failed to update token price metrics: {}", e);
+        }
+    }
+
+    // Don't chart prices from a block where this pair was manipulated
+    let manipulated = anomaly.is_some()
+        || Anomaly::exists_in_block(&event.pair, block_number, &ctx.db_pool)
+            .await
+            .unwrap_or(false);
+    if manipulated {
+        tracing::debug!(
+            "Skipping price snapshot for {} at block {} (reserve anomaly)",
+            token_address, block_number
+        );
+        return Ok(());
     }
 
     // Create price snapshot
     // In production, throttle this to every 5 minutes to avoid too many records
-    let now = Utc::now();
+    let now = event.block_timestamp.unwrap_or_else(Utc::now);
 
     // Get holder count from token (would need separate tracking)
-    let holder_count = match Token::find_by_address(&token_address, &ctx.db_pool).await {
+    let holder_count = match ctx.find_token(&token_address).await {
         Ok(Some(t)) => t.holder_count,
         _ => None,
     };
@@ -136,7 +363,7 @@ pub async fn handle(ctx: &HandlerContext, event: &SyncEvent) -> HandlerResult<()
     let market_cap_usd: Option<BigDecimal> = None;
 
     let snapshot = NewPriceSnapshot {
-        token_address: token_address.clone(),
+        token_address,
         timestamp: now,
         price_usd: Some(price_usd_bd.clone()),
         price_bnb: Some(price_bnb_bd.clone()),
@@ -146,15 +373,136 @@ pub async fn handle(ctx: &HandlerContext, event: &SyncEvent) -> HandlerResult<()
         holder_count,
     };
 
+    // Hold back prices far from the recent median until later blocks agree
+    let reference = PriceSnapshot::find_recent_median_bnb(
+        &token_address,
+        ctx.snapshot_bounds.window,
+        &ctx.db_pool,
+    )
+    .await
+    .unwrap_or_else(|e| {
+        tracing::error!("Failed to read recent prices for {}: {}", token_address, e);
+        None
+    });
+    let outlier = reference.and_then(|reference| {
+        price_change_percent(price_bnb, reference)
+            .filter(|change| *change > ctx.snapshot_bounds.max_change_percent)
+            .map(|change| (reference, change))
+    });
+
+    if let Some((reference, change)) = outlier {
+        tracing::info!(
+            "Quarantining price snapshot for {} at block {}: {:.2}% from the recent median",
+            token_address, block_number, change
+        );
+        let quarantined = NewQuarantinedSnapshot {
+            snapshot,
+            pair_address: event.pair,
+            block_number,
+            reference_price_bnb: decimal(reference, 18),
+            change_percent: decimal(change.min(1e30), 4),
+        };
+        if let Err(e) = quarantine(ctx, &quarantined, price_bnb > reference).await {
+            tracing::error!("Failed to quarantine price snapshot: {}", e);
+        }
+        return Ok(());
+    }
+
+    match QuarantinedSnapshot::reject_pending(&token_address, &ctx.db_pool).await {
+        Ok(0) => {}
+        Ok(rejected) => tracing::info!(
+            "Rejected {} quarantined price snapshot(s) for {}: back near the median",
+            rejected, token_address
+        ),
+        Err(e) => tracing::error!("Failed to reject quarantined price snapshots: {}", e),
+    }
+
     if let Err(e) = PriceSnapshot::create(&snapshot, &ctx.db_pool).await {
         // Might be duplicate timestamp
-        println!("Price snapshot result: {}", e);
+        tracing::debug!("Price snapshot result: {}", e);
     }
 
-    println!(
+    tracing::debug!(
         "Processed Sync: {} - price=${:.10}, liquidity=${:.2}",
         token_address, price_usd, liquidity_usd
     );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEE: u32 = PANCAKE_V2_FEE_BPS;
+
+    /// Reserves after a real swap of `amount_in` token0 into a (p0, p1) pool
+    fn swap0(p0: f64, p1: f64, amount_in: f64) -> (f64, f64) {
+        let with_fee = amount_in * 0.9975;
+        let out = with_fee * p1 / (p0 + with_fee);
+        (p0 + amount_in, p1 - out)
+    }
+
+    #[test]
+    fn honest_swaps_and_liquidity_changes_pass() {
+        let prev = (1_000.0, 5_000_000.0);
+        assert_eq!(
+            check_reserves(prev, swap0(prev.0, prev.1, 250.0), FEE),
+            None
+        );
+
+        // Selling token1 for token0
+        let (n1, n0) = swap0(prev.1, prev.0, 1_000_000.0);
+        assert_eq!(check_reserves(prev, (n0, n1), FEE), None);
+
+        // Proportional mint and burn
+        assert_eq!(check_reserves(prev, (1_500.0, 7_500_000.0), FEE), None);
+        assert_eq!(check_reserves(prev, (100.0, 500_000.0), FEE), None);
+        assert_eq!(check_reserves(prev, prev, FEE), None);
+    }
+
+    #[test]
+    fn price_change_is_symmetric() {
+        assert_eq!(price_change_percent(10.0, 1.0), Some(900.0));
+        assert_eq!(price_change_percent(0.1, 1.0), Some(900.0));
+        assert_eq!(price_change_percent(1.0, 1.0), Some(0.0));
+        assert_eq!(price_change_percent(0.0, 1.0), None);
+        assert_eq!(price_change_percent(1.0, 0.0), None);
+
+        let bounds = SnapshotBounds::default();
+        assert!(price_change_percent(1e10, 1.0).unwrap() > bounds.max_change_percent);
+        assert!(price_change_percent(5.0, 1.0).unwrap() < bounds.max_change_percent);
+    }
+
+    #[test]
+    fn first_sync_is_not_checked() {
+        assert_eq!(check_reserves((0.0, 0.0), (10.0, 1.0), FEE), None);
+    }
+
+    #[test]
+    fn direct_transfers_are_unbalanced() {
+        let (kind, drift) = check_reserves((1_000.0, 1_000.0), (1_200.0, 1_000.0), FEE).unwrap();
+        assert_eq!(kind, AnomalyKind::UnbalancedReserveChange);
+        assert!((drift - 20.0).abs() < 1e-9);
+
+        let (kind, _) = check_reserves((1_000.0, 1_000.0), (0.0, 1_000.0), FEE).unwrap();
+        assert_eq!(kind, AnomalyKind::UnbalancedReserveChange);
+    }
+
+    #[test]
+    fn outputs_beyond_the_invariant_are_flagged() {
+        let prev = (1_000.0, 1_000.0);
+        let (n0, honest_n1) = swap0(prev.0, prev.1, 100.0);
+        let honest_out = prev.1 - honest_n1;
+
+        let (kind, excess) = check_reserves(prev, (n0, prev.1 - honest_out * 1.5), FEE).unwrap();
+        assert_eq!(kind, AnomalyKind::KInvariantViolation);
+        assert!((excess - 50.0).abs() < 1e-6);
+
+        // Within the tolerance for lower-fee forks
+        assert_eq!(
+            check_reserves(prev, (n0, prev.1 - honest_out * 1.002), FEE),
+            None
+        );
+    }
+}
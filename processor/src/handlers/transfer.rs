@@ -1,25 +1,35 @@
 //! Transfer event handler
 //!
 //! Handles ERC20 Transfer events to:
-//! - Track holder balances
-//! - Identify snipers (early buyers)
+//! - Track holder balances and holders entering/exiting (churn), leaving out
+//!   known exchange, router, bridge, locker and burn addresses
+//! - Identify snipers (early buyers) and keep bot-held supply current
 //! - Track dev wallet movements
+//! - Record large transfers to/from exchange wallets
 //! - Create wallet activity records
 
 use chrono::Utc;
 use sqlx::types::BigDecimal;
-use std::str::FromStr;
 
-use indexer_db::entity::{
-    alert::{AlertEvent, AlertType, NewAlert},
-    token::Token,
-    token_holder::{NewTokenHolder, TokenHolder},
-    wallet_activity::{NewWalletActivity, WalletActivity},
+use alloy::primitives::address;
+use indexer_db::{
+    entity::{
+        alert::{AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, DevSellMetadata},
+        bot_wallet::BotWallet,
+        holder_churn::HolderChurn,
+        price_snapshot::PriceSnapshot,
+        token::Token,
+        token_holder::{NewTokenHolder, TokenHolder},
+        wallet_activity::{NewWalletActivity, WalletActivity},
+        wallet_profile::WalletProfile,
+    },
+    Address20,
 };
 
 use crate::events::transfer::TransferEvent;
 
-use super::{HandlerContext, HandlerResult};
+use super::{cex_flow, swap::normalize, HandlerContext, HandlerResult};
 
 /// Parse a hex string (0x...) to BigDecimal
 fn hex_to_bigdecimal(hex: &str) -> BigDecimal {
@@ -34,23 +44,46 @@ fn hex_to_bigdecimal(hex: &str) -> BigDecimal {
     }
 }
 
+/// Furthest a price snapshot may be from a transfer to price it, in seconds
+pub const PRICE_MAX_GAP_SECS: i64 = 3600;
+
+/// Largest USD value `wallet_activity.amount_usd` (DECIMAL(30, 2)) holds
+const MAX_AMOUNT_USD: u128 = 10u128.pow(28);
+
+/// USD value of `raw` token units at `price_usd`, to the cent. `None` when it
+/// doesn't fit, which only junk prices on spam tokens produce.
+fn transfer_usd(raw: &BigDecimal, decimals: i16, price_usd: &BigDecimal) -> Option<BigDecimal> {
+    let usd = (normalize(raw, decimals) * price_usd).round(2);
+    (usd < BigDecimal::from(MAX_AMOUNT_USD)).then_some(usd)
+}
+
+/// Recompute the token's sniper ratio after sniper-held supply moved
+async fn refresh_sniper_ratio(ctx: &HandlerContext, token_address: &Address20) {
+    let written = Token::refresh_sniper_ratio(token_address, &ctx.db_pool).await;
+    if let Err(e) = ctx.entities.store_token(token_address, written) {
+        tracing::error!("Failed to refresh sniper ratio: {}", e);
+    }
+}
+
 /// Zero address constant
-const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+const ZERO_ADDRESS: Address20 = Address20::ZERO;
 
 /// Dead address (burn)
-const DEAD_ADDRESS: &str = "0x000000000000000000000000000000000000dead";
+const DEAD_ADDRESS: Address20 =
+    Address20::new(address!("000000000000000000000000000000000000dead").0 .0);
 
 /// Process a Transfer event
 ///
 /// 1. Update sender's balance (decrease)
 /// 2. Update recipient's balance (increase)
-/// 3. Check for sniper activity (early blocks)
-/// 4. Check for dev sells
-/// 5. Create wallet activity records
+/// 3. Check for sniper activity (buys within the sniper window)
+/// 4. Record holders entering/exiting
+/// 5. Check for dev sells
+/// 6. Create wallet activity records
 pub async fn handle(ctx: &HandlerContext, event: &TransferEvent) -> HandlerResult<()> {
-    let token_address = event.token.clone();
-    let from_address = event.from.clone();
-    let to_address = event.to.clone();
+    let token_address = event.token;
+    let from_address = event.from;
+    let to_address = event.to;
     let value = hex_to_bigdecimal(&event.value);
 
     // Skip zero-value transfers
@@ -58,142 +91,309 @@ pub async fn handle(ctx: &HandlerContext, event: &TransferEvent) -> HandlerResul
         return Ok(());
     }
 
+    // In sampling mode only sampled tokens are tracked; a rescan replays
+    // whatever token it was asked for
+    if ctx.transfer_sampling
+        && ctx.replay.is_none()
+        && !ctx.sample.contains(&token_address, &ctx.db_pool).await?
+    {
+        return Ok(());
+    }
+
     // Check if this token is being tracked
-    let token = match Token::find_by_address(&token_address, &ctx.db_pool).await? {
+    let token = match ctx.find_token(&token_address).await? {
         Some(t) => t,
         None => {
             // Token not in our database, skip
             return Ok(());
         }
     };
+    // Holders of an archived token are in cold storage; restore them first
+    ctx.revive(&token).await;
 
     let block_number = event.block.parse::<i64>().unwrap_or(0);
-    let token_creation_block = token.block_number.unwrap_or(0);
-    let token_symbol = token.symbol.clone().unwrap_or_else(|| token_address[..10].to_string());
+    let token_symbol = token.symbol.clone().unwrap_or_else(|| token_address.short());
+
+    // Price the transfer as of its block, not as of processing
+    let timestamp = event.block_timestamp.unwrap_or_else(Utc::now);
+    let price = match PriceSnapshot::find_closest(
+        &token_address,
+        timestamp,
+        PRICE_MAX_GAP_SECS,
+        &ctx.db_pool,
+    )
+    .await
+    {
+        Ok(snapshot) => snapshot.and_then(|s| s.price_usd),
+        Err(e) => {
+            tracing::error!("Failed to look up transfer price: {}", e);
+            None
+        }
+    };
+    let amount_usd = match price {
+        Some(price) => {
+            let decimals = ctx.token_decimals(&token_address, Some(&token)).await?;
+            transfer_usd(&value, decimals, &price)
+        }
+        None => None,
+    };
 
     // Determine if this is a mint (from zero address)
-    let is_mint = from_address.to_lowercase() == ZERO_ADDRESS;
+    let is_mint = from_address == ZERO_ADDRESS;
 
-    // Determine if this is a burn (to zero or dead address)
-    let is_burn = to_address.to_lowercase() == ZERO_ADDRESS
-        || to_address.to_lowercase() == DEAD_ADDRESS;
+    // Determine if this is a burn (to zero, dead or another known burn address)
+    let is_burn =
+        to_address == ZERO_ADDRESS || to_address == DEAD_ADDRESS || ctx.known.is_burn(&to_address);
 
-    // Check if sender is a sniper (bought in first 2 blocks)
-    let is_from_sniper = if !is_mint {
-        match TokenHolder::find_sniper_holders(&token_address, &ctx.db_pool).await {
-            Ok(snipers) => snipers.iter().any(|s| s.wallet_address.to_lowercase() == from_address.to_lowercase()),
-            Err(_) => false,
+    // Exchange wallets, routers, bridges and lockers aren't holders
+    let from_is_holder = ctx.known.get(&from_address).is_none();
+    let to_is_holder = ctx.known.get(&to_address).is_none();
+
+    // First sightings of both wallets, for wallet age
+    for (wallet, seen) in [(&from_address, !is_mint), (&to_address, !is_burn)] {
+        if seen && ctx.known.get(wallet).is_none() {
+            if let Err(e) =
+                WalletProfile::record_seen(wallet, block_number, timestamp, &ctx.db_pool).await
+            {
+                tracing::error!("Failed to record wallet profile: {}", e);
+            }
         }
-    } else {
-        false
-    };
+    }
 
     // Check if sender is a dev
     let is_from_dev = if !is_mint {
         match TokenHolder::find_dev_holders(&token_address, &ctx.db_pool).await {
-            Ok(devs) => devs.iter().any(|d| d.wallet_address.to_lowercase() == from_address.to_lowercase()),
+            Ok(devs) => devs.iter().any(|d| d.wallet_address == from_address),
             Err(_) => false,
         }
     } else {
         false
     };
 
+    let zero = BigDecimal::from(0);
+    let mut holders_entered = 0;
+    let mut holders_exited = 0;
+
     // Update sender's balance (if not mint)
     if !is_mint {
-        // For simplicity, we're just recording activity
-        // Full balance tracking would require RPC calls to get current balance
+        // Balances are only known for wallets we've seen receive the token
+        let previous = if from_is_holder && ctx.tracks_holders() {
+            TokenHolder::find_balance(&token_address, &from_address, &ctx.db_pool).await
+        } else {
+            Ok(None)
+        };
+        match previous {
+            Ok(Some(previous)) => {
+                let balance = (&previous - &value).max(zero.clone());
+                if previous > zero && balance == zero {
+                    holders_exited += 1;
+                }
+                match TokenHolder::update_balance(
+                    &token_address,
+                    &from_address,
+                    &balance,
+                    Some(event.source),
+                    &ctx.db_pool,
+                )
+                .await
+                {
+                    // A sniper sold or moved tokens, so the persisted ratio is stale
+                    Ok(h) if h.is_sniper == Some(true) => {
+                        refresh_sniper_ratio(ctx, &token_address).await
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Failed to update sender balance: {}", e),
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to read sender balance: {}", e),
+        }
+
         let activity = NewWalletActivity {
-            wallet_address: from_address.clone(),
-            tx_hash: event.tx_hash.clone(),
+            wallet_address: from_address,
+            tx_hash: event.tx_hash,
             block_number,
-            timestamp: Utc::now(),
+            timestamp,
             action: "transfer_out".to_string(),
-            token_address: token_address.clone(),
+            token_address,
             token_symbol: Some(token_symbol.clone()),
             amount_tokens: Some(value.clone()),
-            amount_usd: None, // Would need price lookup
+            amount_usd: amount_usd.clone(),
+            source_log_id: event.source.log_id,
         };
 
         if let Err(e) = WalletActivity::create(&activity, &ctx.db_pool).await {
             // Might be duplicate
-            println!("Wallet activity (from) result: {}", e);
+            tracing::info!("Wallet activity (from) result: {}", e);
         }
     }
 
     // Update recipient's balance (if not burn)
     if !is_burn {
-        // Determine if recipient is a sniper (receiving in first 2 blocks after token creation)
-        let is_sniper = block_number <= token_creation_block + 2 && !is_mint;
-
-        let holder = NewTokenHolder {
-            token_address: token_address.clone(),
-            wallet_address: to_address.clone(),
-            balance: value.clone(), // This should be cumulative, simplified here
-            is_dev: false,
-            is_sniper,
-            is_contract: false, // Would need to check via RPC
-            first_buy_block: Some(block_number),
-        };
+        if to_is_holder && ctx.tracks_holders() {
+            // Recipient is a sniper if they bought from the pair within the sniper window
+            // after pair creation. Mints, liquidity adds and the creator never count.
+            let is_sniper = !is_mint
+                && token.pair_address == Some(from_address)
+                && token.creator_address != Some(to_address)
+                && token
+                    .block_number
+                    .is_some_and(|created| ctx.sniper_window.contains(created, block_number));
 
-        if let Err(e) = TokenHolder::upsert(&holder, &ctx.db_pool).await {
-            eprintln!("Failed to upsert token holder: {}", e);
-        }
+            let previous =
+                match TokenHolder::find_balance(&token_address, &to_address, &ctx.db_pool).await {
+                    Ok(previous) => previous.unwrap_or_else(|| zero.clone()),
+                    Err(e) => {
+                        tracing::error!("Failed to read recipient balance: {}", e);
+                        zero.clone()
+                    }
+                };
+            if previous <= zero {
+                holders_entered += 1;
+            }
+
+            let holder = NewTokenHolder {
+                token_address,
+                wallet_address: to_address,
+                balance: &previous + &value,
+                is_dev: false,
+                is_sniper,
+                is_contract: false, // Would need to check via RPC
+                first_buy_block: Some(block_number),
+                source: Some(event.source),
+            };
 
-        // Mark as sniper if applicable
-        if is_sniper {
-            if let Err(e) = TokenHolder::mark_as_sniper(&token_address, &to_address, &ctx.db_pool).await {
-                eprintln!("Failed to mark sniper: {}", e);
+            match TokenHolder::upsert(&holder, &ctx.db_pool).await {
+                // Sniper-held supply changed, so the persisted ratio is stale
+                Ok(h) if h.is_sniper == Some(true) => {
+                    refresh_sniper_ratio(ctx, &token_address).await
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to upsert token holder: {}", e),
             }
         }
 
         // Create wallet activity for recipient
         let activity = NewWalletActivity {
-            wallet_address: to_address.clone(),
-            tx_hash: event.tx_hash.clone(),
+            wallet_address: to_address,
+            tx_hash: event.tx_hash,
             block_number,
-            timestamp: Utc::now(),
+            timestamp,
             action: "transfer_in".to_string(),
-            token_address: token_address.clone(),
+            token_address,
             token_symbol: Some(token_symbol.clone()),
             amount_tokens: Some(value.clone()),
-            amount_usd: None,
+            amount_usd: amount_usd.clone(),
+            source_log_id: event.source.log_id,
         };
 
         if let Err(e) = WalletActivity::create(&activity, &ctx.db_pool).await {
-            println!("Wallet activity (to) result: {}", e);
+            tracing::info!("Wallet activity (to) result: {}", e);
+        }
+    }
+
+    // Bot-held supply moved, so the organic holder count and bot share are stale
+    if ctx.tracks_holders() {
+        match BotWallet::contains_any(&[from_address, to_address], &ctx.db_pool).await {
+            Ok(true) => {
+                let written = Token::refresh_holder_metrics(&token_address, &ctx.db_pool).await;
+                if let Err(e) = ctx.entities.store_token(&token_address, written) {
+                    tracing::error!("Failed to refresh bot holdings: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => tracing::error!("Failed to look up bot wallets: {}", e),
+        }
+    }
+
+    if holders_entered > 0 || holders_exited > 0 {
+        if let Err(e) = HolderChurn::record(
+            &token_address,
+            timestamp,
+            holders_entered,
+            holders_exited,
+            &ctx.db_pool,
+        )
+        .await
+        {
+            tracing::error!("Failed to record holder churn: {}", e);
+        } else {
+            let written = Token::refresh_holder_churn(&token_address, &ctx.db_pool).await;
+            if let Err(e) = ctx.entities.store_token(&token_address, written) {
+                tracing::error!("Failed to refresh holder churn: {}", e);
+            }
         }
     }
 
+    if let Err(e) = cex_flow::detect(ctx, &token, event, &value, block_number).await {
+        tracing::error!("Failed to record CEX flow: {}", e);
+    }
+
     // Create alert for dev sell
     if is_from_dev && !is_burn {
+        let written = Token::record_dev_sell(&token_address, timestamp, &ctx.db_pool).await;
+        if let Err(e) = ctx.entities.store_token(&token_address, written) {
+            tracing::error!("Failed to record dev sell: {}", e);
+        }
+
         let alert = NewAlert {
             alert_type: AlertType::DevSell.as_str().to_string(),
-            token_address: Some(token_address.clone()),
+            token_address: Some(token_address),
             token_symbol: Some(token_symbol.clone()),
-            wallet_address: Some(from_address.clone()),
+            wallet_address: Some(from_address),
             title: format!("Dev Sell: {}", token_symbol),
             message: Some(format!(
                 "Developer wallet transferred {} tokens at block {}",
                 value, block_number
             )),
             bee_score: token.bee_score,
-            amount_usd: None,
+            amount_usd,
             change_percent: None,
-            metadata: None,
+            metadata: Some(AlertMetadata::DevSell(DevSellMetadata {
+                tx_hash: event.tx_hash,
+                to: to_address,
+                amount: value.to_string(),
+            })),
+            source: Some(event.source),
         };
 
-        if let Err(e) = AlertEvent::create(&alert, &ctx.db_pool).await {
-            eprintln!("Failed to create dev sell alert: {}", e);
+        if let Err(e) = ctx.create_alert(&alert).await {
+            tracing::error!("Failed to create dev sell alert: {}", e);
         }
     }
 
-    println!(
+    tracing::debug!(
         "Processed Transfer: {} -> {} ({} tokens of {})",
-        if is_mint { "MINT" } else { &from_address[..10] },
-        if is_burn { "BURN" } else { &to_address[..10] },
+        if is_mint { "MINT".to_string() } else { from_address.short() },
+        if is_burn { "BURN".to_string() } else { to_address.short() },
         value,
         token_symbol
     );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn transfers_are_priced_in_whole_tokens() {
+        let price = BigDecimal::from_str("0.0125").unwrap();
+        // 1,000.5 tokens of 18 decimals
+        let raw = BigDecimal::from_str("1000500000000000000000").unwrap();
+        assert_eq!(
+            transfer_usd(&raw, 18, &price),
+            Some(BigDecimal::from_str("12.51").unwrap())
+        );
+        assert_eq!(
+            transfer_usd(&BigDecimal::from(5_000_000), 6, &BigDecimal::from(2)),
+            Some(BigDecimal::from(10))
+        );
+
+        let junk = BigDecimal::from_str("1e30").unwrap();
+        assert_eq!(transfer_usd(&raw, 18, &junk), None);
+    }
+}
@@ -0,0 +1,226 @@
+//! Name/symbol impersonation detection
+//!
+//! Scam launches often borrow the name or ticker of a token people already
+//! know ("PEPE2", "Pepe Coin", "PEPE"). Each new token is compared against the
+//! top `TOP_TOKENS` tokens by market cap and the trending top list; a name or
+//! symbol within `SIMILARITY_THRESHOLD` (normalized Levenshtein) of one of
+//! them is flagged, alerted, and costs the token `SAFETY_PENALTY` safety
+//! points.
+
+use std::str::FromStr;
+
+use indexer_db::{
+    entity::{
+        alert::{AlertEvent, AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, ImpersonationMetadata},
+        token::Token,
+        token_impersonation::{ImpersonationTarget, NewTokenImpersonation, TokenImpersonation},
+    },
+    Address20,
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+/// Tokens by market cap a new launch is compared against
+pub const TOP_TOKENS: i64 = 100;
+
+/// Similarity (0-1) at or above which a name or symbol counts as copied
+pub const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// Safety points deducted from a flagged token
+pub const SAFETY_PENALTY: u8 = 20;
+
+/// Names/symbols this short after normalization are too generic to compare
+const MIN_LENGTH: usize = 3;
+
+/// The closest established token to a new one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub target_address: Address20,
+    pub target_source: String,
+    pub target_symbol: Option<String>,
+    /// `name` or `symbol`
+    pub field: &'static str,
+    pub similarity: f64,
+}
+
+/// Lowercase with everything but letters and digits stripped
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Edit distance between `a` and `b`, in chars
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Similarity (0-1) of two names after normalization; 0 when either is too
+/// short to tell
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize(a), normalize(b));
+    let (len_a, len_b) = (a.chars().count(), b.chars().count());
+    if len_a < MIN_LENGTH || len_b < MIN_LENGTH {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / len_a.max(len_b) as f64
+}
+
+/// The target whose name or symbol is closest to the token's, if any is
+/// within `SIMILARITY_THRESHOLD`
+pub fn best_match(
+    address: &Address20,
+    name: Option<&str>,
+    symbol: Option<&str>,
+    targets: &[ImpersonationTarget],
+) -> Option<Match> {
+    let mut best: Option<Match> = None;
+
+    for target in targets.iter().filter(|t| t.address != *address) {
+        let fields = [
+            ("name", name, target.name.as_deref()),
+            ("symbol", symbol, target.symbol.as_deref()),
+        ];
+        for (field, ours, theirs) in fields {
+            let (Some(ours), Some(theirs)) = (ours, theirs) else {
+                continue;
+            };
+            let score = similarity(ours, theirs);
+            if score >= SIMILARITY_THRESHOLD && best.as_ref().is_none_or(|b| score > b.similarity) {
+                best = Some(Match {
+                    target_address: target.address,
+                    target_source: target.source.clone(),
+                    target_symbol: target.symbol.clone(),
+                    field,
+                    similarity: score,
+                });
+            }
+        }
+    }
+
+    best
+}
+
+/// Compare a new token against established ones, flagging and alerting on a
+/// match. Returns the match, if any.
+pub async fn check(token: &Token, db_pool: &Pool<Postgres>) -> Result<Option<Match>, sqlx::Error> {
+    let targets = TokenImpersonation::find_targets(TOP_TOKENS, db_pool).await?;
+    let Some(found) = best_match(
+        &token.address,
+        token.name.as_deref(),
+        token.symbol.as_deref(),
+        &targets,
+    ) else {
+        return Ok(None);
+    };
+
+    let flag = NewTokenImpersonation {
+        token_address: token.address,
+        target_address: found.target_address,
+        target_source: found.target_source.clone(),
+        matched_field: found.field.to_string(),
+        similarity: BigDecimal::from_str(&format!("{:.4}", found.similarity)).unwrap_or_default(),
+    };
+
+    // Already flagged on an earlier pass: no second alert
+    if TokenImpersonation::create(&flag, db_pool).await?.is_none() {
+        return Ok(Some(found));
+    }
+
+    let short_address = token.address.short();
+    let symbol = token.symbol.as_deref().unwrap_or(&short_address);
+    let target_short = found.target_address.short();
+    let target_symbol = found.target_symbol.as_deref().unwrap_or(&target_short);
+    let alert = NewAlert {
+        alert_type: AlertType::Impersonation.as_str().to_string(),
+        token_address: Some(token.address),
+        token_symbol: token.symbol.clone(),
+        wallet_address: None,
+        title: format!(
+            "Possible impersonation: {} copies {}",
+            symbol, target_symbol
+        ),
+        message: Some(format!(
+            "{}'s {} is {:.0}% similar to {} ({} token), a probable copycat scam",
+            symbol,
+            found.field,
+            found.similarity * 100.0,
+            target_symbol,
+            found.target_source
+        )),
+        bee_score: token.bee_score,
+        amount_usd: None,
+        change_percent: None,
+        metadata: Some(AlertMetadata::Impersonation(ImpersonationMetadata {
+            target_address: found.target_address,
+            target_source: found.target_source.to_string(),
+            matched_field: found.field.to_string(),
+            similarity: found.similarity,
+        })),
+        source: None,
+    };
+    AlertEvent::create(&alert, db_pool).await?;
+
+    Ok(Some(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(n: u8, name: &str, symbol: &str) -> ImpersonationTarget {
+        ImpersonationTarget {
+            address: Address20::new([n; 20]),
+            name: Some(name.to_string()),
+            symbol: Some(symbol.to_string()),
+            source: "top".to_string(),
+        }
+    }
+
+    #[test]
+    fn similarity_ignores_case_and_punctuation() {
+        assert_eq!(similarity("PEPE", "pepe"), 1.0);
+        assert_eq!(similarity("Shiba Inu", "SHIBA-INU"), 1.0);
+        assert_eq!(similarity("PEPE", "PEPE2"), 0.8);
+        assert!(similarity("PEPE", "DOGE") < 0.5);
+        // Too short to mean anything
+        assert_eq!(similarity("AI", "AI"), 0.0);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn closest_target_above_threshold_wins() {
+        let targets = [
+            target(1, "Pepe", "PEPE"),
+            target(2, "Dogecoin", "DOGE"),
+            target(3, "Floki Inu", "FLOKI"),
+        ];
+        let new = Address20::new([9; 20]);
+
+        let found = best_match(&new, Some("Dogecoin"), Some("DOGEX"), &targets).unwrap();
+        assert_eq!(found.target_address, Address20::new([2; 20]));
+        assert_eq!(found.field, "name");
+        assert_eq!(found.similarity, 1.0);
+
+        let found = best_match(&new, Some("Frog Money"), Some("PEPE2"), &targets).unwrap();
+        assert_eq!(found.field, "symbol");
+        assert_eq!(found.target_symbol.as_deref(), Some("PEPE"));
+
+        assert!(best_match(&new, Some("Moon Rocket"), Some("MOON"), &targets).is_none());
+        // A target is never matched against itself
+        assert!(best_match(&Address20::new([1; 20]), Some("Pepe"), None, &targets).is_none());
+    }
+}
@@ -0,0 +1,266 @@
+//! Job worker
+//!
+//! Long-running tasks are queued in `jobs` by the API (`/api/jobs`,
+//! `POST /api/tokens/track`) and run here one at a time, oldest first, every
+//! `JOB_WORKER_INTERVAL` seconds until none is pending:
+//!
+//! - `token_import`: index a token's pairs and replay its history (see
+//!   [`token_import`])
+//! - `rescore`: recompute the BeeScore of the given tokens, or of every token
+//! - `reconcile`: check one token's top holders against the chain
+//!
+//! Runners report progress as they go. A report that finds cancellation
+//! requested stops the job, keeping what it did so far as its result; an
+//! import's rescan reports after every chunk of blocks it replays. A running
+//! job that hasn't reported for `JOB_STALE_SECS` was left by a processor
+//! that stopped, and is run again from the start.
+
+use std::{env, error::Error};
+
+use alloy::providers::Provider;
+use indexer_db::{
+    entity::{
+        job::{Job, JobKind},
+        token::Token,
+        token_rescan::TokenRescan,
+    },
+    Address20,
+};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    defaults,
+    reconcile::{self, ReconcileConfig},
+    rescan,
+    rpc::Rpc,
+    service, token_import,
+};
+
+type JobError = Box<dyn Error + Send + Sync>;
+
+/// Tokens re-scored between progress reports
+const RESCORE_PAGE: i64 = 100;
+
+/// How a job ended, with its result
+enum Outcome {
+    Done(JsonValue),
+    Cancelled(JsonValue),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportParams {
+    address: Address20,
+    from_block: Option<i64>,
+    to_block: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RescoreParams {
+    /// Every token when absent
+    tokens: Option<Vec<Address20>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReconcileParams {
+    address: Address20,
+}
+
+/// Run pending jobs until none is left
+pub async fn run(db_pool: &Pool<Postgres>) {
+    let stale_secs = env::var("JOB_STALE_SECS")
+        .unwrap_or_else(|_| defaults::JOB_STALE_SECS.to_string())
+        .parse::<i64>()
+        .unwrap_or(600)
+        .max(1);
+    loop {
+        match Job::claim_next(stale_secs, db_pool).await {
+            Ok(Some(job)) => run_job(&job, db_pool).await,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("Failed to claim a job: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Run one claimed job and record its outcome
+async fn run_job(job: &Job, db_pool: &Pool<Postgres>) {
+    tracing::info!("Running job #{} ({})", job.id, job.kind);
+
+    let outcome = match JobKind::parse(&job.kind) {
+        Some(JobKind::TokenImport) => import(job, db_pool).await,
+        Some(JobKind::Rescore) => rescore(job, db_pool).await,
+        Some(JobKind::Reconcile) => reconcile_holders(job, db_pool).await,
+        None => Err(format!("Unknown job kind `{}`", job.kind).into()),
+    };
+
+    let recorded = match &outcome {
+        Ok(Outcome::Done(result)) => Job::finish(job.id, result, db_pool).await,
+        Ok(Outcome::Cancelled(result)) => Job::mark_cancelled(job.id, result, db_pool).await,
+        Err(e) => Job::fail(job.id, &e.to_string(), db_pool).await,
+    };
+    match outcome {
+        Ok(Outcome::Done(_)) => tracing::info!("Job #{} ({}) done", job.id, job.kind),
+        Ok(Outcome::Cancelled(_)) => tracing::info!("Job #{} ({}) cancelled", job.id, job.kind),
+        Err(e) => tracing::error!("Job #{} ({}) failed: {}", job.id, job.kind, e),
+    }
+    if let Err(e) = recorded {
+        tracing::error!("Failed to record the outcome of job #{}: {}", job.id, e);
+    }
+}
+
+fn params<T: for<'de> Deserialize<'de>>(job: &Job) -> Result<T, JobError> {
+    serde_json::from_value(job.params.0.clone())
+        .map_err(|e| format!("Invalid params for {}: {}", job.kind, e).into())
+}
+
+/// Index the token's pairs, then run the rescan replaying its history. Two
+/// steps; cancelling between them drops the rescan, cancelling during the
+/// second stops it.
+async fn import(job: &Job, db_pool: &Pool<Postgres>) -> Result<Outcome, JobError> {
+    let ImportParams {
+        address,
+        from_block,
+        to_block,
+    } = params(job)?;
+
+    let (rescan, pairs) =
+        token_import::import_token(&address, from_block, to_block, db_pool).await?;
+    let mut result = json!({ "pairsIndexed": pairs, "rescanId": rescan.id });
+    if Job::report_progress(job.id, 1, Some(2), db_pool).await? {
+        if TokenRescan::claim(rescan.id, db_pool).await?.is_some() {
+            let note = format!("Cancelled with job #{}", job.id);
+            TokenRescan::fail(rescan.id, 0, &note, db_pool).await?;
+        }
+        return Ok(Outcome::Cancelled(result));
+    }
+
+    // Run right away unless the rescan schedule got to it first
+    if let Some(claimed) = TokenRescan::claim(rescan.id, db_pool).await? {
+        rescan::execute(&claimed, Some(job.id), db_pool).await;
+        let finished = TokenRescan::find_by_id(rescan.id, db_pool)
+            .await?
+            .ok_or("Rescan disappeared")?;
+        result["logsReplayed"] = json!(finished.logs_replayed);
+        let cancelled = Job::find_by_id(job.id, db_pool)
+            .await?
+            .is_some_and(|job| job.cancel_requested);
+        if finished.status == "failed" && cancelled {
+            return Ok(Outcome::Cancelled(result));
+        }
+        if finished.status == "failed" {
+            let error = finished.error.unwrap_or_default();
+            return Err(format!("Rescan #{} failed: {}", rescan.id, error).into());
+        }
+    }
+    Job::report_progress(job.id, 2, Some(2), db_pool).await?;
+    Ok(Outcome::Done(result))
+}
+
+/// Recompute BeeScores, reporting progress a page of tokens at a time
+async fn rescore(job: &Job, db_pool: &Pool<Postgres>) -> Result<Outcome, JobError> {
+    let RescoreParams { tokens } = params(job)?;
+    let ctx = service::create_handler_context(db_pool.clone()).await?;
+    let total = tokens.as_ref().map(|tokens| tokens.len() as i32);
+
+    let mut done = 0;
+    let mut failed = 0;
+    let mut after: Option<Address20> = None;
+    loop {
+        let page = match &tokens {
+            Some(tokens) => tokens
+                .iter()
+                .skip(done as usize)
+                .take(RESCORE_PAGE as usize)
+                .copied()
+                .collect(),
+            None => Token::find_addresses_after(after.as_ref(), RESCORE_PAGE, db_pool).await?,
+        };
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = Some(*last);
+
+        for token in &page {
+            if let Err(e) = service::update_token_score(token, &ctx).await {
+                tracing::error!("Job #{}: failed to re-score {}: {}", job.id, token, e);
+                failed += 1;
+            }
+        }
+        done += page.len() as i32;
+        if Job::report_progress(job.id, done, total, db_pool).await? {
+            return Ok(Outcome::Cancelled(
+                json!({ "rescored": done - failed, "failed": failed }),
+            ));
+        }
+    }
+
+    Ok(Outcome::Done(
+        json!({ "rescored": done - failed, "failed": failed }),
+    ))
+}
+
+/// Check the token's top holders at the chain head
+async fn reconcile_holders(job: &Job, db_pool: &Pool<Postgres>) -> Result<Outcome, JobError> {
+    let ReconcileParams { address } = params(job)?;
+    if Token::find_by_address(&address, db_pool).await?.is_none() {
+        return Err(format!("Token {} is not indexed", address).into());
+    }
+
+    let rpc = Rpc::shared();
+    let block = rpc.call(|p| async move { p.get_block_number().await }).await?;
+    let config = ReconcileConfig::from_env();
+    let corrected = reconcile::reconcile_token(&rpc, &address, block, &config, db_pool).await?;
+    Job::report_progress(job.id, 1, Some(1), db_pool).await?;
+    Ok(Outcome::Done(
+        json!({ "blockNumber": block, "balancesCorrected": corrected }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::{chrono::Utc, Json};
+
+    use super::*;
+
+    fn job(kind: JobKind, params: JsonValue) -> Job {
+        Job {
+            id: 1,
+            kind: kind.name().to_string(),
+            params: Json(params),
+            dedupe_key: None,
+            status: "running".to_string(),
+            progress_done: 0,
+            progress_total: None,
+            result: None,
+            error: None,
+            cancel_requested: false,
+            requested_at: Utc::now(),
+            started_at: None,
+            updated_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+
+    #[test]
+    fn params_are_read_as_the_api_queues_them() {
+        let address = "0x0101010101010101010101010101010101010101";
+        let import = job(
+            JobKind::TokenImport,
+            json!({ "address": address, "fromBlock": 100 }),
+        );
+        let parsed: ImportParams = params(&import).unwrap();
+        assert_eq!(parsed.address, Address20::new([1; 20]));
+        assert_eq!((parsed.from_block, parsed.to_block), (Some(100), None));
+
+        let everything: RescoreParams = params(&job(JobKind::Rescore, json!({}))).unwrap();
+        assert!(everything.tokens.is_none());
+
+        let missing = params::<ReconcileParams>(&job(JobKind::Reconcile, json!({})));
+        assert!(missing.is_err());
+    }
+}
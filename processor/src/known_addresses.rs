@@ -0,0 +1,100 @@
+//! Known-address directory
+//!
+//! Exchange wallets, routers, bridges, lockers and burn addresses are loaded
+//! from the `known_addresses` table. Transfers touching them don't change the
+//! holder set, and exchange transfers are reported as CEX inflows/outflows.
+
+use std::collections::HashMap;
+
+use indexer_db::{
+    entity::known_address::{KnownAddress, KnownAddressKind},
+    Address20,
+};
+use sqlx::{Executor, Postgres};
+
+use crate::error::AppError;
+
+/// Known addresses of one chain, by address
+#[derive(Debug, Clone, Default)]
+pub struct KnownAddresses {
+    by_address: HashMap<Address20, KnownAddress>,
+}
+
+impl KnownAddresses {
+    /// Load the directory of `chain_id` from `known_addresses`
+    pub async fn load<'c, E>(chain_id: i64, connection: E) -> Result<Self, AppError>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let rows = KnownAddress::find_by_chain(chain_id, connection).await?;
+        Ok(Self::from_rows(rows))
+    }
+
+    pub fn from_rows(rows: Vec<KnownAddress>) -> Self {
+        Self {
+            by_address: rows.into_iter().map(|row| (row.address, row)).collect(),
+        }
+    }
+
+    /// Directory entry for `address`, if it's a known address
+    pub fn get(&self, address: &Address20) -> Option<&KnownAddress> {
+        self.by_address.get(address)
+    }
+
+    /// Whether `address` is of the given kind
+    pub fn is(&self, address: &Address20, kind: KnownAddressKind) -> bool {
+        self.get(address).is_some_and(|known| known.is(kind))
+    }
+
+    /// Whether `address` is a router or an aggregator, the contracts people
+    /// trade through
+    pub fn is_router(&self, address: &Address20) -> bool {
+        self.is(address, KnownAddressKind::Router) || self.is(address, KnownAddressKind::Aggregator)
+    }
+
+    /// Whether `address` is a burn address
+    pub fn is_burn(&self, address: &Address20) -> bool {
+        self.is(address, KnownAddressKind::Burn)
+    }
+
+    /// Exchange name of `address`, if it's a CEX wallet
+    pub fn cex_name(&self, address: &Address20) -> Option<&str> {
+        self.get(address)
+            .filter(|known| known.is(KnownAddressKind::Cex))
+            .map(|known| known.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(kind: KnownAddressKind, name: &str, byte: u8) -> KnownAddress {
+        KnownAddress {
+            id: byte as i32,
+            chain_id: 56,
+            kind: kind.as_str().to_string(),
+            name: name.to_string(),
+            address: Address20::new([byte; 20]),
+        }
+    }
+
+    #[test]
+    fn addresses_are_looked_up_by_kind() {
+        let known = KnownAddresses::from_rows(vec![
+            row(KnownAddressKind::Cex, "Binance", 1),
+            row(KnownAddressKind::Burn, "Dead address", 2),
+            row(KnownAddressKind::Router, "PancakeSwap V2", 3),
+            row(KnownAddressKind::Aggregator, "1inch", 5),
+        ]);
+
+        assert_eq!(known.cex_name(&Address20::new([1; 20])), Some("Binance"));
+        assert_eq!(known.cex_name(&Address20::new([3; 20])), None);
+        assert!(known.is_burn(&Address20::new([2; 20])));
+        assert!(!known.is_burn(&Address20::new([1; 20])));
+        assert!(known.get(&Address20::new([4; 20])).is_none());
+        assert!(known.is_router(&Address20::new([3; 20])));
+        assert!(known.is_router(&Address20::new([5; 20])));
+        assert!(!known.is_router(&Address20::new([1; 20])));
+    }
+}
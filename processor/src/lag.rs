@@ -0,0 +1,388 @@
+//! Processing lag SLO
+//!
+//! End-to-end lag is the time from a log's block timestamp to the processor
+//! finishing with it. Each event type is held to a target (`LAG_SLO_SECONDS`,
+//! overridable per type as `LAG_SLO_SWAP_SECONDS` etc.). Lags are collected
+//! into windows of `LAG_SLO_WINDOW_SECONDS`; a window whose p95 misses the
+//! target is a breach. Each closed window is written to `processing_lag` for
+//! export, and once an event type has been in breach for
+//! `LAG_SLO_BREACH_MINUTES` an alert is raised, so operators see the pipeline
+//! falling behind before users do.
+//!
+//! A log's lag is only known once it is processed, which says nothing while
+//! the processor is stuck. The age of the log at the head of the queue is
+//! sampled every poll as well, under [`QUEUE_HEAD`] with its own target
+//! (`LAG_SLO_QUEUE_HEAD_SECONDS`, else `LAG_SLO_SECONDS`).
+
+use std::{collections::HashMap, env};
+
+use chrono::{DateTime, Duration, Utc};
+use indexer_db::entity::{
+    alert::{AlertEvent, AlertType, NewAlert},
+    alert_metadata::{AlertMetadata, LagSloMetadata},
+    processing_lag::{NewProcessingLag, ProcessingLag},
+};
+use sqlx::{Pool, Postgres};
+
+use crate::defaults;
+
+/// Event types lag is measured for, as in their Redis channel names
+pub const EVENT_TYPES: [&str; 6] = [
+    "new_pair",
+    "swap",
+    "transfer",
+    "sync",
+    "liquidity",
+    "approval",
+];
+
+/// What queue head ages are recorded under
+pub const QUEUE_HEAD: &str = "queue_head";
+
+/// Event type of a Redis channel: `chain:events:swap` → `swap`
+pub fn event_type(channel: &str) -> &str {
+    channel.rsplit(':').next().unwrap_or(channel)
+}
+
+/// Lag targets and how long a breach may last before alerting
+#[derive(Debug, Clone)]
+pub struct LagSlo {
+    /// Target per event type, in seconds
+    targets: HashMap<&'static str, u64>,
+    window: Duration,
+    breach_after: Duration,
+}
+
+impl LagSlo {
+    pub fn from_env() -> Self {
+        let default_target = env::var("LAG_SLO_SECONDS")
+            .unwrap_or_else(|_| defaults::LAG_SLO_SECONDS.to_string())
+            .parse::<u64>()
+            .unwrap_or(60)
+            .max(1);
+        let targets = EVENT_TYPES
+            .iter()
+            .chain([&QUEUE_HEAD])
+            .map(|&event_type| {
+                let var = format!("LAG_SLO_{}_SECONDS", event_type.to_uppercase());
+                let target = env::var(var)
+                    .ok()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .filter(|&secs| secs > 0)
+                    .unwrap_or(default_target);
+                (event_type, target)
+            })
+            .collect();
+        let window = env::var("LAG_SLO_WINDOW_SECONDS")
+            .unwrap_or_else(|_| defaults::LAG_SLO_WINDOW_SECONDS.to_string())
+            .parse::<i64>()
+            .unwrap_or(60)
+            .max(1);
+        let breach_after = env::var("LAG_SLO_BREACH_MINUTES")
+            .unwrap_or_else(|_| defaults::LAG_SLO_BREACH_MINUTES.to_string())
+            .parse::<i64>()
+            .unwrap_or(5)
+            .max(0);
+
+        Self {
+            targets,
+            window: Duration::seconds(window),
+            breach_after: Duration::minutes(breach_after),
+        }
+    }
+
+    /// Target for an event type, in seconds; `None` for types not measured
+    pub fn target_secs(&self, event_type: &str) -> Option<u64> {
+        self.targets.get(event_type).copied()
+    }
+}
+
+/// An event type's run of breaching windows
+#[derive(Debug, Clone, Copy, Default)]
+struct Breach {
+    since: Option<DateTime<Utc>>,
+    alerted: bool,
+}
+
+/// A closed window, to be recorded
+#[derive(Debug, Clone)]
+pub struct WindowReport {
+    pub lag: NewProcessingLag,
+    /// The breach just outlasted `LAG_SLO_BREACH_MINUTES`
+    pub alert: bool,
+    /// The event type was alerted on and is back within target
+    pub recovered: bool,
+}
+
+/// Collects lag samples and tracks breaches across processing batches
+#[derive(Debug)]
+pub struct LagMonitor {
+    slo: LagSlo,
+    window_start: DateTime<Utc>,
+    /// Lags in the open window per event type, in ms
+    samples: HashMap<&'static str, Vec<i64>>,
+    breaches: HashMap<&'static str, Breach>,
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[i64], p: usize) -> i64 {
+    match sorted.len() {
+        0 => 0,
+        len => sorted[((len * p).div_ceil(100)).clamp(1, len) - 1],
+    }
+}
+
+impl LagMonitor {
+    pub fn new(slo: LagSlo, now: DateTime<Utc>) -> Self {
+        Self {
+            slo,
+            window_start: now,
+            samples: HashMap::new(),
+            breaches: HashMap::new(),
+        }
+    }
+
+    /// Record one processed log of a block at `block_timestamp`
+    pub fn record(
+        &mut self,
+        event_type: &str,
+        block_timestamp: DateTime<Utc>,
+        processed_at: DateTime<Utc>,
+    ) {
+        let Some(&event_type) = EVENT_TYPES.iter().find(|&&t| t == event_type) else {
+            return;
+        };
+        let lag_ms = (processed_at - block_timestamp).num_milliseconds().max(0);
+        self.samples.entry(event_type).or_default().push(lag_ms);
+    }
+
+    /// Record how long the log at the head of the queue has waited; an empty
+    /// queue is no wait at all
+    pub fn record_queue_head(&mut self, queued_at: Option<DateTime<Utc>>, now: DateTime<Utc>) {
+        let age_ms = queued_at.map_or(0, |at| (now - at).num_milliseconds().max(0));
+        self.samples.entry(QUEUE_HEAD).or_default().push(age_ms);
+    }
+
+    /// Close the open window if it has run its length. Event types without
+    /// samples keep their breach state: no logs says nothing about lag.
+    pub fn close_window(&mut self, now: DateTime<Utc>) -> Vec<WindowReport> {
+        if now - self.window_start < self.slo.window {
+            return Vec::new();
+        }
+        let window_start = self.window_start;
+        self.window_start = now;
+
+        let mut reports = Vec::new();
+        for (event_type, mut lags) in self.samples.drain() {
+            let Some(target_secs) = self.slo.target_secs(event_type) else {
+                continue;
+            };
+            lags.sort_unstable();
+            let p95 = percentile(&lags, 95);
+            let breach = self.breaches.entry(event_type).or_default();
+
+            let mut alert = false;
+            let mut recovered = false;
+            if p95 > target_secs as i64 * 1000 {
+                let since = *breach.since.get_or_insert(window_start);
+                if !breach.alerted && now - since >= self.slo.breach_after {
+                    breach.alerted = true;
+                    alert = true;
+                }
+            } else {
+                recovered = breach.alerted;
+                *breach = Breach::default();
+            }
+
+            reports.push(WindowReport {
+                lag: NewProcessingLag {
+                    event_type: event_type.to_string(),
+                    target_secs: target_secs as i32,
+                    samples: lags.len() as i32,
+                    p50_lag_ms: percentile(&lags, 50),
+                    p95_lag_ms: p95,
+                    max_lag_ms: lags.last().copied().unwrap_or(0),
+                    breached_since: breach.since,
+                },
+                alert,
+                recovered,
+            });
+        }
+
+        reports.sort_by(|a, b| a.lag.event_type.cmp(&b.lag.event_type));
+        reports
+    }
+
+    /// Close the window if due, export it and alert on breaches that
+    /// outlasted the limit
+    pub async fn flush(&mut self, db_pool: &Pool<Postgres>) {
+        for report in self.close_window(Utc::now()) {
+            let lag = &report.lag;
+            if let Err(e) = ProcessingLag::upsert(lag, db_pool).await {
+                tracing::error!("Failed to record {} processing lag: {}", lag.event_type, e);
+            }
+
+            if report.recovered {
+                tracing::info!(
+                    "Processing lag for {} is back within its {}s SLO (p95 {}ms)",
+                    lag.event_type, lag.target_secs, lag.p95_lag_ms
+                );
+            }
+            if report.alert {
+                tracing::error!(
+                    "Processing lag SLO breached for {}: p95 {}ms, target {}s",
+                    lag.event_type, lag.p95_lag_ms, lag.target_secs
+                );
+                if let Err(e) = AlertEvent::create(&breach_alert(lag), db_pool).await {
+                    tracing::error!("Failed to create lag SLO alert: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Alert for an event type whose lag has missed its target for too long
+fn breach_alert(lag: &NewProcessingLag) -> NewAlert {
+    let minutes = lag
+        .breached_since
+        .map(|since| (Utc::now() - since).num_minutes())
+        .unwrap_or(0);
+
+    NewAlert {
+        alert_type: AlertType::LagSloBreach.as_str().to_string(),
+        token_address: None,
+        token_symbol: None,
+        wallet_address: None,
+        title: format!("Processing lag SLO breached: {}", lag.event_type),
+        message: Some(if lag.event_type == QUEUE_HEAD {
+            format!(
+                "The oldest queued log has waited over {}s for {} minutes (p95 {:.1}s, max {:.1}s)",
+                lag.target_secs,
+                minutes,
+                lag.p95_lag_ms as f64 / 1000.0,
+                lag.max_lag_ms as f64 / 1000.0
+            )
+        } else {
+            format!(
+                "{} events have taken over {}s from block to processed for {} minutes (p95 {:.1}s, max {:.1}s)",
+                lag.event_type,
+                lag.target_secs,
+                minutes,
+                lag.p95_lag_ms as f64 / 1000.0,
+                lag.max_lag_ms as f64 / 1000.0
+            )
+        }),
+        bee_score: None,
+        amount_usd: None,
+        change_percent: None,
+        metadata: Some(AlertMetadata::LagSlo(LagSloMetadata {
+            event_type: lag.event_type.clone(),
+            target_secs: lag.target_secs,
+            p50_lag_ms: lag.p50_lag_ms,
+            p95_lag_ms: lag.p95_lag_ms,
+            max_lag_ms: lag.max_lag_ms,
+            samples: lag.samples,
+            breached_since: lag.breached_since,
+        })),
+        source: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slo(target_secs: u64, breach_minutes: i64) -> LagSlo {
+        LagSlo {
+            targets: EVENT_TYPES
+                .iter()
+                .chain([&QUEUE_HEAD])
+                .map(|&t| (t, target_secs))
+                .collect(),
+            window: Duration::seconds(60),
+            breach_after: Duration::minutes(breach_minutes),
+        }
+    }
+
+    /// Record `lags` (seconds) for swaps and close the window a minute later
+    fn window(monitor: &mut LagMonitor, start: DateTime<Utc>, lags: &[i64]) -> Vec<WindowReport> {
+        let processed_at = start + Duration::seconds(30);
+        for &lag in lags {
+            monitor.record("swap", processed_at - Duration::seconds(lag), processed_at);
+        }
+        monitor.close_window(start + Duration::seconds(60))
+    }
+
+    #[test]
+    fn channels_map_to_event_types() {
+        assert_eq!(event_type("chain:events:swap"), "swap");
+        assert_eq!(event_type("chain:events:new_pair"), "new_pair");
+        assert_eq!(percentile(&[1, 2, 3, 4], 50), 2);
+        assert_eq!(percentile(&(1..=100).collect::<Vec<_>>(), 95), 95);
+        assert_eq!(percentile(&[], 95), 0);
+    }
+
+    #[test]
+    fn window_reports_percentiles_against_the_target() {
+        let start = Utc::now();
+        let mut monitor = LagMonitor::new(slo(10, 5), start);
+        monitor.record("withdrawal", start, start + Duration::seconds(90));
+
+        // Not due yet
+        assert!(monitor
+            .close_window(start + Duration::seconds(59))
+            .is_empty());
+
+        let reports = window(&mut monitor, start, &[1, 2, 3, 4, 20]);
+        assert_eq!(reports.len(), 1);
+        let lag = &reports[0].lag;
+        assert_eq!(lag.event_type, "swap");
+        assert_eq!(lag.samples, 5);
+        assert_eq!(lag.p50_lag_ms, 3_000);
+        assert_eq!(lag.p95_lag_ms, 20_000);
+        assert_eq!(lag.max_lag_ms, 20_000);
+        assert_eq!(lag.breached_since, Some(start));
+        assert!(!reports[0].alert);
+    }
+
+    #[test]
+    fn alerts_once_a_breach_outlasts_the_limit() {
+        let start = Utc::now();
+        let mut monitor = LagMonitor::new(slo(10, 3), start);
+        let minute = |n: i64| start + Duration::minutes(n);
+
+        assert!(!window(&mut monitor, minute(0), &[30])[0].alert);
+        assert!(!window(&mut monitor, minute(1), &[30])[0].alert);
+        // A quiet minute leaves the breach running
+        assert!(monitor.close_window(minute(3)).is_empty());
+        let report = &window(&mut monitor, minute(3), &[30])[0];
+        assert!(report.alert);
+        assert_eq!(report.lag.breached_since, Some(minute(0)));
+        // Only once per breach
+        assert!(!window(&mut monitor, minute(4), &[30])[0].alert);
+
+        let report = &window(&mut monitor, minute(5), &[1])[0];
+        assert!(report.recovered);
+        assert_eq!(report.lag.breached_since, None);
+        assert!(!window(&mut monitor, minute(6), &[1])[0].recovered);
+    }
+
+    #[test]
+    fn queue_head_age_is_measured_while_nothing_is_processed() {
+        let start = Utc::now();
+        let mut monitor = LagMonitor::new(slo(10, 5), start);
+
+        // Stuck on a log queued 40s ago, then the queue drains
+        monitor.record_queue_head(Some(start - Duration::seconds(40)), start);
+        monitor.record_queue_head(None, start + Duration::seconds(30));
+
+        let reports = monitor.close_window(start + Duration::seconds(60));
+        assert_eq!(reports.len(), 1);
+        let lag = &reports[0].lag;
+        assert_eq!(lag.event_type, QUEUE_HEAD);
+        assert_eq!(lag.samples, 2);
+        assert_eq!(lag.p50_lag_ms, 0);
+        assert_eq!(lag.max_lag_ms, 40_000);
+        assert_eq!(lag.breached_since, Some(start));
+    }
+}
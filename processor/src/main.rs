@@ -1,51 +1,231 @@
-use indexer_db::{entity::evm_logs::EvmLogs, initialize_database};
+use chrono::Utc;
+use egress::EventEgress;
+use event_metrics::{ErrorRateLimit, EventMetrics};
+use indexer_db::{
+    entity::deferred_log::DeferredLog,
+    initialize_database, logging,
+    queue::{LogQueue, QueueBackend},
+};
+use lag::{LagMonitor, LagSlo};
+use pair_discovery::PairLookups;
 use redis_client::RedisPublisher;
+use score_queue::ScoreQueue;
 use service::process_logs;
 use std::{env, error::Error};
 use tokio::time::{sleep, Duration};
 
+mod alert_rollup;
+mod bots;
+mod chain;
+#[allow(dead_code)]
 mod contracts;
+mod egress;
+mod entity_cache;
 mod error;
+mod event_metrics;
 mod events;
+mod known_addresses;
+mod mev;
 pub mod handlers;
+mod impersonation;
+mod jobs;
+mod lag;
+mod metadata_repair;
+mod pair_discovery;
+mod price_index;
+mod reconcile;
+mod rescan;
 mod redis_client;
+mod restrictions;
+mod retention;
+mod rpc;
+mod sampling;
+mod sanitize;
+mod scheduler;
+mod score_queue;
 pub mod scoring;
 mod service;
+mod token_gc;
+mod token_import;
+mod tokenlist;
+mod trending;
 mod utils;
+mod wallet_age;
+mod webhooks;
 
 mod defaults {
     pub const POLL_INTERVAL: &str = "10";
+    pub const RPC_URL: &str = "https://bsc-dataseed.binance.org";
+    pub const RPC_CALLS_PER_SEC: &str = "10";
+    pub const RPC_CALL_BURST: &str = "40";
     pub const BATCH_SIZE: &str = "25";
+    pub const ENTITY_CACHE_CAPACITY: &str = "10000";
+    pub const ENTITY_CACHE_TTL_MS: &str = "5000";
+    pub const DEFERRED_LOG_RETRY_CYCLES: &str = "3";
+    pub const DEFERRED_LOG_MAX_RETRIES: &str = "5";
+    pub const PAIR_LOOKUPS_PER_MINUTE: &str = "30";
+    pub const PAIR_LOOKUP_RETRY_SECS: &str = "3600";
     pub const BNB_PRICE_USD: &str = "600";
+    pub const BNB_PRICE_INDEX_INTERVAL: &str = "60";
+    pub const BNB_PRICE_INDEX_MIN_LIQUIDITY_USD: &str = "100000";
+    pub const BNB_PRICE_INDEX_MAX_DEVIATION_PERCENT: &str = "5";
+    pub const BNB_PRICE_INDEX_MAX_AGE_SECS: &str = "600";
     pub const WHALE_THRESHOLD_USD: &str = "5000";
-    pub const WBNB_ADDRESS: &str = "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c";
-    pub const BUSD_ADDRESS: &str = "0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56";
+    pub const CEX_FLOW_THRESHOLD_PERCENT: &str = "1";
+    pub const SNIPER_WINDOW_BLOCKS: &str = "2";
+    pub const SNAPSHOT_QUARANTINE_MAX_CHANGE_PERCENT: &str = "1000";
+    pub const SNAPSHOT_QUARANTINE_WINDOW: &str = "5";
+    pub const SNAPSHOT_QUARANTINE_CONFIRM_BLOCKS: &str = "2";
+    pub const SOCIAL_TRACTION_WEIGHT: &str = "0";
+    pub const CHAIN_ID: &str = "56";
+    pub const TOKEN_LIST_REFRESH_INTERVAL: &str = "30";
+    pub const TOKEN_ROLLUP_REFRESH_INTERVAL: &str = "60";
+    pub const WASH_TRADING_REFRESH_INTERVAL: &str = "300";
+    pub const ALERT_WEBHOOK_INTERVAL: &str = "5";
+    pub const ALERT_ROLLUP_TYPES: &str = "whale_buy,whale_sell,cex_inflow,cex_outflow";
+    pub const ALERT_ROLLUP_WINDOW_SECS: &str = "600";
+    pub const ALERT_ROLLUP_MIN_COUNT: &str = "3";
+    pub const RETENTION_INTERVAL: &str = "3600";
+    pub const RETENTION_BATCH_SIZE: &str = "5000";
+    pub const SWAP_RETENTION_DAYS: &str = "90";
+    pub const WALLET_ACTIVITY_RETENTION_DAYS: &str = "30";
+    pub const ALERT_RETENTION_DAYS: &str = "14";
+    pub const SNAPSHOT_DOWNSAMPLE_DAYS: &str = "7";
+    pub const SCORE_HISTORY_RETENTION_DAYS: &str = "30";
+    pub const IDEMPOTENCY_KEY_RETENTION_DAYS: &str = "2";
+    pub const PROCESSING_ERROR_RETENTION_DAYS: &str = "14";
+    pub const PENDING_SWAP_RETENTION_DAYS: &str = "30";
+    pub const HANDLED_LOG_RETENTION_DAYS: &str = "2";
+    pub const TOKEN_METRICS_RETENTION_DAYS: &str = "2";
+    pub const HOLDER_CHURN_RETENTION_DAYS: &str = "2";
+    pub const RETENTION_RUN_RETENTION_DAYS: &str = "30";
+    pub const LAG_SLO_SECONDS: &str = "60";
+    pub const LAG_SLO_WINDOW_SECONDS: &str = "60";
+    pub const LAG_SLO_BREACH_MINUTES: &str = "5";
+    pub const EVENT_ERROR_WINDOW_SECONDS: &str = "300";
+    pub const EVENT_ERROR_RATE_PERCENT: &str = "5";
+    pub const EVENT_ERROR_MIN_EVENTS: &str = "20";
+    pub const SCORE_QUEUE_INTERVAL_MS: &str = "1000";
+    pub const SCORE_QUEUE_BATCH: &str = "20";
+    pub const RECONCILE_INTERVAL: &str = "900";
+    pub const RECONCILE_TOKENS: &str = "50";
+    pub const RECONCILE_TOP_HOLDERS: &str = "20";
+    pub const RECONCILE_SETTLE_SECS: &str = "300";
+    pub const RECONCILE_BATCH: &str = "200";
+    pub const HOLDER_VERIFY_INTERVAL: &str = "10";
+    pub const HOLDER_VERIFY_STALE_SECS: &str = "600";
+    pub const TRANSFER_USD_BACKFILL_INTERVAL: &str = "300";
+    pub const TRANSFER_USD_BACKFILL_BATCH: &str = "1000";
+    pub const WALLET_VALUATION_INTERVAL: &str = "600";
+    pub const TOKEN_RESCAN_INTERVAL: &str = "10";
+    pub const RESCAN_BLOCK_RANGE: &str = "2000";
+    pub const IMPORT_TOKEN_BLOCKS: &str = "200000";
+    pub const JOB_WORKER_INTERVAL: &str = "5";
+    pub const JOB_STALE_SECS: &str = "600";
+    pub const METADATA_REPAIR_INTERVAL: &str = "120";
+    pub const METADATA_REPAIR_BATCH: &str = "50";
+    pub const METADATA_REPAIR_BACKOFF_SECS: &str = "300";
+    pub const METADATA_REPAIR_MAX_BACKOFF_SECS: &str = "86400";
+    pub const WALLET_AGE_INTERVAL: &str = "60";
+    pub const WALLET_AGE_BATCH: &str = "50";
+    pub const RESTRICTION_WATCH_INTERVAL: &str = "15";
+    pub const RESTRICTION_SCAN_BATCH: &str = "20";
+    pub const RESTRICTION_WATCH_MAX_BLOCKS: &str = "40";
+    pub const WALLET_SUGGESTION_HOUR_UTC: &str = "2";
+    pub const WALLET_SUGGESTION_PNL_DAYS: &str = "7";
+    pub const WALLET_SUGGESTION_TOP_PNL: &str = "50";
+    pub const WALLET_SUGGESTION_EARLY_BLOCKS: &str = "100";
+    pub const WALLET_SUGGESTION_MIN_EARLY_WINS: &str = "3";
+    pub const WALLET_SUGGESTION_MIN_SCORE: &str = "80";
+    pub const TOKEN_GC_INTERVAL: &str = "21600";
+    pub const TOKEN_GC_IDLE_DAYS: &str = "30";
+    pub const TOKEN_GC_BATCH: &str = "500";
+    pub const TOKEN_GC_KEEP_COLD: &str = "true";
+    pub const TRANSFER_SAMPLING: &str = "false";
+    pub const TRANSFER_SAMPLING_INTERVAL: &str = "300";
+    pub const TRANSFER_SAMPLING_MIN_LIQUIDITY_USD: &str = "10000";
+    pub const TRANSFER_SAMPLING_MIN_VOLUME_USD: &str = "1000";
+    pub const TRANSFER_SAMPLING_MAX_TOKENS: &str = "200";
+    pub const TOKENLIST_PUBLISH_INTERVAL: &str = "3600";
+    pub const TOKENLIST_MIN_SCORE: &str = "70";
+    pub const TOKENLIST_EXIT_SCORE: &str = "60";
+    pub const TOKENLIST_MAX_TOKENS: &str = "1000";
+    pub const TOKENLIST_KEEP_RELEASES: &str = "100";
+    pub const RUST_LOG: &str = "info";
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    println!("Starting BeanBee Processor (Dual-Write: Postgres + Redis)...");
+    logging::init(defaults::RUST_LOG);
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("import-token") {
+        let db_pool = initialize_database().await?;
+        match token_import::run(&args[1..], &db_pool).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                tracing::error!("Cannot import token: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    tracing::info!("Starting BeanBee Processor (Dual-Write: Postgres + Redis)...");
 
     // Initialize database connection
     let db_pool = initialize_database().await?;
-    println!("Connected to Postgres");
+    tracing::info!("Connected to Postgres");
+
+    // Fail now on configuration handlers can't run with, not at the first batch
+    if let Err(e) = service::create_handler_context(db_pool.clone()).await {
+        tracing::error!("Cannot start processor: {e}");
+        std::process::exit(1);
+    }
+
+    let queue = QueueBackend::from_env(db_pool.clone()).await?;
+    tracing::info!("Reading logs from the {} queue", queue.kind());
 
     // Initialize Redis publisher
     let mut redis = RedisPublisher::new().await?;
 
+    // Optional durable egress for downstream consumers
+    let egress = EventEgress::from_env().await?;
+
     let poll_interval = env::var("POLL_INTERVAL")
         .or::<String>(Ok(defaults::POLL_INTERVAL.into()))?
         .parse::<u64>()?;
 
     let sleep_duration = Duration::from_secs(poll_interval);
 
-    println!("Processor started. Polling every {} seconds...", poll_interval);
+    // End-to-end lag against its SLO, kept across batches
+    let mut lag = LagMonitor::new(LagSlo::from_env(), Utc::now());
+    // Per-topic counts and failure rates, likewise
+    let mut event_metrics = EventMetrics::new(ErrorRateLimit::from_env(), Utc::now());
+    // Pairs looked up on-chain, and the lookup rate
+    let mut pair_lookups = PairLookups::from_env()?;
+
+    // Background jobs (materialized view refreshes, etc.)
+    scheduler::spawn(db_pool.clone());
+
+    // BeeScores are recomputed by their own task, debounced per token
+    let scores = ScoreQueue::new();
+    scores.spawn(db_pool.clone());
+
+    tracing::info!("Processor started. Polling every {} seconds...", poll_interval);
 
     loop {
-        let unprocessed_count = match EvmLogs::count(&db_pool).await {
+        // Events egress didn't take earlier go out before new ones
+        if let Some(egress) = &egress {
+            if let Err(err) = egress.drain(&db_pool).await {
+                tracing::error!("Error draining the egress outbox: {err}");
+            }
+        }
+
+        let unprocessed_count = match queue.pending().await {
             Ok(count) => count,
             Err(err) => {
-                eprintln!(
+                tracing::error!(
                     "Error counting unprocessed logs: {err}. Sleeping for {} seconds...",
                     sleep_duration.as_secs()
                 );
@@ -56,20 +236,66 @@ async fn main() -> Result<(), Box<dyn Error>> {
         };
 
         match unprocessed_count {
-            Some(count) => {
-                println!("Found {count} unprocessed logs. Processing...");
+            count if count > 0 => {
+                tracing::info!("Found {count} unprocessed logs. Processing...");
 
-                if let Err(err) = process_logs(&db_pool, &mut redis).await {
-                    eprintln!("Error processing logs: {err}");
+                if let Err(err) = process_logs(
+                    &db_pool,
+                    &queue,
+                    &mut redis,
+                    egress.as_ref(),
+                    &mut lag,
+                    &mut event_metrics,
+                    &mut pair_lookups,
+                    &scores,
+                )
+                .await
+                {
+                    tracing::error!("Error processing logs: {err}");
                 }
             }
-            None => {
-                println!(
+            _ => {
+                let parked = match DeferredLog::count(&db_pool).await {
+                    Ok(parked) => parked,
+                    Err(err) => {
+                        tracing::error!("Error counting parked logs: {err}");
+                        0
+                    }
+                };
+                if parked > 0 {
+                    // Parked logs still count down and are retried, one
+                    // batch per poll, while the queue is empty
+                    if let Err(err) = process_logs(
+                        &db_pool,
+                        &queue,
+                        &mut redis,
+                        egress.as_ref(),
+                        &mut lag,
+                        &mut event_metrics,
+                        &mut pair_lookups,
+                        &scores,
+                    )
+                    .await
+                    {
+                        tracing::error!("Error retrying parked logs: {err}");
+                    }
+                } else {
+                    // Caught up with everything the listener has synced
+                    service::record_progress(&db_pool, &queue, None).await;
+                }
+                tracing::info!(
                     "No unprocessed logs. Sleeping for {} seconds...",
                     sleep_duration.as_secs()
                 );
                 sleep(sleep_duration).await;
             }
         }
+
+        match queue.oldest_queued_at().await {
+            Ok(queued_at) => lag.record_queue_head(queued_at, Utc::now()),
+            Err(err) => tracing::error!("Error reading the queue head: {err}"),
+        }
+        lag.flush(&db_pool).await;
+        event_metrics.flush(&db_pool).await;
     }
 }
@@ -0,0 +1,269 @@
+//! Token metadata repair
+//!
+//! A token whose `name()`/`symbol()`/`decimals()` calls failed when it was
+//! first seen, or were deferred because the RPC budget was spent, is stored
+//! without them and would stay that way. Each pass takes up to
+//! `METADATA_REPAIR_BATCH` tokens missing any of them, those trading the most
+//! first, reads name, symbol, decimals and total supply for all of them in one
+//! Multicall3 call, and fills in what came back. Tokens still incomplete wait
+//! `METADATA_REPAIR_BACKOFF_SECS` before the next attempt, doubled per failure
+//! up to `METADATA_REPAIR_MAX_BACKOFF_SECS` (tracked in `token_metadata_retries`).
+
+use std::{env, str::FromStr};
+
+use alloy::{eips::BlockId, primitives::Bytes, sol, sol_types::SolCall};
+use indexer_db::{
+    entity::{
+        token::{NewToken, Token},
+        token_metadata_retry::TokenMetadataRetry,
+    },
+    Address20,
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+use crate::{
+    defaults,
+    handlers::{IERC20Metadata, TokenMetadata},
+    reconcile::{multicall, IMulticall3},
+    rpc::{Rpc, RpcBudget},
+};
+
+sol! {
+    /// Pre-standard tokens (MKR and its copies) return these as `bytes32`
+    interface IERC20Bytes32Metadata {
+        function name() external view returns (bytes32);
+        function symbol() external view returns (bytes32);
+    }
+}
+
+/// Repair batch size and retry backoff, from the environment
+#[derive(Debug, Clone)]
+pub struct RepairConfig {
+    pub batch: i64,
+    pub backoff_secs: i64,
+    pub max_backoff_secs: i64,
+}
+
+impl RepairConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: i64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<i64>()
+                .unwrap_or(fallback)
+                .max(0)
+        };
+
+        Self {
+            batch: read("METADATA_REPAIR_BATCH", defaults::METADATA_REPAIR_BATCH, 50),
+            backoff_secs: read(
+                "METADATA_REPAIR_BACKOFF_SECS",
+                defaults::METADATA_REPAIR_BACKOFF_SECS,
+                300,
+            ),
+            max_backoff_secs: read(
+                "METADATA_REPAIR_MAX_BACKOFF_SECS",
+                defaults::METADATA_REPAIR_MAX_BACKOFF_SECS,
+                86400,
+            ),
+        }
+    }
+}
+
+/// A `name()` or `symbol()` return value, as a string or a zero-padded
+/// `bytes32`
+fn decode_text(data: &[u8]) -> Option<String> {
+    if let Ok(text) = IERC20Metadata::nameCall::abi_decode_returns(data, true) {
+        return Some(text._0).filter(|t| !t.is_empty());
+    }
+
+    let padded = IERC20Bytes32Metadata::nameCall::abi_decode_returns(data, true)
+        .ok()?
+        ._0;
+    let end = padded.iter().position(|b| *b == 0).unwrap_or(padded.len());
+    String::from_utf8(padded[..end].to_vec())
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+/// Name, symbol, decimals and total supply of each token, in one Multicall3
+/// call
+async fn fetch_metadata(
+    rpc: &Rpc,
+    tokens: &[Address20],
+) -> Result<Vec<TokenMetadata>, alloy::contract::Error> {
+    let calls: Vec<IMulticall3::Call3> = tokens
+        .iter()
+        .flat_map(|token| {
+            [
+                IERC20Metadata::nameCall {}.abi_encode(),
+                IERC20Metadata::symbolCall {}.abi_encode(),
+                IERC20Metadata::decimalsCall {}.abi_encode(),
+                IERC20Metadata::totalSupplyCall {}.abi_encode(),
+            ]
+            .map(|data| IMulticall3::Call3 {
+                target: (*token).into(),
+                allowFailure: true,
+                callData: Bytes::from(data),
+            })
+        })
+        .collect();
+
+    let results = multicall(rpc, calls, BlockId::latest()).await?;
+
+    Ok(results
+        .chunks(4)
+        .map(|calls| {
+            let returned = |i: usize| {
+                calls
+                    .get(i)
+                    .filter(|r| r.success)
+                    .map(|r| r.returnData.as_ref())
+            };
+            let mut metadata = TokenMetadata::default();
+            if let Some(name) = returned(0).and_then(decode_text) {
+                metadata.set_name(name);
+            }
+            if let Some(symbol) = returned(1).and_then(decode_text) {
+                metadata.set_symbol(symbol);
+            }
+            metadata.decimals = returned(2)
+                .and_then(|data| IERC20Metadata::decimalsCall::abi_decode_returns(data, true).ok())
+                .map(|d| d._0 as i16);
+            metadata.total_supply = returned(3)
+                .and_then(|data| {
+                    IERC20Metadata::totalSupplyCall::abi_decode_returns(data, true).ok()
+                })
+                .map(|s| s._0.to_string());
+            metadata
+        })
+        .collect())
+}
+
+/// Store what was read for one token, returning whether it is now complete
+async fn apply(
+    token: &Token,
+    metadata: TokenMetadata,
+    db_pool: &Pool<Postgres>,
+) -> Result<bool, sqlx::Error> {
+    let repaired = NewToken {
+        address: token.address,
+        name: metadata.name,
+        symbol: metadata.symbol,
+        name_raw: metadata.name_raw,
+        symbol_raw: metadata.symbol_raw,
+        name_spoofed: metadata.name_spoofed,
+        decimals: metadata.decimals,
+        total_supply: metadata
+            .total_supply
+            .and_then(|s| BigDecimal::from_str(&s).ok()),
+        pair_address: token.pair_address,
+        creator_address: token.creator_address,
+        block_number: token.block_number,
+    };
+    let stored = Token::create(&repaired, db_pool).await?;
+
+    Ok(stored.name.is_some() && stored.symbol.is_some() && stored.decimals.is_some())
+}
+
+/// One repair pass over the tokens due for a metadata retry
+pub async fn run(db_pool: &Pool<Postgres>, config: &RepairConfig) {
+    if config.batch == 0 {
+        return;
+    }
+
+    let tokens = match TokenMetadataRetry::find_due(config.batch, db_pool).await {
+        Ok(tokens) if tokens.is_empty() => return,
+        Ok(tokens) => tokens,
+        Err(e) => {
+            tracing::error!("Metadata repair: failed to list tokens: {}", e);
+            return;
+        }
+    };
+    let addresses: Vec<Address20> = tokens.iter().map(|t| t.address).collect();
+
+    RpcBudget::shared().take(1).await;
+    let fetched = match fetch_metadata(&Rpc::shared(), &addresses).await {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            tracing::error!("Metadata repair: multicall failed: {}", e);
+            let error = e.to_string();
+            for address in &addresses {
+                record_failure(address, &error, config, db_pool).await;
+            }
+            return;
+        }
+    };
+
+    let mut repaired = 0;
+    for (token, metadata) in tokens.iter().zip(fetched) {
+        match apply(token, metadata, db_pool).await {
+            Ok(true) => {
+                repaired += 1;
+                if let Err(e) = TokenMetadataRetry::clear(&token.address, db_pool).await {
+                    tracing::error!("Metadata repair: failed to clear {}: {}", token.address, e);
+                }
+            }
+            Ok(false) => {
+                record_failure(
+                    &token.address,
+                    "name(), symbol() or decimals() returned nothing",
+                    config,
+                    db_pool,
+                )
+                .await
+            }
+            Err(e) => tracing::error!("Metadata repair: failed to update {}: {}", token.address, e),
+        }
+    }
+
+    tracing::info!(
+        "Metadata repair: {} of {} tokens completed",
+        repaired,
+        tokens.len()
+    );
+}
+
+async fn record_failure(
+    address: &Address20,
+    error: &str,
+    config: &RepairConfig,
+    db_pool: &Pool<Postgres>,
+) {
+    if let Err(e) = TokenMetadataRetry::record_failure(
+        address,
+        error,
+        config.backoff_secs,
+        config.max_backoff_secs,
+        db_pool,
+    )
+    .await
+    {
+        tracing::error!(
+            "Metadata repair: failed to record a retry for {}: {}",
+            address, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::FixedBytes, sol_types::SolValue};
+
+    use super::*;
+
+    #[test]
+    fn names_decode_from_strings_and_bytes32() {
+        let string = "Maker".to_string().abi_encode();
+        assert_eq!(decode_text(&string).as_deref(), Some("Maker"));
+
+        let mut padded = [0u8; 32];
+        padded[..3].copy_from_slice(b"MKR");
+        let bytes32 = FixedBytes::<32>::from(padded).abi_encode();
+        assert_eq!(decode_text(&bytes32).as_deref(), Some("MKR"));
+
+        assert_eq!(decode_text(&String::new().abi_encode()), None);
+        assert_eq!(decode_text(&[0u8; 32]), None);
+        assert_eq!(decode_text(&[]), None);
+    }
+}
@@ -0,0 +1,68 @@
+//! MEV/sniper bot indicators for swaps
+//!
+//! Bots buying into launches or sandwiching whales tend to land in the first
+//! transactions of a block and to outbid everyone else on gas. Whale swaps are
+//! flagged when either holds, and as probable MEV when both do.
+
+/// Transactions at or before this index count as top of block
+pub const TOP_OF_BLOCK_INDEX: i64 = 2;
+
+/// Gas price percentile at or above which a transaction counts as outbidding
+pub const HIGH_GAS_PERCENTILE: i16 = 90;
+
+pub const TOP_OF_BLOCK: &str = "top_of_block";
+pub const HIGH_GAS: &str = "high_gas";
+pub const PROBABLE_MEV: &str = "probable_mev";
+
+/// Share (0-100) of `block_gas_prices` strictly below `gas_price`
+pub fn gas_price_percentile(gas_price: u128, block_gas_prices: &[u128]) -> i16 {
+    if block_gas_prices.is_empty() {
+        return 0;
+    }
+
+    let lower = block_gas_prices.iter().filter(|p| **p < gas_price).count();
+    ((lower * 100) as f64 / block_gas_prices.len() as f64).round() as i16
+}
+
+/// Bot indicators for a transaction at `tx_index` paying the given gas percentile
+pub fn flags(tx_index: i64, gas_price_percentile: Option<i16>) -> Vec<String> {
+    let top_of_block = tx_index <= TOP_OF_BLOCK_INDEX;
+    let high_gas = gas_price_percentile.is_some_and(|p| p >= HIGH_GAS_PERCENTILE);
+
+    let mut flags = Vec::new();
+    if top_of_block {
+        flags.push(TOP_OF_BLOCK.to_string());
+    }
+    if high_gas {
+        flags.push(HIGH_GAS.to_string());
+    }
+    if top_of_block && high_gas {
+        flags.push(PROBABLE_MEV.to_string());
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_counts_cheaper_transactions() {
+        let block: Vec<u128> = (1..=10).collect();
+        assert_eq!(gas_price_percentile(10, &block), 90);
+        assert_eq!(gas_price_percentile(1, &block), 0);
+        assert_eq!(gas_price_percentile(100, &block), 100);
+        assert_eq!(gas_price_percentile(5, &[]), 0);
+    }
+
+    #[test]
+    fn early_high_gas_transactions_are_probable_mev() {
+        assert_eq!(
+            flags(0, Some(95)),
+            vec![TOP_OF_BLOCK, HIGH_GAS, PROBABLE_MEV]
+        );
+        assert_eq!(flags(1, Some(50)), vec![TOP_OF_BLOCK]);
+        assert_eq!(flags(40, Some(90)), vec![HIGH_GAS]);
+        assert!(flags(40, None).is_empty());
+    }
+}
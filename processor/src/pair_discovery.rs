@@ -0,0 +1,420 @@
+//! Pairs created before indexing began
+//!
+//! When a swap, sync, mint or burn names a pair the processor never saw a
+//! PairCreated for, the pair is looked up on-chain: the pair contract names
+//! its factory and tokens, and when the factory is one of the chain's and its
+//! `getPair(token0, token1)` returns the pair, the pair and its token are
+//! indexed as a PairCreated would have done, without the new-token alert, and
+//! the log is handled again. Pairs that definitively don't check out (the
+//! contract reverts, or it isn't a base pair a known factory lists) aren't
+//! asked about again for `PAIR_LOOKUP_RETRY_SECS`; lookups that fail on the
+//! endpoint are retried with the next log. At most `PAIR_LOOKUPS_PER_MINUTE`
+//! lookups run, on top of the shared RPC budget.
+//!
+//! A log whose pair is still unknown is parked for a few batches; after that
+//! syncs, mints and burns are dropped and swaps are kept in `pending_swaps`.
+//! Every batch attributes the kept swaps whose pair is indexed by now, whether
+//! it was looked up, replayed by a rescan or imported with
+//! `indexer-db-cli import-seed`.
+//!
+//! [`find_pairs`] goes the other way for `processor import-token`, asking the
+//! factories for a token's pairs.
+
+use std::{
+    collections::HashMap,
+    env,
+    time::{Duration, Instant},
+};
+
+use alloy::sol;
+use indexer_db::{
+    entity::{
+        pair::{NewPair, Pair},
+        token::{NewToken, Token},
+    },
+    Address20,
+};
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+
+use crate::{
+    chain::ChainConstants,
+    defaults,
+    error::AppError,
+    handlers::{HandlerContext, HandlerResult, TokenMetadata},
+    rpc::Failover,
+};
+
+sol! {
+    #[sol(rpc)]
+    interface IPancakePair {
+        function factory() external view returns (address);
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface IPancakeFactory {
+        function getPair(address tokenA, address tokenB) external view returns (address pair);
+    }
+}
+
+/// RPC calls one lookup makes
+const LOOKUP_CALLS: u32 = 4;
+
+/// Misses remembered before expired ones are swept
+const MAX_REMEMBERED_MISSES: usize = 10_000;
+
+/// Lookups made so far: pairs that didn't check out, and the rate limit
+pub struct PairLookups {
+    per_minute: u32,
+    retry_after: Duration,
+    /// Pairs not to look up again until the given time
+    misses: HashMap<Address20, Instant>,
+    window_start: Instant,
+    in_window: u32,
+}
+
+impl PairLookups {
+    pub fn new(per_minute: u32, retry_after: Duration, now: Instant) -> Self {
+        Self {
+            per_minute,
+            retry_after,
+            misses: HashMap::new(),
+            window_start: now,
+            in_window: 0,
+        }
+    }
+
+    /// `PAIR_LOOKUPS_PER_MINUTE` and `PAIR_LOOKUP_RETRY_SECS`
+    pub fn from_env() -> Result<Self, AppError> {
+        let read = |var: &str, default: &str| {
+            let value = env::var(var).unwrap_or_else(|_| default.to_string());
+            value.trim().parse::<u32>().map_err(|_| {
+                AppError::InvalidConfig(format!("`{}` is not a count: `{}`", var, value))
+            })
+        };
+        let per_minute = read("PAIR_LOOKUPS_PER_MINUTE", defaults::PAIR_LOOKUPS_PER_MINUTE)?;
+        let retry_secs = read("PAIR_LOOKUP_RETRY_SECS", defaults::PAIR_LOOKUP_RETRY_SECS)?;
+        Ok(Self::new(
+            per_minute,
+            Duration::from_secs(retry_secs.into()),
+            Instant::now(),
+        ))
+    }
+
+    /// Whether `pair` may be looked up at `now`, counting it against the
+    /// rate limit if so
+    fn admit(&mut self, pair: &Address20, now: Instant) -> bool {
+        if self.misses.get(pair).is_some_and(|until| *until > now) {
+            return false;
+        }
+        if now.duration_since(self.window_start) >= Duration::from_secs(60) {
+            self.window_start = now;
+            self.in_window = 0;
+        }
+        if self.in_window >= self.per_minute {
+            return false;
+        }
+        self.in_window += 1;
+        true
+    }
+
+    fn remember_miss(&mut self, pair: &Address20, now: Instant) {
+        if self.misses.len() >= MAX_REMEMBERED_MISSES {
+            self.misses.retain(|_, until| *until > now);
+        }
+        self.misses.insert(*pair, now + self.retry_after);
+    }
+
+    /// Look `pair` up unless it missed recently or the rate limit is
+    /// reached. Returns whether the pair is indexed now. Only definitive
+    /// misses are remembered; failed lookups may run again with the next log.
+    pub async fn resolve(
+        &mut self,
+        ctx: &HandlerContext,
+        pair: &Address20,
+        block_number: i64,
+    ) -> bool {
+        if !self.admit(pair, Instant::now()) {
+            return false;
+        }
+        match discover(ctx, pair, block_number).await {
+            Ok(true) => true,
+            Ok(false) => {
+                self.remember_miss(pair, Instant::now());
+                false
+            }
+            Err(e) => {
+                tracing::error!("Pair lookup of {} failed: {}", pair, e);
+                false
+            }
+        }
+    }
+}
+
+/// What a pair contract and its factory said about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnChainPair {
+    pub factory: Address20,
+    pub token0: Address20,
+    pub token1: Address20,
+    /// `getPair(token0, token1)` of the factory
+    pub listed: Address20,
+}
+
+/// The pair to index for `pair`, or `None` when its factory isn't one of the
+/// chain's, doesn't list it, or neither token is a base token (skipped like
+/// token/token pairs are on PairCreated)
+pub fn attribution(
+    chain: &ChainConstants,
+    pair: &Address20,
+    found: &OnChainPair,
+    block_number: i64,
+) -> Option<NewPair> {
+    if !chain.factories.contains(&found.factory) || found.listed != *pair {
+        return None;
+    }
+    let is_base = |token: &Address20| chain.is_wrapped_native(token) || chain.is_stablecoin(token);
+    let base_token_index = if is_base(&found.token0) {
+        0
+    } else if is_base(&found.token1) {
+        1
+    } else {
+        return None;
+    };
+
+    Some(NewPair {
+        address: *pair,
+        token0_address: found.token0,
+        token1_address: found.token1,
+        factory_address: found.factory,
+        base_token_index,
+        block_number,
+        discovered: true,
+    })
+}
+
+/// Read `pair`'s factory and tokens, and what the factory lists for them.
+/// `None` when the pair calls revert, e.g. because the address isn't a V2
+/// pair; an error when an endpoint failed, which says nothing about the pair.
+/// A factory that isn't the chain's isn't asked and lists nothing.
+async fn read(ctx: &HandlerContext, pair: &Address20) -> HandlerResult<Option<OnChainPair>> {
+    ctx.rpc_budget.take(LOOKUP_CALLS).await;
+    let address = (*pair).into();
+
+    let pair_calls = ctx
+        .rpc
+        .call(|p| async move {
+            let contract = IPancakePair::new(address, &p);
+            let factory = contract.factory().call().await?._0;
+            let token0 = contract.token0().call().await?._0;
+            let token1 = contract.token1().call().await?._0;
+            Ok::<_, alloy::contract::Error>((factory, token0, token1))
+        })
+        .await;
+    let (factory, token0, token1) = match pair_calls {
+        Ok(found) => found,
+        Err(e) if e.is_transport() => {
+            return Err(AppError::Handler(format!("pair calls on {}: {}", pair, e)));
+        }
+        Err(e) => {
+            tracing::debug!("Pair lookup: {} doesn't answer as a pair: {}", pair, e);
+            return Ok(None);
+        }
+    };
+    let unlisted = OnChainPair {
+        factory: factory.into(),
+        token0: token0.into(),
+        token1: token1.into(),
+        listed: Address20::ZERO,
+    };
+    if !ctx.chain.factories.contains(&unlisted.factory) {
+        return Ok(Some(unlisted));
+    }
+
+    let listed = ctx
+        .rpc
+        .call(|p| async move {
+            IPancakeFactory::new(factory, &p)
+                .getPair(token0, token1)
+                .call()
+                .await
+        })
+        .await
+        .map_err(|e| AppError::Handler(format!("getPair on {}: {}", factory, e)))?;
+    Ok(Some(OnChainPair {
+        listed: listed.pair.into(),
+        ..unlisted
+    }))
+}
+
+/// The pairs the chain's factories list for `token` against each base token,
+/// wrapped native pairs first
+pub async fn find_pairs(ctx: &HandlerContext, token: &Address20) -> HandlerResult<Vec<Address20>> {
+    let mut bases = vec![ctx.chain.wrapped_native];
+    bases.extend(ctx.chain.stablecoins.iter().copied());
+
+    let mut pairs = Vec::new();
+    for base in &bases {
+        for factory in &ctx.chain.factories {
+            ctx.rpc_budget.take(1).await;
+            let (factory_address, token_address, base_address) =
+                ((*factory).into(), (*token).into(), (*base).into());
+            let listed = ctx
+                .rpc
+                .call(|p| async move {
+                    IPancakeFactory::new(factory_address, &p)
+                        .getPair(token_address, base_address)
+                        .call()
+                        .await
+                })
+                .await
+                .map_err(|e| AppError::Handler(format!("getPair on {}: {}", factory, e)))?;
+            let pair: Address20 = listed.pair.into();
+            if pair != Address20::ZERO && !pairs.contains(&pair) {
+                pairs.push(pair);
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+/// Look `pair` up on-chain and index it when one of the chain's factories
+/// lists it. `block_number` (one of its swaps) stands in for the unknown
+/// creation block. Returns whether the pair is indexed now; `false` is a
+/// definitive answer, a failed lookup is an error.
+pub async fn discover(
+    ctx: &HandlerContext,
+    pair: &Address20,
+    block_number: i64,
+) -> HandlerResult<bool> {
+    let Some(found) = read(ctx, pair).await? else {
+        return Ok(false);
+    };
+    let Some(new_pair) = attribution(&ctx.chain, pair, &found, block_number) else {
+        tracing::info!(
+            "Pair lookup: {} ({} / {}, factory {}) isn't a base pair of a known factory",
+            pair, found.token0, found.token1, found.factory
+        );
+        return Ok(false);
+    };
+
+    match Pair::create(&new_pair, &ctx.db_pool).await {
+        Ok(_) | Err(sqlx::Error::RowNotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let token_address = if new_pair.base_token_index == 0 {
+        new_pair.token1_address
+    } else {
+        new_pair.token0_address
+    };
+    // Left to the metadata repair job when the budget is spent
+    let metadata = ctx
+        .try_fetch_token_metadata(&token_address)
+        .await
+        .unwrap_or_else(TokenMetadata::default);
+    let token = NewToken {
+        address: token_address,
+        name: metadata.name,
+        symbol: metadata.symbol,
+        name_raw: metadata.name_raw,
+        symbol_raw: metadata.symbol_raw,
+        name_spoofed: metadata.name_spoofed,
+        // Unknown until the metadata repair job reads them
+        decimals: metadata.decimals,
+        total_supply: metadata
+            .total_supply
+            .as_deref()
+            .and_then(|s| BigDecimal::from_str(s).ok()),
+        pair_address: Some(*pair),
+        creator_address: None,
+        // Its launch predates indexing
+        block_number: None,
+    };
+    // A token already indexed keeps its main pair and metadata
+    Token::import(&token, None, &ctx.db_pool).await?;
+    ctx.entities.invalidate_token(&token_address);
+
+    tracing::info!(
+        "Pair lookup: indexed {} (token {}) from factory {}",
+        pair, token_address, found.factory
+    );
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> ChainConstants {
+        ChainConstants {
+            chain_id: 56,
+            wrapped_native: Address20::new([1; 20]),
+            stablecoins: vec![Address20::new([2; 20])],
+            factories: vec![Address20::new([9; 20])],
+            routers: Vec::new(),
+            lockers: Vec::new(),
+            price_pools: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn pairs_listed_by_a_known_factory_are_attributed() {
+        let pair = Address20::new([7; 20]);
+        let found = OnChainPair {
+            factory: Address20::new([9; 20]),
+            token0: Address20::new([5; 20]),
+            token1: Address20::new([1; 20]),
+            listed: pair,
+        };
+
+        let attributed = attribution(&chain(), &pair, &found, 1_000).unwrap();
+        assert_eq!(attributed.base_token_index, 1);
+        assert_eq!(attributed.block_number, 1_000);
+        assert_eq!(attributed.factory_address, found.factory);
+
+        // Another factory, a factory that lists another pair for the
+        // tokens, and a token/token pair are all left alone
+        let foreign = OnChainPair {
+            factory: Address20::new([8; 20]),
+            ..found
+        };
+        assert!(attribution(&chain(), &pair, &foreign, 1_000).is_none());
+        let unlisted = OnChainPair {
+            listed: Address20::new([6; 20]),
+            ..found
+        };
+        assert!(attribution(&chain(), &pair, &unlisted, 1_000).is_none());
+        let no_base = OnChainPair {
+            token1: Address20::new([4; 20]),
+            ..found
+        };
+        assert!(attribution(&chain(), &pair, &no_base, 1_000).is_none());
+    }
+
+    #[test]
+    fn lookups_are_rate_limited_and_misses_wait() {
+        let start = Instant::now();
+        let mut lookups = PairLookups::new(2, Duration::from_secs(600), start);
+        let (a, b, c) = (
+            Address20::new([1; 20]),
+            Address20::new([2; 20]),
+            Address20::new([3; 20]),
+        );
+
+        assert!(lookups.admit(&a, start));
+        lookups.remember_miss(&a, start);
+        assert!(lookups.admit(&b, start));
+        // The minute's lookups are used up
+        assert!(!lookups.admit(&c, start + Duration::from_secs(30)));
+
+        let next_minute = start + Duration::from_secs(61);
+        assert!(lookups.admit(&c, next_minute));
+        // A miss isn't looked up again until it is due
+        assert!(!lookups.admit(&a, next_minute));
+        let due = start + Duration::from_secs(601);
+        assert!(lookups.admit(&a, due));
+    }
+}
@@ -0,0 +1,326 @@
+//! BNB/USD price index
+//!
+//! The processor values every trade and pool in USD through one BNB/USD rate.
+//! Rather than trusting a fixed `BNB_PRICE_USD` or a single pool, each pass
+//! reads the reserves of the chain's `price_pool` constants (PancakeSwap
+//! WBNB/BUSD and WBNB/USDT on BSC) in one Multicall3 call and weights each
+//! pool's price by its liquidity. Pools under
+//! `BNB_PRICE_INDEX_MIN_LIQUIDITY_USD`, or more than
+//! `BNB_PRICE_INDEX_MAX_DEVIATION_PERCENT` away from the liquidity-weighted
+//! median, are left out, so a manipulated pool has to outweigh the others to
+//! move the rate. The result is stored in `native_price_index`; handler
+//! contexts use it while it is younger than `BNB_PRICE_INDEX_MAX_AGE_SECS`
+//! and fall back to `BNB_PRICE_USD` otherwise.
+
+use std::{env, str::FromStr};
+
+use alloy::{eips::BlockId, primitives::Bytes, sol, sol_types::SolCall};
+use chrono::Utc;
+use indexer_db::{entity::native_price::NativePrice, Address20};
+use serde_json::json;
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+use crate::{
+    chain::ChainConstants,
+    defaults,
+    handlers::IERC20Metadata,
+    reconcile::{multicall, IMulticall3},
+    rpc::Rpc,
+};
+
+sol! {
+    interface IUniswapV2Pair {
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+    }
+}
+
+/// Pool filters and freshness, from the environment
+#[derive(Debug, Clone)]
+pub struct IndexConfig {
+    pub min_liquidity_usd: f64,
+    pub max_deviation_percent: f64,
+    pub max_age_secs: i64,
+}
+
+impl IndexConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: f64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<f64>()
+                .unwrap_or(fallback)
+                .max(0.0)
+        };
+
+        Self {
+            min_liquidity_usd: read(
+                "BNB_PRICE_INDEX_MIN_LIQUIDITY_USD",
+                defaults::BNB_PRICE_INDEX_MIN_LIQUIDITY_USD,
+                100_000.0,
+            ),
+            max_deviation_percent: read(
+                "BNB_PRICE_INDEX_MAX_DEVIATION_PERCENT",
+                defaults::BNB_PRICE_INDEX_MAX_DEVIATION_PERCENT,
+                5.0,
+            ),
+            max_age_secs: read(
+                "BNB_PRICE_INDEX_MAX_AGE_SECS",
+                defaults::BNB_PRICE_INDEX_MAX_AGE_SECS,
+                600.0,
+            ) as i64,
+        }
+    }
+}
+
+/// One pool's BNB price and depth
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolQuote {
+    pub pool: Address20,
+    pub price_usd: f64,
+    /// Both sides, valued at the stablecoin reserve
+    pub liquidity_usd: f64,
+}
+
+/// Liquidity-weighted price of the quotes deep enough and close enough to
+/// the weighted median, with the quotes used
+pub fn index_price<'a>(
+    quotes: &'a [PoolQuote],
+    config: &IndexConfig,
+) -> Option<(f64, Vec<&'a PoolQuote>)> {
+    let mut eligible: Vec<&PoolQuote> = quotes
+        .iter()
+        .filter(|q| q.price_usd > 0.0 && q.liquidity_usd >= config.min_liquidity_usd)
+        .collect();
+    if eligible.is_empty() {
+        return None;
+    }
+
+    eligible.sort_by(|a, b| a.price_usd.total_cmp(&b.price_usd));
+    let half = eligible.iter().map(|q| q.liquidity_usd).sum::<f64>() / 2.0;
+    let mut seen = 0.0;
+    let median = eligible
+        .iter()
+        .find(|q| {
+            seen += q.liquidity_usd;
+            seen >= half
+        })
+        .map(|q| q.price_usd)?;
+
+    let used: Vec<&PoolQuote> = eligible
+        .into_iter()
+        .filter(|q| (q.price_usd / median - 1.0).abs() * 100.0 <= config.max_deviation_percent)
+        .collect();
+    let weight: f64 = used.iter().map(|q| q.liquidity_usd).sum();
+    let price = used.iter().map(|q| q.price_usd * q.liquidity_usd).sum::<f64>() / weight;
+
+    Some((price, used))
+}
+
+/// The BNB/USD rate handlers should use: the stored index while it is fresh,
+/// otherwise `fallback`
+pub async fn current_usd(
+    chain_id: i64,
+    fallback: f64,
+    config: &IndexConfig,
+    db_pool: &Pool<Postgres>,
+) -> f64 {
+    match NativePrice::find(chain_id, db_pool).await {
+        Ok(Some(index)) if (Utc::now() - index.updated_at).num_seconds() <= config.max_age_secs => {
+            index.price_usd.to_string().parse().unwrap_or(fallback)
+        }
+        Ok(_) => fallback,
+        Err(e) => {
+            tracing::error!("BNB price index: failed to read the index: {}", e);
+            fallback
+        }
+    }
+}
+
+fn reserve(value: alloy::primitives::Uint<112, 2>, decimals: u8) -> f64 {
+    value.to::<u128>() as f64 / 10f64.powi(decimals as i32)
+}
+
+/// Reserves, tokens and decimals of each pool, in two Multicall3 calls
+async fn fetch_quotes(
+    rpc: &Rpc,
+    chain: &ChainConstants,
+) -> Result<Vec<PoolQuote>, alloy::contract::Error> {
+    let call = |target: Address20, data: Vec<u8>| IMulticall3::Call3 {
+        target: target.into(),
+        allowFailure: true,
+        callData: Bytes::from(data),
+    };
+
+    let calls: Vec<IMulticall3::Call3> = chain
+        .price_pools
+        .iter()
+        .flat_map(|pool| {
+            [
+                call(*pool, IUniswapV2Pair::getReservesCall {}.abi_encode()),
+                call(*pool, IUniswapV2Pair::token0Call {}.abi_encode()),
+                call(*pool, IUniswapV2Pair::token1Call {}.abi_encode()),
+            ]
+        })
+        .collect();
+    let results = multicall(rpc, calls, BlockId::latest()).await?;
+
+    let pools: Vec<_> = chain
+        .price_pools
+        .iter()
+        .zip(results.chunks(3))
+        .filter_map(|(pool, calls)| {
+            let returned = |i: usize| {
+                calls
+                    .get(i)
+                    .filter(|r| r.success)
+                    .map(|r| r.returnData.as_ref())
+            };
+            let reserves =
+                IUniswapV2Pair::getReservesCall::abi_decode_returns(returned(0)?, true).ok()?;
+            let token0 = IUniswapV2Pair::token0Call::abi_decode_returns(returned(1)?, true).ok()?;
+            let token1 = IUniswapV2Pair::token1Call::abi_decode_returns(returned(2)?, true).ok()?;
+            let (token0, token1) = (Address20::from(token0._0), Address20::from(token1._0));
+
+            // (native reserve, stablecoin reserve, stablecoin)
+            if chain.is_wrapped_native(&token0) {
+                Some((*pool, reserves.reserve0, reserves.reserve1, token1))
+            } else if chain.is_wrapped_native(&token1) {
+                Some((*pool, reserves.reserve1, reserves.reserve0, token0))
+            } else {
+                tracing::warn!("BNB price index: {} has no wrapped native side", pool);
+                None
+            }
+        })
+        .collect();
+
+    let decimals_of = |token: Address20| call(token, IERC20Metadata::decimalsCall {}.abi_encode());
+    let calls = pools
+        .iter()
+        .flat_map(|(_, _, _, stable)| [decimals_of(chain.wrapped_native), decimals_of(*stable)])
+        .collect();
+    let decimals = multicall(rpc, calls, BlockId::latest()).await?;
+
+    Ok(pools
+        .into_iter()
+        .zip(decimals.chunks(2))
+        .filter_map(|((pool, native, stable, _), decimals)| {
+            let decimals_at = |i: usize| {
+                decimals.get(i).filter(|r| r.success).and_then(|r| {
+                    IERC20Metadata::decimalsCall::abi_decode_returns(&r.returnData, true).ok()
+                })
+            };
+            let native = reserve(native, decimals_at(0)?._0);
+            let stable = reserve(stable, decimals_at(1)?._0);
+            (native > 0.0).then(|| PoolQuote {
+                pool,
+                price_usd: stable / native,
+                liquidity_usd: 2.0 * stable,
+            })
+        })
+        .collect())
+}
+
+/// One pass: quote every price pool and store the index
+pub async fn run(db_pool: &Pool<Postgres>, config: &IndexConfig) {
+    let chain_id = env::var("CHAIN_ID")
+        .unwrap_or_else(|_| defaults::CHAIN_ID.to_string())
+        .parse::<i64>()
+        .unwrap_or(56);
+    let chain = match ChainConstants::load(chain_id, db_pool).await {
+        Ok(chain) if chain.price_pools.is_empty() => return,
+        Ok(chain) => chain,
+        Err(e) => {
+            tracing::error!("BNB price index: failed to load chain constants: {}", e);
+            return;
+        }
+    };
+
+    let quotes = match fetch_quotes(&Rpc::shared(), &chain).await {
+        Ok(quotes) => quotes,
+        Err(e) => {
+            tracing::error!("BNB price index: multicall failed: {}", e);
+            return;
+        }
+    };
+    let Some((price, used)) = index_price(&quotes, config) else {
+        tracing::error!(
+            "BNB price index: none of {} pools is usable, keeping the last rate",
+            quotes.len()
+        );
+        return;
+    };
+
+    let pools = json!(quotes
+        .iter()
+        .map(|q| json!({
+            "pool": q.pool,
+            "priceUsd": q.price_usd,
+            "liquidityUsd": q.liquidity_usd,
+            "included": used.contains(&q),
+        }))
+        .collect::<Vec<_>>());
+    let Ok(price_usd) = BigDecimal::from_str(&format!("{:.8}", price)) else {
+        return;
+    };
+    match NativePrice::upsert(chain_id, &price_usd, &pools, db_pool).await {
+        Ok(_) => tracing::info!(
+            "BNB price index: ${} from {} of {} pools",
+            price_usd,
+            used.len(),
+            quotes.len()
+        ),
+        Err(e) => tracing::error!("BNB price index: failed to store the index: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(n: u8, price_usd: f64, liquidity_usd: f64) -> PoolQuote {
+        PoolQuote {
+            pool: Address20::new([n; 20]),
+            price_usd,
+            liquidity_usd,
+        }
+    }
+
+    fn config() -> IndexConfig {
+        IndexConfig {
+            min_liquidity_usd: 100_000.0,
+            max_deviation_percent: 5.0,
+            max_age_secs: 600,
+        }
+    }
+
+    #[test]
+    fn pools_are_weighted_by_liquidity() {
+        let quotes = [quote(1, 600.0, 3_000_000.0), quote(2, 604.0, 1_000_000.0)];
+        let (price, used) = index_price(&quotes, &config()).unwrap();
+        assert!((price - 601.0).abs() < 1e-9);
+        assert_eq!(used.len(), 2);
+    }
+
+    #[test]
+    fn shallow_and_outlying_pools_are_left_out() {
+        let quotes = [
+            quote(1, 600.0, 3_000_000.0),
+            quote(2, 602.0, 2_000_000.0),
+            // Pushed far off by a large trade
+            quote(3, 900.0, 2_500_000.0),
+            // Too shallow to count
+            quote(4, 1.0, 50_000.0),
+        ];
+        let (price, used) = index_price(&quotes, &config()).unwrap();
+        assert!((price - 600.8).abs() < 1e-9);
+        assert_eq!(
+            used.iter().map(|q| q.pool).collect::<Vec<_>>(),
+            vec![Address20::new([1; 20]), Address20::new([2; 20])]
+        );
+
+        assert!(index_price(&quotes[3..], &config()).is_none());
+        assert!(index_price(&[], &config()).is_none());
+    }
+}
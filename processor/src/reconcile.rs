@@ -0,0 +1,549 @@
+//! Holder balance reconciliation
+//!
+//! Holder balances are tracked incrementally from Transfer logs, so a missed
+//! log or a truncated amount leaves them wrong for good. Each pass takes the
+//! `RECONCILE_TOKENS` most traded tokens, reads `balanceOf` for their top
+//! `RECONCILE_TOP_HOLDERS` holders, and overwrites stored balances that
+//! disagree. Every correction is recorded in `holder_reconciliations`.
+//!
+//! Operators can also queue a verification of every stored holder of one
+//! token (`verify-holders`), which the scheduler picks up between passes.
+//! Both read balances `RECONCILE_BATCH` wallets per Multicall3 call, delete
+//! holders found holding nothing, and recompute the token's holder count and
+//! top-10 concentration.
+//!
+//! Balances are read at the processor's last processed block rather than at
+//! the chain head, so transfers still queued aren't counted on top of them.
+//! A verification reports its progress after every page of holders; one not
+//! updated for `HOLDER_VERIFY_STALE_SECS` was left by a processor that
+//! stopped, and is started over.
+//! Holders updated within `RECONCILE_SETTLE_SECS` are skipped as well: logs
+//! of the block after it may already be applied to them.
+
+use std::{env, str::FromStr};
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, Bytes},
+    sol,
+    sol_types::SolCall,
+};
+use chrono::{Duration, Utc};
+use indexer_db::{
+    entity::{
+        holder_reconciliation::{HolderReconciliation, NewHolderReconciliation},
+        holder_verification::{HolderVerification, VerificationCounts},
+        processor_progress::ProcessorProgress,
+        token::Token,
+        token_holder::TokenHolder,
+    },
+    Address20,
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+use crate::{defaults, rpc::Rpc};
+
+/// Multicall3, deployed at the same address on BSC and most EVM chains
+pub(crate) const MULTICALL3: Address =
+    alloy::primitives::address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+sol! {
+    #[sol(rpc)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+
+    interface IERC20Balance {
+        function balanceOf(address account) external view returns (uint256);
+    }
+}
+
+/// Run `calls` in one Multicall3 call at `block`, through the RPC failover
+pub(crate) async fn multicall(
+    rpc: &Rpc,
+    calls: Vec<IMulticall3::Call3>,
+    block: BlockId,
+) -> Result<Vec<IMulticall3::Result>, alloy::contract::Error> {
+    let returned = rpc
+        .call(|p| {
+            let calls = calls.clone();
+            async move {
+                IMulticall3::new(MULTICALL3, &p)
+                    .aggregate3(calls)
+                    .block(block)
+                    .call()
+                    .await
+            }
+        })
+        .await?;
+    Ok(returned.returnData)
+}
+
+/// Reconciliation limits, from the environment
+#[derive(Debug, Clone)]
+pub struct ReconcileConfig {
+    pub tokens: i64,
+    pub top_holders: i32,
+    pub settle: Duration,
+    /// Wallets per Multicall3 call
+    pub batch: usize,
+    /// How long a running verification can go without reporting progress
+    /// before another processor takes it over
+    pub verify_stale: Duration,
+}
+
+impl ReconcileConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: i64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<i64>()
+                .unwrap_or(fallback)
+                .max(0)
+        };
+
+        Self {
+            tokens: read("RECONCILE_TOKENS", defaults::RECONCILE_TOKENS, 50),
+            top_holders: read("RECONCILE_TOP_HOLDERS", defaults::RECONCILE_TOP_HOLDERS, 20) as i32,
+            settle: Duration::seconds(read(
+                "RECONCILE_SETTLE_SECS",
+                defaults::RECONCILE_SETTLE_SECS,
+                300,
+            )),
+            batch: read("RECONCILE_BATCH", defaults::RECONCILE_BATCH, 200).max(1) as usize,
+            verify_stale: Duration::seconds(
+                read(
+                    "HOLDER_VERIFY_STALE_SECS",
+                    defaults::HOLDER_VERIFY_STALE_SECS,
+                    600,
+                )
+                .max(1),
+            ),
+        }
+    }
+}
+
+/// A stored balance that disagrees with the chain
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub wallet_address: Address20,
+    pub stored: BigDecimal,
+    pub onchain: BigDecimal,
+}
+
+/// Holders whose stored balance differs from the one read on chain.
+/// `onchain` lines up with `holders`; `None` is a failed read, never a drift.
+pub fn find_drift(holders: &[TokenHolder], onchain: &[Option<BigDecimal>]) -> Vec<Drift> {
+    holders
+        .iter()
+        .zip(onchain)
+        .filter_map(|(holder, onchain)| {
+            let onchain = onchain.as_ref()?;
+            let stored = holder.balance.clone().unwrap_or_default();
+            (stored != *onchain).then(|| Drift {
+                wallet_address: holder.wallet_address,
+                stored,
+                onchain: onchain.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Wallets reading a zero balance on chain, which no longer hold the token
+pub fn find_empty(holders: &[TokenHolder], onchain: &[Option<BigDecimal>]) -> Vec<Address20> {
+    let zero = BigDecimal::from(0);
+    holders
+        .iter()
+        .zip(onchain)
+        .filter(|(_, onchain)| onchain.as_ref() == Some(&zero))
+        .map(|(holder, _)| holder.wallet_address)
+        .collect()
+}
+
+/// `balanceOf` for each wallet at `block`, in one Multicall3 call
+async fn fetch_balances(
+    rpc: &Rpc,
+    token: &Address20,
+    wallets: &[Address20],
+    block: u64,
+) -> Result<Vec<Option<BigDecimal>>, alloy::contract::Error> {
+    let calls: Vec<IMulticall3::Call3> = wallets
+        .iter()
+        .map(|wallet| IMulticall3::Call3 {
+            target: (*token).into(),
+            allowFailure: true,
+            callData: Bytes::from(
+                IERC20Balance::balanceOfCall {
+                    account: (*wallet).into(),
+                }
+                .abi_encode(),
+            ),
+        })
+        .collect();
+
+    let results = multicall(rpc, calls, BlockId::number(block)).await?;
+
+    Ok(results
+        .into_iter()
+        .map(|result| {
+            if !result.success {
+                return None;
+            }
+            let balance =
+                IERC20Balance::balanceOfCall::abi_decode_returns(&result.returnData, true)
+                    .ok()?
+                    ._0;
+            BigDecimal::from_str(&balance.to_string()).ok()
+        })
+        .collect())
+}
+
+type ReconcileError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Check holders against the chain, `config.batch` per Multicall3 call,
+/// correcting drifted balances and deleting holders that hold nothing
+async fn check_holders(
+    rpc: &Rpc,
+    token_address: &Address20,
+    holders: Vec<TokenHolder>,
+    block: u64,
+    config: &ReconcileConfig,
+    counts: &mut VerificationCounts,
+    db_pool: &Pool<Postgres>,
+) -> Result<(), ReconcileError> {
+    let settled_before = Utc::now() - config.settle;
+    let holders: Vec<TokenHolder> = holders
+        .into_iter()
+        .filter(|h| h.last_updated.is_none_or(|at| at < settled_before))
+        .collect();
+
+    for chunk in holders.chunks(config.batch) {
+        let wallets: Vec<Address20> = chunk.iter().map(|h| h.wallet_address).collect();
+        let onchain = fetch_balances(rpc, token_address, &wallets, block).await?;
+        counts.holders_checked += onchain.iter().flatten().count() as i32;
+
+        let drift = find_drift(chunk, &onchain);
+        let empty = find_empty(chunk, &onchain);
+        if drift.is_empty() && empty.is_empty() {
+            continue;
+        }
+
+        let mut tx = db_pool.begin().await?;
+        for d in &drift {
+            let correction = NewHolderReconciliation {
+                token_address: *token_address,
+                wallet_address: d.wallet_address,
+                stored_balance: d.stored.clone(),
+                onchain_balance: d.onchain.clone(),
+                block_number: block as i64,
+            };
+            HolderReconciliation::create(&correction, &mut *tx).await?;
+            // Emptied holders are pruned below; dev wallets are kept at zero
+            if empty.contains(&d.wallet_address)
+                && TokenHolder::prune(token_address, &d.wallet_address, &mut *tx).await?
+            {
+                counts.holders_pruned += 1;
+            } else {
+                TokenHolder::update_balance(
+                    token_address,
+                    &d.wallet_address,
+                    &d.onchain,
+                    None,
+                    &mut *tx,
+                )
+                .await?;
+            }
+        }
+        for wallet in empty
+            .iter()
+            .filter(|w| !drift.iter().any(|d| d.wallet_address == **w))
+        {
+            if TokenHolder::prune(token_address, wallet, &mut *tx).await? {
+                counts.holders_pruned += 1;
+            }
+        }
+        tx.commit().await?;
+        counts.balances_corrected += drift.len() as i32;
+    }
+
+    Ok(())
+}
+
+/// Recompute supply percentages and the token's holder metrics after a check
+async fn refresh_metrics(
+    token_address: &Address20,
+    db_pool: &Pool<Postgres>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    if let Some(total_supply) = Token::find_by_address(token_address, &mut *tx)
+        .await?
+        .and_then(|t| t.total_supply)
+        .filter(|supply| *supply > BigDecimal::from(0))
+    {
+        TokenHolder::recalculate_percentages(token_address, &total_supply, &mut *tx).await?;
+    }
+    Token::refresh_holder_metrics(token_address, &mut *tx).await?;
+    tx.commit().await
+}
+
+/// Reconcile one token's top holders, returning the corrections made
+pub(crate) async fn reconcile_token(
+    rpc: &Rpc,
+    token_address: &Address20,
+    block: u64,
+    config: &ReconcileConfig,
+    db_pool: &Pool<Postgres>,
+) -> Result<usize, ReconcileError> {
+    let holders = TokenHolder::find_top_holders(token_address, config.top_holders, db_pool).await?;
+    let mut counts = VerificationCounts::default();
+    check_holders(
+        rpc,
+        token_address,
+        holders,
+        block,
+        config,
+        &mut counts,
+        db_pool,
+    )
+    .await?;
+    if counts.balances_corrected == 0 && counts.holders_pruned == 0 {
+        return Ok(0);
+    }
+
+    refresh_metrics(token_address, db_pool).await?;
+    Ok(counts.balances_corrected as usize)
+}
+
+/// Check every stored holder of a token, a page at a time, recording the
+/// progress of verification `id` after each one
+async fn verify_holders(
+    rpc: &Rpc,
+    id: i32,
+    token_address: &Address20,
+    block: u64,
+    config: &ReconcileConfig,
+    counts: &mut VerificationCounts,
+    db_pool: &Pool<Postgres>,
+) -> Result<(), ReconcileError> {
+    let mut after_id = 0;
+    loop {
+        let page =
+            TokenHolder::find_page(token_address, after_id, config.batch as i64, db_pool).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after_id = last.id;
+        check_holders(
+            rpc,
+            token_address,
+            page,
+            block,
+            config,
+            counts,
+            db_pool,
+        )
+        .await?;
+        HolderVerification::record_progress(id, counts, db_pool).await?;
+    }
+
+    refresh_metrics(token_address, db_pool).await?;
+    Ok(())
+}
+
+/// Run the oldest pending holder verification, if any
+pub async fn verify_next(db_pool: &Pool<Postgres>, config: &ReconcileConfig) {
+    // Left queued until the processor has handled a block to read them at
+    let block = match ProcessorProgress::last_processed_block(db_pool).await {
+        Ok(Some(block)) => block as u64,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Holder verification: failed to read processor progress: {}", e);
+            return;
+        }
+    };
+    let stale_secs = config.verify_stale.num_seconds();
+    let verification = match HolderVerification::claim_next(stale_secs, db_pool).await {
+        Ok(Some(verification)) => verification,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to claim a holder verification: {}", e);
+            return;
+        }
+    };
+    let token_address = verification.token_address;
+
+    let mut counts = VerificationCounts::default();
+    let outcome: Result<u64, ReconcileError> = async {
+        verify_holders(
+            &Rpc::shared(),
+            verification.id,
+            &token_address,
+            block,
+            config,
+            &mut counts,
+            db_pool,
+        )
+        .await?;
+        Ok(block)
+    }
+    .await;
+
+    let recorded = match &outcome {
+        Ok(block) => {
+            HolderVerification::finish(verification.id, &counts, *block as i64, db_pool).await
+        }
+        Err(e) => HolderVerification::fail(verification.id, &counts, &e.to_string(), db_pool).await,
+    };
+
+    match outcome {
+        Ok(block) => tracing::info!(
+            "Holder verification #{} of {} at block {}: {} holders checked, {} balances corrected, {} pruned",
+            verification.id,
+            token_address,
+            block,
+            counts.holders_checked,
+            counts.balances_corrected,
+            counts.holders_pruned
+        ),
+        Err(e) => tracing::error!(
+            "Holder verification #{} of {} failed after {} holders: {}",
+            verification.id, token_address, counts.holders_checked, e
+        ),
+    }
+    if let Err(e) = recorded {
+        tracing::error!(
+            "Failed to record the outcome of holder verification #{}: {}",
+            verification.id, e
+        );
+    }
+}
+
+/// One reconciliation pass over the most traded tokens
+pub async fn run(db_pool: &Pool<Postgres>, config: &ReconcileConfig) {
+    if config.tokens == 0 || config.top_holders == 0 {
+        return;
+    }
+
+    let rpc = Rpc::shared();
+
+    let block = match ProcessorProgress::last_processed_block(db_pool).await {
+        Ok(Some(block)) => block as u64,
+        Ok(None) => {
+            tracing::info!("Holder reconciliation: nothing processed yet, skipping");
+            return;
+        }
+        Err(e) => {
+            tracing::error!("Holder reconciliation: failed to read processor progress: {}", e);
+            return;
+        }
+    };
+    let tokens = match HolderReconciliation::find_tracked_tokens(config.tokens, db_pool).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            tracing::error!("Holder reconciliation: failed to list tokens: {}", e);
+            return;
+        }
+    };
+
+    let mut corrected = 0;
+    for token_address in &tokens {
+        match reconcile_token(&rpc, token_address, block, config, db_pool).await {
+            Ok(count) => corrected += count,
+            Err(e) => tracing::error!("Failed to reconcile holders of {}: {}", token_address, e),
+        }
+    }
+
+    tracing::info!(
+        "Holder reconciliation at block {}: {} tokens checked, {} balances corrected",
+        block,
+        tokens.len(),
+        corrected
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holder(wallet: u8, balance: Option<i64>) -> TokenHolder {
+        TokenHolder {
+            id: wallet as i32,
+            token_address: Address20::new([1; 20]),
+            wallet_address: Address20::new([wallet; 20]),
+            balance: balance.map(BigDecimal::from),
+            percent_of_supply: None,
+            is_dev: None,
+            is_sniper: None,
+            is_contract: None,
+            first_buy_block: None,
+            last_updated: None,
+            source_log_id: None,
+            source_tx_hash: None,
+        }
+    }
+
+    #[test]
+    fn only_readable_mismatches_drift() {
+        let holders = [
+            holder(1, Some(100)),
+            holder(2, Some(50)),
+            holder(3, None),
+            holder(4, Some(7)),
+        ];
+        let onchain = [
+            Some(BigDecimal::from(100)),
+            Some(BigDecimal::from(80)),
+            Some(BigDecimal::from(5)),
+            None,
+        ];
+
+        let drift = find_drift(&holders, &onchain);
+        assert_eq!(
+            drift,
+            vec![
+                Drift {
+                    wallet_address: Address20::new([2; 20]),
+                    stored: BigDecimal::from(50),
+                    onchain: BigDecimal::from(80),
+                },
+                Drift {
+                    wallet_address: Address20::new([3; 20]),
+                    stored: BigDecimal::from(0),
+                    onchain: BigDecimal::from(5),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_readable_zero_balances_are_empty() {
+        let holders = [
+            holder(1, Some(100)),
+            holder(2, Some(0)),
+            holder(3, Some(40)),
+            holder(4, Some(0)),
+        ];
+        let onchain = [
+            Some(BigDecimal::from(0)),
+            Some(BigDecimal::from(0)),
+            Some(BigDecimal::from(40)),
+            None,
+        ];
+
+        assert_eq!(
+            find_empty(&holders, &onchain),
+            vec![Address20::new([1; 20]), Address20::new([2; 20])]
+        );
+    }
+}
@@ -23,7 +23,7 @@ impl RedisPublisher {
             .await
             .map_err(|e| AppError::RedisConnection(e.to_string()))?;
 
-        println!("Connected to Redis at {}", redis_url);
+        tracing::info!("Connected to Redis at {}", redis_url);
         Ok(Self { connection })
     }
 
@@ -45,5 +45,11 @@ pub mod channels {
     pub const SWAP: &str = "chain:events:swap";
     /// Channel for transfer events (wallet activity)
     pub const TRANSFER: &str = "chain:events:transfer";
+    /// Channel for sync events (pair reserves)
+    pub const SYNC: &str = "chain:events:sync";
+    /// Channel for mint/burn events (LP supply changes)
+    pub const LIQUIDITY: &str = "chain:events:liquidity";
+    /// Channel for approval events (ERC20 allowances)
+    pub const APPROVAL: &str = "chain:events:approval";
 }
 
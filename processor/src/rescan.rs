@@ -0,0 +1,326 @@
+//! Single-token rescans
+//!
+//! Operators queue a rescan through the API or `indexer-db-cli` when one
+//! token's data is wrong. Each pass claims the oldest pending request and:
+//!
+//! 1. Re-fetches the token's metadata
+//! 2. Deletes the rows the replay recreates (see [`TokenRescan::clear_range`])
+//! 3. Replays its pairs' Swap/Sync/Mint/Burn and its Transfer logs
+//!    for the range, read from the RPC in `RESCAN_BLOCK_RANGE` block chunks,
+//!    through the regular handlers with alerts off
+//! 4. Recomputes the token's derived columns and BeeScore
+//!
+//! The range defaults to the token's creation block up to the processor's
+//! last processed block, and never goes past it: later logs are the live
+//! processor's. While the rescan runs, the processor holds back the token's
+//! and its pairs' logs, and handles them once it is done. Holder balances are
+//! cumulative, so a range starting later leaves them as they are. Replayed
+//! Syncs chart prices but leave the pairs' reserves and the token's price as
+//! they are now.
+
+use std::{env, error::Error};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{Address, B256},
+    providers::Provider,
+    rpc::types::{BlockTransactionsKind, Filter},
+};
+use chrono::{DateTime, Utc};
+use indexer_db::{
+    entity::{
+        evm_logs::EvmLogs,
+        job::Job,
+        pair::Pair,
+        processor_progress::ProcessorProgress,
+        token::{NewToken, Token},
+        token_rescan::{RescanRange, TokenRescan},
+    },
+    maintenance,
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+use crate::{
+    defaults,
+    error::AppError,
+    events::{self, topics},
+    handlers::{self, HandlerContext, Replay},
+    rpc::Rpc,
+    service, utils,
+};
+
+type RescanError = Box<dyn Error + Send + Sync>;
+
+/// Run the oldest pending rescan, if any
+pub async fn run(db_pool: &Pool<Postgres>) {
+    let rescan = match TokenRescan::claim_next(db_pool).await {
+        Ok(Some(rescan)) => rescan,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to claim a token rescan: {}", e);
+            return;
+        }
+    };
+    execute(&rescan, None, db_pool).await;
+}
+
+/// Run a claimed rescan and record its outcome. Returns whether it finished.
+///
+/// `job` is the import job running it, if any: the rescan reports to it
+/// after every chunk of blocks, keeping it from being claimed again, and
+/// fails as cancelled when the job is cancelled.
+pub async fn execute(rescan: &TokenRescan, job: Option<i32>, db_pool: &Pool<Postgres>) -> bool {
+    tracing::info!(
+        "Rescanning {} (rescan #{})",
+        rescan.token_address, rescan.id
+    );
+
+    let mut replayed = 0;
+    let outcome = rescan_token(rescan, job, &mut replayed, db_pool).await;
+    let recorded = match &outcome {
+        Ok(()) => TokenRescan::finish(rescan.id, replayed, db_pool).await,
+        Err(e) => TokenRescan::fail(rescan.id, replayed, &e.to_string(), db_pool).await,
+    };
+
+    if let Err(e) = recorded {
+        tracing::error!(
+            "Failed to record the outcome of rescan #{}: {}",
+            rescan.id, e
+        );
+    }
+    match outcome {
+        Ok(()) => {
+            tracing::info!(
+                "Rescan #{} of {}: replayed {} logs",
+                rescan.id, rescan.token_address, replayed
+            );
+            true
+        }
+        Err(e) => {
+            tracing::error!(
+                "Rescan #{} of {} failed after {} logs: {}",
+                rescan.id, rescan.token_address, replayed, e
+            );
+            false
+        }
+    }
+}
+
+async fn rescan_token(
+    rescan: &TokenRescan,
+    job: Option<i32>,
+    replayed: &mut i32,
+    db_pool: &Pool<Postgres>,
+) -> Result<(), RescanError> {
+    let address = rescan.token_address;
+    let token = Token::find_by_address(&address, db_pool)
+        .await?
+        .ok_or("Token is not indexed")?;
+
+    let mut ctx = service::create_handler_context(db_pool.clone()).await?;
+
+    // 1. Metadata
+    let metadata = ctx.fetch_token_metadata(&address).await;
+    let refreshed = NewToken {
+        address,
+        name: metadata.name,
+        symbol: metadata.symbol,
+        name_raw: metadata.name_raw,
+        symbol_raw: metadata.symbol_raw,
+        name_spoofed: metadata.name_spoofed,
+        decimals: metadata.decimals,
+        total_supply: metadata
+            .total_supply
+            .and_then(|s| s.parse::<BigDecimal>().ok()),
+        pair_address: token.pair_address,
+        creator_address: token.creator_address,
+        block_number: token.block_number,
+    };
+    Token::create(&refreshed, db_pool).await?;
+    ctx.entities.invalidate_token(&address);
+
+    // 2. Range, and the rows it covers
+    let from_block = match (rescan.from_block, token.block_number) {
+        (Some(from), _) => from,
+        (None, Some(created)) => created,
+        (None, None) => return Err("Creation block unknown, a start block is required".into()),
+    };
+    let processed = ProcessorProgress::last_processed_block(db_pool)
+        .await?
+        .ok_or("The processor hasn't processed any logs yet")?;
+    let to_block = rescan.to_block.map_or(processed, |to| to.min(processed));
+    if from_block > to_block {
+        return Err(format!("Start block {} is past end block {}", from_block, to_block).into());
+    }
+    let range = RescanRange {
+        from_block,
+        to_block,
+        from_time: block_time(&ctx.rpc, from_block as u64).await?,
+        to_time: block_time(&ctx.rpc, to_block as u64).await?,
+        rebuild_holders: token
+            .block_number
+            .is_some_and(|created| from_block <= created),
+    };
+
+    let cleared = TokenRescan::clear_range(&address, &range, db_pool).await?;
+    tracing::info!(
+        "Rescan #{}: cleared {} rows for blocks {}..={}",
+        rescan.id, cleared, from_block, to_block
+    );
+    // Its main pair and any other pair it trades in
+    let mut pairs: Vec<_> = Pair::find_by_token(&address, db_pool)
+        .await?
+        .into_iter()
+        .map(|pair| pair.address)
+        .collect();
+    if let Some(main) = token.pair_address.filter(|main| !pairs.contains(main)) {
+        pairs.push(main);
+    }
+
+    // 3. Replay
+    ctx.replay = Some(Replay {
+        holders: range.rebuild_holders,
+    });
+    let mut emitters: Vec<Address> = vec![address.into()];
+    emitters.extend(pairs.iter().map(|pair| Address::from(*pair)));
+    let signatures = [
+        topics::SWAP,
+        topics::SYNC,
+        topics::TRANSFER,
+        topics::MINT,
+        topics::BURN,
+    ]
+    .iter()
+    .map(|t| t.parse::<B256>())
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let chunk = env::var("RESCAN_BLOCK_RANGE")
+        .unwrap_or_else(|_| defaults::RESCAN_BLOCK_RANGE.to_string())
+        .parse::<u64>()
+        .unwrap_or(2000)
+        .max(1);
+    for (start, end) in block_chunks(from_block as u64, to_block as u64, chunk) {
+        let filter = Filter::new()
+            .address(emitters.clone())
+            .event_signature(signatures.clone())
+            .from_block(start)
+            .to_block(end);
+        let mut logs = ctx
+            .rpc
+            .call(|p| {
+                let filter = filter.clone();
+                async move { p.get_logs(&filter).await }
+            })
+            .await?;
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+        let start_time = block_time(&ctx.rpc, start).await?;
+        let end_time = match end > start {
+            true => block_time(&ctx.rpc, end).await?,
+            false => start_time,
+        };
+
+        for mut log in logs {
+            if log.block_timestamp.is_none() {
+                let block = log.block_number.unwrap_or(start);
+                log.block_timestamp = Some(interpolate_time(
+                    (start, start_time),
+                    (end, end_time),
+                    block,
+                ));
+            }
+            let log = EvmLogs::from_rpc_log(&log)?;
+            match replay(&ctx, &log).await {
+                Ok(()) => *replayed += 1,
+                Err(e) => tracing::error!(
+                    "Rescan #{}: skipped log {}:{}: {}",
+                    rescan.id, log.block_number, log.log_index, e
+                ),
+            }
+        }
+
+        if let Some(job) = job {
+            if Job::report_progress(job, 1, Some(2), db_pool).await? {
+                return Err(format!("Cancelled with job #{}", job).into());
+            }
+        }
+    }
+
+    // 4. Derived columns and score
+    maintenance::reindex_token(&address, db_pool).await?;
+    if let Err(e) = service::update_token_score(&address, &ctx).await {
+        tracing::error!("Rescan #{}: failed to re-score: {}", rescan.id, e);
+    }
+
+    Ok(())
+}
+
+/// Run one log through the handler for its event
+async fn replay(ctx: &HandlerContext, log: &EvmLogs) -> Result<(), AppError> {
+    let topic0 = format!("0x{}", utils::vec_to_hex(log.event_signature.to_vec()));
+    match topic0.as_str() {
+        topics::SWAP => handlers::swap::handle(ctx, &events::swap::decode(log)?).await,
+        topics::SYNC => handlers::sync::handle(ctx, &events::sync::decode(log)?).await,
+        topics::TRANSFER => handlers::transfer::handle(ctx, &events::transfer::decode(log)?).await,
+        topics::MINT | topics::BURN => {
+            handlers::liquidity::handle(ctx, &events::liquidity::decode(log)?).await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Timestamp of a block
+async fn block_time(rpc: &Rpc, block_number: u64) -> Result<DateTime<Utc>, RescanError> {
+    let block = rpc
+        .call(|p| async move {
+            p.get_block_by_number(
+                BlockNumberOrTag::Number(block_number),
+                BlockTransactionsKind::Hashes,
+            )
+            .await
+        })
+        .await?
+        .ok_or_else(|| format!("Block {} not found", block_number))?;
+
+    DateTime::from_timestamp(block.header.timestamp as i64, 0)
+        .ok_or_else(|| format!("Block {} has an invalid timestamp", block_number).into())
+}
+
+/// Inclusive `[start, end]` ranges of at most `size` blocks covering `from..=to`
+fn block_chunks(from: u64, to: u64, size: u64) -> impl Iterator<Item = (u64, u64)> {
+    (from..=to)
+        .step_by(size as usize)
+        .map(move |start| (start, start.saturating_add(size - 1).min(to)))
+}
+
+/// Unix seconds for `block`, interpolated between two known block times, as
+/// the listener does for logs the RPC returns without one
+fn interpolate_time(from: (u64, DateTime<Utc>), to: (u64, DateTime<Utc>), block: u64) -> u64 {
+    let (from_block, from_time) = (from.0, from.1.timestamp() as u64);
+    let (to_block, to_time) = (to.0, to.1.timestamp() as u64);
+    let offset = block.saturating_sub(from_block);
+    let span = to_block.saturating_sub(from_block).max(1);
+    from_time + to_time.saturating_sub(from_time) * offset / span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_cover_the_range_without_overlap() {
+        let chunks: Vec<_> = block_chunks(100, 350, 100).collect();
+        assert_eq!(chunks, vec![(100, 199), (200, 299), (300, 350)]);
+        assert_eq!(block_chunks(5, 5, 2000).collect::<Vec<_>>(), vec![(5, 5)]);
+    }
+
+    #[test]
+    fn block_times_are_interpolated_within_a_chunk() {
+        let start = DateTime::from_timestamp(1_000, 0).unwrap();
+        let end = DateTime::from_timestamp(1_300, 0).unwrap();
+        assert_eq!(interpolate_time((100, start), (200, end), 100), 1_000);
+        assert_eq!(interpolate_time((100, start), (200, end), 150), 1_150);
+        assert_eq!(interpolate_time((100, start), (200, end), 200), 1_300);
+        assert_eq!(interpolate_time((100, start), (100, start), 100), 1_000);
+    }
+}
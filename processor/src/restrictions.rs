@@ -0,0 +1,683 @@
+//! Contract restriction detection
+//!
+//! Owner-only functions that blacklist or whitelist addresses or cap
+//! transaction sizes let a dev trap holders after launch. Each pass scans the
+//! bytecode of up to `RESTRICTION_SCAN_BATCH` new tokens for the selectors of
+//! such functions (`token_contract_scans`), then reads the transactions of up
+//! to `RESTRICTION_WATCH_MAX_BLOCKS` new blocks for calls to them on the tokens
+//! that have any. Calls are recorded in `restriction_calls`; blacklisting a
+//! holder after the token's pair was created raises a `holder_blacklisted`
+//! alert.
+
+use std::{collections::HashMap, env};
+
+use alloy::{
+    consensus::Transaction as _, eips::BlockNumberOrTag, primitives::keccak256,
+    providers::Provider, rpc::types::BlockTransactionsKind,
+};
+use chrono::{DateTime, Utc};
+use indexer_db::{
+    entity::{
+        alert::{AlertEvent, AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, HolderBlacklistedMetadata},
+        contract_scan::ContractScan,
+        restriction_call::{NewRestrictionCall, RestrictionCall},
+        token::Token,
+        token_holder::TokenHolder,
+    },
+    Address20, Hash32,
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+use crate::{defaults, rpc::Rpc, utils};
+
+/// Most addresses read from one address-array argument
+const MAX_TARGETS: usize = 500;
+
+/// What a restriction function does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestrictionKind {
+    /// Stops an address from trading
+    Blacklist,
+    /// Exempts an address from limits, or lets only listed addresses trade
+    Whitelist,
+    /// Caps the size of a transaction or wallet
+    MaxTx,
+}
+
+impl RestrictionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RestrictionKind::Blacklist => "blacklist",
+            RestrictionKind::Whitelist => "whitelist",
+            RestrictionKind::MaxTx => "max_tx",
+        }
+    }
+}
+
+/// Arguments of a restriction function, as far as they matter here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Params {
+    /// `(address)`
+    Address,
+    /// `(address,bool)`: the flag sets or lifts the restriction
+    AddressFlag,
+    /// `(address[])`
+    Addresses,
+    /// `(address[],bool)`
+    AddressesFlag,
+    /// `(uint256)`
+    Amount,
+}
+
+/// A known restriction function
+#[derive(Debug, PartialEq, Eq)]
+pub struct RestrictionFunction {
+    pub signature: &'static str,
+    pub kind: RestrictionKind,
+    pub params: Params,
+}
+
+impl RestrictionFunction {
+    /// First four bytes of the signature's keccak256
+    pub fn selector(&self) -> [u8; 4] {
+        let hash = keccak256(self.signature);
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+}
+
+const fn function(
+    signature: &'static str,
+    kind: RestrictionKind,
+    params: Params,
+) -> RestrictionFunction {
+    RestrictionFunction {
+        signature,
+        kind,
+        params,
+    }
+}
+
+/// Restriction functions commonly found in BSC token templates
+pub const FUNCTIONS: &[RestrictionFunction] = &[
+    function(
+        "blacklist(address)",
+        RestrictionKind::Blacklist,
+        Params::Address,
+    ),
+    function(
+        "addToBlacklist(address)",
+        RestrictionKind::Blacklist,
+        Params::Address,
+    ),
+    function(
+        "addBlacklist(address)",
+        RestrictionKind::Blacklist,
+        Params::Address,
+    ),
+    function(
+        "blacklistAddress(address)",
+        RestrictionKind::Blacklist,
+        Params::Address,
+    ),
+    function(
+        "setBlacklist(address,bool)",
+        RestrictionKind::Blacklist,
+        Params::AddressFlag,
+    ),
+    function(
+        "setBlacklisted(address,bool)",
+        RestrictionKind::Blacklist,
+        Params::AddressFlag,
+    ),
+    function(
+        "setIsBlacklisted(address,bool)",
+        RestrictionKind::Blacklist,
+        Params::AddressFlag,
+    ),
+    function(
+        "addBots(address[])",
+        RestrictionKind::Blacklist,
+        Params::Addresses,
+    ),
+    function(
+        "setBots(address[])",
+        RestrictionKind::Blacklist,
+        Params::Addresses,
+    ),
+    function(
+        "blockBots(address[])",
+        RestrictionKind::Blacklist,
+        Params::Addresses,
+    ),
+    function(
+        "blacklistMultipleAddresses(address[],bool)",
+        RestrictionKind::Blacklist,
+        Params::AddressesFlag,
+    ),
+    function(
+        "addToWhitelist(address)",
+        RestrictionKind::Whitelist,
+        Params::Address,
+    ),
+    function(
+        "setWhitelist(address,bool)",
+        RestrictionKind::Whitelist,
+        Params::AddressFlag,
+    ),
+    function(
+        "setWhitelisted(address,bool)",
+        RestrictionKind::Whitelist,
+        Params::AddressFlag,
+    ),
+    function(
+        "setMaxTxAmount(uint256)",
+        RestrictionKind::MaxTx,
+        Params::Amount,
+    ),
+    function("setMaxTx(uint256)", RestrictionKind::MaxTx, Params::Amount),
+    function(
+        "setMaxTxPercent(uint256)",
+        RestrictionKind::MaxTx,
+        Params::Amount,
+    ),
+    function(
+        "setMaxWalletSize(uint256)",
+        RestrictionKind::MaxTx,
+        Params::Amount,
+    ),
+];
+
+/// Restriction functions whose selector `code` pushes
+///
+/// Solidity dispatches on the selector with a `PUSH4 <selector>` comparison,
+/// so the selectors are looked for as PUSH4 operands. Other push data is
+/// skipped rather than read as opcodes.
+pub fn find_in_bytecode(code: &[u8]) -> Vec<&'static RestrictionFunction> {
+    const PUSH1: u8 = 0x60;
+    const PUSH4: u8 = 0x63;
+    const PUSH32: u8 = 0x7f;
+
+    let mut pushed = Vec::new();
+    let mut i = 0;
+    while i < code.len() {
+        let op = code[i];
+        if (PUSH1..=PUSH32).contains(&op) {
+            let size = (op - PUSH1 + 1) as usize;
+            if op == PUSH4 {
+                if let Some(operand) = code.get(i + 1..i + 5) {
+                    pushed.push([operand[0], operand[1], operand[2], operand[3]]);
+                }
+            }
+            i += size;
+        }
+        i += 1;
+    }
+
+    FUNCTIONS
+        .iter()
+        .filter(|f| pushed.contains(&f.selector()))
+        .collect()
+}
+
+/// A decoded call to a restriction function
+#[derive(Debug, PartialEq, Eq)]
+pub struct Call {
+    pub function: &'static RestrictionFunction,
+    /// Addresses restricted (or released); empty for max transaction changes
+    pub targets: Vec<Address20>,
+    /// False when the call lifts the restriction
+    pub enabled: bool,
+}
+
+/// 32-byte argument word `n` of `args`
+fn word(args: &[u8], n: usize) -> Option<&[u8]> {
+    args.get(n * 32..(n + 1) * 32)
+}
+
+/// ABI `bool` at word `n`
+fn flag(args: &[u8], n: usize) -> Option<bool> {
+    word(args, n).map(|w| w.iter().any(|b| *b != 0))
+}
+
+/// ABI `address[]` whose offset is at word `n`
+fn addresses(args: &[u8], n: usize) -> Option<Vec<Address20>> {
+    let offset = utils::word_to_u64(word(args, n)?)? as usize;
+    let len = utils::word_to_u64(args.get(offset..offset.checked_add(32)?)?)? as usize;
+    let items = args.get(offset + 32..)?;
+    (0..len.min(MAX_TARGETS))
+        .map(|i| utils::word_to_address(word(items, i)?))
+        .collect()
+}
+
+/// Decode `input` as a call to one of `functions`
+pub fn decode_call(input: &[u8], functions: &[&'static RestrictionFunction]) -> Option<Call> {
+    let selector = input.get(..4)?;
+    let function = *functions.iter().find(|f| f.selector() == selector)?;
+    let args = &input[4..];
+
+    let (targets, enabled) = match function.params {
+        Params::Address => (vec![utils::word_to_address(word(args, 0)?)?], true),
+        Params::AddressFlag => (
+            vec![utils::word_to_address(word(args, 0)?)?],
+            flag(args, 1)?,
+        ),
+        Params::Addresses => (addresses(args, 0)?, true),
+        Params::AddressesFlag => (addresses(args, 0)?, flag(args, 1)?),
+        Params::Amount => {
+            word(args, 0)?;
+            (Vec::new(), true)
+        }
+    };
+
+    Some(Call {
+        function,
+        targets,
+        enabled,
+    })
+}
+
+/// Scan and watch limits, from the environment
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    pub chain_id: i64,
+    pub scan_batch: i64,
+    pub max_blocks: u64,
+}
+
+impl WatchConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: i64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<i64>()
+                .unwrap_or(fallback)
+                .max(0)
+        };
+
+        Self {
+            chain_id: read("CHAIN_ID", defaults::CHAIN_ID, 56),
+            scan_batch: read(
+                "RESTRICTION_SCAN_BATCH",
+                defaults::RESTRICTION_SCAN_BATCH,
+                20,
+            ),
+            max_blocks: read(
+                "RESTRICTION_WATCH_MAX_BLOCKS",
+                defaults::RESTRICTION_WATCH_MAX_BLOCKS,
+                40,
+            ) as u64,
+        }
+    }
+}
+
+/// Scan the bytecode of tokens not scanned yet
+async fn scan_new_tokens(rpc: &Rpc, batch: i64, db_pool: &Pool<Postgres>) {
+    let tokens = match ContractScan::find_unscanned(batch, db_pool).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            tracing::error!("Restriction scan: failed to list tokens: {}", e);
+            return;
+        }
+    };
+
+    for token_address in &tokens {
+        let address = (*token_address).into();
+        let code = match rpc
+            .call(|p| async move { p.get_code_at(address).await })
+            .await
+        {
+            Ok(code) => code,
+            Err(e) => {
+                tracing::error!(
+                    "Restriction scan: failed to read code of {}: {}",
+                    token_address, e
+                );
+                continue;
+            }
+        };
+        let restrictions: Vec<String> = find_in_bytecode(&code)
+            .iter()
+            .map(|f| f.signature.to_string())
+            .collect();
+
+        if let Err(e) =
+            ContractScan::upsert(token_address, code.len() as i32, &restrictions, db_pool).await
+        {
+            tracing::error!("Failed to record contract scan of {}: {}", token_address, e);
+        } else if !restrictions.is_empty() {
+            tracing::info!(
+                "Token {} can restrict holders: {}",
+                token_address,
+                restrictions.join(", ")
+            );
+        }
+    }
+}
+
+/// Record a restriction call and alert holders blacklisted after launch
+async fn record_call(
+    token_address: &Address20,
+    call: &Call,
+    caller: Address20,
+    tx_hash: Hash32,
+    block_number: i64,
+    timestamp: DateTime<Utc>,
+    db_pool: &Pool<Postgres>,
+) -> Result<(), sqlx::Error> {
+    let zero = BigDecimal::from(0);
+    let mut blacklisted = Vec::new();
+
+    let targets: Vec<Option<Address20>> = if call.targets.is_empty() {
+        vec![None]
+    } else {
+        call.targets.iter().copied().map(Some).collect()
+    };
+    for target in targets {
+        let target_is_holder = match target {
+            Some(target) => TokenHolder::find_balance(token_address, &target, db_pool)
+                .await?
+                .is_some_and(|balance| balance > zero),
+            None => false,
+        };
+        let new_call = NewRestrictionCall {
+            token_address: *token_address,
+            tx_hash,
+            block_number,
+            caller,
+            function: call.function.signature.to_string(),
+            kind: call.function.kind.as_str().to_string(),
+            target_address: target,
+            enabled: call.enabled,
+            target_is_holder,
+            timestamp,
+        };
+        let recorded = RestrictionCall::create(&new_call, db_pool).await?.is_some();
+
+        if recorded && target_is_holder {
+            blacklisted.extend(target);
+        }
+    }
+
+    if call.function.kind != RestrictionKind::Blacklist || !call.enabled || blacklisted.is_empty() {
+        return Ok(());
+    }
+    let Some(token) = Token::find_by_address(token_address, db_pool).await? else {
+        return Ok(());
+    };
+    // Bots blacklisted while the pair is being created are fair game
+    if token
+        .block_number
+        .is_none_or(|created| block_number <= created)
+    {
+        return Ok(());
+    }
+
+    let token_symbol = token
+        .symbol
+        .clone()
+        .unwrap_or_else(|| token_address.short());
+    let by = if token.creator_address == Some(caller) {
+        "Dev"
+    } else {
+        "Owner"
+    };
+    let holders = if blacklisted.len() == 1 {
+        blacklisted[0].short()
+    } else {
+        format!("{} holders", blacklisted.len())
+    };
+
+    let alert = NewAlert {
+        alert_type: AlertType::HolderBlacklisted.as_str().to_string(),
+        token_address: Some(*token_address),
+        token_symbol: Some(token_symbol.clone()),
+        wallet_address: Some(caller),
+        title: format!("{} blacklisted {} of {}", by, holders, token_symbol),
+        message: Some(format!(
+            "{} called {} on {} at block {}, blocking {} from trading",
+            caller, call.function.signature, token_symbol, block_number, holders
+        )),
+        bee_score: token.bee_score,
+        amount_usd: None,
+        change_percent: None,
+        metadata: Some(AlertMetadata::HolderBlacklisted(HolderBlacklistedMetadata {
+            function: call.function.signature.to_string(),
+            caller,
+            tx_hash,
+            holders: blacklisted,
+        })),
+        source: None,
+    };
+    AlertEvent::create(&alert, db_pool).await?;
+
+    Ok(())
+}
+
+/// Read blocks after the watch cursor for calls to restriction functions of
+/// `watched` tokens, returning the last block read
+async fn watch_blocks(
+    rpc: &Rpc,
+    watched: &HashMap<Address20, Vec<&'static RestrictionFunction>>,
+    from: u64,
+    to: u64,
+    db_pool: &Pool<Postgres>,
+) -> u64 {
+    let mut last = from - 1;
+
+    for number in from..=to {
+        let block = match rpc
+            .call(|p| async move {
+                p.get_block_by_number(
+                    BlockNumberOrTag::Number(number),
+                    BlockTransactionsKind::Full,
+                )
+                .await
+            })
+            .await
+        {
+            Ok(Some(block)) => block,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::error!("Restriction watch: failed to fetch block {}: {}", number, e);
+                break;
+            }
+        };
+        let timestamp =
+            DateTime::from_timestamp(block.header.timestamp as i64, 0).unwrap_or_else(Utc::now);
+
+        for tx in block.transactions.txns() {
+            let Some(functions) = tx.to().and_then(|to| watched.get(&Address20::from(to))) else {
+                continue;
+            };
+            let Some(call) = decode_call(tx.input(), functions) else {
+                continue;
+            };
+            let token_address = Address20::from(tx.to().unwrap_or_default());
+
+            if let Err(e) = record_call(
+                &token_address,
+                &call,
+                Address20::from(tx.from),
+                Hash32::from(*tx.inner.tx_hash()),
+                number as i64,
+                timestamp,
+                db_pool,
+            )
+            .await
+            {
+                tracing::error!(
+                    "Failed to record {} on {}: {}",
+                    call.function.signature, token_address, e
+                );
+            }
+        }
+        last = number;
+    }
+
+    last
+}
+
+/// One pass: scan new tokens, then read the blocks since the last pass
+pub async fn run(db_pool: &Pool<Postgres>, config: &WatchConfig) {
+    let rpc = Rpc::shared();
+
+    if config.scan_batch > 0 {
+        scan_new_tokens(&rpc, config.scan_batch, db_pool).await;
+    }
+
+    let head = match rpc.call(|p| async move { p.get_block_number().await }).await {
+        Ok(head) => head,
+        Err(e) => {
+            tracing::error!("Restriction watch: failed to read block number: {}", e);
+            return;
+        }
+    };
+    let from = match RestrictionCall::last_watched_block(config.chain_id, db_pool).await {
+        Ok(Some(last)) => last as u64 + 1,
+        // Start at the head rather than replaying history
+        Ok(None) => head,
+        Err(e) => {
+            tracing::error!("Restriction watch: failed to read cursor: {}", e);
+            return;
+        }
+    };
+    if from > head || config.max_blocks == 0 {
+        return;
+    }
+    let to = head.min(from + config.max_blocks - 1);
+
+    let watched: HashMap<Address20, Vec<&'static RestrictionFunction>> =
+        match ContractScan::find_restricted(db_pool).await {
+            Ok(scans) => scans
+                .into_iter()
+                .map(|scan| {
+                    let functions = FUNCTIONS
+                        .iter()
+                        .filter(|f| scan.restrictions.iter().any(|s| s == f.signature))
+                        .collect();
+                    (scan.token_address, functions)
+                })
+                .collect(),
+            Err(e) => {
+                tracing::error!("Restriction watch: failed to list restricted tokens: {}", e);
+                return;
+            }
+        };
+
+    // Nothing to look for, so the blocks can be skipped unread
+    let last = if watched.is_empty() {
+        to
+    } else {
+        watch_blocks(&rpc, &watched, from, to, db_pool).await
+    };
+    if last >= from {
+        if let Err(e) =
+            RestrictionCall::set_last_watched_block(config.chain_id, last as i64, db_pool).await
+        {
+            tracing::error!("Restriction watch: failed to move cursor: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(signature: &str) -> &'static RestrictionFunction {
+        FUNCTIONS.iter().find(|f| f.signature == signature).unwrap()
+    }
+
+    fn padded(address: Address20) -> Vec<u8> {
+        let mut word = vec![0u8; 32];
+        word[12..].copy_from_slice(address.as_bytes());
+        word
+    }
+
+    fn uint(n: u64) -> Vec<u8> {
+        let mut word = vec![0u8; 32];
+        word[24..].copy_from_slice(&n.to_be_bytes());
+        word
+    }
+
+    #[test]
+    fn selectors_match_the_abi() {
+        assert_eq!(
+            signature("blacklist(address)").selector(),
+            [0xf9, 0xf9, 0x2b, 0xe4]
+        );
+        assert_eq!(
+            signature("setMaxTxAmount(uint256)").selector(),
+            [0xec, 0x28, 0x43, 0x8a]
+        );
+    }
+
+    #[test]
+    fn selectors_are_found_as_push4_operands_only() {
+        let blacklist = signature("blacklist(address)").selector();
+        let max_tx = signature("setMaxTxAmount(uint256)").selector();
+
+        // PUSH4 blacklist, EQ, then a PUSH32 whose data holds max_tx
+        let mut code = vec![0x63];
+        code.extend_from_slice(&blacklist);
+        code.push(0x14);
+        code.push(0x7f);
+        let mut data = [0u8; 32];
+        data[1] = 0x63;
+        data[2..6].copy_from_slice(&max_tx);
+        code.extend_from_slice(&data);
+
+        assert_eq!(
+            find_in_bytecode(&code),
+            vec![signature("blacklist(address)")]
+        );
+        // Truncated operand
+        assert!(find_in_bytecode(&[0x63, blacklist[0], blacklist[1]]).is_empty());
+        assert!(find_in_bytecode(&[]).is_empty());
+    }
+
+    #[test]
+    fn calls_decode_their_targets() {
+        let functions: Vec<_> = FUNCTIONS.iter().collect();
+        let holder = Address20::new([7; 20]);
+        let other = Address20::new([8; 20]);
+
+        let mut input = signature("setBlacklist(address,bool)").selector().to_vec();
+        input.extend(padded(holder));
+        input.extend(uint(0));
+        let call = decode_call(&input, &functions).unwrap();
+        assert_eq!(call.targets, vec![holder]);
+        assert!(!call.enabled);
+
+        let mut input = signature("addBots(address[])").selector().to_vec();
+        input.extend(uint(32));
+        input.extend(uint(2));
+        input.extend(padded(holder));
+        input.extend(padded(other));
+        let call = decode_call(&input, &functions).unwrap();
+        assert_eq!(call.targets, vec![holder, other]);
+        assert!(call.enabled);
+
+        let mut input = signature("setMaxTxAmount(uint256)").selector().to_vec();
+        input.extend(uint(1_000));
+        let call = decode_call(&input, &functions).unwrap();
+        assert_eq!(call.function.kind, RestrictionKind::MaxTx);
+        assert!(call.targets.is_empty());
+
+        // Array length past the calldata
+        let mut input = signature("addBots(address[])").selector().to_vec();
+        input.extend(uint(32));
+        input.extend(uint(3));
+        input.extend(padded(holder));
+        assert_eq!(decode_call(&input, &functions), None);
+
+        // Functions the token doesn't have are ignored
+        let mut input = signature("blacklist(address)").selector().to_vec();
+        input.extend(padded(holder));
+        assert_eq!(
+            decode_call(&input, &[signature("addBots(address[])")]),
+            None
+        );
+        assert_eq!(decode_call(&input[..20], &functions), None);
+    }
+}
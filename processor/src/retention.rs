@@ -0,0 +1,185 @@
+//! Data retention
+//!
+//! Each policy trims one table past a maximum age. Old swaps, alerts, score
+//! history, wallet activity of untracked wallets, expired API idempotency
+//! keys, recorded processing errors, swaps kept for pairs that never got
+//! indexed, the handled log ledger, per-minute trade buckets, hourly holder
+//! churn and past retention runs are deleted. Old price snapshots are
+//! downsampled to one per hour. Rows go in small batches so a backlog never
+//! holds long locks, and every pass is recorded in `retention_runs`. After the
+//! first complete pass, downsampling only ranks the hours that aged past the
+//! cutoff since.
+
+use std::{env, time::Instant};
+
+use chrono::{DateTime, Duration, Utc};
+use indexer_db::{
+    entity::retention_run::{NewRetentionRun, RetentionRun},
+    maintenance::PruneTable,
+};
+use sqlx::{Pool, Postgres};
+
+use crate::defaults;
+
+/// Most batches one policy may run per pass; the rest waits for the next pass
+const MAX_BATCHES_PER_PASS: i32 = 200;
+
+/// Maximum age in days of `table`: its environment variable and the default
+fn max_age_var(table: PruneTable) -> (&'static str, &'static str) {
+    match table {
+        PruneTable::Swaps => ("SWAP_RETENTION_DAYS", defaults::SWAP_RETENTION_DAYS),
+        PruneTable::WalletActivity => (
+            "WALLET_ACTIVITY_RETENTION_DAYS",
+            defaults::WALLET_ACTIVITY_RETENTION_DAYS,
+        ),
+        PruneTable::AlertEvents => ("ALERT_RETENTION_DAYS", defaults::ALERT_RETENTION_DAYS),
+        PruneTable::PriceSnapshots => (
+            "SNAPSHOT_DOWNSAMPLE_DAYS",
+            defaults::SNAPSHOT_DOWNSAMPLE_DAYS,
+        ),
+        PruneTable::ScoreHistory => (
+            "SCORE_HISTORY_RETENTION_DAYS",
+            defaults::SCORE_HISTORY_RETENTION_DAYS,
+        ),
+        PruneTable::IdempotencyKeys => (
+            "IDEMPOTENCY_KEY_RETENTION_DAYS",
+            defaults::IDEMPOTENCY_KEY_RETENTION_DAYS,
+        ),
+        PruneTable::ProcessingErrors => (
+            "PROCESSING_ERROR_RETENTION_DAYS",
+            defaults::PROCESSING_ERROR_RETENTION_DAYS,
+        ),
+        PruneTable::PendingSwaps => (
+            "PENDING_SWAP_RETENTION_DAYS",
+            defaults::PENDING_SWAP_RETENTION_DAYS,
+        ),
+        PruneTable::HandledLogs => (
+            "HANDLED_LOG_RETENTION_DAYS",
+            defaults::HANDLED_LOG_RETENTION_DAYS,
+        ),
+        PruneTable::TokenMetricsMinute => (
+            "TOKEN_METRICS_RETENTION_DAYS",
+            defaults::TOKEN_METRICS_RETENTION_DAYS,
+        ),
+        PruneTable::HolderChurn => (
+            "HOLDER_CHURN_RETENTION_DAYS",
+            defaults::HOLDER_CHURN_RETENTION_DAYS,
+        ),
+        PruneTable::RetentionRuns => (
+            "RETENTION_RUN_RETENTION_DAYS",
+            defaults::RETENTION_RUN_RETENTION_DAYS,
+        ),
+    }
+}
+
+/// Keep rows of `table` for `max_age_days`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub table: PruneTable,
+    pub max_age_days: u64,
+    /// Cutoff of the last pass that trimmed the table completely; the next
+    /// pass downsamples snapshots from there on only
+    pub trimmed_to: Option<DateTime<Utc>>,
+}
+
+/// Policies configured in the environment; a max age of 0 keeps a table forever
+pub fn policies_from_env() -> Vec<Policy> {
+    PruneTable::ALL
+        .iter()
+        .filter_map(|table| {
+            let (var, default) = max_age_var(*table);
+            let max_age_days = parse_days(env::var(var).ok().as_deref(), default);
+            (max_age_days > 0).then_some(Policy {
+                table: *table,
+                max_age_days,
+                trimmed_to: None,
+            })
+        })
+        .collect()
+}
+
+/// Parse a day count, falling back to `default` when unset or malformed
+fn parse_days(value: Option<&str>, default: &str) -> u64 {
+    value
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or_else(|| default.parse().unwrap_or(0))
+}
+
+/// Apply every policy once, in batches of `batch_size` rows
+pub async fn run(db_pool: &Pool<Postgres>, policies: &mut [Policy], batch_size: i64) {
+    for policy in policies {
+        let run = apply(db_pool, policy, batch_size).await;
+
+        if run.rows_deleted > 0 || run.error.is_some() {
+            tracing::info!(
+                "Retention: {} removed {} rows in {} batches ({} ms){}",
+                run.table_name,
+                run.rows_deleted,
+                run.batches,
+                run.duration_ms,
+                run.error
+                    .as_ref()
+                    .map(|e| format!(", stopped: {}", e))
+                    .unwrap_or_default()
+            );
+        }
+
+        if let Err(e) = RetentionRun::create(&run, db_pool).await {
+            tracing::error!(
+                "Failed to record retention run for {}: {}",
+                run.table_name, e
+            );
+        }
+    }
+}
+
+/// Trim one table until a batch comes back short
+async fn apply(db_pool: &Pool<Postgres>, policy: &mut Policy, batch_size: i64) -> NewRetentionRun {
+    let started = Instant::now();
+    let cutoff = Utc::now() - Duration::days(policy.max_age_days as i64);
+
+    let mut run = NewRetentionRun {
+        table_name: policy.table.name().to_string(),
+        rows_deleted: 0,
+        batches: 0,
+        duration_ms: 0,
+        error: None,
+    };
+
+    while run.batches < MAX_BATCHES_PER_PASS {
+        match policy
+            .table
+            .trim_batch(policy.trimmed_to, cutoff, batch_size, db_pool)
+            .await
+        {
+            Ok(removed) => {
+                run.batches += 1;
+                run.rows_deleted += removed as i64;
+                if (removed as i64) < batch_size {
+                    policy.trimmed_to = Some(cutoff);
+                    break;
+                }
+            }
+            Err(e) => {
+                run.error = Some(e.to_string());
+                break;
+            }
+        }
+    }
+
+    run.duration_ms = started.elapsed().as_millis() as i64;
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_counts_fall_back_to_the_default() {
+        assert_eq!(parse_days(Some("30"), "90"), 30);
+        assert_eq!(parse_days(Some("0"), "90"), 0);
+        assert_eq!(parse_days(Some("soon"), "90"), 90);
+        assert_eq!(parse_days(None, "14"), 14);
+    }
+}
@@ -0,0 +1,342 @@
+//! Shared JSON-RPC provider
+//!
+//! Every chain read goes through one [`Rpc`] for the life of the process.
+//! `RPC_URL` is the primary endpoint and `RPC_FALLBACK_URLS` a comma-separated
+//! list of others. Each endpoint's provider is built the first time it is
+//! used; when a call fails because the endpoint is unreachable or answers
+//! garbage, it is retried on the next endpoint, which stays active for later
+//! calls until it fails in turn.
+//!
+//! Handler calls also draw from an [`RpcBudget`], a token bucket refilled at
+//! `RPC_CALLS_PER_SEC` up to `RPC_CALL_BURST`. Calls that can't be skipped
+//! wait for it; metadata for new pairs is deferred to the repair job instead,
+//! so a launch storm doesn't stall processing or flood the endpoint.
+
+use std::{
+    env,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    providers::{ProviderBuilder, ReqwestProvider},
+    transports::{http::reqwest::Url, RpcError, TransportError},
+};
+
+use crate::{defaults, error::AppError};
+
+struct Endpoint {
+    url: Url,
+    provider: OnceLock<ReqwestProvider>,
+}
+
+/// RPC endpoints in failover order, with the one currently in use
+pub struct Rpc {
+    endpoints: Vec<Endpoint>,
+    active: AtomicUsize,
+}
+
+/// Errors that can say whether the endpoint, rather than the request, failed
+pub trait Failover {
+    fn is_transport(&self) -> bool;
+}
+
+impl Failover for TransportError {
+    fn is_transport(&self) -> bool {
+        matches!(
+            self,
+            RpcError::Transport(_) | RpcError::NullResp | RpcError::DeserError { .. }
+        )
+    }
+}
+
+impl Failover for alloy::contract::Error {
+    fn is_transport(&self) -> bool {
+        matches!(self, alloy::contract::Error::TransportError(e) if e.is_transport())
+    }
+}
+
+/// Refuse a malformed `RPC_URL` or `RPC_FALLBACK_URLS` entry, which
+/// [`Rpc::from_env`] would only skip
+pub fn check_env() -> Result<(), AppError> {
+    let fallbacks = env::var("RPC_FALLBACK_URLS").unwrap_or_default();
+    let urls = env::var("RPC_URL")
+        .ok()
+        .into_iter()
+        .chain(fallbacks.split(',').map(str::to_string))
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    for url in urls {
+        if let Err(e) = url.parse::<Url>() {
+            return Err(AppError::InvalidConfig(format!(
+                "RPC endpoint `{}` is not a valid URL: {}",
+                url, e
+            )));
+        }
+    }
+    Ok(())
+}
+
+impl Rpc {
+    /// Endpoints from `urls`, in order. Malformed ones are reported and
+    /// skipped; with none left the default endpoint is used.
+    pub fn new<S: AsRef<str>>(urls: &[S]) -> Self {
+        let mut endpoints: Vec<Endpoint> = urls
+            .iter()
+            .map(|url| url.as_ref().trim())
+            .filter(|url| !url.is_empty())
+            .filter_map(|url| match url.parse() {
+                Ok(url) => Some(Endpoint {
+                    url,
+                    provider: OnceLock::new(),
+                }),
+                Err(e) => {
+                    tracing::warn!("Ignoring malformed RPC endpoint {:?}: {}", url, e);
+                    None
+                }
+            })
+            .collect();
+
+        if endpoints.is_empty() {
+            endpoints.push(Endpoint {
+                url: defaults::RPC_URL.parse().expect("default RPC_URL is valid"),
+                provider: OnceLock::new(),
+            });
+        }
+
+        Self {
+            endpoints,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Endpoints from `RPC_URL` and `RPC_FALLBACK_URLS`
+    pub fn from_env() -> Self {
+        let mut urls = vec![env::var("RPC_URL").unwrap_or_else(|_| defaults::RPC_URL.to_string())];
+        if let Ok(fallbacks) = env::var("RPC_FALLBACK_URLS") {
+            urls.extend(fallbacks.split(',').map(str::to_string));
+        }
+        Self::new(&urls)
+    }
+
+    /// The process-wide instance, read from the environment on first use
+    pub fn shared() -> Arc<Rpc> {
+        static SHARED: OnceLock<Arc<Rpc>> = OnceLock::new();
+        SHARED.get_or_init(|| Arc::new(Rpc::from_env())).clone()
+    }
+
+    /// Index of the endpoint calls currently go to
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed) % self.endpoints.len()
+    }
+
+    /// Provider for the active endpoint
+    pub fn provider(&self) -> ReqwestProvider {
+        self.provider_at(self.active())
+    }
+
+    fn provider_at(&self, index: usize) -> ReqwestProvider {
+        let endpoint = &self.endpoints[index];
+        endpoint
+            .provider
+            .get_or_init(|| ProviderBuilder::new().on_http(endpoint.url.clone()))
+            .clone()
+    }
+
+    /// Run `request` against the active endpoint, moving on to the next one
+    /// each time it fails at the transport level, until every endpoint has
+    /// been tried once
+    pub async fn call<T, E, F, Fut>(&self, request: F) -> Result<T, E>
+    where
+        E: Failover + std::fmt::Display,
+        F: Fn(ReqwestProvider) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut index = self.active();
+        let mut attempts = 1;
+        loop {
+            match request(self.provider_at(index)).await {
+                Err(e) if e.is_transport() && attempts < self.endpoints.len() => {
+                    let next = (index + 1) % self.endpoints.len();
+                    // Concurrent failures of the same endpoint move on only once
+                    if self
+                        .active
+                        .compare_exchange(index, next, Ordering::Relaxed, Ordering::Relaxed)
+                        .is_ok()
+                    {
+                        tracing::error!(
+                            "RPC endpoint {} failed ({}), failing over to {}",
+                            self.endpoints[index].url, e, self.endpoints[next].url
+                        );
+                    }
+                    index = next;
+                    attempts += 1;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+}
+
+/// Token bucket shared by the calls handlers make
+pub struct RpcBudget {
+    /// Calls added back per second; 0 disables the limit
+    per_sec: f64,
+    burst: f64,
+    /// Calls available and when they were last topped up
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RpcBudget {
+    /// A full bucket of `burst` calls, refilled at `per_sec`
+    pub fn new(per_sec: u32, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            per_sec: f64::from(per_sec),
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Limits from `RPC_CALLS_PER_SEC` and `RPC_CALL_BURST`
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: u32| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<u32>()
+                .unwrap_or(fallback)
+        };
+        Self::new(
+            read("RPC_CALLS_PER_SEC", defaults::RPC_CALLS_PER_SEC, 10),
+            read("RPC_CALL_BURST", defaults::RPC_CALL_BURST, 40),
+        )
+    }
+
+    /// The process-wide budget, read from the environment on first use
+    pub fn shared() -> Arc<RpcBudget> {
+        static SHARED: OnceLock<Arc<RpcBudget>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| Arc::new(RpcBudget::from_env()))
+            .clone()
+    }
+
+    /// Take `calls` if they are available at `now`, or say how long until
+    /// they will be. More calls than the burst count as a full bucket.
+    fn take_at(&self, calls: u32, now: Instant) -> Result<(), Duration> {
+        if self.per_sec == 0.0 {
+            return Ok(());
+        }
+
+        let calls = f64::from(calls).min(self.burst);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (available, topped_up) = &mut *state;
+        let elapsed = now.saturating_duration_since(*topped_up).as_secs_f64();
+        *available = (*available + elapsed * self.per_sec).min(self.burst);
+        *topped_up = now;
+
+        if *available >= calls {
+            *available -= calls;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((calls - *available) / self.per_sec))
+        }
+    }
+
+    /// Take `calls` now, `false` if the budget is spent
+    pub fn try_take(&self, calls: u32) -> bool {
+        self.take_at(calls, Instant::now()).is_ok()
+    }
+
+    /// Take `calls`, waiting for the bucket to refill if needed
+    pub async fn take(&self, calls: u32) {
+        while let Err(wait) = self.take_at(calls, Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::transports::TransportErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn malformed_endpoints_are_skipped() {
+        let rpc = Rpc::new(&["not a url", "", "https://bsc-rpc.publicnode.com"]);
+        assert_eq!(rpc.endpoints.len(), 1);
+        assert_eq!(
+            rpc.endpoints[0].url.as_str(),
+            "https://bsc-rpc.publicnode.com/"
+        );
+
+        let rpc = Rpc::new(&["::"]);
+        assert_eq!(
+            rpc.endpoints[0].url.as_str(),
+            "https://bsc-dataseed.binance.org/"
+        );
+    }
+
+    #[tokio::test]
+    async fn transport_failures_move_to_the_next_endpoint() {
+        let rpc = Rpc::new(&["http://a.invalid", "http://b.invalid", "http://c.invalid"]);
+        let attempts = AtomicUsize::new(0);
+
+        let result: Result<u64, TransportError> = rpc
+            .call(|_| async {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 => Err(TransportErrorKind::custom_str("connection refused")),
+                    _ => Ok(7),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(rpc.active(), 1);
+
+        // Request errors are the caller's to handle, on the same endpoint
+        let result: Result<u64, TransportError> = rpc
+            .call(|_| async { Err(RpcError::UnsupportedFeature("eth_foo")) })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(rpc.active(), 1);
+
+        // Each endpoint is tried once before giving up
+        attempts.store(0, Ordering::Relaxed);
+        let result: Result<u64, TransportError> = rpc
+            .call(|_| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(RpcError::NullResp)
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        assert_eq!(rpc.active(), 0);
+    }
+
+    #[test]
+    fn budget_refills_up_to_the_burst() {
+        let budget = RpcBudget::new(2, 4);
+        let start = Instant::now();
+        *budget.state.lock().unwrap() = (4.0, start);
+
+        assert!(budget.take_at(4, start).is_ok());
+        assert_eq!(budget.take_at(1, start), Err(Duration::from_millis(500)));
+        // Half a second refills one call
+        assert!(budget
+            .take_at(1, start + Duration::from_millis(500))
+            .is_ok());
+        // Never more than the burst, however long it was idle
+        let later = start + Duration::from_secs(60);
+        assert!(budget.take_at(4, later).is_ok());
+        assert!(budget.take_at(1, later).is_err());
+        // Oversized requests wait for a full bucket rather than forever
+        assert_eq!(budget.take_at(10, later), Err(Duration::from_secs(2)));
+
+        let unlimited = RpcBudget::new(0, 1);
+        assert!((0..100).all(|_| unlimited.try_take(4)));
+    }
+}
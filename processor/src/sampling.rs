@@ -0,0 +1,206 @@
+//! Transfer sampling
+//!
+//! Indexing every Transfer on the chain is out of reach on a free RPC. With
+//! `TRANSFER_SAMPLING=true`, Transfer and holder tracking is limited to a
+//! sample: the live tokens with liquidity of at least
+//! `TRANSFER_SAMPLING_MIN_LIQUIDITY_USD` and 24h volume of at least
+//! `TRANSFER_SAMPLING_MIN_VOLUME_USD`, the `TRANSFER_SAMPLING_MAX_TOKENS` most
+//! liquid of them. BeeScores don't count: those of tokens outside the sample
+//! are computed without holders. The sample is reselected every
+//! `TRANSFER_SAMPLING_INTERVAL` seconds into `sampled_tokens`; the listener
+//! only asks the RPC for Transfers of those tokens, and the Transfer handler
+//! skips any other.
+//!
+//! A token entering the sample gets a `token_import` job, which replays its
+//! history from its creation block so its holders are complete. Holder
+//! reconciliation corrects the top holders' balances from the chain.
+
+use std::{
+    collections::HashSet,
+    env,
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use indexer_db::{
+    entity::{
+        job::{Job, JobKind, NewJob},
+        sampled_token::{SampledToken, SamplingCriteria},
+    },
+    Address20,
+};
+use serde_json::json;
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+use crate::defaults;
+
+/// How long the Transfer handler trusts the sample it read; a reselection
+/// in this process refreshes it right away
+const SAMPLE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Whether sampling is on and which tokens it keeps, from the environment
+#[derive(Debug, Clone)]
+pub struct SamplingConfig {
+    pub enabled: bool,
+    pub criteria: SamplingCriteria,
+}
+
+impl SamplingConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: i64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<i64>()
+                .unwrap_or(fallback)
+                .max(0)
+        };
+        let usd = |var: &str, default: &str| {
+            env::var(var)
+                .ok()
+                .and_then(|v| BigDecimal::from_str(v.trim()).ok())
+                .unwrap_or_else(|| BigDecimal::from_str(default).unwrap_or_default())
+        };
+
+        Self {
+            enabled: env::var("TRANSFER_SAMPLING")
+                .unwrap_or_else(|_| defaults::TRANSFER_SAMPLING.to_string())
+                .parse::<bool>()
+                .unwrap_or(false),
+            criteria: SamplingCriteria {
+                min_liquidity_usd: usd(
+                    "TRANSFER_SAMPLING_MIN_LIQUIDITY_USD",
+                    defaults::TRANSFER_SAMPLING_MIN_LIQUIDITY_USD,
+                ),
+                min_volume_24h_usd: usd(
+                    "TRANSFER_SAMPLING_MIN_VOLUME_USD",
+                    defaults::TRANSFER_SAMPLING_MIN_VOLUME_USD,
+                ),
+                max_tokens: read(
+                    "TRANSFER_SAMPLING_MAX_TOKENS",
+                    defaults::TRANSFER_SAMPLING_MAX_TOKENS,
+                    200,
+                ),
+            },
+        }
+    }
+}
+
+/// The sampled tokens, read once per [`SAMPLE_CACHE_TTL`] rather than for
+/// every Transfer
+pub struct SampleCache {
+    ttl: Duration,
+    tokens: Mutex<Option<(Instant, Arc<HashSet<Address20>>)>>,
+}
+
+impl SampleCache {
+    /// A cache keeping the sample for `ttl`; zero reads it every time
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            tokens: Mutex::new(None),
+        }
+    }
+
+    /// The process-wide cache
+    pub fn shared() -> Arc<SampleCache> {
+        static SHARED: OnceLock<Arc<SampleCache>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| Arc::new(SampleCache::new(SAMPLE_CACHE_TTL)))
+            .clone()
+    }
+
+    /// The sample as last read, unless it is older than the TTL
+    fn get(&self, now: Instant) -> Option<Arc<HashSet<Address20>>> {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens
+            .as_ref()
+            .filter(|(at, _)| now.duration_since(*at) < self.ttl)
+            .map(|(_, tokens)| tokens.clone())
+    }
+
+    fn store(&self, tokens: Arc<HashSet<Address20>>, now: Instant) {
+        *self.tokens.lock().unwrap_or_else(|e| e.into_inner()) = Some((now, tokens));
+    }
+
+    /// Whether `token_address` is in the sample
+    pub async fn contains(
+        &self,
+        token_address: &Address20,
+        db_pool: &Pool<Postgres>,
+    ) -> Result<bool, sqlx::Error> {
+        if self.ttl.is_zero() {
+            return SampledToken::contains(token_address, db_pool).await;
+        }
+        if let Some(tokens) = self.get(Instant::now()) {
+            return Ok(tokens.contains(token_address));
+        }
+        let tokens: Arc<HashSet<Address20>> =
+            Arc::new(SampledToken::find_addresses(db_pool).await?.into_iter().collect());
+        self.store(tokens.clone(), Instant::now());
+        Ok(tokens.contains(token_address))
+    }
+
+    /// Forget the sample, after it was reselected
+    pub fn invalidate(&self) {
+        *self.tokens.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Reselect the sampled tokens, and queue imports for those entering
+pub async fn run(db_pool: &Pool<Postgres>, config: &SamplingConfig) {
+    let reselected = match SampledToken::reselect(&config.criteria, db_pool).await {
+        Ok(reselected) => reselected,
+        Err(e) => {
+            tracing::error!("Transfer sampling: failed to reselect tokens: {}", e);
+            return;
+        }
+    };
+    SampleCache::shared().invalidate();
+    tracing::info!(
+        "Transfer sampling: {} tokens sampled, {} new",
+        reselected.selected,
+        reselected.entered.len()
+    );
+
+    for address in &reselected.entered {
+        if let Err(e) = queue_import(address, db_pool).await {
+            tracing::error!("Transfer sampling: failed to queue an import of {}: {}", address, e);
+        }
+    }
+}
+
+/// Queue a `token_import` job replaying a token's history, from its creation
+/// block when it is known. Its Transfers weren't tracked while it was out of
+/// the sample, so its holders are rebuilt.
+async fn queue_import(address: &Address20, db_pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+    let job = NewJob {
+        kind: JobKind::TokenImport,
+        params: json!({ "address": address }),
+        // The key the API queues imports under
+        dedupe_key: Some(format!("token_import:{}", address)),
+    };
+    Job::create(&job, db_pool).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_sample_is_kept_until_it_expires_or_is_reselected() {
+        let cache = SampleCache::new(Duration::from_secs(30));
+        let now = Instant::now();
+        assert!(cache.get(now).is_none());
+
+        let tokens = Arc::new(HashSet::from([Address20::new([1; 20])]));
+        cache.store(tokens, now);
+        let cached = cache.get(now + Duration::from_secs(29)).unwrap();
+        assert!(cached.contains(&Address20::new([1; 20])));
+        assert!(cache.get(now + Duration::from_secs(30)).is_none());
+
+        cache.invalidate();
+        assert!(cache.get(now).is_none());
+    }
+}
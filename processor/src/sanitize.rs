@@ -0,0 +1,161 @@
+//! Token name/symbol sanitization
+//!
+//! `name()` and `symbol()` return whatever the deployer put there: control
+//! characters, right-to-left overrides that flip how the text renders,
+//! zero-width characters that make `PE\u{200B}PE` look like `PEPE`, or
+//! hundreds of characters of spam. Fetched metadata is cleaned before it is
+//! stored for display; the raw value is kept next to it.
+//!
+//! Emoji survive intact, including zero-width-joiner sequences like 👨‍👩‍👧.
+
+/// Longest stored name, in chars
+pub const MAX_NAME_CHARS: usize = 64;
+
+/// Longest stored symbol, in chars
+pub const MAX_SYMBOL_CHARS: usize = 20;
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// A cleaned name or symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sanitized {
+    /// `None` when nothing printable was left
+    pub text: Option<String>,
+    /// Invisible characters sat between visible ones
+    pub spoofed: bool,
+}
+
+/// Characters that render as nothing, or reorder the text around them
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'                  // soft hyphen
+        | '\u{034F}'                // combining grapheme joiner
+        | '\u{061C}'                // arabic letter mark
+        | '\u{115F}' | '\u{1160}'   // hangul fillers
+        | '\u{180E}'                // mongolian vowel separator
+        | '\u{200B}'..='\u{200F}'   // zero-width space/joiners, LRM/RLM
+        | '\u{202A}'..='\u{202E}'   // bidi embeddings and overrides
+        | '\u{2060}'..='\u{2064}'   // word joiner, invisible operators
+        | '\u{2066}'..='\u{2069}'   // bidi isolates
+        | '\u{3164}'                // hangul filler
+        | '\u{FEFF}'                // zero-width no-break space
+        | '\u{FFA0}'                // halfwidth hangul filler
+    )
+}
+
+/// A zero-width joiner between two emoji-like characters (not letters or
+/// digits) is part of an emoji sequence, not a spoof
+fn joins_emoji(prev: Option<char>, next: Option<char>) -> bool {
+    let emoji_like = |c: Option<char>| c.is_some_and(|c| !c.is_alphanumeric() && !c.is_ascii());
+    emoji_like(prev) && emoji_like(next)
+}
+
+/// Strip control and invisible characters, collapse whitespace and cap the
+/// length at `max_chars`
+pub fn sanitize(raw: &str, max_chars: usize) -> Sanitized {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut cleaned = String::with_capacity(raw.len());
+    let mut spoofed = false;
+    let mut last_visible: Option<char> = None;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_whitespace() {
+            // Tabs, newlines and exotic spaces all become one plain space
+            if !cleaned.is_empty() && !cleaned.ends_with(' ') {
+                cleaned.push(' ');
+            }
+            continue;
+        }
+        if c.is_control() {
+            continue;
+        }
+        if is_invisible(c) {
+            let next_visible = chars[i + 1..]
+                .iter()
+                .copied()
+                .find(|n| !is_invisible(*n) && !n.is_control());
+            if c == ZERO_WIDTH_JOINER && joins_emoji(last_visible, next_visible) {
+                cleaned.push(c);
+                continue;
+            }
+            // Hidden between two visible characters of the same word
+            let inside_word = last_visible.is_some() && !cleaned.ends_with(' ');
+            if inside_word && next_visible.is_some_and(|n| !n.is_whitespace()) {
+                spoofed = true;
+            }
+            continue;
+        }
+        cleaned.push(c);
+        last_visible = Some(c);
+    }
+
+    let mut text: String = cleaned.trim().chars().take(max_chars).collect();
+    // Don't leave a cut emoji sequence dangling
+    while text.ends_with(ZERO_WIDTH_JOINER) || text.ends_with(' ') {
+        text.pop();
+    }
+
+    Sanitized {
+        text: (!text.is_empty()).then_some(text),
+        spoofed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(raw: &str) -> Option<String> {
+        sanitize(raw, MAX_NAME_CHARS).text
+    }
+
+    #[test]
+    fn strips_control_and_collapses_whitespace() {
+        assert_eq!(
+            clean("  Pepe\n\tCoin\u{0007}  ").as_deref(),
+            Some("Pepe Coin")
+        );
+        assert_eq!(clean("\u{0000}\u{001B}"), None);
+        assert_eq!(clean(""), None);
+        assert_eq!(
+            clean("Moon\u{00A0}\u{3000}Dog").as_deref(),
+            Some("Moon Dog")
+        );
+    }
+
+    #[test]
+    fn zero_width_characters_inside_words_are_spoofing() {
+        let spoof = sanitize("PE\u{200B}PE", MAX_SYMBOL_CHARS);
+        assert_eq!(spoof.text.as_deref(), Some("PEPE"));
+        assert!(spoof.spoofed);
+
+        let flipped = sanitize("Safe\u{202E}gnp.exe", MAX_NAME_CHARS);
+        assert_eq!(flipped.text.as_deref(), Some("Safegnp.exe"));
+        assert!(flipped.spoofed);
+
+        // A stray BOM at the edge hides nothing
+        let bom = sanitize("\u{FEFF}DOGE", MAX_SYMBOL_CHARS);
+        assert_eq!(bom.text.as_deref(), Some("DOGE"));
+        assert!(!bom.spoofed);
+    }
+
+    #[test]
+    fn emoji_sequences_survive() {
+        let family = "Family \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} Coin";
+        let result = sanitize(family, MAX_NAME_CHARS);
+        assert_eq!(result.text.as_deref(), Some(family));
+        assert!(!result.spoofed);
+        assert_eq!(clean("🚀 Rocket 🌕").as_deref(), Some("🚀 Rocket 🌕"));
+    }
+
+    #[test]
+    fn length_is_capped_in_chars() {
+        let spam = "🐸".repeat(500);
+        let result = clean(&spam).unwrap();
+        assert_eq!(result.chars().count(), MAX_NAME_CHARS);
+
+        let symbol = sanitize("ABCDEFGHIJKLMNOPQRSTUVWXYZ", MAX_SYMBOL_CHARS);
+        assert_eq!(symbol.text.as_deref(), Some("ABCDEFGHIJKLMNOPQRST"));
+    }
+}
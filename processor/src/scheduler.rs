@@ -0,0 +1,586 @@
+//! Periodic background jobs
+//!
+//! Jobs run on their own tokio tasks next to the log processing loop, each on
+//! a fixed interval read from the environment. Wallet suggestions run nightly
+//! at a wall-clock hour instead.
+
+use chrono::{DateTime, Utc};
+use indexer_db::entity::{
+    pair::Pair,
+    swap::Swap,
+    token::Token,
+    token_list::TokenList,
+    wallet::Wallet,
+    wallet_activity::WalletActivity,
+    wallet_suggestion::{SuggestionCriteria, WalletSuggestion},
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+use std::{env, str::FromStr};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::{
+    alert_rollup, defaults, jobs,
+    handlers::{swap::PANCAKE_V2_FEE_BPS, transfer::PRICE_MAX_GAP_SECS},
+    metadata_repair, price_index, reconcile, rescan, restrictions, retention, sampling,
+    scoring::wash_trading,
+    token_gc, tokenlist, trending, wallet_age, webhooks,
+};
+
+/// Spawn all scheduled jobs
+pub fn spawn(db_pool: Pool<Postgres>) {
+    let list_secs = interval_secs(
+        "TOKEN_LIST_REFRESH_INTERVAL",
+        defaults::TOKEN_LIST_REFRESH_INTERVAL,
+        30,
+    );
+    let rollup_secs = interval_secs(
+        "TOKEN_ROLLUP_REFRESH_INTERVAL",
+        defaults::TOKEN_ROLLUP_REFRESH_INTERVAL,
+        60,
+    );
+    let wash_secs = interval_secs(
+        "WASH_TRADING_REFRESH_INTERVAL",
+        defaults::WASH_TRADING_REFRESH_INTERVAL,
+        300,
+    );
+    let webhook_secs = interval_secs(
+        "ALERT_WEBHOOK_INTERVAL",
+        defaults::ALERT_WEBHOOK_INTERVAL,
+        5,
+    );
+    let retention_secs = interval_secs(
+        "RETENTION_INTERVAL",
+        defaults::RETENTION_INTERVAL,
+        3600,
+    );
+    // A batch of nothing would never come back short
+    let retention_batch = env_count(
+        "RETENTION_BATCH_SIZE",
+        defaults::RETENTION_BATCH_SIZE,
+        5000,
+    )
+    .max(1) as i64;
+    let mut retention_policies = retention::policies_from_env();
+    let reconcile_secs = interval_secs(
+        "RECONCILE_INTERVAL",
+        defaults::RECONCILE_INTERVAL,
+        900,
+    );
+    let reconcile_config = reconcile::ReconcileConfig::from_env();
+    let verify_secs = interval_secs(
+        "HOLDER_VERIFY_INTERVAL",
+        defaults::HOLDER_VERIFY_INTERVAL,
+        10,
+    );
+    let backfill_secs = interval_secs(
+        "TRANSFER_USD_BACKFILL_INTERVAL",
+        defaults::TRANSFER_USD_BACKFILL_INTERVAL,
+        300,
+    );
+    let backfill_batch = env_count(
+        "TRANSFER_USD_BACKFILL_BATCH",
+        defaults::TRANSFER_USD_BACKFILL_BATCH,
+        1000,
+    ) as i64;
+    let valuation_secs = interval_secs(
+        "WALLET_VALUATION_INTERVAL",
+        defaults::WALLET_VALUATION_INTERVAL,
+        600,
+    );
+    let rescan_secs = interval_secs("TOKEN_RESCAN_INTERVAL", defaults::TOKEN_RESCAN_INTERVAL, 10);
+    let job_secs = interval_secs("JOB_WORKER_INTERVAL", defaults::JOB_WORKER_INTERVAL, 5);
+    let repair_secs = interval_secs(
+        "METADATA_REPAIR_INTERVAL",
+        defaults::METADATA_REPAIR_INTERVAL,
+        120,
+    );
+    let repair_config = metadata_repair::RepairConfig::from_env();
+    let wallet_age_secs = interval_secs(
+        "WALLET_AGE_INTERVAL",
+        defaults::WALLET_AGE_INTERVAL,
+        60,
+    );
+    let wallet_age_config = wallet_age::AgeConfig::from_env();
+    let alert_rollup_config = alert_rollup::RollupConfig::from_env();
+    let price_index_secs = interval_secs(
+        "BNB_PRICE_INDEX_INTERVAL",
+        defaults::BNB_PRICE_INDEX_INTERVAL,
+        60,
+    );
+    let price_index_config = price_index::IndexConfig::from_env();
+    let restriction_secs = interval_secs(
+        "RESTRICTION_WATCH_INTERVAL",
+        defaults::RESTRICTION_WATCH_INTERVAL,
+        15,
+    );
+    let restriction_config = restrictions::WatchConfig::from_env();
+    let suggestion_hour = env_count(
+        "WALLET_SUGGESTION_HOUR_UTC",
+        defaults::WALLET_SUGGESTION_HOUR_UTC,
+        2,
+    )
+    .min(23) as u32;
+    let gc_secs = interval_secs("TOKEN_GC_INTERVAL", defaults::TOKEN_GC_INTERVAL, 21600);
+    let gc_config = token_gc::GcConfig::from_env();
+    let sampling_secs = interval_secs(
+        "TRANSFER_SAMPLING_INTERVAL",
+        defaults::TRANSFER_SAMPLING_INTERVAL,
+        300,
+    );
+    let sampling_config = sampling::SamplingConfig::from_env();
+    let tokenlist_secs = interval_secs(
+        "TOKENLIST_PUBLISH_INTERVAL",
+        defaults::TOKENLIST_PUBLISH_INTERVAL,
+        3600,
+    );
+    let tokenlist_config = tokenlist::PublishConfig::from_env();
+    let suggestion_criteria = SuggestionCriteria {
+        pnl_days: env_count(
+            "WALLET_SUGGESTION_PNL_DAYS",
+            defaults::WALLET_SUGGESTION_PNL_DAYS,
+            7,
+        ) as i32,
+        top_pnl: env_count(
+            "WALLET_SUGGESTION_TOP_PNL",
+            defaults::WALLET_SUGGESTION_TOP_PNL,
+            50,
+        ) as i64,
+        early_blocks: env_count(
+            "WALLET_SUGGESTION_EARLY_BLOCKS",
+            defaults::WALLET_SUGGESTION_EARLY_BLOCKS,
+            100,
+        ) as i64,
+        min_early_wins: env_count(
+            "WALLET_SUGGESTION_MIN_EARLY_WINS",
+            defaults::WALLET_SUGGESTION_MIN_EARLY_WINS,
+            3,
+        ) as i64,
+        min_score: env_count(
+            "WALLET_SUGGESTION_MIN_SCORE",
+            defaults::WALLET_SUGGESTION_MIN_SCORE,
+            80,
+        )
+        .min(100) as i16,
+    };
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(list_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            refresh_token_lists(&pool).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(rollup_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            refresh_trade_rollups(&pool).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(wash_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            refresh_wash_trading_scores(&pool).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(retention_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            retention::run(&pool, &mut retention_policies, retention_batch).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    let config = reconcile_config.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(reconcile_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            reconcile::run(&pool, &config).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(verify_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            reconcile::verify_next(&pool, &reconcile_config).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(backfill_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut after_id = 0;
+
+        loop {
+            ticker.tick().await;
+            after_id = backfill_transfer_usd(&pool, after_id, backfill_batch).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(valuation_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            value_wallets(&pool).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(rescan_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            rescan::run(&pool).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(job_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            jobs::run(&pool).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(repair_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            metadata_repair::run(&pool, &repair_config).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(wallet_age_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            wallet_age::run(&pool, &wallet_age_config).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(price_index_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            price_index::run(&pool, &price_index_config).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(restriction_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            restrictions::run(&pool, &restriction_config).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(until_hour_utc(Utc::now(), suggestion_hour)).await;
+            suggest_wallets(&pool, &suggestion_criteria).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(gc_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            token_gc::run(&pool, &gc_config).await;
+        }
+    });
+
+    let pool = db_pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(tokenlist_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            tokenlist::run(&pool, &tokenlist_config).await;
+        }
+    });
+
+    if sampling_config.enabled {
+        let pool = db_pool.clone();
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(sampling_secs));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                sampling::run(&pool, &sampling_config).await;
+            }
+        });
+        tracing::info!(
+            "Transfer sampling on: sampled tokens reselected every {} seconds",
+            sampling_secs
+        );
+    }
+
+    tokio::spawn(async move {
+        let client = webhooks::client();
+        let mut ticker = interval(Duration::from_secs(webhook_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            alert_rollup::run(&db_pool, &alert_rollup_config).await;
+            webhooks::dispatch(&db_pool, &client, &alert_rollup_config).await;
+        }
+    });
+
+    tracing::info!(
+        "Scheduler started: token lists refresh every {} seconds, trade rollups every {} seconds, wash trading scores every {} seconds, alert rollups and webhooks every {} seconds, retention every {} seconds, holder reconciliation every {} seconds, holder verification requests every {} seconds, transfer USD backfill every {} seconds, wallet valuations every {} seconds, token rescan requests every {} seconds, jobs every {} seconds, metadata repair every {} seconds, BNB price index every {} seconds, contract restriction watch every {} seconds, wallet suggestions nightly at {:02}:00 UTC, dead token collection every {} seconds, token list publishing every {} seconds",
+        list_secs,
+        rollup_secs,
+        wash_secs,
+        webhook_secs,
+        retention_secs,
+        reconcile_secs,
+        verify_secs,
+        backfill_secs,
+        valuation_secs,
+        rescan_secs,
+        job_secs,
+        repair_secs,
+        price_index_secs,
+        restriction_secs,
+        suggestion_hour,
+        gc_secs,
+        tokenlist_secs
+    );
+}
+
+/// Read a job interval in seconds from the environment, at least 1: tokio's
+/// `interval` panics on a zero period
+fn interval_secs(var: &str, default: &str, fallback: u64) -> u64 {
+    env::var(var)
+        .unwrap_or_else(|_| default.to_string())
+        .parse::<u64>()
+        .unwrap_or(fallback)
+        .max(1)
+}
+
+/// Read a count (batch size, threshold, ...) from the environment, where 0 is
+/// a valid value
+fn env_count(var: &str, default: &str, fallback: u64) -> u64 {
+    env::var(var)
+        .unwrap_or_else(|_| default.to_string())
+        .parse::<u64>()
+        .unwrap_or(fallback)
+}
+
+/// Time from `now` until the next `hour`:00 UTC, a full day when it is that
+/// time already
+fn until_hour_utc(now: DateTime<Utc>, hour: u32) -> Duration {
+    let today = now
+        .date_naive()
+        .and_hms_opt(hour, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    let next = if today > now {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    };
+
+    (next - now).to_std().unwrap_or_default()
+}
+
+/// Refresh the hot/new/trending materialized views, then alert on trending changes
+async fn refresh_token_lists(db_pool: &Pool<Postgres>) {
+    for list in TokenList::ALL {
+        if let Err(e) = list.refresh(db_pool).await {
+            tracing::error!("Failed to refresh {}: {}", list.view_name(), e);
+        }
+    }
+
+    if let Err(e) = trending::notify_transitions(db_pool).await {
+        tracing::error!("Failed to diff trending ranks: {}", e);
+    }
+}
+
+/// Slide the 1h/24h trade windows for tokens that stopped trading, and the
+/// 24h volume/fee stats of every pair
+async fn refresh_trade_rollups(db_pool: &Pool<Postgres>) {
+    if let Err(e) = Token::refresh_all_trade_rollups(db_pool).await {
+        tracing::error!("Failed to refresh token trade rollups: {}", e);
+    }
+
+    if let Err(e) = Pair::refresh_volume_stats(PANCAKE_V2_FEE_BPS as i32, db_pool).await {
+        tracing::error!("Failed to refresh pair volume stats: {}", e);
+    }
+}
+
+/// Price one batch of transfers recorded without a USD value, returning the
+/// id to resume from. Starts over once the end is reached: transfers whose
+/// snapshots hadn't been taken yet get another chance.
+async fn backfill_transfer_usd(db_pool: &Pool<Postgres>, after_id: i32, batch: i64) -> i32 {
+    match WalletActivity::backfill_transfer_usd(after_id, batch, PRICE_MAX_GAP_SECS, db_pool).await
+    {
+        Ok((priced, Some(last_id))) => {
+            if priced > 0 {
+                tracing::info!("Transfer USD backfill: priced {} transfers", priced);
+            }
+            last_id
+        }
+        Ok((_, None)) => 0,
+        Err(e) => {
+            tracing::error!("Failed to backfill transfer USD values: {}", e);
+            after_id
+        }
+    }
+}
+
+/// Value every tracked wallet's holdings at current prices
+async fn value_wallets(db_pool: &Pool<Postgres>) {
+    const PAGE: i64 = 500;
+    let mut after_id = 0;
+    let mut valued = 0;
+
+    loop {
+        let page = match Wallet::valuations(after_id, PAGE, db_pool).await {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::error!("Failed to value wallets: {}", e);
+                return;
+            }
+        };
+        let Some(last) = page.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        for v in &page {
+            if let Err(e) = Wallet::update_stats(
+                &v.address,
+                v.token_count,
+                &v.estimated_value_usd,
+                v.last_activity,
+                db_pool,
+            )
+            .await
+            {
+                tracing::error!("Failed to update stats for wallet {}: {}", v.address, e);
+            } else {
+                valued += 1;
+            }
+        }
+    }
+
+    if valued > 0 {
+        tracing::info!("Valued {} wallets", valued);
+    }
+}
+
+/// Rebuild the tracked wallet suggestions from the stored swaps
+async fn suggest_wallets(db_pool: &Pool<Postgres>, criteria: &SuggestionCriteria) {
+    let rebuilt: Result<u64, sqlx::Error> = async {
+        let mut tx = db_pool.begin().await?;
+        WalletSuggestion::clear(&mut *tx).await?;
+        let suggested = WalletSuggestion::analyze(criteria, &mut *tx).await?;
+        tx.commit().await?;
+        Ok(suggested)
+    }
+    .await;
+
+    match rebuilt {
+        Ok(suggested) => tracing::info!("Wallet suggestions: {} wallets suggested", suggested),
+        Err(e) => tracing::error!("Failed to rebuild wallet suggestions: {}", e),
+    }
+}
+
+/// Re-score wash trading for every token traded over the last 24h
+async fn refresh_wash_trading_scores(db_pool: &Pool<Postgres>) {
+    let since = Utc::now() - chrono::Duration::hours(24);
+    let tokens = match Swap::traded_tokens_since(since, db_pool).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            tracing::error!("Failed to list traded tokens: {}", e);
+            return;
+        }
+    };
+
+    for token_address in tokens {
+        let stats = match Swap::wash_trading_stats(&token_address, since, db_pool).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                tracing::error!("Failed to load wash trading stats for {}: {}", token_address, e);
+                continue;
+            }
+        };
+
+        let score = wash_trading::score(&stats);
+        let score = BigDecimal::from_str(&format!("{:.2}", score)).unwrap_or_default();
+        if let Err(e) = Token::update_wash_trading_score(&token_address, &score, db_pool).await {
+            tracing::error!("Failed to update wash trading score for {}: {}", token_address, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn nightly_runs_wait_for_the_next_occurrence_of_the_hour() {
+        let before = Utc.with_ymd_and_hms(2026, 3, 1, 1, 30, 0).unwrap();
+        assert_eq!(until_hour_utc(before, 2), Duration::from_secs(30 * 60));
+
+        let after = Utc.with_ymd_and_hms(2026, 3, 1, 2, 0, 1).unwrap();
+        assert_eq!(until_hour_utc(after, 2), Duration::from_secs(86_399));
+
+        let on_time = Utc.with_ymd_and_hms(2026, 3, 1, 2, 0, 0).unwrap();
+        assert_eq!(until_hour_utc(on_time, 2), Duration::from_secs(86_400));
+    }
+}
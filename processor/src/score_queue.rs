@@ -0,0 +1,155 @@
+//! Debounced BeeScore recomputation
+//!
+//! Swaps and transfers mark their token dirty instead of re-scoring it inline;
+//! a dedicated task drains the queue at a bounded rate (`SCORE_QUEUE_BATCH`
+//! tokens every `SCORE_QUEUE_INTERVAL_MS`). A hot token trading hundreds of
+//! times between drains is scored once, and scoring never holds up log
+//! processing.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    env,
+    sync::{Arc, Mutex},
+};
+
+use indexer_db::Address20;
+use sqlx::{Pool, Postgres};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::{defaults, handlers::HandlerContext, service};
+
+#[derive(Debug, Default)]
+struct Dirty {
+    /// Tokens in the order they were first marked
+    order: VecDeque<Address20>,
+    /// Tokens in `order`, so a repeat mark is a no-op
+    queued: HashSet<Address20>,
+}
+
+/// Tokens waiting for their score to be recomputed
+#[derive(Debug, Clone, Default)]
+pub struct ScoreQueue {
+    dirty: Arc<Mutex<Dirty>>,
+}
+
+impl ScoreQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark a token's score stale; a token already waiting keeps its place
+    pub fn mark(&self, token: Address20) {
+        let mut dirty = self.dirty.lock().unwrap_or_else(|e| e.into_inner());
+        if dirty.queued.insert(token) {
+            dirty.order.push_back(token);
+        }
+    }
+
+    /// Take up to `limit` tokens, oldest first. A token marked again while
+    /// it is being scored is queued anew.
+    pub fn take(&self, limit: usize) -> Vec<Address20> {
+        let mut dirty = self.dirty.lock().unwrap_or_else(|e| e.into_inner());
+        let count = limit.min(dirty.order.len());
+        let batch: Vec<Address20> = dirty.order.drain(..count).collect();
+        for token in &batch {
+            dirty.queued.remove(token);
+        }
+        batch
+    }
+
+    /// Tokens waiting to be scored
+    pub fn len(&self) -> usize {
+        self.dirty
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .order
+            .len()
+    }
+
+    /// Spawn the task that drains the queue
+    pub fn spawn(&self, db_pool: Pool<Postgres>) {
+        let interval_ms = env::var("SCORE_QUEUE_INTERVAL_MS")
+            .unwrap_or_else(|_| defaults::SCORE_QUEUE_INTERVAL_MS.to_string())
+            .parse::<u64>()
+            .unwrap_or(1000)
+            .max(1);
+        let batch = env::var("SCORE_QUEUE_BATCH")
+            .unwrap_or_else(|_| defaults::SCORE_QUEUE_BATCH.to_string())
+            .parse::<usize>()
+            .unwrap_or(20)
+            .max(1);
+
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(interval_ms));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // Built on the first tick that can, then shared by every drain
+            let mut ctx = None;
+
+            loop {
+                ticker.tick().await;
+                if ctx.is_none() {
+                    match service::create_handler_context(db_pool.clone()).await {
+                        Ok(loaded) => ctx = Some(loaded),
+                        Err(e) => {
+                            // The queue keeps its tokens until the next tick
+                            tracing::error!("Score queue: failed to load handler context: {}", e);
+                            continue;
+                        }
+                    }
+                }
+                if let Some(ctx) = &ctx {
+                    queue.drain(batch, ctx).await;
+                }
+            }
+        });
+
+        tracing::info!(
+            "Score queue started: up to {} tokens every {}ms",
+            batch, interval_ms
+        );
+    }
+
+    /// Re-score one batch of dirty tokens
+    async fn drain(&self, limit: usize, ctx: &HandlerContext) {
+        let tokens = self.take(limit);
+        if tokens.is_empty() {
+            return;
+        }
+
+        for token in tokens {
+            if let Err(e) = service::update_token_score(&token, ctx).await {
+                tracing::error!("Failed to update score for {}: {}", token, e);
+            }
+        }
+
+        let waiting = self.len();
+        if waiting > limit * 10 {
+            tracing::info!("Score queue backlog: {} tokens", waiting);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(n: u8) -> Address20 {
+        Address20::new([n; 20])
+    }
+
+    #[test]
+    fn repeat_marks_are_debounced() {
+        let queue = ScoreQueue::new();
+        for n in [1, 2, 1, 1, 3, 2] {
+            queue.mark(token(n));
+        }
+        assert_eq!(queue.len(), 3);
+
+        assert_eq!(queue.take(2), vec![token(1), token(2)]);
+        // Marked again after being taken: scored again later
+        queue.mark(token(1));
+        assert_eq!(queue.take(10), vec![token(3), token(1)]);
+        assert!(queue.take(10).is_empty());
+    }
+}
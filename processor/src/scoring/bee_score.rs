@@ -7,7 +7,7 @@
 //! - Safety Score (0-60): How safe is this token?
 //! - Traction Score (0-40): How much momentum does it have?
 
-use indexer_db::entity::token::TokenMetrics;
+use indexer_db::entity::{score_history::ScoreComponent, token::TokenMetrics};
 
 /// Result of BeeScore calculation
 #[derive(Debug, Clone)]
@@ -24,6 +24,29 @@ pub struct BeeScoreResult {
     pub traction_breakdown: Vec<ScoreBreakdown>,
 }
 
+impl BeeScoreResult {
+    /// Both breakdowns as stored with the score history, safety first
+    pub fn components(&self) -> Vec<ScoreComponent> {
+        let component = |category: &str, b: &ScoreBreakdown| ScoreComponent {
+            category: category.to_string(),
+            name: b.name.clone(),
+            score: b.score as i16,
+            max_score: b.max_score as i16,
+            reason: b.reason.clone(),
+        };
+
+        self.safety_breakdown
+            .iter()
+            .map(|b| component("safety", b))
+            .chain(
+                self.traction_breakdown
+                    .iter()
+                    .map(|b| component("traction", b)),
+            )
+            .collect()
+    }
+}
+
 /// Individual score component breakdown
 #[derive(Debug, Clone)]
 pub struct ScoreBreakdown {
@@ -33,6 +56,13 @@ pub struct ScoreBreakdown {
     pub reason: String,
 }
 
+/// Social reach reported by external collectors
+#[derive(Debug, Clone, Default)]
+pub struct SocialSignals {
+    pub telegram_members: i32,
+    pub x_mentions_24h: i32,
+}
+
 /// BeeScore calculator
 pub struct BeeScoreCalculator;
 
@@ -169,8 +199,8 @@ impl BeeScoreCalculator {
             0.0
         };
         let (vol_score, vol_reason) = match vol_ratio {
-            r if r >= 0.5 && r <= 2.0 => (12, "Healthy volume (50-200% of liquidity)"),
-            r if r >= 0.2 && r <= 3.0 => (8, "Good volume (20-300% of liquidity)"),
+            r if (0.5..=2.0).contains(&r) => (12, "Healthy volume (50-200% of liquidity)"),
+            r if (0.2..=3.0).contains(&r) => (8, "Good volume (20-300% of liquidity)"),
             r if r >= 0.1 => (4, "Low volume (>10% of liquidity)"),
             _ => (0, "Very low volume"),
         };
@@ -224,8 +254,8 @@ impl BeeScoreCalculator {
         // Price Action (0-6 points)
         // Healthy = moderate gains (5-100%), not extreme pumps or dumps
         let (price_score, price_reason) = match metrics.price_change_1h {
-            p if p >= 5.0 && p <= 100.0 => (6, "Healthy gain (5-100%)"),
-            p if p >= 0.0 && p <= 200.0 => (4, "Acceptable price action (0-200%)"),
+            p if (5.0..=100.0).contains(&p) => (6, "Healthy gain (5-100%)"),
+            p if (0.0..=200.0).contains(&p) => (4, "Acceptable price action (0-200%)"),
             p if p >= -20.0 => (2, "Small dip (<20% loss)"),
             p if p < -50.0 => (0, "Major dump (>50% loss)"),
             _ => (1, "Volatile price action"),
@@ -246,8 +276,8 @@ impl BeeScoreCalculator {
             0.5
         };
         let (balance_score, balance_reason) = match buy_ratio {
-            r if r >= 0.4 && r <= 0.7 => (6, "Balanced with buy pressure (40-70% buys)"),
-            r if r >= 0.3 && r <= 0.8 => (4, "Acceptable balance (30-80% buys)"),
+            r if (0.4..=0.7).contains(&r) => (6, "Balanced with buy pressure (40-70% buys)"),
+            r if (0.3..=0.8).contains(&r) => (4, "Acceptable balance (30-80% buys)"),
             r if r >= 0.2 => (2, "Sell pressure (only 20-30% buys)"),
             _ => (0, "Heavy selling (<20% buys)"),
         };
@@ -262,6 +292,148 @@ impl BeeScoreCalculator {
         (score, breakdown)
     }
 
+    /// Blend social reach into the traction score
+    ///
+    /// `weight` (0-1) is the share of the traction score taken from social
+    /// signals; the on-chain traction keeps the rest. Social reach is scored
+    /// on the same 0-40 scale:
+    /// - Telegram Members (0-20)
+    /// - X Mentions (0-20): mentions over the last 24h
+    pub fn apply_social(result: &mut BeeScoreResult, social: &SocialSignals, weight: f64) {
+        let weight = weight.clamp(0.0, 1.0);
+        if weight == 0.0 {
+            return;
+        }
+
+        let tg_score: u8 = match social.telegram_members {
+            m if m >= 10_000 => 20,
+            m if m >= 2_000 => 15,
+            m if m >= 500 => 10,
+            m if m >= 100 => 5,
+            _ => 0,
+        };
+        let x_score: u8 = match social.x_mentions_24h {
+            m if m >= 500 => 20,
+            m if m >= 100 => 15,
+            m if m >= 25 => 10,
+            m if m >= 5 => 5,
+            _ => 0,
+        };
+        let social_score = tg_score + x_score;
+
+        let blended = (result.traction_score as f64 * (1.0 - weight) + social_score as f64 * weight)
+            .round()
+            .min(40.0) as u8;
+
+        result.traction_breakdown.push(ScoreBreakdown {
+            name: "Social".to_string(),
+            score: social_score,
+            max_score: 40,
+            reason: format!(
+                "{} Telegram members, {} X mentions/24h ({:.0}% of traction)",
+                social.telegram_members,
+                social.x_mentions_24h,
+                weight * 100.0
+            ),
+        });
+        result.traction_score = blended;
+        result.total = result.safety_score + blended;
+    }
+
+    /// Deduct traction points for gamed volume
+    ///
+    /// `wash_trading_score` (0-100) scales down the volume-driven components
+    /// (Volume, Trades, Buy/Sell) by the same share. Apply before
+    /// `apply_social` so the social blend starts from the discounted score.
+    pub fn apply_wash_trading(result: &mut BeeScoreResult, wash_trading_score: f64) {
+        let share = (wash_trading_score / 100.0).clamp(0.0, 1.0);
+        if share == 0.0 {
+            return;
+        }
+
+        for item in result
+            .traction_breakdown
+            .iter_mut()
+            .filter(|b| matches!(b.name.as_str(), "Volume" | "Trades" | "Buy/Sell"))
+        {
+            let kept = (item.score as f64 * (1.0 - share)).round() as u8;
+            if kept < item.score {
+                item.reason = format!(
+                    "{} (-{} for wash trading, {:.0}/100)",
+                    item.reason,
+                    item.score - kept,
+                    wash_trading_score
+                );
+                item.score = kept;
+            }
+        }
+
+        result.traction_score = result.traction_breakdown.iter().map(|b| b.score).sum();
+        result.total = result.safety_score + result.traction_score;
+    }
+
+    /// Deduct safety points from a token impersonating an established one
+    ///
+    /// `target_symbol` names the copied token in the breakdown.
+    pub fn apply_impersonation(result: &mut BeeScoreResult, target_symbol: &str, penalty: u8) {
+        let deducted = penalty.min(result.safety_score);
+        result.safety_breakdown.push(ScoreBreakdown {
+            name: "Impersonation".to_string(),
+            score: 0,
+            max_score: 0,
+            reason: format!("Name or symbol copies {} (-{})", target_symbol, deducted),
+        });
+        result.safety_score -= deducted;
+        result.total = result.safety_score + result.traction_score;
+    }
+
+    /// Deduct safety points from a token whose recent buyers were mostly
+    /// brand-new wallets
+    ///
+    /// `fresh_percent` is the share of those buyers, `buyers` how many there were.
+    pub fn apply_fresh_wallets(
+        result: &mut BeeScoreResult,
+        fresh_percent: f64,
+        buyers: i64,
+        penalty: u8,
+    ) {
+        let deducted = penalty.min(result.safety_score);
+        result.safety_breakdown.push(ScoreBreakdown {
+            name: "Fresh Wallets".to_string(),
+            score: 0,
+            max_score: 0,
+            reason: format!(
+                "{:.0}% of {} recent buyers are brand-new wallets (-{})",
+                fresh_percent, buyers, deducted
+            ),
+        });
+        result.safety_score -= deducted;
+        result.total = result.safety_score + result.traction_score;
+    }
+
+    /// Deduct safety points from a token external sources confirmed as risky
+    ///
+    /// `severity` is the worst confirmed report's, `reports` how many there were.
+    pub fn apply_external_reports(
+        result: &mut BeeScoreResult,
+        severity: &str,
+        reports: usize,
+        penalty: u8,
+    ) {
+        let deducted = penalty.min(result.safety_score);
+        result.safety_breakdown.push(ScoreBreakdown {
+            name: "External Reports".to_string(),
+            score: 0,
+            max_score: 0,
+            reason: format!(
+                "{} confirmed report(s), worst {} (-{})",
+                reports, severity, deducted
+            ),
+        });
+        result.safety_score -= deducted;
+        result.total = result.safety_score + result.traction_score;
+    }
+
     /// Get a human-readable rating based on score
     pub fn get_rating(score: u8) -> &'static str {
         match score {
@@ -312,6 +484,12 @@ mod tests {
         assert_eq!(result.safety_score, 60); // Max safety
         assert_eq!(result.traction_score, 40); // Max traction
         assert_eq!(result.total, 100);
+
+        let components = result.components();
+        assert_eq!(components.len(), 10);
+        assert_eq!(components[0].category, "safety");
+        assert_eq!(components[9].category, "traction");
+        assert_eq!(components.iter().map(|c| c.score).sum::<i16>(), 100);
     }
 
     #[test]
@@ -347,4 +525,159 @@ mod tests {
         assert_eq!(BeeScoreCalculator::get_rating(25), "Poor");
         assert_eq!(BeeScoreCalculator::get_rating(10), "Risky");
     }
+
+    #[test]
+    fn test_social_blend_is_weighted_and_optional() {
+        let metrics = TokenMetrics {
+            liquidity_usd: 150_000.0,
+            lp_locked: true,
+            lp_lock_percent: 95.0,
+            top_10_holder_percent: 30.0,
+            dev_holdings_percent: 3.0,
+            ownership_renounced: true,
+            volume_1h_usd: 0.0,
+            trades_1h: 0,
+            holder_count: 100,
+            holder_count_1h_ago: 100,
+            price_change_1h: 0.0,
+            buys_1h: 0,
+            sells_1h: 0,
+        };
+        let base = BeeScoreCalculator::calculate(&metrics);
+        let social = SocialSignals {
+            telegram_members: 12_000,
+            x_mentions_24h: 150,
+        };
+
+        let mut unweighted = base.clone();
+        BeeScoreCalculator::apply_social(&mut unweighted, &social, 0.0);
+        assert_eq!(unweighted.traction_score, base.traction_score);
+        assert_eq!(unweighted.traction_breakdown.len(), base.traction_breakdown.len());
+
+        // Social scores 35/40, on-chain traction is 10/40 (price + buy/sell only)
+        assert_eq!(base.traction_score, 10);
+        let mut blended = base.clone();
+        BeeScoreCalculator::apply_social(&mut blended, &social, 0.2);
+        assert_eq!(blended.traction_score, 15);
+        assert_eq!(blended.total, blended.safety_score + 15);
+        assert_eq!(blended.traction_breakdown.last().unwrap().name, "Social");
+    }
+
+    #[test]
+    fn test_impersonation_deducts_safety() {
+        let metrics = TokenMetrics {
+            liquidity_usd: 150_000.0,
+            lp_locked: true,
+            lp_lock_percent: 95.0,
+            top_10_holder_percent: 30.0,
+            dev_holdings_percent: 3.0,
+            ownership_renounced: true,
+            volume_1h_usd: 0.0,
+            trades_1h: 0,
+            holder_count: 100,
+            holder_count_1h_ago: 100,
+            price_change_1h: 0.0,
+            buys_1h: 0,
+            sells_1h: 0,
+        };
+        let mut result = BeeScoreCalculator::calculate(&metrics);
+        assert_eq!(result.safety_score, 60);
+
+        BeeScoreCalculator::apply_impersonation(&mut result, "PEPE", 20);
+        assert_eq!(result.safety_score, 40);
+        assert_eq!(result.total, 40 + result.traction_score);
+        let item = result.safety_breakdown.last().unwrap();
+        assert_eq!(item.name, "Impersonation");
+        assert!(item.reason.contains("PEPE"));
+
+        // Never below zero
+        BeeScoreCalculator::apply_impersonation(&mut result, "PEPE", 50);
+        assert_eq!(result.safety_score, 0);
+    }
+
+    #[test]
+    fn test_fresh_wallets_deduct_safety() {
+        let metrics = TokenMetrics {
+            liquidity_usd: 150_000.0,
+            lp_locked: true,
+            lp_lock_percent: 95.0,
+            top_10_holder_percent: 30.0,
+            dev_holdings_percent: 3.0,
+            ownership_renounced: true,
+            volume_1h_usd: 0.0,
+            trades_1h: 0,
+            holder_count: 100,
+            holder_count_1h_ago: 100,
+            price_change_1h: 0.0,
+            buys_1h: 0,
+            sells_1h: 0,
+        };
+        let mut result = BeeScoreCalculator::calculate(&metrics);
+
+        BeeScoreCalculator::apply_fresh_wallets(&mut result, 80.0, 25, 10);
+        assert_eq!(result.safety_score, 50);
+        assert_eq!(result.total, 50 + result.traction_score);
+        let item = result.safety_breakdown.last().unwrap();
+        assert_eq!(item.name, "Fresh Wallets");
+        assert!(item.reason.contains("80% of 25"));
+    }
+
+    #[test]
+    fn test_external_reports_deduct_safety() {
+        let metrics = TokenMetrics {
+            liquidity_usd: 150_000.0,
+            lp_locked: true,
+            lp_lock_percent: 95.0,
+            top_10_holder_percent: 30.0,
+            dev_holdings_percent: 3.0,
+            ownership_renounced: true,
+            ..Default::default()
+        };
+        let mut result = BeeScoreCalculator::calculate(&metrics);
+
+        BeeScoreCalculator::apply_external_reports(&mut result, "high", 2, 30);
+        assert_eq!(result.safety_score, 30);
+        assert_eq!(result.total, 30 + result.traction_score);
+        let item = result.safety_breakdown.last().unwrap();
+        assert_eq!(item.name, "External Reports");
+        assert!(item.reason.contains("worst high (-30)"));
+    }
+
+    #[test]
+    fn test_wash_trading_discounts_volume_components() {
+        let metrics = TokenMetrics {
+            liquidity_usd: 100_000.0,
+            lp_locked: true,
+            lp_lock_percent: 95.0,
+            top_10_holder_percent: 30.0,
+            dev_holdings_percent: 3.0,
+            ownership_renounced: true,
+            volume_1h_usd: 100_000.0,
+            trades_1h: 120,
+            holder_count: 100,
+            holder_count_1h_ago: 100,
+            price_change_1h: 10.0,
+            buys_1h: 60,
+            sells_1h: 60,
+        };
+        let base = BeeScoreCalculator::calculate(&metrics);
+        assert_eq!(base.traction_score, 32); // 12 + 8 + 0 + 6 + 6
+
+        let mut clean = base.clone();
+        BeeScoreCalculator::apply_wash_trading(&mut clean, 0.0);
+        assert_eq!(clean.traction_score, base.traction_score);
+
+        // Half the volume is gamed: 6 + 4 + 0 + 6 + 3
+        let mut gamed = base.clone();
+        BeeScoreCalculator::apply_wash_trading(&mut gamed, 50.0);
+        assert_eq!(gamed.traction_score, 19);
+        assert_eq!(gamed.total, gamed.safety_score + 19);
+        let price = gamed
+            .traction_breakdown
+            .iter()
+            .find(|b| b.name == "Price Action")
+            .unwrap();
+        assert_eq!(price.score, 6);
+        assert!(gamed.traction_breakdown[0].reason.contains("wash trading"));
+    }
 }
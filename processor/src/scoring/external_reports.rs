@@ -0,0 +1,67 @@
+//! Confirmed external risk reports
+//!
+//! Integrations push risk reports through the ingest API. Only reports their
+//! source confirmed count against a token: the worst confirmed severity
+//! deducts its [`safety_penalty`] from the safety score.
+
+use indexer_db::entity::external_report::{ExternalReport, ReportSeverity};
+
+/// Safety points deducted for a confirmed report of `severity`
+pub fn safety_penalty(severity: ReportSeverity) -> u8 {
+    match severity {
+        ReportSeverity::Info => 0,
+        ReportSeverity::Low => 5,
+        ReportSeverity::Medium => 15,
+        ReportSeverity::High => 30,
+        ReportSeverity::Critical => 60,
+    }
+}
+
+/// Worst severity among the confirmed reports and how many there are, if it
+/// costs safety points
+pub fn worst_confirmed(reports: &[ExternalReport]) -> Option<(ReportSeverity, usize)> {
+    let confirmed: Vec<_> = reports
+        .iter()
+        .filter(|r| r.confirmed)
+        .filter_map(|r| r.severity())
+        .collect();
+    let worst = confirmed.iter().copied().max()?;
+    (safety_penalty(worst) > 0).then_some((worst, confirmed.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use indexer_db::Address20;
+
+    use super::*;
+
+    fn report(severity: &str, confirmed: bool) -> ExternalReport {
+        ExternalReport {
+            id: 1,
+            token_address: Address20::new([1; 20]),
+            source: "goplus".to_string(),
+            severity: severity.to_string(),
+            category: None,
+            description: None,
+            url: None,
+            confirmed,
+            reported_at: Utc::now(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn only_confirmed_reports_count() {
+        let reports = [
+            report("critical", false),
+            report("high", true),
+            report("low", true),
+        ];
+        assert_eq!(worst_confirmed(&reports), Some((ReportSeverity::High, 2)));
+
+        assert_eq!(worst_confirmed(&[report("critical", false)]), None);
+        assert_eq!(worst_confirmed(&[report("info", true)]), None);
+    }
+}
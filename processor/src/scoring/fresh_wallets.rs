@@ -0,0 +1,65 @@
+//! Sniper farm detection
+//!
+//! One buyer spreading a launch buy over many brand-new wallets shows up as a
+//! token whose recent buyers were mostly first seen just before they bought,
+//! with hardly any transactions sent (their nonce, read by
+//! [`crate::wallet_age`]). Buyers whose nonce isn't known yet don't count.
+//! Past `FARM_SHARE_PERCENT` of at least `MIN_BUYERS` buyers, the token loses
+//! `SAFETY_PENALTY` safety points.
+
+use indexer_db::entity::wallet_profile::FreshBuyers;
+
+/// Hours of buys looked at
+pub const WINDOW_HOURS: i64 = 24;
+
+/// How long before its first buy a wallet may have been first seen and still
+/// count as brand new
+pub const MAX_AGE_HOURS: i32 = 24;
+
+/// Most transactions a brand-new wallet has sent: funding it, approving and
+/// buying take a few, a wallet in use has many more
+pub const MAX_NONCE: i64 = 5;
+
+/// Below this many buyers a cluster of new wallets proves nothing
+const MIN_BUYERS: i64 = 10;
+
+/// Share (%) of buyers that must be brand new
+const FARM_SHARE_PERCENT: f64 = 50.0;
+
+/// Safety points deducted from a token bought up by a sniper farm
+pub const SAFETY_PENALTY: u8 = 10;
+
+/// Share (%) of brand-new buyers, when it marks a sniper farm
+pub fn farm_share(buyers: &FreshBuyers) -> Option<f64> {
+    if buyers.buyers < MIN_BUYERS {
+        return None;
+    }
+    let share = buyers.fresh as f64 * 100.0 / buyers.buyers as f64;
+    (share >= FARM_SHARE_PERCENT).then_some(share)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn farms_need_enough_buyers_and_mostly_new_wallets() {
+        let farm = FreshBuyers {
+            buyers: 20,
+            fresh: 15,
+        };
+        assert_eq!(farm_share(&farm), Some(75.0));
+
+        let organic = FreshBuyers {
+            buyers: 20,
+            fresh: 4,
+        };
+        assert_eq!(farm_share(&organic), None);
+
+        let quiet = FreshBuyers {
+            buyers: 5,
+            fresh: 5,
+        };
+        assert_eq!(farm_share(&quiet), None);
+    }
+}
@@ -5,5 +5,8 @@
 //! - Traction Score (0-40): Volume, trades, holder growth, price action, buy/sell balance
 
 pub mod bee_score;
+pub mod external_reports;
+pub mod fresh_wallets;
+pub mod wash_trading;
 
-pub use bee_score::{BeeScoreCalculator, BeeScoreResult, ScoreBreakdown};
+pub use bee_score::{BeeScoreCalculator, BeeScoreResult, ScoreBreakdown, SocialSignals};
@@ -0,0 +1,81 @@
+//! Wash trading detection
+//!
+//! Scores (0-100) how much of a token's recent volume looks gamed, from three
+//! patterns:
+//! - Round trips (0-40): wallets whose buys are matched by their own sells
+//! - Circular transfers (0-30): wallets passing the token back and forth
+//! - Concentration (0-30): volume dominated by fewer than three wallets
+//!
+//! The score discounts the volume-driven traction components of the BeeScore.
+
+use indexer_db::entity::swap::WashTradingStats;
+
+/// Below this many trades there isn't enough activity to call it gamed
+const MIN_TRADES: i64 = 10;
+
+/// Top-2 wallet share of volume that still counts as organic
+const ORGANIC_TOP2_SHARE: f64 = 0.5;
+
+/// Wash trading score (0-100) from a token's volume breakdown
+pub fn score(stats: &WashTradingStats) -> f64 {
+    let volume = to_f64(&stats.volume_usd);
+    if stats.trades < MIN_TRADES || volume <= 0.0 {
+        return 0.0;
+    }
+
+    let share = |part: &sqlx::types::BigDecimal| (to_f64(part) / volume).clamp(0.0, 1.0);
+
+    let round_trip = share(&stats.round_trip_usd);
+    let circular = share(&stats.circular_usd);
+    let concentration =
+        ((share(&stats.top2_usd) - ORGANIC_TOP2_SHARE) / (1.0 - ORGANIC_TOP2_SHARE)).max(0.0);
+
+    (40.0 * round_trip + 30.0 * circular + 30.0 * concentration).clamp(0.0, 100.0)
+}
+
+fn to_f64(bd: &sqlx::types::BigDecimal) -> f64 {
+    bd.to_string().parse().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::BigDecimal;
+
+    use super::*;
+
+    fn stats(
+        trades: i64,
+        volume: i32,
+        round_trip: i32,
+        circular: i32,
+        top2: i32,
+    ) -> WashTradingStats {
+        WashTradingStats {
+            trades,
+            volume_usd: BigDecimal::from(volume),
+            round_trip_usd: BigDecimal::from(round_trip),
+            circular_usd: BigDecimal::from(circular),
+            top2_usd: BigDecimal::from(top2),
+        }
+    }
+
+    #[test]
+    fn organic_volume_scores_zero() {
+        assert_eq!(score(&stats(200, 10_000, 0, 0, 2_000)), 0.0);
+    }
+
+    #[test]
+    fn too_few_trades_are_not_scored() {
+        assert_eq!(score(&stats(3, 1_000, 1_000, 1_000, 1_000)), 0.0);
+    }
+
+    #[test]
+    fn patterns_add_up() {
+        // Half the volume round-trips, a quarter is circular, top 2 hold 75%
+        let s = score(&stats(50, 1_000, 500, 250, 750));
+        assert!((s - (20.0 + 7.5 + 15.0)).abs() < 1e-9);
+
+        // Two wallets flipping back and forth between each other is the worst case
+        assert_eq!(score(&stats(50, 1_000, 1_000, 1_000, 1_000)), 100.0);
+    }
+}
@@ -1,52 +1,141 @@
-use indexer_db::entity::{
-    alert::{AlertEvent, AlertType, NewAlert},
-    evm_logs::EvmLogs,
-    token::Token,
+use chrono::{Duration, Utc};
+use indexer_db::{
+    entity::{
+        alert::{AlertEvent, AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, BeeScoreMetadata},
+        deferred_log::DeferredLog,
+        drainer_address::DrainerAddress,
+        evm_logs::EvmLogs,
+        evm_sync_logs::EvmSyncLogs,
+        external_report::ExternalReport,
+        handled_log::HandledLog,
+        pending_swap::PendingSwap,
+        processing_error::{ErrorStage, NewProcessingError, ProcessingError},
+        processor_progress::ProcessorProgress,
+        score_history::{NewScoreHistory, ScoreHistory},
+        social_metric::SocialMetric,
+        token::Token,
+        token_impersonation::TokenImpersonation,
+        token_rescan::TokenRescan,
+        wallet_profile::WalletProfile,
+    },
+    queue::{LogQueue, QueueBackend, QueuedLog},
+    Address20, Hash32,
 };
 use sqlx::{Pool, Postgres};
-use std::{env, error::Error};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+    time::Instant,
+};
 
 use crate::{
+    chain::ChainConstants,
     defaults,
+    egress::EventEgress,
+    entity_cache::EntityCache,
+    error::AppError,
+    event_metrics::{self, EventMetrics, Outcome},
     events::{self, topics},
-    handlers::{self, HandlerContext},
+    handlers::{self, sync::SnapshotBounds, HandlerContext, HandlerResult, Replay, SniperWindow},
+    impersonation,
+    known_addresses::KnownAddresses,
+    lag::{self, LagMonitor},
+    pair_discovery::PairLookups,
+    price_index,
     redis_client::RedisPublisher,
-    scoring::bee_score::BeeScoreCalculator,
+    rpc::{self, Rpc, RpcBudget},
+    sampling::{SampleCache, SamplingConfig},
+    score_queue::ScoreQueue,
+    scoring::{
+        bee_score::{BeeScoreCalculator, SocialSignals},
+        external_reports, fresh_wallets,
+    },
     utils,
 };
 
-/// Create handler context from environment
-fn create_handler_context(db_pool: Pool<Postgres>) -> HandlerContext {
-    let wbnb_address = env::var("WBNB_ADDRESS")
-        .unwrap_or_else(|_| defaults::WBNB_ADDRESS.to_string());
-    let busd_address = env::var("BUSD_ADDRESS")
-        .unwrap_or_else(|_| defaults::BUSD_ADDRESS.to_string());
-    let bnb_price_usd = env::var("BNB_PRICE_USD")
-        .unwrap_or_else(|_| defaults::BNB_PRICE_USD.to_string())
-        .parse::<f64>()
-        .unwrap_or(600.0);
-    let whale_threshold_usd = env::var("WHALE_THRESHOLD_USD")
-        .unwrap_or_else(|_| defaults::WHALE_THRESHOLD_USD.to_string())
+/// Create handler context from environment and the chain's `chain_constants`
+pub async fn create_handler_context(db_pool: Pool<Postgres>) -> Result<HandlerContext, AppError> {
+    let chain_id = env::var("CHAIN_ID")
+        .unwrap_or_else(|_| defaults::CHAIN_ID.to_string())
+        .parse::<i64>()
+        .unwrap_or(56);
+    let chain = ChainConstants::load(chain_id, &db_pool).await?;
+    let known = KnownAddresses::load(chain_id, &db_pool).await?;
+    // The price index when it is fresh, else the configured rate
+    let bnb_price_fallback = env_f64("BNB_PRICE_USD", defaults::BNB_PRICE_USD)?;
+    let bnb_price_usd = price_index::current_usd(
+        chain_id,
+        bnb_price_fallback,
+        &price_index::IndexConfig::from_env(),
+        &db_pool,
+    )
+    .await;
+    let whale_threshold_usd = env_f64("WHALE_THRESHOLD_USD", defaults::WHALE_THRESHOLD_USD)?;
+    let cex_flow_threshold_percent = env_f64(
+        "CEX_FLOW_THRESHOLD_PERCENT",
+        defaults::CEX_FLOW_THRESHOLD_PERCENT,
+    )?;
+    // SNIPER_WINDOW_SECONDS, when set, takes precedence over the block count
+    let sniper_window = match env::var("SNIPER_WINDOW_SECONDS") {
+        Ok(_) => SniperWindow::from_seconds(env_count("SNIPER_WINDOW_SECONDS", "")?),
+        Err(_) => SniperWindow::from_blocks(env_count(
+            "SNIPER_WINDOW_BLOCKS",
+            defaults::SNIPER_WINDOW_BLOCKS,
+        )?),
+    };
+    let social_traction_weight =
+        env_f64("SOCIAL_TRACTION_WEIGHT", defaults::SOCIAL_TRACTION_WEIGHT)?;
+    rpc::check_env()?;
+
+    let drainers = DrainerAddress::find_by_chain(chain_id, &db_pool)
+        .await?
+        .into_iter()
+        .map(|drainer| (drainer.address, drainer.label))
+        .collect();
+
+    HandlerContext::builder(db_pool, chain, known)
+        .bnb_price_usd(bnb_price_usd)
+        .whale_threshold_usd(whale_threshold_usd)
+        .cex_flow_threshold_percent(cex_flow_threshold_percent)
+        .sniper_window(sniper_window)
+        .social_traction_weight(social_traction_weight)
+        .rpc(Rpc::shared())
+        .rpc_budget(RpcBudget::shared())
+        .entities(EntityCache::shared())
+        .snapshot_bounds(SnapshotBounds::from_env())
+        .drainers(drainers)
+        .transfer_sampling(SamplingConfig::from_env().enabled)
+        .sample(SampleCache::shared())
+        .build()
+}
+
+/// A number from the environment, or `default` when unset
+fn env_f64(var: &str, default: &str) -> Result<f64, AppError> {
+    let value = env::var(var).unwrap_or_else(|_| default.to_string());
+    value
+        .trim()
         .parse::<f64>()
-        .unwrap_or(5000.0);
-    let rpc_url = env::var("RPC_URL")
-        .unwrap_or_else(|_| "https://bsc-dataseed.binance.org".to_string());
+        .map_err(|_| AppError::InvalidConfig(format!("`{}` is not a number: `{}`", var, value)))
+}
 
-    HandlerContext::new(
-        db_pool,
-        wbnb_address,
-        busd_address,
-        bnb_price_usd,
-        whale_threshold_usd,
-        rpc_url,
-    )
+/// A whole number from the environment, or `default` when unset
+fn env_count(var: &str, default: &str) -> Result<u64, AppError> {
+    let value = env::var(var).unwrap_or_else(|_| default.to_string());
+    value
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| AppError::InvalidConfig(format!("`{}` is not a count: `{}`", var, value)))
 }
 
 /// Update token BeeScore and trigger alerts if needed
-async fn update_token_score(
-    token_address: &str,
-    db_pool: &Pool<Postgres>,
+pub async fn update_token_score(
+    token_address: &Address20,
+    ctx: &HandlerContext,
 ) -> Result<(), Box<dyn Error>> {
+    let db_pool = &ctx.db_pool;
+
     // 1. Fetch token with latest metrics
     let token = match Token::find_by_address(token_address, db_pool).await? {
         Some(t) => t,
@@ -55,7 +144,71 @@ async fn update_token_score(
 
     // 2. Calculate score
     let metrics = token.to_metrics();
-    let result = BeeScoreCalculator::calculate(&metrics);
+    let mut result = BeeScoreCalculator::calculate(&metrics);
+
+    // Discount traction driven by gamed volume
+    if let Some(wash) = token
+        .wash_trading_score
+        .as_ref()
+        .and_then(|v| v.to_string().parse::<f64>().ok())
+    {
+        BeeScoreCalculator::apply_wash_trading(&mut result, wash);
+    }
+
+    // Optionally blend in social reach reported over the last day
+    if ctx.social_traction_weight > 0.0 {
+        let since = Utc::now() - Duration::hours(24);
+        if let Some(social) = SocialMetric::find_latest(token_address, since, db_pool).await? {
+            let signals = SocialSignals {
+                telegram_members: social.telegram_members.unwrap_or(0),
+                x_mentions_24h: social.x_mentions_24h.unwrap_or(0),
+            };
+            BeeScoreCalculator::apply_social(&mut result, &signals, ctx.social_traction_weight);
+        }
+    }
+
+    // Tokens copying an established token's name or symbol lose safety
+    if let Some(flag) = TokenImpersonation::find_by_token(token_address, db_pool).await? {
+        let target = Token::find_by_address(&flag.target_address, db_pool).await?;
+        let target_symbol = target
+            .and_then(|t| t.symbol)
+            .unwrap_or_else(|| flag.target_address.short());
+        BeeScoreCalculator::apply_impersonation(
+            &mut result,
+            &target_symbol,
+            impersonation::SAFETY_PENALTY,
+        );
+    }
+
+    // Recent buyers that are mostly brand-new wallets point at a sniper farm
+    let since = Utc::now() - Duration::hours(fresh_wallets::WINDOW_HOURS);
+    let buyers = WalletProfile::fresh_buyers(
+        token_address,
+        since,
+        fresh_wallets::MAX_AGE_HOURS,
+        fresh_wallets::MAX_NONCE,
+        db_pool,
+    )
+    .await?;
+    if let Some(share) = fresh_wallets::farm_share(&buyers) {
+        BeeScoreCalculator::apply_fresh_wallets(
+            &mut result,
+            share,
+            buyers.buyers,
+            fresh_wallets::SAFETY_PENALTY,
+        );
+    }
+
+    // Risk confirmed by external sources (GoPlus, TokenSniffer, ...)
+    let reports = ExternalReport::find_by_token(token_address, db_pool).await?;
+    if let Some((severity, count)) = external_reports::worst_confirmed(&reports) {
+        BeeScoreCalculator::apply_external_reports(
+            &mut result,
+            severity.as_str(),
+            count,
+            external_reports::safety_penalty(severity),
+        );
+    }
 
     // 3. Update score in DB
     Token::update_bee_score(
@@ -66,6 +219,18 @@ async fn update_token_score(
         db_pool,
     )
     .await?;
+    ctx.entities.invalidate_token(token_address);
+
+    // Keep the holder set and breakdown the score was based on, for disputes
+    // and explanations
+    let history = NewScoreHistory {
+        token_address: *token_address,
+        bee_score: result.total as i16,
+        safety_score: result.safety_score as i16,
+        traction_score: result.traction_score as i16,
+        breakdown: result.components(),
+    };
+    ScoreHistory::create(&history, db_pool).await?;
 
     // 4. Trigger alert if score is high (>80) and wasn't high before
     if result.total >= 80 {
@@ -73,7 +238,7 @@ async fn update_token_score(
         if prev_score < 80 {
             let alert = NewAlert {
                 alert_type: AlertType::HighBeeScore.as_str().to_string(),
-                token_address: Some(token_address.to_string()),
+                token_address: Some(*token_address),
                 token_symbol: token.symbol.clone(),
                 wallet_address: None,
                 title: format!("High BeeScore: {}/100", result.total),
@@ -87,11 +252,16 @@ async fn update_token_score(
                 bee_score: Some(result.total as i16),
                 amount_usd: None,
                 change_percent: None,
-                metadata: None,
+                metadata: Some(AlertMetadata::BeeScore(BeeScoreMetadata {
+                    previous_score: token.bee_score,
+                    safety_score: result.safety_score as i16,
+                    traction_score: result.traction_score as i16,
+                })),
+                source: None,
             };
 
             if let Err(e) = AlertEvent::create(&alert, db_pool).await {
-                eprintln!("Failed to create BeeScore alert: {}", e);
+                tracing::error!("Failed to create BeeScore alert: {}", e);
             }
         }
     }
@@ -99,92 +269,617 @@ async fn update_token_score(
     Ok(())
 }
 
-/// Process logs from Postgres, persist to database, and publish to Redis (dual-write)
+/// Process logs from the log queue, persist to database, and publish to Redis (dual-write)
+#[allow(clippy::too_many_arguments)]
 pub async fn process_logs(
     db_pool: &Pool<Postgres>,
+    queue: &QueueBackend,
     redis: &mut RedisPublisher,
+    egress: Option<&EventEgress>,
+    lag: &mut LagMonitor,
+    metrics: &mut EventMetrics,
+    lookups: &mut PairLookups,
+    scores: &ScoreQueue,
 ) -> Result<(), Box<dyn Error>> {
     let batch_size = env::var("BATCH_SIZE")
         .or::<String>(Ok(defaults::BATCH_SIZE.into()))?
-        .parse::<i32>()?;
+        .parse::<usize>()?;
+    let retry = DeferRetry::from_env()?;
+
+    let batch = take_batch(db_pool, queue, batch_size).await?;
 
-    let unprocessed_logs = EvmLogs::find_all(batch_size, db_pool).await?;
+    // Queues deliver at least once. Logs handled before, and repeats within
+    // the batch, are acked without being handled again.
+    let queued: Vec<&EvmLogs> = batch
+        .iter()
+        .filter(|pending| matches!(pending, Pending::Queued(_)))
+        .map(Pending::log)
+        .collect();
+    let mut seen: HashSet<_> = HandledLog::find_handled(&queued, db_pool)
+        .await?
+        .into_iter()
+        .collect();
 
     // Create handler context
-    let ctx = create_handler_context(db_pool.clone());
+    let ctx = create_handler_context(db_pool.clone()).await?;
+
+    attribute_pending_swaps(db_pool, scores, batch_size as i64).await?;
+
+    // Tokens being rescanned, and their pairs: the rescan replays their logs,
+    // so live ones wait until it is done
+    let paused: HashSet<Address20> = TokenRescan::paused_emitters(db_pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    // Logs parked per pair. A new log of a pair with parked logs is parked
+    // behind them, so each pair's logs are still handled in chain order.
+    let mut parked_logs: HashMap<Address20, i64> = DeferredLog::count_by_pair(db_pool)
+        .await?
+        .into_iter()
+        .collect();
+    // Pairs whose parked logs were put back this batch; their later logs in
+    // it wait with them
+    let mut waiting: HashSet<Address20> = HashSet::new();
+    // New logs to park, stored together before they are acked
+    let mut to_park: Vec<QueuedLog> = Vec::new();
 
-    for log in unprocessed_logs {
-        let log_id = log.id;
+    // Highest block of the new logs; blocks below it are done
+    let mut highest_block = None;
+
+    for pending in batch {
+        let log = pending.log();
+        if matches!(pending, Pending::Queued(_)) {
+            let block_number = log.block_number.to_string().parse::<i64>().unwrap_or(0);
+            highest_block = highest_block.max(Some(block_number));
+        }
+        let log_id = pending.id();
+        if matches!(pending, Pending::Queued(_)) && !seen.insert(HandledLog::key(log)) {
+            tracing::debug!("Skipping log {}, handled already", log_id);
+            if let Pending::Queued(queued) = pending {
+                if let Err(error) = queue.ack(queued).await {
+                    tracing::error!("Error acking log {}: {}", log_id, error);
+                }
+            }
+            continue;
+        }
         let topic0 = format!("0x{}", utils::vec_to_hex(log.event_signature.to_vec()));
+        let event_type = event_metrics::event_type_of(&topic0);
+
+        let emitter = Address20::from(log.address);
+        let held = paused.contains(&emitter);
+        let behind = waiting.contains(&emitter)
+            || (matches!(pending, Pending::Queued(_))
+                && parked_logs.get(&emitter).is_some_and(|n| *n > 0));
+        if held || behind {
+            match pending {
+                Pending::Queued(queued) => {
+                    *parked_logs.entry(emitter).or_default() += 1;
+                    to_park.push(queued);
+                }
+                // The pair's parked logs are held as a whole
+                Pending::Deferred(_) if held && waiting.insert(emitter) => {
+                    DeferredLog::hold(&emitter, retry.cycles, db_pool).await?;
+                }
+                Pending::Deferred(_) => {}
+            }
+            if held {
+                tracing::debug!("Holding {} log {} while its token is rescanned", event_type, log_id);
+            } else {
+                tracing::debug!("Parking {} log {} behind earlier logs of {}", event_type, log_id, emitter);
+            }
+            continue;
+        }
+
+        // Parked again until its pair is indexed
+        let mut parked = false;
 
         // Try to decode and process
-        match events::decode_event(&log) {
+        match events::decode_event(log) {
             Ok(decoded) => {
                 // Process with handler (persist to database)
-                match topic0.as_str() {
-                    topics::PAIR_CREATED => {
-                        let event = events::pair_created::decode(&log)?;
-                        if let Err(e) = handlers::pair_created::handle(&ctx, &event).await {
-                            eprintln!("PairCreated handler error: {}", e);
-                        }
+                let started = Instant::now();
+                let mut handled = handle_log(&ctx, &topic0, log, scores).await?;
+                // A pair from before indexing began is registered from its
+                // factory on the fly, and the log handled again
+                let unknown_pair = match &handled {
+                    Err(AppError::UnknownPair(pair)) => Some(*pair),
+                    _ => None,
+                };
+                if let Some(pair) = unknown_pair {
+                    let block_number = log.block_number.to_string().parse::<i64>().unwrap_or(0);
+                    if lookups.resolve(&ctx, &pair, block_number).await {
+                        handled = handle_log(&ctx, &topic0, log, scores).await?;
                     }
-                    topics::SWAP => {
-                        let event = events::swap::decode(&log)?;
-                        if let Err(e) = handlers::swap::handle(&ctx, &event).await {
-                            eprintln!("Swap handler error: {}", e);
-                        } else {
-                            // Update score after swap
-                            if let Ok(Some(pair)) =
-                                indexer_db::entity::pair::Pair::find_by_address(&event.pair, db_pool)
-                                    .await
-                            {
-                                let token_address = pair.get_token_address();
-                                if let Err(e) = update_token_score(token_address, db_pool).await {
-                                    eprintln!("Failed to update score for {}: {}", token_address, e);
-                                }
+                }
+                let latency = started.elapsed();
+                let outcome = match handled {
+                    Ok(()) => Outcome::Handled,
+                    Err(AppError::UnknownPair(pair)) => {
+                        parked = match &pending {
+                            Pending::Queued(_) if retry.max_retries > 0 => {
+                                *parked_logs.entry(pair).or_default() += 1;
+                                true
+                            }
+                            Pending::Queued(_) => false,
+                            // Its later logs in this batch wait with it, or
+                            // are dropped with it
+                            Pending::Deferred(_) => {
+                                waiting.insert(pair);
+                                retry_pair(db_pool, log, &pair, &retry).await?
+                            }
+                        };
+                        if !parked {
+                            // Most likely a pair from before indexing began
+                            // that its factory didn't vouch for
+                            if topic0 == topics::SWAP {
+                                PendingSwap::buffer(log, &pair, db_pool).await?;
+                                tracing::debug!(
+                                    "Unknown pair: {}, keeping swap until it is indexed",
+                                    pair
+                                );
+                            } else {
+                                tracing::debug!(
+                                    "Unknown pair: {}, skipping {} log {}",
+                                    pair, event_type, log_id
+                                );
                             }
                         }
+                        Outcome::Handled
                     }
-                    topics::TRANSFER => {
-                        let event = events::transfer::decode(&log)?;
-                        if let Err(e) = handlers::transfer::handle(&ctx, &event).await {
-                            eprintln!("Transfer handler error: {}", e);
-                        } else {
-                            // Update score after transfer
-                            if let Err(e) = update_token_score(&event.token, db_pool).await {
-                                eprintln!("Failed to update score for {}: {}", event.token, e);
+                    // Parked behind its emitter's other logs like an unknown
+                    // pair's, but only this log is dropped when retries run out
+                    Err(AppError::UnknownDecimals(token)) => {
+                        parked = match &pending {
+                            Pending::Queued(_) if retry.max_retries > 0 => {
+                                *parked_logs.entry(emitter).or_default() += 1;
+                                true
+                            }
+                            Pending::Queued(_) => false,
+                            Pending::Deferred(_) => {
+                                waiting.insert(emitter);
+                                DeferredLog::postpone(&emitter, retry.cycles, db_pool).await?
+                                    < retry.max_retries
                             }
+                        };
+                        if parked {
+                            Outcome::Handled
+                        } else {
+                            let e = AppError::UnknownDecimals(token);
+                            tracing::error!("{} handler error: {}", event_type, e);
+                            record_error(db_pool, &pending, &topic0, ErrorStage::Handler, e.to_string())
+                                .await;
+                            Outcome::HandlerFailed
                         }
                     }
-                    _ => {
-                        // Unknown event type, skip handler
+                    Err(e) => {
+                        tracing::error!("{} handler error: {}", event_type, e);
+                        record_error(db_pool, &pending, &topic0, ErrorStage::Handler, e.to_string())
+                            .await;
+                        Outcome::HandlerFailed
                     }
-                }
+                };
 
-                // Publish to Redis (hot path for real-time updates)
-                match redis.publish(decoded.channel, &decoded.payload).await {
-                    Ok(_) => {
-                        println!(
-                            "Published to {}: {} bytes",
-                            decoded.channel,
-                            decoded.payload.len()
+                // Published and counted once it is handled for good
+                if !parked {
+                    metrics.record(event_type, outcome, Some(latency));
+
+                    // Publish to Redis (hot path for real-time updates)
+                    match redis.publish(decoded.channel, &decoded.payload).await {
+                        Ok(_) => {
+                            tracing::debug!(
+                                "Published to {}: {} bytes",
+                                decoded.channel,
+                                decoded.payload.len()
+                            );
+                        }
+                        Err(e) => {
+                            tracing::error!("Redis publish error: {}", e);
+                        }
+                    }
+
+                    // Durable egress; an event it doesn't take waits in the
+                    // outbox, the log is acked either way
+                    if let Some(egress) = egress {
+                        let event_id = format!(
+                            "0x{}:{}",
+                            utils::vec_to_hex(log.transaction_hash.to_vec()),
+                            log.log_index
                         );
+                        if let Err(e) = egress
+                            .send(decoded.channel, &event_id, &decoded.payload, db_pool)
+                            .await
+                        {
+                            tracing::error!("Failed to keep egress event {}: {}", event_id, e);
+                        }
                     }
-                    Err(e) => {
-                        eprintln!("Redis publish error: {}", e);
+
+                    if let Some(block_timestamp) = log.block_timestamp {
+                        let event_type = lag::event_type(decoded.channel);
+                        lag.record(event_type, block_timestamp, Utc::now());
                     }
                 }
             }
+            // A topic without a decoder is nothing to fix in the log
+            Err(e @ AppError::UnknownEventTopic(_)) => {
+                tracing::debug!("Event skipped (log_id={}): {}", log_id, e);
+                metrics.record(event_type, Outcome::Skipped, None);
+            }
             Err(e) => {
-                eprintln!("Event decode skipped (log_id={}): {}", log_id, e);
+                tracing::warn!("Event decode skipped (log_id={}): {}", log_id, e);
+                record_error(db_pool, &pending, &topic0, ErrorStage::Decode, e.to_string()).await;
+                metrics.record(event_type, Outcome::DecodeFailed, None);
+            }
+        }
+
+        if !parked {
+            if let Err(error) = HandledLog::record(log, db_pool).await {
+                tracing::error!("Error recording log {} as handled: {}", log_id, error);
+            }
+        }
+
+        match pending {
+            // Acked once it is stored in `deferred_logs`
+            Pending::Queued(queued) if parked => to_park.push(queued),
+            // Remove from the log queue (cold path complete)
+            Pending::Queued(queued) => {
+                if let Err(error) = queue.ack(queued).await {
+                    tracing::error!("Error acking log {}: {}", log_id, error);
+                }
+            }
+            Pending::Deferred(deferred) if !parked => {
+                if let Some(left) = parked_logs.get_mut(&emitter) {
+                    *left -= 1;
+                }
+                if let Err(error) = DeferredLog::delete(&deferred.log, db_pool).await {
+                    tracing::error!("Error removing deferred log {}: {}", log_id, error);
+                }
+            }
+            Pending::Deferred(_) => {}
+        }
+    }
+
+    let parked: Vec<&EvmLogs> = to_park.iter().map(|queued| &queued.log).collect();
+    DeferredLog::defer(&parked, retry.cycles, db_pool).await?;
+    for queued in to_park {
+        let log_id = queued.id();
+        if let Err(error) = queue.ack(queued).await {
+            tracing::error!("Error acking log {}: {}", log_id, error);
+        }
+    }
+
+    record_progress(db_pool, queue, highest_block).await;
+
+    Ok(())
+}
+
+/// Parked logs due for a retry and new logs from the queue, in chain order.
+/// Parked logs count down a cycle each batch, also with the queue empty, so
+/// a swap parked for its pair runs after the PairCreated that arrived since.
+async fn take_batch(
+    db_pool: &Pool<Postgres>,
+    queue: &QueueBackend,
+    batch_size: usize,
+) -> Result<Vec<Pending>, Box<dyn Error>> {
+    DeferredLog::tick(db_pool).await?;
+    let mut batch: Vec<Pending> = DeferredLog::find_due(batch_size as i64, db_pool)
+        .await?
+        .into_iter()
+        .map(Pending::Deferred)
+        .collect();
+    batch.extend(queue.pull(batch_size).await?.into_iter().map(Pending::Queued));
+    batch.sort_by(|a, b| {
+        let (a, b) = (a.log(), b.log());
+        (&a.block_number, a.log_index).cmp(&(&b.block_number, b.log_index))
+    });
+
+    Ok(batch)
+}
+
+/// Move the processor's last processed block forward. With the queue empty
+/// that is as far as the listener has synced; otherwise the block before
+/// `highest_block`, whose later logs may still be queued. Either way it stays
+/// below the earliest parked log, which isn't handled yet.
+pub async fn record_progress(
+    db_pool: &Pool<Postgres>,
+    queue: &QueueBackend,
+    highest_block: Option<i64>,
+) {
+    let through = match queue.pending().await {
+        Ok(0) => EvmSyncLogs::min_synced_block(db_pool).await,
+        _ => Ok(highest_block.map(|block| block - 1)),
+    };
+    let through = match (through, DeferredLog::lowest_block(db_pool).await) {
+        (Ok(through), Ok(parked)) => Ok(below_parked(through, parked)),
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+    match through {
+        Ok(Some(block)) => {
+            if let Err(e) = ProcessorProgress::advance(block, db_pool).await {
+                tracing::error!("Failed to record processor progress: {}", e);
             }
         }
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to read listener progress: {}", e),
+    }
+}
+
+/// `through`, kept before the block of the earliest parked log
+fn below_parked(through: Option<i64>, lowest_parked: Option<i64>) -> Option<i64> {
+    match lowest_parked {
+        Some(parked) => through.map(|block| block.min(parked - 1)),
+        None => through,
+    }
+}
+
+/// Decode a log as its event type and run its handler. The outer error is a
+/// log that decoded generically but not as its own event.
+async fn handle_log(
+    ctx: &HandlerContext,
+    topic0: &str,
+    log: &EvmLogs,
+    scores: &ScoreQueue,
+) -> Result<HandlerResult<()>, AppError> {
+    let handled = match topic0 {
+        topics::PAIR_CREATED => {
+            let event = events::pair_created::decode(log)?;
+            handlers::pair_created::handle(ctx, &event).await
+        }
+        topics::SWAP => {
+            let event = events::swap::decode(log)?;
+            let handled = handlers::swap::handle(ctx, &event).await;
+            if handled.is_ok() {
+                // Re-score the token off the processing path
+                if let Ok(Some(pair)) = ctx.find_pair(&event.pair).await {
+                    scores.mark(*pair.get_token_address());
+                }
+            }
+            handled
+        }
+        topics::TRANSFER => {
+            let event = events::transfer::decode(log)?;
+            let handled = handlers::transfer::handle(ctx, &event).await;
+            if handled.is_ok() {
+                // Re-score the token off the processing path
+                scores.mark(event.token);
+            }
+            handled
+        }
+        topics::SYNC => {
+            let event = events::sync::decode(log)?;
+            handlers::sync::handle(ctx, &event).await
+        }
+        topics::MINT | topics::BURN => {
+            let event = events::liquidity::decode(log)?;
+            handlers::liquidity::handle(ctx, &event).await
+        }
+        topics::APPROVAL => {
+            let event = events::approval::decode(log)?;
+            handlers::approval::handle(ctx, &event).await
+        }
+        _ => {
+            // Unknown event type, skip handler
+            Ok(())
+        }
+    };
+    Ok(handled)
+}
+
+/// A log to process: new from the queue, or parked earlier because its pair
+/// wasn't indexed yet
+enum Pending {
+    Queued(QueuedLog),
+    Deferred(DeferredLog),
+}
+
+impl Pending {
+    fn log(&self) -> &EvmLogs {
+        match self {
+            Pending::Queued(queued) => &queued.log,
+            Pending::Deferred(deferred) => &deferred.log,
+        }
+    }
+
+    /// Queue receipt, or the `evm_logs` id a parked log had
+    fn id(&self) -> String {
+        match self {
+            Pending::Queued(queued) => queued.id(),
+            Pending::Deferred(deferred) => deferred.log.id.to_string(),
+        }
+    }
+}
+
+/// How logs referencing a pair that isn't indexed yet are retried
+struct DeferRetry {
+    /// Batches to wait between retries
+    cycles: i32,
+    /// Retries before the log is dropped
+    max_retries: i32,
+}
+
+impl DeferRetry {
+    fn from_env() -> Result<Self, AppError> {
+        let var = |name: &str, default: &str| {
+            let value = env::var(name).unwrap_or_else(|_| default.to_string());
+            value
+                .trim()
+                .parse::<i32>()
+                .ok()
+                .filter(|n| *n >= 0)
+                .ok_or_else(|| {
+                    AppError::InvalidConfig(format!("`{}` is not a count: `{}`", name, value))
+                })
+        };
+        Ok(Self {
+            cycles: var("DEFERRED_LOG_RETRY_CYCLES", defaults::DEFERRED_LOG_RETRY_CYCLES)?,
+            max_retries: var("DEFERRED_LOG_MAX_RETRIES", defaults::DEFERRED_LOG_MAX_RETRIES)?,
+        })
+    }
+}
+
+/// Count another miss for a pair whose parked `log` was retried. Returns
+/// `false` once its retries are used up: all of its parked logs are dropped
+/// then, the swaps among the others kept in `pending_swaps`.
+async fn retry_pair(
+    db_pool: &Pool<Postgres>,
+    log: &EvmLogs,
+    pair: &Address20,
+    retry: &DeferRetry,
+) -> Result<bool, sqlx::Error> {
+    let retries = DeferredLog::postpone(pair, retry.cycles, db_pool).await?;
+    if retries < retry.max_retries {
+        return Ok(true);
+    }
 
-        // Delete from Postgres queue (cold path complete)
-        if let Err(error) = EvmLogs::delete(log_id, db_pool).await {
-            eprintln!("Error deleting log {}: {}", log_id, error);
+    for dropped in DeferredLog::delete_pair(pair, db_pool).await? {
+        let same = dropped.log.transaction_hash == log.transaction_hash
+            && dropped.log.log_index == log.log_index;
+        let topic0 = format!("0x{}", utils::vec_to_hex(dropped.log.event_signature.to_vec()));
+        if !same && topic0 == topics::SWAP {
+            PendingSwap::buffer(&dropped.log, pair, db_pool).await?;
         }
     }
+    Ok(false)
+}
+
+/// Handle kept swaps whose pair is indexed by now. They are replayed like a
+/// rescan's logs, so week-old whale trades don't raise alerts and don't
+/// overwrite the token's current price.
+async fn attribute_pending_swaps(
+    db_pool: &Pool<Postgres>,
+    scores: &ScoreQueue,
+    limit: i64,
+) -> Result<(), Box<dyn Error>> {
+    let kept = PendingSwap::find_attributable(limit, db_pool).await?;
+    if kept.is_empty() {
+        return Ok(());
+    }
+
+    let mut ctx = create_handler_context(db_pool.clone()).await?;
+    ctx.replay = Some(Replay { holders: false });
+    for swap in kept {
+        let handled = match events::swap::decode(&swap.log) {
+            Ok(event) => handlers::swap::handle(&ctx, &event).await,
+            Err(e) => Err(e),
+        };
+        match handled {
+            Ok(()) => {
+                if let Ok(Some(pair)) = ctx.find_pair(&swap.pair_address).await {
+                    scores.mark(*pair.get_token_address());
+                }
+            }
+            Err(e) => tracing::error!("Kept swap of {} failed: {}", swap.pair_address, e),
+        }
+        // Attributed or not, it isn't retried
+        PendingSwap::delete(&swap.log, db_pool).await?;
+    }
 
     Ok(())
 }
+
+/// Keep a log that failed to decode or handle in `processing_errors`, so it
+/// can be looked into after it is acked
+async fn record_error(
+    db_pool: &Pool<Postgres>,
+    pending: &Pending,
+    topic0: &str,
+    stage: ErrorStage,
+    error: String,
+) {
+    let log = pending.log();
+    let failed = NewProcessingError {
+        log_id: pending.id(),
+        topic: topic0.to_string(),
+        tx_hash: Hash32::new(log.transaction_hash),
+        log_index: log.log_index,
+        stage,
+        error,
+    };
+    if let Err(e) = ProcessingError::create(&failed, db_pool).await {
+        tracing::error!("Failed to record processing error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{b256, Address, Bytes, B256},
+        rpc::types::Log,
+    };
+    use indexer_db::queue::PostgresQueue;
+    use sqlx::PgPool;
+
+    use super::*;
+
+    fn swap_log(block: u64) -> EvmLogs {
+        let log = Log {
+            inner: alloy::primitives::Log::new(
+                Address::repeat_byte(1),
+                vec![b256!(
+                    "d78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822"
+                )],
+                Bytes::from(vec![0xab; 32]),
+            )
+            .unwrap(),
+            block_hash: Some(B256::repeat_byte(block as u8)),
+            block_number: Some(block),
+            block_timestamp: Some(1_700_000_000),
+            transaction_hash: Some(B256::with_last_byte(block as u8)),
+            transaction_index: Some(0),
+            log_index: Some(0),
+            removed: false,
+        };
+        EvmLogs::from_rpc_log(&log).unwrap()
+    }
+
+    #[test]
+    fn progress_stays_below_parked_logs() {
+        assert_eq!(below_parked(Some(100), Some(42)), Some(41));
+        assert_eq!(below_parked(Some(30), Some(42)), Some(30));
+        assert_eq!(below_parked(Some(100), None), Some(100));
+        assert_eq!(below_parked(None, Some(42)), None);
+    }
+
+    #[sqlx::test(migrations = "../libs/indexer-db/migrations")]
+    async fn parked_logs_are_retried_with_the_queue_empty(pool: PgPool) {
+        let queue = QueueBackend::Postgres(PostgresQueue::new(pool.clone()));
+        sqlx::query("INSERT INTO evm_chains (id, name, block_time) VALUES (56, 'bsc', 3)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO evm_sync_logs (address, last_synced_block_number, chain_id) \
+             VALUES ($1, 100, 56)",
+        )
+        .bind(vec![2u8; 20])
+        .execute(&pool)
+        .await
+        .unwrap();
+        DeferredLog::defer(&[&swap_log(42)], 2, &pool)
+            .await
+            .unwrap();
+        assert_eq!(queue.pending().await.unwrap(), 0);
+
+        // Counts down on the first empty poll, comes back on the second
+        assert!(take_batch(&pool, &queue, 10).await.unwrap().is_empty());
+        let batch = take_batch(&pool, &queue, 10).await.unwrap();
+        assert!(matches!(batch.as_slice(), [Pending::Deferred(d)] if d.log.log_index == 0));
+
+        // Synced through 100, but the parked log at 42 isn't handled yet
+        record_progress(&pool, &queue, None).await;
+        assert_eq!(
+            ProcessorProgress::last_processed_block(&pool)
+                .await
+                .unwrap(),
+            Some(41)
+        );
+        DeferredLog::delete(&swap_log(42), &pool).await.unwrap();
+        record_progress(&pool, &queue, None).await;
+        assert_eq!(
+            ProcessorProgress::last_processed_block(&pool)
+                .await
+                .unwrap(),
+            Some(100)
+        );
+    }
+}
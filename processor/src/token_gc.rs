@@ -0,0 +1,84 @@
+//! Dead token garbage collection
+//!
+//! Launches that never took off keep their holders and price snapshots in the
+//! hot tables forever. Each pass archives tokens with no liquidity and no
+//! swaps or transfers for `TOKEN_GC_IDLE_DAYS`, `TOKEN_GC_BATCH` at a time:
+//! they drop out of the token lists, and their holders and snapshots move to
+//! the cold tables (or are deleted with `TOKEN_GC_KEEP_COLD=false`). A token
+//! that trades or transfers again is brought back by its handler.
+
+use std::env;
+
+use indexer_db::entity::token::{ArchivedTokens, Token};
+use sqlx::{Pool, Postgres};
+
+use crate::defaults;
+
+/// Most batches one pass may run; the rest waits for the next pass
+const MAX_BATCHES_PER_PASS: i32 = 20;
+
+/// What counts as dead and where its rows go, from the environment
+#[derive(Debug, Clone)]
+pub struct GcConfig {
+    pub idle_days: i32,
+    pub batch: i64,
+    /// Move holders and snapshots to the cold tables rather than delete them
+    pub keep_cold: bool,
+}
+
+impl GcConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: i64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<i64>()
+                .unwrap_or(fallback)
+                .max(0)
+        };
+
+        Self {
+            idle_days: read("TOKEN_GC_IDLE_DAYS", defaults::TOKEN_GC_IDLE_DAYS, 30) as i32,
+            batch: read("TOKEN_GC_BATCH", defaults::TOKEN_GC_BATCH, 500),
+            keep_cold: env::var("TOKEN_GC_KEEP_COLD")
+                .unwrap_or_else(|_| defaults::TOKEN_GC_KEEP_COLD.to_string())
+                .parse::<bool>()
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// Archive dead tokens until a batch comes back short
+pub async fn run(db_pool: &Pool<Postgres>, config: &GcConfig) {
+    if config.batch == 0 || config.idle_days == 0 {
+        return;
+    }
+
+    let mut total = ArchivedTokens::default();
+    for _ in 0..MAX_BATCHES_PER_PASS {
+        match Token::archive_dead(config.idle_days, config.batch, config.keep_cold, db_pool).await
+        {
+            Ok(archived) => {
+                total.tokens += archived.tokens;
+                total.holders += archived.holders;
+                total.snapshots += archived.snapshots;
+                if archived.tokens < config.batch {
+                    break;
+                }
+            }
+            Err(e) => {
+                tracing::error!("Token GC: failed to archive dead tokens: {}", e);
+                break;
+            }
+        }
+    }
+
+    if total.tokens > 0 {
+        tracing::info!(
+            "Token GC: archived {} dead tokens, {} {} holders and {} snapshots",
+            total.tokens,
+            if config.keep_cold { "moved" } else { "deleted" },
+            total.holders,
+            total.snapshots
+        );
+    }
+}
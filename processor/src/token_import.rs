@@ -0,0 +1,156 @@
+//! Tracking tokens launched before indexing began
+//!
+//! `processor import-token <address> [--from-block <n>] [--to-block <n>]`
+//! adds an existing token on request:
+//!
+//! 1. The chain's factories are asked for the token's pair with each base
+//!    token, and the pairs they list are indexed as a pair lookup would
+//! 2. A rescan of the token is queued and run right away: its pairs'
+//!    Swap/Sync/Mint/Burn and its Transfer logs are replayed over the range,
+//!    then its metrics and BeeScore are computed
+//!
+//! Frontend users request imports with `POST /api/tokens/track`, which the
+//! job worker runs the same way (see [`crate::jobs`]).
+//!
+//! For a token the processor already knows the launch of, the range defaults
+//! to the rescan's, creation block up to the head. Otherwise it covers the
+//! last `IMPORT_TOKEN_BLOCKS` blocks, and holder balances are left to
+//! reconciliation since the transfers before the range aren't replayed.
+
+use std::{env, error::Error};
+
+use alloy::providers::Provider;
+use indexer_db::{
+    entity::{
+        pair::Pair,
+        token::Token,
+        token_rescan::{NewTokenRescan, TokenRescan},
+    },
+    Address20,
+};
+use sqlx::{Pool, Postgres};
+
+use crate::{defaults, pair_discovery, rescan, service};
+
+type ImportError = Box<dyn Error + Send + Sync>;
+
+/// Run `import-token` with the arguments after it. Returns whether the
+/// token's history was replayed.
+pub async fn run(args: &[String], db_pool: &Pool<Postgres>) -> Result<bool, ImportError> {
+    let address: Address20 = args
+        .first()
+        .ok_or("A token address is required")?
+        .parse()
+        .map_err(|_| "Invalid token address")?;
+    let block = |name: &str| -> Result<Option<i64>, String> {
+        flag(args, name)
+            .map(|b| {
+                b.parse::<i64>()
+                    .map_err(|_| format!("`{}` must be a block number", name))
+            })
+            .transpose()
+    };
+    let from_block = block("--from-block")?;
+    let to_block = block("--to-block")?;
+    if let (Some(from), Some(to)) = (from_block, to_block) {
+        if from > to {
+            return Err("`--from-block` must not be after `--to-block`".into());
+        }
+    }
+
+    let (rescan, _) = import_token(&address, from_block, to_block, db_pool).await?;
+    let Some(rescan) = TokenRescan::claim(rescan.id, db_pool).await? else {
+        tracing::info!(
+            "Rescan #{} of {} was picked up by a running processor; track it at /api/admin/rescans/{}",
+            rescan.id, address, rescan.id
+        );
+        return Ok(true);
+    };
+    Ok(rescan::execute(&rescan, None, db_pool).await)
+}
+
+/// Index `address`'s pairs and queue the rescan replaying its history.
+/// Returns the rescan and the number of pairs indexed.
+pub async fn import_token(
+    address: &Address20,
+    from_block: Option<i64>,
+    to_block: Option<i64>,
+    db_pool: &Pool<Postgres>,
+) -> Result<(TokenRescan, usize), ImportError> {
+    let ctx = service::create_handler_context(db_pool.clone()).await?;
+    if ctx.chain.is_wrapped_native(address) || ctx.chain.is_stablecoin(address) {
+        return Err(format!("{} is a base token", address).into());
+    }
+
+    let head = ctx
+        .rpc
+        .call(|p| async move { p.get_block_number().await })
+        .await? as i64;
+    let to_block = to_block.unwrap_or(head);
+    let lookback = env::var("IMPORT_TOKEN_BLOCKS")
+        .unwrap_or_else(|_| defaults::IMPORT_TOKEN_BLOCKS.to_string())
+        .parse::<i64>()
+        .unwrap_or(200_000);
+    // Stands in for the creation block of pairs found now
+    let start = from_block.unwrap_or((to_block - lookback).max(0));
+
+    let pairs = pair_discovery::find_pairs(&ctx, address).await?;
+    if pairs.is_empty() {
+        return Err(format!(
+            "No factory of chain {} lists a pair for {}",
+            ctx.chain.chain_id, address
+        )
+        .into());
+    }
+    let known = Token::find_by_address(address, db_pool).await?;
+    let mut indexed = Vec::new();
+    for pair in pairs {
+        let is_indexed = Pair::find_by_address(&pair, db_pool).await?.is_some()
+            || pair_discovery::discover(&ctx, &pair, start).await?;
+        match is_indexed {
+            true => indexed.push(pair),
+            false => tracing::info!("Import of {}: skipped pair {}", address, pair),
+        }
+    }
+
+    // Indexing each pair pointed the token at it; keep the pair it had, or
+    // the first one found
+    let token = Token::find_by_address(address, db_pool)
+        .await?
+        .ok_or("None of its pairs could be indexed")?;
+    let main = match known.and_then(|token| token.pair_address) {
+        Some(main) => main,
+        None => *indexed
+            .first()
+            .ok_or("None of its pairs could be indexed")?,
+    };
+    if token.pair_address != Some(main) {
+        Token::set_pair(address, &main, db_pool).await?;
+        ctx.entities.invalidate_token(address);
+    }
+    tracing::info!(
+        "Import of {}: {} pair(s), main pair {}",
+        address,
+        indexed.len(),
+        main
+    );
+
+    let range = NewTokenRescan {
+        token_address: *address,
+        from_block: match (from_block, token.block_number) {
+            (None, Some(_)) => None,
+            _ => Some(start),
+        },
+        to_block: Some(to_block),
+    };
+    let rescan = TokenRescan::create(&range, db_pool).await?;
+    Ok((rescan, indexed.len()))
+}
+
+/// Value following `name` in `args`
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
@@ -0,0 +1,233 @@
+//! Token list publishing
+//!
+//! Every `TOKENLIST_PUBLISH_INTERVAL` seconds the live tokens with a BeeScore
+//! of at least `TOKENLIST_MIN_SCORE` (the `TOKENLIST_MAX_TOKENS` highest
+//! scored) are compared with the latest release in `token_list_releases`.
+//! Tokens already listed stay while they score at least
+//! `TOKENLIST_EXIT_SCORE`, so one hovering around the bar doesn't bump the
+//! version every run. A changed set is published as a new version following
+//! the TokenList rules: a removed token bumps the major version, an added one
+//! the minor, changed name, symbol or decimals the patch. Only the
+//! `TOKENLIST_KEEP_RELEASES` latest releases are kept. The API serves the
+//! latest release at `/api/tokenlist.json`.
+
+use std::{collections::HashMap, env};
+
+use indexer_db::entity::token_list_release::{ListVersion, ListedToken, TokenListRelease};
+use sqlx::{Pool, Postgres};
+
+use crate::defaults;
+
+/// Longest name and symbol the TokenList schema accepts
+const MAX_NAME_LEN: usize = 40;
+const MAX_SYMBOL_LEN: usize = 20;
+
+/// Which tokens make the list
+#[derive(Debug, Clone)]
+pub struct PublishConfig {
+    pub chain_id: i64,
+    pub min_score: i16,
+    /// Score a listed token may drop to before it is removed
+    pub exit_score: i16,
+    pub max_tokens: i64,
+    pub keep_releases: i64,
+}
+
+impl PublishConfig {
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: &str, fallback: i64| {
+            env::var(var)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<i64>()
+                .unwrap_or(fallback)
+                .max(0)
+        };
+
+        let min_score = read("TOKENLIST_MIN_SCORE", defaults::TOKENLIST_MIN_SCORE, 70).min(100);
+        let exit_score =
+            read("TOKENLIST_EXIT_SCORE", defaults::TOKENLIST_EXIT_SCORE, 60).min(min_score);
+
+        Self {
+            chain_id: read("CHAIN_ID", defaults::CHAIN_ID, 56),
+            min_score: min_score as i16,
+            exit_score: exit_score as i16,
+            max_tokens: read(
+                "TOKENLIST_MAX_TOKENS",
+                defaults::TOKENLIST_MAX_TOKENS,
+                1000,
+            ),
+            keep_releases: read(
+                "TOKENLIST_KEEP_RELEASES",
+                defaults::TOKENLIST_KEEP_RELEASES,
+                100,
+            )
+            .max(1),
+        }
+    }
+}
+
+/// Whether the TokenList schema accepts the token's name and symbol
+fn fits_schema(token: &ListedToken) -> bool {
+    let name_char = |c: char| {
+        c.is_ascii_alphanumeric()
+            || " _.'+-%/:&[]()".contains(c)
+            || (('À'..='ÿ').contains(&c) && c != '×' && c != '÷')
+    };
+
+    !token.name.is_empty()
+        && token.name.chars().count() <= MAX_NAME_LEN
+        && token.name.chars().all(name_char)
+        && !token.symbol.is_empty()
+        && token.symbol.chars().count() <= MAX_SYMBOL_LEN
+        && !token.symbol.chars().any(char::is_whitespace)
+        && (0..=255).contains(&token.decimals)
+}
+
+/// Version to publish `tokens` under after `latest`, `None` when nothing
+/// changed
+pub fn next_version(
+    latest: Option<(ListVersion, &[ListedToken])>,
+    tokens: &[ListedToken],
+) -> Option<ListVersion> {
+    let Some((version, published)) = latest else {
+        return Some(ListVersion {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        });
+    };
+
+    let current: HashMap<_, _> = tokens.iter().map(|t| (t.address, t)).collect();
+    let previous: HashMap<_, _> = published.iter().map(|t| (t.address, t)).collect();
+
+    if previous.keys().any(|a| !current.contains_key(a)) {
+        Some(ListVersion {
+            major: version.major + 1,
+            minor: 0,
+            patch: 0,
+        })
+    } else if current.keys().any(|a| !previous.contains_key(a)) {
+        Some(ListVersion {
+            minor: version.minor + 1,
+            patch: 0,
+            ..version
+        })
+    } else if current.iter().any(|(a, t)| previous[a] != *t) {
+        Some(ListVersion {
+            patch: version.patch + 1,
+            ..version
+        })
+    } else {
+        None
+    }
+}
+
+/// Publish a new release when the qualifying tokens changed, then drop the
+/// releases past the ones kept
+pub async fn run(db_pool: &Pool<Postgres>, config: &PublishConfig) {
+    let latest = match TokenListRelease::find_latest(db_pool).await {
+        Ok(latest) => latest,
+        Err(e) => {
+            tracing::error!("Token list: failed to read the latest release: {}", e);
+            return;
+        }
+    };
+    let listed: Vec<_> = latest
+        .iter()
+        .flat_map(|r| r.tokens.0.iter().map(|t| t.address))
+        .collect();
+    let tokens = match TokenListRelease::find_candidates(
+        config.min_score,
+        config.exit_score,
+        &listed,
+        config.max_tokens,
+        db_pool,
+    )
+    .await
+    {
+        Ok(tokens) => tokens.into_iter().filter(fits_schema).collect::<Vec<_>>(),
+        Err(e) => {
+            tracing::error!("Token list: failed to read qualifying tokens: {}", e);
+            return;
+        }
+    };
+
+    let Some(version) = next_version(
+        latest.as_ref().map(|r| (r.version(), r.tokens.0.as_slice())),
+        &tokens,
+    ) else {
+        return;
+    };
+    match TokenListRelease::create(config.chain_id, version, &tokens, db_pool).await {
+        Ok(_) => tracing::info!(
+            "Token list: published {}.{}.{} with {} tokens",
+            version.major,
+            version.minor,
+            version.patch,
+            tokens.len()
+        ),
+        Err(e) => {
+            tracing::error!("Token list: failed to publish a release: {}", e);
+            return;
+        }
+    }
+    match TokenListRelease::prune(config.keep_releases, db_pool).await {
+        Ok(0) => {}
+        Ok(pruned) => tracing::info!("Token list: pruned {} old releases", pruned),
+        Err(e) => tracing::error!("Token list: failed to prune old releases: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexer_db::types::Address20;
+
+    fn listed(n: u8, symbol: &str) -> ListedToken {
+        ListedToken {
+            address: Address20::new([n; 20]),
+            name: format!("{} Token", symbol),
+            symbol: symbol.to_string(),
+            decimals: 18,
+        }
+    }
+
+    #[test]
+    fn versions_follow_the_token_list_rules() {
+        let v = |major, minor, patch| ListVersion {
+            major,
+            minor,
+            patch,
+        };
+        let published = vec![listed(1, "ONE"), listed(2, "TWO")];
+        let latest = Some((v(1, 2, 3), published.as_slice()));
+
+        assert_eq!(next_version(None, &published), Some(v(1, 0, 0)));
+        assert_eq!(next_version(latest, &published), None);
+
+        let added = vec![listed(1, "ONE"), listed(2, "TWO"), listed(3, "THREE")];
+        assert_eq!(next_version(latest, &added), Some(v(1, 3, 0)));
+
+        let renamed = vec![listed(1, "ONE"), listed(2, "DOS")];
+        assert_eq!(next_version(latest, &renamed), Some(v(1, 2, 4)));
+
+        // A removal outweighs the addition made with it
+        let swapped = vec![listed(1, "ONE"), listed(3, "THREE")];
+        assert_eq!(next_version(latest, &swapped), Some(v(2, 0, 0)));
+    }
+
+    #[test]
+    fn names_and_symbols_must_fit_the_schema() {
+        assert!(fits_schema(&listed(1, "BEE")));
+        assert!(fits_schema(&ListedToken {
+            name: "Café (Wrapped) 2.0".to_string(),
+            ..listed(1, "CAFE")
+        }));
+        assert!(!fits_schema(&listed(1, "TWO WORDS")));
+        assert!(!fits_schema(&listed(1, "ABCDEFGHIJKLMNOPQRSTU")));
+        assert!(!fits_schema(&ListedToken {
+            name: "Rocket 🚀".to_string(),
+            ..listed(1, "RKT")
+        }));
+    }
+}
@@ -0,0 +1,221 @@
+//! Trending list enter/leave alerts
+//!
+//! After each refresh of `token_list_trending` the new ranking is diffed
+//! against the one stored in `trending_ranks`. A token enters the top list at
+//! rank `ENTER_RANK` or better but only leaves once it falls below
+//! `EXIT_RANK`, so tokens hovering around the cut-off don't flap.
+
+use std::collections::HashMap;
+
+use indexer_db::{
+    entity::{
+        alert::{AlertEvent, AlertType, NewAlert},
+        alert_metadata::{AlertMetadata, TrendingMetadata},
+        token::Token,
+        token_list::TokenList,
+        trending_rank::{NewTrendingRank, TrendingRank},
+    },
+    Address20,
+};
+use sqlx::{Pool, Postgres};
+
+/// Rank a token must reach to enter the top list
+pub const ENTER_RANK: i32 = 10;
+
+/// Rank a top-list token must fall below to leave it
+pub const EXIT_RANK: i32 = 15;
+
+/// A token entering or leaving the top list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Entered {
+        token_address: Address20,
+        rank: i32,
+    },
+    /// The token fell below `EXIT_RANK`
+    Left {
+        token_address: Address20,
+        /// Its rank in the previous ranking
+        last_rank: i32,
+    },
+}
+
+/// Apply hysteresis to a new ranking (best first)
+///
+/// Only the first `EXIT_RANK` tokens are tracked, so a top-list token leaves
+/// exactly when it drops out of them. Returns the positions to store and the
+/// transitions against `previous`.
+pub fn diff(
+    previous: &[TrendingRank],
+    ranking: &[Address20],
+) -> (Vec<NewTrendingRank>, Vec<Transition>) {
+    let was_in_top: HashMap<Address20, bool> = previous
+        .iter()
+        .map(|p| (p.token_address, p.in_top))
+        .collect();
+
+    let mut ranks = Vec::with_capacity(ranking.len());
+    let mut transitions = Vec::new();
+
+    for (i, token_address) in ranking.iter().take(EXIT_RANK as usize).enumerate() {
+        let rank = i as i32 + 1;
+        let was = was_in_top.get(token_address).copied().unwrap_or(false);
+        let in_top = was || rank <= ENTER_RANK;
+
+        if in_top && !was {
+            transitions.push(Transition::Entered {
+                token_address: *token_address,
+                rank,
+            });
+        }
+
+        ranks.push(NewTrendingRank {
+            token_address: *token_address,
+            rank,
+            in_top,
+        });
+    }
+
+    for p in previous {
+        if p.in_top && !ranks.iter().any(|r| r.token_address == p.token_address) {
+            transitions.push(Transition::Left {
+                token_address: p.token_address,
+                last_rank: p.rank,
+            });
+        }
+    }
+
+    (ranks, transitions)
+}
+
+/// Diff the freshly refreshed trending list and raise enter/leave alerts
+pub async fn notify_transitions(db_pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+    let previous = TrendingRank::find_all(db_pool).await?;
+    let tokens = TokenList::Trending.find(EXIT_RANK, false, db_pool).await?;
+    let ranking: Vec<Address20> = tokens.iter().map(|t| t.address).collect();
+
+    let (ranks, transitions) = diff(&previous, &ranking);
+    TrendingRank::replace_all(&ranks, db_pool).await?;
+
+    for transition in transitions {
+        let alert = match transition {
+            Transition::Entered {
+                token_address,
+                rank,
+            } => {
+                let token = tokens.iter().find(|t| t.address == token_address);
+                let symbol = symbol_of(token, &token_address);
+                NewAlert {
+                    alert_type: AlertType::TrendingEnter.as_str().to_string(),
+                    token_address: Some(token_address),
+                    token_symbol: token.and_then(|t| t.symbol.clone()),
+                    wallet_address: None,
+                    title: format!("Trending: {} enters the top {}", symbol, ENTER_RANK),
+                    message: Some(format!("{} is #{} on the trending list", symbol, rank)),
+                    bee_score: token.and_then(|t| t.bee_score),
+                    amount_usd: None,
+                    change_percent: token.and_then(|t| t.price_change_1h.clone()),
+                    metadata: Some(AlertMetadata::Trending(TrendingMetadata { rank })),
+                    source: None,
+                }
+            }
+            Transition::Left {
+                token_address,
+                last_rank,
+            } => {
+                let token = match tokens.iter().find(|t| t.address == token_address) {
+                    Some(t) => Some(t.clone()),
+                    None => Token::find_by_address(&token_address, db_pool).await?,
+                };
+                let symbol = symbol_of(token.as_ref(), &token_address);
+                NewAlert {
+                    alert_type: AlertType::TrendingExit.as_str().to_string(),
+                    token_address: Some(token_address),
+                    token_symbol: token.as_ref().and_then(|t| t.symbol.clone()),
+                    wallet_address: None,
+                    title: format!("Trending: {} left the top {}", symbol, ENTER_RANK),
+                    message: Some(format!(
+                        "{} fell below #{} on the trending list",
+                        symbol, EXIT_RANK
+                    )),
+                    bee_score: token.as_ref().and_then(|t| t.bee_score),
+                    amount_usd: None,
+                    change_percent: token.as_ref().and_then(|t| t.price_change_1h.clone()),
+                    metadata: Some(AlertMetadata::Trending(TrendingMetadata { rank: last_rank })),
+                    source: None,
+                }
+            }
+        };
+
+        AlertEvent::create(&alert, db_pool).await?;
+    }
+
+    Ok(())
+}
+
+/// Token symbol for alert text, falling back to the short address
+fn symbol_of(token: Option<&Token>, address: &Address20) -> String {
+    token
+        .and_then(|t| t.symbol.clone())
+        .unwrap_or_else(|| address.short())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn token(n: u8) -> Address20 {
+        Address20::new([n; 20])
+    }
+
+    fn stored(ranks: &[NewTrendingRank]) -> Vec<TrendingRank> {
+        ranks
+            .iter()
+            .map(|r| TrendingRank {
+                token_address: r.token_address,
+                rank: r.rank,
+                in_top: r.in_top,
+                updated_at: Utc::now(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tokens_enter_at_ten_and_leave_below_fifteen() {
+        let ranking: Vec<Address20> = (1..=15).map(token).collect();
+        let (ranks, transitions) = diff(&[], &ranking);
+        assert_eq!(transitions.len(), 10);
+        assert!(ranks[..10].iter().all(|r| r.in_top));
+        assert!(ranks[10..].iter().all(|r| !r.in_top));
+
+        // Token 10 slips to #12: still in the top list, token 12 (now #10) enters
+        let mut reordered = ranking.clone();
+        reordered.swap(9, 11);
+        let (ranks, transitions) = diff(&stored(&ranks), &reordered);
+        assert_eq!(
+            transitions,
+            vec![Transition::Entered {
+                token_address: token(12),
+                rank: 10
+            }]
+        );
+        assert!(ranks[11].in_top);
+
+        // Token 10 drops off entirely
+        let shrunk: Vec<Address20> = reordered
+            .iter()
+            .copied()
+            .filter(|a| *a != token(10))
+            .collect();
+        let (_, transitions) = diff(&stored(&ranks), &shrunk);
+        assert_eq!(
+            transitions,
+            vec![Transition::Left {
+                token_address: token(10),
+                last_rank: 12
+            }]
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use indexer_db::Address20;
+
 pub fn vec_to_hex<T>(vec: Vec<T>) -> String
 where
     T: std::fmt::LowerHex + Copy,
@@ -7,3 +9,26 @@ where
         acc
     })
 }
+
+/// Take the address from the low 20 bytes of a 32-byte ABI word
+///
+/// Returns `None` unless the word is exactly 32 bytes with a zeroed high 12
+/// bytes, which is how the ABI pads an `address`.
+pub fn word_to_address(word: &[u8]) -> Option<Address20> {
+    if word.len() != 32 || word[..12].iter().any(|b| *b != 0) {
+        return None;
+    }
+
+    Address20::from_slice(&word[12..])
+}
+
+/// Read a 32-byte ABI word as a `u64`, e.g. an offset or array length
+///
+/// Returns `None` unless the word is exactly 32 bytes and the value fits.
+pub fn word_to_u64(word: &[u8]) -> Option<u64> {
+    if word.len() != 32 || word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+
+    Some(u64::from_be_bytes(word[24..].try_into().ok()?))
+}
@@ -0,0 +1,83 @@
+//! On-chain wallet age
+//!
+//! The index only knows when it first saw a wallet, which says nothing about
+//! wallets active before the indexed range. Each pass reads the nonce
+//! (`eth_getTransactionCount`) of up to `WALLET_AGE_BATCH` wallets first seen
+//! within the sniper farm window that bought something since, newest first.
+//! The nonce is read at the latest block, so it can only overstate how new
+//! the wallet was when it bought. Wallets whose read fails are tried again on
+//! the next pass until they leave the window.
+
+use std::env;
+
+use alloy::{primitives::Address, providers::Provider};
+use chrono::{Duration, Utc};
+use indexer_db::entity::wallet_profile::WalletProfile;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    defaults,
+    rpc::{Rpc, RpcBudget},
+    scoring::fresh_wallets,
+};
+
+/// Wallets read per pass, from the environment
+#[derive(Debug, Clone)]
+pub struct AgeConfig {
+    pub batch: i64,
+}
+
+impl AgeConfig {
+    pub fn from_env() -> Self {
+        Self {
+            batch: env::var("WALLET_AGE_BATCH")
+                .unwrap_or_else(|_| defaults::WALLET_AGE_BATCH.to_string())
+                .parse::<i64>()
+                .unwrap_or(50)
+                .max(0),
+        }
+    }
+}
+
+/// One pass over the recent buyers still missing a nonce
+pub async fn run(db_pool: &Pool<Postgres>, config: &AgeConfig) {
+    if config.batch == 0 {
+        return;
+    }
+
+    let since = Utc::now() - Duration::hours(fresh_wallets::WINDOW_HOURS);
+    let wallets = match WalletProfile::find_unchecked_buyers(since, config.batch, db_pool).await {
+        Ok(wallets) if wallets.is_empty() => return,
+        Ok(wallets) => wallets,
+        Err(e) => {
+            tracing::error!("Wallet age: failed to list wallets: {}", e);
+            return;
+        }
+    };
+
+    let rpc = Rpc::shared();
+    let mut read = 0;
+    for wallet in &wallets {
+        RpcBudget::shared().take(1).await;
+        let address = Address::from(*wallet);
+        let nonce = match rpc
+            .call(|p| async move { p.get_transaction_count(address).await })
+            .await
+        {
+            Ok(nonce) => nonce,
+            Err(e) => {
+                // Left unchecked for the next pass
+                tracing::error!("Wallet age: failed to read nonce of {}: {}", wallet, e);
+                break;
+            }
+        };
+
+        let nonce = i64::try_from(nonce).unwrap_or(i64::MAX);
+        match WalletProfile::record_nonce(wallet, nonce, db_pool).await {
+            Ok(()) => read += 1,
+            Err(e) => tracing::error!("Wallet age: failed to store nonce of {}: {}", wallet, e),
+        }
+    }
+
+    tracing::info!("Wallet age: read {} of {} nonces", read, wallets.len());
+}
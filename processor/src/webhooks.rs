@@ -0,0 +1,283 @@
+//! Generic HTTP webhook delivery for alerts
+//!
+//! Each alert matching a webhook's filters is POSTed as JSON. Receivers
+//! verify the `X-BeanBee-Signature` header, an HMAC-SHA256 of
+//! `"{timestamp}.{body}"` keyed with the webhook secret, where `timestamp` is
+//! the `X-BeanBee-Timestamp` header (unix seconds). Due deliveries go out a
+//! few at a time; failed ones are retried with exponential backoff, then
+//! given up on.
+//!
+//! A URL that was public when registered may resolve elsewhere later, so
+//! every delivery checks the addresses it connects to again and never follows
+//! redirects.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use chrono::{Duration, Utc};
+use futures::{stream, StreamExt};
+use hmac::{Hmac, Mac};
+use indexer_db::{
+    entity::{
+        alert::AlertEvent,
+        alert_webhook::{AlertWebhook, DueDelivery},
+    },
+    net::{is_public_domain, is_public_ip},
+};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::{Pool, Postgres};
+
+use crate::alert_rollup::RollupConfig;
+
+pub const SIGNATURE_HEADER: &str = "X-BeanBee-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-BeanBee-Timestamp";
+pub const DELIVERY_HEADER: &str = "X-BeanBee-Delivery";
+
+/// Attempts before a delivery is marked failed
+const MAX_ATTEMPTS: i32 = 10;
+
+/// Delay before the first retry; doubles on each further failure
+const BASE_RETRY_SECS: i64 = 30;
+
+/// Longest wait between two attempts
+const MAX_RETRY_SECS: i64 = 3600;
+
+/// Deliveries sent per run
+const BATCH_SIZE: i32 = 100;
+
+/// Deliveries in flight at once, so one slow receiver doesn't hold up the
+/// rest of the batch
+const CONCURRENT_DELIVERIES: usize = 8;
+
+/// Per-request timeout
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// Resolves webhook hosts to their public addresses only, so a name can't
+/// point deliveries into the private network
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            if !is_public_domain(host) {
+                return Err(format!("{} is not a public host", host).into());
+            }
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Build the HTTP client used for deliveries
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Refuse a URL whose host is a non-public IP address. Address literals are
+/// connected to directly, without going through the client's resolver.
+fn check_target(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    let host = parsed.host_str().unwrap_or_default();
+    match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) if !is_public_ip(ip) => Err(format!("{} is not a public address", ip)),
+        _ => Ok(()),
+    }
+}
+
+/// Queue new alerts and send every delivery that is due. Alerts `rollups`
+/// may still collapse are held back until their window is over.
+pub async fn dispatch(db_pool: &Pool<Postgres>, client: &reqwest::Client, rollups: &RollupConfig) {
+    let (held_types, hold_secs) = rollups.delivery_hold();
+    if let Err(e) = AlertWebhook::enqueue_deliveries(held_types, hold_secs, db_pool).await {
+        tracing::error!("Failed to queue webhook deliveries: {}", e);
+        return;
+    }
+
+    let due = match AlertWebhook::find_due_deliveries(BATCH_SIZE, db_pool).await {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::error!("Failed to load due webhook deliveries: {}", e);
+            return;
+        }
+    };
+
+    stream::iter(due)
+        .for_each_concurrent(CONCURRENT_DELIVERIES, |delivery| {
+            deliver(db_pool, client, delivery)
+        })
+        .await;
+}
+
+/// Send one delivery and record how it went
+async fn deliver(db_pool: &Pool<Postgres>, client: &reqwest::Client, delivery: DueDelivery) {
+    let result = match send(client, &delivery).await {
+        Ok(status) => AlertWebhook::mark_delivered(delivery.delivery_id, status, db_pool).await,
+        Err((status, error)) => {
+            let retry_at = retry_delay(delivery.attempts + 1).map(|delay| Utc::now() + delay);
+            AlertWebhook::mark_attempt_failed(
+                delivery.delivery_id,
+                status,
+                &error,
+                retry_at,
+                db_pool,
+            )
+            .await
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::error!(
+            "Failed to record webhook delivery {}: {}",
+            delivery.delivery_id,
+            e
+        );
+    }
+}
+
+/// POST one delivery, returning the status code or the failure
+async fn send(
+    client: &reqwest::Client,
+    delivery: &DueDelivery,
+) -> Result<i32, (Option<i32>, String)> {
+    check_target(&delivery.webhook_url).map_err(|e| (None, e))?;
+    let body = payload(&delivery.alert).to_string();
+    let timestamp = Utc::now().timestamp();
+
+    let response = client
+        .post(&delivery.webhook_url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(
+            SIGNATURE_HEADER,
+            sign(&delivery.webhook_secret, timestamp, body.as_bytes()),
+        )
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(DELIVERY_HEADER, delivery.delivery_id.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16() as i32)
+    } else {
+        Err((Some(status.as_u16() as i32), format!("HTTP {}", status)))
+    }
+}
+
+/// JSON body sent for an alert
+fn payload(alert: &AlertEvent) -> Value {
+    json!({
+        "id": alert.id,
+        "type": alert.alert_type,
+        "title": alert.title,
+        "message": alert.message,
+        "tokenAddress": alert.token_address,
+        "tokenSymbol": alert.token_symbol,
+        "walletAddress": alert.wallet_address,
+        "beeScore": alert.bee_score,
+        "amountUsd": alert.amount_usd.as_ref().map(|v| v.to_string()),
+        "changePercent": alert.change_percent.as_ref().map(|v| v.to_string()),
+        "metadata": alert.metadata.as_ref().map(|m| &m.0),
+        "createdAt": alert.created_at.map(|dt| dt.to_rfc3339()),
+    })
+}
+
+/// Signature header value for a body sent at `timestamp`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut message = timestamp.to_string().into_bytes();
+    message.push(b'.');
+    message.extend_from_slice(body);
+
+    format!("sha256={}", hmac_sha256_hex(secret.as_bytes(), &message))
+}
+
+fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Wait before the next attempt once `attempts` have failed, or `None` to give up
+fn retry_delay(attempts: i32) -> Option<Duration> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    Some(Duration::seconds(
+        (BASE_RETRY_SECS << exponent).min(MAX_RETRY_SECS),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_matches_rfc4231() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let sig = sign("secret", 1_700_000_000, b"{}");
+        assert!(sig.starts_with("sha256="));
+        assert_eq!(
+            sig,
+            format!("sha256={}", hmac_sha256_hex(b"secret", b"1700000000.{}"))
+        );
+        assert_ne!(sig, sign("secret", 1_700_000_001, b"{}"));
+        assert_ne!(sig, sign("other", 1_700_000_000, b"{}"));
+    }
+
+    #[test]
+    fn retries_back_off_then_give_up() {
+        assert_eq!(retry_delay(1), Some(Duration::seconds(30)));
+        assert_eq!(retry_delay(2), Some(Duration::seconds(60)));
+        assert_eq!(retry_delay(3), Some(Duration::seconds(120)));
+        assert_eq!(retry_delay(9), Some(Duration::seconds(MAX_RETRY_SECS)));
+        assert_eq!(retry_delay(MAX_ATTEMPTS), None);
+    }
+
+    #[test]
+    fn address_literals_must_be_public() {
+        assert!(check_target("https://8.8.8.8/hook").is_ok());
+        assert!(check_target("https://hooks.example.com/hook").is_ok());
+        assert!(check_target("http://127.0.0.1/hook").is_err());
+        assert!(check_target("http://169.254.169.254/latest/meta-data").is_err());
+        assert!(check_target("http://[::1]:8080/hook").is_err());
+    }
+
+    #[tokio::test]
+    async fn private_names_do_not_resolve() {
+        for host in ["localhost", "db.internal"] {
+            let name = host.parse::<Name>().unwrap();
+            assert!(PublicResolver.resolve(name).await.is_err(), "{}", host);
+        }
+    }
+}